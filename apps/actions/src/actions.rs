@@ -0,0 +1,513 @@
+//! The Actions endpoints: `GET` renders a market as a Blink, `POST` builds
+//! the transaction a wallet signs for the button pressed.
+//!
+//! - `/bet?market=` offers each side at every preset amount, plus a custom
+//!   amount. Its buttons post to `/bet?market=&side=A|B&amount=`, with the
+//!   amount in whole tokens.
+//! - `/claim?market=` claims a finalized market's winnings, or a cancelled
+//!   market's stake, for the wallet posting.
+//!
+//! Markets and positions are read from the RPC rather than the indexer, so
+//! a link works the moment its market is created. Transactions are legacy,
+//! unsigned, and paid for by the wallet in the request.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::AccountDeserialize;
+use axum::body::Bytes;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use friend_bets_sdk::offline::PartiallySignedTransaction;
+use friend_bets_sdk::pda::{position_address, stake_address};
+use friend_bets_sdk::simulate::{implied_odds, simulate_claim};
+use friend_bets_sdk::{instructions, BetSide, Market, MarketStatus, Position, StakeAccount};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_message::{Message, VersionedMessage};
+
+use crate::rpc::RpcClient;
+use crate::units::{format_amount, format_utc, now, parse_amount};
+
+/// Byte offset of `decimals` in an SPL token mint.
+const MINT_DECIMALS_OFFSET: usize = 44;
+
+pub enum ActionError {
+    BadRequest(String),
+    NotFound(String),
+    Internal(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ActionError {
+    fn from(err: anyhow::Error) -> Self {
+        ActionError::Internal(err)
+    }
+}
+
+/// Errors as Action clients show them, `{"message": ...}`.
+impl IntoResponse for ActionError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ActionError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ActionError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ActionError::Internal(err) => {
+                eprintln!("error: {err:#}");
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal error".into())
+            }
+        };
+        (status, Json(json!({ "message": message }))).into_response()
+    }
+}
+
+type Result<T> = std::result::Result<T, ActionError>;
+
+/// A request's query string. Empty values count as absent.
+#[derive(Default, Deserialize)]
+#[serde(transparent)]
+pub struct Params(HashMap<String, String>);
+
+impl Params {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .get(name)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// `GET /actions.json`: every path on this domain is an Action.
+pub async fn actions_json() -> Json<Value> {
+    Json(json!({ "rules": [{ "pathPattern": "/**", "apiPath": "/**" }] }))
+}
+
+/// `GET /bet?market=`.
+pub async fn bet_blink(
+    State(actions): State<Arc<Actions>>,
+    Query(params): Query<Params>,
+) -> Result<Json<Value>> {
+    blocking(move || {
+        let loaded = actions.load(&params)?;
+        Ok(actions.bet_action(&loaded, now()))
+    })
+    .await
+}
+
+/// `POST /bet?market=&side=&amount=`.
+pub async fn bet(
+    State(actions): State<Arc<Actions>>,
+    Query(params): Query<Params>,
+    body: Bytes,
+) -> Result<Json<Value>> {
+    blocking(move || actions.bet(&params, &body)).await
+}
+
+/// `GET /claim?market=`.
+pub async fn claim_blink(
+    State(actions): State<Arc<Actions>>,
+    Query(params): Query<Params>,
+) -> Result<Json<Value>> {
+    blocking(move || {
+        let loaded = actions.load(&params)?;
+        Ok(actions.claim_action(&loaded))
+    })
+    .await
+}
+
+/// `POST /claim?market=`.
+pub async fn claim(
+    State(actions): State<Arc<Actions>>,
+    Query(params): Query<Params>,
+    body: Bytes,
+) -> Result<Json<Value>> {
+    blocking(move || actions.claim(&params, &body)).await
+}
+
+pub async fn not_found() -> ActionError {
+    ActionError::NotFound("no such action".into())
+}
+
+pub async fn method_not_allowed() -> Response {
+    (
+        StatusCode::METHOD_NOT_ALLOWED,
+        Json(json!({ "message": "only GET and POST are supported" })),
+    )
+        .into_response()
+}
+
+/// Runs `run` where it may block, as RPC reads do.
+async fn blocking(run: impl FnOnce() -> Result<Value> + Send + 'static) -> Result<Json<Value>> {
+    tokio::task::spawn_blocking(run)
+        .await
+        .map_err(|err| ActionError::Internal(err.into()))?
+        .map(Json)
+}
+
+pub struct Actions {
+    pub rpc: RpcClient,
+    /// Image shown on every Blink.
+    pub icon: String,
+    /// Bet buttons offered on each side, in whole tokens.
+    pub presets: Vec<String>,
+    /// Shown after amounts, such as `USDC`.
+    pub symbol: Option<String>,
+}
+
+/// A market as read for a request.
+struct Loaded {
+    address: Pubkey,
+    market: Market,
+    decimals: u8,
+}
+
+impl Actions {
+    /// The market in `?market=`, and its mint's decimals.
+    fn load(&self, params: &Params) -> Result<Loaded> {
+        let param = params
+            .get("market")
+            .ok_or_else(|| ActionError::BadRequest("market is required".into()))?;
+        let address: Pubkey = param
+            .parse()
+            .map_err(|_| ActionError::BadRequest(format!("invalid market {param:?}")))?;
+        let data = self
+            .rpc
+            .get_account_data(&address)?
+            .ok_or_else(|| ActionError::NotFound(format!("no market {address}")))?;
+        let market = Market::try_deserialize(&mut data.as_slice())
+            .map_err(|_| ActionError::NotFound(format!("{address} isn't a market")))?;
+        let decimals = self
+            .rpc
+            .get_account_data(&market.mint)?
+            .and_then(|data| data.get(MINT_DECIMALS_OFFSET).copied())
+            .ok_or_else(|| anyhow::anyhow!("mint {} not found", market.mint))?;
+        Ok(Loaded {
+            address,
+            market,
+            decimals,
+        })
+    }
+
+    fn amount(&self, raw: u64, decimals: u8) -> String {
+        let amount = format_amount(raw, decimals);
+        match &self.symbol {
+            Some(symbol) => format!("{amount} {symbol}"),
+            None => amount,
+        }
+    }
+
+    fn bet_action(&self, loaded: &Loaded, now: i64) -> Value {
+        let Loaded {
+            address,
+            market,
+            decimals,
+        } = loaded;
+        let mut description = format!(
+            "Pool {}",
+            self.amount(market.staked_a + market.staked_b, *decimals)
+        );
+        if let Ok(odds) = implied_odds(market) {
+            for (side, bps) in [("A", odds.a_bps), ("B", odds.b_bps)] {
+                if bps > 0 {
+                    description.push_str(&format!(
+                        " · {side} pays {}.{:02}x",
+                        bps / 10_000,
+                        bps % 10_000 / 100
+                    ));
+                }
+            }
+        }
+        description.push_str(&format!(" · Betting closes {}", format_utc(market.end_ts)));
+
+        let mut actions = Vec::new();
+        for side in ["A", "B"] {
+            for preset in &self.presets {
+                let label = match &self.symbol {
+                    Some(symbol) => format!("{preset} {symbol} on {side}"),
+                    None => format!("{preset} on {side}"),
+                };
+                actions.push(json!({
+                    "type": "transaction",
+                    "label": label,
+                    "href": format!("/bet?market={address}&side={side}&amount={preset}"),
+                }));
+            }
+        }
+        for side in ["A", "B"] {
+            actions.push(json!({
+                "type": "transaction",
+                "label": format!("Bet on {side}"),
+                "href": format!("/bet?market={address}&side={side}&amount={{amount}}"),
+                "parameters": [{
+                    "type": "number",
+                    "name": "amount",
+                    "label": "Amount",
+                    "required": true,
+                }],
+            }));
+        }
+
+        let mut action = json!({
+            "type": "action",
+            "icon": self.icon,
+            "title": market.title,
+            "description": description,
+            "label": "Bet",
+            "links": { "actions": actions },
+        });
+        if market.status != MarketStatus::Open || now >= market.end_ts {
+            action["disabled"] = json!(true);
+            action["error"] = json!({ "message": "Betting on this market has closed" });
+        }
+        action
+    }
+
+    fn claim_action(&self, loaded: &Loaded) -> Value {
+        let Loaded {
+            address, market, ..
+        } = loaded;
+        let (description, error) = match (market.status, market.outcome) {
+            (MarketStatus::Resolved, Some(outcome)) => (
+                format!(
+                    "Side {} won. Winners can claim their share of the pool.",
+                    side_name(outcome)
+                ),
+                None,
+            ),
+            (MarketStatus::Cancelled, _) => (
+                "This market was cancelled. Everyone who bet can claim their stake back."
+                    .to_string(),
+                None,
+            ),
+            _ => (
+                format!("Betting closes {}", format_utc(market.end_ts)),
+                Some("This market hasn't been resolved yet"),
+            ),
+        };
+        let mut action = json!({
+            "type": "action",
+            "icon": self.icon,
+            "title": market.title,
+            "description": description,
+            "label": "Claim",
+            "links": { "actions": [{
+                "type": "transaction",
+                "label": "Claim",
+                "href": format!("/claim?market={address}"),
+            }] },
+        });
+        if let Some(error) = error {
+            action["disabled"] = json!(true);
+            action["error"] = json!({ "message": error });
+        }
+        action
+    }
+
+    fn bet(&self, params: &Params, body: &[u8]) -> Result<Value> {
+        let account = account(body)?;
+        let side = match params.get("side") {
+            Some("A") => BetSide::A,
+            Some("B") => BetSide::B,
+            _ => return Err(ActionError::BadRequest("side must be A or B".into())),
+        };
+        let amount = params
+            .get("amount")
+            .ok_or_else(|| ActionError::BadRequest("amount is required".into()))?;
+        let loaded = self.load(params)?;
+        let market = &loaded.market;
+        let raw = parse_amount(amount, loaded.decimals)
+            .map_err(|err| ActionError::BadRequest(err.to_string()))?;
+        if raw == 0 {
+            return Err(ActionError::BadRequest("amount must be more than 0".into()));
+        }
+        if market.status != MarketStatus::Open || now() >= market.end_ts {
+            return Err(ActionError::BadRequest(
+                "Betting on this market has closed".into(),
+            ));
+        }
+        let mut ix = instructions::place_bet_on(&account, &loaded.address, market, side, raw, 0);
+        // Stakers lock in their fee discount by passing their stake account
+        if self
+            .rpc
+            .optional_account::<StakeAccount>(&stake_address(&account).0)?
+            .is_some()
+        {
+            ix = instructions::with_bettor_stake(ix, &account);
+        }
+        let message = format!(
+            "Bet {} on {} in \"{}\"",
+            self.amount(raw, loaded.decimals),
+            side_name(side),
+            market.title
+        );
+        self.transaction(&account, ix, &message)
+    }
+
+    fn claim(&self, params: &Params, body: &[u8]) -> Result<Value> {
+        let account = account(body)?;
+        let loaded = self.load(params)?;
+        let market = &loaded.market;
+        if !matches!(
+            market.status,
+            MarketStatus::Resolved | MarketStatus::Cancelled
+        ) {
+            return Err(ActionError::BadRequest(
+                "This market hasn't been resolved yet".into(),
+            ));
+        }
+        let position: Position = self
+            .rpc
+            .optional_account(&position_address(&loaded.address, &account).0)?
+            .ok_or_else(|| ActionError::BadRequest("You have no bet in this market".into()))?;
+        if position.claimed {
+            return Err(ActionError::BadRequest("You've already claimed".into()));
+        }
+        let payout = simulate_claim(market, &position)
+            .map_err(|err| ActionError::Internal(anyhow::anyhow!("{err}")))?
+            .total();
+        if payout == 0 {
+            return Err(ActionError::BadRequest(
+                "Your bet didn't win; there's nothing to claim".into(),
+            ));
+        }
+        let ix = instructions::claim(&account, &loaded.address, &market.mint);
+        let message = format!(
+            "Claim {} from \"{}\"",
+            self.amount(payout, loaded.decimals),
+            market.title
+        );
+        self.transaction(&account, ix, &message)
+    }
+
+    /// The unsigned transaction `payer` signs to run `ix`.
+    fn transaction(&self, payer: &Pubkey, ix: Instruction, message: &str) -> Result<Value> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let tx = PartiallySignedTransaction::new(VersionedMessage::Legacy(
+            Message::new_with_blockhash(&[ix], Some(payer), &blockhash),
+        ));
+        Ok(json!({
+            "type": "transaction",
+            "transaction": tx.to_base64(),
+            "message": message,
+        }))
+    }
+}
+
+/// The wallet in a POST body, `{"account": "<pubkey>"}`.
+fn account(body: &[u8]) -> Result<Pubkey> {
+    let body: Value = serde_json::from_slice(body)
+        .map_err(|_| ActionError::BadRequest("the body must be JSON".into()))?;
+    let account = body["account"]
+        .as_str()
+        .ok_or_else(|| ActionError::BadRequest("account is required".into()))?;
+    account
+        .parse()
+        .map_err(|_| ActionError::BadRequest(format!("invalid account {account:?}")))
+}
+
+fn side_name(side: BetSide) -> &'static str {
+    match side {
+        BetSide::A => "A",
+        BetSide::B => "B",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(status: MarketStatus) -> Market {
+        Market {
+            market_id: 1,
+            creator: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            vault_a: Pubkey::new_unique(),
+            vault_b: Pubkey::new_unique(),
+            fee_bps: 100,
+            end_ts: 1_800_000_000,
+            resolve_deadline_ts: 1_800_086_400,
+            staked_a: 2_000_000,
+            staked_b: 5_000_000,
+            status,
+            outcome: None,
+            creator_fee_withdrawn: false,
+            bump: 0,
+            vault_a_bump: 0,
+            vault_b_bump: 0,
+            title: "Rain?".into(),
+            allow_cash_out: false,
+            cashout_haircut_bps: 0,
+            retained: 0,
+            lock_odds: false,
+            locked_payout_a: 0,
+            locked_payout_b: 0,
+            forked_from: None,
+            dependency: None,
+            open_positions: 2,
+            burn_fee: false,
+            fee_discounts: 0,
+            discounted_stake: 0,
+            compressed: false,
+            track_bettors: false,
+            bettor_count: 0,
+            bettor_pages: 0,
+            insurance_bps: 0,
+            insurance_collected: false,
+            compensated: 0,
+            charity: None,
+            charity_bps: 0,
+            charity_paid: false,
+            large_bet_threshold: 0,
+            track_activity: false,
+            cap_a: 0,
+            cap_b: 0,
+            max_ratio_bps: 0,
+            title_registered: false,
+            fee_splits: Vec::new(),
+            pools_changed_slot: 0,
+            scheduled_bets: 0,
+        }
+    }
+
+    #[test]
+    fn renders_blinks() {
+        let actions = Actions {
+            rpc: RpcClient::new(String::new()),
+            icon: "https://example.com/icon.png".into(),
+            presets: vec!["5".into(), "10".into()],
+            symbol: Some("USDC".into()),
+        };
+        let address = Pubkey::new_unique();
+        let open = Loaded {
+            address,
+            market: market(MarketStatus::Open),
+            decimals: 6,
+        };
+        let action = actions.bet_action(&open, 1_700_000_000);
+        assert_eq!(
+            action["description"],
+            "Pool 7 USDC · A pays 3.46x · B pays 1.38x · Betting closes 2027-01-15 08:00 UTC"
+        );
+        let buttons = action["links"]["actions"].as_array().unwrap();
+        assert_eq!(buttons.len(), 6);
+        assert_eq!(buttons[1]["label"], "10 USDC on A");
+        assert_eq!(
+            buttons[5]["href"],
+            format!("/bet?market={address}&side=B&amount={{amount}}")
+        );
+        assert!(action.get("disabled").is_none());
+        assert_eq!(actions.bet_action(&open, 1_800_000_000)["disabled"], true);
+
+        let mut resolved = open;
+        resolved.market.status = MarketStatus::Resolved;
+        resolved.market.outcome = Some(BetSide::B);
+        let claim = actions.claim_action(&resolved);
+        assert_eq!(
+            claim["description"],
+            "Side B won. Winners can claim their share of the pool."
+        );
+        assert!(claim.get("disabled").is_none());
+    }
+}
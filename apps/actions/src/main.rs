@@ -0,0 +1,261 @@
+//! `friend-bets-actions`: serves Solana Actions for markets, so a link to
+//! `/bet?market=<pubkey>` pasted in a group chat unfurls as a Blink that
+//! friends bet from without leaving the chat, and `/claim?market=` pays them
+//! out once it's settled.
+
+mod actions;
+mod rpc;
+mod units;
+
+use std::collections::HashMap;
+use std::env;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use axum::extract::DefaultBodyLimit;
+use axum::http::header::{AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE};
+use axum::http::{HeaderName, HeaderValue, Method};
+use axum::middleware;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use tokio::net::TcpListener;
+use tower_http::cors::{Any, CorsLayer};
+
+use crate::actions::Actions;
+use crate::rpc::RpcClient;
+
+const USAGE: &str = "\
+Usage: friend-bets-actions [OPTIONS]
+
+Actions (GET renders the Blink, POST {\"account\": <wallet>} builds the transaction):
+  /bet?market=<pubkey>        POST with &side=A|B &amount=<whole tokens>
+  /claim?market=<pubkey>
+  /actions.json
+
+Options:
+      --listen <ADDR>       Address to serve on, default 127.0.0.1:8081
+                            [env: FRIEND_BETS_ACTIONS_LISTEN]
+      --rpc-url <URL>       RPC endpoint [env: FRIEND_BETS_RPC_URL]
+      --icon <URL>          Image shown on each Blink [env: FRIEND_BETS_ACTIONS_ICON]
+      --presets <AMOUNTS>   Bet buttons for each side, in whole tokens, default 5,10,25
+                            [env: FRIEND_BETS_ACTIONS_PRESETS]
+      --symbol <SYMBOL>     Shown after amounts, such as USDC [env: FRIEND_BETS_ACTIONS_SYMBOL]
+  -h, --help                Print this help
+
+Serve it over HTTPS on the domain the links use; Blink clients fetch
+/actions.json from the domain root.
+";
+
+/// Largest request body accepted.
+const MAX_BODY: usize = 64 * 1024;
+/// The Actions spec version the responses follow.
+const ACTION_VERSION: &str = "2.4";
+const X_ACTION_VERSION: HeaderName = HeaderName::from_static("x-action-version");
+const X_BLOCKCHAIN_IDS: HeaderName = HeaderName::from_static("x-blockchain-ids");
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let mut options = HashMap::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            flag if flag.starts_with("--") => {
+                let (name, value) = match flag[2..].split_once('=') {
+                    Some((name, value)) => (name.to_string(), value.to_string()),
+                    None => (
+                        flag[2..].to_string(),
+                        args.next().ok_or_else(|| anyhow!("{flag} needs a value"))?,
+                    ),
+                };
+                options.insert(name, value);
+            }
+            _ => bail!("unexpected argument {arg:?}"),
+        }
+    }
+    let mut option = |name: &str, var: &str| options.remove(name).or_else(|| env::var(var).ok());
+
+    let rpc_url = option("rpc-url", "FRIEND_BETS_RPC_URL")
+        .ok_or_else(|| anyhow!("set --rpc-url or FRIEND_BETS_RPC_URL"))?;
+    let icon = option("icon", "FRIEND_BETS_ACTIONS_ICON")
+        .ok_or_else(|| anyhow!("set --icon or FRIEND_BETS_ACTIONS_ICON"))?;
+    let presets: Vec<String> = option("presets", "FRIEND_BETS_ACTIONS_PRESETS")
+        .unwrap_or_else(|| "5,10,25".into())
+        .split(',')
+        .map(|preset| preset.trim().to_string())
+        .filter(|preset| !preset.is_empty())
+        .collect();
+    if let Some(preset) = presets
+        .iter()
+        .find(|preset| !preset.parse::<f64>().is_ok_and(|amount| amount > 0.0))
+    {
+        bail!("invalid --presets amount {preset:?}");
+    }
+    let symbol = option("symbol", "FRIEND_BETS_ACTIONS_SYMBOL");
+    let listen =
+        option("listen", "FRIEND_BETS_ACTIONS_LISTEN").unwrap_or_else(|| "127.0.0.1:8081".into());
+    if let Some(name) = options.keys().next() {
+        bail!("unknown option --{name}; see --help");
+    }
+
+    let rpc = RpcClient::new(rpc_url);
+    // CAIP-2 names a Solana cluster by the first 32 characters of its
+    // genesis hash
+    let genesis = rpc.get_genesis_hash().context("reading the cluster")?;
+    let blockchain_id = format!("solana:{}", &genesis[..genesis.len().min(32)]);
+
+    let actions = Arc::new(Actions {
+        rpc,
+        icon,
+        presets,
+        symbol,
+    });
+    let app = router(Arc::clone(&actions), &blockchain_id)?;
+
+    // `actions` outlives the runtime, since its blocking RPC client can't
+    // be dropped inside one
+    let runtime = tokio::runtime::Runtime::new().context("starting the runtime")?;
+    runtime.block_on(async {
+        let listener = TcpListener::bind(&listen)
+            .await
+            .with_context(|| format!("listening on {listen}"))?;
+        eprintln!("Listening on http://{}", listener.local_addr()?);
+        axum::serve(listener, app)
+            .await
+            .with_context(|| format!("serving on {listen}"))
+    })
+}
+
+/// The Actions routes; `blockchain_id` is the CAIP-2 id of the cluster
+/// transactions are built for. Responses carry the CORS and version headers
+/// wallets and Blink clients check for.
+fn router(actions: Arc<Actions>, blockchain_id: &str) -> Result<Router> {
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::OPTIONS])
+        .allow_headers([
+            CONTENT_TYPE,
+            AUTHORIZATION,
+            CONTENT_ENCODING,
+            HeaderName::from_static("accept-encoding"),
+            X_ACTION_VERSION,
+            X_BLOCKCHAIN_IDS,
+        ])
+        .expose_headers([X_ACTION_VERSION, X_BLOCKCHAIN_IDS]);
+    let blockchain_id = HeaderValue::from_str(blockchain_id).context("invalid genesis hash")?;
+    let headers = middleware::map_response(move |mut response: Response| {
+        let blockchain_id = blockchain_id.clone();
+        async move {
+            let headers = response.headers_mut();
+            headers.insert(X_ACTION_VERSION, HeaderValue::from_static(ACTION_VERSION));
+            headers.insert(X_BLOCKCHAIN_IDS, blockchain_id);
+            response
+        }
+    });
+    Ok(Router::new()
+        .route(
+            "/actions.json",
+            get(actions::actions_json).fallback(actions::method_not_allowed),
+        )
+        .route(
+            "/bet",
+            get(actions::bet_blink)
+                .post(actions::bet)
+                .fallback(actions::method_not_allowed),
+        )
+        .route(
+            "/claim",
+            get(actions::claim_blink)
+                .post(actions::claim)
+                .fallback(actions::method_not_allowed),
+        )
+        .fallback(actions::not_found)
+        .layer(DefaultBodyLimit::max(MAX_BODY))
+        .layer(cors)
+        .layer(headers)
+        .with_state(actions))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn call(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        assert_eq!(response.headers()["x-action-version"], ACTION_VERSION);
+        assert_eq!(response.headers()["x-blockchain-ids"], "solana:test");
+        let body = to_bytes(response.into_body(), MAX_BODY).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn get(uri: &str) -> Request<Body> {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn routes_requests() {
+        // Nothing here reaches the RPC. The app is built outside the runtime
+        // and outlives it, as the blocking RPC client can't be dropped in one
+        let actions = Arc::new(Actions {
+            rpc: RpcClient::new(String::new()),
+            icon: String::new(),
+            presets: Vec::new(),
+            symbol: None,
+        });
+        let app = router(Arc::clone(&actions), "solana:test").unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (status, body) = call(&app, get("/actions.json")).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["rules"][0]["apiPath"], "/**");
+
+            let (status, body) = call(&app, get("/nowhere")).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            assert_eq!(body["message"], "no such action");
+            let (status, body) = call(&app, get("/bet?market=")).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["message"], "market is required");
+            let not_json = Request::post("/claim?market=x")
+                .body(Body::from("nope"))
+                .unwrap();
+            let (status, body) = call(&app, not_json).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["message"], "the body must be JSON");
+            let delete = Request::delete("/bet").body(Body::empty()).unwrap();
+            assert_eq!(call(&app, delete).await.0, StatusCode::METHOD_NOT_ALLOWED);
+            let oversized = Request::post("/bet")
+                .body(Body::from(vec![b' '; MAX_BODY + 1]))
+                .unwrap();
+            assert_eq!(call(&app, oversized).await.0, StatusCode::PAYLOAD_TOO_LARGE);
+
+            let preflight = Request::options("/bet")
+                .header("Origin", "https://example.com")
+                .header("Access-Control-Request-Method", "POST")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(preflight).await.unwrap();
+            assert_eq!(response.headers()["access-control-allow-origin"], "*");
+            assert_eq!(response.headers()["x-action-version"], ACTION_VERSION);
+        });
+    }
+}
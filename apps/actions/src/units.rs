@@ -0,0 +1,59 @@
+//! Token amounts and timestamps in human-readable form.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+
+/// Parses a decimal token amount like `1.5` into raw units.
+pub fn parse_amount(amount: &str, decimals: u8) -> Result<u64> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if fraction.len() > decimals as usize {
+        bail!("{amount} has more than {decimals} decimal places");
+    }
+    let digits = format!("{whole}{fraction:0<width$}", width = decimals as usize);
+    digits
+        .parse::<u64>()
+        .map_err(|_| anyhow!("invalid amount {amount:?}"))
+}
+
+/// Formats raw units with the mint's decimals, trimming trailing zeros.
+pub fn format_amount(raw: u64, decimals: u8) -> String {
+    if decimals == 0 {
+        return raw.to_string();
+    }
+    let scale = 10u64.pow(decimals as u32);
+    let fraction = format!("{:0width$}", raw % scale, width = decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (raw / scale).to_string()
+    } else {
+        format!("{}.{fraction}", raw / scale)
+    }
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+/// Formats a timestamp as UTC to the minute, e.g. `2024-05-01 12:00 UTC`.
+pub fn format_utc(ts: i64) -> String {
+    // Civil-from-days, after Howard Hinnant's date algorithms
+    let days = ts.div_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let secs = ts.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02} UTC",
+        secs / 3_600,
+        secs % 3_600 / 60
+    )
+}
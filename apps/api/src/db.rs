@@ -0,0 +1,88 @@
+//! Read access to the indexer's database over `tokio-postgres`. Queries have
+//! Postgres build the JSON, so rows come back as one document, and take
+//! their inputs as parameters.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use rustls::ClientConfig;
+use serde_json::Value;
+use tokio::sync::Mutex;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::Client;
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+/// A connection shared by every request, made on the first query and again
+/// whenever it drops.
+pub struct Database {
+    url: String,
+    client: Mutex<Option<Arc<Client>>>,
+}
+
+impl Database {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: Mutex::new(None),
+        }
+    }
+
+    /// Runs a query selecting a single JSON value, with `args` for its
+    /// placeholders; null when there's no row.
+    pub async fn json(&self, sql: &str, args: &Args) -> Result<Value> {
+        let row = self.client().await?.query_opt(sql, &args.params()).await?;
+        Ok(row
+            .map(|row| row.try_get::<_, Option<Value>>(0))
+            .transpose()?
+            .flatten()
+            .unwrap_or_default())
+    }
+
+    async fn client(&self) -> Result<Arc<Client>> {
+        let mut client = self.client.lock().await;
+        if let Some(connected) = client.as_ref().filter(|client| !client.is_closed()) {
+            return Ok(Arc::clone(connected));
+        }
+        let (connected, connection) = tokio_postgres::connect(&self.url, tls()?)
+            .await
+            .context("connecting to Postgres")?;
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                eprintln!("warning: Postgres connection: {err}");
+            }
+        });
+        Ok(Arc::clone(client.insert(Arc::new(connected))))
+    }
+}
+
+/// TLS for servers that offer it, as `sslmode` allows, checking their
+/// certificates against the system's roots.
+fn tls() -> Result<MakeRustlsConnect> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    Ok(MakeRustlsConnect::new(config))
+}
+
+/// Values for a query's `$n` placeholders, added as its SQL is written.
+#[derive(Default)]
+pub struct Args(Vec<Box<dyn ToSql + Send + Sync>>);
+
+impl Args {
+    /// Adds `value`, returning the placeholder that takes it.
+    pub fn add(&mut self, value: impl ToSql + Send + Sync + 'static) -> String {
+        self.0.push(Box::new(value));
+        format!("${}", self.0.len())
+    }
+
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.0
+            .iter()
+            .map(|value| value.as_ref() as &(dyn ToSql + Sync))
+            .collect()
+    }
+}
@@ -0,0 +1,312 @@
+//! Live market updates at `/ws`, over WebSocket.
+//!
+//! A thread follows the indexer's `events` table and pushes each event to
+//! the connections subscribed to its market, along with the market's pools
+//! and implied probabilities just after it:
+//!
+//! ```text
+//! {"type": "event", "market": ..., "name": "bet_placed", "data": {...},
+//!  "signature": ..., "event_index": 0, "slot": ..., "block_time": ...,
+//!  "commitment": "confirmed", "odds": {"staked_a": "...", "staked_b": "...",
+//!  "probability_a": 0.6, "probability_b": 0.4}}
+//! ```
+//!
+//! Clients pick markets with `?markets=a,b` on the upgrade request, or by
+//! sending `{"subscribe": [...]}` or `{"unsubscribe": [...]}`. Each change
+//! is answered with `{"type": "subscribed", "markets": [...]}` and then a
+//! `{"type": "odds", "market", "status", "odds"}` snapshot of every market
+//! newly subscribed to.
+//!
+//! Events are pushed as soon as they're indexed, usually at `confirmed`.
+//! One whose fork is later dropped isn't retracted, and events the indexer
+//! replays after a rollback are pushed again.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use axum::body::Bytes;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use serde_json::{json, Value};
+use tokio::sync::mpsc::{self, Sender};
+use tokio::time;
+
+use crate::db::{Args, Database};
+use crate::routes::{is_address, ApiError, Params};
+
+/// How often the events table is checked for new rows.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Most events read per check.
+const BATCH: u32 = 500;
+/// How long a connection can go quiet before it's pinged.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+/// Messages queued for a connection before it's dropped as too slow.
+const QUEUE: usize = 1024;
+/// Most markets one connection can follow.
+const MAX_SUBSCRIPTIONS: usize = 100;
+/// Largest message accepted from a client.
+const MAX_MESSAGE: usize = 64 * 1024;
+
+struct Client {
+    markets: BTreeSet<String>,
+    sender: Sender<Message>,
+}
+
+/// The open connections and what each follows.
+pub struct Hub {
+    db: Arc<Database>,
+    clients: Mutex<HashMap<u64, Client>>,
+    next_id: AtomicU64,
+}
+
+impl Hub {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self {
+            db,
+            clients: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Pushes new events to their subscribers, forever. Starts from the
+    /// newest event, as clients get a snapshot when they subscribe.
+    pub async fn follow(&self) {
+        let mut last = None;
+        loop {
+            if let Err(err) = self.poll(&mut last).await {
+                eprintln!("warning: following events: {err:#}");
+            }
+            time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn poll(&self, last: &mut Option<i64>) -> Result<()> {
+        let Some(after) = *last else {
+            let newest = self
+                .db
+                .json(
+                    "SELECT to_json(COALESCE(max(id), 0)) FROM events",
+                    &Args::default(),
+                )
+                .await?;
+            *last = Some(newest.as_i64().context("expected an event id")?);
+            return Ok(());
+        };
+        let mut args = Args::default();
+        let sql = format!(
+            "SELECT COALESCE(json_agg(json_build_object('id', e.id, 'market', e.market, \
+             'message', json_build_object('type', 'event', 'market', e.market, \
+             'name', e.name, 'data', e.data, 'signature', e.signature, \
+             'event_index', e.event_index, 'slot', e.slot, 'block_time', e.block_time, \
+             'commitment', e.commitment, 'odds', {})) ORDER BY e.id), '[]') \
+             FROM (SELECT * FROM events WHERE id > {} ORDER BY id LIMIT {BATCH}) e",
+            odds_json("e"),
+            args.add(after)
+        );
+        let rows = self.db.json(&sql, &args).await?;
+        let Value::Array(rows) = rows else {
+            bail!("expected rows");
+        };
+        for row in rows {
+            *last = row["id"].as_i64();
+            if let Some(market) = row["market"].as_str() {
+                self.publish(market, &row["message"]);
+            }
+        }
+        Ok(())
+    }
+
+    fn publish(&self, market: &str, message: &Value) {
+        let text = message.to_string();
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|_, client| {
+            !client.markets.contains(market)
+                || queue(&client.sender, Message::Text(text.clone().into()))
+        });
+    }
+
+    /// Queues `message` for connection `id`; false once it's gone.
+    fn send(&self, id: u64, message: Message) -> bool {
+        let mut clients = self.clients.lock().unwrap();
+        let Some(client) = clients.get(&id) else {
+            return false;
+        };
+        if !queue(&client.sender, message) {
+            clients.remove(&id);
+            return false;
+        }
+        true
+    }
+
+    fn send_json(&self, id: u64, message: &Value) -> bool {
+        self.send(id, Message::Text(message.to_string().into()))
+    }
+
+    /// Adds and removes connection `id`'s markets, then confirms them and
+    /// sends the odds of those it just joined.
+    async fn subscribe(&self, id: u64, add: &[String], remove: &[String]) -> Result<()> {
+        let (added, markets) = {
+            let mut clients = self.clients.lock().unwrap();
+            let Some(client) = clients.get_mut(&id) else {
+                return Ok(());
+            };
+            for market in remove {
+                client.markets.remove(market);
+            }
+            let added: Vec<String> = add
+                .iter()
+                .filter(|market| !client.markets.contains(*market))
+                .cloned()
+                .collect();
+            if client.markets.len() + added.len() > MAX_SUBSCRIPTIONS {
+                bail!("at most {MAX_SUBSCRIPTIONS} markets can be followed at once");
+            }
+            client.markets.extend(added.iter().cloned());
+            (added, client.markets.clone())
+        };
+        self.send_json(id, &json!({ "type": "subscribed", "markets": markets }));
+        if added.is_empty() {
+            return Ok(());
+        }
+        let mut args = Args::default();
+        let sql = format!(
+            "SELECT COALESCE(json_agg(json_build_object('type', 'odds', 'market', m.address, \
+             'status', m.status, 'odds', {})), '[]') FROM markets m WHERE m.address = ANY ({})",
+            odds_json("m"),
+            args.add(added)
+        );
+        let snapshots = self.db.json(&sql, &args).await?;
+        for snapshot in snapshots.as_array().into_iter().flatten() {
+            self.send_json(id, snapshot);
+        }
+        Ok(())
+    }
+}
+
+/// Queues a message without waiting; false when the connection is gone or
+/// has fallen too far behind.
+fn queue(sender: &Sender<Message>, message: Message) -> bool {
+    sender.try_send(message).is_ok()
+}
+
+/// A row's pools and implied probabilities, as a JSON expression.
+fn odds_json(row: &str) -> String {
+    let probability = |side: &str| {
+        format!(
+            "CASE WHEN {row}.staked_a + {row}.staked_b > 0 \
+             THEN ({row}.staked_{side} / ({row}.staked_a + {row}.staked_b))::float8 END"
+        )
+    };
+    format!(
+        "json_build_object('staked_a', {row}.staked_a::text, 'staked_b', {row}.staked_b::text, \
+         'probability_a', {}, 'probability_b', {})",
+        probability("a"),
+        probability("b")
+    )
+}
+
+/// `GET /ws?markets=`: upgrades the connection and serves it until either
+/// side closes it.
+pub async fn upgrade(
+    State(hub): State<Arc<Hub>>,
+    Query(params): Query<Params>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let markets = params
+        .get("markets")
+        .map(|list| markets(&list.split(',').map(Value::from).collect::<Vec<_>>()))
+        .transpose()
+        .map_err(|err| ApiError::BadRequest(format!("{err:#}")))?
+        .unwrap_or_default();
+    Ok(upgrade
+        .max_message_size(MAX_MESSAGE)
+        .on_upgrade(move |socket| serve(hub, socket, markets)))
+}
+
+async fn serve(hub: Arc<Hub>, mut socket: WebSocket, markets: Vec<String>) {
+    let (sender, mut receiver) = mpsc::channel(QUEUE);
+    let id = hub.next_id.fetch_add(1, Ordering::Relaxed);
+    hub.clients.lock().unwrap().insert(
+        id,
+        Client {
+            markets: BTreeSet::new(),
+            sender,
+        },
+    );
+    if !markets.is_empty() {
+        if let Err(err) = hub.subscribe(id, &markets, &[]).await {
+            hub.send_json(id, &error(&err));
+        }
+    }
+
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => {
+                // Pings are answered by the socket itself
+                let reply = match incoming {
+                    Some(Ok(Message::Text(text))) => {
+                        command(&hub, id, &text).await.err().map(|err| error(&err))
+                    }
+                    Some(Ok(Message::Binary(_))) => {
+                        Some(json!({ "type": "error", "message": "send JSON text messages" }))
+                    }
+                    Some(Ok(Message::Ping(_) | Message::Pong(_))) => None,
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                };
+                if let Some(reply) = reply {
+                    if !hub.send_json(id, &reply) {
+                        break;
+                    }
+                }
+            }
+            outgoing = time::timeout(PING_INTERVAL, receiver.recv()) => {
+                let message = match outgoing {
+                    Ok(Some(message)) => message,
+                    // Clients may stay quiet indefinitely; pings find the
+                    // dead ones
+                    Err(_) => Message::Ping(Bytes::new()),
+                    // Dropped by the hub for falling too far behind
+                    Ok(None) => Message::Close(None),
+                };
+                let closing = matches!(message, Message::Close(_));
+                if socket.send(message).await.is_err() || closing {
+                    break;
+                }
+            }
+        }
+    }
+    hub.clients.lock().unwrap().remove(&id);
+}
+
+fn error(err: &anyhow::Error) -> Value {
+    json!({ "type": "error", "message": format!("{err:#}") })
+}
+
+/// `{"subscribe": [...]}` or `{"unsubscribe": [...]}`.
+async fn command(hub: &Hub, id: u64, text: &str) -> Result<()> {
+    let command: Value = serde_json::from_str(text).context("messages must be JSON")?;
+    let list = |name: &str| match &command[name] {
+        Value::Null => Ok(Vec::new()),
+        Value::Array(items) => markets(items),
+        _ => bail!("{name} must be a list of market addresses"),
+    };
+    let (add, remove) = (list("subscribe")?, list("unsubscribe")?);
+    if add.is_empty() && remove.is_empty() {
+        bail!("expected {{\"subscribe\": [...]}} or {{\"unsubscribe\": [...]}}");
+    }
+    hub.subscribe(id, &add, &remove).await
+}
+
+fn markets(items: &[Value]) -> Result<Vec<String>> {
+    items
+        .iter()
+        .map(|item| match item.as_str() {
+            Some(market) if is_address(market) => Ok(market.to_string()),
+            _ => bail!("{item} isn't a market address"),
+        })
+        .collect()
+}
@@ -0,0 +1,234 @@
+//! `friend-bets-api`: a read-only REST and GraphQL API over the tables the
+//! indexer keeps, plus a WebSocket feed of new events, so frontends don't
+//! need RPC access for read paths. The one thing it writes is wallets'
+//! notification subscriptions, which the notifier reads.
+
+mod db;
+mod live;
+mod routes;
+mod schema;
+mod subscriptions;
+
+use std::collections::HashMap;
+use std::env;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use axum::extract::{DefaultBodyLimit, FromRef};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::Method;
+use axum::routing::{get, post};
+use axum::Router;
+use tokio::net::TcpListener;
+use tower_http::cors::{Any, CorsLayer};
+
+use crate::db::Database;
+use crate::live::Hub;
+use crate::schema::ApiSchema;
+
+const USAGE: &str = "\
+Usage: friend-bets-api [OPTIONS]
+
+Endpoints (GET):
+  /markets                    ?status= &creator= &mint=
+  /markets/:pubkey
+  /markets/:pubkey/bets       ?owner= &side=A|B &kind=bet|cash_out
+  /markets/:pubkey/odds-history
+  /users/:wallet/positions    ?status= &claimed=true|false
+  /leaderboard                ?mint= &sort=profit|volume|markets
+
+Lists take ?limit= (default 50, at most 200) and ?offset=.
+
+GraphQL (GET or POST):
+  /graphql                    ?query= &variables= &operationName=, or the same as a JSON body
+
+Notifications (POST to subscribe, DELETE to unsubscribe):
+  /subscriptions              {\"wallet\", \"email\" or \"push\", \"notify\", \"timestamp\", \"signature\"}
+                              signed by the wallet; the notifier sends them
+
+WebSocket:
+  /ws                         ?markets=a,b, then send {\"subscribe\": [...]} or {\"unsubscribe\": [...]}
+                              Pushes each market's events and odds as they're indexed
+
+Options:
+      --listen <ADDR>       Address to serve on, default 127.0.0.1:8080 [env: FRIEND_BETS_API_LISTEN]
+      --database-url <URL>  The indexer's Postgres connection URI [env: DATABASE_URL]
+  -h, --help                Print this help
+";
+
+/// Largest request body accepted.
+const MAX_BODY: usize = 64 * 1024;
+
+/// What the handlers share.
+#[derive(Clone)]
+struct AppState {
+    db: Arc<Database>,
+    hub: Arc<Hub>,
+    schema: ApiSchema,
+}
+
+impl FromRef<AppState> for Arc<Database> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.db)
+    }
+}
+
+impl FromRef<AppState> for Arc<Hub> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.hub)
+    }
+}
+
+impl FromRef<AppState> for ApiSchema {
+    fn from_ref(state: &AppState) -> Self {
+        state.schema.clone()
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let mut options = HashMap::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            flag if flag.starts_with("--") => {
+                let (name, value) = match flag[2..].split_once('=') {
+                    Some((name, value)) => (name.to_string(), value.to_string()),
+                    None => (
+                        flag[2..].to_string(),
+                        args.next().ok_or_else(|| anyhow!("{flag} needs a value"))?,
+                    ),
+                };
+                options.insert(name, value);
+            }
+            _ => bail!("unexpected argument {arg:?}"),
+        }
+    }
+    let mut option = |name: &str, var: &str| options.remove(name).or_else(|| env::var(var).ok());
+
+    let database_url = option("database-url", "DATABASE_URL")
+        .ok_or_else(|| anyhow!("set --database-url or DATABASE_URL"))?;
+    let listen =
+        option("listen", "FRIEND_BETS_API_LISTEN").unwrap_or_else(|| "127.0.0.1:8080".into());
+
+    let db = Arc::new(Database::new(database_url));
+    let hub = Arc::new(Hub::new(Arc::clone(&db)));
+    let app = router(AppState {
+        schema: schema::build(Arc::clone(&db)),
+        db,
+        hub: Arc::clone(&hub),
+    });
+
+    let runtime = tokio::runtime::Runtime::new().context("starting the runtime")?;
+    runtime.block_on(async {
+        let listener = TcpListener::bind(&listen)
+            .await
+            .with_context(|| format!("listening on {listen}"))?;
+        eprintln!("Listening on http://{}", listener.local_addr()?);
+        tokio::spawn(async move { hub.follow().await });
+        axum::serve(listener, app)
+            .await
+            .with_context(|| format!("serving on {listen}"))
+    })
+}
+
+fn router(state: AppState) -> Router {
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers([CONTENT_TYPE]);
+    Router::new()
+        .route("/markets", get(routes::markets))
+        .route("/markets/{market}", get(routes::market))
+        .route("/markets/{market}/bets", get(routes::bets))
+        .route("/markets/{market}/odds-history", get(routes::odds_history))
+        .route("/users/{wallet}/positions", get(routes::positions))
+        .route("/leaderboard", get(routes::leaderboard))
+        .route(
+            "/graphql",
+            get(routes::graphql_get).post(routes::graphql_post),
+        )
+        .route(
+            "/subscriptions",
+            post(subscriptions::subscribe).delete(subscriptions::unsubscribe),
+        )
+        .route("/ws", get(live::upgrade))
+        .fallback(routes::not_found)
+        .layer(DefaultBodyLimit::max(MAX_BODY))
+        .layer(cors)
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app() -> Router {
+        // Nothing here reaches the database
+        let db = Arc::new(Database::new("postgres://unused".into()));
+        router(AppState {
+            hub: Arc::new(Hub::new(Arc::clone(&db))),
+            schema: schema::build(Arc::clone(&db)),
+            db,
+        })
+    }
+
+    async fn call(request: Request<Body>) -> (StatusCode, Value) {
+        let response = app().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), MAX_BODY).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn get(uri: &str) -> Request<Body> {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn routes_requests() {
+        let (status, body) = call(get("/nowhere")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "no such endpoint");
+
+        let (status, body) = call(get("/markets?limit=0")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "limit must be between 1 and 200");
+        let (status, body) = call(get("/markets/not%20an%20address")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "\"not an address\" isn't an address");
+        let (status, _) = call(get("/ws?markets=nope")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let oversized = Request::post("/subscriptions")
+            .body(Body::from(vec![b' '; MAX_BODY + 1]))
+            .unwrap();
+        assert_eq!(call(oversized).await.0, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let preflight = Request::options("/subscriptions")
+            .header("Origin", "https://example.com")
+            .header("Access-Control-Request-Method", "DELETE")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(preflight).await.unwrap();
+        assert_eq!(response.headers()["access-control-allow-origin"], "*");
+    }
+}
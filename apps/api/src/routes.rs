@@ -0,0 +1,371 @@
+//! The API's endpoints, each a query over the indexer's tables.
+//!
+//! Lists take `limit` (default 50, at most 200) and `offset`, and answer
+//! `{"items": [...], "next_offset": n}` with `next_offset` null on the last
+//! page. Token amounts are strings of raw units, as they can exceed what a
+//! JSON number holds exactly.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_graphql as graphql;
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::db::{Args, Database};
+use crate::schema::ApiSchema;
+
+const DEFAULT_LIMIT: u64 = 50;
+const MAX_LIMIT: u64 = 200;
+
+pub const MARKET_STATUSES: [&str; 5] = [
+    "open",
+    "pending_resolve",
+    "resolved",
+    "cancelled",
+    "collected",
+];
+
+/// Every stake and return, one row per bet, cash-out or claim, with the
+/// market's mint and whether its payouts have settled.
+pub const ACTIVITY: &str = "(SELECT a.owner, a.market, a.staked, a.returned, a.bets, m.mint, \
+    m.status IN ('resolved', 'cancelled', 'collected') AS settled \
+    FROM (SELECT owner, market, CASE kind WHEN 'bet' THEN amount ELSE 0 END AS staked, \
+    COALESCE(payout, 0) AS returned, (kind = 'bet')::int AS bets FROM bets \
+    UNION ALL SELECT owner, market, 0, amount, 0 FROM claims) a \
+    JOIN markets m ON m.address = a.market)";
+
+/// Per-staker totals over [`ACTIVITY`], given its filter.
+pub fn stats_sql(filter: &str) -> String {
+    format!(
+        "SELECT owner, count(DISTINCT market) AS markets, COALESCE(sum(bets), 0) AS bet_count, \
+         COALESCE(sum(staked), 0) AS volume, \
+         COALESCE(sum(CASE WHEN settled THEN returned - staked ELSE 0 END), 0) AS profit \
+         FROM {ACTIVITY} activity{filter} GROUP BY owner"
+    )
+}
+
+pub const MARKET_COLUMNS: &str = "address, creator, mint, title, fee_bps, end_ts, \
+    resolve_deadline_ts, status, outcome, staked_a::text AS staked_a, \
+    staked_b::text AS staked_b, creator_fee_withdrawn, cloned_from, forked_from, depends_on, \
+    depends_on_outcome, created_slot, created_at";
+
+pub enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    Internal(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        ApiError::Internal(err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ApiError::Internal(err) => {
+                eprintln!("error: {err:#}");
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal error".into())
+            }
+        };
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+type Result<T> = std::result::Result<T, ApiError>;
+
+/// A request's query string. Empty values count as absent.
+#[derive(Default, Deserialize)]
+#[serde(transparent)]
+pub struct Params(HashMap<String, String>);
+
+impl Params {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .get(name)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+}
+
+pub async fn not_found() -> ApiError {
+    ApiError::NotFound("no such endpoint".into())
+}
+
+/// `GET /markets?status=&creator=&mint=`, newest first.
+pub async fn markets(
+    State(db): State<Arc<Database>>,
+    Query(params): Query<Params>,
+) -> Result<Json<Value>> {
+    let mut args = Args::default();
+    let mut filters = Vec::new();
+    if let Some(status) = one_of(&params, "status", &MARKET_STATUSES)? {
+        filters.push(format!("status = {}", args.add(status.to_string())));
+    }
+    for column in ["creator", "mint"] {
+        if let Some(address) = address_param(&params, column)? {
+            filters.push(format!("{column} = {}", args.add(address.to_string())));
+        }
+    }
+    let select = format!(
+        "SELECT {MARKET_COLUMNS} FROM markets{} ORDER BY created_slot DESC, address",
+        where_clause(&filters)
+    );
+    list(&db, &params, &select, args).await
+}
+
+/// `GET /markets/:pubkey`.
+pub async fn market(
+    State(db): State<Arc<Database>>,
+    Path(market): Path<String>,
+) -> Result<Json<Value>> {
+    address(&market)?;
+    let mut args = Args::default();
+    let sql = format!(
+        "SELECT row_to_json(m) FROM (SELECT {MARKET_COLUMNS} FROM markets WHERE address = {}) m",
+        args.add(market.clone())
+    );
+    let row = db.json(&sql, &args).await?;
+    if row.is_null() {
+        return Err(ApiError::NotFound(format!("no market {market}")));
+    }
+    Ok(Json(row))
+}
+
+/// `GET /markets/:pubkey/bets?owner=&side=&kind=`, newest first.
+pub async fn bets(
+    State(db): State<Arc<Database>>,
+    Path(market): Path<String>,
+    Query(params): Query<Params>,
+) -> Result<Json<Value>> {
+    known_market(&db, &market).await?;
+    let mut args = Args::default();
+    let mut filters = vec![format!("market = {}", args.add(market))];
+    if let Some(owner) = address_param(&params, "owner")? {
+        filters.push(format!("owner = {}", args.add(owner.to_string())));
+    }
+    if let Some(side) = one_of(&params, "side", &["A", "B"])? {
+        filters.push(format!("side = {}", args.add(side.to_string())));
+    }
+    if let Some(kind) = one_of(&params, "kind", &["bet", "cash_out"])? {
+        filters.push(format!("kind = {}", args.add(kind.to_string())));
+    }
+    let select = format!(
+        "SELECT signature, event_index, owner, side, kind, amount::text AS amount, \
+         payout::text AS payout, slot, block_time, commitment FROM bets{} \
+         ORDER BY slot DESC, signature DESC, event_index DESC",
+        where_clause(&filters)
+    );
+    list(&db, &params, &select, args).await
+}
+
+/// `GET /markets/:pubkey/odds-history`, oldest first: the pools and the
+/// program's implied odds (payout per unit staked, in basis points) after
+/// each bet and cash-out.
+pub async fn odds_history(
+    State(db): State<Arc<Database>>,
+    Path(market): Path<String>,
+    Query(params): Query<Params>,
+) -> Result<Json<Value>> {
+    known_market(&db, &market).await?;
+    let probability = |side: &str| {
+        format!(
+            "CASE WHEN staked_a + staked_b > 0 \
+             THEN (staked_{side} / (staked_a + staked_b))::float8 END AS probability_{side}"
+        )
+    };
+    let mut args = Args::default();
+    let select = format!(
+        "SELECT signature, event_index, slot, block_time, commitment, \
+         staked_a::text AS staked_a, staked_b::text AS staked_b, \
+         odds_a_bps::text AS odds_a_bps, odds_b_bps::text AS odds_b_bps, {}, {} \
+         FROM odds_history WHERE market = {} \
+         ORDER BY slot, signature, event_index",
+        probability("a"),
+        probability("b"),
+        args.add(market)
+    );
+    list(&db, &params, &select, args).await
+}
+
+/// `GET /users/:wallet/positions?status=&claimed=`, newest market first.
+pub async fn positions(
+    State(db): State<Arc<Database>>,
+    Path(wallet): Path<String>,
+    Query(params): Query<Params>,
+) -> Result<Json<Value>> {
+    address(&wallet)?;
+    let mut args = Args::default();
+    let mut filters = vec![format!("p.owner = {}", args.add(wallet))];
+    if let Some(status) = one_of(&params, "status", &MARKET_STATUSES)? {
+        filters.push(format!("m.status = {}", args.add(status.to_string())));
+    }
+    if let Some(claimed) = one_of(&params, "claimed", &["true", "false"])? {
+        filters.push(format!("p.claimed = {}", args.add(claimed == "true")));
+    }
+    let select = format!(
+        "SELECT p.address, p.market, m.title, m.mint, m.status, m.outcome, p.side, \
+         p.amount::text AS amount, p.claimed, p.claimed_amount::text AS claimed_amount \
+         FROM positions p JOIN markets m ON m.address = p.market{} \
+         ORDER BY m.created_slot DESC, p.market",
+        where_clause(&filters)
+    );
+    list(&db, &params, &select, args).await
+}
+
+/// `GET /leaderboard?mint=&sort=profit|volume|markets`.
+///
+/// Volume is every stake placed; profit is what came back less what was
+/// staked, over settled markets only, since open stakes aren't lost yet.
+/// Amounts in different mints don't add up, so pass `mint` when markets use
+/// more than one.
+pub async fn leaderboard(
+    State(db): State<Arc<Database>>,
+    Query(params): Query<Params>,
+) -> Result<Json<Value>> {
+    let sort = one_of(&params, "sort", &["profit", "volume", "markets"])?.unwrap_or("profit");
+    let mut args = Args::default();
+    let filter = match address_param(&params, "mint")? {
+        Some(mint) => format!(" WHERE activity.mint = {}", args.add(mint.to_string())),
+        None => String::new(),
+    };
+    let column = match sort {
+        "volume" => "volume",
+        "markets" => "markets",
+        _ => "profit",
+    };
+    let select = format!(
+        "SELECT owner, markets, volume::text AS volume, profit::text AS profit \
+         FROM ({}) stats ORDER BY {column} DESC, owner",
+        stats_sql(&filter)
+    );
+    list(&db, &params, &select, args).await
+}
+
+/// `GET /graphql?query=&variables=&operationName=`. See [`crate::schema`]
+/// for the schema.
+pub async fn graphql_get(
+    State(schema): State<ApiSchema>,
+    Query(params): Query<Params>,
+) -> Result<Json<graphql::Response>> {
+    let Some(query) = params.get("query") else {
+        return Err(ApiError::BadRequest("query is required".into()));
+    };
+    let mut request = graphql::Request::new(query);
+    if let Some(variables) = params.get("variables") {
+        let variables = serde_json::from_str(variables)
+            .map_err(|_| ApiError::BadRequest("variables isn't valid JSON".into()))?;
+        request = request.variables(graphql::Variables::from_json(variables));
+    }
+    if let Some(operation_name) = params.get("operationName") {
+        request = request.operation_name(operation_name);
+    }
+    Ok(Json(schema.execute(request).await))
+}
+
+/// `POST /graphql` with the query, variables and operation name in a JSON
+/// body.
+pub async fn graphql_post(
+    State(schema): State<ApiSchema>,
+    body: Bytes,
+) -> Result<Json<graphql::Response>> {
+    let request: graphql::Request = serde_json::from_slice(&body)
+        .map_err(|err| ApiError::BadRequest(format!("invalid GraphQL request: {err}")))?;
+    Ok(Json(schema.execute(request).await))
+}
+
+/// One page of `select`'s rows, with `args` for its placeholders.
+async fn list(db: &Database, params: &Params, select: &str, mut args: Args) -> Result<Json<Value>> {
+    let limit = number(params, "limit")?.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    let offset = number(params, "offset")?.unwrap_or(0);
+    // One extra row says whether there's another page
+    let sql = format!(
+        "SELECT COALESCE(json_agg(page), '[]') FROM ({select} LIMIT {} OFFSET {}) page",
+        args.add((limit + 1) as i64),
+        args.add(offset as i64)
+    );
+    let rows = db.json(&sql, &args).await?;
+    let Value::Array(mut items) = rows else {
+        return Err(anyhow::anyhow!("expected rows").into());
+    };
+    let next_offset = (items.len() as u64 > limit).then(|| offset + limit);
+    items.truncate(limit as usize);
+    Ok(Json(json!({ "items": items, "next_offset": next_offset })))
+}
+
+/// Checks that `market` is an indexed market's address.
+async fn known_market(db: &Database, market: &str) -> Result<()> {
+    address(market)?;
+    let mut args = Args::default();
+    let sql = format!(
+        "SELECT to_json(EXISTS (SELECT 1 FROM markets WHERE address = {}))",
+        args.add(market.to_string())
+    );
+    let exists = db.json(&sql, &args).await?;
+    if exists != Value::Bool(true) {
+        return Err(ApiError::NotFound(format!("no market {market}")));
+    }
+    Ok(())
+}
+
+fn where_clause(filters: &[String]) -> String {
+    if filters.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", filters.join(" AND "))
+    }
+}
+
+fn address(text: &str) -> Result<&str> {
+    if is_address(text) {
+        Ok(text)
+    } else {
+        Err(ApiError::BadRequest(format!("{text:?} isn't an address")))
+    }
+}
+
+pub fn is_address(text: &str) -> bool {
+    bs58::decode(text)
+        .into_vec()
+        .is_ok_and(|bytes| bytes.len() == 32)
+}
+
+fn address_param<'a>(params: &'a Params, name: &str) -> Result<Option<&'a str>> {
+    params.get(name).map(address).transpose()
+}
+
+fn one_of<'a>(params: &'a Params, name: &str, allowed: &[&str]) -> Result<Option<&'a str>> {
+    match params.get(name) {
+        Some(value) if !allowed.contains(&value) => Err(ApiError::BadRequest(format!(
+            "{name} must be one of {}",
+            allowed.join(", ")
+        ))),
+        value => Ok(value),
+    }
+}
+
+fn number(params: &Params, name: &str) -> Result<Option<u64>> {
+    params
+        .get(name)
+        .map(|value| {
+            value
+                .parse::<u32>()
+                .map(u64::from)
+                .map_err(|_| ApiError::BadRequest(format!("{name} must be a whole number")))
+        })
+        .transpose()
+}
@@ -0,0 +1,675 @@
+//! The GraphQL schema over the indexer's tables.
+//!
+//! Each object or list field is one query, so a nested view such as market
+//! → bets → bettor → stats runs a query per row it expands. Depth and
+//! complexity limits keep that bounded: a list costs its `limit` times the
+//! fields selected on each row. As in the REST endpoints, amounts are
+//! strings of raw units and lists take `limit` (default 50, at most 200)
+//! and `offset`.
+//!
+//! ```graphql
+//! type Query {
+//!   market(address: String!): Market
+//!   markets(status: String, creator: String, mint: String, limit: Int, offset: Int): [Market!]!
+//!   user(address: String!): User!
+//!   leaderboard(mint: String, sort: String, limit: Int, offset: Int): [UserStats!]!
+//! }
+//!
+//! type Market {
+//!   address: String!  creator: User!  mint: String  title: String!  feeBps: Int!
+//!   endTs: String!  resolveDeadlineTs: String!  status: String!  outcome: String
+//!   stakedA: String!  stakedB: String!  probabilityA: Float  probabilityB: Float
+//!   creatorFeeWithdrawn: Boolean!  clonedFrom: String  forkedFrom: String
+//!   dependsOn: String  dependsOnOutcome: String  createdSlot: Int!  createdAt: String
+//!   bets(owner: String, side: String, kind: String, limit: Int, offset: Int): [Bet!]!
+//!   positions(side: String, claimed: Boolean, limit: Int, offset: Int): [Position!]!
+//!   oddsHistory(limit: Int): [OddsPoint!]!
+//! }
+//!
+//! type Bet {
+//!   signature: String!  eventIndex: Int!  market: Market!  bettor: User!  side: String!
+//!   kind: String!  amount: String!  payout: String  slot: Int!  blockTime: String
+//!   commitment: String!
+//! }
+//!
+//! type Position {
+//!   address: String!  market: Market!  owner: User!  side: String!  amount: String!
+//!   claimed: Boolean!  claimedAmount: String!
+//! }
+//!
+//! type User {
+//!   address: String!
+//!   stats(mint: String): UserStats!
+//!   positions(status: String, claimed: Boolean, limit: Int, offset: Int): [Position!]!
+//!   bets(market: String, limit: Int, offset: Int): [Bet!]!
+//!   marketsCreated(status: String, limit: Int, offset: Int): [Market!]!
+//! }
+//!
+//! type UserStats {
+//!   address: String!  user: User!  markets: Int!  betCount: Int!  volume: String!
+//!   profit: String!
+//! }
+//!
+//! # The pools after each bet or cash-out, oldest first, with the program's
+//! # implied odds: payout per unit staked, in basis points
+//! type OddsPoint {
+//!   signature: String!  slot: Int!  blockTime: String  stakedA: String!  stakedB: String!
+//!   probabilityA: Float  probabilityB: Float  oddsABps: String!  oddsBBps: String!
+//! }
+//! ```
+//!
+//! Profit counts settled markets only, and amounts in different mints don't
+//! add up, so pass `mint` to `stats` and `leaderboard` when markets use more
+//! than one. Enum-like arguments take the REST values, in any case.
+
+use std::sync::Arc;
+
+use async_graphql::{
+    ComplexObject, Context, EmptyMutation, EmptySubscription, Error, Object, Result, Schema,
+    SimpleObject,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::db::{Args, Database};
+use crate::routes::{is_address, stats_sql, ACTIVITY, MARKET_COLUMNS, MARKET_STATUSES};
+
+const DEFAULT_LIMIT: i32 = 50;
+const MAX_LIMIT: i32 = 200;
+/// Most a query may cost, counting each field of each row it could return.
+const MAX_COMPLEXITY: usize = 10_000;
+/// Deepest nesting of selections.
+const MAX_DEPTH: usize = 12;
+
+pub type ApiSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+pub fn build(db: Arc<Database>) -> ApiSchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(db)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    async fn market(&self, ctx: &Context<'_>, address: String) -> Result<Option<Market>> {
+        required_address("address", &address)?;
+        market(ctx, &address).await
+    }
+
+    #[graphql(complexity = "list_cost(limit, child_complexity)")]
+    async fn markets(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        creator: Option<String>,
+        mint: Option<String>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Market>> {
+        let mut args = Args::default();
+        let mut filters = Vec::new();
+        if let Some(status) = one_of("status", status.as_deref(), &MARKET_STATUSES)? {
+            filters.push(format!("status = {}", args.add(status)));
+        }
+        if let Some(creator) = address("creator", creator)? {
+            filters.push(format!("creator = {}", args.add(creator)));
+        }
+        if let Some(mint) = address("mint", mint)? {
+            filters.push(format!("mint = {}", args.add(mint)));
+        }
+        markets(ctx, &filters, args, limit, offset).await
+    }
+
+    async fn user(&self, address: String) -> Result<User> {
+        required_address("address", &address)?;
+        Ok(User { address })
+    }
+
+    #[graphql(complexity = "list_cost(limit, child_complexity)")]
+    async fn leaderboard(
+        &self,
+        ctx: &Context<'_>,
+        mint: Option<String>,
+        sort: Option<String>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<UserStats>> {
+        let mut args = Args::default();
+        let filter = match address("mint", mint)? {
+            Some(mint) => format!(" WHERE activity.mint = {}", args.add(mint)),
+            None => String::new(),
+        };
+        let sort =
+            one_of("sort", sort.as_deref(), &["profit", "volume", "markets"])?.unwrap_or("profit");
+        let (limit, offset) = page(limit, offset)?;
+        let select = format!(
+            "SELECT owner, markets, bet_count, volume::text AS volume, \
+             profit::text AS profit FROM ({}) stats ORDER BY {sort} DESC, owner \
+             LIMIT {} OFFSET {}",
+            stats_sql(&filter),
+            args.add(limit),
+            args.add(offset)
+        );
+        rows(ctx, &select, &args).await
+    }
+}
+
+#[derive(Deserialize, SimpleObject)]
+#[graphql(complex)]
+pub struct Market {
+    address: String,
+    #[graphql(skip)]
+    creator: String,
+    mint: Option<String>,
+    title: String,
+    fee_bps: i32,
+    end_ts: String,
+    resolve_deadline_ts: String,
+    status: String,
+    outcome: Option<String>,
+    staked_a: String,
+    staked_b: String,
+    creator_fee_withdrawn: bool,
+    cloned_from: Option<String>,
+    forked_from: Option<String>,
+    depends_on: Option<String>,
+    depends_on_outcome: Option<String>,
+    created_slot: i64,
+    created_at: Option<String>,
+}
+
+#[ComplexObject]
+impl Market {
+    async fn creator(&self) -> User {
+        User {
+            address: self.creator.clone(),
+        }
+    }
+
+    async fn probability_a(&self) -> Option<f64> {
+        probability(&self.staked_a, &self.staked_b)
+    }
+
+    async fn probability_b(&self) -> Option<f64> {
+        probability(&self.staked_b, &self.staked_a)
+    }
+
+    #[graphql(complexity = "list_cost(limit, child_complexity)")]
+    async fn bets(
+        &self,
+        ctx: &Context<'_>,
+        owner: Option<String>,
+        side: Option<String>,
+        kind: Option<String>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Bet>> {
+        let mut args = Args::default();
+        let mut filters = vec![format!("market = {}", args.add(self.address.clone()))];
+        if let Some(owner) = address("owner", owner)? {
+            filters.push(format!("owner = {}", args.add(owner)));
+        }
+        if let Some(side) = one_of("side", side.as_deref(), &["A", "B"])? {
+            filters.push(format!("side = {}", args.add(side)));
+        }
+        if let Some(kind) = one_of("kind", kind.as_deref(), &["bet", "cash_out"])? {
+            filters.push(format!("kind = {}", args.add(kind)));
+        }
+        bets(ctx, &filters, args, limit, offset).await
+    }
+
+    #[graphql(complexity = "list_cost(limit, child_complexity)")]
+    async fn positions(
+        &self,
+        ctx: &Context<'_>,
+        side: Option<String>,
+        claimed: Option<bool>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Position>> {
+        let mut args = Args::default();
+        let mut filters = vec![format!("market = {}", args.add(self.address.clone()))];
+        if let Some(side) = one_of("side", side.as_deref(), &["A", "B"])? {
+            filters.push(format!("side = {}", args.add(side)));
+        }
+        if let Some(claimed) = claimed {
+            filters.push(format!("claimed = {}", args.add(claimed)));
+        }
+        let (limit, offset) = page(limit, offset)?;
+        let select = format!(
+            "SELECT {POSITION_COLUMNS} FROM positions{} ORDER BY amount DESC, owner \
+             LIMIT {} OFFSET {}",
+            where_clause(&filters),
+            args.add(limit),
+            args.add(offset)
+        );
+        rows(ctx, &select, &args).await
+    }
+
+    /// The latest `limit` points, oldest first.
+    #[graphql(complexity = "list_cost(limit, child_complexity)")]
+    async fn odds_history(&self, ctx: &Context<'_>, limit: Option<i32>) -> Result<Vec<OddsPoint>> {
+        let (limit, _) = page(limit, None)?;
+        let mut args = Args::default();
+        let select = format!(
+            "SELECT signature, slot, block_time, staked_a::text AS staked_a, \
+             staked_b::text AS staked_b, odds_a_bps::text AS odds_a_bps, \
+             odds_b_bps::text AS odds_b_bps FROM (SELECT * FROM odds_history \
+             WHERE market = {} ORDER BY slot DESC, signature DESC, event_index DESC \
+             LIMIT {}) latest ORDER BY slot, signature, event_index",
+            args.add(self.address.clone()),
+            args.add(limit)
+        );
+        rows(ctx, &select, &args).await
+    }
+}
+
+const BET_COLUMNS: &str = "signature, event_index, market, owner, side, kind, \
+    amount::text AS amount, payout::text AS payout, slot, block_time, commitment";
+
+#[derive(Deserialize, SimpleObject)]
+#[graphql(complex)]
+pub struct Bet {
+    signature: String,
+    event_index: i32,
+    #[graphql(skip)]
+    market: String,
+    #[graphql(skip)]
+    owner: String,
+    side: String,
+    kind: String,
+    amount: String,
+    payout: Option<String>,
+    slot: i64,
+    block_time: Option<String>,
+    commitment: String,
+}
+
+#[ComplexObject]
+impl Bet {
+    async fn market(&self, ctx: &Context<'_>) -> Result<Market> {
+        indexed_market(ctx, &self.market).await
+    }
+
+    async fn bettor(&self) -> User {
+        User {
+            address: self.owner.clone(),
+        }
+    }
+}
+
+const POSITION_COLUMNS: &str = "address, market, owner, side, amount::text AS amount, claimed, \
+    claimed_amount::text AS claimed_amount";
+
+#[derive(Deserialize, SimpleObject)]
+#[graphql(complex)]
+pub struct Position {
+    address: String,
+    #[graphql(skip)]
+    market: String,
+    #[graphql(skip)]
+    owner: String,
+    side: String,
+    amount: String,
+    claimed: bool,
+    claimed_amount: String,
+}
+
+#[ComplexObject]
+impl Position {
+    async fn market(&self, ctx: &Context<'_>) -> Result<Market> {
+        indexed_market(ctx, &self.market).await
+    }
+
+    async fn owner(&self) -> User {
+        User {
+            address: self.owner.clone(),
+        }
+    }
+}
+
+/// A wallet, which is only its address until a field asks for more.
+pub struct User {
+    address: String,
+}
+
+#[Object]
+impl User {
+    async fn address(&self) -> &str {
+        &self.address
+    }
+
+    async fn stats(&self, ctx: &Context<'_>, mint: Option<String>) -> Result<UserStats> {
+        let mut args = Args::default();
+        let owner = args.add(self.address.clone());
+        let mut filter = format!(" WHERE activity.owner = {owner}");
+        if let Some(mint) = address("mint", mint)? {
+            filter.push_str(&format!(" AND activity.mint = {}", args.add(mint)));
+        }
+        // Without GROUP BY, a user with no activity still gets a row
+        let select = format!(
+            "SELECT {owner}::text AS owner, count(DISTINCT market) AS markets, \
+             COALESCE(sum(bets), 0) AS bet_count, COALESCE(sum(staked), 0)::text AS volume, \
+             COALESCE(sum(CASE WHEN settled THEN returned - staked ELSE 0 END), 0)::text \
+             AS profit FROM {ACTIVITY} activity{filter}"
+        );
+        let stats = row(ctx, &select, &args).await?;
+        stats.ok_or_else(|| internal("expected a row of stats"))
+    }
+
+    #[graphql(complexity = "list_cost(limit, child_complexity)")]
+    async fn positions(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        claimed: Option<bool>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Position>> {
+        let mut args = Args::default();
+        let mut filters = vec![format!("p.owner = {}", args.add(self.address.clone()))];
+        if let Some(status) = one_of("status", status.as_deref(), &MARKET_STATUSES)? {
+            filters.push(format!("m.status = {}", args.add(status)));
+        }
+        if let Some(claimed) = claimed {
+            filters.push(format!("p.claimed = {}", args.add(claimed)));
+        }
+        let (limit, offset) = page(limit, offset)?;
+        let select = format!(
+            "SELECT p.address, p.market, p.owner, p.side, p.amount::text AS amount, \
+             p.claimed, p.claimed_amount::text AS claimed_amount FROM positions p \
+             JOIN markets m ON m.address = p.market{} \
+             ORDER BY m.created_slot DESC, p.market LIMIT {} OFFSET {}",
+            where_clause(&filters),
+            args.add(limit),
+            args.add(offset)
+        );
+        rows(ctx, &select, &args).await
+    }
+
+    #[graphql(complexity = "list_cost(limit, child_complexity)")]
+    async fn bets(
+        &self,
+        ctx: &Context<'_>,
+        market: Option<String>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Bet>> {
+        let mut args = Args::default();
+        let mut filters = vec![format!("owner = {}", args.add(self.address.clone()))];
+        if let Some(market) = address("market", market)? {
+            filters.push(format!("market = {}", args.add(market)));
+        }
+        bets(ctx, &filters, args, limit, offset).await
+    }
+
+    #[graphql(complexity = "list_cost(limit, child_complexity)")]
+    async fn markets_created(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Market>> {
+        let mut args = Args::default();
+        let mut filters = vec![format!("creator = {}", args.add(self.address.clone()))];
+        if let Some(status) = one_of("status", status.as_deref(), &MARKET_STATUSES)? {
+            filters.push(format!("status = {}", args.add(status)));
+        }
+        markets(ctx, &filters, args, limit, offset).await
+    }
+}
+
+#[derive(Deserialize, SimpleObject)]
+#[graphql(complex)]
+pub struct UserStats {
+    #[graphql(name = "address")]
+    owner: String,
+    markets: i64,
+    bet_count: i64,
+    volume: String,
+    profit: String,
+}
+
+#[ComplexObject]
+impl UserStats {
+    async fn user(&self) -> User {
+        User {
+            address: self.owner.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, SimpleObject)]
+#[graphql(complex)]
+pub struct OddsPoint {
+    signature: String,
+    slot: i64,
+    block_time: Option<String>,
+    staked_a: String,
+    staked_b: String,
+    #[graphql(name = "oddsABps")]
+    odds_a_bps: String,
+    #[graphql(name = "oddsBBps")]
+    odds_b_bps: String,
+}
+
+#[ComplexObject]
+impl OddsPoint {
+    async fn probability_a(&self) -> Option<f64> {
+        probability(&self.staked_a, &self.staked_b)
+    }
+
+    async fn probability_b(&self) -> Option<f64> {
+        probability(&self.staked_b, &self.staked_a)
+    }
+}
+
+/// The share of the pools staked on one side; none while both are empty.
+fn probability(side: &str, other: &str) -> Option<f64> {
+    let (side, other) = (side.parse::<f64>().ok()?, other.parse::<f64>().ok()?);
+    (side + other > 0.0).then(|| side / (side + other))
+}
+
+/// A list field's cost: its rows times what's selected on each.
+fn list_cost(limit: Option<i32>, child_complexity: usize) -> usize {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize * child_complexity
+}
+
+async fn market(ctx: &Context<'_>, address: &str) -> Result<Option<Market>> {
+    let mut args = Args::default();
+    let select = format!(
+        "SELECT {MARKET_COLUMNS} FROM markets WHERE address = {}",
+        args.add(address.to_string())
+    );
+    row(ctx, &select, &args).await
+}
+
+/// The market a bet or position belongs to, which the indexer always has.
+async fn indexed_market(ctx: &Context<'_>, address: &str) -> Result<Market> {
+    market(ctx, address)
+        .await?
+        .ok_or_else(|| internal(&format!("market {address} isn't indexed")))
+}
+
+/// Markets matching `filters`, which take `args`, newest first.
+async fn markets(
+    ctx: &Context<'_>,
+    filters: &[String],
+    mut args: Args,
+    limit: Option<i32>,
+    offset: Option<i32>,
+) -> Result<Vec<Market>> {
+    let (limit, offset) = page(limit, offset)?;
+    let select = format!(
+        "SELECT {MARKET_COLUMNS} FROM markets{} ORDER BY created_slot DESC, address \
+         LIMIT {} OFFSET {}",
+        where_clause(filters),
+        args.add(limit),
+        args.add(offset)
+    );
+    rows(ctx, &select, &args).await
+}
+
+/// Bets matching `filters`, which take `args`, newest first.
+async fn bets(
+    ctx: &Context<'_>,
+    filters: &[String],
+    mut args: Args,
+    limit: Option<i32>,
+    offset: Option<i32>,
+) -> Result<Vec<Bet>> {
+    let (limit, offset) = page(limit, offset)?;
+    let select = format!(
+        "SELECT {BET_COLUMNS} FROM bets{} \
+         ORDER BY slot DESC, signature DESC, event_index DESC LIMIT {} OFFSET {}",
+        where_clause(filters),
+        args.add(limit),
+        args.add(offset)
+    );
+    rows(ctx, &select, &args).await
+}
+
+/// The rows `select` returns, in its order.
+async fn rows<T: DeserializeOwned>(ctx: &Context<'_>, select: &str, args: &Args) -> Result<Vec<T>> {
+    let db = ctx.data_unchecked::<Arc<Database>>();
+    let sql = format!("SELECT COALESCE(json_agg(r), '[]') FROM ({select}) r");
+    let rows = db
+        .json(&sql, args)
+        .await
+        .map_err(|err| internal(&format!("{err:#}")))?;
+    serde_json::from_value(rows).map_err(|err| internal(&err.to_string()))
+}
+
+async fn row<T: DeserializeOwned>(
+    ctx: &Context<'_>,
+    select: &str,
+    args: &Args,
+) -> Result<Option<T>> {
+    Ok(rows(ctx, select, args).await?.pop())
+}
+
+/// Logs `err` and answers with a generic error, as the REST endpoints do.
+fn internal(err: &str) -> Error {
+    eprintln!("error: graphql: {err}");
+    Error::new("internal error")
+}
+
+fn where_clause(filters: &[String]) -> String {
+    if filters.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", filters.join(" AND "))
+    }
+}
+
+/// An optional address argument, once checked.
+fn address(name: &str, value: Option<String>) -> Result<Option<String>> {
+    if let Some(value) = &value {
+        required_address(name, value)?;
+    }
+    Ok(value)
+}
+
+fn required_address(name: &str, value: &str) -> Result<()> {
+    if !is_address(value) {
+        return Err(Error::new(format!("{name}: {value:?} isn't an address")));
+    }
+    Ok(())
+}
+
+/// One of `allowed`, matched ignoring case as enums are often written in
+/// capitals.
+fn one_of(
+    name: &str,
+    value: Option<&str>,
+    allowed: &[&'static str],
+) -> Result<Option<&'static str>> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    allowed
+        .iter()
+        .find(|allowed| allowed.eq_ignore_ascii_case(value))
+        .copied()
+        .map(Some)
+        .ok_or_else(|| Error::new(format!("{name} must be one of {}", allowed.join(", "))))
+}
+
+/// `limit` and `offset`, as Postgres takes them.
+fn page(limit: Option<i32>, offset: Option<i32>) -> Result<(i64, i64)> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(Error::new(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    let offset = offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(Error::new("offset must be a whole number"));
+    }
+    Ok((limit.into(), offset.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The errors `query` gets without reaching the database.
+    async fn errors(query: &str) -> Vec<String> {
+        let schema = build(Arc::new(Database::new("postgres://unused".into())));
+        let response = schema.execute(query).await;
+        response
+            .errors
+            .into_iter()
+            .map(|error| error.message)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn describes_the_schema() {
+        let schema = build(Arc::new(Database::new("postgres://unused".into())));
+        let sdl = schema.sdl();
+        assert!(sdl.contains("oddsHistory(limit: Int): [OddsPoint!]!"));
+        assert!(sdl.contains("oddsABps: String!"));
+        assert!(sdl.contains("marketsCreated(status: String, limit: Int, offset: Int): [Market!]!"));
+
+        let response = schema.execute("{ __typename }").await;
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            serde_json::json!({ "__typename": "Query" })
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_queries() {
+        assert_eq!(
+            errors("{ markets { nope } }").await,
+            [r#"Unknown field "nope" on type "Market"."#]
+        );
+        assert_eq!(
+            errors("{ market(address: \"x\") { title } }").await,
+            ["address: \"x\" isn't an address"]
+        );
+        assert_eq!(
+            errors("{ markets(status: \"gone\") { title } }").await,
+            ["status must be one of open, pending_resolve, resolved, cancelled, collected"]
+        );
+        assert_eq!(
+            errors("{ markets(limit: 0) { title } }").await,
+            ["limit must be between 1 and 200"]
+        );
+        assert_eq!(
+            errors("{ markets(limit: 200) { bets(limit: 200) { slot } } }").await,
+            ["Query is too complex."]
+        );
+        assert_eq!(
+            errors("mutation { markets { title } }").await[0],
+            "Schema is not configured for mutations."
+        );
+    }
+}
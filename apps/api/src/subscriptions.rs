@@ -0,0 +1,303 @@
+//! `POST` and `DELETE /subscriptions`: wallets opting in to, or out of,
+//! email and web push notifications, which the notifier sends.
+//!
+//! The body names the wallet, one address to notify (`email`, or a
+//! browser's `push` subscription as `PushSubscription.toJSON()` gives it),
+//! `timestamp` in Unix seconds and `signature`, the wallet's base58
+//! signature of [`message`]. Signing proves the wallet is the caller's; the
+//! timestamp keeps an old signature from being replayed for long. When
+//! subscribing, `notify` picks which of [`NOTIFICATIONS`] to send, all of
+//! them by default:
+//!
+//! ```json
+//! {"wallet": "8qbH…", "email": "me@example.com", "notify": ["resolved", "unclaimed"],
+//!  "timestamp": 1760000000, "signature": "5xFr…"}
+//! ```
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::Json;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde_json::{json, Value};
+
+use crate::db::{Args, Database};
+use crate::routes::{is_address, ApiError};
+
+type Result<T> = std::result::Result<T, ApiError>;
+
+/// What a wallet can be notified about.
+const NOTIFICATIONS: [&str; 4] = ["closing_soon", "resolve_due", "resolved", "unclaimed"];
+
+/// How far a signed timestamp may be from now, in seconds.
+const MAX_AGE: i64 = 600;
+/// Most addresses one wallet can have notified.
+const MAX_PER_WALLET: u64 = 10;
+
+/// Where a wallet is notified.
+enum Target {
+    Email(String),
+    Push {
+        endpoint: String,
+        p256dh: String,
+        auth: String,
+    },
+}
+
+impl Target {
+    fn kind(&self) -> &'static str {
+        match self {
+            Target::Email(_) => "email",
+            Target::Push { .. } => "push",
+        }
+    }
+
+    fn address(&self) -> &str {
+        match self {
+            Target::Email(email) => email,
+            Target::Push { endpoint, .. } => endpoint,
+        }
+    }
+}
+
+pub async fn subscribe(State(db): State<Arc<Database>>, body: Bytes) -> Result<Json<Value>> {
+    handle(&db, true, &body).await
+}
+
+pub async fn unsubscribe(State(db): State<Arc<Database>>, body: Bytes) -> Result<Json<Value>> {
+    handle(&db, false, &body).await
+}
+
+async fn handle(db: &Database, subscribe: bool, body: &[u8]) -> Result<Json<Value>> {
+    let body: Value = serde_json::from_slice(body)
+        .map_err(|err| ApiError::BadRequest(format!("invalid JSON body: {err}")))?;
+    let wallet = match body["wallet"].as_str() {
+        Some(wallet) if is_address(wallet) => wallet,
+        _ => return Err(bad("wallet must be an address")),
+    };
+    let target = target(&body)?;
+    let timestamp = body["timestamp"]
+        .as_i64()
+        .ok_or_else(|| bad("timestamp must be Unix seconds"))?;
+    if (now() - timestamp).abs() > MAX_AGE {
+        return Err(bad("timestamp is too far from now; sign a new message"));
+    }
+    let signature = body["signature"]
+        .as_str()
+        .and_then(|signature| bs58::decode(signature).into_vec().ok())
+        .and_then(|signature| <[u8; 64]>::try_from(signature).ok())
+        .ok_or_else(|| bad("signature must be a base58 ed25519 signature"))?;
+    let pubkey: [u8; 32] = bs58::decode(wallet)
+        .into_vec()
+        .ok()
+        .and_then(|pubkey| pubkey.try_into().ok())
+        .ok_or_else(|| bad("wallet must be an address"))?;
+    let message = message(subscribe, wallet, &target, timestamp);
+    if !verify(&pubkey, message.as_bytes(), &signature) {
+        return Err(bad("signature doesn't match the wallet and message"));
+    }
+
+    // The wallet, kind and target, as $1, $2 and $3
+    let mut args = Args::default();
+    args.add(wallet.to_string());
+    args.add(target.kind());
+    args.add(target.address().to_string());
+    let matching = "wallet = $1 AND kind = $2 AND target = $3";
+    if !subscribe {
+        let removed = db
+            .json(
+                &format!(
+                    "WITH removed AS (DELETE FROM subscriptions WHERE {matching} RETURNING 1) \
+                     SELECT to_json(count(*)) FROM removed"
+                ),
+                &args,
+            )
+            .await?;
+        return Ok(Json(json!({ "removed": removed })));
+    }
+
+    let notify = notify(&body)?;
+    let others = db
+        .json(
+            &format!("SELECT to_json(count(*)) FROM subscriptions WHERE wallet = $1 AND NOT ({matching})"),
+            &args,
+        )
+        .await?;
+    if others.as_u64().unwrap_or_default() >= MAX_PER_WALLET {
+        return Err(bad(&format!(
+            "a wallet can have at most {MAX_PER_WALLET} subscriptions"
+        )));
+    }
+    let (p256dh, auth) = match &target {
+        Target::Push { p256dh, auth, .. } => (Some(p256dh.clone()), Some(auth.clone())),
+        Target::Email(_) => (None, None),
+    };
+    let sql = format!(
+        "INSERT INTO subscriptions (wallet, kind, target, p256dh, auth, notify) \
+         VALUES ($1, $2, $3, {}, {}, {}) \
+         ON CONFLICT (wallet, kind, target) DO UPDATE SET p256dh = EXCLUDED.p256dh, \
+           auth = EXCLUDED.auth, notify = EXCLUDED.notify \
+         RETURNING json_build_object('wallet', wallet, 'kind', kind, 'target', target, \
+           'notify', notify, 'created_at', created_at)",
+        args.add(p256dh),
+        args.add(auth),
+        args.add(notify)
+    );
+    let row = db.json(&sql, &args).await?;
+    Ok(Json(row))
+}
+
+/// The text a wallet signs to subscribe `target`, or unsubscribe it.
+fn message(subscribe: bool, wallet: &str, target: &Target, timestamp: i64) -> String {
+    let heading = if subscribe {
+        "Subscribe to friend-bets notifications"
+    } else {
+        "Unsubscribe from friend-bets notifications"
+    };
+    let target = match target {
+        Target::Email(email) => format!("Email: {email}"),
+        Target::Push { endpoint, .. } => format!("Push: {endpoint}"),
+    };
+    format!("{heading}\nWallet: {wallet}\n{target}\nIssued: {timestamp}")
+}
+
+fn target(body: &Value) -> Result<Target> {
+    match (&body["email"], &body["push"]) {
+        (Value::String(email), Value::Null) => {
+            if !is_email(email) {
+                return Err(bad(&format!("{email:?} isn't an email address")));
+            }
+            Ok(Target::Email(email.clone()))
+        }
+        (Value::Null, push @ Value::Object(_)) => {
+            let endpoint = push["endpoint"].as_str().unwrap_or_default();
+            if !endpoint.starts_with("https://")
+                || endpoint.len() > 1024
+                || endpoint.contains(|c: char| c.is_whitespace() || c.is_control())
+            {
+                return Err(bad("push.endpoint must be an https URL"));
+            }
+            let key = |name: &str, length: usize| {
+                let text = push["keys"][name].as_str().unwrap_or_default();
+                match URL_SAFE_NO_PAD.decode(text.trim_end_matches('=')) {
+                    Ok(bytes) if bytes.len() == length => Ok(text.trim_end_matches('=').into()),
+                    _ => Err(bad(&format!(
+                        "push.keys.{name} must be {length} bytes of base64url"
+                    ))),
+                }
+            };
+            Ok(Target::Push {
+                endpoint: endpoint.to_string(),
+                p256dh: key("p256dh", 65)?,
+                auth: key("auth", 16)?,
+            })
+        }
+        _ => Err(bad("give either email or push")),
+    }
+}
+
+fn notify(body: &Value) -> Result<Vec<String>> {
+    let notify = match &body["notify"] {
+        Value::Null => return Ok(NOTIFICATIONS.map(String::from).to_vec()),
+        Value::Array(kinds) => kinds,
+        _ => return Err(bad("notify must be a list")),
+    };
+    let mut kinds = Vec::new();
+    for kind in notify {
+        match kind.as_str() {
+            Some(kind) if NOTIFICATIONS.contains(&kind) => kinds.push(kind.to_string()),
+            _ => {
+                return Err(bad(&format!(
+                    "notify can hold {}",
+                    NOTIFICATIONS.join(", ")
+                )))
+            }
+        }
+    }
+    if kinds.is_empty() {
+        return Err(bad("notify is empty; unsubscribe instead"));
+    }
+    kinds.sort();
+    kinds.dedup();
+    Ok(kinds)
+}
+
+/// A plausible address: one `@` between a local part and a dotted domain,
+/// with nothing that could break out of a mail header.
+fn is_email(text: &str) -> bool {
+    let Some((local, domain)) = text.split_once('@') else {
+        return false;
+    };
+    text.len() <= 254
+        && !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !domain.contains('@')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !"<>()[],;:\\\"".contains(c))
+}
+
+/// Checks an ed25519 signature by `pubkey` of `message`, strictly: keys
+/// of small order and non-canonical signatures are refused.
+fn verify(pubkey: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    VerifyingKey::from_bytes(pubkey).is_ok_and(|key| {
+        key.verify_strict(message, &Signature::from_bytes(signature))
+            .is_ok()
+    })
+}
+
+fn bad(message: &str) -> ApiError {
+    ApiError::BadRequest(message.to_string())
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn verifies_signatures() {
+        // RFC 8032, section 7.1, test 2
+        let pubkey: [u8; 32] =
+            hex("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c")
+                .try_into()
+                .unwrap();
+        let signature: [u8; 64] = hex(
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+             085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        )
+        .try_into()
+        .unwrap();
+        assert!(verify(&pubkey, &[0x72], &signature));
+        assert!(!verify(&pubkey, &[0x73], &signature));
+
+        assert!(is_email("me+bets@example.co.uk"));
+        assert!(!is_email("me@localhost"));
+        assert!(!is_email("me@example.com\r\nBcc: you@example.com"));
+        let target = Target::Email("me@example.com".into());
+        assert_eq!(
+            message(false, "8qbH", &target, 1760000000),
+            "Unsubscribe from friend-bets notifications\nWallet: 8qbH\n\
+             Email: me@example.com\nIssued: 1760000000"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! `admin-log`: every privileged action taken on the program, read from the
+//! config's transaction history and checked against the hash the config
+//! keeps of them.
+//!
+//! The check needs the whole history, which only archive nodes keep; a node
+//! missing the early transactions reports the log incomplete.
+
+use anyhow::{bail, Context, Result};
+use friend_bets_sdk::events::{parse_logs, verify_admin_log, FriendBetsEvent};
+use friend_bets_sdk::pda::config_address;
+use friend_bets_sdk::{AdminAction, Config as ProgramConfig, Market};
+use serde_json::json;
+
+use crate::args::Args;
+use crate::rpc::RpcClient;
+use crate::units::format_time;
+use crate::watch::{admin_action_json, describe_admin_action, hex};
+
+pub fn admin_log(rpc: &RpcClient, mut args: Args) -> Result<()> {
+    let json = args.switch("json");
+    args.finish()?;
+
+    let config_address = config_address().0;
+    let config = rpc
+        .optional_account::<ProgramConfig>(&config_address)?
+        .context("the program has no config yet")?;
+
+    let history = rpc.get_signature_history(&config_address)?;
+    eprintln!("Reading {} transactions", history.len());
+    let mut entries = Vec::new();
+    let mut signatures = Vec::new();
+    for info in history.iter().filter(|info| !info.failed) {
+        let Some(logs) = rpc.get_transaction_logs(&info.signature)? else {
+            continue;
+        };
+        for event in parse_logs(&logs)? {
+            if let FriendBetsEvent::AdminActionLogged(entry) = event {
+                entries.push(entry);
+                signatures.push(info.signature.clone());
+            }
+        }
+    }
+    let verified = verify_admin_log(&entries, &config);
+
+    if json {
+        let rows: Vec<_> = entries
+            .iter()
+            .zip(&signatures)
+            .map(|(entry, signature)| {
+                json!({
+                    "signature": signature,
+                    "sequence": entry.sequence,
+                    "admin": entry.admin.to_string(),
+                    "timestamp": entry.timestamp,
+                    "action": admin_action_json(&entry.action),
+                    "log_hash": hex(&entry.log_hash),
+                })
+            })
+            .collect();
+        let document = json!({
+            "admin_actions": config.admin_actions,
+            "admin_log_hash": hex(&config.admin_log_hash),
+            "verified": verified.is_ok(),
+            "entries": rows,
+        });
+        println!("{}", serde_json::to_string_pretty(&document)?);
+    } else {
+        for entry in &entries {
+            let decimals = match entry.action {
+                AdminAction::Compensated { market, .. } => rpc
+                    .optional_account::<Market>(&market)?
+                    .map(|market| rpc.mint_decimals(&market.mint))
+                    .transpose()?,
+                _ => None,
+            };
+            println!(
+                "#{:<4} {}  {}  {}",
+                entry.sequence,
+                format_time(entry.timestamp),
+                entry.admin,
+                describe_admin_action(&entry.action, decimals)
+            );
+        }
+    }
+
+    match verified {
+        Ok(()) => {
+            eprintln!(
+                "Verified {} admin actions against the config's log hash",
+                entries.len()
+            );
+            Ok(())
+        }
+        Err(err) => bail!("the admin log doesn't check out: {err}"),
+    }
+}
@@ -0,0 +1,129 @@
+//! Command-line parsing.
+//!
+//! Options take a value (`--fee-bps 250` or `--fee-bps=250`); switches are
+//! the names in [`SWITCHES`] and take none. Everything else is positional.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+pub const SWITCHES: &[&str] = &[
+    "help",
+    "allow-cash-out",
+    "lock-odds",
+    "burn-fee",
+    "stake-rebate",
+    "json",
+    "dry-run",
+    "distribute",
+    "gc",
+    "claim-all",
+];
+
+#[derive(Debug, Default)]
+pub struct Args {
+    positional: VecDeque<String>,
+    options: HashMap<String, String>,
+    switches: HashSet<String>,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name.to_string(),
+                None => match arg.as_str() {
+                    "-u" => "url".to_string(),
+                    "-k" => "keypair".to_string(),
+                    "-p" => "profile".to_string(),
+                    "-h" => "help".to_string(),
+                    _ => {
+                        parsed.positional.push_back(arg);
+                        continue;
+                    }
+                },
+            };
+
+            if let Some((name, value)) = name.split_once('=') {
+                parsed.options.insert(name.to_string(), value.to_string());
+            } else if SWITCHES.contains(&name.as_str()) {
+                parsed.switches.insert(name);
+            } else {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--{name} needs a value"))?;
+                parsed.options.insert(name, value);
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn positional(&mut self, name: &str) -> Result<String> {
+        self.positional
+            .pop_front()
+            .ok_or_else(|| anyhow!("missing <{name}>"))
+    }
+
+    pub fn optional_positional(&mut self) -> Option<String> {
+        self.positional.pop_front()
+    }
+
+    /// Parses the next positional argument.
+    pub fn parse_positional<T>(&mut self, name: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let value = self.positional(name)?;
+        value
+            .parse()
+            .map_err(|err| anyhow!("invalid <{name}> {value:?}: {err}"))
+    }
+
+    pub fn option(&mut self, name: &str) -> Option<String> {
+        self.options.remove(name)
+    }
+
+    pub fn parse_option<T>(&mut self, name: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        self.option(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|err| anyhow!("invalid --{name} {value:?}: {err}"))
+            })
+            .transpose()
+    }
+
+    pub fn required_option<T>(&mut self, name: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        self.parse_option(name)?
+            .with_context(|| format!("missing --{name}"))
+    }
+
+    pub fn switch(&mut self, name: &str) -> bool {
+        self.switches.remove(name)
+    }
+
+    /// Fails on anything the command didn't consume.
+    pub fn finish(self) -> Result<()> {
+        if let Some(arg) = self.positional.front() {
+            bail!("unexpected argument {arg:?}");
+        }
+        if let Some(name) = self.options.keys().chain(&self.switches).next() {
+            bail!("unknown option --{name}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::{anyhow, Result};
+use friend_bets_sdk::keypair::Keypair;
+use friend_bets_sdk::offline::PartiallySignedTransaction;
+use friend_bets_sdk::send::{
+    send_with_retry, Commitment, SendClient, SendConfig, SendError, SignatureStatus,
+};
+use solana_message::VersionedMessage;
+use solana_signature::Signature;
+
+use crate::rpc::RpcClient;
+use crate::signer::Signer;
+
+/// RPC access plus the signer that pays for and signs transactions.
+pub struct Client {
+    pub rpc: RpcClient,
+    pub payer: Signer,
+    pub commitment: Commitment,
+}
+
+impl Client {
+    pub fn pubkey(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    /// Signs and sends `instructions`, waiting for confirmation.
+    pub fn send(&self, instructions: &[Instruction]) -> Result<Signature> {
+        self.send_signed(instructions, &[])
+    }
+
+    /// Like [`Client::send`], for instructions that other keypairs must sign
+    /// too. The payer still pays.
+    pub fn send_signed(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Signature> {
+        let config = SendConfig {
+            commitment: self.commitment,
+            poll_interval: Duration::from_millis(800),
+            ..Default::default()
+        };
+        let sender = Sender {
+            client: self,
+            signers,
+        };
+        send_with_retry(&sender, &self.payer.pubkey(), instructions, &[], &config).map_err(|err| {
+            match err {
+                SendError::Rpc(err) => err,
+                other => anyhow!("{other}"),
+            }
+        })
+    }
+}
+
+/// The client plus the extra keypairs a transaction needs.
+struct Sender<'a> {
+    client: &'a Client,
+    signers: &'a [&'a Keypair],
+}
+
+impl SendClient for Sender<'_> {
+    type Error = anyhow::Error;
+
+    fn recent_prioritization_fees(&self, writable: &[Pubkey]) -> Result<Vec<u64>> {
+        self.client.rpc.get_recent_prioritization_fees(writable)
+    }
+
+    fn latest_blockhash(&self) -> Result<(Hash, u64)> {
+        self.client.rpc.get_latest_blockhash()
+    }
+
+    fn block_height(&self) -> Result<u64> {
+        self.client.rpc.get_block_height()
+    }
+
+    fn sign_and_send(&self, message: &VersionedMessage) -> Result<Signature> {
+        let mut tx = PartiallySignedTransaction::new(message.clone());
+        let message_bytes = tx.message_bytes();
+        let payer = &self.client.payer;
+        tx.add_signature(&payer.pubkey(), payer.sign(&message_bytes)?)?;
+        for signer in self.signers {
+            tx.add_signature(&signer.pubkey(), signer.sign(&message_bytes))?;
+        }
+        self.client.rpc.send_transaction(&tx.serialize())
+    }
+
+    fn signature_status(&self, signature: &Signature) -> Result<Option<SignatureStatus>> {
+        Ok(self
+            .client
+            .rpc
+            .get_signature_status(signature)?
+            .map(|(confirmation, err)| match err {
+                Some(err) => SignatureStatus::Failed(err),
+                None => SignatureStatus::Landed(match confirmation.as_str() {
+                    "finalized" => Commitment::Finalized,
+                    "confirmed" => Commitment::Confirmed,
+                    _ => Commitment::Processed,
+                }),
+            }))
+    }
+}
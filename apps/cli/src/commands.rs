@@ -0,0 +1,615 @@
+//! Subcommands that send a single program instruction.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::{anyhow, bail, Result};
+use friend_bets_sdk::fetch::{fetch_portfolio_pages, find_market_by_title};
+use friend_bets_sdk::instructions::{self, NewMarket};
+use friend_bets_sdk::pda::{
+    config_address, portfolio_address, position_address, scheduled_bet_address, stake_address,
+};
+use friend_bets_sdk::simulate::{implied_odds, simulate_cash_out, simulate_claim};
+use friend_bets_sdk::{
+    BetSide, Config as ProgramConfig, FeeSplit, Market, MarketOptions, MarketStatus, Portfolio,
+    ScheduledBet, StakeAccount, DEFAULT_FEE,
+};
+
+use crate::args::Args;
+use crate::client::Client;
+use crate::rpc::RpcClient;
+use crate::units::{format_amount, format_odds, format_time, now, parse_amount, parse_time};
+
+/// Portfolio pages closed in one transaction by `close-portfolio`.
+const PORTFOLIO_CLOSES_PER_TRANSACTION: usize = 8;
+
+pub fn parse_pubkey(value: &str) -> Result<Pubkey> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("invalid address {value:?}"))
+}
+
+pub fn parse_side(value: &str) -> Result<BetSide> {
+    match value.to_ascii_lowercase().as_str() {
+        "a" => Ok(BetSide::A),
+        "b" => Ok(BetSide::B),
+        _ => bail!("side must be a or b, got {value:?}"),
+    }
+}
+
+fn market_arg(client: &Client, args: &mut Args) -> Result<(Pubkey, Market)> {
+    let address = parse_pubkey(&args.positional("market")?)?;
+    Ok((address, client.rpc.account(&address)?))
+}
+
+pub fn create_market(client: &Client, mut args: Args) -> Result<()> {
+    let mint = parse_pubkey(&args.required_option::<String>("mint")?)?;
+    let market_id = args.parse_option("id")?.unwrap_or(now() as u64);
+    // Left out, the fee and times take the config's defaults
+    let fee_bps = args.parse_option("fee-bps")?.unwrap_or(DEFAULT_FEE);
+    let end_ts = match args.option("end") {
+        Some(value) => parse_time(&value)?,
+        None => 0,
+    };
+    let resolve_deadline_ts = match args.option("resolve-deadline") {
+        Some(value) => parse_time(&value)?,
+        None => 0,
+    };
+    let title: String = args.required_option("title")?;
+    let options = MarketOptions {
+        allow_cash_out: args.switch("allow-cash-out"),
+        cashout_haircut_bps: args.parse_option("haircut-bps")?.unwrap_or(0),
+        lock_odds: args.switch("lock-odds"),
+        burn_fee: args.switch("burn-fee"),
+    };
+    let side = args
+        .option("bet")
+        .map(|side| parse_side(&side))
+        .transpose()?;
+    let amount = args.option("amount");
+    let unique_title = args.switch("unique-title");
+    args.finish()?;
+
+    // Link to a market already open on the question rather than splitting
+    // its bettors; settled ones only bump the nonce
+    let title_nonce = if unique_title {
+        let existing = find_market_by_title(&client.rpc, &title)?;
+        for (_, registration) in &existing {
+            let market: Option<Market> = client.rpc.optional_account(&registration.market)?;
+            if market.is_some_and(|market| {
+                matches!(
+                    market.status,
+                    MarketStatus::Open | MarketStatus::PendingResolve
+                )
+            }) {
+                bail!(
+                    "market {} is already open on this question",
+                    registration.market
+                );
+            }
+        }
+        let nonce = existing.last().map_or(Ok(0), |(_, registration)| {
+            registration
+                .nonce
+                .checked_add(1)
+                .ok_or_else(|| anyhow!("no title nonce left for {title:?}"))
+        })?;
+        Some(nonce)
+    } else {
+        None
+    };
+
+    let new_market = NewMarket {
+        creator: client.pubkey(),
+        mint,
+        market_id,
+    };
+    // The first bet goes in the same instruction, so a failed bet can't
+    // leave an empty market behind
+    let first_bet = match (side, amount) {
+        (Some(side), Some(amount)) => {
+            let decimals = client.rpc.mint_decimals(&mint)?;
+            Some((side, parse_amount(&amount, decimals)?, decimals))
+        }
+        (None, None) => None,
+        _ => bail!("--bet and --amount go together"),
+    };
+    let instruction = match first_bet {
+        Some((side, amount, _)) => instructions::initialize_market_with_bet(
+            &new_market,
+            fee_bps,
+            end_ts,
+            resolve_deadline_ts,
+            title.clone(),
+            options,
+            side,
+            amount,
+        ),
+        None => instructions::initialize_market(
+            &new_market,
+            fee_bps,
+            end_ts,
+            resolve_deadline_ts,
+            title.clone(),
+            options,
+        ),
+    };
+    // A stake can lift the fee ceiling to the creator's fee tier
+    let staked = client
+        .rpc
+        .optional_account::<StakeAccount>(&stake_address(&new_market.creator).0)?
+        .is_some();
+    let instruction = if staked {
+        instructions::with_creator_stake(instruction, &new_market.creator)
+    } else {
+        instruction
+    };
+    let mut ixs = vec![instruction];
+    if let Some(nonce) = title_nonce {
+        ixs.push(instructions::register_market_title(
+            &new_market.creator,
+            &new_market.market(),
+            &title,
+            nonce,
+        ));
+    }
+    let signature = client.send(&ixs)?;
+    let market: Market = client.rpc.account(&new_market.market())?;
+
+    println!("Created market {}", new_market.market());
+    println!("  title     {title}");
+    println!("  id        {market_id}");
+    println!("  fee       {} bps", market.fee_bps);
+    println!("  betting   closes {}", format_time(market.end_ts));
+    println!("  resolve   by {}", format_time(market.resolve_deadline_ts));
+    if let Some((side, amount, decimals)) = first_bet {
+        println!(
+            "  bet       {} on {side:?}",
+            format_amount(amount, decimals)
+        );
+    }
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn bet(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    let side = parse_side(&args.positional("side")?)?;
+    let decimals = client.rpc.mint_decimals(&market.mint)?;
+    let mut amount = parse_amount(&args.positional("amount")?, decimals)?;
+    let min_odds_bps = args.parse_option("min-odds-bps")?.unwrap_or(0);
+    let force_rebate = args.switch("stake-rebate");
+    let up_to_cap = args.switch("up-to-cap");
+    args.finish()?;
+
+    if let Some(room) = market.bet_room(side)?.filter(|room| amount > *room) {
+        if !up_to_cap || room == 0 {
+            bail!(
+                "at most {} more can be bet on {side:?} under the market's stake caps",
+                format_amount(room, decimals)
+            );
+        }
+        println!(
+            "Trimming the bet to {} to stay under the stake caps",
+            format_amount(room, decimals)
+        );
+        amount = room;
+    }
+
+    // The discount is locked in with the bet, so stakers always pass it
+    let user = client.pubkey();
+    let discount = staker_discount(&client.rpc, &user)?;
+    if force_rebate && discount.is_none() {
+        bail!("no stake account found for {user}");
+    }
+    let mut instruction =
+        instructions::place_bet_on(&user, &address, &market, side, amount, min_odds_bps);
+    if discount.is_some() {
+        instruction = instructions::with_bettor_stake(instruction, &user);
+    }
+    let instruction = with_wallet_portfolio(&client.rpc, &user, instruction)?;
+    let signature = client.send(&[instruction])?;
+
+    println!(
+        "Bet {} on {side:?} in \"{}\"",
+        format_amount(amount, decimals),
+        market.title
+    );
+    if let Some(discount) = discount.filter(|discount| *discount > 0) {
+        println!("  at a {discount} bps staker fee discount");
+    }
+    let market: Market = client.rpc.account(&address)?;
+    let odds = implied_odds(&market)?;
+    println!(
+        "  odds now A {} / B {}",
+        format_odds(odds.a_bps),
+        format_odds(odds.b_bps)
+    );
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn schedule(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    let side = parse_side(&args.positional("side")?)?;
+    let decimals = client.rpc.mint_decimals(&market.mint)?;
+    let amount = parse_amount(&args.positional("amount")?, decimals)?;
+    let execute_after = parse_time(&args.required_option::<String>("after")?)?;
+    let expiry = parse_time(&args.required_option::<String>("until")?)?;
+    let id = args.parse_option("id")?.unwrap_or(now() as u64);
+    let min_odds_bps = args.parse_option("min-odds-bps")?.unwrap_or(0);
+    args.finish()?;
+
+    let owner = client.pubkey();
+    let signature = client.send(&[instructions::schedule_bet(
+        &owner,
+        &address,
+        &market.mint,
+        id,
+        side,
+        amount,
+        min_odds_bps,
+        execute_after,
+        expiry,
+    )])?;
+    println!(
+        "Scheduled {} on {side:?} in \"{}\" as {}",
+        format_amount(amount, decimals),
+        market.title,
+        scheduled_bet_address(&address, &owner, id).0
+    );
+    println!(
+        "  placed between {} and {}",
+        format_time(execute_after),
+        format_time(expiry)
+    );
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn unschedule(client: &Client, mut args: Args) -> Result<()> {
+    let address = parse_pubkey(&args.positional("scheduled bet")?)?;
+    args.finish()?;
+
+    let scheduled: ScheduledBet = client.rpc.account(&address)?;
+    let market: Market = client.rpc.account(&scheduled.market)?;
+    let signature = client.send(&[instructions::cancel_scheduled_bet(
+        &client.pubkey(),
+        &address,
+        &scheduled,
+        &market.mint,
+    )])?;
+    println!(
+        "Cancelled the scheduled bet on \"{}\"; its stake is back with {}",
+        market.title, scheduled.owner
+    );
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn cashout_order(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    let min_value = args.optional_positional();
+    args.finish()?;
+
+    let decimals = client.rpc.mint_decimals(&market.mint)?;
+    let min_value = match min_value {
+        Some(value) => parse_amount(&value, decimals)?,
+        None => 0,
+    };
+    let user = client.pubkey();
+    let signature = client.send(&[instructions::set_cashout_order(&user, &address, min_value)])?;
+    if min_value == 0 {
+        println!("Cleared the cash-out order on \"{}\"", market.title);
+    } else {
+        let position = client.rpc.account(&position_address(&address, &user).0)?;
+        println!(
+            "Cashing out of \"{}\" once it would win {} (now {}, cashing out pays {})",
+            market.title,
+            format_amount(min_value, decimals),
+            format_amount(market.payout_if_won(&position)?, decimals),
+            format_amount(simulate_cash_out(&market, &position)?, decimals)
+        );
+    }
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn open_portfolio(client: &Client, args: Args) -> Result<()> {
+    args.finish()?;
+
+    let user = client.pubkey();
+    let signature = client.send(&[instructions::open_portfolio(&user)])?;
+    println!("Opened the portfolio of {user}; bets and claims from this wallet are recorded in it");
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn close_portfolio(client: &Client, args: Args) -> Result<()> {
+    args.finish()?;
+
+    let user = client.pubkey();
+    let portfolio: Portfolio = client.rpc.account(&portfolio_address(&user).0)?;
+    // Every page but the current one has to go first
+    let current = portfolio.current_page();
+    let mut closing: Vec<Instruction> = fetch_portfolio_pages(&client.rpc, &user)?
+        .into_iter()
+        .filter(|(_, page)| page.index < current)
+        .map(|(_, page)| instructions::close_portfolio_page(&user, page.index))
+        .collect();
+    closing.push(instructions::close_portfolio(&portfolio));
+    for batch in closing.chunks(PORTFOLIO_CLOSES_PER_TRANSACTION) {
+        let signature = client.send(batch)?;
+        println!("Signature {signature}");
+    }
+    println!(
+        "Closed the portfolio of {user} and {} pages",
+        portfolio.pages
+    );
+    Ok(())
+}
+
+pub fn close(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    args.finish()?;
+
+    let signature = client.send(&[instructions::close_betting(&address)])?;
+    println!("Closed betting on \"{}\"", market.title);
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn resolve(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    let outcome = parse_side(&args.positional("outcome")?)?;
+    args.finish()?;
+
+    let parent = market.dependency.map(|dependency| dependency.market);
+    let signature = client.send(&[instructions::resolve(
+        &client.pubkey(),
+        &address,
+        outcome,
+        parent.as_ref(),
+    )])?;
+    println!("Resolved \"{}\" to {outcome:?}", market.title);
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn cancel(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    args.finish()?;
+
+    let signature = client.send(&[instructions::cancel_expired(&address)])?;
+    println!("Cancelled \"{}\"; stakes are refundable", market.title);
+    println!("Signature {signature}");
+    Ok(())
+}
+
+/// Fee discount the payer's stake earns, if staking is set up.
+pub fn staker_discount(rpc: &RpcClient, user: &Pubkey) -> Result<Option<u16>> {
+    let Some(config) = rpc.optional_account::<ProgramConfig>(&config_address().0)? else {
+        return Ok(None);
+    };
+    let Some(stake) = rpc.optional_account::<StakeAccount>(&stake_address(user).0)? else {
+        return Ok(None);
+    };
+    Ok(Some(config.discount_bps_for(stake.amount)))
+}
+
+/// Records the bet or claim in `user`'s portfolio, if they opened one.
+pub fn with_wallet_portfolio(
+    rpc: &RpcClient,
+    user: &Pubkey,
+    instruction: Instruction,
+) -> Result<Instruction> {
+    Ok(
+        match rpc.optional_account::<Portfolio>(&portfolio_address(user).0)? {
+            Some(portfolio) => instructions::with_portfolio(instruction, &portfolio),
+            None => instruction,
+        },
+    )
+}
+
+pub fn claim(client: &Client, mut args: Args) -> Result<()> {
+    let (address, mut market) = market_arg(client, &mut args)?;
+    args.finish()?;
+
+    // Claiming cancels a market nobody resolved in time
+    market.cancel_if_expired(now());
+
+    let user = client.pubkey();
+    let position = client.rpc.account(&position_address(&address, &user).0)?;
+    let expected = simulate_claim(&market, &position)?;
+
+    let instruction = instructions::claim(&user, &address, &market.mint);
+    let signature = client.send(&[with_wallet_portfolio(&client.rpc, &user, instruction)?])?;
+    let decimals = client.rpc.mint_decimals(&market.mint)?;
+    println!(
+        "Claimed {} from \"{}\"",
+        format_amount(expected.total(), decimals),
+        market.title
+    );
+    if expected.rebate > 0 {
+        println!(
+            "  includes {} staker fee rebate",
+            format_amount(expected.rebate, decimals)
+        );
+    }
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn withdraw_fee(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    args.finish()?;
+
+    let (_, amount) = market.split_fee()?;
+    let signature = client.send(&[instructions::pay_creator_fee(
+        &client.pubkey(),
+        &address,
+        &market,
+    )])?;
+    let decimals = client.rpc.mint_decimals(&market.mint)?;
+    println!(
+        "Withdrew {} in fees from \"{}\"",
+        format_amount(amount, decimals),
+        market.title
+    );
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn fee_splits(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    let mut splits = Vec::new();
+    while let Some(split) = args.optional_positional() {
+        let (recipient, bps) = split
+            .split_once('=')
+            .ok_or_else(|| anyhow!("fee splits are <RECIPIENT>=<BPS>, got {split:?}"))?;
+        let bps = bps
+            .parse()
+            .map_err(|_| anyhow!("invalid share {bps:?} for {recipient}"))?;
+        splits.push(FeeSplit {
+            recipient: parse_pubkey(recipient)?,
+            bps,
+        });
+    }
+    args.finish()?;
+
+    let described = match splits.len() {
+        0 => "no longer split".to_string(),
+        _ => format!(
+            "split {}",
+            splits
+                .iter()
+                .map(|split| format!("{} bps to {}", split.bps, split.recipient))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let signature = client.send(&[instructions::set_fee_splits(
+        &client.pubkey(),
+        &address,
+        splits,
+    )])?;
+    println!("Fee of \"{}\" {described}", market.title);
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn charity(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    let charity = args
+        .optional_positional()
+        .map(|charity| parse_pubkey(&charity))
+        .transpose()?;
+    let charity_bps = match charity {
+        Some(_) => args.parse_positional("bps")?,
+        None => 0,
+    };
+    args.finish()?;
+
+    let signature = client.send(&[instructions::set_charity(
+        &client.pubkey(),
+        &address,
+        charity,
+        charity_bps,
+    )])?;
+    match charity {
+        Some(charity) => println!(
+            "\"{}\" gives {charity_bps} bps of its pool to charity {charity}",
+            market.title
+        ),
+        None => println!("\"{}\" no longer gives to charity", market.title),
+    }
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn large_bet(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    let decimals = client.rpc.mint_decimals(&market.mint)?;
+    let threshold = parse_amount(&args.positional("amount")?, decimals)?;
+    args.finish()?;
+
+    let signature = client.send(&[instructions::set_large_bet_threshold(
+        &client.pubkey(),
+        &address,
+        threshold,
+    )])?;
+    match threshold {
+        0 => println!("\"{}\" no longer flags large bets", market.title),
+        _ => println!(
+            "\"{}\" flags bets of {} or more",
+            market.title,
+            format_amount(threshold, decimals)
+        ),
+    }
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn caps(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    let decimals = client.rpc.mint_decimals(&market.mint)?;
+    let mut cap = |name| -> Result<u64> {
+        Ok(match args.option(name) {
+            Some(cap) => parse_amount(&cap, decimals)?,
+            None => 0,
+        })
+    };
+    let (cap_a, cap_b) = (cap("a")?, cap("b")?);
+    let max_ratio_bps = args.parse_option("max-ratio-bps")?.unwrap_or(0);
+    args.finish()?;
+
+    let signature = client.send(&[instructions::set_stake_caps(
+        &client.pubkey(),
+        &address,
+        cap_a,
+        cap_b,
+        max_ratio_bps,
+    )])?;
+    let show = |cap| match cap {
+        0 => "none".to_string(),
+        cap => format_amount(cap, decimals),
+    };
+    println!(
+        "\"{}\" caps A at {}, B at {}, ratio {}",
+        market.title,
+        show(cap_a),
+        show(cap_b),
+        match max_ratio_bps {
+            0 => "none".to_string(),
+            bps => format_odds(bps.into()),
+        }
+    );
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn activity(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    args.finish()?;
+
+    let signature = client.send(&[instructions::enable_activity_stats(
+        &client.pubkey(),
+        &address,
+    )])?;
+    println!("\"{}\" now keeps activity stats", market.title);
+    println!("Signature {signature}");
+    Ok(())
+}
+
+pub fn compact(client: &Client, mut args: Args) -> Result<()> {
+    let (address, market) = market_arg(client, &mut args)?;
+    args.finish()?;
+
+    let signature = client.send(&[instructions::compact_market(&client.pubkey(), &address)])?;
+    println!(
+        "Compacted \"{}\", freeing {} bytes of rent",
+        market.title,
+        Market::LEN - Market::COMPACT_LEN
+    );
+    println!("Signature {signature}");
+    Ok(())
+}
@@ -0,0 +1,216 @@
+//! Connection settings, taken from flags, then the environment, then the
+//! selected profile, then defaults.
+//!
+//! Profiles live in `~/.config/friend-bets/config.toml` (or
+//! `$FRIEND_BETS_CONFIG`):
+//!
+//! ```toml
+//! default = "devnet"
+//!
+//! [profiles.devnet]
+//! url = "devnet"
+//! keypair = "~/.config/solana/devnet.json"
+//!
+//! [profiles.mainnet]
+//! url = "mainnet-beta"
+//! keypair = "usb://ledger?key=0"
+//! commitment = "finalized"
+//! program_id = "BtNtmmrm3KHc5EmvednmUv43hxL8P3S2fsfPVpffx1Rt"
+//! ```
+//!
+//! `--profile` (or `$FRIEND_BETS_PROFILE`) picks one, falling back to
+//! `default`. A profile's `program_id` must match the program this binary
+//! was built for, so a profile can't silently act on the wrong deployment.
+//!
+//! `url` can list several endpoints, comma-separated and most preferred
+//! first, to fail over between; `URL#N` sends one at most `N` requests a
+//! second, as in `url = "https://rpc.example.com#10,mainnet-beta#4"`.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, bail, Context, Result};
+use friend_bets_sdk::failover::{parse_endpoints, Endpoint};
+use friend_bets_sdk::send::Commitment;
+use friend_bets_sdk::PROGRAM_ID;
+use serde::Deserialize;
+
+use crate::args::Args;
+use crate::client::Client;
+use crate::rpc::RpcClient;
+use crate::signer::Signer;
+
+const DEFAULT_URL: &str = "https://api.devnet.solana.com";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    default: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Profile {
+    url: Option<String>,
+    keypair: Option<String>,
+    commitment: Option<String>,
+    program_id: Option<String>,
+}
+
+pub struct Config {
+    /// Name of the profile in use, if any.
+    pub profile: Option<String>,
+    /// RPC endpoints, most preferred first.
+    pub endpoints: Vec<Endpoint>,
+    /// Keypair file path or `usb://ledger` URI.
+    pub keypair: String,
+    pub commitment: Commitment,
+}
+
+impl Config {
+    pub fn from_args(args: &mut Args) -> Result<Self> {
+        let file = read_config_file()?;
+        let name = args
+            .option("profile")
+            .or_else(|| env::var("FRIEND_BETS_PROFILE").ok())
+            .or(file.default);
+        let profile = match &name {
+            Some(name) => file.profiles.get(name).ok_or_else(|| {
+                let known: Vec<&str> = file.profiles.keys().map(String::as_str).collect();
+                anyhow!("no profile {name:?}; known profiles: {}", known.join(", "))
+            })?,
+            None => &Profile::default(),
+        };
+        if let Some(program_id) = &profile.program_id {
+            let program_id: Pubkey = program_id
+                .parse()
+                .map_err(|_| anyhow!("invalid program_id {program_id:?}"))?;
+            if program_id != PROGRAM_ID {
+                bail!(
+                    "profile {:?} targets program {program_id}, but this build of friend-bets \
+                     targets {PROGRAM_ID}",
+                    name.unwrap_or_default()
+                );
+            }
+        }
+
+        let url = args
+            .option("url")
+            .or_else(|| env::var("FRIEND_BETS_URL").ok())
+            .or_else(|| profile.url.clone())
+            .unwrap_or_else(|| DEFAULT_URL.to_string());
+        let keypair = args
+            .option("keypair")
+            .or_else(|| env::var("FRIEND_BETS_KEYPAIR").ok())
+            .or_else(|| profile.keypair.as_deref().map(expand_home))
+            .unwrap_or_else(|| expand_home("~/.config/solana/id.json"));
+        let commitment = match args
+            .option("commitment")
+            .or_else(|| env::var("FRIEND_BETS_COMMITMENT").ok())
+            .or_else(|| profile.commitment.clone())
+            .as_deref()
+        {
+            None | Some("confirmed") => Commitment::Confirmed,
+            Some("processed") => Commitment::Processed,
+            Some("finalized") => Commitment::Finalized,
+            Some(other) => bail!("unknown commitment {other:?}"),
+        };
+
+        Ok(Self {
+            profile: name,
+            endpoints: parse_endpoints(&url)?
+                .into_iter()
+                .map(|endpoint| Endpoint {
+                    url: expand_cluster(endpoint.url),
+                    ..endpoint
+                })
+                .collect(),
+            keypair,
+            commitment,
+        })
+    }
+
+    pub fn rpc(&self) -> RpcClient {
+        RpcClient::new(
+            self.endpoints.clone(),
+            commitment_name(self.commitment).into(),
+        )
+    }
+
+    pub fn signer(&self) -> Result<Signer> {
+        Signer::open(&self.keypair)
+    }
+
+    pub fn client(&self) -> Result<Client> {
+        Ok(Client {
+            rpc: self.rpc(),
+            payer: self.signer()?,
+            commitment: self.commitment,
+        })
+    }
+
+    /// Prints the settings commands will run with.
+    pub fn show(&self) {
+        println!("config    {}", config_path().display());
+        println!("profile   {}", self.profile.as_deref().unwrap_or("(none)"));
+        for endpoint in &self.endpoints {
+            match endpoint.rate_limit {
+                Some(rate) => println!("url       {} (at most {rate}/s)", endpoint.url),
+                None => println!("url       {}", endpoint.url),
+            }
+        }
+        println!("keypair   {}", self.keypair);
+        println!("commit    {}", commitment_name(self.commitment));
+        println!("program   {PROGRAM_ID}");
+    }
+}
+
+pub fn commitment_name(commitment: Commitment) -> &'static str {
+    match commitment {
+        Commitment::Processed => "processed",
+        Commitment::Confirmed => "confirmed",
+        Commitment::Finalized => "finalized",
+    }
+}
+
+fn config_path() -> PathBuf {
+    env::var("FRIEND_BETS_CONFIG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(expand_home("~/.config/friend-bets/config.toml")))
+}
+
+/// Reads the config file; a missing file means no profiles.
+fn read_config_file() -> Result<ConfigFile> {
+    let path = config_path();
+    match fs::read_to_string(&path) {
+        Ok(text) => toml::from_str(&text).with_context(|| format!("parsing {}", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(ConfigFile::default()),
+        Err(err) => Err(err).with_context(|| format!("reading {}", path.display())),
+    }
+}
+
+/// Accepts the cluster monikers `solana` understands.
+fn expand_cluster(url: String) -> String {
+    match url.as_str() {
+        "d" | "devnet" => "https://api.devnet.solana.com".into(),
+        "m" | "mainnet-beta" => "https://api.mainnet-beta.solana.com".into(),
+        "t" | "testnet" => "https://api.testnet.solana.com".into(),
+        "l" | "localhost" => "http://127.0.0.1:8899".into(),
+        _ => url,
+    }
+}
+
+fn expand_home(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => {
+            let home = env::var("HOME").unwrap_or_else(|_| ".".into());
+            PathBuf::from(home).join(rest).display().to_string()
+        }
+        None => path.to_string(),
+    }
+}
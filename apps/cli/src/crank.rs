@@ -0,0 +1,383 @@
+//! `crank`: permissionless market upkeep, meant to run from cron.
+//!
+//! Closes betting on markets past `end_ts` and cancels markets nobody
+//! resolved by their deadline. With `--distribute` it pushes payouts to
+//! unclaimed winners of finalized markets, and with `--gc` it collects
+//! markets whose claim window has passed, earning the caller half the rent.
+//! A market's bettor pages, activity account and title registration are
+//! closed first, refunding whoever paid for them, and scheduled bets left
+//! on it are returned to their owners; the market itself is collected on a
+//! later run.
+//!
+//! `--metrics-file` writes the run's results in Prometheus text format,
+//! for node_exporter's textfile collector to pick up. The file is only
+//! replaced by runs that got as far as acting, so a stale
+//! `friend_bets_crank_last_run_timestamp_seconds` means the crank is failing.
+
+use std::fmt::Write as _;
+use std::fs;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_spl::token::TokenAccount;
+use anyhow::{Context, Result};
+use friend_bets_sdk::fetch::{
+    fetch_bettor_pages, fetch_market_title, fetch_markets, fetch_positions,
+    fetch_scheduled_bets_by_market,
+};
+use friend_bets_sdk::friends_bets::CLAIM_WINDOW_SECS;
+use friend_bets_sdk::instructions;
+use friend_bets_sdk::pda::{
+    activity_address, is_position_of, token_account_address, vault_address,
+};
+use friend_bets_sdk::{BetSide, Market, MarketActivity, MarketStatus, Position};
+
+use crate::args::Args;
+use crate::client::Client;
+use crate::units::now;
+
+/// Positions paid per `distribute`, keeping the transaction under the
+/// legacy account limit.
+const DISTRIBUTE_BATCH: usize = 10;
+/// Positions a single `gc_market` can close.
+const GC_MAX_POSITIONS: usize = 24;
+
+/// Each kind of action, as named in metrics.
+const ACTION_KINDS: [&str; 8] = [
+    "close_betting",
+    "cancel_expired",
+    "distribute",
+    "close_bettor_page",
+    "close_activity_stats",
+    "close_market_title",
+    "cancel_scheduled_bet",
+    "gc_market",
+];
+
+struct Action {
+    kind: &'static str,
+    description: String,
+    instruction: Instruction,
+}
+
+pub fn crank(client: &Client, mut args: Args) -> Result<()> {
+    let dry_run = args.switch("dry-run");
+    let distribute = args.switch("distribute");
+    let gc = args.switch("gc");
+    let metrics_file = args.option("metrics-file");
+    args.finish()?;
+
+    let now = now();
+    let markets = fetch_markets(&client.rpc)?;
+    let positions = if distribute || gc {
+        fetch_positions(&client.rpc)?
+    } else {
+        Vec::new()
+    };
+
+    let mut actions = Vec::new();
+    for (address, market) in &markets {
+        let label = format!("{address} \"{}\"", market.title);
+        match market.status {
+            MarketStatus::Open | MarketStatus::PendingResolve => {
+                if market.status == MarketStatus::Open && now >= market.end_ts {
+                    actions.push(Action {
+                        kind: "close_betting",
+                        description: format!("close betting on {label}"),
+                        instruction: instructions::close_betting(address),
+                    });
+                }
+                if now >= market.resolve_deadline_ts {
+                    actions.push(Action {
+                        kind: "cancel_expired",
+                        description: format!("cancel unresolved {label}"),
+                        instruction: instructions::cancel_expired(address),
+                    });
+                }
+            }
+            MarketStatus::Resolved | MarketStatus::Cancelled => {
+                let market_positions: Vec<&(Pubkey, Position)> = positions
+                    .iter()
+                    .filter(|(position_address, position)| {
+                        is_position_of(position_address, position, address)
+                    })
+                    .collect();
+                if distribute {
+                    distribute_actions(client, address, market, &market_positions, &mut actions)?;
+                }
+                if gc {
+                    gc_action(
+                        client,
+                        address,
+                        market,
+                        &market_positions,
+                        now,
+                        &mut actions,
+                    )?;
+                }
+            }
+        }
+    }
+
+    // Which actions failed, in the order taken
+    let mut failures = Vec::new();
+    if actions.is_empty() {
+        println!("Nothing to do across {} markets", markets.len());
+    }
+    for action in &actions {
+        if dry_run {
+            println!("would {}", action.description);
+            continue;
+        }
+        let result = client.send(std::slice::from_ref(&action.instruction));
+        match &result {
+            Ok(signature) => println!("{} ({signature})", action.description),
+            Err(err) => eprintln!("failed to {}: {err:#}", action.description),
+        }
+        failures.push(result.is_err());
+    }
+    if !dry_run && !actions.is_empty() {
+        let failed = failures.iter().filter(|failed| **failed).count();
+        println!("{} sent, {failed} failed", actions.len() - failed);
+    }
+    if let Some(path) = metrics_file {
+        write_metrics(&path, now, markets.len(), &actions, &failures, dry_run)?;
+    }
+    Ok(())
+}
+
+/// Writes the run's results to `path` in Prometheus text format, through a
+/// temporary file so the collector never reads half of one.
+fn write_metrics(
+    path: &str,
+    now: i64,
+    markets: usize,
+    actions: &[Action],
+    failures: &[bool],
+    dry_run: bool,
+) -> Result<()> {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# HELP friend_bets_crank_last_run_timestamp_seconds When the crank last ran.\n\
+         # TYPE friend_bets_crank_last_run_timestamp_seconds gauge\n\
+         friend_bets_crank_last_run_timestamp_seconds {now}\n\
+         # HELP friend_bets_crank_markets Markets the last run looked at.\n\
+         # TYPE friend_bets_crank_markets gauge\n\
+         friend_bets_crank_markets {markets}\n\
+         # HELP friend_bets_crank_actions Actions in the last run, by outcome.\n\
+         # TYPE friend_bets_crank_actions gauge"
+    );
+    for kind in ACTION_KINDS {
+        let of_kind = || actions.iter().enumerate().filter(|(_, a)| a.kind == kind);
+        let results: &[(&str, usize)] = if dry_run {
+            &[("planned", of_kind().count())]
+        } else {
+            let failed = of_kind().filter(|(i, _)| failures[*i]).count();
+            &[
+                ("succeeded", of_kind().count() - failed),
+                ("failed", failed),
+            ]
+        };
+        for (result, count) in results {
+            let _ = writeln!(
+                out,
+                "friend_bets_crank_actions{{action=\"{kind}\",result=\"{result}\"}} {count}"
+            );
+        }
+    }
+    let temporary = format!("{path}.tmp");
+    fs::write(&temporary, out).with_context(|| format!("writing {temporary}"))?;
+    fs::rename(&temporary, path).with_context(|| format!("writing {path}"))
+}
+
+/// Pays unclaimed positions with something owed whose owners already have a
+/// token account; the rest are left to claim themselves.
+fn distribute_actions(
+    client: &Client,
+    address: &Pubkey,
+    market: &Market,
+    positions: &[&(Pubkey, Position)],
+    actions: &mut Vec<Action>,
+) -> Result<()> {
+    let mut owed = Vec::new();
+    for (_, position) in positions {
+        if !position.claimed && market.claim_total(position)? > 0 {
+            owed.push(position.owner);
+        }
+    }
+    let token_accounts: Vec<Pubkey> = owed
+        .iter()
+        .map(|owner| token_account_address(owner, &market.mint))
+        .collect();
+    let existing = client.rpc.get_multiple_accounts(&token_accounts)?;
+    let owners: Vec<Pubkey> = owed
+        .into_iter()
+        .zip(existing)
+        .filter_map(|(owner, account)| account.map(|_| owner))
+        .collect();
+
+    for batch in owners.chunks(DISTRIBUTE_BATCH) {
+        actions.push(Action {
+            kind: "distribute",
+            description: format!(
+                "pay {} positions of {address} \"{}\"",
+                batch.len(),
+                market.title
+            ),
+            instruction: instructions::distribute(address, &market.mint, batch),
+        });
+    }
+    Ok(())
+}
+
+/// Collects the market once its claim window is over and nothing in it is
+/// owed to anyone.
+fn gc_action(
+    client: &Client,
+    address: &Pubkey,
+    market: &Market,
+    positions: &[&(Pubkey, Position)],
+    now: i64,
+    actions: &mut Vec<Action>,
+) -> Result<()> {
+    if now < market.resolve_deadline_ts.saturating_add(CLAIM_WINDOW_SECS) {
+        return Ok(());
+    }
+    if market.bettor_pages > 0 {
+        for (_, page) in fetch_bettor_pages(&client.rpc, address)? {
+            actions.push(Action {
+                kind: "close_bettor_page",
+                description: format!(
+                    "close bettor page {} of {address} \"{}\"",
+                    page.index, market.title
+                ),
+                instruction: instructions::close_bettor_page(address, page.index, &page.payer),
+            });
+        }
+        return Ok(());
+    }
+    if market.track_activity {
+        if let Some(activity) = client
+            .rpc
+            .optional_account::<MarketActivity>(&activity_address(address).0)?
+        {
+            actions.push(Action {
+                kind: "close_activity_stats",
+                description: format!("close activity stats of {address} \"{}\"", market.title),
+                instruction: instructions::close_activity_stats(address, &activity.payer),
+            });
+        }
+        return Ok(());
+    }
+    if market.title_registered {
+        if let Some((registration, _)) = fetch_market_title(&client.rpc, address)? {
+            actions.push(Action {
+                kind: "close_market_title",
+                description: format!("release the title of {address} \"{}\"", market.title),
+                instruction: instructions::close_market_title(
+                    address,
+                    &registration,
+                    &market.creator,
+                ),
+            });
+        }
+        return Ok(());
+    }
+    if market.scheduled_bets > 0 {
+        let scheduled_bets = fetch_scheduled_bets_by_market(&client.rpc, address)?;
+        for (scheduled_address, scheduled) in scheduled_bets {
+            actions.push(Action {
+                kind: "cancel_scheduled_bet",
+                description: format!(
+                    "return the scheduled bet of {} on {address} \"{}\"",
+                    scheduled.owner, market.title
+                ),
+                instruction: instructions::cancel_scheduled_bet(
+                    &client.pubkey(),
+                    &scheduled_address,
+                    &scheduled,
+                    &market.mint,
+                ),
+            });
+        }
+        return Ok(());
+    }
+    if market.status == MarketStatus::Resolved
+        && !market.insurance_collected
+        && market.insurance_amount()? > 0
+    {
+        actions.push(Action {
+            kind: "collect_insurance",
+            description: format!("collect insurance of {address} \"{}\"", market.title),
+            instruction: instructions::collect_insurance(&client.pubkey(), address, &market.mint),
+        });
+        return Ok(());
+    }
+    if market.status == MarketStatus::Resolved
+        && !market.charity_paid
+        && market.charity_amount()? > 0
+    {
+        if let Some(charity) = market.charity {
+            actions.push(Action {
+                kind: "donate_to_charity",
+                description: format!("donate the charity cut of {address} \"{}\"", market.title),
+                instruction: instructions::donate_to_charity(address, &charity),
+            });
+        }
+        return Ok(());
+    }
+    if market.status != MarketStatus::Cancelled
+        && !market.creator_fee_withdrawn
+        && market.fee_amount()? > 0
+    {
+        return Ok(());
+    }
+    let mut unclaimed = Vec::new();
+    for (_, position) in positions {
+        if !position.claimed {
+            if market.claim_total(position)? > 0 {
+                return Ok(());
+            }
+            unclaimed.push(position.owner);
+        }
+    }
+    if unclaimed.len() > GC_MAX_POSITIONS {
+        return Ok(());
+    }
+    let mut dust = 0;
+    for side in [BetSide::A, BetSide::B] {
+        let Some(vault) = client
+            .rpc
+            .optional_account::<TokenAccount>(&vault_address(address, side).0)?
+        else {
+            return Ok(());
+        };
+        dust += vault.amount;
+    }
+    if dust > market.unowed_balance()? {
+        return Ok(());
+    }
+
+    // Unclaimed positions must be closed; claimed ones ride along while
+    // there's room, returning their rent too.
+    let claimed = positions
+        .iter()
+        .filter(|(_, position)| position.claimed)
+        .map(|(_, position)| position.owner);
+    let mut owners = unclaimed;
+    owners.extend(claimed.take(GC_MAX_POSITIONS - owners.len()));
+
+    actions.push(Action {
+        kind: "gc_market",
+        description: format!("collect {address} \"{}\"", market.title),
+        instruction: instructions::gc_market(
+            &client.pubkey(),
+            address,
+            &market.creator,
+            &market.mint,
+            &owners,
+        ),
+    });
+    Ok(())
+}
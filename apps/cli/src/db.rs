@@ -0,0 +1,53 @@
+//! Read access to the indexer's database through the system `psql`, for
+//! the commands that summarize indexed history rather than reading the
+//! chain. Inputs go in as psql variables, which psql quotes, rather than
+//! into the SQL text.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+pub struct Database {
+    url: String,
+}
+
+impl Database {
+    pub fn new(url: String) -> Self {
+        Self { url }
+    }
+
+    /// Runs a query selecting a single JSON value; null when there's no row.
+    /// Each of `variables` is set for the query to read as `:'name'`.
+    pub fn json(&self, sql: &str, variables: &[(&str, String)]) -> Result<Value> {
+        let mut child = Command::new("psql")
+            .args(["--no-psqlrc", "--quiet", "--set=ON_ERROR_STOP=1"])
+            .args(["--tuples-only", "--no-align"])
+            .args(["--dbname", &self.url, "--file=-"])
+            .args(
+                variables
+                    .iter()
+                    .map(|(name, value)| format!("--set={name}={value}")),
+            )
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("running psql")?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(sql.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!("psql: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        let text = String::from_utf8_lossy(&output.stdout);
+        if text.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(text.trim()).context("psql returned invalid JSON")
+    }
+}
@@ -0,0 +1,216 @@
+//! `dev bootstrap`: a one-command sandbox on devnet or a local validator.
+//!
+//! Airdrops SOL to the payer, creates a test mint, funds a few generated
+//! wallets with SOL and tokens, creates the program config if there is none,
+//! creates a sample market and has each wallet bet on it. Wallet keypairs
+//! are saved so they can be used with `-k`.
+
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use anchor_spl::token::spl_token;
+use anchor_spl::token::Mint;
+use anyhow::{bail, Result};
+use friend_bets_sdk::instructions::{self, NewMarket};
+use friend_bets_sdk::keypair::Keypair;
+use friend_bets_sdk::pda::{config_address, token_account_address};
+use friend_bets_sdk::{BetSide, MarketOptions, MarketPolicy};
+use solana_signature::Signature;
+use solana_system_interface::instruction::{create_account, transfer};
+
+use crate::args::Args;
+use crate::client::Client;
+use crate::units::{format_amount, format_time, now};
+
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+const DECIMALS: u8 = 6;
+/// Tokens minted to each wallet.
+const WALLET_TOKENS: u64 = 1_000 * 10u64.pow(DECIMALS as u32);
+/// SOL sent to each wallet for fees and position rent.
+const WALLET_LAMPORTS: u64 = LAMPORTS_PER_SOL / 20;
+const AIRDROP_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn dev(client: &Client, mut args: Args) -> Result<()> {
+    let action = args.positional("action")?;
+    match action.as_str() {
+        "bootstrap" => bootstrap(client, args),
+        other => bail!("unknown dev action {other:?}; expected bootstrap"),
+    }
+}
+
+fn bootstrap(client: &Client, mut args: Args) -> Result<()> {
+    let wallet_count: usize = args.parse_option("wallets")?.unwrap_or(3);
+    let airdrop_sol: u64 = args.parse_option("airdrop")?.unwrap_or(2);
+    let out = PathBuf::from(args.option("out").unwrap_or_else(|| "dev-wallets".into()));
+    args.finish()?;
+
+    if client.rpc.urls().iter().any(|url| url.contains("mainnet")) {
+        bail!(
+            "dev bootstrap spends real funds on mainnet; point it at devnet or a local validator"
+        );
+    }
+    let paths: Vec<PathBuf> = (1..=wallet_count)
+        .map(|index| out.join(format!("wallet-{index}.json")))
+        .collect();
+    if let Some(existing) = paths.iter().find(|path| path.exists()) {
+        bail!(
+            "{} already exists; pass a different --out",
+            existing.display()
+        );
+    }
+    fs::create_dir_all(&out)?;
+
+    let payer = client.pubkey();
+    let needed = WALLET_LAMPORTS * wallet_count as u64 + LAMPORTS_PER_SOL / 10;
+    if client.rpc.get_balance(&payer)? < needed {
+        let signature = client
+            .rpc
+            .request_airdrop(&payer, airdrop_sol * LAMPORTS_PER_SOL)?;
+        wait_for(client, &signature)?;
+        println!("Airdropped {airdrop_sol} SOL to {payer}");
+    }
+
+    let mint = Keypair::generate();
+    let rent = client
+        .rpc
+        .get_minimum_balance_for_rent_exemption(Mint::LEN)?;
+    client.send_signed(
+        &[
+            create_account(
+                &payer,
+                &mint.pubkey(),
+                rent,
+                Mint::LEN as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_mint2(
+                &spl_token::ID,
+                &mint.pubkey(),
+                &payer,
+                None,
+                DECIMALS,
+            )?,
+        ],
+        &[&mint],
+    )?;
+    let mint = mint.pubkey();
+    println!("Created mint {mint} ({DECIMALS} decimals)");
+
+    let mut wallets = Vec::new();
+    for path in &paths {
+        let wallet = Keypair::generate();
+        wallet.write(path)?;
+        let owner = wallet.pubkey();
+        client.send(&[
+            transfer(&payer, &owner, WALLET_LAMPORTS),
+            create_associated_token_account_idempotent(&payer, &owner, &mint, &spl_token::ID),
+            spl_token::instruction::mint_to(
+                &spl_token::ID,
+                &mint,
+                &token_account_address(&owner, &mint),
+                &payer,
+                &[],
+                WALLET_TOKENS,
+            )?,
+        ])?;
+        println!(
+            "Funded {owner} with {} tokens ({})",
+            format_amount(WALLET_TOKENS, DECIMALS),
+            path.display()
+        );
+        wallets.push(wallet);
+    }
+
+    // A fresh local validator has no config yet; markets need one. Only the
+    // upgrade authority can create it, which `anchor deploy` made the payer
+    if client.rpc.get_account_data(&config_address().0)?.is_none() {
+        client.send(&[instructions::initialize_config(
+            &payer,
+            &mint,
+            0,
+            Vec::new(),
+            MarketPolicy::default(),
+            0,
+        )])?;
+        println!("Created the program config with the default market policy, {payer} as admin");
+    }
+
+    let new_market = NewMarket {
+        creator: payer,
+        mint,
+        market_id: now() as u64,
+    };
+    let end_ts = now() + 60 * 60;
+    client.send(&[instructions::initialize_market(
+        &new_market,
+        100,
+        end_ts,
+        end_ts + 24 * 60 * 60,
+        "Sandbox: will it rain tomorrow?".into(),
+        MarketOptions {
+            allow_cash_out: true,
+            cashout_haircut_bps: 500,
+            lock_odds: false,
+            burn_fee: false,
+        },
+    )])?;
+    let market = new_market.market();
+    println!(
+        "Created market {market}, betting closes {}",
+        format_time(end_ts)
+    );
+
+    for (index, wallet) in wallets.iter().enumerate() {
+        let side = if index % 2 == 0 {
+            BetSide::A
+        } else {
+            BetSide::B
+        };
+        let amount = (index as u64 + 1) * 10 * 10u64.pow(DECIMALS as u32);
+        client.send_signed(
+            &[instructions::place_bet(
+                &wallet.pubkey(),
+                &market,
+                &mint,
+                side,
+                amount,
+                0,
+            )],
+            &[wallet],
+        )?;
+        println!(
+            "{} bet {} on {side:?}",
+            wallet.pubkey(),
+            format_amount(amount, DECIMALS)
+        );
+    }
+
+    println!();
+    println!("Sandbox ready:");
+    println!("  mint     {mint}");
+    println!("  market   {market}");
+    println!("  wallets  {}", out.display());
+    println!(
+        "Try: friend-bets -k {} bet {market} a 5",
+        paths
+            .first()
+            .map_or_else(|| "<wallet>".into(), |path| path.display().to_string())
+    );
+    Ok(())
+}
+
+/// Waits until `signature` is confirmed.
+fn wait_for(client: &Client, signature: &Signature) -> Result<()> {
+    let started = Instant::now();
+    while started.elapsed() < AIRDROP_TIMEOUT {
+        match client.rpc.get_signature_status(signature)? {
+            Some((_, Some(err))) => bail!("airdrop {signature} failed: {err}"),
+            Some((confirmation, None)) if confirmation != "processed" => return Ok(()),
+            _ => thread::sleep(Duration::from_secs(1)),
+        }
+    }
+    bail!("airdrop {signature} wasn't confirmed within {AIRDROP_TIMEOUT:?}")
+}
@@ -0,0 +1,176 @@
+//! `export`: a market's full bet and claim ledger, rebuilt from its
+//! transaction history, as CSV or JSON.
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{bail, Result};
+use friend_bets_sdk::events::{market_events, FriendBetsEvent};
+use friend_bets_sdk::{BetSide, Market};
+use serde_json::{json, Value};
+
+use crate::args::Args;
+use crate::commands::parse_pubkey;
+use crate::rpc::RpcClient;
+use crate::units::{format_amount, format_utc};
+
+const COLUMNS: [&str; 8] = [
+    "time",
+    "slot",
+    "signature",
+    "event",
+    "user",
+    "side",
+    "amount",
+    "payout",
+];
+
+/// One ledger line. Amounts are whole tokens, or raw units when the market
+/// account (and so its mint) no longer exists.
+struct Entry {
+    time: Option<i64>,
+    slot: u64,
+    signature: String,
+    event: &'static str,
+    user: Option<Pubkey>,
+    side: Option<BetSide>,
+    amount: Option<u64>,
+    payout: Option<u64>,
+}
+
+/// User, side, amount and payout.
+type Columns = (Option<Pubkey>, Option<BetSide>, Option<u64>, Option<u64>);
+
+impl Entry {
+    fn fields(&self, decimals: Option<u8>) -> [String; 8] {
+        let amount = |raw: Option<u64>| {
+            raw.map(|raw| decimals.map_or_else(|| raw.to_string(), |d| format_amount(raw, d)))
+                .unwrap_or_default()
+        };
+        [
+            self.time.map(format_utc).unwrap_or_default(),
+            self.slot.to_string(),
+            self.signature.clone(),
+            self.event.to_string(),
+            self.user.map(|user| user.to_string()).unwrap_or_default(),
+            self.side
+                .map(|side| format!("{side:?}"))
+                .unwrap_or_default(),
+            amount(self.amount),
+            amount(self.payout),
+        ]
+    }
+}
+
+pub fn export(rpc: &RpcClient, mut args: Args) -> Result<()> {
+    let market: String = args.required_option("market")?;
+    let market = parse_pubkey(&market)?;
+    let format = args.option("format").unwrap_or_else(|| "csv".into());
+    if format != "csv" && format != "json" {
+        bail!("unknown format {format:?}; use csv or json");
+    }
+    args.finish()?;
+
+    let decimals = match rpc.optional_account::<Market>(&market)? {
+        Some(state) => Some(rpc.mint_decimals(&state.mint)?),
+        None => {
+            eprintln!("warning: {market} no longer exists; amounts are in raw units");
+            None
+        }
+    };
+
+    let history = rpc.get_signature_history(&market)?;
+    eprintln!("Reading {} transactions", history.len());
+    let mut entries = Vec::new();
+    for info in history.iter().filter(|info| !info.failed) {
+        let Some(logs) = rpc.get_transaction_logs(&info.signature)? else {
+            continue;
+        };
+        for event in market_events(&logs, &market)? {
+            let Some((user, side, amount, payout)) = columns(&event) else {
+                continue;
+            };
+            entries.push(Entry {
+                time: info.block_time,
+                slot: info.slot,
+                signature: info.signature.clone(),
+                event: event.name(),
+                user,
+                side,
+                amount,
+                payout,
+            });
+        }
+    }
+
+    if format == "json" {
+        let rows: Vec<Value> = entries
+            .iter()
+            .map(|entry| {
+                let fields = entry.fields(decimals);
+                let object = COLUMNS
+                    .iter()
+                    .zip(fields)
+                    .filter(|(_, value)| !value.is_empty())
+                    .map(|(column, value)| (column.to_string(), Value::String(value)))
+                    .collect();
+                Value::Object(object)
+            })
+            .collect();
+        let document = json!({
+            "market": market.to_string(),
+            "decimals": decimals,
+            "entries": rows,
+        });
+        println!("{}", serde_json::to_string_pretty(&document)?);
+    } else {
+        println!("{}", COLUMNS.join(","));
+        for entry in &entries {
+            let fields = entry.fields(decimals);
+            let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            println!("{}", line.join(","));
+        }
+    }
+    Ok(())
+}
+
+/// The columns of a ledger event. Bet receipts repeat their bet, so they're
+/// left out along with anything else that isn't part of the market's ledger.
+fn columns(event: &FriendBetsEvent) -> Option<Columns> {
+    Some(match event {
+        FriendBetsEvent::MarketInitialized(e) => (Some(e.creator), None, None, None),
+        FriendBetsEvent::MarketCloned(_)
+        | FriendBetsEvent::MarketForked(_)
+        | FriendBetsEvent::BettingClosed(_)
+        | FriendBetsEvent::Cancelled(_) => (None, None, None, None),
+        FriendBetsEvent::DependencySet(e) => (None, Some(e.outcome), None, None),
+        FriendBetsEvent::BetPlaced(e) => (Some(e.user), Some(e.side), Some(e.amount), None),
+        FriendBetsEvent::CompressedBetPlaced(e) => {
+            (Some(e.user), Some(e.side), Some(e.amount), None)
+        }
+        FriendBetsEvent::CashedOut(e) => {
+            (Some(e.user), Some(e.side), Some(e.stake), Some(e.payout))
+        }
+        FriendBetsEvent::Resolved(e) => (None, Some(e.outcome), None, None),
+        FriendBetsEvent::Claimed(e) => (Some(e.user), None, None, Some(e.amount)),
+        FriendBetsEvent::CreatorFeeWithdrawn(e) => (Some(e.creator), None, None, Some(e.amount)),
+        FriendBetsEvent::FeeSplitPaid(e) => (Some(e.recipient), None, None, Some(e.amount)),
+        FriendBetsEvent::FeeBurned(e) => (None, None, Some(e.amount), None),
+        FriendBetsEvent::CharityDonated(e) => (Some(e.charity), None, None, Some(e.amount)),
+        FriendBetsEvent::FeeRebated(e) => (Some(e.user), None, None, Some(e.amount)),
+        FriendBetsEvent::MarketCollected(e) => (
+            Some(e.caller),
+            None,
+            None,
+            Some(e.caller_share.saturating_add(e.creator_share)),
+        ),
+        _ => return None,
+    })
+}
+
+/// Quotes a field when it holds a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
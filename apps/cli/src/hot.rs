@@ -0,0 +1,72 @@
+//! `hot`: open markets ranked by their recent bets, read from the activity
+//! accounts of markets that keep one rather than from an indexer.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use anchor_lang::AccountDeserialize;
+use anyhow::{bail, Result};
+use friend_bets_sdk::fetch::fetch_market_activity;
+use friend_bets_sdk::friends_bets::activity::ACTIVITY_BUCKETS;
+use friend_bets_sdk::{Market, MarketStatus};
+
+use crate::args::Args;
+use crate::portfolio::truncate;
+use crate::rpc::RpcClient;
+use crate::units::{format_amount, now};
+
+pub fn hot(rpc: &RpcClient, mut args: Args) -> Result<()> {
+    let hours: usize = args.parse_option("hours")?.unwrap_or(ACTIVITY_BUCKETS);
+    let limit: usize = args.parse_option("limit")?.unwrap_or(10);
+    if hours == 0 || hours > ACTIVITY_BUCKETS {
+        bail!("--hours must be between 1 and {ACTIVITY_BUCKETS}");
+    }
+    args.finish()?;
+
+    let now = now();
+    let activity = fetch_market_activity(rpc)?;
+    let addresses: Vec<_> = activity
+        .iter()
+        .map(|(_, activity)| activity.market)
+        .collect();
+    let markets = rpc.get_multiple_accounts(&addresses)?;
+
+    let mut rows = Vec::new();
+    for ((_, activity), data) in activity.iter().zip(markets) {
+        let Some(data) = data else { continue };
+        let market = Market::try_deserialize(&mut data.as_slice())?;
+        let (bets, volume) = activity.recent(now, hours);
+        if market.status == MarketStatus::Open && now < market.end_ts && bets > 0 {
+            rows.push((market, activity, bets, volume));
+        }
+    }
+    rows.sort_by_key(|(_, _, bets, volume)| std::cmp::Reverse((*bets, *volume)));
+    rows.truncate(limit);
+    if rows.is_empty() {
+        println!("No open market with activity stats had bets in the last {hours}h");
+        return Ok(());
+    }
+
+    let mut decimals = HashMap::new();
+    for (market, ..) in &rows {
+        if let Entry::Vacant(entry) = decimals.entry(market.mint) {
+            entry.insert(rpc.mint_decimals(&market.mint)?);
+        }
+    }
+
+    println!(
+        "{:<24} {:>6} {:>12} {:>8}  MARKET",
+        "TITLE", "BETS", "VOLUME", "BETTORS"
+    );
+    for (market, activity, bets, volume) in &rows {
+        println!(
+            "{:<24} {:>6} {:>12} {:>8}  {}",
+            truncate(&market.title, 24),
+            bets,
+            format_amount(*volume, decimals[&market.mint]),
+            activity.bettors,
+            activity.market
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,256 @@
+//! Signing with the Solana app on a Ledger hardware wallet.
+//!
+//! Talks to the device through Linux `hidraw` nodes using Ledger's HID
+//! framing, so no USB library is needed. APDUs follow the Solana app: a BIP32
+//! path under `44'/501'`, and messages longer than one APDU sent in chunks.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, bail, Context, Result};
+use solana_signature::Signature;
+
+const LEDGER_VENDOR_ID: &str = "00002C97";
+/// Usage page of the interface that carries APDUs, as it opens the HID
+/// report descriptor.
+const APDU_USAGE_PAGE: [u8; 3] = [0x06, 0xa0, 0xff];
+
+const HID_PACKET_SIZE: usize = 64;
+const APDU_CHANNEL: [u8; 2] = [0x01, 0x01];
+const APDU_TAG: u8 = 0x05;
+
+const CLA: u8 = 0xe0;
+const INS_GET_PUBKEY: u8 = 0x05;
+const INS_SIGN_MESSAGE: u8 = 0x06;
+const P1_NON_CONFIRM: u8 = 0x00;
+const P1_CONFIRM: u8 = 0x01;
+const P2_EXTEND: u8 = 0x01;
+const P2_MORE: u8 = 0x02;
+const MAX_CHUNK_SIZE: usize = 255;
+
+const SOLANA_COIN_TYPE: u32 = 501;
+const HARDENED: u32 = 0x8000_0000;
+
+pub struct Ledger {
+    device: File,
+    path: Vec<u32>,
+    pubkey: Pubkey,
+}
+
+impl Ledger {
+    /// Opens the Ledger named by a `usb://ledger[/<wallet>][?key=<account>[/<change>]]`
+    /// URI. `<wallet>` is the base `44'/501'` address and picks a device when
+    /// several are plugged in.
+    pub fn open(uri: &str) -> Result<Self> {
+        let rest = uri
+            .strip_prefix("usb://ledger")
+            .ok_or_else(|| anyhow!("not a Ledger URI: {uri}"))?;
+        let (wallet, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let wallet = match wallet.trim_start_matches('/') {
+            "" => None,
+            wallet => Some(
+                wallet
+                    .parse::<Pubkey>()
+                    .map_err(|_| anyhow!("invalid wallet in {uri}"))?,
+            ),
+        };
+        let path = derivation_path(query).with_context(|| format!("parsing {uri}"))?;
+
+        let devices = hidraw_devices()?;
+        if devices.is_empty() {
+            bail!("no Ledger found; plug it in and unlock it");
+        }
+        for node in devices {
+            let device = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&node)
+                .with_context(|| format!("opening {}", node.display()))?;
+            let mut ledger = Self {
+                device,
+                path: path.clone(),
+                pubkey: Pubkey::default(),
+            };
+            if let Some(wallet) = wallet {
+                if ledger.get_pubkey(&[])? != wallet {
+                    continue;
+                }
+            }
+            ledger.pubkey = ledger.get_pubkey(&path)?;
+            return Ok(ledger);
+        }
+        bail!("no connected Ledger holds {}", wallet.unwrap_or_default())
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    /// Asks the device to sign `message`, waiting for the user to approve it.
+    pub fn sign(&self, message: &[u8]) -> Result<Signature> {
+        eprintln!("Approve the transaction on your Ledger...");
+
+        let mut payload = vec![1]; // number of signers
+        payload.extend(serialize_path(&self.path));
+        let room = MAX_CHUNK_SIZE - payload.len();
+        let (first, rest) = message.split_at(message.len().min(room));
+        payload.extend_from_slice(first);
+
+        let mut chunks: Vec<(u8, &[u8])> = rest
+            .chunks(MAX_CHUNK_SIZE)
+            .map(|chunk| (P2_EXTEND | P2_MORE, chunk))
+            .collect();
+        if let Some(last) = chunks.last_mut() {
+            last.0 &= !P2_MORE;
+        }
+        let p2 = if chunks.is_empty() { 0 } else { P2_MORE };
+
+        let mut response = self.exchange(INS_SIGN_MESSAGE, P1_CONFIRM, p2, &payload)?;
+        for (p2, chunk) in chunks {
+            response = self.exchange(INS_SIGN_MESSAGE, P1_CONFIRM, p2, chunk)?;
+        }
+        let bytes: [u8; 64] = response
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("Ledger returned a {}-byte signature", response.len()))?;
+        Ok(Signature::from(bytes))
+    }
+
+    fn get_pubkey(&self, path: &[u32]) -> Result<Pubkey> {
+        let response = self.exchange(INS_GET_PUBKEY, P1_NON_CONFIRM, 0, &serialize_path(path))?;
+        let bytes: [u8; 32] = response
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("Ledger returned a {}-byte public key", response.len()))?;
+        Ok(Pubkey::new_from_array(bytes))
+    }
+
+    fn exchange(&self, ins: u8, p1: u8, p2: u8, data: &[u8]) -> Result<Vec<u8>> {
+        self.write_apdu(ins, p1, p2, data)
+            .context("writing to the Ledger")?;
+        let mut response = self.read_response().context("reading from the Ledger")?;
+        if response.len() < 2 {
+            bail!("Ledger sent a truncated response");
+        }
+        let status =
+            u16::from_be_bytes([response[response.len() - 2], response[response.len() - 1]]);
+        response.truncate(response.len() - 2);
+        match status {
+            0x9000 => Ok(response),
+            0x6985 => bail!("the request was rejected on the Ledger"),
+            0x6808 => bail!("enable blind signing in the Ledger's Solana app settings"),
+            0x6d00 | 0x6e00 | 0x6e01 | 0x6511 => bail!("open the Solana app on the Ledger"),
+            0x5515 => bail!("unlock the Ledger"),
+            status => bail!("Ledger error {status:#06x}"),
+        }
+    }
+
+    /// Frames an APDU into 64-byte HID reports. The first report carries the
+    /// APDU length and header.
+    fn write_apdu(&self, ins: u8, p1: u8, p2: u8, data: &[u8]) -> Result<()> {
+        let mut apdu = Vec::with_capacity(7 + data.len());
+        apdu.extend_from_slice(&((data.len() + 5) as u16).to_be_bytes());
+        apdu.extend_from_slice(&[CLA, ins, p1, p2, data.len() as u8]);
+        apdu.extend_from_slice(data);
+
+        let mut device = &self.device;
+        for (sequence, chunk) in apdu.chunks(HID_PACKET_SIZE - 5).enumerate() {
+            // Leading zero is the report ID hidraw expects on writes
+            let mut report = [0u8; HID_PACKET_SIZE + 1];
+            report[1..3].copy_from_slice(&APDU_CHANNEL);
+            report[3] = APDU_TAG;
+            report[4..6].copy_from_slice(&(sequence as u16).to_be_bytes());
+            report[6..6 + chunk.len()].copy_from_slice(chunk);
+            device.write_all(&report)?;
+        }
+        Ok(())
+    }
+
+    fn read_response(&self) -> Result<Vec<u8>> {
+        let mut device = &self.device;
+        let mut message = Vec::new();
+        let mut expected = None;
+        for sequence in 0..=u16::MAX {
+            let mut report = [0u8; HID_PACKET_SIZE];
+            let read = device.read(&mut report)?;
+            if read < 5 || report[..2] != APDU_CHANNEL || report[2] != APDU_TAG {
+                bail!("unexpected HID report");
+            }
+            if u16::from_be_bytes([report[3], report[4]]) != sequence {
+                bail!("HID report out of sequence");
+            }
+            let mut offset = 5;
+            if sequence == 0 {
+                if read < 7 {
+                    bail!("unexpected HID report");
+                }
+                expected = Some(u16::from_be_bytes([report[5], report[6]]) as usize);
+                offset = 7;
+            }
+            let size = expected.unwrap_or_default();
+            message.extend_from_slice(&report[offset..read]);
+            if message.len() >= size {
+                message.truncate(size);
+                return Ok(message);
+            }
+        }
+        bail!("Ledger response too long")
+    }
+}
+
+/// `key=<account>[/<change>]` selects `44'/501'/<account>'[/<change>']`;
+/// without it the base `44'/501'` key is used, as `solana-keygen` does.
+fn derivation_path(query: &str) -> Result<Vec<u32>> {
+    let mut path = vec![44 | HARDENED, SOLANA_COIN_TYPE | HARDENED];
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        match pair.split_once('=') {
+            Some(("key", key)) => {
+                for index in key.split('/') {
+                    let index: u32 = index
+                        .trim_end_matches('\'')
+                        .parse()
+                        .map_err(|_| anyhow!("invalid key {key:?}"))?;
+                    path.push(index | HARDENED);
+                }
+            }
+            _ => bail!("unknown query {pair:?}"),
+        }
+    }
+    if path.len() > 4 {
+        bail!("key takes at most an account and a change index");
+    }
+    Ok(path)
+}
+
+fn serialize_path(path: &[u32]) -> Vec<u8> {
+    let mut bytes = vec![path.len() as u8];
+    for index in path {
+        bytes.extend_from_slice(&index.to_be_bytes());
+    }
+    bytes
+}
+
+/// `/dev/hidrawN` nodes for Ledger APDU interfaces.
+fn hidraw_devices() -> Result<Vec<PathBuf>> {
+    let class = Path::new("/sys/class/hidraw");
+    let Ok(entries) = fs::read_dir(class) else {
+        return Ok(Vec::new());
+    };
+    let mut devices = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let device = entry.path().join("device");
+        let uevent = fs::read_to_string(device.join("uevent")).unwrap_or_default();
+        let is_ledger = uevent
+            .lines()
+            .any(|line| line.starts_with("HID_ID=") && line.contains(LEDGER_VENDOR_ID));
+        let descriptor = fs::read(device.join("report_descriptor")).unwrap_or_default();
+        if is_ledger && descriptor.starts_with(&APDU_USAGE_PAGE) {
+            devices.push(Path::new("/dev").join(entry.file_name()));
+        }
+    }
+    devices.sort();
+    Ok(devices)
+}
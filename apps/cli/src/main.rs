@@ -0,0 +1,173 @@
+//! `friend-bets`: create, bet on and settle markets from the terminal.
+
+mod admin_log;
+mod args;
+mod client;
+mod commands;
+mod config;
+mod crank;
+mod db;
+mod dev;
+mod export;
+mod hot;
+mod ledger;
+mod portfolio;
+mod rpc;
+mod signer;
+mod snapshot;
+mod squads;
+mod stats;
+mod tui;
+mod units;
+mod watch;
+
+use std::env;
+use std::process::ExitCode;
+
+use anyhow::{bail, Result};
+
+use crate::args::Args;
+use crate::config::Config;
+
+const USAGE: &str = "\
+Usage: friend-bets [OPTIONS] <COMMAND> [ARGS]
+
+Commands:
+  create-market --mint <MINT> --title <TITLE>
+                [--id <ID>] [--fee-bps <BPS>] [--end <TIME>] [--resolve-deadline <TIME>]
+                [--allow-cash-out] [--haircut-bps <BPS>] [--lock-odds] [--burn-fee]
+                [--bet <a|b> --amount <AMOUNT>] [--unique-title]
+                           --unique-title refuses a title already open elsewhere and
+                           registers this one so others can find it
+  bet <MARKET> <a|b> <AMOUNT> [--min-odds-bps <BPS>] [--stake-rebate] [--up-to-cap]
+  schedule <MARKET> <a|b> <AMOUNT> --after <TIME> --until <TIME>
+           [--id <ID>] [--min-odds-bps <BPS>]
+                           Escrow a bet for anyone, such as friend-bets-keeper, to place
+                           between the two times
+  unschedule <SCHEDULED_BET>
+                           Cancel a scheduled bet, returning its stake
+  cashout-order <MARKET> [<MIN_VALUE>]
+                           Let anyone, such as friend-bets-keeper, cash out your position
+                           for a small tip once it would win MIN_VALUE or less; none clears it
+  close <MARKET>
+  resolve <MARKET> <a|b>
+  cancel <MARKET>
+  claim <MARKET>
+  withdraw-fee <MARKET>    Pays out split fees too
+  fee-splits <MARKET> [<RECIPIENT>=<BPS>...]
+                           Split the creator's fee before anyone bets; no splits clears them
+  charity <MARKET> [<TOKEN_ACCOUNT> <BPS>]
+                           Give part of the pool to charity once resolved, set before
+                           anyone bets; no account clears it
+  large-bet <MARKET> <AMOUNT>
+                           Emit LargeBetPlaced for bets of at least AMOUNT; 0 turns it off
+  caps <MARKET> [--a <AMOUNT>] [--b <AMOUNT>] [--max-ratio-bps <BPS>]
+                           Cap each side's stake and how far one can outgrow the other
+                           (20000 for 2x); left out means no limit
+  activity <MARKET>        Keep on-chain bet and bettor counts for ranking with `hot`
+  compact <MARKET>         Drop a settled market's title to refund part of its rent
+  squads propose-resolve <MARKET> <a|b> --multisig <MULTISIG> [--vault-index <N>]
+  squads propose-withdraw-fee <MARKET> --multisig <MULTISIG> [--vault-index <N>]
+  squads status|approve|execute <INDEX> --multisig <MULTISIG>
+  portfolio [<WALLET>] [--claim-all] [--history]
+                           --history lists the bets and claims recorded on-chain
+  open-portfolio           Record this wallet's bets and claims on-chain, paying rent
+                           for a page every 32
+  close-portfolio          Close the portfolio and its pages, refunding their rent
+  profile                  Show the settings in effect
+  hot [--hours <N>] [--limit <N>]
+                           Open markets with the most bets in the last N hours (default 24),
+                           among those keeping activity stats
+  crank [--dry-run] [--distribute] [--gc] [--metrics-file <PATH>]
+  export --market <MARKET> [--format csv|json]
+  snapshot <MARKET> [--slot <SLOT>] [--output <PATH>]
+                           A market's decoded accounts as JSON, all read at one slot;
+                           --slot waits for a slot that hasn't passed yet
+  stats [--by creator|mint|day|week|month] [--from <DATE>] [--to <DATE>]
+        [--mint <MINT>] [--creator <CREATOR>] [--format csv|json]
+        [--database-url <URL>]
+                           Volume, fee and participation summaries from the indexer's
+                           database [env: DATABASE_URL]; dates are YYYY-MM-DD in UTC
+  tui [--interval <SECS>]  Full-screen market explorer
+  watch [--market <MARKET>] [--json] [--interval <SECS>]
+  admin-log [--json]       Every config change and insurance payout the admin has made,
+                           checked against the hash the config keeps of them; needs an
+                           RPC node with the config's full history
+  dev bootstrap [--wallets <N>] [--airdrop <SOL>] [--out <DIR>]
+
+Options:
+  -p, --profile <NAME>     Profile from the config file [env: FRIEND_BETS_PROFILE]
+  -u, --url <URLS>         RPC URLs or cluster monikers, comma-separated [env: FRIEND_BETS_URL]
+  -k, --keypair <PATH>     Keypair file or usb://ledger[?key=N] [env: FRIEND_BETS_KEYPAIR]
+      --commitment <LEVEL> processed, confirmed or finalized [env: FRIEND_BETS_COMMITMENT]
+  -h, --help               Print this help
+
+Profiles are read from ~/.config/friend-bets/config.toml [env: FRIEND_BETS_CONFIG].
+Requests fail over between --url endpoints in order; URL#N caps one at N requests a second.
+Amounts are in whole tokens (e.g. 2.5) using the market mint's decimals.
+Times are unix timestamps or relative to now, like +2h or +3d.
+create-market takes the program config's default fee and times for those left out.
+";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let mut args = Args::parse(env::args().skip(1))?;
+    let command = args.optional_positional();
+    let Some(command) = command.filter(|_| !args.switch("help")) else {
+        print!("{USAGE}");
+        return Ok(());
+    };
+
+    let config = Config::from_args(&mut args)?;
+    match command.as_str() {
+        "watch" => return watch::watch(&config.rpc(), args),
+        "admin-log" => return admin_log::admin_log(&config.rpc(), args),
+        "export" => return export::export(&config.rpc(), args),
+        "stats" => return stats::stats(&config.rpc(), args),
+        "hot" => return hot::hot(&config.rpc(), args),
+        "snapshot" => return snapshot::snapshot(&config.rpc(), args),
+        "portfolio" => return portfolio::portfolio(&config, args),
+        "profile" => {
+            args.finish()?;
+            config.show();
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let client = config.client()?;
+    match command.as_str() {
+        "create-market" => commands::create_market(&client, args),
+        "bet" => commands::bet(&client, args),
+        "schedule" => commands::schedule(&client, args),
+        "unschedule" => commands::unschedule(&client, args),
+        "cashout-order" => commands::cashout_order(&client, args),
+        "close" => commands::close(&client, args),
+        "resolve" => commands::resolve(&client, args),
+        "cancel" => commands::cancel(&client, args),
+        "claim" => commands::claim(&client, args),
+        "withdraw-fee" => commands::withdraw_fee(&client, args),
+        "fee-splits" => commands::fee_splits(&client, args),
+        "charity" => commands::charity(&client, args),
+        "large-bet" => commands::large_bet(&client, args),
+        "caps" => commands::caps(&client, args),
+        "activity" => commands::activity(&client, args),
+        "compact" => commands::compact(&client, args),
+        "open-portfolio" => commands::open_portfolio(&client, args),
+        "close-portfolio" => commands::close_portfolio(&client, args),
+        "crank" => crank::crank(&client, args),
+        "squads" => squads::squads(&client, args),
+        "tui" => tui::tui(&client, args),
+        "dev" => dev::dev(&client, args),
+        other => bail!("unknown command {other:?}; see --help"),
+    }
+}
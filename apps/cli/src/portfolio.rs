@@ -0,0 +1,195 @@
+//! `portfolio`: every position a wallet holds and what it's still owed, or
+//! with `--history`, the bets and claims its on-chain portfolio recorded.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{bail, Result};
+use friend_bets_sdk::batch::claim_instructions;
+use friend_bets_sdk::fetch::{fetch_markets, fetch_portfolio_pages, fetch_positions_by_owner};
+use friend_bets_sdk::pda::is_position_of;
+use friend_bets_sdk::simulate::{position_value, simulate_claim};
+use friend_bets_sdk::{BetSide, Market, MarketStatus, PortfolioAction};
+
+use crate::args::Args;
+use crate::commands::parse_pubkey;
+use crate::config::Config;
+use crate::rpc::RpcClient;
+use crate::units::{format_amount, format_time, now};
+
+/// Claims packed into one transaction by `--claim-all`.
+const CLAIMS_PER_TRANSACTION: usize = 4;
+
+struct Row {
+    market: Pubkey,
+    title: String,
+    mint: Pubkey,
+    status: String,
+    side: String,
+    stake: u64,
+    value: u64,
+    /// `None` once claimed.
+    claimable: Option<u64>,
+}
+
+pub fn portfolio(config: &Config, mut args: Args) -> Result<()> {
+    let wallet = match args.optional_positional() {
+        Some(wallet) => parse_pubkey(&wallet)?,
+        None => config.signer()?.pubkey(),
+    };
+    let claim_all = args.switch("claim-all");
+    let history = args.switch("history");
+    args.finish()?;
+
+    let rpc = config.rpc();
+    if history {
+        return portfolio_history(&rpc, &wallet);
+    }
+    let positions = fetch_positions_by_owner(&rpc, &wallet)?;
+    if positions.is_empty() {
+        println!("{wallet} has no positions");
+        return Ok(());
+    }
+    let mut markets = fetch_markets(&rpc)?;
+    // Claiming cancels a market nobody resolved in time
+    let now = now();
+    for (_, market) in &mut markets {
+        market.cancel_if_expired(now);
+    }
+
+    let mut rows = Vec::new();
+    for (address, position) in &positions {
+        let Some((market_address, market)) = markets
+            .iter()
+            .find(|(market, _)| is_position_of(address, position, market))
+        else {
+            continue;
+        };
+        let finalized = matches!(
+            market.status,
+            MarketStatus::Resolved | MarketStatus::Cancelled
+        );
+        let claimable = if position.claimed {
+            None
+        } else if finalized {
+            Some(simulate_claim(market, position)?.total())
+        } else {
+            Some(0)
+        };
+        rows.push(Row {
+            market: *market_address,
+            title: market.title.clone(),
+            mint: market.mint,
+            status: status(market, position.side),
+            side: format!("{:?}", position.side),
+            stake: position.amount,
+            value: position_value(market, position)?,
+            claimable,
+        });
+    }
+    rows.sort_by_key(|row| std::cmp::Reverse(row.claimable.unwrap_or(0) > 0));
+
+    let mut decimals = HashMap::new();
+    for row in &rows {
+        if let Entry::Vacant(entry) = decimals.entry(row.mint) {
+            entry.insert(rpc.mint_decimals(&row.mint)?);
+        }
+    }
+
+    println!(
+        "{:<24} {:<10} {:<4} {:>12} {:>12} {:>12}  MARKET",
+        "TITLE", "STATUS", "SIDE", "STAKE", "VALUE", "CLAIMABLE"
+    );
+    for row in &rows {
+        let decimals = decimals[&row.mint];
+        let claimable = row
+            .claimable
+            .map_or_else(|| "claimed".into(), |raw| format_amount(raw, decimals));
+        println!(
+            "{:<24} {:<10} {:<4} {:>12} {:>12} {:>12}  {}",
+            truncate(&row.title, 24),
+            row.status,
+            row.side,
+            format_amount(row.stake, decimals),
+            format_amount(row.value, decimals),
+            claimable,
+            row.market
+        );
+    }
+
+    let owed: Vec<(Pubkey, Pubkey)> = rows
+        .iter()
+        .filter(|row| row.claimable.unwrap_or(0) > 0)
+        .map(|row| (row.market, row.mint))
+        .collect();
+    let skipped = positions.len() - rows.len();
+    if skipped > 0 {
+        println!("{skipped} positions belong to markets that no longer exist");
+    }
+    println!(
+        "{} positions, {} with something to claim",
+        rows.len(),
+        owed.len()
+    );
+
+    if !claim_all || owed.is_empty() {
+        return Ok(());
+    }
+    let client = config.client()?;
+    if client.pubkey() != wallet {
+        bail!(
+            "--claim-all must be signed by {wallet}, not {}",
+            client.pubkey()
+        );
+    }
+    let claims = claim_instructions(&wallet, &owed);
+    for batch in claims.chunks(CLAIMS_PER_TRANSACTION) {
+        let signature = client.send(batch)?;
+        println!("Claimed {} positions ({signature})", batch.len());
+    }
+    Ok(())
+}
+
+fn portfolio_history(rpc: &RpcClient, wallet: &Pubkey) -> Result<()> {
+    let pages = fetch_portfolio_pages(rpc, wallet)?;
+    if pages.is_empty() {
+        println!("{wallet} has no portfolio pages; see open-portfolio");
+        return Ok(());
+    }
+    println!("{:<20} {:<6} {:<4}  MARKET", "TIME", "ACTION", "SIDE");
+    for entry in pages.iter().flat_map(|(_, page)| &page.entries) {
+        let action = match entry.action {
+            PortfolioAction::Bet => "bet",
+            PortfolioAction::Claim => "claim",
+        };
+        let side = format!("{:?}", entry.side);
+        println!(
+            "{:<20} {:<6} {:<4}  {}",
+            format_time(entry.timestamp),
+            action,
+            side,
+            entry.market
+        );
+    }
+    Ok(())
+}
+
+pub fn status(market: &Market, side: BetSide) -> String {
+    match (market.status, market.outcome) {
+        (MarketStatus::Open, _) => "open".into(),
+        (MarketStatus::PendingResolve, _) => "pending".into(),
+        (MarketStatus::Resolved, Some(outcome)) if outcome == side => "won".into(),
+        (MarketStatus::Resolved, _) => "lost".into(),
+        (MarketStatus::Cancelled, _) => "cancelled".into(),
+    }
+}
+
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width - 1).collect();
+    cut.push('…');
+    cut
+}
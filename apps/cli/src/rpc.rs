@@ -0,0 +1,440 @@
+//! Minimal blocking JSON-RPC client.
+//!
+//! Requests go through `reqwest`'s blocking client. Given several
+//! endpoints, requests fail over between them and keep under each one's
+//! rate limit; see [`friend_bets_sdk::failover`].
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token::Mint;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use friend_bets_sdk::failover::{Endpoint, Endpoints, Failure, HealthPolicy, ENDPOINT_ERROR_CODES};
+use friend_bets_sdk::fetch::{AccountFilter, ProgramAccounts};
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+use solana_signature::Signature;
+
+pub struct RpcClient {
+    http: Client,
+    endpoints: Endpoints,
+    commitment: String,
+}
+
+impl RpcClient {
+    pub fn new(endpoints: Vec<Endpoint>, commitment: String) -> Self {
+        Self {
+            http: Client::new(),
+            endpoints: Endpoints::new(endpoints, HealthPolicy::default()),
+            commitment,
+        }
+    }
+
+    pub fn urls(&self) -> Vec<String> {
+        self.endpoints.urls()
+    }
+
+    /// Sends a request to the first endpoint that's up, failing over to the
+    /// next when one is unreachable, overloaded or behind.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value> {
+        self.endpoints.check_due(|url| {
+            post(
+                &self.http,
+                url,
+                "getHealth",
+                &request("getHealth", json!([])),
+            )
+            .is_ok()
+        });
+        let body = request(method, params);
+        self.endpoints
+            .call(|url| post(&self.http, url, method, &body))
+    }
+
+    fn config(&self) -> Value {
+        json!({ "commitment": self.commitment, "encoding": "base64" })
+    }
+
+    pub fn get_account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let result = self.call(
+            "getAccountInfo",
+            json!([address.to_string(), self.config()]),
+        )?;
+        account_data(&result["value"])
+    }
+
+    pub fn account<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T> {
+        let data = self
+            .get_account_data(address)?
+            .ok_or_else(|| anyhow!("account {address} not found"))?;
+        T::try_deserialize(&mut data.as_slice()).with_context(|| format!("decoding {address}"))
+    }
+
+    pub fn optional_account<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<Option<T>> {
+        self.get_account_data(address)?
+            .map(|data| {
+                T::try_deserialize(&mut data.as_slice())
+                    .with_context(|| format!("decoding {address}"))
+            })
+            .transpose()
+    }
+
+    /// Data of each of `addresses`, in order; `None` for missing accounts.
+    pub fn get_multiple_accounts(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut accounts = Vec::with_capacity(addresses.len());
+        // The RPC accepts at most 100 addresses per request
+        for chunk in addresses.chunks(100) {
+            let keys: Vec<String> = chunk.iter().map(ToString::to_string).collect();
+            let result = self.call("getMultipleAccounts", json!([keys, self.config()]))?;
+            for value in result["value"].as_array().into_iter().flatten() {
+                accounts.push(account_data(value)?);
+            }
+        }
+        Ok(accounts)
+    }
+
+    /// Like [`RpcClient::get_multiple_accounts`], but every account is read
+    /// at one slot, no earlier than `min_slot`, which is returned with them.
+    pub fn get_multiple_accounts_at(
+        &self,
+        addresses: &[Pubkey],
+        min_slot: u64,
+    ) -> Result<(u64, Vec<Option<Vec<u8>>>)> {
+        let mut slot = min_slot;
+        // Requests of 100 can each land on a newer slot; start over at the
+        // newest until one pass reads them all at the same one
+        for _ in 0..5 {
+            let mut accounts = Vec::with_capacity(addresses.len());
+            let mut read_at = None;
+            for chunk in addresses.chunks(100) {
+                let keys: Vec<String> = chunk.iter().map(ToString::to_string).collect();
+                let mut config = self.config();
+                config["minContextSlot"] = json!(slot);
+                let result = self.call("getMultipleAccounts", json!([keys, config]))?;
+                let context = result["context"]["slot"]
+                    .as_u64()
+                    .ok_or_else(|| anyhow!("getMultipleAccounts: no context slot"))?;
+                if read_at.is_some_and(|read_at| read_at != context) {
+                    read_at = None;
+                    slot = context;
+                    break;
+                }
+                read_at = Some(context);
+                for value in result["value"].as_array().into_iter().flatten() {
+                    accounts.push(account_data(value)?);
+                }
+            }
+            if let Some(read_at) = read_at {
+                return Ok((read_at, accounts));
+            }
+        }
+        bail!("couldn't read {} accounts at one slot", addresses.len())
+    }
+
+    pub fn get_slot(&self) -> Result<u64> {
+        Ok(self
+            .call("getSlot", json!([{ "commitment": self.commitment }]))?
+            .as_u64()
+            .unwrap_or_default())
+    }
+
+    pub fn mint_decimals(&self, mint: &Pubkey) -> Result<u8> {
+        Ok(self.account::<Mint>(mint)?.decimals)
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<(Hash, u64)> {
+        let result = self.call(
+            "getLatestBlockhash",
+            json!([{ "commitment": self.commitment }]),
+        )?;
+        let blockhash = result["value"]["blockhash"]
+            .as_str()
+            .ok_or_else(|| anyhow!("getLatestBlockhash: missing blockhash"))?
+            .parse()
+            .map_err(|_| anyhow!("getLatestBlockhash: invalid blockhash"))?;
+        let last_valid = result["value"]["lastValidBlockHeight"]
+            .as_u64()
+            .unwrap_or_default();
+        Ok((blockhash, last_valid))
+    }
+
+    pub fn get_balance(&self, address: &Pubkey) -> Result<u64> {
+        Ok(self.call(
+            "getBalance",
+            json!([address.to_string(), { "commitment": self.commitment }]),
+        )?["value"]
+            .as_u64()
+            .unwrap_or_default())
+    }
+
+    pub fn get_minimum_balance_for_rent_exemption(&self, space: usize) -> Result<u64> {
+        self.call("getMinimumBalanceForRentExemption", json!([space]))?
+            .as_u64()
+            .ok_or_else(|| anyhow!("getMinimumBalanceForRentExemption: missing amount"))
+    }
+
+    /// Asks the cluster's faucet for `lamports`; only devnet, testnet and
+    /// local validators have one.
+    pub fn request_airdrop(&self, address: &Pubkey, lamports: u64) -> Result<Signature> {
+        let result = self.call(
+            "requestAirdrop",
+            json!([address.to_string(), lamports, { "commitment": self.commitment }]),
+        )?;
+        result
+            .as_str()
+            .ok_or_else(|| anyhow!("requestAirdrop: missing signature"))?
+            .parse()
+            .map_err(|_| anyhow!("requestAirdrop: invalid signature"))
+    }
+
+    pub fn get_block_height(&self) -> Result<u64> {
+        Ok(self
+            .call("getBlockHeight", json!([{ "commitment": self.commitment }]))?
+            .as_u64()
+            .unwrap_or_default())
+    }
+
+    pub fn get_recent_prioritization_fees(&self, writable: &[Pubkey]) -> Result<Vec<u64>> {
+        // The RPC accepts at most 128 accounts
+        let keys: Vec<String> = writable.iter().take(128).map(ToString::to_string).collect();
+        let result = self.call("getRecentPrioritizationFees", json!([keys]))?;
+        Ok(result
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|fee| fee["prioritizationFee"].as_u64())
+            .collect())
+    }
+
+    pub fn send_transaction(&self, wire: &[u8]) -> Result<Signature> {
+        let result = self.call(
+            "sendTransaction",
+            json!([STANDARD.encode(wire), { "encoding": "base64", "skipPreflight": true }]),
+        )?;
+        result
+            .as_str()
+            .ok_or_else(|| anyhow!("sendTransaction: missing signature"))?
+            .parse()
+            .map_err(|_| anyhow!("sendTransaction: invalid signature"))
+    }
+
+    /// Status of `signature`: `None` if unknown, otherwise its confirmation
+    /// status and error, if any.
+    pub fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<(String, Option<String>)>> {
+        let result = self.call(
+            "getSignatureStatuses",
+            json!([[signature.to_string()], { "searchTransactionHistory": false }]),
+        )?;
+        let status = &result["value"][0];
+        if status.is_null() {
+            return Ok(None);
+        }
+        let confirmation = status["confirmationStatus"]
+            .as_str()
+            .unwrap_or("processed")
+            .to_string();
+        let err = (!status["err"].is_null()).then(|| status["err"].to_string());
+        Ok(Some((confirmation, err)))
+    }
+
+    /// Transactions touching `address` that landed after `until`, newest
+    /// first.
+    pub fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        until: Option<&str>,
+    ) -> Result<Vec<SignatureInfo>> {
+        self.signatures_page(address, None, until)
+    }
+
+    /// Every transaction touching `address` the node still has, oldest
+    /// first.
+    pub fn get_signature_history(&self, address: &Pubkey) -> Result<Vec<SignatureInfo>> {
+        let mut history = Vec::new();
+        loop {
+            let before = history
+                .last()
+                .map(|info: &SignatureInfo| info.signature.clone());
+            let page = self.signatures_page(address, before.as_deref(), None)?;
+            if page.is_empty() {
+                break;
+            }
+            history.extend(page);
+        }
+        history.reverse();
+        Ok(history)
+    }
+
+    /// One page, newest first, of the transactions touching `address`
+    /// between `until` and `before`.
+    fn signatures_page(
+        &self,
+        address: &Pubkey,
+        before: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Vec<SignatureInfo>> {
+        let mut config = json!({ "commitment": self.history_commitment() });
+        if let Some(before) = before {
+            config["before"] = before.into();
+        }
+        if let Some(until) = until {
+            config["until"] = until.into();
+        }
+        let result = self.call(
+            "getSignaturesForAddress",
+            json!([address.to_string(), config]),
+        )?;
+        Ok(result
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                Some(SignatureInfo {
+                    signature: entry["signature"].as_str()?.to_string(),
+                    slot: entry["slot"].as_u64().unwrap_or_default(),
+                    block_time: entry["blockTime"].as_i64(),
+                    failed: !entry["err"].is_null(),
+                })
+            })
+            .collect())
+    }
+
+    /// Log messages of a landed transaction, if the node still has it.
+    pub fn get_transaction_logs(&self, signature: &str) -> Result<Option<Vec<String>>> {
+        let result = self.call(
+            "getTransaction",
+            json!([signature, {
+                "commitment": self.history_commitment(),
+                "encoding": "base64",
+                "maxSupportedTransactionVersion": 0,
+            }]),
+        )?;
+        if result.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(
+            result["meta"]["logMessages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|line| line.as_str().map(String::from))
+                .collect(),
+        ))
+    }
+
+    /// Transaction history isn't served at `processed`.
+    fn history_commitment(&self) -> &str {
+        match self.commitment.as_str() {
+            "processed" => "confirmed",
+            commitment => commitment,
+        }
+    }
+}
+
+pub struct SignatureInfo {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub failed: bool,
+}
+
+fn account_data(value: &Value) -> Result<Option<Vec<u8>>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    let encoded = value["data"][0]
+        .as_str()
+        .ok_or_else(|| anyhow!("account data isn't base64"))?;
+    Ok(Some(STANDARD.decode(encoded)?))
+}
+
+impl ProgramAccounts for RpcClient {
+    type Error = anyhow::Error;
+
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[AccountFilter],
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>> {
+        let filters: Vec<Value> = filters
+            .iter()
+            .map(|filter| match filter {
+                AccountFilter::DataSize(size) => json!({ "dataSize": size }),
+                AccountFilter::Memcmp { offset, bytes } => json!({
+                    "memcmp": { "offset": offset, "bytes": STANDARD.encode(bytes), "encoding": "base64" }
+                }),
+            })
+            .collect();
+        let mut config = self.config();
+        config["filters"] = Value::Array(filters);
+
+        let result = self.call(
+            "getProgramAccounts",
+            json!([program_id.to_string(), config]),
+        )?;
+        result
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|entry| {
+                let address = entry["pubkey"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .parse()
+                    .map_err(|_| anyhow!("getProgramAccounts: invalid pubkey"))?;
+                let data = account_data(&entry["account"])?.unwrap_or_default();
+                Ok((address, data))
+            })
+            .collect()
+    }
+}
+
+fn request(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params })
+}
+
+/// Sends `body` to one endpoint, telling the endpoint's failures (no
+/// answer, an HTTP error or a node that's behind) from the request's.
+fn post(
+    http: &Client,
+    url: &str,
+    method: &str,
+    body: &Value,
+) -> Result<Value, Failure<anyhow::Error>> {
+    let endpoint = |err: anyhow::Error| Failure::Endpoint(err.context(method.to_string()));
+    let response = http
+        .post(url)
+        .json(body)
+        .send()
+        .map_err(|err| endpoint(err.into()))?;
+    let status = response.status();
+    let text = response.text().map_err(|err| endpoint(err.into()))?;
+
+    let mut response: Value = match serde_json::from_str(&text) {
+        Ok(response) => response,
+        Err(_) if !status.is_success() => return Err(endpoint(anyhow!("HTTP {status}"))),
+        Err(_) => return Err(endpoint(anyhow!("invalid response"))),
+    };
+    if let Some(error) = response.get("error") {
+        let err = anyhow!(
+            "{method}: {}",
+            error["message"].as_str().unwrap_or("rpc error")
+        );
+        let code = error["code"].as_i64().unwrap_or_default();
+        return Err(if ENDPOINT_ERROR_CODES.contains(&code) {
+            Failure::Endpoint(err)
+        } else {
+            Failure::Request(err)
+        });
+    }
+    // An HTTP error without a JSON-RPC one, such as a gateway's
+    if !status.is_success() {
+        return Err(endpoint(anyhow!("HTTP {status}")));
+    }
+    Ok(response["result"].take())
+}
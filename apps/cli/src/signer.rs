@@ -0,0 +1,41 @@
+//! Transaction signers: keypair files or a Ledger.
+
+use std::path::Path;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use friend_bets_sdk::keypair::Keypair;
+use solana_signature::Signature;
+
+use crate::ledger::Ledger;
+
+pub enum Signer {
+    Keypair(Keypair),
+    Ledger(Ledger),
+}
+
+impl Signer {
+    /// Opens `usb://ledger` URIs on a Ledger and reads anything else as a
+    /// keypair file.
+    pub fn open(uri: &str) -> Result<Self> {
+        if uri.starts_with("usb://ledger") {
+            Ok(Self::Ledger(Ledger::open(uri)?))
+        } else {
+            Ok(Self::Keypair(Keypair::read(Path::new(uri))?))
+        }
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        match self {
+            Self::Keypair(keypair) => keypair.pubkey(),
+            Self::Ledger(ledger) => ledger.pubkey(),
+        }
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Result<Signature> {
+        match self {
+            Self::Keypair(keypair) => Ok(keypair.sign(message)),
+            Self::Ledger(ledger) => ledger.sign(message),
+        }
+    }
+}
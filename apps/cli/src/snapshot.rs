@@ -0,0 +1,282 @@
+//! `snapshot`: a market's full decoded state as JSON, for dispute evidence,
+//! audits and support: the market account, every position, each side vault's
+//! balance and where the fee stands.
+//!
+//! All of it is read at one slot, which the snapshot records, and amounts
+//! are strings of raw units, next to the mint's decimals. RPC nodes
+//! only serve accounts' current state, so `--slot` waits for a slot
+//! rather than going back to one; take the snapshot ahead of the moment
+//! that matters, such as a market's resolve deadline.
+
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token::{Mint, TokenAccount};
+use anyhow::{anyhow, bail, Context, Result};
+use friend_bets_sdk::fetch::{positions_filters, ProgramAccounts};
+use friend_bets_sdk::pda::is_position_of;
+use friend_bets_sdk::{BetSide, Market, MarketStatus, Position, PROGRAM_ID};
+use serde_json::{json, Value};
+
+use crate::args::Args;
+use crate::commands::parse_pubkey;
+use crate::rpc::RpcClient;
+use crate::units::format_utc;
+
+/// Tries at finding every position before giving up on a consistent read.
+const ATTEMPTS: usize = 5;
+/// How often to check whether the RPC has reached `--slot`.
+const SLOT_POLL: Duration = Duration::from_millis(400);
+
+pub fn snapshot(rpc: &RpcClient, mut args: Args) -> Result<()> {
+    let market = parse_pubkey(&args.positional("MARKET")?)?;
+    let slot: Option<u64> = args.parse_option("slot")?;
+    let output = args.option("output");
+    args.finish()?;
+
+    let mut min_slot = 0;
+    if let Some(slot) = slot {
+        let current = rpc.get_slot()?;
+        if current > slot {
+            bail!(
+                "slot {slot} has passed (the RPC is at {current}); RPC nodes only serve \
+                 current state, so snapshots can't go back to it"
+            );
+        }
+        if current < slot {
+            eprintln!("Waiting for slot {slot} (at {current})");
+        }
+        while rpc.get_slot()? < slot {
+            thread::sleep(SLOT_POLL);
+        }
+        min_slot = slot;
+    }
+
+    let snapshot = read(rpc, &market, min_slot)?;
+    let text = serde_json::to_string_pretty(&snapshot)? + "\n";
+    match output {
+        Some(path) => fs::write(&path, text).with_context(|| format!("writing {path}"))?,
+        None => print!("{text}"),
+    }
+    Ok(())
+}
+
+/// Reads the market and its accounts at one slot, no earlier than
+/// `min_slot`, and decodes them.
+fn read(rpc: &RpcClient, market: &Pubkey, min_slot: u64) -> Result<Value> {
+    let state: Market = rpc.account(market)?;
+    for _ in 0..ATTEMPTS {
+        // Positions don't name their market; they're found by address
+        let positions: Vec<Pubkey> = rpc
+            .get_program_accounts(&PROGRAM_ID, &positions_filters())?
+            .into_iter()
+            .filter_map(|(address, data)| {
+                let position = Position::try_deserialize(&mut data.as_slice()).ok()?;
+                is_position_of(&address, &position, market).then_some(address)
+            })
+            .collect();
+
+        let mut addresses = vec![*market, state.vault_a, state.vault_b, state.mint];
+        addresses.extend(&positions);
+        let (slot, accounts) = rpc.get_multiple_accounts_at(&addresses, min_slot)?;
+        let decode = |index: usize| {
+            accounts[index]
+                .as_deref()
+                .ok_or_else(|| anyhow!("account {} not found", addresses[index]))
+        };
+        let state = Market::try_deserialize(&mut decode(0)?)
+            .with_context(|| format!("decoding {market}"))?;
+        let vault = |index: usize| {
+            accounts[index]
+                .as_deref()
+                .map(|mut data| TokenAccount::try_deserialize(&mut data))
+                .transpose()
+                .context("decoding a vault")
+        };
+        let vaults = [vault(1)?, vault(2)?];
+        let mint = Mint::try_deserialize(&mut decode(3)?).context("decoding the mint")?;
+        let mut held = Vec::new();
+        for (address, data) in positions.iter().zip(&accounts[4..]) {
+            // Claimed positions may be closed between the two reads
+            if let Some(mut data) = data.as_deref() {
+                let position = Position::try_deserialize(&mut data)
+                    .with_context(|| format!("decoding {address}"))?;
+                held.push((*address, position));
+            }
+        }
+
+        // A position opened after they were listed would be missing, and
+        // the market's count of open positions would give it away
+        let open = held
+            .iter()
+            .filter(|(_, position)| position.amount > 0 && !position.claimed)
+            .count();
+        if open != state.open_positions as usize {
+            eprintln!(
+                "warning: found {open} open positions at slot {slot}, but the market counts \
+                 {}; retrying",
+                state.open_positions
+            );
+            continue;
+        }
+        return Ok(document(market, slot, &state, &vaults, &mint, &held));
+    }
+    bail!("positions kept changing; try again")
+}
+
+fn document(
+    address: &Pubkey,
+    slot: u64,
+    market: &Market,
+    vaults: &[Option<TokenAccount>; 2],
+    mint: &Mint,
+    positions: &[(Pubkey, Position)],
+) -> Value {
+    let raw = |amount: u64| Value::String(amount.to_string());
+    let settled = matches!(
+        market.status,
+        MarketStatus::Resolved | MarketStatus::Cancelled
+    );
+    let positions: Vec<Value> = positions
+        .iter()
+        .map(|(address, position)| {
+            // What `claim` pays, staker rebate included
+            let payout = settled.then(|| market.claim_total(position).ok()).flatten();
+            json!({
+                "address": address.to_string(),
+                "owner": position.owner.to_string(),
+                "side": format!("{:?}", position.side),
+                "amount": raw(position.amount),
+                "claimed": position.claimed,
+                "locked_payout": raw(position.locked_payout),
+                "discounted_stake": raw(position.discounted_stake),
+                "cashout_min_value": raw(position.cashout_min_value),
+                "payout": payout.map(raw),
+            })
+        })
+        .collect();
+    let owed: u64 = positions
+        .iter()
+        .filter(|position| position["claimed"] == false)
+        .filter_map(|position| position["payout"].as_str()?.parse::<u64>().ok())
+        .sum();
+    // Cancelled markets refund every stake whole
+    let fee = match market.status {
+        MarketStatus::Cancelled => 0,
+        _ => market.fee_amount().unwrap_or_default(),
+    };
+    let fee_status = match market.status {
+        MarketStatus::Cancelled => "none",
+        _ if market.creator_fee_withdrawn && market.burn_fee => "burned",
+        _ if market.creator_fee_withdrawn => "withdrawn",
+        MarketStatus::Resolved => "due",
+        MarketStatus::Open | MarketStatus::PendingResolve => "accruing",
+    };
+    // Stakers' rebates are set by their bets, so the creator's cut is known
+    // before resolution
+    let (splits_due, creator_due) = match fee_status {
+        "accruing" | "due" => {
+            let mut resolved = market.clone();
+            resolved.status = MarketStatus::Resolved;
+            resolved.split_fee().unwrap_or_default()
+        }
+        _ => (Vec::new(), 0),
+    };
+    let splits: Vec<Value> = market
+        .fee_splits
+        .iter()
+        .enumerate()
+        .map(|(index, split)| {
+            json!({
+                "recipient": split.recipient.to_string(),
+                "bps": split.bps,
+                "due": raw(splits_due.get(index).copied().unwrap_or_default()),
+            })
+        })
+        .collect();
+
+    let vaults: Vec<Value> = [BetSide::A, BetSide::B]
+        .into_iter()
+        .zip(vaults)
+        .map(|(side, vault)| {
+            json!({
+                "side": format!("{side:?}"),
+                "address": market.vault(side).to_string(),
+                "exists": vault.is_some(),
+                "balance": raw(vault.as_ref().map_or(0, |vault| vault.amount)),
+            })
+        })
+        .collect();
+
+    json!({
+        "market": address.to_string(),
+        "slot": slot,
+        "program": PROGRAM_ID.to_string(),
+        "account": {
+            "market_id": market.market_id,
+            "creator": market.creator.to_string(),
+            "mint": market.mint.to_string(),
+            "vault_a": market.vault_a.to_string(),
+            "vault_b": market.vault_b.to_string(),
+            "title": market.title,
+            "fee_bps": market.fee_bps,
+            "end_ts": market.end_ts,
+            "end": format_utc(market.end_ts),
+            "resolve_deadline_ts": market.resolve_deadline_ts,
+            "resolve_deadline": format_utc(market.resolve_deadline_ts),
+            "status": format!("{:?}", market.status),
+            "outcome": market.outcome.map(|side| format!("{side:?}")),
+            "staked_a": raw(market.staked_a),
+            "staked_b": raw(market.staked_b),
+            "retained": raw(market.retained),
+            "allow_cash_out": market.allow_cash_out,
+            "cashout_haircut_bps": market.cashout_haircut_bps,
+            "lock_odds": market.lock_odds,
+            "locked_payout_a": raw(market.locked_payout_a),
+            "locked_payout_b": raw(market.locked_payout_b),
+            "forked_from": market.forked_from.map(|market| market.to_string()),
+            "dependency": market.dependency.map(|dependency| json!({
+                "market": dependency.market.to_string(),
+                "outcome": format!("{:?}", dependency.outcome),
+            })),
+            "open_positions": market.open_positions,
+            "burn_fee": market.burn_fee,
+            "creator_fee_withdrawn": market.creator_fee_withdrawn,
+            "fee_discounts": raw(market.fee_discounts),
+            "discounted_stake": raw(market.discounted_stake),
+            "charity": market.charity.map(|charity| charity.to_string()),
+            "charity_bps": market.charity_bps,
+            "charity_paid": market.charity_paid,
+            "cap_a": raw(market.cap_a),
+            "cap_b": raw(market.cap_b),
+            "max_ratio_bps": market.max_ratio_bps,
+            "bump": market.bump,
+            "vault_a_bump": market.vault_a_bump,
+            "vault_b_bump": market.vault_b_bump,
+        },
+        "mint": {
+            "address": market.mint.to_string(),
+            "decimals": mint.decimals,
+            "supply": raw(mint.supply),
+        },
+        "vaults": vaults,
+        "fee": {
+            "status": fee_status,
+            "bps": market.fee_bps,
+            "amount": raw(fee),
+            "rebated": raw(market.fee_discounts),
+            "splits": splits,
+            "creator_due": raw(creator_due),
+        },
+        "totals": {
+            "pool": raw(market.pool_total().unwrap_or_default()),
+            "distributable": raw(market.distributable().unwrap_or_default()),
+            "positions": positions.len(),
+            "unclaimed_payouts": settled.then(|| raw(owed)),
+        },
+        "positions": positions,
+    })
+}
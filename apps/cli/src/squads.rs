@@ -0,0 +1,422 @@
+//! Squads v4 proposals for markets whose creator is a multisig vault.
+//!
+//! `resolve` and `withdraw_creator_fee` (or `distribute_fees`, when the fee
+//! is split) are wrapped in a vault transaction plus a proposal; members
+//! approve it and, once the threshold is met, any member executes it. Accounts and instructions are encoded by hand from
+//! the Squads v4 IDL, as there is no Squads crate to lean on.
+
+use anchor_lang::prelude::{borsh, AccountMeta, Pubkey};
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{AnchorDeserialize, AnchorSerialize};
+use anyhow::{anyhow, bail, Context, Result};
+use friend_bets_sdk::instructions;
+use friend_bets_sdk::Market;
+use solana_message::Message;
+use solana_sdk_ids::system_program;
+
+use crate::args::Args;
+use crate::client::Client;
+use crate::commands::{parse_pubkey, parse_side};
+use crate::rpc::RpcClient;
+use crate::units::format_time;
+
+pub const SQUADS_PROGRAM_ID: Pubkey =
+    anchor_lang::pubkey!("SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf");
+
+const SEED_PREFIX: &[u8] = b"multisig";
+const SEED_VAULT: &[u8] = b"vault";
+const SEED_TRANSACTION: &[u8] = b"transaction";
+const SEED_PROPOSAL: &[u8] = b"proposal";
+
+#[derive(AnchorDeserialize)]
+struct Multisig {
+    _create_key: Pubkey,
+    _config_authority: Pubkey,
+    threshold: u16,
+    time_lock: u32,
+    transaction_index: u64,
+    _stale_transaction_index: u64,
+    _rent_collector: Option<Pubkey>,
+    _bump: u8,
+    members: Vec<Member>,
+}
+
+#[derive(AnchorDeserialize)]
+struct Member {
+    key: Pubkey,
+    _permissions: u8,
+}
+
+#[derive(AnchorDeserialize)]
+struct Proposal {
+    _multisig: Pubkey,
+    _transaction_index: u64,
+    status: ProposalStatus,
+    _bump: u8,
+    approved: Vec<Pubkey>,
+    rejected: Vec<Pubkey>,
+    _cancelled: Vec<Pubkey>,
+}
+
+#[derive(AnchorDeserialize)]
+enum ProposalStatus {
+    Draft { timestamp: i64 },
+    Active { timestamp: i64 },
+    Rejected { timestamp: i64 },
+    Approved { timestamp: i64 },
+    Executing,
+    Executed { timestamp: i64 },
+    Cancelled { timestamp: i64 },
+}
+
+impl ProposalStatus {
+    fn describe(&self) -> String {
+        let (name, timestamp) = match *self {
+            Self::Draft { timestamp } => ("draft", timestamp),
+            Self::Active { timestamp } => ("active", timestamp),
+            Self::Rejected { timestamp } => ("rejected", timestamp),
+            Self::Approved { timestamp } => ("approved", timestamp),
+            Self::Executing => return "executing".into(),
+            Self::Executed { timestamp } => ("executed", timestamp),
+            Self::Cancelled { timestamp } => ("cancelled", timestamp),
+        };
+        format!("{name} {}", format_time(timestamp))
+    }
+}
+
+#[derive(AnchorDeserialize)]
+struct VaultTransaction {
+    _multisig: Pubkey,
+    _creator: Pubkey,
+    _index: u64,
+    _bump: u8,
+    _vault_index: u8,
+    _vault_bump: u8,
+    _ephemeral_signer_bumps: Vec<u8>,
+    message: VaultTransactionMessage,
+}
+
+#[derive(AnchorDeserialize)]
+struct VaultTransactionMessage {
+    num_signers: u8,
+    num_writable_signers: u8,
+    num_writable_non_signers: u8,
+    account_keys: Vec<Pubkey>,
+    _instructions: Vec<CompiledInstruction>,
+    address_table_lookups: Vec<AddressTableLookup>,
+}
+
+#[derive(AnchorDeserialize)]
+struct CompiledInstruction {
+    _program_id_index: u8,
+    _account_indexes: Vec<u8>,
+    _data: Vec<u8>,
+}
+
+#[derive(AnchorDeserialize)]
+struct AddressTableLookup {
+    _account_key: Pubkey,
+    _writable_indexes: Vec<u8>,
+    _readonly_indexes: Vec<u8>,
+}
+
+#[derive(AnchorSerialize)]
+struct VaultTransactionCreateArgs {
+    vault_index: u8,
+    ephemeral_signers: u8,
+    transaction_message: Vec<u8>,
+    memo: Option<String>,
+}
+
+#[derive(AnchorSerialize)]
+struct ProposalCreateArgs {
+    transaction_index: u64,
+    draft: bool,
+}
+
+#[derive(AnchorSerialize)]
+struct ProposalVoteArgs {
+    memo: Option<String>,
+}
+
+pub fn vault_address(multisig: &Pubkey, vault_index: u8) -> Pubkey {
+    Pubkey::find_program_address(
+        &[SEED_PREFIX, multisig.as_ref(), SEED_VAULT, &[vault_index]],
+        &SQUADS_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn transaction_address(multisig: &Pubkey, index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            SEED_PREFIX,
+            multisig.as_ref(),
+            SEED_TRANSACTION,
+            &index.to_le_bytes(),
+        ],
+        &SQUADS_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn proposal_address(multisig: &Pubkey, index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            SEED_PREFIX,
+            multisig.as_ref(),
+            SEED_TRANSACTION,
+            &index.to_le_bytes(),
+            SEED_PROPOSAL,
+        ],
+        &SQUADS_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn squads(client: &Client, mut args: Args) -> Result<()> {
+    let action = args.positional("action")?;
+    let multisig = parse_pubkey(&args.required_option::<String>("multisig")?)?;
+    match action.as_str() {
+        "propose-resolve" | "propose-withdraw-fee" => {
+            let vault_index = args.parse_option("vault-index")?.unwrap_or(0);
+            let vault = vault_address(&multisig, vault_index);
+            let market_address = parse_pubkey(&args.positional("market")?)?;
+            let market: Market = client.rpc.account(&market_address)?;
+            if market.creator != vault {
+                bail!(
+                    "market creator {} isn't vault {vault_index} of {multisig} ({vault})",
+                    market.creator
+                );
+            }
+            let inner = if action == "propose-resolve" {
+                let outcome = parse_side(&args.positional("outcome")?)?;
+                let parent = market.dependency.map(|dependency| dependency.market);
+                instructions::resolve(&vault, &market_address, outcome, parent.as_ref())
+            } else {
+                instructions::pay_creator_fee(&vault, &market_address, &market)
+            };
+            args.finish()?;
+            propose(client, &multisig, vault_index, &inner)
+        }
+        "status" => {
+            let index = args.parse_positional("index")?;
+            args.finish()?;
+            status(&client.rpc, &multisig, index)
+        }
+        "approve" => {
+            let index = args.parse_positional("index")?;
+            args.finish()?;
+            approve(client, &multisig, index)
+        }
+        "execute" => {
+            let index = args.parse_positional("index")?;
+            args.finish()?;
+            execute(client, &multisig, index)
+        }
+        other => bail!("unknown squads action {other:?}; see --help"),
+    }
+}
+
+fn read_account<T: AnchorDeserialize>(rpc: &RpcClient, address: &Pubkey) -> Result<T> {
+    let data = rpc
+        .get_account_data(address)?
+        .ok_or_else(|| anyhow!("account {address} not found"))?;
+    let mut payload = data.get(8..).unwrap_or_default();
+    T::deserialize(&mut payload).with_context(|| format!("decoding {address}"))
+}
+
+/// Creates the vault transaction and its proposal, and approves it as the
+/// proposer, all in one transaction.
+fn propose(client: &Client, multisig: &Pubkey, vault_index: u8, inner: &Instruction) -> Result<()> {
+    let state: Multisig = read_account(&client.rpc, multisig)?;
+    let member = client.pubkey();
+    if !state.members.iter().any(|m| m.key == member) {
+        bail!("{member} isn't a member of {multisig}");
+    }
+    let index = state.transaction_index + 1;
+    let transaction = transaction_address(multisig, index);
+    let proposal = proposal_address(multisig, index);
+    let vault = vault_address(multisig, vault_index);
+
+    let create = squads_instruction(
+        "vault_transaction_create",
+        vec![
+            AccountMeta::new(*multisig, false),
+            AccountMeta::new(transaction, false),
+            AccountMeta::new_readonly(member, true),
+            AccountMeta::new(member, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        VaultTransactionCreateArgs {
+            vault_index,
+            ephemeral_signers: 0,
+            transaction_message: transaction_message(&vault, inner),
+            memo: None,
+        },
+    );
+    let create_proposal = squads_instruction(
+        "proposal_create",
+        vec![
+            AccountMeta::new_readonly(*multisig, false),
+            AccountMeta::new(proposal, false),
+            AccountMeta::new_readonly(member, true),
+            AccountMeta::new(member, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        ProposalCreateArgs {
+            transaction_index: index,
+            draft: false,
+        },
+    );
+    let signature = client.send(&[
+        create,
+        create_proposal,
+        approve_instruction(multisig, &proposal, &member),
+    ])?;
+
+    println!("Proposed transaction {index} on {multisig}");
+    println!("  proposal  {proposal}");
+    println!("  approvals 1/{}", state.threshold);
+    println!("Signature {signature}");
+    Ok(())
+}
+
+fn status(rpc: &RpcClient, multisig: &Pubkey, index: u64) -> Result<()> {
+    let state: Multisig = read_account(rpc, multisig)?;
+    let proposal: Proposal = read_account(rpc, &proposal_address(multisig, index))?;
+    println!("Transaction {index} on {multisig}");
+    println!("  status    {}", proposal.status.describe());
+    println!(
+        "  approvals {}/{}",
+        proposal.approved.len(),
+        state.threshold
+    );
+    for member in &proposal.approved {
+        println!("    {member}");
+    }
+    if !proposal.rejected.is_empty() {
+        println!("  rejections {}", proposal.rejected.len());
+    }
+    if state.time_lock > 0 {
+        println!("  time lock {}s after approval", state.time_lock);
+    }
+    Ok(())
+}
+
+fn approve(client: &Client, multisig: &Pubkey, index: u64) -> Result<()> {
+    let proposal = proposal_address(multisig, index);
+    let signature = client.send(&[approve_instruction(multisig, &proposal, &client.pubkey())])?;
+    println!("Approved transaction {index} on {multisig}");
+    println!("Signature {signature}");
+    Ok(())
+}
+
+fn execute(client: &Client, multisig: &Pubkey, index: u64) -> Result<()> {
+    let proposal_key = proposal_address(multisig, index);
+    let proposal: Proposal = read_account(&client.rpc, &proposal_key)?;
+    if !matches!(proposal.status, ProposalStatus::Approved { .. }) {
+        bail!(
+            "transaction {index} is {}, not approved",
+            proposal.status.describe()
+        );
+    }
+    let transaction = transaction_address(multisig, index);
+    let message = read_account::<VaultTransaction>(&client.rpc, &transaction)?.message;
+    if !message.address_table_lookups.is_empty() {
+        bail!("transactions using lookup tables aren't supported");
+    }
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*multisig, false),
+        AccountMeta::new(proposal_key, false),
+        AccountMeta::new_readonly(transaction, false),
+        AccountMeta::new_readonly(client.pubkey(), true),
+    ];
+    let num_signers = message.num_signers as usize;
+    accounts.extend(message.account_keys.iter().enumerate().map(|(i, key)| {
+        let writable = if i < num_signers {
+            i < message.num_writable_signers as usize
+        } else {
+            i - num_signers < message.num_writable_non_signers as usize
+        };
+        if writable {
+            AccountMeta::new(*key, false)
+        } else {
+            AccountMeta::new_readonly(*key, false)
+        }
+    }));
+    let execute = Instruction {
+        program_id: SQUADS_PROGRAM_ID,
+        accounts,
+        data: discriminator("vault_transaction_execute").to_vec(),
+    };
+
+    let signature = client.send(&[execute])?;
+    println!("Executed transaction {index} on {multisig}");
+    println!("Signature {signature}");
+    Ok(())
+}
+
+fn approve_instruction(multisig: &Pubkey, proposal: &Pubkey, member: &Pubkey) -> Instruction {
+    squads_instruction(
+        "proposal_approve",
+        vec![
+            AccountMeta::new_readonly(*multisig, false),
+            AccountMeta::new(*member, true),
+            AccountMeta::new(*proposal, false),
+        ],
+        ProposalVoteArgs { memo: None },
+    )
+}
+
+fn squads_instruction(
+    name: &str,
+    accounts: Vec<AccountMeta>,
+    args: impl AnchorSerialize,
+) -> Instruction {
+    let mut data = discriminator(name).to_vec();
+    args.serialize(&mut data).expect("writing to a Vec");
+    Instruction {
+        program_id: SQUADS_PROGRAM_ID,
+        accounts,
+        data,
+    }
+}
+
+fn discriminator(name: &str) -> [u8; 8] {
+    hash(format!("global:{name}").as_bytes()).to_bytes()[..8]
+        .try_into()
+        .expect("8 bytes")
+}
+
+/// Encodes `instruction`, paid for and signed by `vault`, as the compact
+/// `TransactionMessage` Squads expects: u8-prefixed vectors everywhere except
+/// instruction data, which has a u16 prefix.
+fn transaction_message(vault: &Pubkey, instruction: &Instruction) -> Vec<u8> {
+    let message = Message::new(std::slice::from_ref(instruction), Some(vault));
+    let header = message.header;
+    let num_signers = header.num_required_signatures;
+    let non_signers = message.account_keys.len() as u8 - num_signers;
+
+    let mut bytes = vec![
+        num_signers,
+        num_signers - header.num_readonly_signed_accounts,
+        non_signers - header.num_readonly_unsigned_accounts,
+        message.account_keys.len() as u8,
+    ];
+    for key in &message.account_keys {
+        bytes.extend_from_slice(key.as_ref());
+    }
+    bytes.push(message.instructions.len() as u8);
+    for compiled in &message.instructions {
+        bytes.push(compiled.program_id_index);
+        bytes.push(compiled.accounts.len() as u8);
+        bytes.extend_from_slice(&compiled.accounts);
+        bytes.extend_from_slice(&(compiled.data.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&compiled.data);
+    }
+    bytes.push(0); // address table lookups
+    bytes
+}
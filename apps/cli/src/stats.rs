@@ -0,0 +1,164 @@
+//! `stats`: volume, fee and participation summaries from the indexer's
+//! database, per creator, per mint or per day, week or month.
+//!
+//! Each row counts the markets created, the bets placed and the distinct
+//! wallets placing them, and sums the stake bet and the fees taken
+//! (withdrawn, split, burned or rebated) in its group. Rows are split by mint too,
+//! as amounts in different tokens don't add up.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Map, Value};
+
+use crate::args::Args;
+use crate::commands::parse_pubkey;
+use crate::db::Database;
+use crate::rpc::RpcClient;
+use crate::units::format_amount;
+
+const GROUPS: [&str; 5] = ["creator", "mint", "day", "week", "month"];
+
+const COUNTS: [&str; 3] = ["markets", "bets", "bettors"];
+
+const AMOUNTS: [&str; 2] = ["volume", "fees"];
+
+pub fn stats(rpc: &RpcClient, mut args: Args) -> Result<()> {
+    let by = args.option("by").unwrap_or_else(|| "month".into());
+    if !GROUPS.contains(&by.as_str()) {
+        bail!("unknown --by {by:?}; use {}", GROUPS.join(", "));
+    }
+    let format = args.option("format").unwrap_or_else(|| "csv".into());
+    if format != "csv" && format != "json" {
+        bail!("unknown format {format:?}; use csv or json");
+    }
+    let mut filters = vec!["a.at IS NOT NULL".to_string()];
+    let mut variables = Vec::new();
+    for column in ["mint", "creator"] {
+        if let Some(address) = args.option(column) {
+            filters.push(format!("m.{column} = :'{column}'"));
+            variables.push((column, parse_pubkey(&address)?.to_string()));
+        }
+    }
+    if let Some(from) = args.option("from") {
+        filters.push("a.at >= :'from'::date".to_string());
+        variables.push(("from", date(&from)?));
+    }
+    if let Some(to) = args.option("to") {
+        filters.push("a.at < :'to'::date + 1".to_string());
+        variables.push(("to", date(&to)?));
+    }
+    let database_url = args
+        .option("database-url")
+        .or_else(|| std::env::var("DATABASE_URL").ok())
+        .ok_or_else(|| anyhow!("set --database-url or DATABASE_URL"))?;
+    args.finish()?;
+
+    let key = match by.as_str() {
+        "creator" | "mint" => format!("m.{by}"),
+        period => format!("to_char(date_trunc('{period}', a.at AT TIME ZONE 'UTC'), 'YYYY-MM-DD')"),
+    };
+    let rows = Database::new(database_url).json(
+        &format!(
+            "SELECT COALESCE(json_agg(r ORDER BY r.key, r.mint), '[]') FROM (\
+         SELECT {key} AS key, m.mint, \
+         count(DISTINCT a.market) FILTER (WHERE a.kind = 'market') AS markets, \
+         count(*) FILTER (WHERE a.kind = 'bet') AS bets, \
+         count(DISTINCT a.owner) FILTER (WHERE a.kind = 'bet') AS bettors, \
+         COALESCE(sum(a.amount) FILTER (WHERE a.kind = 'bet'), 0)::text AS volume, \
+         COALESCE(sum(a.amount) FILTER (WHERE a.kind = 'fee'), 0)::text AS fees \
+         FROM (SELECT address AS market, 'market' AS kind, NULL AS owner, 0 AS amount, \
+                 created_at AS at FROM markets \
+               UNION ALL SELECT market, 'bet', owner, amount, block_time FROM bets \
+                 WHERE kind = 'bet' \
+               UNION ALL SELECT market, 'fee', NULL, amount, block_time FROM fees \
+                 WHERE kind NOT IN ('insurance', 'charity')) a \
+         JOIN markets m ON m.address = a.market \
+         WHERE {} GROUP BY 1, 2) r",
+            filters.join(" AND ")
+        ),
+        &variables,
+    )?;
+    let rows = rows.as_array().cloned().unwrap_or_default();
+
+    // Amounts are shown in whole tokens of each row's mint
+    let mut decimals: HashMap<String, Option<u8>> = HashMap::new();
+    for mint in rows.iter().filter_map(|row| row["mint"].as_str()) {
+        if decimals.contains_key(mint) {
+            continue;
+        }
+        let found = parse_pubkey(mint).and_then(|mint| rpc.mint_decimals(&mint));
+        if let Err(err) = &found {
+            eprintln!("warning: {mint}: {err:#}; its amounts are in raw units");
+        }
+        decimals.insert(mint.to_string(), found.ok());
+    }
+
+    let mut columns = vec![if by == "creator" || by == "mint" {
+        by.as_str()
+    } else {
+        "period"
+    }];
+    if by != "mint" {
+        columns.push("mint");
+    }
+    columns.extend(COUNTS);
+    columns.extend(AMOUNTS);
+    let records: Vec<Map<String, Value>> = rows
+        .iter()
+        .map(|row| {
+            let mint = row["mint"].as_str().unwrap_or_default();
+            let decimals = decimals.get(mint).copied().flatten();
+            let mut record = Map::new();
+            record.insert(columns[0].into(), row["key"].clone());
+            if by != "mint" {
+                record.insert("mint".into(), row["mint"].clone());
+            }
+            for count in COUNTS {
+                record.insert(count.into(), row[count].clone());
+            }
+            for amount in AMOUNTS {
+                let raw = row[amount].as_str().unwrap_or("0");
+                let shown = match (decimals, raw.parse::<u64>()) {
+                    (Some(decimals), Ok(raw)) => format_amount(raw, decimals),
+                    _ => raw.to_string(),
+                };
+                record.insert(amount.into(), Value::String(shown));
+            }
+            record
+        })
+        .collect();
+
+    if format == "json" {
+        let document = json!({ "by": by, "rows": records });
+        println!("{}", serde_json::to_string_pretty(&document)?);
+    } else {
+        println!("{}", columns.join(","));
+        for record in &records {
+            let line: Vec<String> = columns
+                .iter()
+                .map(|column| match &record[*column] {
+                    Value::String(text) => text.clone(),
+                    Value::Null => String::new(),
+                    other => other.to_string(),
+                })
+                .collect();
+            println!("{}", line.join(","));
+        }
+    }
+    Ok(())
+}
+
+/// Checks a `YYYY-MM-DD` date.
+fn date(text: &str) -> Result<String> {
+    let bytes = text.as_bytes();
+    let valid = bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, byte)| match i {
+            4 | 7 => *byte == b'-',
+            _ => byte.is_ascii_digit(),
+        });
+    if !valid {
+        bail!("invalid date {text:?}; use YYYY-MM-DD");
+    }
+    Ok(text.to_string())
+}
@@ -0,0 +1,633 @@
+//! `tui`: a full-screen market explorer.
+//!
+//! Three views: open markets with live odds, the wallet's positions, and
+//! pending actions (claims owed, plus resolutions and fees due on markets
+//! the wallet created). Bets, claims and resolutions are sent from the
+//! keyboard; data refreshes every `--interval` seconds or on `r`.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal};
+use std::time::{Duration, Instant};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::{bail, Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use friend_bets_sdk::fetch::{fetch_markets, fetch_positions_by_owner};
+use friend_bets_sdk::instructions;
+use friend_bets_sdk::pda::is_position_of;
+use friend_bets_sdk::simulate::{implied_odds, position_value, simulate_claim, ImpliedOdds};
+use friend_bets_sdk::{BetSide, Market, MarketStatus};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Cell, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::args::Args;
+use crate::client::Client;
+use crate::commands::staker_discount;
+use crate::portfolio::{status, truncate};
+use crate::units::{format_amount, format_odds, format_time, now, parse_amount};
+
+/// How long to wait for a key before checking whether a refresh is due.
+const TICK: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum View {
+    Markets,
+    Positions,
+    Actions,
+}
+
+impl View {
+    const ALL: [View; 3] = [View::Markets, View::Positions, View::Actions];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            View::Markets => "Markets",
+            View::Positions => "Positions",
+            View::Actions => "Actions",
+        }
+    }
+
+    fn keys(self) -> &'static str {
+        match self {
+            View::Markets => "a/b bet on a side",
+            View::Positions => "c claim",
+            View::Actions => "c claim or withdraw  a/b resolve",
+        }
+    }
+}
+
+struct MarketRow {
+    address: Pubkey,
+    market: Market,
+    odds: ImpliedOdds,
+}
+
+struct PositionRow {
+    address: Pubkey,
+    market: Market,
+    side: BetSide,
+    stake: u64,
+    value: u64,
+    /// `None` once claimed.
+    claimable: Option<u64>,
+}
+
+enum ActionKind {
+    Claim(u64),
+    Resolve,
+    WithdrawFee(u64),
+}
+
+struct ActionRow {
+    address: Pubkey,
+    market: Market,
+    kind: ActionKind,
+}
+
+struct App<'a> {
+    client: &'a Client,
+    wallet: Pubkey,
+    view: View,
+    selected: [usize; 3],
+    markets: Vec<MarketRow>,
+    positions: Vec<PositionRow>,
+    actions: Vec<ActionRow>,
+    decimals: HashMap<Pubkey, u8>,
+    discount: Option<u16>,
+    status: String,
+}
+
+pub fn tui(client: &Client, mut args: Args) -> Result<()> {
+    let interval = Duration::from_secs(args.parse_option("interval")?.unwrap_or(15));
+    args.finish()?;
+
+    let mut app = App {
+        client,
+        wallet: client.pubkey(),
+        view: View::Markets,
+        selected: [0; 3],
+        markets: Vec::new(),
+        positions: Vec::new(),
+        actions: Vec::new(),
+        decimals: HashMap::new(),
+        discount: None,
+        status: String::new(),
+    };
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        bail!("tui needs an interactive terminal");
+    }
+    let mut terminal = ratatui::try_init().context("setting up the terminal")?;
+    let result = app.run(&mut terminal, interval);
+    ratatui::restore();
+    result
+}
+
+impl App<'_> {
+    fn run(&mut self, terminal: &mut DefaultTerminal, interval: Duration) -> Result<()> {
+        self.status = "Loading...".into();
+        self.draw(terminal)?;
+        self.status.clear();
+        self.reload();
+
+        let mut refreshed = Instant::now();
+        loop {
+            // Only changed cells are written, so this also picks up resizes
+            self.draw(terminal)?;
+            for key in read_keys()? {
+                let result = match key {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Tab | KeyCode::Right => {
+                        self.view = View::ALL[(self.view.index() + 1) % View::ALL.len()];
+                        Ok(())
+                    }
+                    KeyCode::Left => {
+                        self.view =
+                            View::ALL[(self.view.index() + View::ALL.len() - 1) % View::ALL.len()];
+                        Ok(())
+                    }
+                    KeyCode::Char(c @ '1'..='3') => {
+                        self.view = View::ALL[c as usize - '1' as usize];
+                        Ok(())
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        self.move_selection(-1);
+                        Ok(())
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        self.move_selection(1);
+                        Ok(())
+                    }
+                    KeyCode::Char('r') => {
+                        self.reload();
+                        refreshed = Instant::now();
+                        Ok(())
+                    }
+                    KeyCode::Char('a') => self.choose_side(terminal, BetSide::A),
+                    KeyCode::Char('b') => self.choose_side(terminal, BetSide::B),
+                    KeyCode::Char('c') | KeyCode::Enter => self.claim(terminal),
+                    _ => Ok(()),
+                };
+                if let Err(err) = result {
+                    self.status = format!("error: {err:#}");
+                }
+            }
+            if refreshed.elapsed() >= interval {
+                self.reload();
+                refreshed = Instant::now();
+            }
+        }
+    }
+
+    /// Refreshes everything, reporting failures in the status line.
+    fn reload(&mut self) {
+        if let Err(err) = self.refresh() {
+            self.status = format!("refresh failed: {err:#}");
+        }
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        let rpc = &self.client.rpc;
+        let now = now();
+        let mut markets = fetch_markets(rpc)?;
+        // Claiming cancels a market nobody resolved in time
+        for (_, market) in &mut markets {
+            market.cancel_if_expired(now);
+        }
+        let positions = fetch_positions_by_owner(rpc, &self.wallet)?;
+        self.discount = staker_discount(rpc, &self.wallet)?;
+
+        self.markets.clear();
+        self.positions.clear();
+        self.actions.clear();
+        for (address, market) in &markets {
+            if market.status == MarketStatus::Open && now < market.end_ts {
+                self.markets.push(MarketRow {
+                    address: *address,
+                    market: market.clone(),
+                    odds: implied_odds(market)?,
+                });
+            }
+            if market.creator != self.wallet {
+                continue;
+            }
+            let resolvable = market.status == MarketStatus::PendingResolve
+                || (market.status == MarketStatus::Open && now >= market.end_ts);
+            if resolvable && now < market.resolve_deadline_ts {
+                self.actions.push(ActionRow {
+                    address: *address,
+                    market: market.clone(),
+                    kind: ActionKind::Resolve,
+                });
+            }
+            if market.status == MarketStatus::Resolved && !market.creator_fee_withdrawn {
+                let (_, fee) = market.split_fee()?;
+                if fee > 0 {
+                    self.actions.push(ActionRow {
+                        address: *address,
+                        market: market.clone(),
+                        kind: ActionKind::WithdrawFee(fee),
+                    });
+                }
+            }
+        }
+        self.markets.sort_by_key(|row| row.market.end_ts);
+
+        for (position_address, position) in &positions {
+            let Some((address, market)) = markets
+                .iter()
+                .find(|(market, _)| is_position_of(position_address, position, market))
+            else {
+                continue;
+            };
+            let finalized = matches!(
+                market.status,
+                MarketStatus::Resolved | MarketStatus::Cancelled
+            );
+            let claimable = if position.claimed {
+                None
+            } else if finalized {
+                Some(simulate_claim(market, position)?.total())
+            } else {
+                Some(0)
+            };
+            if let Some(amount) = claimable.filter(|amount| *amount > 0) {
+                self.actions.push(ActionRow {
+                    address: *address,
+                    market: market.clone(),
+                    kind: ActionKind::Claim(amount),
+                });
+            }
+            self.positions.push(PositionRow {
+                address: *address,
+                market: market.clone(),
+                side: position.side,
+                stake: position.amount,
+                value: position_value(market, position)?,
+                claimable,
+            });
+        }
+        self.positions
+            .sort_by_key(|row| std::cmp::Reverse(row.claimable.unwrap_or(0) > 0));
+
+        let mints: Vec<Pubkey> = self
+            .markets
+            .iter()
+            .map(|row| row.market.mint)
+            .chain(self.positions.iter().map(|row| row.market.mint))
+            .chain(self.actions.iter().map(|row| row.market.mint))
+            .collect();
+        for mint in mints {
+            if let Entry::Vacant(entry) = self.decimals.entry(mint) {
+                entry.insert(rpc.mint_decimals(&mint)?);
+            }
+        }
+
+        for view in View::ALL {
+            let len = self.len(view);
+            let selected = &mut self.selected[view.index()];
+            *selected = (*selected).min(len.saturating_sub(1));
+        }
+        Ok(())
+    }
+
+    fn len(&self, view: View) -> usize {
+        match view {
+            View::Markets => self.markets.len(),
+            View::Positions => self.positions.len(),
+            View::Actions => self.actions.len(),
+        }
+    }
+
+    fn move_selection(&mut self, by: isize) {
+        let len = self.len(self.view);
+        let selected = &mut self.selected[self.view.index()];
+        *selected = selected
+            .saturating_add_signed(by)
+            .min(len.saturating_sub(1));
+    }
+
+    fn amount(&self, raw: u64, mint: &Pubkey) -> String {
+        match self.decimals.get(mint) {
+            Some(decimals) => format_amount(raw, *decimals),
+            None => raw.to_string(),
+        }
+    }
+
+    fn draw(&self, terminal: &mut DefaultTerminal) -> Result<()> {
+        terminal.draw(|frame| self.render(frame))?;
+        Ok(())
+    }
+
+    fn render(&self, frame: &mut Frame) {
+        let [top, body, help, status] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let mut tabs = Vec::new();
+        for (index, view) in View::ALL.into_iter().enumerate() {
+            let label = Span::raw(format!(" {} {} ", index + 1, view.title()));
+            tabs.push(if view == self.view {
+                label.reversed()
+            } else {
+                label
+            });
+            tabs.push(Span::raw(" "));
+        }
+        tabs.push(Span::raw(format!(" {}", self.wallet)));
+        frame.render_widget(Line::from(tabs), top);
+
+        let (header, widths, rows) = self.table();
+        let selected = (!rows.is_empty()).then(|| self.selected[self.view.index()]);
+        let rows = if rows.is_empty() {
+            vec![Row::new([" (nothing here)"])]
+        } else {
+            rows
+        };
+        let table = Table::new(rows, widths)
+            .header(header.bold())
+            .row_highlight_style(Style::new().reversed());
+        let mut state = TableState::new().with_selected(selected);
+        frame.render_stateful_widget(table, body, &mut state);
+
+        let keys = format!(
+            "↑/↓ move  tab/1-3 switch  {}  r refresh  q quit",
+            self.view.keys()
+        );
+        frame.render_widget(Line::raw(keys).dim(), help);
+        frame.render_widget(Line::raw(self.status.as_str()), status);
+    }
+
+    /// Column headings, widths and rows of the current view.
+    fn table(&self) -> (Row<'static>, Vec<Constraint>, Vec<Row<'static>>) {
+        match self.view {
+            View::Markets => (
+                Row::new([
+                    cell("TITLE"),
+                    cell("CLOSES"),
+                    number("POOL A"),
+                    number("POOL B"),
+                    number("ODDS A"),
+                    number("ODDS B"),
+                ]),
+                widths(&[28, 10, 12, 12, 7, 7]),
+                self.markets
+                    .iter()
+                    .map(|row| {
+                        Row::new([
+                            cell(truncate(&row.market.title, 28)),
+                            cell(format_time(row.market.end_ts)),
+                            number(self.amount(row.market.staked_a, &row.market.mint)),
+                            number(self.amount(row.market.staked_b, &row.market.mint)),
+                            number(format_odds(row.odds.a_bps)),
+                            number(format_odds(row.odds.b_bps)),
+                        ])
+                    })
+                    .collect(),
+            ),
+            View::Positions => (
+                Row::new([
+                    cell("TITLE"),
+                    cell("STATUS"),
+                    cell("SIDE"),
+                    number("STAKE"),
+                    number("VALUE"),
+                    number("CLAIMABLE"),
+                ]),
+                widths(&[28, 10, 4, 12, 12, 12]),
+                self.positions
+                    .iter()
+                    .map(|row| {
+                        let claimable = row.claimable.map_or_else(
+                            || "claimed".into(),
+                            |raw| self.amount(raw, &row.market.mint),
+                        );
+                        Row::new([
+                            cell(truncate(&row.market.title, 28)),
+                            cell(status(&row.market, row.side)),
+                            cell(format!("{:?}", row.side)),
+                            number(self.amount(row.stake, &row.market.mint)),
+                            number(self.amount(row.value, &row.market.mint)),
+                            number(claimable),
+                        ])
+                    })
+                    .collect(),
+            ),
+            View::Actions => (
+                Row::new(["ACTION", "MARKET", "DETAIL"]),
+                vec![
+                    Constraint::Length(12),
+                    Constraint::Length(28),
+                    Constraint::Min(0),
+                ],
+                self.actions
+                    .iter()
+                    .map(|row| {
+                        let (action, detail) = match row.kind {
+                            ActionKind::Claim(amount) => {
+                                ("claim", self.amount(amount, &row.market.mint))
+                            }
+                            ActionKind::Resolve => (
+                                "resolve",
+                                format!("deadline {}", format_time(row.market.resolve_deadline_ts)),
+                            ),
+                            ActionKind::WithdrawFee(amount) => {
+                                ("withdraw fee", self.amount(amount, &row.market.mint))
+                            }
+                        };
+                        Row::new([
+                            cell(action),
+                            cell(truncate(&row.market.title, 28)),
+                            cell(detail),
+                        ])
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Reads a line in the status bar; `None` if the user backs out.
+    fn prompt(&mut self, terminal: &mut DefaultTerminal, label: &str) -> Result<Option<String>> {
+        let mut input = String::new();
+        loop {
+            self.status = format!("{label}{input}_");
+            self.draw(terminal)?;
+            for key in read_keys()? {
+                match key {
+                    KeyCode::Enter => {
+                        self.status.clear();
+                        return Ok(Some(input));
+                    }
+                    KeyCode::Esc => {
+                        self.status = "Cancelled".into();
+                        return Ok(None);
+                    }
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) => input.push(c),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// `a`/`b`: bet on the selected market, or resolve the selected action.
+    fn choose_side(&mut self, terminal: &mut DefaultTerminal, side: BetSide) -> Result<()> {
+        let index = self.selected[self.view.index()];
+        match self.view {
+            View::Markets => {
+                let Some(row) = self.markets.get(index) else {
+                    return Ok(());
+                };
+                let (address, market) = (row.address, row.market.clone());
+                let label = format!("Bet on {side:?} in \"{}\", amount: ", market.title);
+                let Some(input) = self.prompt(terminal, &label)? else {
+                    return Ok(());
+                };
+                let amount = parse_amount(input.trim(), self.decimals[&market.mint])?;
+                let done = format!(
+                    "Bet {} on {side:?} in \"{}\"",
+                    self.amount(amount, &market.mint),
+                    market.title
+                );
+                let mut instruction =
+                    instructions::place_bet_on(&self.wallet, &address, &market, side, amount, 0);
+                if self.discount.is_some() {
+                    instruction = instructions::with_bettor_stake(instruction, &self.wallet);
+                }
+                self.send(terminal, done, instruction)
+            }
+            View::Actions => {
+                let Some(row) = self.actions.get(index) else {
+                    return Ok(());
+                };
+                if !matches!(row.kind, ActionKind::Resolve) {
+                    return Ok(());
+                }
+                let (address, market) = (row.address, row.market.clone());
+                let label = format!("Resolve \"{}\" to {side:?}? [y/N] ", market.title);
+                let Some(answer) = self.prompt(terminal, &label)? else {
+                    return Ok(());
+                };
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    self.status = "Cancelled".into();
+                    return Ok(());
+                }
+                let parent = market.dependency.map(|dependency| dependency.market);
+                let instruction = if market.status == MarketStatus::Open {
+                    instructions::close_and_resolve(&self.wallet, &address, side, parent.as_ref())
+                } else {
+                    instructions::resolve(&self.wallet, &address, side, parent.as_ref())
+                };
+                let done = format!("Resolved \"{}\" to {side:?}", market.title);
+                self.send(terminal, done, instruction)
+            }
+            View::Positions => Ok(()),
+        }
+    }
+
+    /// `c`: claim the selected position or action, or withdraw its fee.
+    fn claim(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let index = self.selected[self.view.index()];
+        let (address, market, amount, fee) = match self.view {
+            View::Positions => match self.positions.get(index) {
+                Some(row) if row.claimable.unwrap_or(0) > 0 => (
+                    row.address,
+                    row.market.clone(),
+                    row.claimable.unwrap_or(0),
+                    false,
+                ),
+                _ => return Ok(()),
+            },
+            View::Actions => match self.actions.get(index) {
+                Some(ActionRow {
+                    address,
+                    market,
+                    kind: ActionKind::Claim(amount),
+                }) => (*address, market.clone(), *amount, false),
+                Some(ActionRow {
+                    address,
+                    market,
+                    kind: ActionKind::WithdrawFee(amount),
+                }) => (*address, market.clone(), *amount, true),
+                _ => return Ok(()),
+            },
+            View::Markets => return Ok(()),
+        };
+        let amount = self.amount(amount, &market.mint);
+        if fee {
+            let done = format!("Withdrew {amount} in fees from \"{}\"", market.title);
+            let instruction = instructions::pay_creator_fee(&self.wallet, &address, &market);
+            self.send(terminal, done, instruction)
+        } else {
+            let done = format!("Claimed {amount} from \"{}\"", market.title);
+            let instruction = instructions::claim(&self.wallet, &address, &market.mint);
+            self.send(terminal, done, instruction)
+        }
+    }
+
+    fn send(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        done: String,
+        instruction: Instruction,
+    ) -> Result<()> {
+        self.status = "Sending...".into();
+        self.draw(terminal)?;
+        let signature = self.client.send(&[instruction])?;
+        self.status = format!("{done} ({signature})");
+        self.reload();
+        Ok(())
+    }
+}
+
+/// Key presses waiting on the terminal, after waiting up to [`TICK`] for
+/// the first. Ctrl-C reads as Esc, since both back out; other control
+/// chords are dropped.
+fn read_keys() -> Result<Vec<KeyCode>> {
+    let mut keys = Vec::new();
+    let mut timeout = TICK;
+    while event::poll(timeout)? {
+        timeout = Duration::ZERO;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            if key.code == KeyCode::Char('c') {
+                keys.push(KeyCode::Esc);
+            }
+            continue;
+        }
+        keys.push(key.code);
+    }
+    Ok(keys)
+}
+
+fn cell(text: impl Into<String>) -> Cell<'static> {
+    Cell::from(text.into())
+}
+
+/// A right-aligned cell, for amounts and odds.
+fn number(text: impl Into<String>) -> Cell<'static> {
+    Cell::from(Line::raw(text.into()).right_aligned())
+}
+
+fn widths(columns: &[u16]) -> Vec<Constraint> {
+    columns.iter().copied().map(Constraint::Length).collect()
+}
@@ -0,0 +1,109 @@
+//! Token amounts, odds and timestamps in human-readable form.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+
+/// Parses a decimal token amount like `1.5` into raw units.
+pub fn parse_amount(amount: &str, decimals: u8) -> Result<u64> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if fraction.len() > decimals as usize {
+        bail!("{amount} has more than {decimals} decimal places");
+    }
+    let digits = format!("{whole}{fraction:0<width$}", width = decimals as usize);
+    digits
+        .parse::<u64>()
+        .map_err(|_| anyhow!("invalid amount {amount:?}"))
+}
+
+/// Formats raw units with the mint's decimals, trimming trailing zeros.
+pub fn format_amount(raw: u64, decimals: u8) -> String {
+    if decimals == 0 {
+        return raw.to_string();
+    }
+    let scale = 10u64.pow(decimals as u32);
+    let fraction = format!("{:0width$}", raw % scale, width = decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (raw / scale).to_string()
+    } else {
+        format!("{}.{fraction}", raw / scale)
+    }
+}
+
+/// Formats basis-point odds as a multiplier, e.g. `15000` as `1.50x`.
+pub fn format_odds(odds_bps: u64) -> String {
+    format!("{}.{:02}x", odds_bps / 10_000, odds_bps % 10_000 / 100)
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+/// Parses a unix timestamp, or a time relative to now such as `+90m`,
+/// `+2h` or `+3d`.
+pub fn parse_time(value: &str) -> Result<i64> {
+    let Some(relative) = value.strip_prefix('+') else {
+        return value.parse().map_err(|_| anyhow!("invalid time {value:?}"));
+    };
+
+    let split = relative.len().saturating_sub(1);
+    let (count, unit) = relative.split_at(split);
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => bail!("invalid time {value:?}; use s, m, h or d"),
+    };
+    let count: i64 = count
+        .parse()
+        .map_err(|_| anyhow!("invalid time {value:?}"))?;
+    Ok(now() + count * seconds)
+}
+
+/// Formats a timestamp as a UTC time of day, `HH:MM:SS`.
+pub fn format_clock(ts: i64) -> String {
+    let secs = ts.rem_euclid(86_400);
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+/// Formats a timestamp as UTC RFC 3339, e.g. `2024-05-01T12:00:00Z`.
+pub fn format_utc(ts: i64) -> String {
+    // Civil-from-days, after Howard Hinnant's date algorithms
+    let days = ts.div_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}T{}Z", format_clock(ts))
+}
+
+/// Formats a timestamp relative to now, e.g. `in 2h 5m` or `3d ago`.
+pub fn format_time(ts: i64) -> String {
+    let delta = ts - now();
+    let secs = delta.unsigned_abs();
+    let span = match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3_599 => format!("{}m", secs / 60),
+        3_600..=86_399 => format!("{}h {}m", secs / 3_600, secs % 3_600 / 60),
+        _ => format!("{}d {}h", secs / 86_400, secs % 86_400 / 3_600),
+    };
+    if delta >= 0 {
+        format!("in {span}")
+    } else {
+        format!("{span} ago")
+    }
+}
@@ -0,0 +1,833 @@
+//! `watch`: a live feed of the program's events.
+//!
+//! Polls `getSignaturesForAddress` for the program, or a single market, and
+//! decodes the events in each new transaction's logs. Bets and cash-outs are
+//! followed by the market's current implied odds.
+
+use std::collections::{BTreeSet, HashMap};
+use std::thread;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use friend_bets_sdk::events::{market_events, parse_logs, FriendBetsEvent};
+use friend_bets_sdk::pda::config_address;
+use friend_bets_sdk::simulate::implied_odds;
+use friend_bets_sdk::{
+    AdminAction, Config as ProgramConfig, ConfigUpdate, FeeSplit, FeeTier, Market, MarketPolicy,
+    PROGRAM_ID,
+};
+use serde_json::{json, Value};
+
+use crate::args::Args;
+use crate::commands::parse_pubkey;
+use crate::rpc::{RpcClient, SignatureInfo};
+use crate::units::{format_amount, format_clock, format_odds, format_time};
+
+pub fn watch(rpc: &RpcClient, mut args: Args) -> Result<()> {
+    let market = args
+        .option("market")
+        .map(|value| parse_pubkey(&value))
+        .transpose()?;
+    let json = args.switch("json");
+    let interval = Duration::from_secs(args.parse_option("interval")?.unwrap_or(2));
+    args.finish()?;
+
+    let address = market.unwrap_or(PROGRAM_ID);
+    let mut feed = Feed {
+        rpc,
+        market,
+        json,
+        decimals: HashMap::new(),
+        stake_decimals: None,
+    };
+    // Only transactions after this point are shown.
+    let mut last = rpc
+        .get_signatures_for_address(&address, None)?
+        .first()
+        .map(|info| info.signature.clone());
+    if !json {
+        eprintln!("Watching {address}; press Ctrl-C to stop");
+    }
+
+    loop {
+        thread::sleep(interval);
+        let infos = match rpc.get_signatures_for_address(&address, last.as_deref()) {
+            Ok(infos) => infos,
+            Err(err) => {
+                eprintln!("warning: {err:#}");
+                continue;
+            }
+        };
+        if let Some(newest) = infos.first() {
+            last = Some(newest.signature.clone());
+        }
+        for info in infos.iter().rev().filter(|info| !info.failed) {
+            if let Err(err) = feed.transaction(info) {
+                eprintln!("warning: {}: {err:#}", info.signature);
+            }
+        }
+    }
+}
+
+struct Feed<'a> {
+    rpc: &'a RpcClient,
+    market: Option<Pubkey>,
+    json: bool,
+    /// Mint decimals by market; `None` once the market is gone.
+    decimals: HashMap<Pubkey, Option<u8>>,
+    stake_decimals: Option<Option<u8>>,
+}
+
+impl Feed<'_> {
+    fn transaction(&mut self, info: &SignatureInfo) -> Result<()> {
+        let Some(logs) = self.rpc.get_transaction_logs(&info.signature)? else {
+            return Ok(());
+        };
+        let events = match &self.market {
+            Some(market) => market_events(&logs, market)?,
+            None => parse_logs(&logs)?,
+        };
+
+        let mut moved = BTreeSet::new();
+        for event in &events {
+            if let FriendBetsEvent::BetPlaced(e) = event {
+                moved.insert(e.market);
+            } else if let FriendBetsEvent::CashedOut(e) = event {
+                moved.insert(e.market);
+            }
+
+            if self.json {
+                println!(
+                    "{}",
+                    json!({
+                        "signature": info.signature,
+                        "slot": info.slot,
+                        "block_time": info.block_time,
+                        "event": event.name(),
+                        "data": event_json(event),
+                    })
+                );
+            } else {
+                let decimals = match event.market() {
+                    Some(market) => self.market_decimals(market),
+                    None => self.stake_decimals(),
+                };
+                let clock = info
+                    .block_time
+                    .map_or_else(|| "--:--:--".into(), format_clock);
+                println!("{clock} {}", describe(event, decimals));
+            }
+        }
+
+        for market in moved {
+            let Ok(state) = self.rpc.account::<Market>(&market) else {
+                continue;
+            };
+            let odds = implied_odds(&state)?;
+            if self.json {
+                println!(
+                    "{}",
+                    json!({
+                        "signature": info.signature,
+                        "slot": info.slot,
+                        "block_time": info.block_time,
+                        "odds": {
+                            "market": market.to_string(),
+                            "a_bps": odds.a_bps,
+                            "b_bps": odds.b_bps,
+                        },
+                    })
+                );
+            } else {
+                println!(
+                    "         odds on \"{}\" now A {} / B {}",
+                    state.title,
+                    format_odds(odds.a_bps),
+                    format_odds(odds.b_bps)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn market_decimals(&mut self, market: Pubkey) -> Option<u8> {
+        let rpc = self.rpc;
+        *self.decimals.entry(market).or_insert_with(|| {
+            let state = rpc.account::<Market>(&market).ok()?;
+            rpc.mint_decimals(&state.mint).ok()
+        })
+    }
+
+    fn stake_decimals(&mut self) -> Option<u8> {
+        let rpc = self.rpc;
+        *self.stake_decimals.get_or_insert_with(|| {
+            let config = rpc.account::<ProgramConfig>(&config_address().0).ok()?;
+            rpc.mint_decimals(&config.stake_mint).ok()
+        })
+    }
+}
+
+/// One line describing `event`. Amounts are raw units when `decimals` is
+/// unknown.
+fn describe(event: &FriendBetsEvent, decimals: Option<u8>) -> String {
+    let amount = |raw: u64| decimals.map_or_else(|| raw.to_string(), |d| format_amount(raw, d));
+    let cap = |raw: u64| match raw {
+        0 => "none".to_string(),
+        raw => amount(raw),
+    };
+    match event {
+        FriendBetsEvent::MarketInitialized(e) => format!(
+            "new market {} \"{}\" by {}, fee {} bps, betting closes {}",
+            e.market,
+            e.title,
+            e.creator,
+            e.fee_bps,
+            format_time(e.end_ts)
+        ),
+        FriendBetsEvent::MarketCloned(e) => {
+            format!("market {} cloned from {}", e.market, e.source)
+        }
+        FriendBetsEvent::MarketForked(e) => {
+            format!("market {} forked from {}", e.market, e.original)
+        }
+        FriendBetsEvent::DependencySet(e) => format!(
+            "market {} now depends on {} resolving {:?}",
+            e.market, e.parent, e.outcome
+        ),
+        FriendBetsEvent::BetPlaced(e) => format!(
+            "{} bet {} on {:?} in {}",
+            e.user,
+            amount(e.amount),
+            e.side,
+            e.market
+        ),
+        FriendBetsEvent::LargeBetPlaced(e) => format!(
+            "large bet: {} bet {} on {:?} in {} (pools A {}, B {})",
+            e.user,
+            amount(e.amount),
+            e.side,
+            e.market,
+            amount(e.staked_a),
+            amount(e.staked_b)
+        ),
+        FriendBetsEvent::LargeBetThresholdSet(e) => match e.threshold {
+            0 => format!("{} no longer reports large bets", e.market),
+            threshold => format!(
+                "{} reports bets of {} or more as large",
+                e.market,
+                amount(threshold)
+            ),
+        },
+        FriendBetsEvent::StakeCapsSet(e) => format!(
+            "{} stake caps now A {}, B {}, ratio {}",
+            e.market,
+            cap(e.cap_a),
+            cap(e.cap_b),
+            match e.max_ratio_bps {
+                0 => "none".to_string(),
+                bps => format_odds(bps.into()),
+            }
+        ),
+        FriendBetsEvent::BetReceipt(e) => format!(
+            "{} locked {} on {:?} in {}, payout {}",
+            e.user,
+            format_odds(e.odds_bps),
+            e.side,
+            e.market,
+            amount(e.locked_payout)
+        ),
+        FriendBetsEvent::CashedOut(e) => format!(
+            "{} cashed out {} on {:?} from {} for {}",
+            e.user,
+            amount(e.stake),
+            e.side,
+            e.market,
+            amount(e.payout)
+        ),
+        FriendBetsEvent::CashOutOrderSet(e) if e.min_value == 0 => {
+            format!("{} cleared their cash-out order on {}", e.user, e.market)
+        }
+        FriendBetsEvent::CashOutOrderSet(e) => format!(
+            "{} set a cash-out order on {} at {}",
+            e.user,
+            e.market,
+            amount(e.min_value)
+        ),
+        FriendBetsEvent::CashOutOrderExecuted(e) => format!(
+            "{} filled the cash-out order of {} on {} for {}, tip {}",
+            e.executor,
+            e.user,
+            e.market,
+            amount(e.payout),
+            amount(e.tip)
+        ),
+        FriendBetsEvent::BettingClosed(e) => format!("betting closed on {}", e.market),
+        FriendBetsEvent::Resolved(e) => format!("{} resolved to {:?}", e.market, e.outcome),
+        FriendBetsEvent::Cancelled(e) => format!("{} cancelled", e.market),
+        FriendBetsEvent::Claimed(e) => {
+            format!("{} claimed {} from {}", e.user, amount(e.amount), e.market)
+        }
+        FriendBetsEvent::CreatorFeeWithdrawn(e) => format!(
+            "{} withdrew {} in fees from {}",
+            e.creator,
+            amount(e.amount),
+            e.market
+        ),
+        FriendBetsEvent::FeeSplitsSet(e) => format!(
+            "fee of {} split {}",
+            e.market,
+            e.splits
+                .iter()
+                .map(|split| format!("{} bps to {}", split.bps, split.recipient))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        FriendBetsEvent::FeeSplitPaid(e) => format!(
+            "{} got {} of the fee split from {}",
+            e.recipient,
+            amount(e.amount),
+            e.market
+        ),
+        FriendBetsEvent::FeeBurned(e) => {
+            format!("{} in fees burned from {}", amount(e.amount), e.market)
+        }
+        FriendBetsEvent::InsuranceCollected(e) => {
+            format!("{} of insurance collected from {}", amount(e.amount), e.market)
+        }
+        FriendBetsEvent::Compensated(e) => format!(
+            "{} compensated {} from insurance for {}",
+            e.recipient,
+            amount(e.amount),
+            e.market
+        ),
+        FriendBetsEvent::CharitySet(e) => match e.charity {
+            Some(charity) => format!(
+                "{} gives {} bps of its pool to charity {charity}",
+                e.market, e.charity_bps
+            ),
+            None => format!("{} no longer gives to charity", e.market),
+        },
+        FriendBetsEvent::CharityDonated(e) => format!(
+            "{} donated {} to charity {}",
+            e.market,
+            amount(e.amount),
+            e.charity
+        ),
+        FriendBetsEvent::FeeRebated(e) => format!(
+            "{} got a {} fee rebate (on {} staked at a discount) from {}",
+            e.user,
+            amount(e.amount),
+            amount(e.discounted_stake),
+            e.market
+        ),
+        FriendBetsEvent::ConfigUpdated(e) => format!(
+            "config updated by {}: {} fee tiers, {}s unbonding, fees {} bps by default up to {} bps",
+            e.admin,
+            e.fee_tiers.len(),
+            e.unbonding_secs,
+            e.policy.default_fee_bps,
+            e.policy.max_fee_bps
+        ),
+        FriendBetsEvent::ConfigUpdateProposed(e) => format!(
+            "config update proposed by {}, applicable {}: admin {}, {} fee tiers, {}s unbonding, \
+             fees {} bps by default up to {} bps, {}s delay",
+            e.admin,
+            format_time(e.ready_ts),
+            e.update.admin,
+            e.update.fee_tiers.len(),
+            e.update.unbonding_secs,
+            e.update.policy.default_fee_bps,
+            e.update.policy.max_fee_bps,
+            e.update.update_delay_secs
+        ),
+        FriendBetsEvent::ConfigUpdateCancelled(e) => {
+            format!("config update cancelled by {}", e.admin)
+        }
+        FriendBetsEvent::AdminActionLogged(e) => format!(
+            "admin action #{} by {}: {}",
+            e.sequence,
+            e.admin,
+            describe_admin_action(&e.action, decimals)
+        ),
+        FriendBetsEvent::Staked(e) => format!(
+            "{} staked {} (total {})",
+            e.user,
+            amount(e.amount),
+            amount(e.total)
+        ),
+        FriendBetsEvent::UnstakeRequested(e) => format!(
+            "{} started unstaking {}, withdrawable {}",
+            e.user,
+            amount(e.amount),
+            format_time(e.unbonding_end_ts)
+        ),
+        FriendBetsEvent::StakeWithdrawn(e) => {
+            format!("{} withdrew {} of stake", e.user, amount(e.amount))
+        }
+        FriendBetsEvent::MarketCollected(e) => format!(
+            "{} collected {}: {} positions closed, {} to caller, {} to creator",
+            e.caller,
+            e.market,
+            e.positions_closed,
+            amount(e.caller_share),
+            amount(e.creator_share)
+        ),
+        FriendBetsEvent::PositionsCompressed(e) => format!(
+            "{} now keeps positions in tree {}",
+            e.market, e.position_tree
+        ),
+        FriendBetsEvent::CompressedBetPlaced(e) => format!(
+            "{} bet {} on {:?} in {} (leaf {})",
+            e.user,
+            amount(e.amount),
+            e.side,
+            e.market,
+            e.leaf_index
+        ),
+        FriendBetsEvent::CompressedPositionClaimed(e) => {
+            format!("{} claimed leaf {} of {}", e.user, e.leaf_index, e.market)
+        }
+        FriendBetsEvent::BettorPagesEnabled(e) => {
+            format!("{} now records its bettors in pages", e.market)
+        }
+        FriendBetsEvent::BettorPageClosed(e) => format!(
+            "bettor page {} of {} closed, rent to {}",
+            e.index, e.market, e.payer
+        ),
+        FriendBetsEvent::ActivityStatsEnabled(e) => {
+            format!("{} now keeps activity stats", e.market)
+        }
+        FriendBetsEvent::ActivityStatsClosed(e) => {
+            format!("activity stats of {} closed, rent to {}", e.market, e.payer)
+        }
+        FriendBetsEvent::MarketTitleRegistered(e) => {
+            format!("{} registered its title under nonce {}", e.market, e.nonce)
+        }
+        FriendBetsEvent::MarketTitleClosed(e) => {
+            format!("{} released its title (nonce {})", e.market, e.nonce)
+        }
+        FriendBetsEvent::BetScheduled(e) => format!(
+            "{} scheduled {} on {:?} in {} for {} to {}",
+            e.owner,
+            amount(e.amount),
+            e.side,
+            e.market,
+            format_time(e.execute_after),
+            format_time(e.expiry)
+        ),
+        FriendBetsEvent::ScheduledBetExecuted(e) => format!(
+            "{} placed scheduled bet {} of {} in {}",
+            e.executor, e.scheduled_bet, e.owner, e.market
+        ),
+        FriendBetsEvent::ScheduledBetCancelled(e) => format!(
+            "scheduled bet {} of {} in {} cancelled, {} returned",
+            e.scheduled_bet,
+            e.owner,
+            e.market,
+            amount(e.amount)
+        ),
+        FriendBetsEvent::PortfolioOpened(e) => format!("{} opened portfolio {}", e.owner, e.portfolio),
+        FriendBetsEvent::PortfolioPageClosed(e) => {
+            format!("{} closed portfolio page {}", e.owner, e.index)
+        }
+        FriendBetsEvent::PortfolioClosed(e) => format!("{} closed portfolio {}", e.owner, e.portfolio),
+        FriendBetsEvent::MarketCompacted(e) => format!(
+            "{} compacted, {} lamports of rent refunded",
+            e.market, e.refund
+        ),
+        FriendBetsEvent::Unknown { discriminator, .. } => {
+            format!("unknown event {discriminator:?}")
+        }
+    }
+}
+
+/// The event's fields as JSON, with raw amounts.
+fn event_json(event: &FriendBetsEvent) -> Value {
+    match event {
+        FriendBetsEvent::MarketInitialized(e) => json!({
+            "market": e.market.to_string(),
+            "creator": e.creator.to_string(),
+            "title": e.title,
+            "fee_bps": e.fee_bps,
+            "end_ts": e.end_ts,
+            "resolve_deadline_ts": e.resolve_deadline_ts,
+        }),
+        FriendBetsEvent::MarketCloned(e) => json!({
+            "market": e.market.to_string(),
+            "source": e.source.to_string(),
+        }),
+        FriendBetsEvent::MarketForked(e) => json!({
+            "market": e.market.to_string(),
+            "original": e.original.to_string(),
+        }),
+        FriendBetsEvent::DependencySet(e) => json!({
+            "market": e.market.to_string(),
+            "parent": e.parent.to_string(),
+            "outcome": format!("{:?}", e.outcome),
+        }),
+        FriendBetsEvent::BetPlaced(e) => json!({
+            "market": e.market.to_string(),
+            "user": e.user.to_string(),
+            "side": format!("{:?}", e.side),
+            "amount": e.amount,
+        }),
+        FriendBetsEvent::LargeBetPlaced(e) => json!({
+            "market": e.market.to_string(),
+            "user": e.user.to_string(),
+            "side": format!("{:?}", e.side),
+            "amount": e.amount,
+            "staked_a": e.staked_a,
+            "staked_b": e.staked_b,
+        }),
+        FriendBetsEvent::LargeBetThresholdSet(e) => json!({
+            "market": e.market.to_string(),
+            "threshold": e.threshold,
+        }),
+        FriendBetsEvent::StakeCapsSet(e) => json!({
+            "market": e.market.to_string(),
+            "cap_a": e.cap_a,
+            "cap_b": e.cap_b,
+            "max_ratio_bps": e.max_ratio_bps,
+        }),
+        FriendBetsEvent::BetReceipt(e) => json!({
+            "market": e.market.to_string(),
+            "user": e.user.to_string(),
+            "side": format!("{:?}", e.side),
+            "amount": e.amount,
+            "odds_bps": e.odds_bps,
+            "locked_payout": e.locked_payout,
+        }),
+        FriendBetsEvent::CashedOut(e) => json!({
+            "market": e.market.to_string(),
+            "user": e.user.to_string(),
+            "side": format!("{:?}", e.side),
+            "stake": e.stake,
+            "payout": e.payout,
+        }),
+        FriendBetsEvent::CashOutOrderSet(e) => json!({
+            "market": e.market.to_string(),
+            "user": e.user.to_string(),
+            "min_value": e.min_value,
+        }),
+        FriendBetsEvent::CashOutOrderExecuted(e) => json!({
+            "market": e.market.to_string(),
+            "user": e.user.to_string(),
+            "executor": e.executor.to_string(),
+            "min_value": e.min_value,
+            "payout": e.payout,
+            "tip": e.tip,
+        }),
+        FriendBetsEvent::BettingClosed(e) => json!({ "market": e.market.to_string() }),
+        FriendBetsEvent::Resolved(e) => json!({
+            "market": e.market.to_string(),
+            "outcome": format!("{:?}", e.outcome),
+        }),
+        FriendBetsEvent::Cancelled(e) => json!({ "market": e.market.to_string() }),
+        FriendBetsEvent::Claimed(e) => json!({
+            "market": e.market.to_string(),
+            "user": e.user.to_string(),
+            "amount": e.amount,
+        }),
+        FriendBetsEvent::CreatorFeeWithdrawn(e) => json!({
+            "market": e.market.to_string(),
+            "creator": e.creator.to_string(),
+            "amount": e.amount,
+        }),
+        FriendBetsEvent::FeeSplitsSet(e) => json!({
+            "market": e.market.to_string(),
+            "splits": fee_splits_json(&e.splits),
+        }),
+        FriendBetsEvent::FeeSplitPaid(e) => json!({
+            "market": e.market.to_string(),
+            "recipient": e.recipient.to_string(),
+            "amount": e.amount,
+        }),
+        FriendBetsEvent::FeeBurned(e) => json!({
+            "market": e.market.to_string(),
+            "mint": e.mint.to_string(),
+            "amount": e.amount,
+        }),
+        FriendBetsEvent::InsuranceCollected(e) => json!({
+            "market": e.market.to_string(),
+            "mint": e.mint.to_string(),
+            "amount": e.amount,
+        }),
+        FriendBetsEvent::Compensated(e) => json!({
+            "market": e.market.to_string(),
+            "recipient": e.recipient.to_string(),
+            "amount": e.amount,
+        }),
+        FriendBetsEvent::CharitySet(e) => json!({
+            "market": e.market.to_string(),
+            "charity": e.charity.map(|charity| charity.to_string()),
+            "charity_bps": e.charity_bps,
+        }),
+        FriendBetsEvent::CharityDonated(e) => json!({
+            "market": e.market.to_string(),
+            "charity": e.charity.to_string(),
+            "amount": e.amount,
+        }),
+        FriendBetsEvent::FeeRebated(e) => json!({
+            "market": e.market.to_string(),
+            "user": e.user.to_string(),
+            "discounted_stake": e.discounted_stake,
+            "amount": e.amount,
+        }),
+        FriendBetsEvent::ConfigUpdated(e) => json!({
+            "admin": e.admin.to_string(),
+            "unbonding_secs": e.unbonding_secs,
+            "fee_tiers": fee_tiers_json(&e.fee_tiers),
+            "policy": policy_json(&e.policy),
+            "update_delay_secs": e.update_delay_secs,
+        }),
+        FriendBetsEvent::ConfigUpdateProposed(e) => json!({
+            "admin": e.admin.to_string(),
+            "ready_ts": e.ready_ts,
+            "update": {
+                "admin": e.update.admin.to_string(),
+                "unbonding_secs": e.update.unbonding_secs,
+                "fee_tiers": fee_tiers_json(&e.update.fee_tiers),
+                "policy": policy_json(&e.update.policy),
+                "update_delay_secs": e.update.update_delay_secs,
+            },
+        }),
+        FriendBetsEvent::ConfigUpdateCancelled(e) => json!({
+            "admin": e.admin.to_string(),
+        }),
+        FriendBetsEvent::AdminActionLogged(e) => json!({
+            "sequence": e.sequence,
+            "admin": e.admin.to_string(),
+            "timestamp": e.timestamp,
+            "action": admin_action_json(&e.action),
+            "log_hash": hex(&e.log_hash),
+        }),
+        FriendBetsEvent::Staked(e) => json!({
+            "user": e.user.to_string(),
+            "amount": e.amount,
+            "total": e.total,
+        }),
+        FriendBetsEvent::UnstakeRequested(e) => json!({
+            "user": e.user.to_string(),
+            "amount": e.amount,
+            "unbonding_end_ts": e.unbonding_end_ts,
+        }),
+        FriendBetsEvent::StakeWithdrawn(e) => json!({
+            "user": e.user.to_string(),
+            "amount": e.amount,
+        }),
+        FriendBetsEvent::MarketCollected(e) => json!({
+            "market": e.market.to_string(),
+            "caller": e.caller.to_string(),
+            "positions_closed": e.positions_closed,
+            "caller_share": e.caller_share,
+            "creator_share": e.creator_share,
+        }),
+        FriendBetsEvent::PositionsCompressed(e) => json!({
+            "market": e.market.to_string(),
+            "position_tree": e.position_tree.to_string(),
+        }),
+        FriendBetsEvent::CompressedBetPlaced(e) => json!({
+            "market": e.market.to_string(),
+            "user": e.user.to_string(),
+            "side": format!("{:?}", e.side),
+            "amount": e.amount,
+            "locked_payout": e.locked_payout,
+            "discounted_stake": e.discounted_stake,
+            "leaf_index": e.leaf_index,
+        }),
+        FriendBetsEvent::CompressedPositionClaimed(e) => json!({
+            "market": e.market.to_string(),
+            "user": e.user.to_string(),
+            "leaf_index": e.leaf_index,
+        }),
+        FriendBetsEvent::BettorPagesEnabled(e) => json!({ "market": e.market.to_string() }),
+        FriendBetsEvent::BettorPageClosed(e) => json!({
+            "market": e.market.to_string(),
+            "index": e.index,
+            "payer": e.payer.to_string(),
+        }),
+        FriendBetsEvent::ActivityStatsEnabled(e) => json!({ "market": e.market.to_string() }),
+        FriendBetsEvent::ActivityStatsClosed(e) => json!({
+            "market": e.market.to_string(),
+            "payer": e.payer.to_string(),
+        }),
+        FriendBetsEvent::MarketTitleRegistered(e) => json!({
+            "market": e.market.to_string(),
+            "title_hash": hex(&e.title_hash),
+            "nonce": e.nonce,
+        }),
+        FriendBetsEvent::MarketTitleClosed(e) => json!({
+            "market": e.market.to_string(),
+            "title_hash": hex(&e.title_hash),
+            "nonce": e.nonce,
+        }),
+        FriendBetsEvent::BetScheduled(e) => json!({
+            "market": e.market.to_string(),
+            "scheduled_bet": e.scheduled_bet.to_string(),
+            "owner": e.owner.to_string(),
+            "side": format!("{:?}", e.side),
+            "amount": e.amount,
+            "execute_after": e.execute_after,
+            "expiry": e.expiry,
+        }),
+        FriendBetsEvent::ScheduledBetExecuted(e) => json!({
+            "market": e.market.to_string(),
+            "scheduled_bet": e.scheduled_bet.to_string(),
+            "owner": e.owner.to_string(),
+            "executor": e.executor.to_string(),
+        }),
+        FriendBetsEvent::ScheduledBetCancelled(e) => json!({
+            "market": e.market.to_string(),
+            "scheduled_bet": e.scheduled_bet.to_string(),
+            "owner": e.owner.to_string(),
+            "amount": e.amount,
+        }),
+        FriendBetsEvent::PortfolioOpened(e) => json!({
+            "owner": e.owner.to_string(),
+            "portfolio": e.portfolio.to_string(),
+        }),
+        FriendBetsEvent::PortfolioPageClosed(e) => json!({
+            "owner": e.owner.to_string(),
+            "index": e.index,
+        }),
+        FriendBetsEvent::PortfolioClosed(e) => json!({
+            "owner": e.owner.to_string(),
+            "portfolio": e.portfolio.to_string(),
+        }),
+        FriendBetsEvent::MarketCompacted(e) => json!({
+            "market": e.market.to_string(),
+            "refund": e.refund,
+        }),
+        FriendBetsEvent::Unknown {
+            discriminator,
+            data,
+        } => json!({
+            "discriminator": discriminator,
+            "data": data,
+        }),
+    }
+}
+
+/// What a logged admin action did, amounts formatted with `decimals`.
+pub fn describe_admin_action(action: &AdminAction, decimals: Option<u8>) -> String {
+    let settings = |config: &ConfigUpdate| {
+        format!(
+            "admin {}, {} fee tiers, {}s unbonding, fees {} bps by default up to {} bps, {}s delay",
+            config.admin,
+            config.fee_tiers.len(),
+            config.unbonding_secs,
+            config.policy.default_fee_bps,
+            config.policy.max_fee_bps,
+            config.update_delay_secs
+        )
+    };
+    match action {
+        AdminAction::ConfigInitialized { config } => {
+            format!("created the config: {}", settings(config))
+        }
+        AdminAction::ConfigUpdateProposed { update, ready_ts } => format!(
+            "proposed a config update, applicable {}: {}",
+            format_time(*ready_ts),
+            settings(update)
+        ),
+        AdminAction::ConfigUpdateApplied { config } => {
+            format!("config update applied: {}", settings(config))
+        }
+        AdminAction::ConfigUpdateCancelled => "cancelled the config update".into(),
+        AdminAction::Compensated {
+            market,
+            recipient,
+            amount,
+        } => format!(
+            "paid {} of {market}'s insurance to {recipient}",
+            decimals.map_or_else(|| amount.to_string(), |d| format_amount(*amount, d))
+        ),
+        AdminAction::MarketForked { original, market } => {
+            format!("cancelled {original}, forking it into {market}")
+        }
+    }
+}
+
+pub fn admin_action_json(action: &AdminAction) -> Value {
+    let settings = |config: &ConfigUpdate| {
+        json!({
+            "admin": config.admin.to_string(),
+            "unbonding_secs": config.unbonding_secs,
+            "fee_tiers": fee_tiers_json(&config.fee_tiers),
+            "policy": policy_json(&config.policy),
+            "update_delay_secs": config.update_delay_secs,
+        })
+    };
+    match action {
+        AdminAction::ConfigInitialized { config } => json!({
+            "kind": "ConfigInitialized",
+            "config": settings(config),
+        }),
+        AdminAction::ConfigUpdateProposed { update, ready_ts } => json!({
+            "kind": "ConfigUpdateProposed",
+            "update": settings(update),
+            "ready_ts": ready_ts,
+        }),
+        AdminAction::ConfigUpdateApplied { config } => json!({
+            "kind": "ConfigUpdateApplied",
+            "config": settings(config),
+        }),
+        AdminAction::ConfigUpdateCancelled => json!({ "kind": "ConfigUpdateCancelled" }),
+        AdminAction::Compensated {
+            market,
+            recipient,
+            amount,
+        } => json!({
+            "kind": "Compensated",
+            "market": market.to_string(),
+            "recipient": recipient.to_string(),
+            "amount": amount,
+        }),
+        AdminAction::MarketForked { original, market } => json!({
+            "kind": "MarketForked",
+            "original": original.to_string(),
+            "market": market.to_string(),
+        }),
+    }
+}
+
+fn fee_tiers_json(fee_tiers: &[FeeTier]) -> Value {
+    fee_tiers
+        .iter()
+        .map(|tier| {
+            json!({
+                "min_stake": tier.min_stake,
+                "discount_bps": tier.discount_bps,
+                "max_fee_bps": tier.max_fee_bps,
+            })
+        })
+        .collect()
+}
+
+fn fee_splits_json(splits: &[FeeSplit]) -> Value {
+    splits
+        .iter()
+        .map(|split| {
+            json!({
+                "recipient": split.recipient.to_string(),
+                "bps": split.bps,
+            })
+        })
+        .collect()
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn policy_json(policy: &MarketPolicy) -> Value {
+    json!({
+        "default_fee_bps": policy.default_fee_bps,
+        "max_fee_bps": policy.max_fee_bps,
+        "default_betting_secs": policy.default_betting_secs,
+        "default_resolve_secs": policy.default_resolve_secs,
+        "max_title_len": policy.max_title_len,
+        "insurance_bps": policy.insurance_bps,
+    })
+}
@@ -0,0 +1,11 @@
+// Generates `bets.v1` from the shared protos: the server, and the client the
+// tests call it with. `protoc` is vendored, so building needs nothing
+// installed.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::configure().compile_protos(
+        &["../../packages/proto/bets/v1/market.proto"],
+        &["../../packages/proto"],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! Read access to the indexer's database through the `postgres` client, as
+//! the indexer has. Queries have Postgres build the JSON, so rows come back
+//! as one document, and take their inputs as parameters.
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::{Context, Result};
+use postgres::types::ToSql;
+use postgres::Client;
+use rustls::ClientConfig;
+use serde_json::Value;
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+/// A connection made on first use, and again after it drops.
+pub struct Database {
+    url: String,
+    client: Mutex<Option<Client>>,
+}
+
+impl Database {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: Mutex::new(None),
+        }
+    }
+
+    /// Runs a query selecting a single JSON value; null when there's no
+    /// row.
+    pub fn json(&self, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Value> {
+        let mut client = self.client.lock().unwrap_or_else(PoisonError::into_inner);
+        if client.as_ref().is_none_or(Client::is_closed) {
+            *client = Some(connect(&self.url)?);
+        }
+        let row = client.as_mut().expect("connected").query_opt(sql, params)?;
+        Ok(row
+            .map(|row| row.try_get::<_, Option<Value>>(0))
+            .transpose()?
+            .flatten()
+            .unwrap_or_default())
+    }
+}
+
+/// Connects to `url`, over TLS when the server supports it and `sslmode`
+/// allows, as `psql` would. Certificates are checked against the system's
+/// roots.
+fn connect(url: &str) -> Result<Client> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    Client::connect(url, MakeRustlsConnect::new(config)).context("connecting to Postgres")
+}
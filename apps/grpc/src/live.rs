@@ -0,0 +1,109 @@
+//! The feed behind `WatchEvents` and `WatchMarkets`.
+//!
+//! A thread follows the indexer's `events` table, as the REST API's `/ws`
+//! does, and broadcasts each new event along with its market's row, pools
+//! as they stood just after the event. Each stream forwards the updates for
+//! the markets it asked for.
+//!
+//! Events are sent as soon as they're indexed, usually at `confirmed`. One
+//! whose fork is later dropped isn't retracted, and events the indexer
+//! replays after a rollback are sent again.
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::db::Database;
+use crate::service::MARKET_COLUMNS;
+
+/// How often the events table is checked for new rows.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Most events read per check.
+const BATCH: u32 = 500;
+/// Updates a stream can fall behind by before it's ended as too slow.
+pub const QUEUE: usize = 1024;
+
+/// An indexed event and the market it belongs to, as rows.
+pub struct Update {
+    pub market: String,
+    pub event: Value,
+    pub market_row: Value,
+}
+
+pub struct Feed {
+    db: Arc<Database>,
+    sender: broadcast::Sender<Arc<Update>>,
+}
+
+impl Feed {
+    pub fn new(db: Arc<Database>) -> Self {
+        let (sender, _) = broadcast::channel(QUEUE);
+        Self { db, sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Update>> {
+        self.sender.subscribe()
+    }
+
+    /// Broadcasts new events, forever. Starts from the newest event, as
+    /// clients read current state with the unary calls.
+    pub fn follow(&self) {
+        let mut last = None;
+        loop {
+            if let Err(err) = self.poll(&mut last) {
+                eprintln!("warning: following events: {err:#}");
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn poll(&self, last: &mut Option<i64>) -> Result<()> {
+        let Some(after) = *last else {
+            let newest = self
+                .db
+                .json("SELECT to_json(COALESCE(max(id), 0)) FROM events", &[])?;
+            *last = Some(newest.as_i64().context("expected an event id")?);
+            return Ok(());
+        };
+        // The market's row with the pools the event left
+        let rows = self.db.json(
+            &format!(
+                "SELECT COALESCE(json_agg(json_build_object('id', e.id, 'market', e.market, \
+             'event', json_build_object('id', e.id, 'name', e.name, 'data', e.data, \
+             'signature', e.signature, \
+             'timestamp', COALESCE(extract(epoch FROM e.block_time)::bigint, 0)), \
+             'market_row', (SELECT to_jsonb(m) || jsonb_build_object('staked_a', \
+             e.staked_a::text, 'staked_b', e.staked_b::text) \
+             FROM (SELECT {MARKET_COLUMNS} FROM markets WHERE address = e.market) m)) \
+             ORDER BY e.id), '[]') \
+             FROM (SELECT * FROM events WHERE id > $1 ORDER BY id LIMIT {BATCH}) e"
+            ),
+            &[&after],
+        )?;
+        let Value::Array(rows) = rows else {
+            bail!("expected rows");
+        };
+        for mut row in rows {
+            *last = Some(row["id"].as_i64().context("expected an event id")?);
+            let Some(market) = row["market"].as_str().map(String::from) else {
+                continue;
+            };
+            self.publish(Update {
+                market,
+                event: row["event"].take(),
+                market_row: row["market_row"].take(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Sends `update` to every stream following its market.
+    pub fn publish(&self, update: Update) {
+        // Nobody listening isn't an error
+        let _ = self.sender.send(Arc::new(update));
+    }
+}
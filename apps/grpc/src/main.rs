@@ -0,0 +1,124 @@
+//! `friend-bets-grpc`: the `bets.v1.BetsService` gRPC API, serving the
+//! indexer's tables alongside the REST API, streaming market updates as
+//! they're indexed and building market, bet, resolve and claim
+//! transactions, so clients in other languages get them from the generated
+//! stubs instead of reimplementing the SDK.
+
+mod db;
+mod live;
+mod rpc;
+mod service;
+
+// Generated from the protos by build.rs, helpers this server doesn't need
+// included
+#[allow(dead_code)]
+mod proto {
+    tonic::include_proto!("bets.v1");
+}
+
+use std::collections::HashMap;
+use std::env;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::{anyhow, bail, Context, Result};
+use tonic::transport::Server;
+
+use crate::db::Database;
+use crate::live::Feed;
+use crate::proto::bets_service_server::BetsServiceServer;
+use crate::rpc::RpcClient;
+use crate::service::Service;
+
+const USAGE: &str = "\
+Usage: friend-bets-grpc [OPTIONS]
+
+Serves bets.v1.BetsService from packages/proto/bets/v1/market.proto:
+  ListMarkets, GetMarket, GetPosition, GetUserPositions
+                              Read from the indexer's database
+  WatchEvents, WatchMarkets   Stream each event, or the market after it, as it's
+                              indexed; no market_ids follows every market
+  CreateMarket, PlaceBet, Resolve, Claim
+                              Answer an unsigned transaction for the creator,
+                              bettor or resolver to sign and send, base64 encoded;
+                              CreateMarket picks the market's id, so leave
+                              market_id empty
+
+Options:
+      --listen <ADDR>       Address to serve on, default 127.0.0.1:50051
+                            [env: FRIEND_BETS_GRPC_LISTEN]
+      --database-url <URL>  The indexer's Postgres connection URI [env: DATABASE_URL]
+      --rpc-url <URL>       RPC endpoint [env: FRIEND_BETS_RPC_URL]
+  -h, --help                Print this help
+";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let mut options = HashMap::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            flag if flag.starts_with("--") => {
+                let (name, value) = match flag[2..].split_once('=') {
+                    Some((name, value)) => (name.to_string(), value.to_string()),
+                    None => (
+                        flag[2..].to_string(),
+                        args.next().ok_or_else(|| anyhow!("{flag} needs a value"))?,
+                    ),
+                };
+                options.insert(name, value);
+            }
+            _ => bail!("unexpected argument {arg:?}"),
+        }
+    }
+    let mut option = |name: &str, var: &str| options.remove(name).or_else(|| env::var(var).ok());
+
+    let database_url = option("database-url", "DATABASE_URL")
+        .ok_or_else(|| anyhow!("set --database-url or DATABASE_URL"))?;
+    let rpc_url = option("rpc-url", "FRIEND_BETS_RPC_URL")
+        .ok_or_else(|| anyhow!("set --rpc-url or FRIEND_BETS_RPC_URL"))?;
+    let listen =
+        option("listen", "FRIEND_BETS_GRPC_LISTEN").unwrap_or_else(|| "127.0.0.1:50051".into());
+    let address: SocketAddr = listen
+        .to_socket_addrs()
+        .with_context(|| format!("resolving {listen}"))?
+        .next()
+        .ok_or_else(|| anyhow!("{listen} resolves to no address"))?;
+
+    // The feed polls on a connection of its own
+    let feed = Arc::new(Feed::new(Arc::new(Database::new(database_url.clone()))));
+    {
+        let feed = Arc::clone(&feed);
+        thread::spawn(move || feed.follow());
+    }
+    // Held here so the blocking Postgres and RPC clients outlive the
+    // runtime, which they can't be dropped inside
+    let db = Arc::new(Database::new(database_url));
+    let rpc = Arc::new(RpcClient::new(rpc_url));
+    let service = Service::new(Arc::clone(&db), Arc::clone(&rpc), feed);
+
+    let runtime = tokio::runtime::Runtime::new().context("starting the runtime")?;
+    eprintln!("Listening on {address}");
+    runtime
+        .block_on(
+            Server::builder()
+                .add_service(BetsServiceServer::new(service))
+                .serve(address),
+        )
+        .with_context(|| format!("serving on {address}"))
+}
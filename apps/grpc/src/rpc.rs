@@ -0,0 +1,92 @@
+//! Minimal blocking JSON-RPC client for the reads building a transaction
+//! needs.
+//!
+//! Requests go through `reqwest`'s blocking client, as in the CLI and the
+//! Actions server. Everything is read at `confirmed`.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::AccountDeserialize;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+
+const COMMITMENT: &str = "confirmed";
+
+pub struct RpcClient {
+    http: Client,
+    url: String,
+}
+
+impl RpcClient {
+    pub fn new(url: String) -> Self {
+        Self {
+            http: Client::new(),
+            url,
+        }
+    }
+
+    pub fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response = self
+            .http
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .with_context(|| method.to_string())?;
+        let status = response.status();
+        let text = response.text().with_context(|| method.to_string())?;
+
+        let mut response: Value = match serde_json::from_str(&text) {
+            Ok(response) => response,
+            Err(_) if !status.is_success() => bail!("{method}: HTTP {status}"),
+            Err(_) => bail!("{method}: invalid response"),
+        };
+        if let Some(error) = response.get("error") {
+            bail!(
+                "{method}: {}",
+                error["message"].as_str().unwrap_or("rpc error")
+            );
+        }
+        // An HTTP error without a JSON-RPC one, such as a gateway's
+        if !status.is_success() {
+            bail!("{method}: HTTP {status}");
+        }
+        Ok(response["result"].take())
+    }
+
+    pub fn get_account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let result = self.call(
+            "getAccountInfo",
+            json!([address.to_string(), { "commitment": COMMITMENT, "encoding": "base64" }]),
+        )?;
+        let value = &result["value"];
+        if value.is_null() {
+            return Ok(None);
+        }
+        let encoded = value["data"][0]
+            .as_str()
+            .ok_or_else(|| anyhow!("account data isn't base64"))?;
+        Ok(Some(STANDARD.decode(encoded)?))
+    }
+
+    pub fn optional_account<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<Option<T>> {
+        self.get_account_data(address)?
+            .map(|data| {
+                T::try_deserialize(&mut data.as_slice())
+                    .with_context(|| format!("decoding {address}"))
+            })
+            .transpose()
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<Hash> {
+        let result = self.call("getLatestBlockhash", json!([{ "commitment": COMMITMENT }]))?;
+        result["value"]["blockhash"]
+            .as_str()
+            .ok_or_else(|| anyhow!("getLatestBlockhash: missing blockhash"))?
+            .parse()
+            .map_err(|_| anyhow!("getLatestBlockhash: invalid blockhash"))
+    }
+}
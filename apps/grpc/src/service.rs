@@ -0,0 +1,825 @@
+//! `bets.v1.BetsService`.
+//!
+//! Reads come from the indexer's tables, like the REST API's, with lists
+//! paged by `page_size` (default 50, at most 200) and an opaque
+//! `page_token`. `Market.vault` is left empty, as each side has its own.
+//!
+//! `CreateMarket`, `PlaceBet`, `Resolve` and `Claim` read what they need
+//! from the RPC, so they work before the indexer catches up, and answer an
+//! unsigned legacy transaction for the wallet in the request to sign and
+//! pay for, base64 encoded. Markets and bets pass a staker's stake account,
+//! for the creator's fee ceiling and the bettor's discount, and bets and
+//! claims record to the wallet's portfolio when it has one.
+//!
+//! `CreateMarketRequest.market_id` is left empty: the server picks the id
+//! the market's address derives from, and answers the address.
+
+// Handlers answer `Status`, which is large, and helpers return it to be
+// passed straight on
+#![allow(clippy::result_large_err)]
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use friend_bets_sdk::instructions::NewMarket;
+use friend_bets_sdk::offline::PartiallySignedTransaction;
+use friend_bets_sdk::pda::{portfolio_address, position_address, stake_address};
+use friend_bets_sdk::simulate::simulate_claim;
+use friend_bets_sdk::{
+    instructions, BetSide, Market, MarketOptions, MarketStatus, Portfolio, Position, StakeAccount,
+};
+use serde_json::Value;
+use solana_message::{Message, VersionedMessage};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::db::Database;
+use crate::live::{Feed, Update, QUEUE};
+use crate::proto::bets_service_server::BetsService;
+use crate::proto::{self, *};
+use crate::rpc::RpcClient;
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+/// Most markets one stream can follow; none follows every market.
+const MAX_WATCHED: usize = 100;
+
+/// A market row as [`market`] reads it.
+pub const MARKET_COLUMNS: &str = "address, creator, COALESCE(mint, '') AS mint, title, fee_bps, \
+    extract(epoch FROM end_ts)::bigint AS end_ts, \
+    extract(epoch FROM resolve_deadline_ts)::bigint AS resolve_deadline_ts, status, outcome, \
+    staked_a::text AS staked_a, staked_b::text AS staked_b, creator_fee_withdrawn, \
+    COALESCE(extract(epoch FROM created_at)::bigint, 0) AS created_at";
+
+/// A position row as [`position`] reads it, opened at its first bet.
+const POSITION_COLUMNS: &str = "p.address, p.market, p.owner, p.side, \
+    p.amount::text AS amount, p.claimed, \
+    COALESCE((SELECT extract(epoch FROM min(b.block_time))::bigint FROM bets b \
+    WHERE b.market = p.market AND b.owner = p.owner AND b.kind = 'bet'), 0) AS created_at";
+
+type Stream<T> = ReceiverStream<Result<T, Status>>;
+
+pub struct Service {
+    db: Arc<Database>,
+    rpc: Arc<RpcClient>,
+    feed: Arc<Feed>,
+}
+
+impl Service {
+    pub fn new(db: Arc<Database>, rpc: Arc<RpcClient>, feed: Arc<Feed>) -> Self {
+        Self { db, rpc, feed }
+    }
+
+    /// Runs a query on a blocking thread, as the `postgres` client blocks.
+    /// `params` fill its `$1`, `$2`, ...
+    async fn query(&self, sql: String, params: Vec<String>) -> Result<Value, Status> {
+        let db = Arc::clone(&self.db);
+        blocking(move || {
+            let params: Vec<_> = params.iter().map(|param| param as _).collect();
+            db.json(&sql, &params).map_err(internal)
+        })
+        .await
+    }
+
+    /// A page of `select`'s rows, with the token for the next page.
+    async fn page(
+        &self,
+        select: &str,
+        params: Vec<String>,
+        page_size: u32,
+        page_token: &str,
+    ) -> Result<(Vec<Value>, String), Status> {
+        let limit = match page_size {
+            0 => DEFAULT_PAGE_SIZE,
+            size => size.min(MAX_PAGE_SIZE),
+        };
+        let offset: u64 = match page_token {
+            "" => 0,
+            token => token
+                .parse()
+                .map_err(|_| Status::invalid_argument("invalid page_token"))?,
+        };
+        // One extra row says whether there's another page
+        let rows = self
+            .query(
+                format!(
+                    "SELECT COALESCE(json_agg(page), '[]') FROM ({select} LIMIT {} OFFSET {offset}) page",
+                    limit + 1
+                ),
+                params,
+            )
+            .await?;
+        let Value::Array(mut rows) = rows else {
+            return Err(Status::internal("expected rows"));
+        };
+        let next = if rows.len() > limit as usize {
+            (offset + u64::from(limit)).to_string()
+        } else {
+            String::new()
+        };
+        rows.truncate(limit as usize);
+        Ok((rows, next))
+    }
+
+    /// Streams `message` for each update to one of `market_ids`.
+    fn watch<T: Send + 'static>(
+        &self,
+        market_ids: Vec<String>,
+        message: fn(&Update) -> Result<T, Status>,
+    ) -> Result<Stream<T>, Status> {
+        if market_ids.len() > MAX_WATCHED {
+            return Err(Status::invalid_argument(format!(
+                "at most {MAX_WATCHED} markets can be watched"
+            )));
+        }
+        for market in &market_ids {
+            address(market)?;
+        }
+        let mut updates = self.feed.subscribe();
+        let (sender, receiver) = mpsc::channel(QUEUE);
+        tokio::spawn(async move {
+            loop {
+                let update = match updates.recv().await {
+                    Ok(update) => update,
+                    Err(RecvError::Lagged(_)) => {
+                        let _ = sender
+                            .send(Err(Status::resource_exhausted(
+                                "the stream fell behind; reconnect",
+                            )))
+                            .await;
+                        return;
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+                if !market_ids.is_empty() && !market_ids.contains(&update.market) {
+                    continue;
+                }
+                // The client has gone once nobody receives
+                if sender.send(message(&update)).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(ReceiverStream::new(receiver))
+    }
+
+    /// The unsigned transaction `payer` signs to run `ix`, base64 encoded.
+    async fn transaction(&self, payer: Pubkey, ix: Instruction) -> Result<String, Status> {
+        let rpc = Arc::clone(&self.rpc);
+        let blockhash = blocking(move || rpc.get_latest_blockhash().map_err(internal)).await?;
+        let tx = PartiallySignedTransaction::new(VersionedMessage::Legacy(
+            Message::new_with_blockhash(&[ix], Some(&payer), &blockhash),
+        ));
+        Ok(tx.to_base64())
+    }
+
+    /// The market at `address`, from the RPC.
+    async fn load_market(&self, address: Pubkey) -> Result<Market, Status> {
+        let rpc = Arc::clone(&self.rpc);
+        blocking(move || {
+            rpc.optional_account(&address)
+                .map_err(internal)?
+                .ok_or_else(|| Status::not_found(format!("no market {address}")))
+        })
+        .await
+    }
+
+    /// The market at `address` and `owner`'s position in it, from the RPC.
+    async fn load(
+        &self,
+        address: Pubkey,
+        owner: Pubkey,
+    ) -> Result<(Market, Option<Position>), Status> {
+        let rpc = Arc::clone(&self.rpc);
+        blocking(move || {
+            let market: Market = rpc
+                .optional_account(&address)
+                .map_err(internal)?
+                .ok_or_else(|| Status::not_found(format!("no market {address}")))?;
+            let position = rpc
+                .optional_account(&position_address(&address, &owner).0)
+                .map_err(internal)?;
+            Ok((market, position))
+        })
+        .await
+    }
+
+    /// `ix` with `creator`'s stake account, when they have one.
+    async fn with_creator_stake(
+        &self,
+        creator: Pubkey,
+        ix: Instruction,
+    ) -> Result<Instruction, Status> {
+        let rpc = Arc::clone(&self.rpc);
+        let staked = blocking(move || {
+            rpc.optional_account::<StakeAccount>(&stake_address(&creator).0)
+                .map_err(internal)
+        })
+        .await?
+        .is_some();
+        Ok(if staked {
+            instructions::with_creator_stake(ix, &creator)
+        } else {
+            ix
+        })
+    }
+
+    /// `ix` with `owner`'s stake account, when `stake` and they have one,
+    /// and their portfolio, when they've opened one.
+    async fn with_owner_accounts(
+        &self,
+        owner: Pubkey,
+        ix: Instruction,
+        stake: bool,
+    ) -> Result<Instruction, Status> {
+        let rpc = Arc::clone(&self.rpc);
+        blocking(move || {
+            let mut ix = ix;
+            let staker = stake
+                && rpc
+                    .optional_account::<StakeAccount>(&stake_address(&owner).0)
+                    .map_err(internal)?
+                    .is_some();
+            if staker {
+                ix = instructions::with_bettor_stake(ix, &owner);
+            }
+            let portfolio: Option<Portfolio> = rpc
+                .optional_account(&portfolio_address(&owner).0)
+                .map_err(internal)?;
+            if let Some(portfolio) = portfolio {
+                ix = instructions::with_portfolio(ix, &portfolio);
+            }
+            Ok(ix)
+        })
+        .await
+    }
+}
+
+#[tonic::async_trait]
+impl BetsService for Service {
+    async fn list_markets(
+        &self,
+        request: Request<ListMarketsRequest>,
+    ) -> Result<Response<ListMarketsResponse>, Status> {
+        let request = request.into_inner();
+        let (mut filters, mut params) = (Vec::new(), Vec::new());
+        if !request.title_filter.is_empty() {
+            params.push(format!("%{}%", like_escape(&request.title_filter)));
+            filters.push(format!("title ILIKE ${}", params.len()));
+        }
+        if let Some(status) = status_name(request.status_filter()) {
+            params.push(status.to_string());
+            filters.push(format!("status = ${}", params.len()));
+        }
+        let (rows, next_page_token) = self
+            .page(
+                &format!(
+                    "SELECT {MARKET_COLUMNS} FROM markets{} ORDER BY created_slot DESC, address",
+                    where_clause(&filters)
+                ),
+                params,
+                request.page_size,
+                &request.page_token,
+            )
+            .await?;
+        Ok(Response::new(ListMarketsResponse {
+            markets: rows.iter().map(market).collect::<Result<_, _>>()?,
+            next_page_token,
+        }))
+    }
+
+    async fn get_market(
+        &self,
+        request: Request<GetMarketRequest>,
+    ) -> Result<Response<GetMarketResponse>, Status> {
+        let id = request.into_inner().market_id;
+        address(&id)?;
+        let row = self
+            .query(
+                format!(
+                    "SELECT row_to_json(m) FROM (SELECT {MARKET_COLUMNS} FROM markets \
+                     WHERE address = $1) m"
+                ),
+                vec![id.clone()],
+            )
+            .await?;
+        if row.is_null() {
+            return Err(Status::not_found(format!("no market {id}")));
+        }
+        Ok(Response::new(GetMarketResponse {
+            market: Some(market(&row)?),
+        }))
+    }
+
+    async fn create_market(
+        &self,
+        request: Request<CreateMarketRequest>,
+    ) -> Result<Response<CreateMarketResponse>, Status> {
+        let request = request.into_inner();
+        let creator = required(&request.creator, "creator")?;
+        let mint = required(&request.mint, "mint")?;
+        if !request.market_id.is_empty() {
+            return Err(Status::invalid_argument(
+                "market_id is picked by the server; leave it empty",
+            ));
+        }
+        if request.title.trim().is_empty() {
+            return Err(Status::invalid_argument("title is required"));
+        }
+        let fee_bps = u16::try_from(request.fee_bps)
+            .map_err(|_| Status::invalid_argument("fee_bps is too large"))?;
+
+        // Ids only need to be unique per creator; the clock in milliseconds
+        // keeps two requests apart
+        let market_id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        let new_market = NewMarket {
+            creator,
+            mint,
+            market_id,
+        };
+        // Times of 0 take the config's defaults
+        let ix = instructions::initialize_market(
+            &new_market,
+            fee_bps,
+            request.end_ts,
+            request.resolve_deadline_ts,
+            request.title,
+            MarketOptions {
+                allow_cash_out: false,
+                cashout_haircut_bps: 0,
+                lock_odds: false,
+                burn_fee: false,
+            },
+        );
+        let ix = self.with_creator_stake(creator, ix).await?;
+        Ok(Response::new(CreateMarketResponse {
+            market_id: new_market.market().to_string(),
+            unsigned_tx_base64: self.transaction(creator, ix).await?,
+            signature: String::new(),
+        }))
+    }
+
+    async fn place_bet(
+        &self,
+        request: Request<PlaceBetRequest>,
+    ) -> Result<Response<PlaceBetResponse>, Status> {
+        let request = request.into_inner();
+        let address = address(&request.market_id)?;
+        let owner = required(&request.owner, "owner")?;
+        let side = bet_side(request.side(), "side")?;
+        if request.amount == 0 {
+            return Err(Status::invalid_argument("amount must be more than 0"));
+        }
+        let (market, _) = self.load(address, owner).await?;
+        if market.status != MarketStatus::Open || now() >= market.end_ts {
+            return Err(Status::failed_precondition(
+                "betting on this market has closed",
+            ));
+        }
+        market
+            .check_stake_caps(side, request.amount)
+            .map_err(|err| Status::failed_precondition(err.to_string()))?;
+
+        let ix = instructions::place_bet_on(
+            &owner,
+            &address,
+            &market,
+            side,
+            request.amount,
+            request.min_odds_bps,
+        );
+        let ix = self.with_owner_accounts(owner, ix, true).await?;
+        Ok(Response::new(PlaceBetResponse {
+            position_id: position_address(&address, &owner).0.to_string(),
+            unsigned_tx_base64: self.transaction(owner, ix).await?,
+            signature: String::new(),
+        }))
+    }
+
+    async fn resolve(
+        &self,
+        request: Request<ResolveRequest>,
+    ) -> Result<Response<ResolveResponse>, Status> {
+        let request = request.into_inner();
+        let address = address(&request.market_id)?;
+        let resolver = required(&request.resolver, "resolver")?;
+        let outcome = bet_side(request.outcome(), "outcome")?;
+        let market = self.load_market(address).await?;
+        if market.creator != resolver {
+            return Err(Status::permission_denied(
+                "only the market's creator resolves it",
+            ));
+        }
+        let now = now();
+        if now >= market.resolve_deadline_ts {
+            return Err(Status::failed_precondition(
+                "the resolve deadline has passed",
+            ));
+        }
+
+        let parent = market.dependency.map(|dependency| dependency.market);
+        let ix = match market.status {
+            MarketStatus::PendingResolve => {
+                instructions::resolve(&resolver, &address, outcome, parent.as_ref())
+            }
+            MarketStatus::Open if now >= market.end_ts => {
+                instructions::close_and_resolve(&resolver, &address, outcome, parent.as_ref())
+            }
+            MarketStatus::Open => {
+                return Err(Status::failed_precondition("betting hasn't closed yet"))
+            }
+            _ => return Err(Status::failed_precondition("this market is settled")),
+        };
+        Ok(Response::new(ResolveResponse {
+            unsigned_tx_base64: self.transaction(resolver, ix).await?,
+            signature: String::new(),
+        }))
+    }
+
+    async fn claim(
+        &self,
+        request: Request<ClaimRequest>,
+    ) -> Result<Response<ClaimResponse>, Status> {
+        let request = request.into_inner();
+        let address = address(&request.market_id)?;
+        let owner = required(&request.owner, "owner")?;
+        let (mut market, position) = self.load(address, owner).await?;
+        // Claiming cancels a market nobody resolved in time
+        market.cancel_if_expired(now());
+        if !matches!(
+            market.status,
+            MarketStatus::Resolved | MarketStatus::Cancelled
+        ) {
+            return Err(Status::failed_precondition(
+                "this market hasn't been resolved yet",
+            ));
+        }
+        let position = position
+            .ok_or_else(|| Status::failed_precondition(format!("{owner} has no bet here")))?;
+        if position.claimed {
+            return Err(Status::failed_precondition("already claimed"));
+        }
+        let payout_amount = simulate_claim(&market, &position)
+            .map_err(|err| internal(anyhow::anyhow!("{err}")))?
+            .total();
+        if payout_amount == 0 {
+            return Err(Status::failed_precondition(
+                "the bet lost; nothing to claim",
+            ));
+        }
+
+        let ix = instructions::claim(&owner, &address, &market.mint);
+        let ix = self.with_owner_accounts(owner, ix, false).await?;
+        Ok(Response::new(ClaimResponse {
+            payout_amount,
+            unsigned_tx_base64: self.transaction(owner, ix).await?,
+            signature: String::new(),
+        }))
+    }
+
+    async fn get_position(
+        &self,
+        request: Request<GetPositionRequest>,
+    ) -> Result<Response<GetPositionResponse>, Status> {
+        let request = request.into_inner();
+        address(&request.market_id)?;
+        required(&request.owner, "owner")?;
+        let row = self
+            .query(
+                format!(
+                    "SELECT row_to_json(r) FROM (SELECT {POSITION_COLUMNS} FROM positions p \
+                     WHERE p.market = $1 AND p.owner = $2) r"
+                ),
+                vec![request.market_id.clone(), request.owner.clone()],
+            )
+            .await?;
+        if row.is_null() {
+            return Err(Status::not_found(format!(
+                "{} has no position in {}",
+                request.owner, request.market_id
+            )));
+        }
+        Ok(Response::new(GetPositionResponse {
+            position: Some(position(&row)?),
+        }))
+    }
+
+    async fn get_user_positions(
+        &self,
+        request: Request<GetUserPositionsRequest>,
+    ) -> Result<Response<GetUserPositionsResponse>, Status> {
+        let request = request.into_inner();
+        required(&request.owner, "owner")?;
+        let (rows, next_page_token) = self
+            .page(
+                &format!(
+                    "SELECT {POSITION_COLUMNS} FROM positions p \
+                     JOIN markets m ON m.address = p.market WHERE p.owner = $1 \
+                     ORDER BY m.created_slot DESC, p.market"
+                ),
+                vec![request.owner.clone()],
+                request.page_size,
+                &request.page_token,
+            )
+            .await?;
+        Ok(Response::new(GetUserPositionsResponse {
+            positions: rows.iter().map(position).collect::<Result<_, _>>()?,
+            next_page_token,
+        }))
+    }
+
+    type WatchEventsStream = Stream<WatchEventsResponse>;
+
+    async fn watch_events(
+        &self,
+        request: Request<WatchEventsRequest>,
+    ) -> Result<Response<Self::WatchEventsStream>, Status> {
+        let stream = self.watch(request.into_inner().market_ids, |update| {
+            let event = &update.event;
+            Ok(WatchEventsResponse {
+                event: Some(MarketEvent {
+                    id: event["id"].to_string(),
+                    market_id: update.market.clone(),
+                    event_type: string(event, "name"),
+                    data: event["data"].to_string(),
+                    timestamp: event["timestamp"].as_i64().unwrap_or_default(),
+                    tx_signature: string(event, "signature"),
+                }),
+            })
+        })?;
+        Ok(Response::new(stream))
+    }
+
+    type WatchMarketsStream = Stream<WatchMarketsResponse>;
+
+    async fn watch_markets(
+        &self,
+        request: Request<WatchMarketsRequest>,
+    ) -> Result<Response<Self::WatchMarketsStream>, Status> {
+        let stream = self.watch(request.into_inner().market_ids, |update| {
+            Ok(WatchMarketsResponse {
+                market: Some(market(&update.market_row)?),
+            })
+        })?;
+        Ok(Response::new(stream))
+    }
+}
+
+async fn blocking<T: Send + 'static>(
+    run: impl FnOnce() -> Result<T, Status> + Send + 'static,
+) -> Result<T, Status> {
+    tokio::task::spawn_blocking(run)
+        .await
+        .map_err(|err| internal(err.into()))?
+}
+
+/// Logs `err` and answers a generic error, as the REST API does.
+fn internal(err: anyhow::Error) -> Status {
+    eprintln!("error: {err:#}");
+    Status::internal("internal error")
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+fn address(text: &str) -> Result<Pubkey, Status> {
+    text.parse()
+        .map_err(|_| Status::invalid_argument(format!("invalid address {text:?}")))
+}
+
+/// The address in the request's `field`, which must be set.
+fn required(text: &str, field: &str) -> Result<Pubkey, Status> {
+    if text.is_empty() {
+        return Err(Status::invalid_argument(format!("{field} is required")));
+    }
+    address(text)
+}
+
+fn bet_side(side: Side, field: &str) -> Result<BetSide, Status> {
+    match side {
+        Side::A => Ok(BetSide::A),
+        Side::B => Ok(BetSide::B),
+        Side::Unspecified => Err(Status::invalid_argument(format!(
+            "{field} must be SIDE_A or SIDE_B"
+        ))),
+    }
+}
+
+/// The indexer's name for `status`; `None` for any status.
+fn status_name(status: proto::MarketStatus) -> Option<&'static str> {
+    match status {
+        proto::MarketStatus::Unspecified => None,
+        proto::MarketStatus::Open => Some("open"),
+        proto::MarketStatus::PendingResolve => Some("pending_resolve"),
+        proto::MarketStatus::Resolved => Some("resolved"),
+        proto::MarketStatus::Cancelled => Some("cancelled"),
+        proto::MarketStatus::Collected => Some("collected"),
+    }
+}
+
+fn status(name: &str) -> proto::MarketStatus {
+    match name {
+        "open" => proto::MarketStatus::Open,
+        "pending_resolve" => proto::MarketStatus::PendingResolve,
+        "resolved" => proto::MarketStatus::Resolved,
+        "cancelled" => proto::MarketStatus::Cancelled,
+        "collected" => proto::MarketStatus::Collected,
+        _ => proto::MarketStatus::Unspecified,
+    }
+}
+
+fn side(name: &str) -> Side {
+    match name {
+        "A" => Side::A,
+        "B" => Side::B,
+        _ => Side::Unspecified,
+    }
+}
+
+fn market(row: &Value) -> Result<proto::Market, Status> {
+    Ok(proto::Market {
+        id: string(row, "address"),
+        creator: string(row, "creator"),
+        mint: string(row, "mint"),
+        vault: String::new(),
+        fee_bps: row["fee_bps"].as_u64().unwrap_or_default() as u32,
+        end_ts: row["end_ts"].as_i64().unwrap_or_default(),
+        resolve_deadline_ts: row["resolve_deadline_ts"].as_i64().unwrap_or_default(),
+        staked_a: amount(row, "staked_a")?,
+        staked_b: amount(row, "staked_b")?,
+        status: status(&string(row, "status")).into(),
+        outcome: side(&string(row, "outcome")).into(),
+        creator_fee_withdrawn: row["creator_fee_withdrawn"].as_bool().unwrap_or_default(),
+        title: string(row, "title"),
+        created_at: row["created_at"].as_i64().unwrap_or_default(),
+    })
+}
+
+fn position(row: &Value) -> Result<proto::Position, Status> {
+    Ok(proto::Position {
+        id: string(row, "address"),
+        market_id: string(row, "market"),
+        owner: string(row, "owner"),
+        side: side(&string(row, "side")).into(),
+        amount: amount(row, "amount")?,
+        claimed: row["claimed"].as_bool().unwrap_or_default(),
+        created_at: row["created_at"].as_i64().unwrap_or_default(),
+    })
+}
+
+fn string(row: &Value, key: &str) -> String {
+    row[key].as_str().unwrap_or_default().to_string()
+}
+
+/// A token amount, which the queries select as text.
+fn amount(row: &Value, key: &str) -> Result<u64, Status> {
+    string(row, key)
+        .parse()
+        .map_err(|_| internal(anyhow::anyhow!("{key} isn't an amount: {}", row[key])))
+}
+
+/// `text` matched literally by `LIKE`.
+/// `text` matched literally in a `LIKE` pattern. Postgres text can't hold
+/// NUL, so it's dropped.
+fn like_escape(text: &str) -> String {
+    text.replace('\0', "")
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn where_clause(filters: &[String]) -> String {
+    if filters.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", filters.join(" AND "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+    use tonic::Code;
+
+    use super::*;
+    use crate::proto::bets_service_client::BetsServiceClient;
+    use crate::proto::bets_service_server::BetsServiceServer;
+
+    #[test]
+    fn reads_market_rows() {
+        let row = json!({
+            "address": "market", "creator": "creator", "mint": "mint", "title": "Rain?",
+            "fee_bps": 200, "end_ts": 100, "resolve_deadline_ts": 200,
+            "status": "pending_resolve", "outcome": null,
+            "staked_a": "18446744073709551615", "staked_b": "0",
+            "creator_fee_withdrawn": false, "created_at": 50,
+        });
+        let market = market(&row).unwrap();
+        assert_eq!(market.staked_a, u64::MAX);
+        assert_eq!(market.status(), proto::MarketStatus::PendingResolve);
+        assert_eq!(market.outcome(), Side::Unspecified);
+        assert!(super::market(&json!({ "staked_a": 5 })).is_err());
+
+        assert_eq!(like_escape("50%_off"), "50\\%\\_off");
+    }
+
+    /// Calls a server on a local port through the generated client. Nothing
+    /// here reaches the database or the RPC, which point nowhere.
+    #[test]
+    fn serves_the_generated_client() {
+        // The blocking clients are made here, and outlive the runtime, as
+        // they can't be dropped inside it
+        let db = Arc::new(Database::new("postgres://127.0.0.1:1/none".into()));
+        let feed = Arc::new(Feed::new(Arc::clone(&db)));
+        let rpc = Arc::new(RpcClient::new("http://127.0.0.1:1".into()));
+        let service = Service::new(Arc::clone(&db), Arc::clone(&rpc), Arc::clone(&feed));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            tokio::spawn(
+                Server::builder()
+                    .add_service(BetsServiceServer::new(service))
+                    .serve_with_incoming(TcpListenerStream::new(listener)),
+            );
+            let mut client = BetsServiceClient::connect(format!("http://{address}"))
+                .await
+                .unwrap();
+
+            let err = client
+                .get_market(GetMarketRequest {
+                    market_id: "nope".into(),
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), Code::InvalidArgument);
+
+            let request = CreateMarketRequest {
+                creator: Pubkey::new_unique().to_string(),
+                mint: Pubkey::new_unique().to_string(),
+                title: "Rain?".into(),
+                market_id: Pubkey::new_unique().to_string(),
+                ..Default::default()
+            };
+            let err = client.create_market(request).await.unwrap_err();
+            assert_eq!(err.code(), Code::InvalidArgument);
+            assert!(err.message().contains("market_id"), "{}", err.message());
+
+            let err = client
+                .resolve(ResolveRequest {
+                    market_id: Pubkey::new_unique().to_string(),
+                    resolver: Pubkey::new_unique().to_string(),
+                    outcome: Side::Unspecified.into(),
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), Code::InvalidArgument);
+            assert_eq!(err.message(), "outcome must be SIDE_A or SIDE_B");
+
+            // The stream is subscribed by the time its response arrives
+            let watched = Pubkey::new_unique().to_string();
+            let mut updates = client
+                .watch_markets(WatchMarketsRequest {
+                    market_ids: vec![watched.clone()],
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            let row = |address: &str, staked_a: &str| {
+                json!({
+                    "address": address, "creator": "creator", "mint": "mint", "title": "Rain?",
+                    "fee_bps": 200, "end_ts": 100, "resolve_deadline_ts": 200,
+                    "status": "open", "outcome": null, "staked_a": staked_a, "staked_b": "0",
+                    "creator_fee_withdrawn": false, "created_at": 50,
+                })
+            };
+            let other = Pubkey::new_unique().to_string();
+            feed.publish(Update {
+                market: other.clone(),
+                event: json!({}),
+                market_row: row(&other, "1"),
+            });
+            feed.publish(Update {
+                market: watched.clone(),
+                event: json!({}),
+                market_row: row(&watched, "5"),
+            });
+            let market = updates.message().await.unwrap().unwrap().market.unwrap();
+            assert_eq!(market.id, watched);
+            assert_eq!(market.staked_a, 5);
+            assert_eq!(market.status(), proto::MarketStatus::Open);
+        });
+    }
+}
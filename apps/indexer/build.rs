@@ -0,0 +1,14 @@
+use std::path::PathBuf;
+
+// Generates the Geyser client from the Yellowstone protos, with a vendored
+// `protoc` so building needs nothing installed.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::configure()
+        .build_server(false)
+        .compile_protos(
+            &["proto/geyser.proto"],
+            &[PathBuf::from("proto"), protoc_bin_vendored::include_path()?],
+        )?;
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! Instruction decoding driven by the program's IDL.
+//!
+//! The IDL is compiled in, so instruction names and account names follow
+//! whatever interface the indexer was built against without a hand-kept
+//! table of discriminators and account positions.
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, Context, Result};
+use friend_bets_sdk::PROGRAM_ID;
+use serde_json::Value;
+
+use crate::rpc::RawInstruction;
+
+const IDL: &str = include_str!("../../../packages/clients/rust-interface/idls/friends_bets.json");
+
+/// A program instruction, named, with its accounts named too.
+pub struct Instruction<'a> {
+    pub name: &'a str,
+    accounts: Vec<(&'a str, Pubkey)>,
+}
+
+impl Instruction<'_> {
+    /// The account passed as `name`, if the instruction has one.
+    pub fn account(&self, name: &str) -> Option<Pubkey> {
+        self.accounts
+            .iter()
+            .find(|(account, _)| *account == name)
+            .map(|(_, address)| *address)
+    }
+}
+
+struct Definition {
+    name: String,
+    accounts: Vec<String>,
+}
+
+pub struct Decoder {
+    definitions: HashMap<[u8; 8], Definition>,
+}
+
+impl Decoder {
+    pub fn new() -> Result<Self> {
+        let idl: Value = serde_json::from_str(IDL).context("parsing the IDL")?;
+        let mut definitions = HashMap::new();
+        for instruction in idl["instructions"].as_array().into_iter().flatten() {
+            let name = instruction["name"]
+                .as_str()
+                .ok_or_else(|| anyhow!("IDL instruction without a name"))?;
+            let discriminator: Vec<u8> =
+                serde_json::from_value(instruction["discriminator"].clone())
+                    .with_context(|| format!("IDL discriminator of {name}"))?;
+            let discriminator: [u8; 8] = discriminator
+                .try_into()
+                .map_err(|_| anyhow!("IDL discriminator of {name} isn't 8 bytes"))?;
+            let mut accounts = Vec::new();
+            flatten_accounts(&instruction["accounts"], &mut accounts);
+            definitions.insert(
+                discriminator,
+                Definition {
+                    name: name.to_string(),
+                    accounts,
+                },
+            );
+        }
+        Ok(Self { definitions })
+    }
+
+    /// Decodes the program's instructions in `instructions`, skipping other
+    /// programs and anything this IDL doesn't describe (such as event CPIs).
+    pub fn decode(&self, instructions: &[RawInstruction]) -> Vec<Instruction<'_>> {
+        instructions
+            .iter()
+            .filter(|instruction| instruction.program_id == PROGRAM_ID)
+            .filter_map(|instruction| {
+                let discriminator: [u8; 8] = instruction.data.get(..8)?.try_into().ok()?;
+                let definition = self.definitions.get(&discriminator)?;
+                Some(Instruction {
+                    name: &definition.name,
+                    accounts: definition
+                        .accounts
+                        .iter()
+                        .map(String::as_str)
+                        .zip(instruction.accounts.iter().copied())
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Account names in order, descending into composite account groups.
+fn flatten_accounts(accounts: &Value, names: &mut Vec<String>) {
+    for account in accounts.as_array().into_iter().flatten() {
+        if account.get("accounts").is_some() {
+            flatten_accounts(&account["accounts"], names);
+        } else if let Some(name) = account["name"].as_str() {
+            names.push(name.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use friend_bets_sdk::instructions::{self, NewMarket};
+    use friend_bets_sdk::MarketOptions;
+
+    use super::*;
+
+    #[test]
+    fn names_accounts_like_the_sdk_builds_them() {
+        let new_market = NewMarket {
+            creator: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            market_id: 7,
+        };
+        let built = instructions::initialize_market(
+            &new_market,
+            100,
+            10,
+            20,
+            "title".into(),
+            MarketOptions {
+                allow_cash_out: false,
+                cashout_haircut_bps: 0,
+                lock_odds: false,
+                burn_fee: false,
+            },
+        );
+        let raw = RawInstruction {
+            program_id: built.program_id,
+            accounts: built.accounts.iter().map(|meta| meta.pubkey).collect(),
+            data: built.data,
+        };
+
+        let decoder = Decoder::new().unwrap();
+        let decoded = decoder.decode(&[raw]);
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].name, "initialize_market");
+        assert_eq!(decoded[0].account("market"), Some(new_market.market()));
+        assert_eq!(decoded[0].account("mint"), Some(new_market.mint));
+        assert_eq!(decoded[0].account("creator"), Some(new_market.creator));
+    }
+}
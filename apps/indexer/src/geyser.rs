@@ -0,0 +1,350 @@
+//! Transactions streamed from a Yellowstone gRPC endpoint, the Geyser plugin
+//! most RPC providers run, for lower latency than polling RPC.
+//!
+//! The stream runs on a small tokio runtime of its own, over tonic; the rest
+//! of the indexer stays blocking and reads updates from a channel.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, Context, Result};
+use tokio::runtime::{self, Runtime};
+use tokio::sync::mpsc as async_mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::metadata::AsciiMetadataValue;
+use tonic::transport::{ClientTlsConfig, Endpoint};
+use tonic::Streaming;
+
+use crate::proto::geyser::geyser_client::GeyserClient as Client;
+use crate::proto::geyser::subscribe_update::UpdateOneof;
+use crate::proto::geyser::{
+    CommitmentLevel, SubscribeRequest, SubscribeRequestFilterTransactions, SubscribeRequestPing,
+    SubscribeUpdate, SubscribeUpdateTransaction,
+};
+use crate::rpc::{RawInstruction, Transaction};
+
+pub struct GeyserClient {
+    url: String,
+    token: Option<AsciiMetadataValue>,
+    runtime: Runtime,
+}
+
+pub enum Update {
+    Transaction(Transaction),
+    /// The server checking the connection; answer with [`Subscription::ping`].
+    Ping,
+}
+
+/// An open stream. Dropping it disconnects.
+pub struct Subscription {
+    requests: async_mpsc::Sender<SubscribeRequest>,
+    updates: Receiver<Result<Update>>,
+    task: JoinHandle<()>,
+}
+
+impl GeyserClient {
+    pub fn new(url: String, token: Option<String>) -> Result<Self> {
+        let token = token
+            .map(|token| token.parse())
+            .transpose()
+            .context("invalid Geyser token")?;
+        let runtime = runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            url,
+            token,
+            runtime,
+        })
+    }
+
+    /// Subscribes to successful transactions that involve `program`.
+    pub fn subscribe(&self, program: &Pubkey) -> Result<Subscription> {
+        let filter = SubscribeRequestFilterTransactions {
+            vote: Some(false),
+            failed: Some(false),
+            account_include: vec![program.to_string()],
+            ..Default::default()
+        };
+        let request = SubscribeRequest {
+            transactions: HashMap::from([("friend-bets".to_string(), filter)]),
+            // Matching what's read over RPC
+            commitment: Some(CommitmentLevel::Confirmed.into()),
+            ping: None,
+        };
+
+        let (requests, outgoing) = async_mpsc::channel(4);
+        requests.blocking_send(request)?;
+        let stream = self
+            .runtime
+            .block_on(self.open(ReceiverStream::new(outgoing)))
+            .context("Geyser")?;
+        let (sender, updates) = mpsc::channel();
+        let task = self.runtime.spawn(forward_updates(stream, sender));
+        Ok(Subscription {
+            requests,
+            updates,
+            task,
+        })
+    }
+
+    async fn open(
+        &self,
+        requests: ReceiverStream<SubscribeRequest>,
+    ) -> Result<Streaming<SubscribeUpdate>> {
+        let mut endpoint = Endpoint::from_shared(self.url.clone())?;
+        if self.url.starts_with("https://") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new().with_webpki_roots())?;
+        }
+        let channel = endpoint.connect().await?;
+        let mut request = tonic::Request::new(requests);
+        if let Some(token) = &self.token {
+            request.metadata_mut().insert("x-token", token.clone());
+        }
+        Ok(Client::new(channel).subscribe(request).await?.into_inner())
+    }
+}
+
+impl Subscription {
+    /// Waits for updates, returning all that have arrived.
+    pub fn recv(&mut self) -> Result<Vec<Update>> {
+        let first = match self.updates.recv() {
+            Ok(update) => update?,
+            Err(_) => return Err(anyhow!("Geyser: stream closed")),
+        };
+        let mut updates = vec![first];
+        loop {
+            match self.updates.try_recv() {
+                Ok(Ok(update)) => updates.push(update),
+                // Whatever arrived before is dropped with the stream; the
+                // catch-up on reconnecting reads it again
+                Ok(Err(err)) => return Err(err),
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => return Ok(updates),
+            }
+        }
+    }
+
+    /// Answers a server ping so proxies keep the stream open.
+    pub fn ping(&mut self) -> Result<()> {
+        let ping = SubscribeRequest {
+            ping: Some(SubscribeRequestPing { id: 1 }),
+            ..Default::default()
+        };
+        self.requests
+            .blocking_send(ping)
+            .map_err(|_| anyhow!("Geyser: stream closed"))
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Forwards decoded updates until the stream ends or the receiver goes away.
+async fn forward_updates(mut stream: Streaming<SubscribeUpdate>, sender: Sender<Result<Update>>) {
+    loop {
+        let update = match stream.message().await {
+            Ok(Some(update)) => parse_update(update),
+            Ok(None) => Err(anyhow!("Geyser: stream closed")),
+            Err(status) => Err(anyhow!("Geyser: {:?}: {}", status.code(), status.message())),
+        };
+        let failed = update.is_err();
+        let update = match update {
+            Ok(Some(update)) => Ok(update),
+            Ok(None) => continue,
+            Err(err) => Err(err),
+        };
+        if sender.send(update).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Reads a `SubscribeUpdate`, skipping kinds the indexer doesn't use and
+/// transactions that failed.
+fn parse_update(update: SubscribeUpdate) -> Result<Option<Update>> {
+    // The stream has no block time, but the update is created as the slot
+    // is processed, within moments of it
+    let created_at = update.created_at.map(|time| time.seconds);
+    match update.update_oneof {
+        Some(UpdateOneof::Transaction(transaction)) => {
+            Ok(parse_transaction(transaction, created_at)
+                .context("invalid transaction update")?
+                .map(Update::Transaction))
+        }
+        Some(UpdateOneof::Ping(_)) => Ok(Some(Update::Ping)),
+        Some(UpdateOneof::Pong(_)) | None => Ok(None),
+    }
+}
+
+/// Reads a `SubscribeUpdateTransaction` like [`crate::rpc`] reads a
+/// transaction, inner instructions following their parent.
+fn parse_transaction(
+    update: SubscribeUpdateTransaction,
+    block_time: Option<i64>,
+) -> Result<Option<Transaction>> {
+    let info = update
+        .transaction
+        .ok_or_else(|| anyhow!("no transaction"))?;
+    let meta = info.meta.unwrap_or_default();
+    if meta.err.is_some() {
+        return Ok(None);
+    }
+    let message = info
+        .transaction
+        .and_then(|transaction| transaction.message)
+        .unwrap_or_default();
+
+    // Static keys, then those loaded from lookup tables, writable first
+    let keys = message
+        .account_keys
+        .iter()
+        .chain(&meta.loaded_writable_addresses)
+        .chain(&meta.loaded_readonly_addresses)
+        .map(|key| Pubkey::try_from(key.as_slice()).map_err(|_| anyhow!("invalid account key")))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut instructions = Vec::new();
+    for (index, instruction) in message.instructions.iter().enumerate() {
+        instructions.push(parse_instruction(
+            instruction.program_id_index,
+            &instruction.accounts,
+            &instruction.data,
+            &keys,
+        )?);
+        for set in meta
+            .inner_instructions
+            .iter()
+            .filter(|set| set.index as usize == index)
+        {
+            for instruction in &set.instructions {
+                instructions.push(parse_instruction(
+                    instruction.program_id_index,
+                    &instruction.accounts,
+                    &instruction.data,
+                    &keys,
+                )?);
+            }
+        }
+    }
+
+    Ok(Some(Transaction {
+        signature: bs58::encode(&info.signature).into_string(),
+        slot: update.slot,
+        block_time,
+        instructions,
+        logs: meta.log_messages,
+    }))
+}
+
+/// Resolves a compiled or inner instruction's account indexes.
+fn parse_instruction(
+    program: u32,
+    accounts: &[u8],
+    data: &[u8],
+    keys: &[Pubkey],
+) -> Result<RawInstruction> {
+    let key = |index: usize| {
+        keys.get(index)
+            .copied()
+            .ok_or_else(|| anyhow!("account index out of range"))
+    };
+    Ok(RawInstruction {
+        program_id: key(program as usize)?,
+        accounts: accounts
+            .iter()
+            .map(|&index| key(index.into()))
+            .collect::<Result<_>>()?,
+        data: data.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_types::Timestamp;
+
+    use super::*;
+    use crate::proto::geyser::SubscribeUpdateTransactionInfo;
+    use crate::proto::solana::storage::confirmed_block::{
+        self as block, CompiledInstruction, InnerInstruction, InnerInstructions,
+        TransactionStatusMeta,
+    };
+
+    fn instruction(program: u32, accounts: &[u8], data: &[u8]) -> CompiledInstruction {
+        CompiledInstruction {
+            program_id_index: program,
+            accounts: accounts.to_vec(),
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn decodes_transaction_updates() {
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let message = block::Message {
+            account_keys: vec![keys[0].to_bytes().to_vec(), keys[1].to_bytes().to_vec()],
+            instructions: vec![instruction(1, &[0, 3], &[1, 2]), instruction(2, &[], &[3])],
+            ..Default::default()
+        };
+        let meta = TransactionStatusMeta {
+            inner_instructions: vec![InnerInstructions {
+                index: 0,
+                instructions: vec![InnerInstruction {
+                    program_id_index: 2,
+                    accounts: vec![1],
+                    data: vec![4],
+                    stack_height: Some(2),
+                }],
+            }],
+            log_messages: vec!["Program log: hi".into()],
+            loaded_writable_addresses: vec![keys[2].to_bytes().to_vec()],
+            loaded_readonly_addresses: vec![keys[3].to_bytes().to_vec()],
+            ..Default::default()
+        };
+        let update = SubscribeUpdate {
+            filters: vec!["friend-bets".into()],
+            update_oneof: Some(UpdateOneof::Transaction(SubscribeUpdateTransaction {
+                transaction: Some(SubscribeUpdateTransactionInfo {
+                    signature: vec![7; 64],
+                    transaction: Some(block::Transaction {
+                        signatures: vec![vec![7; 64]],
+                        message: Some(message),
+                    }),
+                    meta: Some(meta),
+                    ..Default::default()
+                }),
+                slot: 42,
+            })),
+            created_at: Some(Timestamp {
+                seconds: 1_700_000_000,
+                nanos: 0,
+            }),
+        };
+
+        let Some(Update::Transaction(tx)) = parse_update(update).unwrap() else {
+            panic!("expected a transaction");
+        };
+        assert_eq!(tx.signature, bs58::encode([7; 64]).into_string());
+        assert_eq!(tx.slot, 42);
+        assert_eq!(tx.block_time, Some(1_700_000_000));
+        assert_eq!(tx.logs, ["Program log: hi"]);
+        let decoded: Vec<(Pubkey, Vec<Pubkey>, Vec<u8>)> = tx
+            .instructions
+            .into_iter()
+            .map(|ix| (ix.program_id, ix.accounts, ix.data))
+            .collect();
+        assert_eq!(
+            decoded,
+            [
+                (keys[1], vec![keys[0], keys[3]], vec![1, 2]),
+                (keys[2], vec![keys[1]], vec![4]),
+                (keys[2], vec![], vec![3]),
+            ]
+        );
+    }
+}
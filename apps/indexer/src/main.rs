@@ -0,0 +1,470 @@
+//! `friend-bets-indexer`: follows the program's transactions and keeps
+//! normalized Postgres tables of markets, bets, positions, claims and fees.
+
+mod db;
+mod decode;
+mod geyser;
+mod metrics;
+mod proto;
+mod report;
+mod rpc;
+mod store;
+mod webhooks;
+
+use std::collections::HashMap;
+use std::env;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Result};
+use friend_bets_sdk::events::parse_logs;
+use friend_bets_sdk::PROGRAM_ID;
+
+use crate::db::{set_cursor, Args, Batch, Database};
+use crate::decode::Decoder;
+use crate::geyser::{GeyserClient, Update};
+use crate::metrics::Metrics;
+use crate::rpc::{RpcClient, SignatureInfo, Transaction};
+
+const USAGE: &str = "\
+Usage: friend-bets-indexer [OPTIONS] <COMMAND>
+
+Commands:
+  migrate   Create the database tables
+  run       Follow new program transactions into the database
+  backfill  Index past program transactions, back to the first by default
+  report    Export a wallet's realized profit and loss as CSV, for tax reporting
+
+Backfill options:
+      --before <SIG>        Start from the transaction before this one instead of the newest
+      --until <SIG>         Stop at this transaction instead of the program's first
+
+Report options:
+      --wallet <PUBKEY>     Whose profit and loss to report
+      --from <DATE>         First day to include, YYYY-MM-DD in UTC; default the beginning
+      --to <DATE>           Last day to include, YYYY-MM-DD in UTC; default the latest
+      --by <GROUP>          One row per market (default) or per mint
+      --output <FILE>       Write the CSV here instead of stdout
+                            Amounts are whole tokens with --rpc-url, raw units without
+
+Options:
+      --rpc-url <URLS>      Comma-separated RPC endpoints, failing over in order; URL#N
+                            caps one at N requests a second [env: FRIEND_BETS_RPC_URL]
+      --database-url <URL>  Postgres connection URI [env: DATABASE_URL]
+      --geyser-url <URL>    Stream from a Yellowstone gRPC endpoint instead of polling [env: FRIEND_BETS_GEYSER_URL]
+      --geyser-token <TOK>  Yellowstone x-token [env: FRIEND_BETS_GEYSER_TOKEN]
+      --interval <SECS>     Seconds between polls, or before reconnecting the stream, default 2
+                            [env: FRIEND_BETS_INDEXER_INTERVAL]
+      --webhooks <FILE>     POST matching events to the webhooks listed in this TOML file
+                            [env: FRIEND_BETS_WEBHOOKS]
+      --metrics-listen <ADDR>
+                            Serve Prometheus metrics at http://ADDR/metrics
+                            [env: FRIEND_BETS_METRICS_LISTEN]
+  -h, --help                Print this help
+
+With --geyser-url, RPC is still used to catch up after each (re)connect.
+";
+
+/// Transactions written per database transaction.
+const BATCH_SIZE: usize = 100;
+
+/// The most signatures `getSignatureStatuses` takes at once.
+const STATUS_PAGE: usize = 256;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let mut command = None;
+    let mut options = HashMap::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            flag if flag.starts_with("--") => {
+                let (name, value) = match flag[2..].split_once('=') {
+                    Some((name, value)) => (name.to_string(), value.to_string()),
+                    None => (
+                        flag[2..].to_string(),
+                        args.next().ok_or_else(|| anyhow!("{flag} needs a value"))?,
+                    ),
+                };
+                options.insert(name, value);
+            }
+            _ if command.is_none() => command = Some(arg),
+            _ => bail!("unexpected argument {arg:?}"),
+        }
+    }
+    let mut option = |name: &str, var: &str| options.remove(name).or_else(|| env::var(var).ok());
+
+    let Some(command) = command else {
+        print!("{USAGE}");
+        return Ok(());
+    };
+    let database_url = option("database-url", "DATABASE_URL")
+        .ok_or_else(|| anyhow!("set --database-url or DATABASE_URL"))?;
+    let db = Database::new(database_url);
+    match command.as_str() {
+        "migrate" => {
+            db.migrate()?;
+            println!("Database is up to date");
+            Ok(())
+        }
+        "run" => {
+            let rpc_url = option("rpc-url", "FRIEND_BETS_RPC_URL")
+                .ok_or_else(|| anyhow!("set --rpc-url or FRIEND_BETS_RPC_URL"))?;
+            let interval = match option("interval", "FRIEND_BETS_INDEXER_INTERVAL") {
+                Some(secs) => secs.parse().context("invalid --interval")?,
+                None => 2,
+            };
+            let interval = Duration::from_secs(interval);
+            if let Some(path) = option("webhooks", "FRIEND_BETS_WEBHOOKS") {
+                let hooks = webhooks::load(&path)?;
+                db.migrate()?;
+                for hook in hooks {
+                    eprintln!("Delivering events to webhook {}", hook.name);
+                    let db = db.clone();
+                    thread::spawn(move || hook.dispatch(&db, interval));
+                }
+            }
+            let metrics = Arc::new(Metrics::default());
+            if let Some(listen) = option("metrics-listen", "FRIEND_BETS_METRICS_LISTEN") {
+                metrics::serve(
+                    &listen,
+                    Arc::clone(&metrics),
+                    db.clone(),
+                    RpcClient::new(&rpc_url)?,
+                )?;
+            }
+            let indexer = Indexer {
+                rpc: RpcClient::new(&rpc_url)?,
+                db,
+                decoder: Decoder::new()?,
+                metrics,
+            };
+            match option("geyser-url", "FRIEND_BETS_GEYSER_URL") {
+                Some(url) => {
+                    let token = option("geyser-token", "FRIEND_BETS_GEYSER_TOKEN");
+                    indexer.stream(&GeyserClient::new(url, token)?, interval)
+                }
+                None => indexer.follow(interval),
+            }
+        }
+        "backfill" => {
+            let rpc_url = option("rpc-url", "FRIEND_BETS_RPC_URL")
+                .ok_or_else(|| anyhow!("set --rpc-url or FRIEND_BETS_RPC_URL"))?;
+            let before = options.remove("before");
+            let until = options.remove("until");
+            let indexer = Indexer {
+                rpc: RpcClient::new(&rpc_url)?,
+                db,
+                decoder: Decoder::new()?,
+                metrics: Arc::default(),
+            };
+            indexer.backfill(before.as_deref(), until.as_deref())
+        }
+        "report" => {
+            let rpc = option("rpc-url", "FRIEND_BETS_RPC_URL")
+                .map(|urls| RpcClient::new(&urls))
+                .transpose()?;
+            let wallet = options
+                .remove("wallet")
+                .ok_or_else(|| anyhow!("report needs --wallet"))?;
+            let by_mint = match options.remove("by").as_deref() {
+                None | Some("market") => false,
+                Some("mint") => true,
+                Some(other) => bail!("unknown --by {other:?}; use market or mint"),
+            };
+            let report = report::Report {
+                wallet,
+                from: options.remove("from"),
+                to: options.remove("to"),
+                by_mint,
+            };
+            let output = options.remove("output");
+            let csv = report.csv(&db, rpc.as_ref())?;
+            report::write(&csv, output.as_deref())
+        }
+        other => bail!("unknown command {other:?}; see --help"),
+    }
+}
+
+struct Indexer {
+    rpc: RpcClient,
+    db: Database,
+    decoder: Decoder,
+    metrics: Arc<Metrics>,
+}
+
+impl Indexer {
+    /// Indexes new transactions as they land, resuming from the saved
+    /// cursor. A fresh database starts from the newest transaction.
+    fn follow(&self, interval: Duration) -> Result<()> {
+        self.db.migrate()?;
+        let mut cursor = self.db.cursor()?;
+        if cursor.is_none() {
+            eprintln!("No cursor saved; indexing from the newest transaction on");
+        }
+        loop {
+            if let Err(err) = self.poll(&mut cursor).and_then(|()| self.finalize()) {
+                Metrics::add(&self.metrics.errors, 1);
+                eprintln!("warning: {err:#}");
+            }
+            thread::sleep(interval);
+        }
+    }
+
+    /// Indexes transactions from a Geyser stream as they're processed,
+    /// reconnecting when it drops.
+    fn stream(&self, geyser: &GeyserClient, interval: Duration) -> Result<()> {
+        self.db.migrate()?;
+        let mut cursor = self.db.cursor()?;
+        loop {
+            if let Err(err) = self.stream_once(geyser, &mut cursor, interval) {
+                Metrics::add(&self.metrics.errors, 1);
+                eprintln!("warning: {err:#}");
+            }
+            thread::sleep(interval);
+        }
+    }
+
+    fn stream_once(
+        &self,
+        geyser: &GeyserClient,
+        cursor: &mut Option<String>,
+        interval: Duration,
+    ) -> Result<()> {
+        // Subscribe before catching up so nothing lands unseen in between
+        let mut subscription = geyser.subscribe(&PROGRAM_ID)?;
+        self.poll(cursor)?;
+        eprintln!("Streaming transactions from Geyser");
+        let mut finalized_at = Instant::now();
+        loop {
+            if finalized_at.elapsed() >= interval {
+                self.finalize()?;
+                // The stream is live, so everything up to now is in
+                let head = self.rpc.get_slot("confirmed")?;
+                self.metrics.indexed(head, head);
+                finalized_at = Instant::now();
+            }
+            let mut batch = Batch::default();
+            let mut last = None;
+            let mut count = 0;
+            for update in subscription.recv()? {
+                match update {
+                    Update::Ping => subscription.ping()?,
+                    Update::Transaction(tx) => {
+                        // Streamed transactions are too new to be finalized
+                        batch.append(self.transaction_sql(&tx, 0));
+                        count += 1;
+                        last = Some(tx);
+                    }
+                }
+            }
+            let Some(last) = last else {
+                continue;
+            };
+            batch.append(set_cursor(&last.signature));
+            self.db.execute(&batch)?;
+            self.metrics.indexed(last.slot, last.slot);
+            eprintln!("Indexed {count} transactions through slot {}", last.slot);
+            *cursor = Some(last.signature);
+        }
+    }
+
+    /// Indexes the transactions between `before` and `until`, oldest first
+    /// so each market exists before what happens to it. The cursor `run`
+    /// follows is left alone, except that a database without one gets the
+    /// newest transaction backfilled, for `run` to continue from.
+    fn backfill(&self, before: Option<&str>, until: Option<&str>) -> Result<()> {
+        self.db.migrate()?;
+        eprintln!("Listing transactions...");
+        let signatures = self.rpc.signatures_between(&PROGRAM_ID, before, until)?;
+        let total = signatures.len();
+        eprintln!("Backfilling {total} transactions");
+        let mut done = 0;
+        for batch in signatures.chunks(BATCH_SIZE) {
+            let last = batch.last().expect("chunks aren't empty");
+            let mut statements = self.batch_sql(batch)?;
+            done += batch.len();
+            if done == total && before.is_none() && self.db.cursor()?.is_none() {
+                statements.append(set_cursor(&last.signature));
+            }
+            self.db.execute(&statements)?;
+            eprintln!("Backfilled {done}/{total} through slot {}", last.slot);
+        }
+        Ok(())
+    }
+
+    fn poll(&self, cursor: &mut Option<String>) -> Result<()> {
+        // Read first: whatever the listing returns covers up to here
+        let head = self.rpc.get_slot("confirmed")?;
+        let Some(until) = cursor.as_deref() else {
+            if let Some(latest) = self.rpc.latest_signature(&PROGRAM_ID)? {
+                self.db.execute(&set_cursor(&latest.signature))?;
+                *cursor = Some(latest.signature);
+            }
+            self.metrics.indexed(head, head);
+            return Ok(());
+        };
+        let signatures = self.rpc.signatures_since(&PROGRAM_ID, until)?;
+        for batch in signatures.chunks(BATCH_SIZE) {
+            let last = batch.last().expect("chunks aren't empty");
+            let mut statements = self.batch_sql(batch)?;
+            statements.append(set_cursor(&last.signature));
+            self.db.execute(&statements)?;
+            *cursor = Some(last.signature.clone());
+            eprintln!(
+                "Indexed {} transactions through slot {}",
+                batch.len(),
+                last.slot
+            );
+        }
+        self.metrics.indexed(head, head);
+        Ok(())
+    }
+
+    /// SQL applying every successful transaction in `signatures`.
+    fn batch_sql(&self, signatures: &[SignatureInfo]) -> Result<Batch> {
+        // Read first: a transaction fetched afterwards at or below this slot
+        // is on the finalized fork
+        let finalized = self.rpc.get_slot("finalized")?;
+        let mut batch = Batch::default();
+        for info in signatures.iter().filter(|info| !info.failed) {
+            let Some(tx) = self.rpc.get_transaction(&info.signature)? else {
+                eprintln!("warning: {} is no longer available", info.signature);
+                continue;
+            };
+            batch.append(self.transaction_sql(&tx, finalized));
+        }
+        Ok(batch)
+    }
+
+    /// Promotes transactions to finalized once their slot is, and rolls back
+    /// any the cluster no longer has, as they were on an abandoned fork.
+    fn finalize(&self) -> Result<()> {
+        let finalized = self.rpc.get_slot("finalized")?;
+        loop {
+            let signatures: Vec<String> = self
+                .db
+                .query(
+                    &format!(
+                        "SELECT signature FROM transactions \
+                         WHERE commitment <> 'finalized' AND slot <= {finalized} \
+                         ORDER BY slot LIMIT {STATUS_PAGE}"
+                    ),
+                    &Args::default(),
+                )?
+                .iter()
+                .map(|row| row.get(0))
+                .collect();
+            if signatures.is_empty() {
+                return Ok(());
+            }
+            let statuses = self.rpc.signature_statuses(&signatures)?;
+            let mut batch = Batch::default();
+            let mut dropped = Vec::new();
+            let mut settled = 0;
+            for (signature, status) in signatures.iter().zip(statuses) {
+                match status {
+                    Some(status) if status.finalized => {
+                        batch.append(store::commitment_sql(signature, status.slot, "finalized"));
+                        settled += 1;
+                    }
+                    // Landed again on the surviving fork, which hasn't
+                    // finalized that far yet
+                    Some(status) if status.slot > finalized => {
+                        batch.append(store::commitment_sql(signature, status.slot, "confirmed"));
+                        settled += 1;
+                    }
+                    Some(_) => {}
+                    None => dropped.push(signature.clone()),
+                }
+            }
+            if !dropped.is_empty() {
+                eprintln!("Rolling back {} dropped transactions", dropped.len());
+                Metrics::add(&self.metrics.rollbacks, dropped.len());
+                batch.append(self.rollback_sql(&dropped)?);
+            }
+            if !batch.is_empty() {
+                self.db.execute(&batch)?;
+            }
+            // The node is behind its own finalized slot; try again later
+            if settled + dropped.len() == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// SQL removing `signatures`' rows and rebuilding the markets they
+    /// touched from those markets' other transactions, refetched in order.
+    fn rollback_sql(&self, signatures: &[String]) -> Result<Batch> {
+        let column = |sql: &str, args: &Args| -> Result<Vec<String>> {
+            Ok(self
+                .db
+                .query(sql, args)?
+                .iter()
+                .map(|row| row.get(0))
+                .collect())
+        };
+        let mut args = Args::default();
+        let sql = format!(
+            "SELECT DISTINCT unnest(markets) FROM transactions WHERE signature = ANY ({})",
+            args.add(signatures.to_vec())
+        );
+        let markets = column(&sql, &args)?;
+        let mut batch = store::delete_sql(signatures);
+        if markets.is_empty() {
+            return Ok(batch);
+        }
+        let mut args = Args::default();
+        let sql = format!(
+            "SELECT signature FROM transactions \
+             WHERE markets && {} AND signature <> ALL ({}) ORDER BY slot",
+            args.add(markets.clone()),
+            args.add(signatures.to_vec())
+        );
+        let replay = column(&sql, &args)?;
+        batch.append(store::reset_markets_sql(&markets));
+        let finalized = self.rpc.get_slot("finalized")?;
+        for signature in replay {
+            match self.rpc.get_transaction(&signature)? {
+                Some(tx) => batch.append(self.transaction_sql(&tx, finalized)),
+                None => eprintln!("warning: {signature} is no longer available to replay"),
+            }
+        }
+        Ok(batch)
+    }
+
+    /// SQL for `tx`, as finalized if it landed at or below `finalized`.
+    fn transaction_sql(&self, tx: &Transaction, finalized: u64) -> Batch {
+        let events = match parse_logs(&tx.logs) {
+            Ok(events) => events,
+            Err(err) => {
+                eprintln!("warning: {}: {err}", tx.signature);
+                Vec::new()
+            }
+        };
+        Metrics::add(&self.metrics.transactions, 1);
+        Metrics::add(&self.metrics.events, events.len());
+        let instructions = self.decoder.decode(&tx.instructions);
+        let commitment = if tx.slot <= finalized {
+            "finalized"
+        } else {
+            "confirmed"
+        };
+        store::transaction_sql(tx, &instructions, &events, commitment)
+    }
+}
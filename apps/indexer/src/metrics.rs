@@ -0,0 +1,312 @@
+//! Prometheus metrics, served at `/metrics` when `run` is given
+//! `--metrics-listen`.
+//!
+//! Counters and slots are kept in memory as the indexer works. Market and
+//! vault figures are read from the database, and vault balances from RPC,
+//! on each scrape:
+//!
+//! - `friend_bets_markets{status}`: markets by status.
+//! - `friend_bets_markets_pending_resolution`: betting is over and the
+//!   resolve deadline hasn't passed, but nobody has resolved them yet.
+//! - `friend_bets_markets_awaiting_crank{action}`: markets `crank` would
+//!   act on, `close_betting` past their end or `cancel_expired` past their
+//!   resolve deadline. These should stay near zero while a keeper runs.
+//! - `friend_bets_vault_balance{market,side}` and
+//!   `friend_bets_vault_liabilities{market}`, for every market not yet
+//!   collected: what each of its side vaults holds, and what the indexed
+//!   events say they should hold together (stakes in, less cash-outs,
+//!   claims, fees and insurance paid out). Balances under their
+//!   liabilities mean the accounting has drifted or the indexer missed
+//!   something; `friend_bets_vaults_short` counts those markets.
+
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{Context, Result};
+use friend_bets_sdk::pda::vault_address;
+use friend_bets_sdk::BetSide;
+
+use crate::db::{Args, Database};
+use crate::rpc::RpcClient;
+
+/// Vaults read per `getMultipleAccounts`.
+const ACCOUNTS_PAGE: usize = 100;
+
+/// What the indexer has done since it started.
+#[derive(Default)]
+pub struct Metrics {
+    pub transactions: AtomicU64,
+    pub events: AtomicU64,
+    pub errors: AtomicU64,
+    pub rollbacks: AtomicU64,
+    head_slot: AtomicU64,
+    indexed_slot: AtomicU64,
+    last_success: AtomicU64,
+}
+
+impl Metrics {
+    pub fn add(counter: &AtomicU64, n: usize) {
+        counter.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    /// Records that everything up to `indexed` is in, as of the chain being
+    /// at `head`.
+    pub fn indexed(&self, indexed: u64, head: u64) {
+        self.head_slot.fetch_max(head, Ordering::Relaxed);
+        self.indexed_slot.fetch_max(indexed, Ordering::Relaxed);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        self.last_success.store(now, Ordering::Relaxed);
+    }
+}
+
+/// Serves `/metrics` on `listen`, from a thread per scrape.
+pub fn serve(listen: &str, metrics: Arc<Metrics>, db: Database, rpc: RpcClient) -> Result<()> {
+    let listener = TcpListener::bind(listen).with_context(|| format!("listening on {listen}"))?;
+    eprintln!(
+        "Serving metrics on http://{}/metrics",
+        listener.local_addr()?
+    );
+    let state = Arc::new((metrics, db, rpc));
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let state = Arc::clone(&state);
+            thread::spawn(move || {
+                let (metrics, db, rpc) = &*state;
+                respond(stream, metrics, db, rpc)
+            });
+        }
+    });
+    Ok(())
+}
+
+fn respond(stream: TcpStream, metrics: &Metrics, db: &Database, rpc: &RpcClient) {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // Skip the headers
+    let mut line = String::new();
+    while reader.read_line(&mut line).is_ok_and(|n| n > 0) && line.trim_end() != "" {
+        line.clear();
+    }
+    let path = request_line.split(' ').nth(1).unwrap_or_default();
+    let (status, body) = if path.split('?').next() == Some("/metrics") {
+        ("200 OK", render(metrics, db, rpc))
+    } else {
+        ("404 Not Found", "not found\n".to_string())
+    };
+    let _ = write!(
+        writer,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+}
+
+fn render(metrics: &Metrics, db: &Database, rpc: &RpcClient) -> String {
+    let mut out = String::new();
+    let counter = |out: &mut String, name: &str, help: &str, value: &AtomicU64| {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} counter");
+        let _ = writeln!(out, "{name} {}", value.load(Ordering::Relaxed));
+    };
+    counter(
+        &mut out,
+        "friend_bets_indexer_transactions_total",
+        "Transactions indexed, including replays.",
+        &metrics.transactions,
+    );
+    counter(
+        &mut out,
+        "friend_bets_indexer_events_total",
+        "Program events decoded.",
+        &metrics.events,
+    );
+    counter(
+        &mut out,
+        "friend_bets_indexer_errors_total",
+        "Polls or stream batches that failed.",
+        &metrics.errors,
+    );
+    counter(
+        &mut out,
+        "friend_bets_indexer_rollbacks_total",
+        "Transactions rolled back after their fork was dropped.",
+        &metrics.rollbacks,
+    );
+
+    let head = metrics.head_slot.load(Ordering::Relaxed);
+    let indexed = metrics.indexed_slot.load(Ordering::Relaxed);
+    for (name, help, value) in [
+        (
+            "friend_bets_indexer_head_slot",
+            "Newest confirmed slot the indexer has seen.",
+            head,
+        ),
+        (
+            "friend_bets_indexer_indexed_slot",
+            "Slot the indexer is caught up to.",
+            indexed,
+        ),
+        (
+            "friend_bets_indexer_lag_slots",
+            "Slots between the chain and what's indexed.",
+            head.saturating_sub(indexed),
+        ),
+        (
+            "friend_bets_indexer_last_success_timestamp_seconds",
+            "When the indexer last caught up.",
+            metrics.last_success.load(Ordering::Relaxed),
+        ),
+    ] {
+        gauge_header(&mut out, name, help);
+        let _ = writeln!(out, "{name} {value}");
+    }
+
+    if let Err(err) = render_database(&mut out, db, rpc) {
+        eprintln!("warning: metrics: {err:#}");
+    }
+    out
+}
+
+fn render_database(out: &mut String, db: &Database, rpc: &RpcClient) -> Result<()> {
+    gauge_header(out, "friend_bets_markets", "Markets by status.");
+    let none = Args::default();
+    for row in db.query(
+        "SELECT status, count(*) FROM markets GROUP BY status ORDER BY status",
+        &none,
+    )? {
+        let (status, count): (String, i64) = (row.get(0), row.get(1));
+        let _ = writeln!(out, "friend_bets_markets{{status=\"{status}\"}} {count}");
+    }
+
+    let counts = db.query(
+        "SELECT \
+         count(*) FILTER (WHERE end_ts <= now() AND resolve_deadline_ts > now()), \
+         count(*) FILTER (WHERE status = 'open' AND end_ts <= now()), \
+         count(*) FILTER (WHERE resolve_deadline_ts <= now()) \
+         FROM markets WHERE status IN ('open', 'pending_resolve')",
+        &none,
+    )?;
+    if let Some(row) = counts.first() {
+        let (pending, close, cancel): (i64, i64, i64) = (row.get(0), row.get(1), row.get(2));
+        gauge_header(
+            out,
+            "friend_bets_markets_pending_resolution",
+            "Markets past their end, not resolved, before their resolve deadline.",
+        );
+        let _ = writeln!(out, "friend_bets_markets_pending_resolution {pending}");
+        gauge_header(
+            out,
+            "friend_bets_markets_awaiting_crank",
+            "Markets a crank would act on.",
+        );
+        let _ = writeln!(
+            out,
+            "friend_bets_markets_awaiting_crank{{action=\"close_betting\"}} {close}\n\
+             friend_bets_markets_awaiting_crank{{action=\"cancel_expired\"}} {cancel}"
+        );
+    }
+
+    let unfinalized = db.query(
+        "SELECT count(*) FROM transactions WHERE commitment <> 'finalized'",
+        &none,
+    )?;
+    if let Some(count) = unfinalized.first().map(|row| row.get::<_, i64>(0)) {
+        gauge_header(
+            out,
+            "friend_bets_indexer_unfinalized_transactions",
+            "Indexed transactions not yet finalized.",
+        );
+        let _ = writeln!(out, "friend_bets_indexer_unfinalized_transactions {count}");
+    }
+
+    // Stakes in, less everything the program transfers or burns out
+    let liabilities = db.query(
+        "SELECT m.address, \
+         ((SELECT COALESCE(sum(CASE kind WHEN 'bet' THEN amount ELSE -payout END), 0) \
+          FROM bets WHERE market = m.address) \
+         - (SELECT COALESCE(sum(amount), 0) FROM claims WHERE market = m.address) \
+         - (SELECT COALESCE(sum(amount), 0) FROM fees \
+            WHERE market = m.address AND kind IN ('withdrawn', 'split', 'burned', 'insurance', 'charity')))::text \
+         FROM markets m WHERE m.status <> 'collected' ORDER BY m.address",
+        &none,
+    )?;
+    let markets: Vec<(Pubkey, String)> = liabilities
+        .iter()
+        .filter_map(|row| Some((row.get::<_, String>(0).parse().ok()?, row.get(1))))
+        .collect();
+    // Side A's vault and side B's for each market, in turn
+    let mut balances = Vec::with_capacity(markets.len() * 2);
+    for page in markets.chunks(ACCOUNTS_PAGE / 2) {
+        let vaults: Vec<Pubkey> = page
+            .iter()
+            .flat_map(|(market, _)| {
+                [BetSide::A, BetSide::B].map(|side| vault_address(market, side).0)
+            })
+            .collect();
+        balances.extend(rpc.token_balances(&vaults)?);
+    }
+    let balances: Vec<[Option<u64>; 2]> =
+        balances.chunks(2).map(|pair| [pair[0], pair[1]]).collect();
+    gauge_header(
+        out,
+        "friend_bets_vault_balance",
+        "Tokens in a market's vault for one side, in raw units.",
+    );
+    for ((market, _), pair) in markets.iter().zip(&balances) {
+        for (side, balance) in ["a", "b"].into_iter().zip(pair) {
+            if let Some(balance) = balance {
+                let _ = writeln!(
+                    out,
+                    "friend_bets_vault_balance{{market=\"{market}\",side=\"{side}\"}} {balance}"
+                );
+            }
+        }
+    }
+    gauge_header(
+        out,
+        "friend_bets_vault_liabilities",
+        "What indexed events say a market's vaults should hold, in raw units.",
+    );
+    for (market, owed) in &markets {
+        let _ = writeln!(
+            out,
+            "friend_bets_vault_liabilities{{market=\"{market}\"}} {owed}"
+        );
+    }
+    let short = markets
+        .iter()
+        .zip(&balances)
+        .filter(|((_, owed), pair)| {
+            let owed: i128 = owed.parse().unwrap_or_default();
+            match pair {
+                [None, None] => owed > 0,
+                [a, b] => i128::from(a.unwrap_or(0)) + i128::from(b.unwrap_or(0)) < owed,
+            }
+        })
+        .count();
+    gauge_header(
+        out,
+        "friend_bets_vaults_short",
+        "Markets whose vaults hold less than their liabilities.",
+    );
+    let _ = writeln!(out, "friend_bets_vaults_short {short}");
+    Ok(())
+}
+
+fn gauge_header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} gauge");
+}
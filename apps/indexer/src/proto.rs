@@ -0,0 +1,16 @@
+//! The Geyser client and messages, generated from `proto/` by the build
+//! script.
+
+// Transaction updates dwarf pings, but they're most of what arrives
+#[allow(clippy::large_enum_variant)]
+pub mod geyser {
+    tonic::include_proto!("geyser");
+}
+
+pub mod solana {
+    pub mod storage {
+        pub mod confirmed_block {
+            tonic::include_proto!("solana.storage.confirmed_block");
+        }
+    }
+}
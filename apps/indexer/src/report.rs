@@ -0,0 +1,254 @@
+//! `report`: a wallet's realized profit and loss over a date range, as CSV
+//! for tax reporting.
+//!
+//! A stake is realized when it leaves the market, on the day it does:
+//!
+//! - a cash-out realizes the stake it withdraws against its payout;
+//! - a claim realizes what's left staked against the claim and any fee
+//!   rebated with it;
+//! - a losing position is realized at nothing when its market resolves.
+//!
+//! Winnings and refunds not yet claimed aren't realized, and are left out.
+//! Fees a creator withdraws, or a fee split pays, count as income on the day
+//! they're paid.
+
+use std::collections::HashMap;
+use std::fs;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+
+use crate::db::{Args, Database};
+use crate::rpc::RpcClient;
+
+const MARKET_COLUMNS: [&str; 9] = [
+    "market",
+    "title",
+    "mint",
+    "status",
+    "realized_at",
+    "cost",
+    "proceeds",
+    "creator_fees",
+    "pnl",
+];
+
+const MINT_COLUMNS: [&str; 6] = ["mint", "markets", "cost", "proceeds", "creator_fees", "pnl"];
+
+pub struct Report {
+    pub wallet: String,
+    /// First and last days included, `YYYY-MM-DD` in UTC.
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub by_mint: bool,
+}
+
+impl Report {
+    /// Builds the report's CSV; amounts are in whole tokens when `rpc` is
+    /// given to read mint decimals, and raw units otherwise.
+    pub fn csv(&self, db: &Database, rpc: Option<&RpcClient>) -> Result<String> {
+        self.wallet
+            .parse::<Pubkey>()
+            .map_err(|_| anyhow!("invalid wallet {:?}", self.wallet))?;
+        for date in [&self.from, &self.to].into_iter().flatten() {
+            if !is_date(date) {
+                bail!("invalid date {date:?}; use YYYY-MM-DD");
+            }
+        }
+        let mut args = Args::default();
+        let rows = db.json(&self.sql(&mut args), &args)?;
+
+        let mut decimals: HashMap<String, u8> = HashMap::new();
+        if let Some(rpc) = rpc {
+            let mut mints: Vec<String> = rows
+                .iter()
+                .filter_map(|row| row["mint"].as_str().map(String::from))
+                .collect();
+            mints.sort();
+            mints.dedup();
+            let keys = mints
+                .iter()
+                .map(|mint| mint.parse().map_err(|_| anyhow!("invalid mint {mint}")))
+                .collect::<Result<Vec<Pubkey>>>()?;
+            for (mint, found) in mints.iter().zip(rpc.mint_decimals(&keys)?) {
+                match found {
+                    Some(found) => {
+                        decimals.insert(mint.clone(), found);
+                    }
+                    None => eprintln!("warning: mint {mint} not found; its amounts are raw units"),
+                }
+            }
+        }
+
+        let columns: &[&str] = if self.by_mint {
+            &MINT_COLUMNS
+        } else {
+            &MARKET_COLUMNS
+        };
+        let mut csv = columns.join(",") + "\n";
+        for row in &rows {
+            let mint = row["mint"].as_str().unwrap_or_default();
+            let amount = |column: &str| {
+                let raw = row[column].as_str().unwrap_or("0");
+                format_units(raw, decimals.get(mint).copied())
+            };
+            let fields: Vec<String> = columns
+                .iter()
+                .map(|&column| match column {
+                    "cost" | "proceeds" | "creator_fees" | "pnl" => amount(column),
+                    // Titles are anyone's text; keep spreadsheets from
+                    // reading one as a formula
+                    "title" => {
+                        let title = row[column].as_str().unwrap_or_default();
+                        if title.starts_with(['=', '+', '-', '@']) {
+                            format!("'{title}")
+                        } else {
+                            title.to_string()
+                        }
+                    }
+                    _ => match &row[column] {
+                        Value::String(text) => text.clone(),
+                        Value::Null => String::new(),
+                        other => other.to_string(),
+                    },
+                })
+                .collect();
+            let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&line.join(","));
+            csv.push('\n');
+        }
+        Ok(csv)
+    }
+
+    fn sql(&self, args: &mut Args) -> String {
+        let wallet = args.add(self.wallet.clone());
+        let mut range = Vec::new();
+        if let Some(from) = &self.from {
+            range.push(format!("d.at >= {}::text::date", args.add(from.clone())));
+        }
+        if let Some(to) = &self.to {
+            range.push(format!("d.at < {}::text::date + 1", args.add(to.clone())));
+        }
+        let range: String = range.iter().map(|term| format!(" AND {term}")).collect();
+        let disposals = format!(
+            "WITH ledger AS (\
+               SELECT market, COALESCE(sum(amount) FILTER (WHERE kind = 'bet'), 0) \
+                 - COALESCE(sum(amount) FILTER (WHERE kind = 'cash_out'), 0) AS held \
+               FROM bets WHERE owner = {wallet} GROUP BY market), \
+             claimed AS (\
+               SELECT c.market, max(c.block_time) AS at, sum(c.amount) AS amount \
+               FROM claims c WHERE c.owner = {wallet} GROUP BY c.market), \
+             disposals AS (\
+               SELECT market, block_time AS at, amount AS cost, payout AS proceeds, 0 AS fees \
+               FROM bets WHERE owner = {wallet} AND kind = 'cash_out' \
+               UNION ALL \
+               SELECT c.market, c.at, l.held, c.amount + COALESCE((SELECT sum(f.amount) \
+                 FROM fees f WHERE f.market = c.market AND f.kind = 'rebated' \
+                 AND f.account = {wallet}), 0), 0 \
+               FROM claimed c JOIN ledger l ON l.market = c.market \
+               UNION ALL \
+               SELECT l.market, r.at, l.held, 0, 0 \
+               FROM ledger l JOIN markets m ON m.address = l.market \
+               JOIN (SELECT market, max(block_time) AS at FROM events \
+                     WHERE name = 'resolved' GROUP BY market) r ON r.market = l.market \
+               WHERE l.held > 0 AND m.outcome IS NOT NULL \
+               AND NOT EXISTS (SELECT 1 FROM claimed c WHERE c.market = l.market) \
+               AND EXISTS (SELECT 1 FROM positions p WHERE p.market = l.market \
+                 AND p.owner = {wallet} AND p.side <> m.outcome) \
+               UNION ALL \
+               SELECT market, block_time, 0, 0, amount FROM fees \
+               WHERE kind IN ('withdrawn', 'split') AND account = {wallet}) "
+        );
+        if self.by_mint {
+            format!(
+                "{disposals}SELECT json_build_object('mint', m.mint, \
+                 'markets', count(DISTINCT d.market), 'cost', sum(d.cost)::text, \
+                 'proceeds', sum(d.proceeds)::text, 'creator_fees', sum(d.fees)::text, \
+                 'pnl', (sum(d.proceeds) + sum(d.fees) - sum(d.cost))::text) \
+                 FROM disposals d JOIN markets m ON m.address = d.market \
+                 WHERE true{range} GROUP BY m.mint ORDER BY m.mint"
+            )
+        } else {
+            format!(
+                "{disposals}SELECT json_build_object('market', m.address, 'title', m.title, \
+                 'mint', m.mint, 'status', m.status, \
+                 'realized_at', to_char(max(d.at) AT TIME ZONE 'UTC', \
+                   'YYYY-MM-DD HH24:MI:SS'), \
+                 'cost', sum(d.cost)::text, 'proceeds', sum(d.proceeds)::text, \
+                 'creator_fees', sum(d.fees)::text, \
+                 'pnl', (sum(d.proceeds) + sum(d.fees) - sum(d.cost))::text) \
+                 FROM disposals d JOIN markets m ON m.address = d.market \
+                 WHERE true{range} GROUP BY m.address ORDER BY max(d.at), m.address"
+            )
+        }
+    }
+}
+
+/// Writes `csv` to `path`, or stdout without one.
+pub fn write(csv: &str, path: Option<&str>) -> Result<()> {
+    match path {
+        Some(path) => fs::write(path, csv).with_context(|| format!("writing {path}")),
+        None => {
+            print!("{csv}");
+            Ok(())
+        }
+    }
+}
+
+fn is_date(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, byte)| match i {
+            4 | 7 => *byte == b'-',
+            _ => byte.is_ascii_digit(),
+        })
+}
+
+/// Formats signed raw units in whole tokens, trimming trailing zeros, or
+/// leaves them raw without `decimals`.
+fn format_units(raw: &str, decimals: Option<u8>) -> String {
+    let Some(decimals) = decimals.filter(|decimals| *decimals > 0) else {
+        return raw.to_string();
+    };
+    let (sign, digits) = match raw.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", raw),
+    };
+    let decimals = usize::from(decimals);
+    let digits = format!("{digits:0>width$}", width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{sign}{whole}")
+    } else {
+        format!("{sign}{whole}.{fraction}")
+    }
+}
+
+/// Quotes a field when it holds a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_amounts_and_dates() {
+        assert_eq!(format_units("6930000", Some(6)), "6.93");
+        assert_eq!(format_units("-2000000", Some(6)), "-2");
+        assert_eq!(format_units("-5", Some(6)), "-0.000005");
+        assert_eq!(format_units("0", Some(6)), "0");
+        assert_eq!(format_units("1234", None), "1234");
+        assert!(is_date("2026-01-31"));
+        assert!(!is_date("2026-1-31"));
+        assert!(!is_date("2026-01-31' OR"));
+        assert_eq!(csv_field("Rain, or not?"), "\"Rain, or not?\"");
+    }
+}
@@ -0,0 +1,378 @@
+//! Minimal blocking JSON-RPC client for transaction history.
+//!
+//! Requests go through `reqwest`'s blocking client, as in the CLI.
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, Context, Result};
+use friend_bets_sdk::failover::{
+    parse_endpoints, Endpoints, Failure, HealthPolicy, ENDPOINT_ERROR_CODES,
+};
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+
+/// The most signatures `getSignaturesForAddress` returns at once.
+const SIGNATURE_PAGE: usize = 1_000;
+
+pub struct RpcClient {
+    http: Client,
+    endpoints: Endpoints,
+}
+
+pub struct SignatureInfo {
+    pub signature: String,
+    pub slot: u64,
+    pub failed: bool,
+}
+
+/// An instruction with its program and accounts resolved to addresses.
+pub struct RawInstruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<Pubkey>,
+    pub data: Vec<u8>,
+}
+
+/// Where a transaction landed.
+pub struct SignatureStatus {
+    pub slot: u64,
+    pub finalized: bool,
+}
+
+pub struct Transaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    /// Top-level instructions followed by each one's inner instructions, in
+    /// execution order.
+    pub instructions: Vec<RawInstruction>,
+    pub logs: Vec<String>,
+}
+
+impl RpcClient {
+    /// `urls` lists endpoints, comma-separated and most preferred first;
+    /// `URL#N` sends one at most `N` requests a second.
+    pub fn new(urls: &str) -> Result<Self> {
+        Ok(Self {
+            http: Client::new(),
+            endpoints: Endpoints::new(parse_endpoints(urls)?, HealthPolicy::default()),
+        })
+    }
+
+    /// Sends a request to the first endpoint that's up, failing over to the
+    /// next when one is unreachable, overloaded or behind.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let checked = self.endpoints.check_due(|url| {
+            post(
+                &self.http,
+                url,
+                "getHealth",
+                &request("getHealth", json!([])),
+            )
+            .is_ok()
+        });
+        for (url, _) in checked.into_iter().flatten().filter(|(_, up)| !up) {
+            eprintln!("warning: RPC endpoint {url} failed its health check");
+        }
+        let body = request(method, params);
+        self.endpoints
+            .call(|url| post(&self.http, url, method, &body))
+    }
+
+    /// The newest transaction touching `address`.
+    pub fn latest_signature(&self, address: &Pubkey) -> Result<Option<SignatureInfo>> {
+        Ok(self.signatures_page(address, None, None, 1)?.pop())
+    }
+
+    /// Transactions touching `address` after `until`, oldest first.
+    pub fn signatures_since(&self, address: &Pubkey, until: &str) -> Result<Vec<SignatureInfo>> {
+        self.signatures_between(address, None, Some(until))
+    }
+
+    /// Transactions touching `address` older than `before` and newer than
+    /// `until`, oldest first. Either end left open runs to the newest
+    /// transaction or the first one.
+    pub fn signatures_between(
+        &self,
+        address: &Pubkey,
+        before: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Vec<SignatureInfo>> {
+        let mut signatures = Vec::new();
+        loop {
+            let before = signatures
+                .last()
+                .map(|info: &SignatureInfo| info.signature.clone())
+                .or_else(|| before.map(String::from));
+            let page = self.signatures_page(address, before.as_deref(), until, SIGNATURE_PAGE)?;
+            let done = page.len() < SIGNATURE_PAGE;
+            signatures.extend(page);
+            if done {
+                break;
+            }
+        }
+        signatures.reverse();
+        Ok(signatures)
+    }
+
+    /// One page of transactions touching `address`, newest first.
+    pub fn signatures_page(
+        &self,
+        address: &Pubkey,
+        before: Option<&str>,
+        until: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let mut config = json!({ "commitment": "confirmed", "limit": limit });
+        if let Some(before) = before {
+            config["before"] = before.into();
+        }
+        if let Some(until) = until {
+            config["until"] = until.into();
+        }
+        let result = self.call(
+            "getSignaturesForAddress",
+            json!([address.to_string(), config]),
+        )?;
+        Ok(result
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                Some(SignatureInfo {
+                    signature: entry["signature"].as_str()?.to_string(),
+                    slot: entry["slot"].as_u64().unwrap_or_default(),
+                    failed: !entry["err"].is_null(),
+                })
+            })
+            .collect())
+    }
+
+    /// The newest slot at `commitment`.
+    pub fn get_slot(&self, commitment: &str) -> Result<u64> {
+        self.call("getSlot", json!([{ "commitment": commitment }]))?
+            .as_u64()
+            .ok_or_else(|| anyhow!("getSlot: invalid response"))
+    }
+
+    /// The balance of each of the token accounts `addresses`, in raw units,
+    /// or `None` where there's no such account.
+    pub fn token_balances(&self, addresses: &[Pubkey]) -> Result<Vec<Option<u64>>> {
+        let addresses: Vec<String> = addresses.iter().map(Pubkey::to_string).collect();
+        let result = self.call(
+            "getMultipleAccounts",
+            json!([addresses, { "commitment": "confirmed", "encoding": "jsonParsed" }]),
+        )?;
+        let accounts = result["value"]
+            .as_array()
+            .filter(|accounts| accounts.len() == addresses.len())
+            .ok_or_else(|| anyhow!("getMultipleAccounts: invalid response"))?;
+        Ok(accounts
+            .iter()
+            .map(|account| {
+                account["data"]["parsed"]["info"]["tokenAmount"]["amount"]
+                    .as_str()?
+                    .parse()
+                    .ok()
+            })
+            .collect())
+    }
+
+    /// The decimals of each of the mints `addresses`, or `None` where
+    /// there's no such mint.
+    pub fn mint_decimals(&self, addresses: &[Pubkey]) -> Result<Vec<Option<u8>>> {
+        let mut decimals = Vec::with_capacity(addresses.len());
+        // The RPC accepts at most 100 addresses per request
+        for chunk in addresses.chunks(100) {
+            let keys: Vec<String> = chunk.iter().map(Pubkey::to_string).collect();
+            let result = self.call(
+                "getMultipleAccounts",
+                json!([keys, { "commitment": "confirmed", "encoding": "jsonParsed" }]),
+            )?;
+            let accounts = result["value"]
+                .as_array()
+                .filter(|accounts| accounts.len() == keys.len())
+                .ok_or_else(|| anyhow!("getMultipleAccounts: invalid response"))?;
+            decimals.extend(accounts.iter().map(|account| {
+                let decimals = account["data"]["parsed"]["info"]["decimals"].as_u64()?;
+                u8::try_from(decimals).ok()
+            }));
+        }
+        Ok(decimals)
+    }
+
+    /// The status of each of `signatures`, searching the node's whole
+    /// history, or `None` where the cluster has no such transaction.
+    pub fn signature_statuses(
+        &self,
+        signatures: &[String],
+    ) -> Result<Vec<Option<SignatureStatus>>> {
+        let result = self.call(
+            "getSignatureStatuses",
+            json!([signatures, { "searchTransactionHistory": true }]),
+        )?;
+        let statuses = result["value"]
+            .as_array()
+            .filter(|statuses| statuses.len() == signatures.len())
+            .ok_or_else(|| anyhow!("getSignatureStatuses: invalid response"))?;
+        Ok(statuses
+            .iter()
+            .map(|status| {
+                Some(SignatureStatus {
+                    slot: status["slot"].as_u64()?,
+                    finalized: status["confirmationStatus"] == "finalized",
+                })
+            })
+            .collect())
+    }
+
+    /// A landed transaction, if the node still has it.
+    pub fn get_transaction(&self, signature: &str) -> Result<Option<Transaction>> {
+        let result = self.call(
+            "getTransaction",
+            json!([signature, {
+                "commitment": "confirmed",
+                "encoding": "json",
+                "maxSupportedTransactionVersion": 0,
+            }]),
+        )?;
+        if result.is_null() {
+            return Ok(None);
+        }
+        parse_transaction(signature, &result)
+            .with_context(|| format!("getTransaction {signature}"))
+            .map(Some)
+    }
+}
+
+fn parse_transaction(signature: &str, result: &Value) -> Result<Transaction> {
+    let message = &result["transaction"]["message"];
+    let meta = &result["meta"];
+
+    // Versioned transactions list accounts loaded from lookup tables after
+    // the static keys, writable ones first.
+    let mut keys = Vec::new();
+    for key in message["accountKeys"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .chain(
+            meta["loadedAddresses"]["writable"]
+                .as_array()
+                .into_iter()
+                .flatten(),
+        )
+        .chain(
+            meta["loadedAddresses"]["readonly"]
+                .as_array()
+                .into_iter()
+                .flatten(),
+        )
+    {
+        let key = key.as_str().ok_or_else(|| anyhow!("invalid account key"))?;
+        keys.push(
+            key.parse::<Pubkey>()
+                .map_err(|_| anyhow!("invalid account key {key}"))?,
+        );
+    }
+
+    let inner: Vec<(u64, &Value)> = meta["innerInstructions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|set| Some((set["index"].as_u64()?, &set["instructions"])))
+        .collect();
+
+    let mut instructions = Vec::new();
+    for (index, instruction) in message["instructions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        instructions.push(parse_instruction(instruction, &keys)?);
+        for (_, set) in inner.iter().filter(|(i, _)| *i == index as u64) {
+            for instruction in set.as_array().into_iter().flatten() {
+                instructions.push(parse_instruction(instruction, &keys)?);
+            }
+        }
+    }
+
+    Ok(Transaction {
+        signature: signature.to_string(),
+        slot: result["slot"].as_u64().unwrap_or_default(),
+        block_time: result["blockTime"].as_i64(),
+        instructions,
+        logs: meta["logMessages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|line| line.as_str().map(String::from))
+            .collect(),
+    })
+}
+
+fn parse_instruction(instruction: &Value, keys: &[Pubkey]) -> Result<RawInstruction> {
+    let key = |index: &Value| {
+        index
+            .as_u64()
+            .and_then(|index| keys.get(index as usize))
+            .copied()
+            .ok_or_else(|| anyhow!("account index out of range"))
+    };
+    Ok(RawInstruction {
+        program_id: key(&instruction["programIdIndex"])?,
+        accounts: instruction["accounts"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(key)
+            .collect::<Result<_>>()?,
+        data: bs58::decode(instruction["data"].as_str().unwrap_or_default())
+            .into_vec()
+            .context("invalid instruction data")?,
+    })
+}
+
+fn request(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params })
+}
+
+/// Sends `body` to one endpoint, telling the endpoint's failures (no
+/// answer, an HTTP error or a node that's behind) from the request's.
+fn post(
+    http: &Client,
+    url: &str,
+    method: &str,
+    body: &Value,
+) -> Result<Value, Failure<anyhow::Error>> {
+    let endpoint = |err: anyhow::Error| Failure::Endpoint(err.context(method.to_string()));
+    let response = http
+        .post(url)
+        .json(body)
+        .send()
+        .map_err(|err| endpoint(err.into()))?;
+    let status = response.status();
+    let text = response.text().map_err(|err| endpoint(err.into()))?;
+
+    let mut response: Value = match serde_json::from_str(&text) {
+        Ok(response) => response,
+        Err(_) if !status.is_success() => return Err(endpoint(anyhow!("HTTP {status}"))),
+        Err(_) => return Err(endpoint(anyhow!("invalid response"))),
+    };
+    if let Some(error) = response.get("error") {
+        let err = anyhow!(
+            "{method}: {}",
+            error["message"].as_str().unwrap_or("rpc error")
+        );
+        let code = error["code"].as_i64().unwrap_or_default();
+        return Err(if ENDPOINT_ERROR_CODES.contains(&code) {
+            Failure::Endpoint(err)
+        } else {
+            Failure::Request(err)
+        });
+    }
+    // An HTTP error without a JSON-RPC one, such as a gateway's
+    if !status.is_success() {
+        return Err(endpoint(anyhow!("HTTP {status}")));
+    }
+    Ok(response["result"].take())
+}
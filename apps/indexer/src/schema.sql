@@ -0,0 +1,195 @@
+-- Tables the indexer maintains. Every statement is safe to re-run, and rows
+-- are keyed by on-chain identity so replaying a transaction changes nothing.
+-- Token amounts are raw units.
+
+CREATE TABLE IF NOT EXISTS indexer_state (
+    key text PRIMARY KEY,
+    value text NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS transactions (
+    signature text PRIMARY KEY,
+    slot bigint NOT NULL,
+    block_time timestamptz,
+    -- Program instructions, including CPIs into the program, in order
+    instructions text[] NOT NULL
+);
+CREATE INDEX IF NOT EXISTS transactions_slot ON transactions (slot);
+
+CREATE TABLE IF NOT EXISTS markets (
+    address text PRIMARY KEY,
+    creator text NOT NULL,
+    mint text,
+    title text NOT NULL,
+    fee_bps integer NOT NULL,
+    end_ts timestamptz NOT NULL,
+    resolve_deadline_ts timestamptz NOT NULL,
+    -- open, pending_resolve, resolved, cancelled or collected
+    status text NOT NULL DEFAULT 'open',
+    outcome text,
+    staked_a numeric(20, 0) NOT NULL DEFAULT 0,
+    staked_b numeric(20, 0) NOT NULL DEFAULT 0,
+    creator_fee_withdrawn boolean NOT NULL DEFAULT false,
+    cloned_from text,
+    forked_from text,
+    depends_on text,
+    depends_on_outcome text,
+    created_slot bigint NOT NULL,
+    created_at timestamptz
+);
+CREATE INDEX IF NOT EXISTS markets_creator ON markets (creator);
+CREATE INDEX IF NOT EXISTS markets_status ON markets (status);
+
+-- Bets placed and cashed out; `amount` is the stake either way
+CREATE TABLE IF NOT EXISTS bets (
+    signature text NOT NULL,
+    event_index integer NOT NULL,
+    market text NOT NULL,
+    owner text NOT NULL,
+    side text NOT NULL,
+    -- bet or cash_out
+    kind text NOT NULL,
+    amount numeric(20, 0) NOT NULL,
+    payout numeric(20, 0),
+    slot bigint NOT NULL,
+    block_time timestamptz,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS bets_market_owner ON bets (market, owner);
+
+-- Derived from bets and claims, one row per position account
+CREATE TABLE IF NOT EXISTS positions (
+    address text PRIMARY KEY,
+    market text NOT NULL,
+    owner text NOT NULL,
+    side text NOT NULL,
+    amount numeric(20, 0) NOT NULL DEFAULT 0,
+    claimed boolean NOT NULL DEFAULT false,
+    claimed_amount numeric(20, 0) NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS positions_owner ON positions (owner);
+CREATE INDEX IF NOT EXISTS positions_market ON positions (market);
+
+CREATE TABLE IF NOT EXISTS claims (
+    signature text NOT NULL,
+    event_index integer NOT NULL,
+    market text NOT NULL,
+    owner text NOT NULL,
+    amount numeric(20, 0) NOT NULL,
+    slot bigint NOT NULL,
+    block_time timestamptz,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS claims_market_owner ON claims (market, owner);
+
+CREATE TABLE IF NOT EXISTS fees (
+    signature text NOT NULL,
+    event_index integer NOT NULL,
+    market text NOT NULL,
+    -- withdrawn (by the creator), split (to a fee split recipient), burned,
+    -- rebated (to a staker), insurance (collected into the mint's
+    -- insurance vault) or charity (donated to the market's charity)
+    kind text NOT NULL,
+    -- Who received it, the charity's token account for charity; null when
+    -- burned or insurance
+    account text,
+    amount numeric(20, 0) NOT NULL,
+    slot bigint NOT NULL,
+    block_time timestamptz,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS fees_market ON fees (market);
+
+-- Commitment tracking. Rows are written as 'confirmed' and become
+-- 'finalized' once their slot is; rows of transactions that turn out to be
+-- on an abandoned fork are deleted. Rows from before this existed count as
+-- finalized.
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS commitment text NOT NULL DEFAULT 'finalized';
+-- Markets the transaction's events touched, to rebuild them on a rollback
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS markets text[] NOT NULL DEFAULT '{}';
+ALTER TABLE bets ADD COLUMN IF NOT EXISTS commitment text NOT NULL DEFAULT 'finalized';
+ALTER TABLE claims ADD COLUMN IF NOT EXISTS commitment text NOT NULL DEFAULT 'finalized';
+ALTER TABLE fees ADD COLUMN IF NOT EXISTS commitment text NOT NULL DEFAULT 'finalized';
+CREATE INDEX IF NOT EXISTS transactions_pending ON transactions (slot)
+    WHERE commitment <> 'finalized';
+CREATE INDEX IF NOT EXISTS transactions_markets ON transactions USING gin (markets);
+
+-- Every market event in order, as JSON, for the API to push to subscribers.
+-- `id` only grows, so readers follow the table by it. The stakes are the
+-- market's pools just after the event, counting bets in slot then signature
+-- order.
+CREATE TABLE IF NOT EXISTS events (
+    id bigserial PRIMARY KEY,
+    signature text NOT NULL,
+    event_index integer NOT NULL,
+    market text NOT NULL,
+    name text NOT NULL,
+    data jsonb NOT NULL,
+    staked_a numeric(20, 0) NOT NULL,
+    staked_b numeric(20, 0) NOT NULL,
+    slot bigint NOT NULL,
+    block_time timestamptz,
+    commitment text NOT NULL,
+    UNIQUE (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS events_market ON events (market, id);
+
+-- The pools and the program's implied odds (payout per unit staked, in basis
+-- points, 0 for an empty side) after each bet and cash-out, for charting
+CREATE TABLE IF NOT EXISTS odds_history (
+    signature text NOT NULL,
+    event_index integer NOT NULL,
+    market text NOT NULL,
+    staked_a numeric(20, 0) NOT NULL,
+    staked_b numeric(20, 0) NOT NULL,
+    -- Stake cash-outs left in the pool
+    retained numeric(20, 0) NOT NULL,
+    odds_a_bps numeric(30, 0) NOT NULL,
+    odds_b_bps numeric(30, 0) NOT NULL,
+    slot bigint NOT NULL,
+    block_time timestamptz,
+    commitment text NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS odds_history_market ON odds_history (market, slot);
+-- Fills the table from bets indexed before it existed
+INSERT INTO odds_history (signature, event_index, market, staked_a, staked_b, retained,
+    odds_a_bps, odds_b_bps, slot, block_time, commitment)
+SELECT signature, event_index, market, a, b, retained,
+    CASE WHEN a > 0 THEN div(distributable * 10000, a) ELSE 0 END,
+    CASE WHEN b > 0 THEN div(distributable * 10000, b) ELSE 0 END,
+    slot, block_time, commitment
+FROM (
+    SELECT pool.*, a + b + retained - div((a + b + retained) * m.fee_bps, 10000) AS distributable
+    FROM (
+        SELECT signature, event_index, market, slot, block_time, commitment,
+            sum(CASE WHEN side <> 'A' THEN 0 WHEN kind = 'bet' THEN amount ELSE -amount END)
+                OVER history AS a,
+            sum(CASE WHEN side <> 'B' THEN 0 WHEN kind = 'bet' THEN amount ELSE -amount END)
+                OVER history AS b,
+            sum(CASE kind WHEN 'cash_out' THEN amount - payout ELSE 0 END) OVER history AS retained
+        FROM bets
+        WINDOW history AS (PARTITION BY market ORDER BY slot, signature, event_index)
+    ) pool
+    JOIN markets m ON m.address = pool.market
+) history
+WHERE NOT EXISTS (SELECT 1 FROM odds_history)
+ON CONFLICT DO NOTHING;
+
+-- Wallets' opt-in to email and web push notifications, written by the API
+-- and read by the notifier; the indexer only creates the table
+CREATE TABLE IF NOT EXISTS subscriptions (
+    id bigserial PRIMARY KEY,
+    wallet text NOT NULL,
+    -- email or push
+    kind text NOT NULL,
+    -- The email address, or the push service endpoint
+    target text NOT NULL,
+    -- For push, the browser's p256dh key and auth secret, base64url
+    p256dh text,
+    auth text,
+    -- Which of closing_soon, resolve_due, resolved and unclaimed to send
+    notify text[] NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    UNIQUE (wallet, kind, target)
+);
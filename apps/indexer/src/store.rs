@@ -0,0 +1,565 @@
+//! SQL for one transaction's instructions and events.
+//!
+//! Event rows are keyed by signature and event index and inserted with
+//! `ON CONFLICT DO NOTHING`; market status only moves forward; stake totals
+//! and positions are recomputed from the rows rather than incremented. So a
+//! transaction can be applied any number of times with the same result.
+//!
+//! Market events are also kept whole, as JSON, in `events`, which the API
+//! follows to push them to subscribers, and the odds after each bet or
+//! cash-out go in `odds_history`. Both carry the pools as of the event,
+//! which replays recompute.
+//!
+//! Rows carry the commitment they were read at. [`commitment_sql`] promotes
+//! them as their transaction finalizes and [`delete_sql`] removes those of
+//! a transaction that was dropped with its fork.
+
+use std::collections::BTreeSet;
+
+use anchor_lang::prelude::Pubkey;
+use friend_bets_sdk::events::FriendBetsEvent;
+use friend_bets_sdk::pda::position_address;
+use friend_bets_sdk::BetSide;
+use serde_json::{json, Value};
+
+use crate::db::{Args, Batch};
+use crate::decode::Instruction;
+use crate::rpc::Transaction;
+
+/// Instructions that create a market, which name its mint.
+const MARKET_CREATORS: [&str; 3] = ["initialize_market", "clone_market", "fork_market"];
+
+/// Tables with a row per event, keyed by signature.
+const EVENT_TABLES: [&str; 5] = ["bets", "claims", "fees", "events", "odds_history"];
+
+pub fn transaction_sql(
+    tx: &Transaction,
+    instructions: &[Instruction],
+    events: &[FriendBetsEvent],
+    commitment: &str,
+) -> Batch {
+    let mut batch = Batch::default();
+    let block_time = tx
+        .block_time
+        .map_or_else(|| "NULL".into(), |ts| format!("to_timestamp({ts})"));
+    let names: Vec<String> = instructions.iter().map(|ix| ix.name.to_string()).collect();
+    let touched: BTreeSet<String> = events
+        .iter()
+        .filter_map(event_market)
+        .map(|market| market.to_string())
+        .collect();
+    let mut args = Args::default();
+    let sql = format!(
+        "INSERT INTO transactions (signature, slot, block_time, instructions, commitment, markets) \
+         VALUES ({}, {}, {block_time}, {}, {}, {}) ON CONFLICT (signature) DO NOTHING",
+        args.add(tx.signature.clone()),
+        tx.slot,
+        args.add(names),
+        args.add(commitment.to_string()),
+        args.add(touched.into_iter().collect::<Vec<_>>())
+    );
+    batch.push(sql, args);
+
+    // Columns shared by the event tables
+    let key =
+        |args: &mut Args, index: usize| format!("{}, {index}", args.add(tx.signature.clone()));
+    let at = |args: &mut Args| {
+        format!(
+            "{}, {block_time}, {}",
+            tx.slot,
+            args.add(commitment.to_string())
+        )
+    };
+    let mut markets = BTreeSet::new();
+    let mut positions = BTreeSet::new();
+    for (index, event) in events.iter().enumerate() {
+        let mut args = Args::default();
+        // The market, kind, recipient and amount of a fee the event pays
+        let mut fee = None;
+        match event {
+            FriendBetsEvent::MarketInitialized(e) => {
+                let mint = instructions
+                    .iter()
+                    .filter(|ix| MARKET_CREATORS.contains(&ix.name))
+                    .find(|ix| ix.account("market") == Some(e.market))
+                    .and_then(|ix| ix.account("mint"));
+                let sql = format!(
+                    "INSERT INTO markets (address, creator, mint, title, fee_bps, end_ts, \
+                     resolve_deadline_ts, created_slot, created_at) \
+                     VALUES ({}, {}, {}, {}, {}, to_timestamp({}), to_timestamp({}), {}, {block_time}) \
+                     ON CONFLICT (address) DO NOTHING",
+                    address(&mut args, &e.market),
+                    address(&mut args, &e.creator),
+                    args.add(mint.map(|mint| mint.to_string())),
+                    // Postgres text can't hold NUL
+                    args.add(e.title.replace('\0', "")),
+                    e.fee_bps,
+                    e.end_ts,
+                    e.resolve_deadline_ts,
+                    tx.slot
+                );
+                batch.push(sql, args);
+            }
+            FriendBetsEvent::MarketCloned(e) => {
+                let set = format!("cloned_from = {}", address(&mut args, &e.source));
+                update_market(&mut batch, args, &e.market, &set, None);
+            }
+            FriendBetsEvent::MarketForked(e) => {
+                let set = format!("forked_from = {}", address(&mut args, &e.original));
+                update_market(&mut batch, args, &e.market, &set, None);
+            }
+            FriendBetsEvent::DependencySet(e) => {
+                let set = format!(
+                    "depends_on = {}, depends_on_outcome = {}",
+                    address(&mut args, &e.parent),
+                    side(e.outcome)
+                );
+                update_market(&mut batch, args, &e.market, &set, None);
+            }
+            FriendBetsEvent::BetPlaced(e) => {
+                let sql = format!(
+                    "INSERT INTO bets (signature, event_index, market, owner, side, kind, amount, \
+                     payout, slot, block_time, commitment) \
+                     VALUES ({}, {}, {}, {}, 'bet', {}, NULL, {}) ON CONFLICT DO NOTHING",
+                    key(&mut args, index),
+                    address(&mut args, &e.market),
+                    address(&mut args, &e.user),
+                    side(e.side),
+                    e.amount,
+                    at(&mut args)
+                );
+                batch.push(sql, args);
+                markets.insert(e.market);
+                positions.insert((e.market, e.user));
+            }
+            FriendBetsEvent::CashedOut(e) => {
+                let sql = format!(
+                    "INSERT INTO bets (signature, event_index, market, owner, side, kind, amount, \
+                     payout, slot, block_time, commitment) \
+                     VALUES ({}, {}, {}, {}, 'cash_out', {}, {}, {}) ON CONFLICT DO NOTHING",
+                    key(&mut args, index),
+                    address(&mut args, &e.market),
+                    address(&mut args, &e.user),
+                    side(e.side),
+                    e.stake,
+                    e.payout,
+                    at(&mut args)
+                );
+                batch.push(sql, args);
+                markets.insert(e.market);
+                positions.insert((e.market, e.user));
+            }
+            FriendBetsEvent::BettingClosed(e) => {
+                update_market(
+                    &mut batch,
+                    args,
+                    &e.market,
+                    "status = 'pending_resolve'",
+                    Some("'open'"),
+                );
+            }
+            FriendBetsEvent::Resolved(e) => {
+                update_market(
+                    &mut batch,
+                    args,
+                    &e.market,
+                    &format!("status = 'resolved', outcome = {}", side(e.outcome)),
+                    Some("'open', 'pending_resolve'"),
+                );
+            }
+            FriendBetsEvent::Cancelled(e) => {
+                update_market(
+                    &mut batch,
+                    args,
+                    &e.market,
+                    "status = 'cancelled'",
+                    Some("'open', 'pending_resolve'"),
+                );
+            }
+            FriendBetsEvent::MarketCollected(e) => {
+                update_market(&mut batch, args, &e.market, "status = 'collected'", None);
+            }
+            FriendBetsEvent::Claimed(e) => {
+                let sql = format!(
+                    "INSERT INTO claims (signature, event_index, market, owner, amount, slot, \
+                     block_time, commitment) VALUES ({}, {}, {}, {}, {}) ON CONFLICT DO NOTHING",
+                    key(&mut args, index),
+                    address(&mut args, &e.market),
+                    address(&mut args, &e.user),
+                    e.amount,
+                    at(&mut args)
+                );
+                batch.push(sql, args);
+                positions.insert((e.market, e.user));
+            }
+            FriendBetsEvent::CreatorFeeWithdrawn(e) => {
+                fee = Some((e.market, "withdrawn", Some(e.creator), e.amount));
+                update_market(
+                    &mut batch,
+                    args,
+                    &e.market,
+                    "creator_fee_withdrawn = true",
+                    None,
+                );
+            }
+            FriendBetsEvent::FeeSplitPaid(e) => {
+                fee = Some((e.market, "split", Some(e.recipient), e.amount))
+            }
+            FriendBetsEvent::FeeBurned(e) => fee = Some((e.market, "burned", None, e.amount)),
+            FriendBetsEvent::InsuranceCollected(e) => {
+                fee = Some((e.market, "insurance", None, e.amount))
+            }
+            FriendBetsEvent::CharityDonated(e) => {
+                fee = Some((e.market, "charity", Some(e.charity), e.amount))
+            }
+            FriendBetsEvent::FeeRebated(e) => {
+                fee = Some((e.market, "rebated", Some(e.user), e.amount))
+            }
+            // Receipts repeat their bet; config and staking aren't indexed
+            _ => {}
+        }
+        if let Some((market, kind, account, amount)) = fee {
+            let mut args = Args::default();
+            let sql = format!(
+                "INSERT INTO fees (signature, event_index, market, kind, account, amount, slot, \
+                 block_time, commitment) VALUES ({}, {}, '{kind}', {}, {amount}, {}) \
+                 ON CONFLICT DO NOTHING",
+                key(&mut args, index),
+                address(&mut args, &market),
+                args.add(account.map(|account: Pubkey| account.to_string())),
+                at(&mut args)
+            );
+            batch.push(sql, args);
+        }
+        // After the event's own rows, so the pools include it. Replays
+        // refresh the pools, as a rollback may have changed what came before.
+        let Some(market) = event_market(event) else {
+            continue;
+        };
+        if let Some((name, data)) = event_json(event) {
+            let mut args = Args::default();
+            let sql = format!(
+                "INSERT INTO events (signature, event_index, market, name, data, staked_a, \
+                 staked_b, slot, block_time, commitment) \
+                 SELECT {}, {}, '{name}', {}::jsonb, pool.a, pool.b, {} FROM {} \
+                 ON CONFLICT (signature, event_index) DO UPDATE \
+                 SET staked_a = EXCLUDED.staked_a, staked_b = EXCLUDED.staked_b",
+                key(&mut args, index),
+                address(&mut args, &market),
+                args.add(data),
+                at(&mut args),
+                pool(&mut args, &market, tx.slot, &tx.signature, index)
+            );
+            batch.push(sql, args);
+        }
+        if matches!(
+            event,
+            FriendBetsEvent::BetPlaced(_) | FriendBetsEvent::CashedOut(_)
+        ) {
+            // The program's implied odds: the pool less its fee, per unit
+            // staked on each side. Needs the market row for its fee.
+            let mut args = Args::default();
+            let market_arg = address(&mut args, &market);
+            let sql = format!(
+                "INSERT INTO odds_history (signature, event_index, market, staked_a, staked_b, \
+                 retained, odds_a_bps, odds_b_bps, slot, block_time, commitment) \
+                 SELECT {key}, {market}, pool.a, pool.b, pool.retained, \
+                 CASE WHEN pool.a > 0 THEN div(pool.distributable * 10000, pool.a) ELSE 0 END, \
+                 CASE WHEN pool.b > 0 THEN div(pool.distributable * 10000, pool.b) ELSE 0 END, \
+                 {at} FROM (SELECT pool.*, pool.a + pool.b + pool.retained \
+                 - div((pool.a + pool.b + pool.retained) * m.fee_bps, 10000) AS distributable \
+                 FROM {pool} JOIN markets m ON m.address = {market}) pool \
+                 ON CONFLICT (signature, event_index) DO UPDATE \
+                 SET staked_a = EXCLUDED.staked_a, staked_b = EXCLUDED.staked_b, \
+                 retained = EXCLUDED.retained, odds_a_bps = EXCLUDED.odds_a_bps, \
+                 odds_b_bps = EXCLUDED.odds_b_bps",
+                key = key(&mut args, index),
+                market = market_arg,
+                at = at(&mut args),
+                pool = pool(&mut args, &market, tx.slot, &tx.signature, index)
+            );
+            batch.push(sql, args);
+        }
+    }
+
+    for market in &markets {
+        let mut args = Args::default();
+        let sql = format!(
+            "UPDATE markets SET staked_a = {}, staked_b = {} WHERE address = {}",
+            staked(&mut args, market, "A"),
+            staked(&mut args, market, "B"),
+            address(&mut args, market)
+        );
+        batch.push(sql, args);
+    }
+    for (market, owner) in &positions {
+        let position = position_address(market, owner).0;
+        let mut args = Args::default();
+        let sql = format!(
+            "INSERT INTO positions (address, market, owner, side) \
+             SELECT {}, market, owner, side FROM bets WHERE market = {} AND owner = {} \
+             ORDER BY slot LIMIT 1 ON CONFLICT (address) DO NOTHING",
+            address(&mut args, &position),
+            address(&mut args, market),
+            address(&mut args, owner)
+        );
+        batch.push(sql, args);
+        let mut args = Args::default();
+        let filter = format!(
+            "market = {} AND owner = {}",
+            address(&mut args, market),
+            address(&mut args, owner)
+        );
+        let sql = format!(
+            "UPDATE positions SET \
+             amount = (SELECT COALESCE(SUM(CASE kind WHEN 'bet' THEN amount ELSE -amount END), 0) \
+             FROM bets WHERE {filter}), \
+             claimed = EXISTS (SELECT 1 FROM claims WHERE {filter}), \
+             claimed_amount = (SELECT COALESCE(SUM(amount), 0) FROM claims WHERE {filter}) \
+             WHERE address = {}",
+            address(&mut args, &position)
+        );
+        batch.push(sql, args);
+    }
+    batch
+}
+
+/// Moves a transaction's rows to `commitment`, and to `slot` in case it
+/// landed again elsewhere.
+pub fn commitment_sql(signature: &str, slot: u64, commitment: &str) -> Batch {
+    let mut batch = Batch::default();
+    for table in ["transactions"].iter().chain(&EVENT_TABLES) {
+        let mut args = Args::default();
+        let sql = format!(
+            "UPDATE {table} SET commitment = {}, slot = {slot} WHERE signature = {}",
+            args.add(commitment.to_string()),
+            args.add(signature.to_string())
+        );
+        batch.push(sql, args);
+    }
+    batch
+}
+
+/// Deletes the rows of `signatures`. Market rows and positions aren't keyed
+/// by transaction, so the caller rebuilds those.
+pub fn delete_sql(signatures: &[String]) -> Batch {
+    let mut batch = Batch::default();
+    for table in EVENT_TABLES.iter().chain(&["transactions"]) {
+        let mut args = Args::default();
+        let sql = format!(
+            "DELETE FROM {table} WHERE signature = ANY ({})",
+            args.add(signatures.to_vec())
+        );
+        batch.push(sql, args);
+    }
+    batch
+}
+
+/// Deletes `markets` and their positions, ahead of replaying what's left of
+/// their transactions.
+pub fn reset_markets_sql(markets: &[String]) -> Batch {
+    let mut batch = Batch::default();
+    for sql in [
+        "DELETE FROM positions WHERE market = ANY ($1)",
+        "DELETE FROM markets WHERE address = ANY ($1)",
+    ] {
+        let mut args = Args::default();
+        args.add(markets.to_vec());
+        batch.push(sql.into(), args);
+    }
+    batch
+}
+
+/// The market an indexed event changes.
+fn event_market(event: &FriendBetsEvent) -> Option<Pubkey> {
+    Some(match event {
+        FriendBetsEvent::MarketInitialized(e) => e.market,
+        FriendBetsEvent::MarketCloned(e) => e.market,
+        FriendBetsEvent::MarketForked(e) => e.market,
+        FriendBetsEvent::DependencySet(e) => e.market,
+        FriendBetsEvent::BetPlaced(e) => e.market,
+        FriendBetsEvent::LargeBetPlaced(e) => e.market,
+        FriendBetsEvent::CashedOut(e) => e.market,
+        FriendBetsEvent::BettingClosed(e) => e.market,
+        FriendBetsEvent::Resolved(e) => e.market,
+        FriendBetsEvent::Cancelled(e) => e.market,
+        FriendBetsEvent::MarketCollected(e) => e.market,
+        FriendBetsEvent::Claimed(e) => e.market,
+        FriendBetsEvent::CreatorFeeWithdrawn(e) => e.market,
+        FriendBetsEvent::FeeSplitPaid(e) => e.market,
+        FriendBetsEvent::FeeBurned(e) => e.market,
+        FriendBetsEvent::CharityDonated(e) => e.market,
+        FriendBetsEvent::FeeRebated(e) => e.market,
+        _ => return None,
+    })
+}
+
+/// The names [`event_json`] gives events, as stored in `events`.
+pub const EVENT_NAMES: [&str; 17] = [
+    "market_initialized",
+    "market_cloned",
+    "market_forked",
+    "dependency_set",
+    "bet_placed",
+    "large_bet_placed",
+    "cashed_out",
+    "betting_closed",
+    "resolved",
+    "cancelled",
+    "market_collected",
+    "claimed",
+    "creator_fee_withdrawn",
+    "fee_split_paid",
+    "fee_burned",
+    "charity_donated",
+    "fee_rebated",
+];
+
+/// A market event's name and fields. Amounts are strings, as the API
+/// serves them.
+fn event_json(event: &FriendBetsEvent) -> Option<(&'static str, Value)> {
+    Some(match event {
+        FriendBetsEvent::MarketInitialized(e) => (
+            "market_initialized",
+            json!({
+                "creator": e.creator.to_string(),
+                "title": e.title.replace('\0', ""),
+                "fee_bps": e.fee_bps,
+                "end_ts": e.end_ts,
+                "resolve_deadline_ts": e.resolve_deadline_ts,
+            }),
+        ),
+        FriendBetsEvent::MarketCloned(e) => {
+            ("market_cloned", json!({ "source": e.source.to_string() }))
+        }
+        FriendBetsEvent::MarketForked(e) => (
+            "market_forked",
+            json!({ "original": e.original.to_string() }),
+        ),
+        FriendBetsEvent::DependencySet(e) => (
+            "dependency_set",
+            json!({ "parent": e.parent.to_string(), "outcome": format!("{:?}", e.outcome) }),
+        ),
+        FriendBetsEvent::BetPlaced(e) => (
+            "bet_placed",
+            json!({
+                "user": e.user.to_string(),
+                "side": format!("{:?}", e.side),
+                "amount": e.amount.to_string(),
+            }),
+        ),
+        FriendBetsEvent::LargeBetPlaced(e) => (
+            "large_bet_placed",
+            json!({
+                "user": e.user.to_string(),
+                "side": format!("{:?}", e.side),
+                "amount": e.amount.to_string(),
+            }),
+        ),
+        FriendBetsEvent::CashedOut(e) => (
+            "cashed_out",
+            json!({
+                "user": e.user.to_string(),
+                "side": format!("{:?}", e.side),
+                "stake": e.stake.to_string(),
+                "payout": e.payout.to_string(),
+            }),
+        ),
+        FriendBetsEvent::BettingClosed(_) => ("betting_closed", json!({})),
+        FriendBetsEvent::Resolved(e) => {
+            ("resolved", json!({ "outcome": format!("{:?}", e.outcome) }))
+        }
+        FriendBetsEvent::Cancelled(_) => ("cancelled", json!({})),
+        FriendBetsEvent::MarketCollected(e) => (
+            "market_collected",
+            json!({
+                "caller": e.caller.to_string(),
+                "positions_closed": e.positions_closed,
+                "caller_share": e.caller_share.to_string(),
+                "creator_share": e.creator_share.to_string(),
+            }),
+        ),
+        FriendBetsEvent::Claimed(e) => (
+            "claimed",
+            json!({ "user": e.user.to_string(), "amount": e.amount.to_string() }),
+        ),
+        FriendBetsEvent::CreatorFeeWithdrawn(e) => (
+            "creator_fee_withdrawn",
+            json!({ "creator": e.creator.to_string(), "amount": e.amount.to_string() }),
+        ),
+        FriendBetsEvent::FeeSplitPaid(e) => (
+            "fee_split_paid",
+            json!({ "recipient": e.recipient.to_string(), "amount": e.amount.to_string() }),
+        ),
+        FriendBetsEvent::FeeBurned(e) => (
+            "fee_burned",
+            json!({ "mint": e.mint.to_string(), "amount": e.amount.to_string() }),
+        ),
+        FriendBetsEvent::CharityDonated(e) => (
+            "charity_donated",
+            json!({ "charity": e.charity.to_string(), "amount": e.amount.to_string() }),
+        ),
+        FriendBetsEvent::FeeRebated(e) => (
+            "fee_rebated",
+            json!({
+                "user": e.user.to_string(),
+                "discounted_stake": e.discounted_stake.to_string(),
+                "amount": e.amount.to_string(),
+            }),
+        ),
+        _ => return None,
+    })
+}
+
+/// `market`'s pools as of event `index` of `signature`, as a subquery
+/// `pool` of what's staked on each side (`a`, `b`) and the stake cash-outs
+/// left behind (`retained`). Bets are ordered by slot, then signature.
+fn pool(args: &mut Args, market: &Pubkey, slot: u64, signature: &str, index: usize) -> String {
+    let stake = "CASE kind WHEN 'bet' THEN amount ELSE -amount END";
+    format!(
+        "(SELECT COALESCE(SUM({stake}) FILTER (WHERE side = 'A'), 0) AS a, \
+         COALESCE(SUM({stake}) FILTER (WHERE side = 'B'), 0) AS b, \
+         COALESCE(SUM(amount - payout) FILTER (WHERE kind = 'cash_out'), 0) AS retained \
+         FROM bets WHERE market = {} \
+         AND (slot, signature, event_index) <= ({slot}, {}, {index})) pool",
+        address(args, market),
+        args.add(signature.to_string())
+    )
+}
+
+/// What's staked on `side` of `market`, as a subquery over `bets`.
+fn staked(args: &mut Args, market: &Pubkey, side: &str) -> String {
+    format!(
+        "(SELECT COALESCE(SUM(CASE kind WHEN 'bet' THEN amount ELSE -amount END), 0) \
+         FROM bets WHERE market = {} AND side = '{side}')",
+        address(args, market)
+    )
+}
+
+/// Updates a market, only while its status is one of `from` when given.
+/// `args` holds the values `set` takes.
+fn update_market(
+    batch: &mut Batch,
+    mut args: Args,
+    market: &Pubkey,
+    set: &str,
+    from: Option<&str>,
+) {
+    let guard = from.map_or_else(String::new, |from| format!(" AND status IN ({from})"));
+    let sql = format!(
+        "UPDATE markets SET {set} WHERE address = {}{guard}",
+        address(&mut args, market)
+    );
+    batch.push(sql, args);
+}
+
+fn address(args: &mut Args, address: &Pubkey) -> String {
+    args.add(address.to_string())
+}
+
+fn side(side: BetSide) -> &'static str {
+    match side {
+        BetSide::A => "'A'",
+        BetSide::B => "'B'",
+    }
+}
@@ -0,0 +1,360 @@
+//! Webhooks: each market event that matches a hook's filters is POSTed to
+//! it as JSON, signed with the hook's secret.
+//!
+//! Hooks are listed in the TOML file given to `run --webhooks`:
+//!
+//! ```toml
+//! [[webhook]]
+//! name = "group-slack"
+//! url = "https://hooks.slack.com/services/..."
+//! # "json" (the default) posts the event; "slack" posts a message
+//! format = "slack"
+//! # Filters; each left out matches everything
+//! events = ["bet_placed", "resolved", "cancelled"]
+//! markets = ["4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi"]
+//! creators = ["8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR"]
+//! # Signs bodies; optional
+//! secret = "..."
+//! # Wait for events to finalize rather than sending them once confirmed
+//! finalized = true
+//! ```
+//!
+//! An event matches when it's one of `events`, on one of `markets`, by one
+//! of `creators`. JSON bodies look like
+//!
+//! ```text
+//! {"id": 42, "event": "bet_placed", "market": ..., "title": ..., "creator": ...,
+//!  "data": {...}, "staked_a": "...", "staked_b": "...", "signature": ...,
+//!  "event_index": 0, "slot": ..., "block_time": ..., "commitment": "confirmed"}
+//! ```
+//!
+//! with `data` as the API's WebSocket feed has it. With a secret, requests
+//! carry `X-Friend-Bets-Timestamp` and `X-Friend-Bets-Signature:
+//! sha256=<hex>`, the HMAC-SHA256 of `<timestamp>.<body>`. `id` is also
+//! sent as `X-Friend-Bets-Delivery`, for receivers to drop repeats.
+//!
+//! Each hook gets events in order from its own thread, resuming from where
+//! it left off (kept in `indexer_state`); a new hook starts with the next
+//! event. Failed deliveries are retried with exponential backoff, then
+//! skipped. A 4xx other than 408 or 429 is skipped without retrying.
+
+use std::fs;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, bail, Context, Result};
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::db::{set_state, Args, Database};
+use crate::store::EVENT_NAMES;
+
+/// Most events read per check.
+const BATCH: usize = 100;
+/// Attempts per event before it's skipped.
+const MAX_ATTEMPTS: u32 = 8;
+/// Wait before the first retry, doubled for each after.
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(300);
+/// How long one request may take.
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    webhook: Vec<Webhook>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub name: String,
+    url: String,
+    #[serde(default)]
+    format: Format,
+    secret: Option<String>,
+    #[serde(default)]
+    events: Vec<String>,
+    #[serde(default)]
+    markets: Vec<String>,
+    #[serde(default)]
+    creators: Vec<String>,
+    #[serde(default)]
+    finalized: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    #[default]
+    Json,
+    Slack,
+}
+
+/// Why a delivery failed.
+enum Failure {
+    /// Worth trying again: a network error, timeout, 408, 429 or 5xx.
+    Retry(String),
+    /// The receiver refused it.
+    Refused(u16),
+}
+
+/// Reads and checks the hooks in `path`.
+pub fn load(path: &str) -> Result<Vec<Webhook>> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+    let file: ConfigFile = toml::from_str(&text).with_context(|| format!("parsing {path}"))?;
+    for (i, hook) in file.webhook.iter().enumerate() {
+        if hook.name.is_empty() {
+            bail!("webhook {} has no name", i + 1);
+        }
+        if file.webhook[..i]
+            .iter()
+            .any(|other| other.name == hook.name)
+        {
+            bail!("two webhooks are named {:?}", hook.name);
+        }
+        if !hook.url.starts_with("https://") && !hook.url.starts_with("http://") {
+            bail!("webhook {:?}: url must be http(s)", hook.name);
+        }
+        if let Some(event) = hook
+            .events
+            .iter()
+            .find(|e| !EVENT_NAMES.contains(&e.as_str()))
+        {
+            bail!(
+                "webhook {:?}: unknown event {event:?}; events are {}",
+                hook.name,
+                EVENT_NAMES.join(", ")
+            );
+        }
+        for address in hook.markets.iter().chain(&hook.creators) {
+            address
+                .parse::<Pubkey>()
+                .map_err(|_| anyhow!("webhook {:?}: invalid address {address:?}", hook.name))?;
+        }
+    }
+    Ok(file.webhook)
+}
+
+impl Webhook {
+    /// Delivers matching events as they're indexed, forever.
+    pub fn dispatch(&self, db: &Database, interval: Duration) {
+        loop {
+            if let Err(err) = self.deliver_new(db) {
+                eprintln!("warning: webhook {}: {err:#}", self.name);
+            }
+            thread::sleep(interval);
+        }
+    }
+
+    fn deliver_new(&self, db: &Database) -> Result<()> {
+        let key = format!("webhook:{}", self.name);
+        let Some(cursor) = db.state(&key)? else {
+            let newest = db.query("SELECT COALESCE(max(id), 0) FROM events", &Args::default())?;
+            let newest: i64 = newest.first().context("expected an event id")?.get(0);
+            db.execute(&set_state(&key, &newest.to_string()))?;
+            return Ok(());
+        };
+        let cursor: i64 = cursor.parse().context("invalid webhook cursor")?;
+        let mut args = Args::default();
+        for payload in db.json(&self.events_sql(cursor, &mut args), &args)? {
+            if self.finalized && payload["commitment"] != "finalized" {
+                break;
+            }
+            self.deliver(&payload);
+            db.execute(&set_state(&key, &payload["id"].to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// The matching events after `cursor`, as one JSON payload per row.
+    fn events_sql(&self, cursor: i64, args: &mut Args) -> String {
+        let mut filters = vec![format!("e.id > {cursor}")];
+        for (column, values) in [
+            ("e.name", &self.events),
+            ("e.market", &self.markets),
+            ("m.creator", &self.creators),
+        ] {
+            if !values.is_empty() {
+                filters.push(format!("{column} = ANY ({})", args.add(values.clone())));
+            }
+        }
+        format!(
+            "SELECT json_build_object('id', e.id, 'event', e.name, 'market', e.market, \
+             'title', m.title, 'creator', m.creator, 'data', e.data, \
+             'staked_a', e.staked_a::text, 'staked_b', e.staked_b::text, \
+             'signature', e.signature, 'event_index', e.event_index, 'slot', e.slot, \
+             'block_time', e.block_time, 'commitment', e.commitment) \
+             FROM events e LEFT JOIN markets m ON m.address = e.market \
+             WHERE {} ORDER BY e.id LIMIT {BATCH}",
+            filters.join(" AND ")
+        )
+    }
+
+    /// Sends `payload`, retrying with backoff; logs and gives up when that
+    /// doesn't work.
+    fn deliver(&self, payload: &Value) {
+        let body = match self.format {
+            Format::Json => payload.to_string(),
+            Format::Slack => json!({ "text": slack_text(payload) }).to_string(),
+        };
+        let id = &payload["id"];
+        let mut delay = FIRST_RETRY;
+        for attempt in 1.. {
+            let reason = match self.post(&body, payload) {
+                Ok(()) => return,
+                Err(Failure::Refused(status)) => {
+                    eprintln!(
+                        "warning: webhook {}: event {id} refused with {status}; skipping it",
+                        self.name
+                    );
+                    return;
+                }
+                Err(Failure::Retry(reason)) => reason,
+            };
+            if attempt == MAX_ATTEMPTS {
+                eprintln!(
+                    "warning: webhook {}: event {id} failed {MAX_ATTEMPTS} times ({reason}); \
+                     skipping it",
+                    self.name
+                );
+                return;
+            }
+            eprintln!(
+                "warning: webhook {}: event {id}: {reason}; retrying in {}s",
+                self.name,
+                delay.as_secs()
+            );
+            thread::sleep(delay);
+            delay = (delay * 2).min(MAX_RETRY);
+        }
+    }
+
+    fn post(&self, body: &str, payload: &Value) -> std::result::Result<(), Failure> {
+        static HTTP: OnceLock<Client> = OnceLock::new();
+        let http = HTTP.get_or_init(|| {
+            Client::builder()
+                .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+                .build()
+                .expect("building the HTTP client")
+        });
+        let mut request = http
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .header(
+                "X-Friend-Bets-Event",
+                payload["event"].as_str().unwrap_or(""),
+            )
+            .header("X-Friend-Bets-Delivery", payload["id"].to_string());
+        if let Some(secret) = &self.secret {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_secs());
+            let mac = hmac_sha256(secret.as_bytes(), format!("{timestamp}.{body}").as_bytes());
+            request = request
+                .header("X-Friend-Bets-Timestamp", timestamp)
+                .header("X-Friend-Bets-Signature", format!("sha256={}", hex(&mac)));
+        }
+
+        let status = request
+            .body(body.to_string())
+            .send()
+            .map_err(|err| Failure::Retry(err.without_url().to_string()))?
+            .status()
+            .as_u16();
+        match status {
+            200..=299 => Ok(()),
+            408 | 429 | 500.. => Err(Failure::Retry(format!("status {status}"))),
+            _ => Err(Failure::Refused(status)),
+        }
+    }
+}
+
+/// A one-line Slack message for an event. Amounts are raw token units.
+fn slack_text(payload: &Value) -> String {
+    let data = &payload["data"];
+    let field = |name: &str| data[name].as_str().unwrap_or_default().to_string();
+    let title = payload["title"]
+        .as_str()
+        .or(payload["market"].as_str())
+        .unwrap_or_default();
+    let market = format!("*{title}*");
+    match payload["event"].as_str().unwrap_or_default() {
+        "market_initialized" => format!("New market {market} by `{}`", field("creator")),
+        "bet_placed" => format!(
+            "`{}` bet {} on {} in {market} (pools: A {}, B {})",
+            field("user"),
+            field("amount"),
+            field("side"),
+            payload["staked_a"].as_str().unwrap_or_default(),
+            payload["staked_b"].as_str().unwrap_or_default()
+        ),
+        "large_bet_placed" => format!(
+            "Large bet: `{}` bet {} on {} in {market} (pools: A {}, B {})",
+            field("user"),
+            field("amount"),
+            field("side"),
+            payload["staked_a"].as_str().unwrap_or_default(),
+            payload["staked_b"].as_str().unwrap_or_default()
+        ),
+        "cashed_out" => format!(
+            "`{}` cashed out {} on {} for {} in {market}",
+            field("user"),
+            field("stake"),
+            field("side"),
+            field("payout")
+        ),
+        "betting_closed" => format!("Betting closed on {market}"),
+        "resolved" => format!("{market} resolved: {} wins", field("outcome")),
+        "cancelled" => format!("{market} was cancelled"),
+        "claimed" => format!(
+            "`{}` claimed {} from {market}",
+            field("user"),
+            field("amount")
+        ),
+        other => format!("{} on {market}", other.replace('_', " ")),
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_and_formats() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        let payload = json!({
+            "event": "bet_placed",
+            "market": "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi",
+            "title": "Rain?",
+            "data": { "user": "abc", "side": "B", "amount": "5000000" },
+            "staked_a": "0",
+            "staked_b": "5000000",
+        });
+        assert_eq!(
+            slack_text(&payload),
+            "`abc` bet 5000000 on B in *Rain?* (pools: A 0, B 5000000)"
+        );
+    }
+}
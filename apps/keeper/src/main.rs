@@ -0,0 +1,391 @@
+//! `friend-bets-keeper`: keeps markets moving without anyone running
+//! `friend-bets crank` by hand. Each round it reads every market, works out
+//! what's due (see [`tasks`]) and sends it, a few transactions of each kind
+//! at a time.
+
+mod oracle;
+mod rpc;
+mod tasks;
+mod wallet;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::process::ExitCode;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token::TokenAccount;
+use anyhow::{anyhow, bail, Context, Result};
+use friend_bets_sdk::fetch::{
+    fetch_markets, fetch_positions, fetch_scheduled_bets, fetch_stake_accounts,
+};
+use friend_bets_sdk::friends_bets::CLAIM_WINDOW_SECS;
+use friend_bets_sdk::pda::{token_account_address, vault_address};
+use friend_bets_sdk::{BetSide, MarketStatus};
+
+use crate::oracle::Oracle;
+use crate::rpc::RpcClient;
+use crate::tasks::{plan, Action, Snapshot, Task};
+use crate::wallet::Wallets;
+
+const USAGE: &str = "\
+Usage: friend-bets-keeper [OPTIONS]
+
+Options:
+      --rpc-url <URLS>      Comma-separated RPC endpoints, failing over in order; URL#N
+                            caps one at N requests a second [env: FRIEND_BETS_RPC_URL]
+      --keypair <PATHS>     Comma-separated keypair files, or directories of them, to sign
+                            and pay with [env: FRIEND_BETS_KEEPER_KEYPAIR]
+      --oracles <FILE>      Resolve markets our wallets created from the oracles in this
+                            TOML file [env: FRIEND_BETS_ORACLES]
+      --tasks <LIST>        Comma-separated tasks to run, default all: close, cancel,
+                            resolve, distribute, sweep, schedule, cashout
+                            [env: FRIEND_BETS_KEEPER_TASKS]
+      --concurrency <LIST>  Transactions sent at once per task, such as close=8,sweep=2;
+                            defaults close=4, cancel=4, resolve=2, distribute=2, sweep=1,
+                            schedule=2, cashout=2 [env: FRIEND_BETS_KEEPER_CONCURRENCY]
+      --min-balance <SOL>   Stop paying from a wallet under this balance, default 0.01
+                            [env: FRIEND_BETS_KEEPER_MIN_BALANCE]
+      --interval <SECS>     Seconds between rounds, default 30 [env: FRIEND_BETS_KEEPER_INTERVAL]
+      --retry-after <SECS>  Seconds before retrying a failed action, default 300
+                            [env: FRIEND_BETS_KEEPER_RETRY_AFTER]
+      --dry-run             Print what would be sent instead of sending it
+      --once                Run a single round and exit
+  -h, --help                Print this help
+
+Without --oracles nothing is resolved; markets are closed and, if nobody
+resolves them by their deadline, cancelled.
+";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let mut options = HashMap::new();
+    let mut dry_run = false;
+    let mut once = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            "--dry-run" => dry_run = true,
+            "--once" => once = true,
+            flag if flag.starts_with("--") => {
+                let (name, value) = match flag[2..].split_once('=') {
+                    Some((name, value)) => (name.to_string(), value.to_string()),
+                    None => (
+                        flag[2..].to_string(),
+                        args.next().ok_or_else(|| anyhow!("{flag} needs a value"))?,
+                    ),
+                };
+                options.insert(name, value);
+            }
+            _ => bail!("unexpected argument {arg:?}"),
+        }
+    }
+    let mut option = |name: &str, var: &str| options.remove(name).or_else(|| env::var(var).ok());
+
+    let rpc_url = option("rpc-url", "FRIEND_BETS_RPC_URL")
+        .ok_or_else(|| anyhow!("set --rpc-url or FRIEND_BETS_RPC_URL"))?;
+    let keypair = option("keypair", "FRIEND_BETS_KEEPER_KEYPAIR")
+        .ok_or_else(|| anyhow!("set --keypair or FRIEND_BETS_KEEPER_KEYPAIR"))?;
+    let min_balance = match option("min-balance", "FRIEND_BETS_KEEPER_MIN_BALANCE") {
+        Some(amount) => lamports(&amount).context("invalid --min-balance")?,
+        None => 10_000_000,
+    };
+    let oracles = match option("oracles", "FRIEND_BETS_ORACLES") {
+        Some(path) => oracle::load(&path)?,
+        None => HashMap::new(),
+    };
+    let tasks: HashSet<Task> = match option("tasks", "FRIEND_BETS_KEEPER_TASKS") {
+        Some(list) => list
+            .split(',')
+            .map(|name| Task::parse(name.trim()))
+            .collect::<Result<_>>()?,
+        None => Task::ALL.into_iter().collect(),
+    };
+    let mut concurrency: HashMap<Task, usize> = Task::ALL
+        .into_iter()
+        .map(|task| (task, task.default_concurrency()))
+        .collect();
+    if let Some(list) = option("concurrency", "FRIEND_BETS_KEEPER_CONCURRENCY") {
+        for entry in list.split(',') {
+            let (name, limit) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("--concurrency takes task=N pairs"))?;
+            let limit: usize = limit.trim().parse().context("invalid --concurrency")?;
+            if limit == 0 {
+                bail!("--concurrency limits must be at least 1; leave tasks out with --tasks");
+            }
+            concurrency.insert(Task::parse(name.trim())?, limit);
+        }
+    }
+    let interval = match option("interval", "FRIEND_BETS_KEEPER_INTERVAL") {
+        Some(secs) => secs.parse().context("invalid --interval")?,
+        None => 30,
+    };
+    let retry_after = match option("retry-after", "FRIEND_BETS_KEEPER_RETRY_AFTER") {
+        Some(secs) => secs.parse().context("invalid --retry-after")?,
+        None => 300,
+    };
+    if let Some(name) = options.keys().next() {
+        bail!("unknown option --{name}; see --help");
+    }
+
+    let wallets = Wallets::load(&keypair, min_balance)?;
+    eprintln!(
+        "Keeping markets with {} wallets, collecting to {}{}",
+        wallets.pubkeys().len(),
+        wallets.primary(),
+        if dry_run { " (dry run)" } else { "" }
+    );
+    let mut keeper = Keeper {
+        rpc: RpcClient::new(&rpc_url)?,
+        wallets,
+        oracles,
+        tasks,
+        concurrency,
+        dry_run,
+        retry_after: Duration::from_secs(retry_after),
+        failed: HashMap::new(),
+    };
+    loop {
+        if let Err(err) = keeper.round() {
+            if once {
+                return Err(err);
+            }
+            eprintln!("warning: {err:#}");
+        }
+        if once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(interval));
+    }
+}
+
+/// Parses an amount of SOL such as `0.05` into lamports.
+fn lamports(sol: &str) -> Result<u64> {
+    let (whole, fraction) = sol.split_once('.').unwrap_or((sol, ""));
+    if fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        bail!("{sol:?} isn't an amount of SOL");
+    }
+    let whole: u64 = whole.parse()?;
+    let fraction: u64 = format!("{fraction:0<9}").parse()?;
+    whole
+        .checked_mul(1_000_000_000)
+        .and_then(|lamports| lamports.checked_add(fraction))
+        .ok_or_else(|| anyhow!("{sol} SOL is too much"))
+}
+
+struct Keeper {
+    rpc: RpcClient,
+    wallets: Wallets,
+    oracles: HashMap<Pubkey, Oracle>,
+    tasks: HashSet<Task>,
+    concurrency: HashMap<Task, usize>,
+    dry_run: bool,
+    retry_after: Duration,
+    /// When each action last failed, by description, so a stuck one isn't
+    /// retried every round.
+    failed: HashMap<String, Instant>,
+}
+
+impl Keeper {
+    fn round(&mut self) -> Result<()> {
+        self.wallets.refresh(&self.rpc)?;
+        let snapshot = self.snapshot()?;
+        let mut actions = plan(&snapshot, &self.tasks)?;
+        self.failed
+            .retain(|_, failed_at| failed_at.elapsed() < self.retry_after);
+        actions.retain(|action| !self.failed.contains_key(&action.description));
+        if actions.is_empty() {
+            return Ok(());
+        }
+        if self.dry_run {
+            for action in &actions {
+                println!("[{}] would {}", action.task.name(), action.description);
+            }
+            return Ok(());
+        }
+
+        let failures = self.execute(actions);
+        let now = Instant::now();
+        for description in failures {
+            self.failed.insert(description, now);
+        }
+        Ok(())
+    }
+
+    /// Reads what this round's tasks need to know.
+    fn snapshot(&self) -> Result<Snapshot> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() as i64);
+        let mut snapshot = Snapshot {
+            now,
+            markets: fetch_markets(&self.rpc)?,
+            positions: Vec::new(),
+            token_accounts: HashSet::new(),
+            vaults: HashMap::new(),
+            outcomes: HashMap::new(),
+            wallets: self.wallets.pubkeys().into_iter().collect(),
+            collector: self.wallets.primary(),
+            scheduled_bets: Vec::new(),
+            stakers: HashSet::new(),
+        };
+
+        if self.tasks.contains(&Task::Schedule) {
+            snapshot.scheduled_bets = fetch_scheduled_bets(&self.rpc)?;
+            if !snapshot.scheduled_bets.is_empty() {
+                snapshot.stakers = fetch_stake_accounts(&self.rpc)?
+                    .into_iter()
+                    .map(|(_, stake_account)| stake_account.owner)
+                    .collect();
+            }
+        }
+
+        if self.tasks.contains(&Task::Resolve) {
+            let resolvable: Vec<Pubkey> = snapshot.resolvable().copied().collect();
+            for market in resolvable {
+                let Some(oracle) = self.oracles.get(&market) else {
+                    continue;
+                };
+                match oracle.outcome() {
+                    Ok(Some(outcome)) => {
+                        snapshot.outcomes.insert(market, outcome);
+                    }
+                    Ok(None) => {}
+                    Err(err) => eprintln!("warning: oracle for {market}: {err:#}"),
+                }
+            }
+        }
+
+        let distribute = self.tasks.contains(&Task::Distribute);
+        let sweep = self.tasks.contains(&Task::Sweep);
+        let cashout = self.tasks.contains(&Task::CashOut);
+        if !distribute && !sweep && !cashout {
+            return Ok(snapshot);
+        }
+        snapshot.positions = fetch_positions(&self.rpc)?;
+        if distribute || cashout {
+            let mints: HashMap<Pubkey, Pubkey> = snapshot
+                .markets
+                .iter()
+                .map(|(address, market)| (*address, market.mint))
+                .collect();
+            let mut payees = Vec::new();
+            if distribute {
+                payees.extend(snapshot.owed()?);
+                payees.extend(snapshot.fee_payees()?);
+            }
+            if cashout {
+                payees.extend(snapshot.cashout_owners()?);
+            }
+            let token_accounts: Vec<Pubkey> = payees
+                .iter()
+                .map(|(market, owner)| token_account_address(owner, &mints[market]))
+                .collect();
+            let existing = self.rpc.get_multiple_accounts(&token_accounts)?;
+            snapshot.token_accounts = token_accounts
+                .into_iter()
+                .zip(existing)
+                .filter_map(|(address, account)| account.map(|_| address))
+                .collect();
+        }
+        if sweep {
+            let expired: Vec<Pubkey> = snapshot
+                .markets
+                .iter()
+                .filter(|(_, market)| {
+                    matches!(
+                        market.status,
+                        MarketStatus::Resolved | MarketStatus::Cancelled
+                    ) && now >= market.resolve_deadline_ts.saturating_add(CLAIM_WINDOW_SECS)
+                })
+                .map(|(address, _)| *address)
+                .collect();
+            let vaults: Vec<Pubkey> = expired
+                .iter()
+                .flat_map(|market| {
+                    [BetSide::A, BetSide::B].map(|side| vault_address(market, side).0)
+                })
+                .collect();
+            let accounts = self.rpc.get_multiple_accounts(&vaults)?;
+            for (market, pair) in expired.into_iter().zip(accounts.chunks(2)) {
+                let mut total = None;
+                for data in pair.iter().flatten() {
+                    let vault = TokenAccount::try_deserialize(&mut data.as_slice())
+                        .with_context(|| format!("decoding a vault of {market}"))?;
+                    total = Some(total.unwrap_or(0) + vault.amount);
+                }
+                if let Some(total) = total {
+                    snapshot.vaults.insert(market, total);
+                }
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Sends `actions`, up to each task's concurrency limit at a time, and
+    /// returns the descriptions of those that failed.
+    fn execute(&self, actions: Vec<Action>) -> Vec<String> {
+        let total = actions.len();
+        let mut queues: HashMap<Task, VecDeque<Action>> = HashMap::new();
+        for action in actions {
+            queues.entry(action.task).or_default().push_back(action);
+        }
+        let queues: Vec<(Task, Mutex<VecDeque<Action>>)> = queues
+            .into_iter()
+            .map(|(task, queue)| (task, Mutex::new(queue)))
+            .collect();
+        let failures = Mutex::new(Vec::new());
+        thread::scope(|scope| {
+            for (task, queue) in &queues {
+                let workers = self.concurrency[task].min(queue.lock().unwrap().len());
+                for _ in 0..workers {
+                    scope.spawn(|| {
+                        // Taken in its own statement so the lock isn't held
+                        // while sending
+                        loop {
+                            let Some(action) = queue.lock().unwrap().pop_front() else {
+                                break;
+                            };
+                            if let Err(err) = self.send(&action) {
+                                eprintln!("failed to {}: {err:#}", action.description);
+                                failures.lock().unwrap().push(action.description);
+                            }
+                        }
+                    });
+                }
+            }
+        });
+        let failures = failures.into_inner().unwrap();
+        println!("{} sent, {} failed", total - failures.len(), failures.len());
+        failures
+    }
+
+    fn send(&self, action: &Action) -> Result<()> {
+        let payer = self.wallets.payer(action.signer.as_ref())?;
+        let signature = wallet::send(&self.rpc, payer, &action.instructions)?;
+        println!(
+            "[{}] {} ({signature}, paid by {})",
+            action.task.name(),
+            action.description,
+            payer.pubkey()
+        );
+        Ok(())
+    }
+}
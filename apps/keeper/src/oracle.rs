@@ -0,0 +1,139 @@
+//! Oracles: where the keeper looks up the outcome of markets it resolves.
+//!
+//! The program only accepts a resolution signed by the market's creator, so
+//! the keeper resolves markets created by one of its wallets, once betting
+//! is over and the market's oracle reports a winner. Oracles are listed in
+//! the TOML file given to `--oracles`:
+//!
+//! ```toml
+//! [[oracle]]
+//! market = "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi"
+//! url = "https://scores.example.com/games/1234"
+//! # JSON pointer to the result in the response
+//! pointer = "/result/winner"
+//! # Values of the result meaning each side won
+//! a = "home"
+//! b = "away"
+//! # Extra request headers; optional
+//! headers = ["Authorization: Bearer ..."]
+//! ```
+//!
+//! Any other result, including none, means the outcome isn't known yet and
+//! the market is checked again next round. Numbers and booleans are compared
+//! in their JSON form, so `a = "1"` matches `{"winner": 1}`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, bail, Context, Result};
+use friend_bets_sdk::BetSide;
+use reqwest::blocking::Client;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// How long one lookup may take.
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    oracle: Vec<Oracle>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Oracle {
+    market: String,
+    url: String,
+    pointer: String,
+    a: String,
+    b: String,
+    #[serde(default)]
+    headers: Vec<String>,
+}
+
+/// Reads and checks the oracles in `path`, by market.
+pub fn load(path: &str) -> Result<HashMap<Pubkey, Oracle>> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+    let file: ConfigFile = toml::from_str(&text).with_context(|| format!("parsing {path}"))?;
+    let mut oracles = HashMap::new();
+    for oracle in file.oracle {
+        let market: Pubkey = oracle
+            .market
+            .parse()
+            .map_err(|_| anyhow!("oracle: invalid market {:?}", oracle.market))?;
+        if !oracle.url.starts_with("https://") && !oracle.url.starts_with("http://") {
+            bail!("oracle for {market}: url must be http(s)");
+        }
+        if !oracle.pointer.is_empty() && !oracle.pointer.starts_with('/') {
+            bail!("oracle for {market}: pointer must start with /");
+        }
+        if oracle.a == oracle.b {
+            bail!("oracle for {market}: a and b must differ");
+        }
+        oracle
+            .header_map()
+            .with_context(|| format!("oracle for {market}"))?;
+        if oracles.insert(market, oracle).is_some() {
+            bail!("two oracles are given for {market}");
+        }
+    }
+    Ok(oracles)
+}
+
+impl Oracle {
+    /// The winning side, once the oracle reports one.
+    pub fn outcome(&self) -> Result<Option<BetSide>> {
+        static HTTP: OnceLock<Client> = OnceLock::new();
+        let http = HTTP.get_or_init(|| {
+            Client::builder()
+                .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+                .build()
+                .expect("building the HTTP client")
+        });
+        let response = http
+            .get(&self.url)
+            .headers(self.header_map()?)
+            .send()?
+            .error_for_status()?;
+        let response: Value =
+            serde_json::from_str(&response.text()?).context("response isn't JSON")?;
+        Ok(self.side(response.pointer(&self.pointer)))
+    }
+
+    /// `headers`, each given as `Name: value`.
+    fn header_map(&self) -> Result<HeaderMap> {
+        let mut map = HeaderMap::new();
+        for header in &self.headers {
+            let (name, value) = header
+                .split_once(':')
+                .ok_or_else(|| anyhow!("header {header:?} should read `Name: value`"))?;
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .with_context(|| format!("invalid header name in {header:?}"))?;
+            let value = HeaderValue::from_str(value.trim())
+                .with_context(|| format!("invalid header value in {header:?}"))?;
+            map.append(name, value);
+        }
+        Ok(map)
+    }
+
+    fn side(&self, result: Option<&Value>) -> Option<BetSide> {
+        let result = match result? {
+            Value::String(text) => text.clone(),
+            Value::Null => return None,
+            other => other.to_string(),
+        };
+        if result == self.a {
+            Some(BetSide::A)
+        } else if result == self.b {
+            Some(BetSide::B)
+        } else {
+            None
+        }
+    }
+}
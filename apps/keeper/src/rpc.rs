@@ -0,0 +1,245 @@
+//! Minimal blocking JSON-RPC client for reading accounts and sending
+//! transactions.
+//!
+//! Requests go through `reqwest`'s blocking client, as in the CLI.
+//! Everything is read at `confirmed`.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use friend_bets_sdk::failover::{
+    parse_endpoints, Endpoints, Failure, HealthPolicy, ENDPOINT_ERROR_CODES,
+};
+use friend_bets_sdk::fetch::{AccountFilter, ProgramAccounts};
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+use solana_signature::Signature;
+
+const COMMITMENT: &str = "confirmed";
+
+pub struct RpcClient {
+    http: Client,
+    endpoints: Endpoints,
+}
+
+impl RpcClient {
+    /// `urls` lists endpoints, comma-separated and most preferred first;
+    /// `URL#N` sends one at most `N` requests a second.
+    pub fn new(urls: &str) -> Result<Self> {
+        Ok(Self {
+            http: Client::new(),
+            endpoints: Endpoints::new(parse_endpoints(urls)?, HealthPolicy::default()),
+        })
+    }
+
+    /// Sends a request to the first endpoint that's up, failing over to the
+    /// next when one is unreachable, overloaded or behind.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let checked = self.endpoints.check_due(|url| {
+            post(
+                &self.http,
+                url,
+                "getHealth",
+                &request("getHealth", json!([])),
+            )
+            .is_ok()
+        });
+        for (url, _) in checked.into_iter().flatten().filter(|(_, up)| !up) {
+            eprintln!("warning: RPC endpoint {url} failed its health check");
+        }
+        let body = request(method, params);
+        self.endpoints
+            .call(|url| post(&self.http, url, method, &body))
+    }
+
+    fn config() -> Value {
+        json!({ "commitment": COMMITMENT, "encoding": "base64" })
+    }
+
+    /// Data of each of `addresses`, in order; `None` for missing accounts.
+    pub fn get_multiple_accounts(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut accounts = Vec::with_capacity(addresses.len());
+        // The RPC accepts at most 100 addresses per request
+        for chunk in addresses.chunks(100) {
+            let keys: Vec<String> = chunk.iter().map(ToString::to_string).collect();
+            let result = self.call("getMultipleAccounts", json!([keys, Self::config()]))?;
+            for value in result["value"].as_array().into_iter().flatten() {
+                accounts.push(account_data(value)?);
+            }
+        }
+        Ok(accounts)
+    }
+
+    pub fn get_balance(&self, address: &Pubkey) -> Result<u64> {
+        Ok(self.call(
+            "getBalance",
+            json!([address.to_string(), { "commitment": COMMITMENT }]),
+        )?["value"]
+            .as_u64()
+            .unwrap_or_default())
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<(Hash, u64)> {
+        let result = self.call("getLatestBlockhash", json!([{ "commitment": COMMITMENT }]))?;
+        let blockhash = result["value"]["blockhash"]
+            .as_str()
+            .ok_or_else(|| anyhow!("getLatestBlockhash: missing blockhash"))?
+            .parse()
+            .map_err(|_| anyhow!("getLatestBlockhash: invalid blockhash"))?;
+        let last_valid = result["value"]["lastValidBlockHeight"]
+            .as_u64()
+            .unwrap_or_default();
+        Ok((blockhash, last_valid))
+    }
+
+    pub fn get_block_height(&self) -> Result<u64> {
+        Ok(self
+            .call("getBlockHeight", json!([{ "commitment": COMMITMENT }]))?
+            .as_u64()
+            .unwrap_or_default())
+    }
+
+    pub fn get_recent_prioritization_fees(&self, writable: &[Pubkey]) -> Result<Vec<u64>> {
+        // The RPC accepts at most 128 accounts
+        let keys: Vec<String> = writable.iter().take(128).map(ToString::to_string).collect();
+        let result = self.call("getRecentPrioritizationFees", json!([keys]))?;
+        Ok(result
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|fee| fee["prioritizationFee"].as_u64())
+            .collect())
+    }
+
+    pub fn send_transaction(&self, wire: &[u8]) -> Result<Signature> {
+        let result = self.call(
+            "sendTransaction",
+            json!([STANDARD.encode(wire), { "encoding": "base64", "skipPreflight": true }]),
+        )?;
+        result
+            .as_str()
+            .ok_or_else(|| anyhow!("sendTransaction: missing signature"))?
+            .parse()
+            .map_err(|_| anyhow!("sendTransaction: invalid signature"))
+    }
+
+    /// Status of `signature`: `None` if unknown, otherwise its confirmation
+    /// status and error, if any.
+    pub fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<(String, Option<String>)>> {
+        let result = self.call(
+            "getSignatureStatuses",
+            json!([[signature.to_string()], { "searchTransactionHistory": false }]),
+        )?;
+        let status = &result["value"][0];
+        if status.is_null() {
+            return Ok(None);
+        }
+        let confirmation = status["confirmationStatus"]
+            .as_str()
+            .unwrap_or("processed")
+            .to_string();
+        let err = (!status["err"].is_null()).then(|| status["err"].to_string());
+        Ok(Some((confirmation, err)))
+    }
+}
+
+fn account_data(value: &Value) -> Result<Option<Vec<u8>>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    let encoded = value["data"][0]
+        .as_str()
+        .ok_or_else(|| anyhow!("account data isn't base64"))?;
+    Ok(Some(STANDARD.decode(encoded)?))
+}
+
+impl ProgramAccounts for RpcClient {
+    type Error = anyhow::Error;
+
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[AccountFilter],
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>> {
+        let filters: Vec<Value> = filters
+            .iter()
+            .map(|filter| match filter {
+                AccountFilter::DataSize(size) => json!({ "dataSize": size }),
+                AccountFilter::Memcmp { offset, bytes } => json!({
+                    "memcmp": { "offset": offset, "bytes": STANDARD.encode(bytes), "encoding": "base64" }
+                }),
+            })
+            .collect();
+        let mut config = Self::config();
+        config["filters"] = Value::Array(filters);
+
+        let result = self.call(
+            "getProgramAccounts",
+            json!([program_id.to_string(), config]),
+        )?;
+        result
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|entry| {
+                let address = entry["pubkey"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .parse()
+                    .map_err(|_| anyhow!("getProgramAccounts: invalid pubkey"))?;
+                let data = account_data(&entry["account"])?.unwrap_or_default();
+                Ok((address, data))
+            })
+            .collect()
+    }
+}
+
+fn request(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params })
+}
+
+/// Sends `body` to one endpoint, telling the endpoint's failures (no
+/// answer, an HTTP error or a node that's behind) from the request's.
+fn post(
+    http: &Client,
+    url: &str,
+    method: &str,
+    body: &Value,
+) -> Result<Value, Failure<anyhow::Error>> {
+    let endpoint = |err: anyhow::Error| Failure::Endpoint(err.context(method.to_string()));
+    let response = http
+        .post(url)
+        .json(body)
+        .send()
+        .map_err(|err| endpoint(err.into()))?;
+    let status = response.status();
+    let text = response.text().map_err(|err| endpoint(err.into()))?;
+
+    let mut response: Value = match serde_json::from_str(&text) {
+        Ok(response) => response,
+        Err(_) if !status.is_success() => return Err(endpoint(anyhow!("HTTP {status}"))),
+        Err(_) => return Err(endpoint(anyhow!("invalid response"))),
+    };
+    if let Some(error) = response.get("error") {
+        let err = anyhow!(
+            "{method}: {}",
+            error["message"].as_str().unwrap_or("rpc error")
+        );
+        let code = error["code"].as_i64().unwrap_or_default();
+        return Err(if ENDPOINT_ERROR_CODES.contains(&code) {
+            Failure::Endpoint(err)
+        } else {
+            Failure::Request(err)
+        });
+    }
+    // An HTTP error without a JSON-RPC one, such as a gateway's
+    if !status.is_success() {
+        return Err(endpoint(anyhow!("HTTP {status}")));
+    }
+    Ok(response["result"].take())
+}
@@ -0,0 +1,813 @@
+//! What the keeper does to each market, worked out from a snapshot of the
+//! program's accounts.
+//!
+//! - `close`: `close_betting` on open markets past `end_ts`.
+//! - `cancel`: `cancel_expired` on markets nobody resolved by their
+//!   deadline, closing betting first if it's still open, and
+//!   `void_dependent` on conditional markets whose parent went the other
+//!   way or was cancelled.
+//! - `resolve`: `resolve`, or `close_and_resolve` while betting is still
+//!   open, on markets whose oracle has reported and whose creator is one of
+//!   the keeper's wallets.
+//! - `distribute`: pays unclaimed winners and staker rebates of finalized
+//!   markets to bettors who already have a token account, in batches, and
+//!   `distribute_fees` on resolved markets whose fee is split, once the
+//!   creator and every recipient have a token account, and
+//!   `donate_to_charity` on resolved markets with a charity cut.
+//! - `sweep`: `collect_insurance` on resolved markets as soon as they have
+//!   an insurance cut to hand over, and `gc_market` on markets past their
+//!   claim window with nothing left owed, no bettor pages left open and at
+//!   most dust in the vaults, which goes to the creator.
+//! - `schedule`: `execute_scheduled_bet` on scheduled bets that are due on
+//!   markets still taking bets, and `cancel_scheduled_bet` on those that
+//!   expired or whose market stopped taking bets, returning their stake.
+//! - `cashout`: `execute_cashout` on positions whose cash-out order is due,
+//!   for the tip, once the owner has a token account to be paid in.
+
+use std::collections::{HashMap, HashSet};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use anchor_spl::token;
+use anyhow::{bail, Result};
+use friend_bets_sdk::friends_bets::CLAIM_WINDOW_SECS;
+use friend_bets_sdk::instructions;
+use friend_bets_sdk::pda::{is_position_of, token_account_address};
+use friend_bets_sdk::simulate::cashout_order_due;
+use friend_bets_sdk::{BetSide, Market, MarketStatus, Position, ScheduledBet};
+
+/// Positions paid per `distribute`, keeping the transaction under the
+/// legacy account limit.
+const DISTRIBUTE_BATCH: usize = 10;
+/// Positions a single `gc_market` can close.
+const GC_MAX_POSITIONS: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Task {
+    Close,
+    Cancel,
+    Resolve,
+    Distribute,
+    Sweep,
+    Schedule,
+    CashOut,
+}
+
+impl Task {
+    pub const ALL: [Task; 7] = [
+        Task::Close,
+        Task::Cancel,
+        Task::Resolve,
+        Task::Distribute,
+        Task::Sweep,
+        Task::Schedule,
+        Task::CashOut,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Task::Close => "close",
+            Task::Cancel => "cancel",
+            Task::Resolve => "resolve",
+            Task::Distribute => "distribute",
+            Task::Sweep => "sweep",
+            Task::Schedule => "schedule",
+            Task::CashOut => "cashout",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        match Task::ALL.into_iter().find(|task| task.name() == name) {
+            Some(task) => Ok(task),
+            None => bail!(
+                "unknown task {name:?}; tasks are {}",
+                Task::ALL.map(Task::name).join(", ")
+            ),
+        }
+    }
+
+    /// Transactions of this kind sent at once, unless `--concurrency` says
+    /// otherwise.
+    pub fn default_concurrency(self) -> usize {
+        match self {
+            Task::Close | Task::Cancel => 4,
+            Task::Resolve | Task::Distribute | Task::Schedule | Task::CashOut => 2,
+            Task::Sweep => 1,
+        }
+    }
+}
+
+pub struct Action {
+    pub task: Task,
+    pub description: String,
+    /// The wallet that has to sign, if it matters which.
+    pub signer: Option<Pubkey>,
+    pub instructions: Vec<Instruction>,
+}
+
+/// The chain as of the start of a round.
+pub struct Snapshot {
+    pub now: i64,
+    pub markets: Vec<(Pubkey, Market)>,
+    /// Positions; only read when distributing, sweeping or cashing out.
+    pub positions: Vec<(Pubkey, Position)>,
+    /// Token accounts of bettors still owed a payout or rebate, of the
+    /// payees of split fees due, and of owners of due cash-out orders,
+    /// that exist.
+    pub token_accounts: HashSet<Pubkey>,
+    /// What the vaults of finalized markets hold together.
+    pub vaults: HashMap<Pubkey, u64>,
+    /// Outcomes oracles have reported.
+    pub outcomes: HashMap<Pubkey, BetSide>,
+    /// The keeper's wallets, which can resolve the markets they created.
+    pub wallets: HashSet<Pubkey>,
+    /// The wallet that collects markets.
+    pub collector: Pubkey,
+    /// Scheduled bets; only read when running `schedule`.
+    pub scheduled_bets: Vec<(Pubkey, ScheduledBet)>,
+    /// Owners of stake accounts, whose scheduled bets are placed at their
+    /// fee discount.
+    pub stakers: HashSet<Pubkey>,
+}
+
+impl Snapshot {
+    /// Markets the keeper could resolve once their oracle reports: betting
+    /// is over or about to be, the deadline hasn't passed, and one of the
+    /// keeper's wallets created them.
+    pub fn resolvable(&self) -> impl Iterator<Item = &Pubkey> {
+        self.markets
+            .iter()
+            .filter(|(_, market)| {
+                matches!(
+                    market.status,
+                    MarketStatus::Open | MarketStatus::PendingResolve
+                ) && self.now >= market.end_ts
+                    && self.now < market.resolve_deadline_ts
+                    && self.wallets.contains(&market.creator)
+            })
+            .map(|(address, _)| address)
+    }
+
+    /// Unclaimed positions that are owed something, by market.
+    pub fn owed(&self) -> Result<Vec<(Pubkey, Pubkey)>> {
+        let mut owed = Vec::new();
+        for (address, market) in &self.markets {
+            if !is_finalized(market) {
+                continue;
+            }
+            for (_, position) in self.positions_of(address) {
+                if !position.claimed && market.claim_total(position)? > 0 {
+                    owed.push((*address, position.owner));
+                }
+            }
+        }
+        Ok(owed)
+    }
+
+    /// The creator and fee split recipients of markets whose split fee is
+    /// due, by market.
+    pub fn fee_payees(&self) -> Result<Vec<(Pubkey, Pubkey)>> {
+        let mut payees = Vec::new();
+        for (address, market) in &self.markets {
+            if split_fee_due(market)? {
+                payees.push((*address, market.creator));
+                payees.extend(
+                    market
+                        .fee_splits
+                        .iter()
+                        .map(|split| (*address, split.recipient)),
+                );
+            }
+        }
+        Ok(payees)
+    }
+
+    /// Owners of positions whose cash-out order is due, by market.
+    pub fn cashout_owners(&self) -> Result<Vec<(Pubkey, Pubkey)>> {
+        let mut owners = Vec::new();
+        for (address, market) in &self.markets {
+            if !cashing_out(market, self.now) {
+                continue;
+            }
+            for (_, position) in self.positions_of(address) {
+                if position.amount > 0 && cashout_order_due(market, position)? {
+                    owners.push((*address, position.owner));
+                }
+            }
+        }
+        Ok(owners)
+    }
+
+    fn positions_of<'a>(
+        &'a self,
+        market: &'a Pubkey,
+    ) -> impl Iterator<Item = &'a (Pubkey, Position)> + 'a {
+        self.positions
+            .iter()
+            .filter(move |(address, position)| is_position_of(address, position, market))
+    }
+
+    fn market(&self, address: &Pubkey) -> Option<&Market> {
+        self.markets
+            .iter()
+            .find(|(candidate, _)| candidate == address)
+            .map(|(_, market)| market)
+    }
+}
+
+/// Whether `market` still takes cash-outs at `now`.
+fn cashing_out(market: &Market, now: i64) -> bool {
+    market.status == MarketStatus::Open && now < market.end_ts && market.allow_cash_out
+}
+
+fn is_finalized(market: &Market) -> bool {
+    matches!(
+        market.status,
+        MarketStatus::Resolved | MarketStatus::Cancelled
+    )
+}
+
+/// Everything the enabled `tasks` would do with `snapshot`.
+pub fn plan(snapshot: &Snapshot, tasks: &HashSet<Task>) -> Result<Vec<Action>> {
+    let mut actions = Vec::new();
+    let mut push = |task, description: String, signer, instructions| {
+        if tasks.contains(&task) {
+            actions.push(Action {
+                task,
+                description,
+                signer,
+                instructions,
+            });
+        }
+    };
+    let now = snapshot.now;
+    for (address, market) in &snapshot.markets {
+        let label = format!("{address} \"{}\"", market.title);
+        match market.status {
+            MarketStatus::Open | MarketStatus::PendingResolve => {
+                let open = market.status == MarketStatus::Open;
+                let parent = market.dependency.and_then(|dependency| {
+                    Some((dependency, snapshot.market(&dependency.market)?))
+                });
+                if let Some((dependency, parent)) = parent {
+                    let failed = match parent.status {
+                        MarketStatus::Cancelled => true,
+                        MarketStatus::Resolved => parent.outcome != Some(dependency.outcome),
+                        _ => false,
+                    };
+                    if failed {
+                        push(
+                            Task::Cancel,
+                            format!("void {label}, as its parent failed"),
+                            None,
+                            vec![instructions::void_dependent(address, &dependency.market)],
+                        );
+                        continue;
+                    }
+                }
+                if now >= market.resolve_deadline_ts {
+                    let mut ixs = Vec::new();
+                    if open {
+                        ixs.push(instructions::close_betting(address));
+                    }
+                    ixs.push(instructions::cancel_expired(address));
+                    push(
+                        Task::Cancel,
+                        format!("cancel unresolved {label}"),
+                        None,
+                        ixs,
+                    );
+                    continue;
+                }
+                if now < market.end_ts {
+                    continue;
+                }
+                // A conditional market can only resolve once its parent has
+                // gone the required way
+                let dependency_met = parent.is_none_or(|(dependency, parent)| {
+                    parent.status == MarketStatus::Resolved
+                        && parent.outcome == Some(dependency.outcome)
+                });
+                let outcome = snapshot.outcomes.get(address).copied();
+                match outcome {
+                    Some(outcome)
+                        if dependency_met
+                            && tasks.contains(&Task::Resolve)
+                            && snapshot.wallets.contains(&market.creator) =>
+                    {
+                        let parent = market.dependency.map(|dependency| dependency.market);
+                        let build = if open {
+                            instructions::close_and_resolve
+                        } else {
+                            instructions::resolve
+                        };
+                        push(
+                            Task::Resolve,
+                            format!("resolve {label} for side {}", side_name(outcome)),
+                            Some(market.creator),
+                            vec![build(&market.creator, address, outcome, parent.as_ref())],
+                        );
+                    }
+                    _ if open => push(
+                        Task::Close,
+                        format!("close betting on {label}"),
+                        None,
+                        vec![instructions::close_betting(address)],
+                    ),
+                    _ => {}
+                }
+            }
+            MarketStatus::Resolved | MarketStatus::Cancelled => {
+                let positions: Vec<&(Pubkey, Position)> = snapshot.positions_of(address).collect();
+                let mut owners = Vec::new();
+                for (_, position) in &positions {
+                    if !position.claimed
+                        && market.claim_total(position)? > 0
+                        && snapshot
+                            .token_accounts
+                            .contains(&token_account_address(&position.owner, &market.mint))
+                    {
+                        owners.push(position.owner);
+                    }
+                }
+                for batch in owners.chunks(DISTRIBUTE_BATCH) {
+                    push(
+                        Task::Distribute,
+                        format!("pay {} positions of {label}", batch.len()),
+                        None,
+                        vec![instructions::distribute(address, &market.mint, batch)],
+                    );
+                }
+                let payees_ready = market
+                    .fee_splits
+                    .iter()
+                    .map(|split| &split.recipient)
+                    .chain([&market.creator])
+                    .all(|payee| {
+                        snapshot
+                            .token_accounts
+                            .contains(&token_account_address(payee, &market.mint))
+                    });
+                if split_fee_due(market)? && payees_ready {
+                    push(
+                        Task::Distribute,
+                        format!("pay the split fee of {label}"),
+                        None,
+                        vec![instructions::distribute_fees(address, market)],
+                    );
+                }
+                if let (true, Some(charity)) = (charity_due(market)?, market.charity) {
+                    push(
+                        Task::Distribute,
+                        format!("donate the charity cut of {label}"),
+                        None,
+                        vec![instructions::donate_to_charity(address, &charity)],
+                    );
+                }
+                if insurance_due(market)? {
+                    push(
+                        Task::Sweep,
+                        format!("collect insurance of {label}"),
+                        Some(snapshot.collector),
+                        vec![instructions::collect_insurance(
+                            &snapshot.collector,
+                            address,
+                            &market.mint,
+                        )],
+                    );
+                }
+                if let Some(owners) = sweepable(snapshot, address, market, &positions)? {
+                    push(
+                        Task::Sweep,
+                        format!("collect {label}"),
+                        Some(snapshot.collector),
+                        vec![instructions::gc_market(
+                            &snapshot.collector,
+                            address,
+                            &market.creator,
+                            &market.mint,
+                            &owners,
+                        )],
+                    );
+                }
+            }
+        }
+    }
+    for (address, scheduled) in &snapshot.scheduled_bets {
+        let Some(market) = snapshot.market(&scheduled.market) else {
+            continue;
+        };
+        let label = format!("{address} on \"{}\"", market.title);
+        let betting = market.status == MarketStatus::Open && now < market.end_ts;
+        if betting && scheduled.is_due(now) {
+            let mut ix = instructions::execute_scheduled_bet(
+                &snapshot.collector,
+                address,
+                scheduled,
+                market,
+            );
+            if snapshot.stakers.contains(&scheduled.owner) {
+                ix = instructions::with_bettor_stake(ix, &scheduled.owner);
+            }
+            push(
+                Task::Schedule,
+                format!("place scheduled bet {label}"),
+                Some(snapshot.collector),
+                vec![ix],
+            );
+        } else if !betting || scheduled.is_expired(now) {
+            push(
+                Task::Schedule,
+                format!("return scheduled bet {label}"),
+                Some(snapshot.collector),
+                vec![instructions::cancel_scheduled_bet(
+                    &snapshot.collector,
+                    address,
+                    scheduled,
+                    &market.mint,
+                )],
+            );
+        }
+    }
+    for (market, owner) in snapshot.cashout_owners()? {
+        let Some(details) = snapshot.market(&market) else {
+            continue;
+        };
+        if !snapshot
+            .token_accounts
+            .contains(&token_account_address(&owner, &details.mint))
+        {
+            continue;
+        }
+        push(
+            Task::CashOut,
+            format!("cash {owner} out of {market} \"{}\"", details.title),
+            Some(snapshot.collector),
+            vec![
+                // The tip is paid in the market's mint
+                create_associated_token_account_idempotent(
+                    &snapshot.collector,
+                    &snapshot.collector,
+                    &details.mint,
+                    &token::ID,
+                ),
+                instructions::execute_cashout(&snapshot.collector, &market, &owner, &details.mint),
+            ],
+        );
+    }
+    Ok(actions)
+}
+
+/// Whether a resolved market still holds a split fee to pay out.
+fn split_fee_due(market: &Market) -> Result<bool> {
+    Ok(market.status == MarketStatus::Resolved
+        && !market.fee_splits.is_empty()
+        && !market.creator_fee_withdrawn
+        && market.fee_amount()? > 0)
+}
+
+/// Whether a resolved market still holds a charity cut to donate.
+fn charity_due(market: &Market) -> Result<bool> {
+    Ok(market.status == MarketStatus::Resolved
+        && !market.charity_paid
+        && market.charity_amount()? > 0)
+}
+
+/// Whether a resolved market still holds an insurance cut to collect.
+fn insurance_due(market: &Market) -> Result<bool> {
+    Ok(market.status == MarketStatus::Resolved
+        && !market.insurance_collected
+        && market.insurance_amount()? > 0)
+}
+
+/// The positions `gc_market` should close, if the market can be collected:
+/// its claim window is over, its bettor pages, activity account, title
+/// registration and scheduled bets are closed, nothing in it is owed to
+/// anyone and its vaults hold no more than dust.
+fn sweepable(
+    snapshot: &Snapshot,
+    address: &Pubkey,
+    market: &Market,
+    positions: &[&(Pubkey, Position)],
+) -> Result<Option<Vec<Pubkey>>> {
+    // Bettor pages, activity accounts and title registrations are left for
+    // `friend-bets crank --gc`, which refunds their payers, to close first.
+    // Scheduled bets are returned by the scheduled-bet task.
+    if snapshot.now < market.resolve_deadline_ts.saturating_add(CLAIM_WINDOW_SECS)
+        || market.bettor_pages > 0
+        || market.track_activity
+        || market.title_registered
+        || market.scheduled_bets > 0
+    {
+        return Ok(None);
+    }
+    if market.status != MarketStatus::Cancelled
+        && !market.creator_fee_withdrawn
+        && market.fee_amount()? > 0
+        || insurance_due(market)?
+        || charity_due(market)?
+    {
+        return Ok(None);
+    }
+    let mut unclaimed = Vec::new();
+    for (_, position) in positions {
+        if !position.claimed {
+            if market.claim_total(position)? > 0 {
+                return Ok(None);
+            }
+            unclaimed.push(position.owner);
+        }
+    }
+    if unclaimed.len() > GC_MAX_POSITIONS {
+        return Ok(None);
+    }
+    match snapshot.vaults.get(address) {
+        Some(amount) if *amount <= market.unowed_balance()? => {}
+        _ => return Ok(None),
+    }
+
+    // Unclaimed positions must be closed; claimed ones ride along while
+    // there's room, returning their rent too.
+    let claimed = positions
+        .iter()
+        .filter(|(_, position)| position.claimed)
+        .map(|(_, position)| position.owner);
+    let mut owners = unclaimed;
+    owners.extend(claimed.take(GC_MAX_POSITIONS - owners.len()));
+    Ok(Some(owners))
+}
+
+fn side_name(side: BetSide) -> &'static str {
+    match side {
+        BetSide::A => "A",
+        BetSide::B => "B",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use friend_bets_sdk::pda::position_address;
+    use friend_bets_sdk::MarketDependency;
+
+    use super::*;
+
+    fn market(creator: Pubkey, status: MarketStatus) -> Market {
+        Market {
+            market_id: 1,
+            creator,
+            mint: Pubkey::new_unique(),
+            vault_a: Pubkey::new_unique(),
+            vault_b: Pubkey::new_unique(),
+            fee_bps: 0,
+            end_ts: 100,
+            resolve_deadline_ts: 200,
+            staked_a: 0,
+            staked_b: 0,
+            status,
+            outcome: None,
+            creator_fee_withdrawn: false,
+            bump: 0,
+            vault_a_bump: 0,
+            vault_b_bump: 0,
+            title: String::new(),
+            allow_cash_out: false,
+            cashout_haircut_bps: 0,
+            retained: 0,
+            lock_odds: false,
+            locked_payout_a: 0,
+            locked_payout_b: 0,
+            forked_from: None,
+            dependency: None,
+            open_positions: 0,
+            burn_fee: false,
+            fee_discounts: 0,
+            discounted_stake: 0,
+            compressed: false,
+            track_bettors: false,
+            bettor_count: 0,
+            bettor_pages: 0,
+            insurance_bps: 0,
+            insurance_collected: false,
+            compensated: 0,
+            charity: None,
+            charity_bps: 0,
+            charity_paid: false,
+            large_bet_threshold: 0,
+            track_activity: false,
+            cap_a: 0,
+            cap_b: 0,
+            max_ratio_bps: 0,
+            title_registered: false,
+            fee_splits: Vec::new(),
+            pools_changed_slot: 0,
+            scheduled_bets: 0,
+        }
+    }
+
+    fn snapshot(now: i64, markets: Vec<(Pubkey, Market)>, wallet: Pubkey) -> Snapshot {
+        Snapshot {
+            now,
+            markets,
+            positions: Vec::new(),
+            token_accounts: HashSet::new(),
+            vaults: HashMap::new(),
+            outcomes: HashMap::new(),
+            wallets: HashSet::from([wallet]),
+            collector: wallet,
+            scheduled_bets: Vec::new(),
+            stakers: HashSet::new(),
+        }
+    }
+
+    fn planned(snapshot: &Snapshot, tasks: &[Task]) -> Vec<(Task, Option<Pubkey>, usize)> {
+        plan(snapshot, &tasks.iter().copied().collect())
+            .unwrap()
+            .into_iter()
+            .map(|action| (action.task, action.signer, action.instructions.len()))
+            .collect()
+    }
+
+    #[test]
+    fn plans_closing_cancelling_and_resolving() {
+        let wallet = Pubkey::new_unique();
+        let (ours, theirs, expired, child) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut parent = market(Pubkey::new_unique(), MarketStatus::Resolved);
+        parent.outcome = Some(BetSide::B);
+        let parent_address = Pubkey::new_unique();
+        let mut conditional = market(wallet, MarketStatus::PendingResolve);
+        conditional.dependency = Some(MarketDependency {
+            market: parent_address,
+            outcome: BetSide::A,
+        });
+        let mut expired_market = market(Pubkey::new_unique(), MarketStatus::Open);
+        expired_market.resolve_deadline_ts = 150;
+        let mut snapshot = snapshot(
+            160,
+            vec![
+                (ours, market(wallet, MarketStatus::Open)),
+                (theirs, market(Pubkey::new_unique(), MarketStatus::Open)),
+                (expired, expired_market),
+                (child, conditional),
+                (parent_address, parent),
+            ],
+            wallet,
+        );
+        let all = Task::ALL;
+
+        // Nobody has reported: everything past its end gets closed
+        assert_eq!(
+            planned(&snapshot, &all),
+            [
+                (Task::Close, None, 1),
+                (Task::Close, None, 1),
+                (Task::Cancel, None, 2),
+                (Task::Cancel, None, 1),
+            ]
+        );
+
+        // Our own market resolves straight from open, theirs can't
+        snapshot.outcomes.insert(ours, BetSide::A);
+        snapshot.outcomes.insert(theirs, BetSide::A);
+        assert_eq!(
+            planned(&snapshot, &all)[..2],
+            [(Task::Resolve, Some(wallet), 1), (Task::Close, None, 1)]
+        );
+        // With resolving off, ours is only closed
+        assert_eq!(
+            planned(&snapshot, &[Task::Close]),
+            [(Task::Close, None, 1), (Task::Close, None, 1)]
+        );
+        // Nothing happens before betting ends
+        snapshot.now = 50;
+        assert_eq!(planned(&snapshot, &all), [(Task::Cancel, None, 1)]);
+    }
+
+    #[test]
+    fn plans_placing_and_returning_scheduled_bets() {
+        let wallet = Pubkey::new_unique();
+        let (open, closed) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let scheduled = |market: Pubkey, execute_after: i64, expiry: i64| {
+            (
+                Pubkey::new_unique(),
+                ScheduledBet {
+                    owner: Pubkey::new_unique(),
+                    market,
+                    id: 0,
+                    side: BetSide::A,
+                    amount: 100,
+                    min_odds_bps: 0,
+                    execute_after,
+                    expiry,
+                    bump: 0,
+                    escrow_bump: 0,
+                },
+            )
+        };
+        let mut snapshot = snapshot(
+            50,
+            vec![
+                (open, market(Pubkey::new_unique(), MarketStatus::Open)),
+                (
+                    closed,
+                    market(Pubkey::new_unique(), MarketStatus::Cancelled),
+                ),
+            ],
+            wallet,
+        );
+        snapshot.scheduled_bets = vec![
+            scheduled(open, 40, 60),
+            scheduled(open, 55, 60),
+            scheduled(open, 10, 20),
+            scheduled(closed, 40, 60),
+        ];
+
+        // The due bet is placed, the expired one and the one on a
+        // cancelled market returned, the early one left alone
+        assert_eq!(
+            planned(&snapshot, &[Task::Schedule]),
+            [
+                (Task::Schedule, Some(wallet), 1),
+                (Task::Schedule, Some(wallet), 1),
+                (Task::Schedule, Some(wallet), 1),
+            ]
+        );
+        let actions = plan(&snapshot, &HashSet::from([Task::Schedule])).unwrap();
+        assert!(actions[0].description.starts_with("place"));
+        assert!(actions[1..]
+            .iter()
+            .all(|action| action.description.starts_with("return")));
+    }
+
+    #[test]
+    fn sweeps_once_scheduled_bets_are_returned() {
+        let wallet = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let mut cancelled = market(Pubkey::new_unique(), MarketStatus::Cancelled);
+        cancelled.scheduled_bets = 1;
+        let mut snapshot = snapshot(200 + CLAIM_WINDOW_SECS, vec![(address, cancelled)], wallet);
+        snapshot.vaults.insert(address, 0);
+
+        // A scheduled bet's escrow would be stranded by collecting the market
+        assert!(planned(&snapshot, &[Task::Sweep]).is_empty());
+        snapshot.markets[0].1.scheduled_bets = 0;
+        assert_eq!(
+            planned(&snapshot, &[Task::Sweep]),
+            [(Task::Sweep, Some(wallet), 1)]
+        );
+    }
+
+    #[test]
+    fn plans_cashing_out_due_orders() {
+        let wallet = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let mut open = market(Pubkey::new_unique(), MarketStatus::Open);
+        open.allow_cash_out = true;
+        open.cashout_haircut_bps = 1_000;
+        open.staked_a = 400;
+        open.staked_b = 400;
+        let mint = open.mint;
+        let mut snapshot = snapshot(50, vec![(address, open)], wallet);
+        // Each 100 staked on A would win 200
+        let orders = [200, 150, 200, 0];
+        let owners: Vec<Pubkey> = orders.iter().map(|_| Pubkey::new_unique()).collect();
+        for (owner, cashout_min_value) in owners.iter().zip(orders) {
+            let (position, bump) = position_address(&address, owner);
+            snapshot.positions.push((
+                position,
+                Position {
+                    owner: *owner,
+                    side: BetSide::A,
+                    amount: 100,
+                    claimed: false,
+                    bump,
+                    locked_payout: 0,
+                    discounted_stake: 0,
+                    cashout_min_value,
+                    compensated: 0,
+                },
+            ));
+        }
+        snapshot.token_accounts = [owners[0], owners[1], owners[3]]
+            .iter()
+            .map(|owner| token_account_address(owner, &mint))
+            .collect();
+
+        // Only the due order whose owner can be paid is filled, creating
+        // the keeper's token account for the tip first
+        assert_eq!(
+            planned(&snapshot, &[Task::CashOut]),
+            [(Task::CashOut, Some(wallet), 2)]
+        );
+        let actions = plan(&snapshot, &HashSet::from([Task::CashOut])).unwrap();
+        assert!(actions[0].description.contains(&owners[0].to_string()));
+        // Nothing is cashed out once betting ends
+        snapshot.now = 100;
+        assert!(planned(&snapshot, &[Task::CashOut]).is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+//! The keypairs the keeper signs and pays with.
+//!
+//! Transactions are spread round-robin over the wallets holding at least
+//! the minimum balance, so one wallet running dry doesn't stall everything.
+//! Resolutions are the exception: the program only takes them from a
+//! market's creator, so they're paid by that wallet or not sent at all.
+//! Balances are read at the start of each round.
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::{anyhow, bail, Context, Result};
+use friend_bets_sdk::keypair::Keypair;
+use friend_bets_sdk::offline::PartiallySignedTransaction;
+use friend_bets_sdk::send::{
+    send_with_retry, Commitment, SendClient, SendConfig, SendError, SignatureStatus,
+};
+use solana_message::VersionedMessage;
+use solana_signature::Signature;
+
+use crate::rpc::RpcClient;
+
+pub struct Wallet {
+    pub keypair: Keypair,
+    /// Lamports as of the last refresh.
+    pub balance: u64,
+}
+
+pub struct Wallets {
+    wallets: Vec<Wallet>,
+    min_balance: u64,
+    next: AtomicUsize,
+}
+
+impl Wallets {
+    /// Reads each of `paths`, a keypair file or a directory of them.
+    pub fn load(paths: &str, min_balance: u64) -> Result<Self> {
+        let mut wallets: Vec<Wallet> = Vec::new();
+        for path in paths.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let path = Path::new(path);
+            let files = if path.is_dir() {
+                let mut files: Vec<_> = fs::read_dir(path)
+                    .with_context(|| format!("reading {}", path.display()))?
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<Result<_, _>>()?;
+                files.retain(|file| file.extension().is_some_and(|ext| ext == "json"));
+                files.sort();
+                files
+            } else {
+                vec![path.to_path_buf()]
+            };
+            for file in files {
+                let keypair = Keypair::read(&file)?;
+                if wallets
+                    .iter()
+                    .all(|w| w.keypair.pubkey() != keypair.pubkey())
+                {
+                    wallets.push(Wallet {
+                        keypair,
+                        balance: 0,
+                    });
+                }
+            }
+        }
+        if wallets.is_empty() {
+            bail!("no keypairs found in {paths}");
+        }
+        Ok(Self {
+            wallets,
+            min_balance,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn pubkeys(&self) -> Vec<Pubkey> {
+        self.wallets.iter().map(|w| w.keypair.pubkey()).collect()
+    }
+
+    /// The first wallet, which collects markets and so receives the rent
+    /// `gc_market` pays its caller.
+    pub fn primary(&self) -> Pubkey {
+        self.wallets[0].keypair.pubkey()
+    }
+
+    /// Reads every wallet's balance, warning about those under the minimum.
+    pub fn refresh(&mut self, rpc: &RpcClient) -> Result<()> {
+        for wallet in &mut self.wallets {
+            wallet.balance = rpc.get_balance(&wallet.keypair.pubkey())?;
+            if wallet.balance < self.min_balance {
+                eprintln!(
+                    "warning: wallet {} is down to {} SOL; it won't pay for anything until topped up",
+                    wallet.keypair.pubkey(),
+                    sol(wallet.balance)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The wallet to pay with: `signer` if given, otherwise the next funded
+    /// one in turn.
+    pub fn payer(&self, signer: Option<&Pubkey>) -> Result<&Keypair> {
+        let funded = |wallet: &&Wallet| wallet.balance >= self.min_balance;
+        if let Some(signer) = signer {
+            let wallet = self
+                .wallets
+                .iter()
+                .find(|w| w.keypair.pubkey() == *signer)
+                .ok_or_else(|| anyhow!("no wallet for {signer}"))?;
+            if !funded(&wallet) {
+                bail!("wallet {signer} is under the minimum balance");
+            }
+            return Ok(&wallet.keypair);
+        }
+        let funded: Vec<&Wallet> = self.wallets.iter().filter(funded).collect();
+        if funded.is_empty() {
+            bail!("every wallet is under the minimum balance");
+        }
+        let turn = self.next.fetch_add(1, Ordering::Relaxed);
+        Ok(&funded[turn % funded.len()].keypair)
+    }
+}
+
+fn sol(lamports: u64) -> String {
+    format!(
+        "{}.{:09}",
+        lamports / 1_000_000_000,
+        lamports % 1_000_000_000
+    )
+}
+
+/// Signs `instructions` with `payer` and sends them, waiting for
+/// confirmation.
+pub fn send(rpc: &RpcClient, payer: &Keypair, instructions: &[Instruction]) -> Result<Signature> {
+    let config = SendConfig {
+        commitment: Commitment::Confirmed,
+        poll_interval: Duration::from_millis(800),
+        ..Default::default()
+    };
+    send_with_retry(
+        &Sender { rpc, payer },
+        &payer.pubkey(),
+        instructions,
+        &[],
+        &config,
+    )
+    .map_err(|err| match err {
+        SendError::Rpc(err) => err,
+        other => anyhow!("{other}"),
+    })
+}
+
+struct Sender<'a> {
+    rpc: &'a RpcClient,
+    payer: &'a Keypair,
+}
+
+impl SendClient for Sender<'_> {
+    type Error = anyhow::Error;
+
+    fn recent_prioritization_fees(&self, writable: &[Pubkey]) -> Result<Vec<u64>> {
+        self.rpc.get_recent_prioritization_fees(writable)
+    }
+
+    fn latest_blockhash(&self) -> Result<(Hash, u64)> {
+        self.rpc.get_latest_blockhash()
+    }
+
+    fn block_height(&self) -> Result<u64> {
+        self.rpc.get_block_height()
+    }
+
+    fn sign_and_send(&self, message: &VersionedMessage) -> Result<Signature> {
+        let mut tx = PartiallySignedTransaction::new(message.clone());
+        let signature = self.payer.sign(&tx.message_bytes());
+        tx.add_signature(&self.payer.pubkey(), signature)?;
+        self.rpc.send_transaction(&tx.serialize())
+    }
+
+    fn signature_status(&self, signature: &Signature) -> Result<Option<SignatureStatus>> {
+        Ok(self
+            .rpc
+            .get_signature_status(signature)?
+            .map(|(confirmation, err)| match err {
+                Some(err) => SignatureStatus::Failed(err),
+                None => SignatureStatus::Landed(match confirmation.as_str() {
+                    "finalized" => Commitment::Finalized,
+                    "confirmed" => Commitment::Confirmed,
+                    _ => Commitment::Processed,
+                }),
+            }))
+    }
+}
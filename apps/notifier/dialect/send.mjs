@@ -0,0 +1,61 @@
+// Sends one alert through the Dialect SDK, as the notifier's dialect
+// channels do for each wallet they alert. The alert is read as JSON from
+// stdin:
+//
+//   {
+//     "keypair": "/etc/friend-bets/dialect.json",
+//     "environment": "production",
+//     "recipient": "<wallet address>",
+//     "title": "...",
+//     "message": "...",
+//     "link": "https://friendbets.app/markets/..."
+//   }
+//
+// The keypair is the dapp registered with Dialect, in the Solana CLI's
+// format. Dialect delivers the alert wherever the recipient subscribed to
+// that dapp: their wallet inbox, email, Telegram or SMS. Failures exit
+// non-zero with the reason on stderr.
+
+import { readFileSync } from "node:fs";
+
+import { DappMessageActionType, Dialect } from "@dialectlabs/sdk";
+import {
+  NodeDialectSolanaWalletAdapter,
+  SolanaSdkFactory,
+} from "@dialectlabs/blockchain-sdk-solana";
+import { Keypair } from "@solana/web3.js";
+
+const alert = JSON.parse(readFileSync(0, "utf8"));
+const secretKey = Uint8Array.from(
+  JSON.parse(readFileSync(alert.keypair, "utf8"))
+);
+
+const sdk = Dialect.sdk(
+  { environment: alert.environment ?? "production" },
+  SolanaSdkFactory.create({
+    wallet: NodeDialectSolanaWalletAdapter.create(
+      Keypair.fromSecretKey(secretKey)
+    ),
+  })
+);
+
+try {
+  const dapp = await sdk.dapps.find();
+  if (!dapp) {
+    throw new Error("the keypair isn't registered with Dialect as a dapp");
+  }
+  await dapp.messages.send({
+    recipient: alert.recipient,
+    title: alert.title,
+    message: alert.message,
+    ...(alert.link && {
+      actionsV2: {
+        type: DappMessageActionType.LINK,
+        links: [{ label: "Open market", url: alert.link }],
+      },
+    }),
+  });
+} catch (err) {
+  console.error(err instanceof Error ? err.message : String(err));
+  process.exit(1);
+}
@@ -0,0 +1,577 @@
+//! Channels and what each is told about.
+//!
+//! Channels are listed in the TOML file given to `--config`:
+//!
+//! ```toml
+//! [[channel]]
+//! name = "friday-poker"
+//! # Telegram: a bot token and the chat to post in
+//! kind = "telegram"
+//! token = "123456:ABC..."
+//! chat_id = "-1001234567890"
+//!
+//! [[channel]]
+//! name = "office-discord"
+//! # Discord: a channel webhook
+//! kind = "discord"
+//! webhook_url = "https://discord.com/api/webhooks/..."
+//! # Markets to follow, and creators whose markets to follow; with
+//! # neither, every market
+//! markets = ["4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi"]
+//! creators = ["8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR"]
+//! # What to post; all of these by default
+//! notify = ["market_created", "big_bet", "closing_soon", "resolve_due", "resolved", "unclaimed"]
+//! # Amounts are shown in whole tokens with this many decimals
+//! decimals = 6
+//! symbol = "USDC"
+//! # Smallest bet worth a post, in whole tokens; without it, bets of at
+//! # least their market's own large bet threshold are posted
+//! big_bet = "50"
+//! # How long before betting closes to warn, how long before the resolve
+//! # deadline to remind the creator, and how often to remind winners who
+//! # haven't claimed
+//! closing_soon_minutes = 60
+//! resolve_due_hours = 6
+//! reminder_hours = 24
+//! # Appended to each post, with {market} replaced by its address
+//! link = "https://friendbets.app/markets/{market}"
+//!
+//! [[channel]]
+//! name = "wallets"
+//! # Dialect: alerts sent to bettors' wallets through the Dialect SDK,
+//! # by dialect/send.mjs (run `npm install` next to it). They're sent as
+//! # the dapp registered with Dialect under this keypair, and only wallets
+//! # subscribed to that dapp receive them, wherever they subscribed to:
+//! # wallet inbox, email, Telegram or SMS.
+//! kind = "dialect"
+//! keypair = "/etc/friend-bets/dialect.json"
+//! sender = "/opt/friend-bets/notifier/dialect/send.mjs"
+//! # Dialect's environment; "production" by default
+//! environment = "production"
+//! # Only "closing_soon", "resolve_due", "resolved" and "unclaimed", which
+//! # are all by default
+//! notify = ["resolve_due", "resolved", "unclaimed"]
+//!
+//! [[channel]]
+//! name = "email"
+//! # Email: alerts sent to the addresses wallets subscribed through the
+//! # API's /subscriptions, over SMTP to the provider
+//! kind = "email"
+//! smtp_url = "smtps://smtp.example.com:465"
+//! smtp_username = "..."
+//! smtp_password = "..."
+//! from = "friend-bets <alerts@example.com>"
+//!
+//! [[channel]]
+//! name = "browsers"
+//! # Web push: alerts sent to the browsers wallets subscribed through the
+//! # API, signed for with a VAPID key (see the push module)
+//! kind = "web_push"
+//! vapid_private_key = "/etc/friend-bets/vapid.pem"
+//! vapid_subject = "mailto:ops@example.com"
+//! ```
+//!
+//! Following a creator's markets is how a group, or league, gets every
+//! market it runs posted to its chat without listing each one. For a
+//! channel alerting wallets they pick the markets whose bettors are
+//! alerted. Email and web push only reach wallets subscribed to the kind
+//! of alert, on top of the channel's `notify`.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, bail, Context, Result};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::push::{self, Subscription};
+
+/// Every kind of post.
+pub const NOTIFICATIONS: [&str; 6] = [
+    "market_created",
+    "big_bet",
+    "closing_soon",
+    "resolve_due",
+    "resolved",
+    "unclaimed",
+];
+
+/// The posts a channel alerting wallets can send one.
+pub const WALLET_NOTIFICATIONS: [&str; 4] =
+    ["closing_soon", "resolve_due", "resolved", "unclaimed"];
+
+/// Dialect's environments.
+pub const DIALECT_ENVIRONMENTS: [&str; 3] = ["production", "development", "local-development"];
+
+/// How long one post may take.
+const REQUEST_TIMEOUT_SECS: u32 = 10;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    channel: Vec<Channel>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Channel {
+    pub name: String,
+    kind: Kind,
+    token: Option<String>,
+    chat_id: Option<String>,
+    webhook_url: Option<String>,
+    keypair: Option<String>,
+    sender: Option<String>,
+    environment: Option<String>,
+    smtp_url: Option<String>,
+    smtp_username: Option<String>,
+    smtp_password: Option<String>,
+    from: Option<String>,
+    vapid_private_key: Option<String>,
+    vapid_subject: Option<String>,
+    #[serde(default)]
+    pub markets: Vec<String>,
+    #[serde(default)]
+    pub creators: Vec<String>,
+    #[serde(default)]
+    notify: Vec<String>,
+    #[serde(default)]
+    pub decimals: u8,
+    pub symbol: Option<String>,
+    big_bet: Option<String>,
+    #[serde(default = "default_closing_soon_minutes")]
+    pub closing_soon_minutes: u32,
+    #[serde(default = "default_resolve_due_hours")]
+    pub resolve_due_hours: u32,
+    #[serde(default = "default_reminder_hours")]
+    pub reminder_hours: u32,
+    link: Option<String>,
+}
+
+fn default_closing_soon_minutes() -> u32 {
+    60
+}
+
+fn default_resolve_due_hours() -> u32 {
+    6
+}
+
+fn default_reminder_hours() -> u32 {
+    24
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Telegram,
+    Discord,
+    Dialect,
+    Email,
+    #[serde(rename = "web_push")]
+    WebPush,
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Telegram => "telegram",
+            Kind::Discord => "discord",
+            Kind::Dialect => "dialect",
+            Kind::Email => "email",
+            Kind::WebPush => "web_push",
+        }
+    }
+}
+
+/// Reads and checks the channels in `path`.
+pub fn load(path: &str) -> Result<Vec<Channel>> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+    let file: ConfigFile = toml::from_str(&text).with_context(|| format!("parsing {path}"))?;
+    if file.channel.is_empty() {
+        bail!("{path} lists no channels");
+    }
+    for (i, channel) in file.channel.iter().enumerate() {
+        let name = &channel.name;
+        if name.is_empty() {
+            bail!("channel {} has no name", i + 1);
+        }
+        if file.channel[..i].iter().any(|other| other.name == *name) {
+            bail!("two channels are named {name:?}");
+        }
+        match channel.kind {
+            Kind::Telegram if channel.token.is_none() || channel.chat_id.is_none() => {
+                bail!("channel {name:?}: telegram channels need token and chat_id")
+            }
+            Kind::Discord => match &channel.webhook_url {
+                Some(url) if url.starts_with("https://") || url.starts_with("http://") => {}
+                _ => bail!("channel {name:?}: discord channels need an http(s) webhook_url"),
+            },
+            Kind::Dialect => match (&channel.keypair, &channel.sender) {
+                (Some(keypair), Some(sender)) => {
+                    dapp_keypair(keypair).with_context(|| format!("channel {name:?}"))?;
+                    if !Path::new(sender).is_file() {
+                        bail!("channel {name:?}: no sender at {sender}");
+                    }
+                }
+                _ => bail!("channel {name:?}: dialect channels need keypair and sender"),
+            },
+            Kind::Email => match (&channel.smtp_url, &channel.from) {
+                (Some(url), Some(from))
+                    if (url.starts_with("smtp://") || url.starts_with("smtps://"))
+                        && from.parse::<Mailbox>().is_ok() => {}
+                _ => bail!(
+                    "channel {name:?}: email channels need an smtp(s) smtp_url and a from address"
+                ),
+            },
+            Kind::WebPush => match (&channel.vapid_private_key, &channel.vapid_subject) {
+                (Some(key), Some(subject))
+                    if subject.starts_with("mailto:") || subject.starts_with("https://") =>
+                {
+                    push::public_key(key).with_context(|| format!("channel {name:?}"))?;
+                }
+                _ => bail!(
+                    "channel {name:?}: web_push channels need vapid_private_key and a \
+                     mailto: or https vapid_subject"
+                ),
+            },
+            Kind::Telegram => {}
+        }
+        let notifications: &[&str] = if channel.alerts_wallets() {
+            &WALLET_NOTIFICATIONS
+        } else {
+            &NOTIFICATIONS
+        };
+        if let Some(kind) = channel
+            .notify
+            .iter()
+            .find(|kind| !notifications.contains(&kind.as_str()))
+        {
+            bail!(
+                "channel {name:?}: unknown notification {kind:?}; notifications are {}",
+                notifications.join(", ")
+            );
+        }
+        if let Some(environment) = channel
+            .environment
+            .as_ref()
+            .filter(|environment| !DIALECT_ENVIRONMENTS.contains(&environment.as_str()))
+        {
+            bail!(
+                "channel {name:?}: unknown environment {environment:?}; they are {}",
+                DIALECT_ENVIRONMENTS.join(", ")
+            );
+        }
+        if let Some(big_bet) = &channel.big_bet {
+            units(big_bet, channel.decimals)
+                .with_context(|| format!("channel {name:?}: invalid big_bet"))?;
+        }
+        for address in channel.markets.iter().chain(&channel.creators) {
+            address
+                .parse::<Pubkey>()
+                .map_err(|_| anyhow!("channel {name:?}: invalid address {address:?}"))?;
+        }
+    }
+    Ok(file.channel)
+}
+
+impl Channel {
+    pub fn wants(&self, notification: &str) -> bool {
+        if self.notify.is_empty() {
+            return !self.alerts_wallets() || WALLET_NOTIFICATIONS.contains(&notification);
+        }
+        self.notify.iter().any(|kind| kind == notification)
+    }
+
+    /// Whether posts go to bettors' wallets, through [`Channel::alert`] or
+    /// their subscriptions, rather than to a chat.
+    pub fn alerts_wallets(&self) -> bool {
+        matches!(self.kind, Kind::Dialect | Kind::Email | Kind::WebPush)
+    }
+
+    /// The kind of subscription in the `subscriptions` table a wallet needs
+    /// to be alerted through this channel, for email and web push.
+    pub fn subscriptions(&self) -> Option<&'static str> {
+        match self.kind {
+            Kind::Email => Some("email"),
+            Kind::WebPush => Some("push"),
+            Kind::Telegram | Kind::Discord | Kind::Dialect => None,
+        }
+    }
+
+    /// The public half of a web push channel's VAPID key, for the site to
+    /// subscribe browsers with.
+    pub fn vapid_public_key(&self) -> Option<Result<String>> {
+        self.vapid_private_key.as_deref().map(push::public_key)
+    }
+
+    /// The big bet threshold in raw units, if big bets are posted.
+    pub fn big_bet(&self) -> Option<u128> {
+        let threshold = self.big_bet.as_deref()?;
+        units(threshold, self.decimals).ok()
+    }
+
+    /// `raw` token units in whole tokens, such as `12.5 USDC`.
+    pub fn amount(&self, raw: &str) -> String {
+        let mut amount = raw.trim_start_matches('0').to_string();
+        let decimals = usize::from(self.decimals);
+        if decimals > 0 {
+            if amount.len() <= decimals {
+                amount = format!("{amount:0>width$}", width = decimals + 1);
+            }
+            amount.insert(amount.len() - decimals, '.');
+            amount = amount
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string();
+        }
+        if amount.is_empty() {
+            amount.push('0');
+        }
+        match &self.symbol {
+            Some(symbol) => format!("{amount} {symbol}"),
+            None => amount,
+        }
+    }
+
+    /// Posts `text` about `market`, with the channel's link to it.
+    pub fn post(&self, text: &str, market: &str) -> Result<()> {
+        let text = match &self.link {
+            Some(link) => format!("{text}\n{}", link.replace("{market}", market)),
+            None => text.to_string(),
+        };
+        self.send(&text)
+    }
+
+    /// Alerts `wallet` about `market` through the Dialect SDK, with the
+    /// channel's link to it as the alert's action.
+    pub fn alert(&self, wallet: &str, title: &str, body: &str, market: &str) -> Result<()> {
+        let alert = json!({
+            "keypair": self.keypair,
+            "environment": self.environment.as_deref().unwrap_or("production"),
+            "recipient": wallet,
+            "title": title,
+            "message": body,
+            "link": self.link.as_ref().map(|link| link.replace("{market}", market)),
+        });
+        let mut child = Command::new("node")
+            .arg(self.sender.as_deref().unwrap_or_default())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("running node")?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(alert.to_string().as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(())
+    }
+
+    /// Alerts a wallet through one of its `subscriptions` rows, returning
+    /// `false` when a browser has dropped its push subscription.
+    pub fn notify(
+        &self,
+        subscription: &Value,
+        title: &str,
+        body: &str,
+        market: &str,
+    ) -> Result<bool> {
+        let target = subscription["target"].as_str().unwrap_or_default();
+        let link = self
+            .link
+            .as_ref()
+            .map(|link| link.replace("{market}", market));
+        match self.kind {
+            Kind::Email => {
+                let text = match &link {
+                    Some(link) => format!("{body}\n\n{link}\n"),
+                    None => format!("{body}\n"),
+                };
+                self.mail(target, title, &text).map(|()| true)
+            }
+            Kind::WebPush => {
+                let payload = json!({ "title": title, "body": body, "url": link });
+                let subscription = Subscription {
+                    endpoint: target,
+                    p256dh: subscription["p256dh"].as_str().unwrap_or_default(),
+                    auth: subscription["auth"].as_str().unwrap_or_default(),
+                };
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |now| now.as_secs() as i64);
+                push::send(
+                    &subscription,
+                    payload.to_string().as_bytes(),
+                    self.vapid_private_key.as_deref().unwrap_or_default(),
+                    self.vapid_subject.as_deref().unwrap_or_default(),
+                    now,
+                )
+            }
+            Kind::Telegram | Kind::Discord | Kind::Dialect => {
+                bail!("only email and web push channels have subscriptions")
+            }
+        }
+    }
+
+    /// Emails `text` to `to` through the channel's SMTP server.
+    fn mail(&self, to: &str, subject: &str, text: &str) -> Result<()> {
+        let from: Mailbox = self.from.as_deref().unwrap_or_default().parse()?;
+        let to: Mailbox = to
+            .parse()
+            .with_context(|| format!("invalid address {to:?}"))?;
+        // Subjects carry titles, which are anyone's text: kept to one line
+        let message = Message::builder()
+            .from(from)
+            .to(to)
+            .subject(subject.replace(['\r', '\n'], " "))
+            .header(ContentType::TEXT_PLAIN)
+            .body(text.to_string())?;
+        self.mailer()?.send(&message)?;
+        Ok(())
+    }
+
+    /// The channel's SMTP server: TLS from the start for `smtps://`,
+    /// otherwise upgraded with STARTTLS, but for a relay on this host.
+    fn mailer(&self) -> Result<SmtpTransport> {
+        let url = self.smtp_url.as_deref().unwrap_or_default();
+        let (scheme, address) = url.split_once("://").unwrap_or_default();
+        let address = address.trim_end_matches('/');
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port.parse::<u16>()?)),
+            None => (address, None),
+        };
+        let mut builder = if scheme == "smtps" {
+            SmtpTransport::relay(host)?
+        } else if host == "localhost" || host == "127.0.0.1" {
+            SmtpTransport::builder_dangerous(host)
+        } else {
+            SmtpTransport::starttls_relay(host)?
+        };
+        if let Some(port) = port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = &self.smtp_username {
+            let password = self.smtp_password.clone().unwrap_or_default();
+            builder = builder.credentials(Credentials::new(username.clone(), password));
+        }
+        Ok(builder
+            .timeout(Some(Duration::from_secs(REQUEST_TIMEOUT_SECS.into())))
+            .build())
+    }
+
+    /// Posts `text` as plain text, so titles can't inject markup or
+    /// mentions.
+    pub fn send(&self, text: &str) -> Result<()> {
+        let (url, body) = match self.kind {
+            Kind::Telegram => (
+                format!(
+                    "https://api.telegram.org/bot{}/sendMessage",
+                    self.token.as_deref().unwrap_or_default()
+                ),
+                json!({
+                    "chat_id": self.chat_id,
+                    "text": text,
+                    "disable_web_page_preview": true,
+                }),
+            ),
+            Kind::Discord => (
+                self.webhook_url.clone().unwrap_or_default(),
+                json!({ "content": text, "allowed_mentions": { "parse": [] } }),
+            ),
+            Kind::Dialect | Kind::Email | Kind::WebPush => {
+                bail!("{} channels only alert bettors' wallets", self.kind.name())
+            }
+        };
+        request(&url, &body)
+    }
+}
+
+/// The HTTP client posts and pushes go through, giving each request
+/// [`REQUEST_TIMEOUT_SECS`].
+pub fn http() -> &'static Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS.into()))
+            .build()
+            .expect("building the HTTP client")
+    })
+}
+
+/// POSTs `body` as JSON to `url`.
+fn request(url: &str, body: &Value) -> Result<()> {
+    // Telegram's URL holds the bot token, kept out of errors
+    let response = http()
+        .post(url)
+        .json(body)
+        .send()
+        .map_err(reqwest::Error::without_url)?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let status = status.as_u16();
+    let response = response.text().unwrap_or_default();
+    // Each says why in the body; Telegram in "description", the others in
+    // "message"
+    let reason = serde_json::from_str::<Value>(&response)
+        .ok()
+        .and_then(|body| {
+            body["description"]
+                .as_str()
+                .or(body["message"].as_str())
+                .map(String::from)
+        })
+        .unwrap_or_else(|| response.trim().to_string());
+    if reason.is_empty() {
+        bail!("status {status}");
+    }
+    bail!("status {status}: {reason}")
+}
+
+/// Checks `path` holds a keypair as the Solana CLI writes one: a JSON
+/// array of its 64 secret key bytes.
+fn dapp_keypair(path: &str) -> Result<()> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+    let bytes: Vec<u8> =
+        serde_json::from_str(&text).with_context(|| format!("{path} isn't a keypair file"))?;
+    if bytes.len() != 64 {
+        bail!("{path} isn't a keypair file");
+    }
+    Ok(())
+}
+
+/// Parses an amount in whole tokens into raw units.
+fn units(amount: &str, decimals: u8) -> Result<u128> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let decimals = usize::from(decimals);
+    if fraction.len() > decimals || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        bail!("{amount:?} has more than {decimals} decimals");
+    }
+    let whole: u128 = whole.parse()?;
+    let fraction: u128 = if decimals == 0 {
+        0
+    } else {
+        format!("{fraction:0<decimals$}").parse()?
+    };
+    whole
+        .checked_mul(10u128.pow(decimals as u32))
+        .and_then(|raw| raw.checked_add(fraction))
+        .ok_or_else(|| anyhow!("{amount} is too large"))
+}
@@ -0,0 +1,108 @@
+//! Postgres access through the `postgres` client, as in the indexer. The
+//! notifier reads the indexer's tables and keeps its place in
+//! `indexer_state`.
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::{Context, Result};
+use postgres::types::ToSql;
+use postgres::{Client, Row};
+use rustls::ClientConfig;
+use serde_json::Value;
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+/// A connection made on first use, and again after it drops.
+pub struct Database {
+    url: String,
+    client: Mutex<Option<Client>>,
+}
+
+impl Database {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: Mutex::new(None),
+        }
+    }
+
+    /// Runs a statement, with `args` for its placeholders.
+    pub fn execute(&self, sql: &str, args: &Args) -> Result<()> {
+        self.with_client(|client| client.execute(sql, &args.params()))?;
+        Ok(())
+    }
+
+    /// Runs a query, with `args` for its placeholders.
+    pub fn query(&self, sql: &str, args: &Args) -> Result<Vec<Row>> {
+        self.with_client(|client| client.query(sql, &args.params()))
+    }
+
+    /// Runs a query selecting one JSON value per row.
+    pub fn json(&self, sql: &str, args: &Args) -> Result<Vec<Value>> {
+        self.query(sql, args)?
+            .iter()
+            .map(|row| row.try_get(0).context("decoding a row"))
+            .collect()
+    }
+
+    /// The `indexer_state` value under `key`, if any.
+    pub fn state(&self, key: &str) -> Result<Option<String>> {
+        let mut args = Args::default();
+        let sql = format!(
+            "SELECT value FROM indexer_state WHERE key = {}",
+            args.add(key.to_string())
+        );
+        Ok(self.query(&sql, &args)?.first().map(|row| row.get(0)))
+    }
+
+    /// Saves `value` under `key` in `indexer_state`.
+    pub fn set_state(&self, key: &str, value: &str) -> Result<()> {
+        let mut args = Args::default();
+        let sql = format!(
+            "INSERT INTO indexer_state (key, value) VALUES ({}, {}) \
+             ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value",
+            args.add(key.to_string()),
+            args.add(value.to_string())
+        );
+        self.execute(&sql, &args)
+    }
+
+    fn with_client<T>(
+        &self,
+        run: impl FnOnce(&mut Client) -> Result<T, postgres::Error>,
+    ) -> Result<T> {
+        let mut client = self.client.lock().unwrap_or_else(PoisonError::into_inner);
+        if client.as_ref().is_none_or(Client::is_closed) {
+            *client = Some(connect(&self.url)?);
+        }
+        Ok(run(client.as_mut().expect("connected"))?)
+    }
+}
+
+/// Connects over TLS when the server offers it, checking its certificate
+/// against the system's roots.
+fn connect(url: &str) -> Result<Client> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    Client::connect(url, MakeRustlsConnect::new(config)).context("connecting to Postgres")
+}
+
+/// Values for a statement's `$n` placeholders, added as its SQL is written.
+#[derive(Default)]
+pub struct Args(Vec<Box<dyn ToSql + Sync>>);
+
+impl Args {
+    /// Adds `value`, returning the placeholder that takes it.
+    pub fn add(&mut self, value: impl ToSql + Sync + 'static) -> String {
+        self.0.push(Box::new(value));
+        format!("${}", self.0.len())
+    }
+
+    fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.0.iter().map(|value| value.as_ref()).collect()
+    }
+}
@@ -0,0 +1,116 @@
+//! `friend-bets-notifier`: posts market activity from the indexer's
+//! database to Telegram chats and Discord channels, so a group hears about
+//! new markets, big bets, markets about to close, markets nearing their
+//! resolve deadline, results and unclaimed winnings where it already talks.
+//! Through Dialect, bettors can also be alerted in their wallets when a
+//! market they bet on is about to close or resolves and while they have
+//! winnings to claim, and creators when a market of theirs is due a
+//! result; wallets that subscribed through the API get the same by email
+//! or web push.
+
+mod channels;
+mod db;
+mod notify;
+mod push;
+
+use std::collections::HashMap;
+use std::env;
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::db::Database;
+use crate::notify::Notifier;
+
+const USAGE: &str = "\
+Usage: friend-bets-notifier [OPTIONS]
+
+Options:
+      --config <FILE>       TOML file listing the channels to post to
+                            [env: FRIEND_BETS_NOTIFIER_CONFIG]
+      --database-url <URL>  The indexer's Postgres database [env: DATABASE_URL]
+      --interval <SECS>     Seconds between checks, default 10
+                            [env: FRIEND_BETS_NOTIFIER_INTERVAL]
+      --test                Post a test message to each chat channel and exit
+  -h, --help                Print this help
+
+The indexer must be running against the same database. Dialect is reached
+through `node`, which runs the channel's sender script.
+";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let mut options = HashMap::new();
+    let mut test = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            "--test" => test = true,
+            flag if flag.starts_with("--") => {
+                let (name, value) = match flag[2..].split_once('=') {
+                    Some((name, value)) => (name.to_string(), value.to_string()),
+                    None => (
+                        flag[2..].to_string(),
+                        args.next().ok_or_else(|| anyhow!("{flag} needs a value"))?,
+                    ),
+                };
+                options.insert(name, value);
+            }
+            _ => bail!("unexpected argument {arg:?}"),
+        }
+    }
+    let mut option = |name: &str, var: &str| options.remove(name).or_else(|| env::var(var).ok());
+
+    let config = option("config", "FRIEND_BETS_NOTIFIER_CONFIG")
+        .ok_or_else(|| anyhow!("set --config or FRIEND_BETS_NOTIFIER_CONFIG"))?;
+    let channels = channels::load(&config)?;
+    if test {
+        for channel in &channels {
+            match channel.send("friend-bets notifier is connected") {
+                Ok(()) => println!("{}: sent", channel.name),
+                Err(err) => println!("{}: {err:#}", channel.name),
+            }
+        }
+        return Ok(());
+    }
+    let database_url = option("database-url", "DATABASE_URL")
+        .ok_or_else(|| anyhow!("set --database-url or DATABASE_URL"))?;
+    let interval = match option("interval", "FRIEND_BETS_NOTIFIER_INTERVAL") {
+        Some(secs) => secs.parse().context("invalid --interval")?,
+        None => 10,
+    };
+    if let Some(name) = options.keys().next() {
+        bail!("unknown option --{name}; see --help");
+    }
+    let db = Database::new(database_url);
+    let interval = Duration::from_secs(interval);
+    thread::scope(|scope| {
+        for channel in &channels {
+            eprintln!("Posting to channel {}", channel.name);
+            if let Some(Ok(key)) = channel.vapid_public_key() {
+                eprintln!(
+                    "Channel {} pushes with VAPID public key {key}",
+                    channel.name
+                );
+            }
+            let db = &db;
+            scope.spawn(move || Notifier::new(channel, db).run(interval));
+        }
+    });
+    Ok(())
+}
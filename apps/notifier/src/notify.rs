@@ -0,0 +1,671 @@
+//! What gets posted, worked out from the indexer's tables.
+//!
+//! - `market_created`, `big_bet` and `resolved` (which covers
+//!   cancellations too) follow the `events` table from where the channel
+//!   left off, kept in `indexer_state` under `notifier:<name>`; a new
+//!   channel starts with the next event.
+//! - `closing_soon` posts once for each open market whose betting closes
+//!   within `closing_soon_minutes`, as that moment passes. Markets created
+//!   with less time than that left aren't warned about; their
+//!   `market_created` post already says when betting closes.
+//! - `resolve_due` posts once for each market still waiting on its result
+//!   `resolve_due_hours` before its resolve deadline, when it passes
+//!   without the market being cancelled. Markets whose betting closes
+//!   inside that window are posted about when it closes.
+//! - `unclaimed` posts every `reminder_hours` for each finalized market
+//!   with winners (or, if it was cancelled, bettors) yet to claim.
+//!
+//! Dialect, email and web push channels alert wallets instead: on
+//! `closing_soon`, everyone who bet on the market; on `resolved`, the same,
+//! with what they can claim; on `resolve_due`, the market's creator, who's
+//! the one to resolve it; on `unclaimed`, each winner (or bettor in a
+//! cancelled market) yet to claim. Which bettors an alert has reached is
+//! kept under `notifier:<name>:wallet`, so a retry doesn't alert anyone
+//! twice. Email and web push go to each of a wallet's subscriptions that
+//! asked for the alert; a retry can repeat it to the wallet's others, and
+//! push subscriptions the browser has dropped are deleted.
+//!
+//! Posts that fail are retried on the next check, and skipped after
+//! [`MAX_ATTEMPTS`].
+
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::channels::Channel;
+use crate::db::{Args, Database};
+
+/// Most events read per check.
+const BATCH: usize = 100;
+/// Tries at one post before it's skipped.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// What an unclaimed position `p` in market `m` is owed: its stake back
+/// when cancelled, otherwise its share of the pool after fees if it won.
+const OWED: &str = "CASE WHEN p.claimed OR p.amount = 0 THEN 0 \
+     WHEN m.status = 'cancelled' THEN p.amount \
+     WHEN m.status = 'resolved' AND p.side = m.outcome \
+       THEN div(p.amount * div((m.staked_a + m.staked_b) * (10000 - m.fee_bps), 10000), \
+                CASE p.side WHEN 'A' THEN m.staked_a ELSE m.staked_b END) \
+     ELSE 0 END";
+
+/// Events that can lead to a post.
+const EVENTS: [&str; 5] = [
+    "market_initialized",
+    "bet_placed",
+    "large_bet_placed",
+    "resolved",
+    "cancelled",
+];
+
+pub struct Notifier<'a> {
+    channel: &'a Channel,
+    db: &'a Database,
+    /// Failed tries at the post currently at the head of the queue.
+    attempts: u32,
+}
+
+impl<'a> Notifier<'a> {
+    pub fn new(channel: &'a Channel, db: &'a Database) -> Self {
+        Self {
+            channel,
+            db,
+            attempts: 0,
+        }
+    }
+
+    /// Posts to the channel as things happen, forever.
+    pub fn run(&mut self, interval: Duration) {
+        loop {
+            if let Err(err) = self.check() {
+                eprintln!("warning: channel {}: {err:#}", self.channel.name);
+            }
+            thread::sleep(interval);
+        }
+    }
+
+    fn check(&mut self) -> Result<()> {
+        self.post_events()?;
+        if self.channel.wants("closing_soon") {
+            self.post_closing_soon()?;
+        }
+        if self.channel.wants("resolve_due") {
+            self.post_resolve_due()?;
+        }
+        if self.channel.wants("unclaimed") {
+            self.post_reminders()?;
+        }
+        Ok(())
+    }
+
+    fn key(&self, suffix: &str) -> String {
+        match suffix {
+            "" => format!("notifier:{}", self.channel.name),
+            suffix => format!("notifier:{}:{suffix}", self.channel.name),
+        }
+    }
+
+    /// Sends a post with `send`, returning whether to move on: it went
+    /// out, or has failed too often to keep trying.
+    fn deliver(&mut self, send: impl FnOnce(&Channel) -> Result<()>) -> bool {
+        match send(self.channel) {
+            Ok(()) => {
+                self.attempts = 0;
+                true
+            }
+            Err(err) => {
+                self.attempts += 1;
+                if self.attempts < MAX_ATTEMPTS {
+                    eprintln!("warning: channel {}: {err:#}; retrying", self.channel.name);
+                    return false;
+                }
+                eprintln!(
+                    "warning: channel {}: {err:#}; skipping after {MAX_ATTEMPTS} tries",
+                    self.channel.name
+                );
+                self.attempts = 0;
+                true
+            }
+        }
+    }
+
+    /// `WHERE` conditions picking the channel's markets from `m`.
+    fn market_filters(&self, args: &mut Args) -> Vec<String> {
+        let (markets, creators) = (&self.channel.markets, &self.channel.creators);
+        match (markets.is_empty(), creators.is_empty()) {
+            (true, true) => Vec::new(),
+            (false, true) => vec![format!("m.address = ANY ({})", args.add(markets.clone()))],
+            (true, false) => vec![format!("m.creator = ANY ({})", args.add(creators.clone()))],
+            (false, false) => vec![format!(
+                "(m.address = ANY ({}) OR m.creator = ANY ({}))",
+                args.add(markets.clone()),
+                args.add(creators.clone())
+            )],
+        }
+    }
+
+    fn post_events(&mut self) -> Result<()> {
+        let key = self.key("");
+        let Some(cursor) = self.db.state(&key)? else {
+            let newest = self
+                .db
+                .query("SELECT COALESCE(max(id), 0) FROM events", &Args::default())?;
+            let newest: i64 = newest.first().context("expected an event id")?.get(0);
+            self.db.set_state(&key, &newest.to_string())?;
+            return Ok(());
+        };
+        let cursor: i64 = cursor.parse().context("invalid notifier cursor")?;
+        let mut args = Args::default();
+        let mut filters = self.market_filters(&mut args);
+        filters.push(format!("e.id > {cursor}"));
+        filters.push(format!(
+            "e.name = ANY ({})",
+            args.add(EVENTS.map(String::from).to_vec())
+        ));
+        let sql = format!(
+            "SELECT json_build_object('id', e.id, 'event', e.name, 'market', e.market, \
+             'title', m.title, 'data', e.data, 'staked_a', e.staked_a::text, \
+             'staked_b', e.staked_b::text, \
+             'end_ts', to_char(m.end_ts AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI \"UTC\"')) \
+             FROM events e JOIN markets m ON m.address = e.market \
+             WHERE {} ORDER BY e.id LIMIT {BATCH}",
+            filters.join(" AND ")
+        );
+        for event in self.db.json(&sql, &args)? {
+            if self.channel.alerts_wallets() {
+                let name = event["event"].as_str().unwrap_or_default();
+                if matches!(name, "resolved" | "cancelled") && self.channel.wants("resolved") {
+                    let channel = self.channel;
+                    let market = event["market"].as_str().unwrap_or_default();
+                    let alerted =
+                        self.alert_bettors(&event["id"].to_string(), market, "resolved", |owed| {
+                            bettor_alert(channel, &event, owed)
+                        })?;
+                    if !alerted {
+                        return Ok(());
+                    }
+                }
+            } else if let Some(text) = event_text(self.channel, &event) {
+                let market = event["market"].as_str().unwrap_or_default();
+                if !self.deliver(|channel| channel.post(&text, market)) {
+                    return Ok(());
+                }
+            }
+            self.db.set_state(&key, &event["id"].to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Posts about markets whose warning time has come since the last check.
+    fn post_closing_soon(&mut self) -> Result<()> {
+        let key = self.key("closing");
+        let now = now();
+        let Some(since) = self.db.state(&key)? else {
+            self.db.set_state(&key, &now.to_string())?;
+            return Ok(());
+        };
+        let since: i64 = since.parse().context("invalid closing_soon cursor")?;
+        let lead = i64::from(self.channel.closing_soon_minutes) * 60;
+        let mut args = Args::default();
+        let mut filters = self.market_filters(&mut args);
+        filters.push("m.status = 'open'".into());
+        filters.push(format!(
+            "extract(epoch FROM m.end_ts)::bigint - {lead} > {since}"
+        ));
+        filters.push(format!(
+            "extract(epoch FROM m.end_ts)::bigint - {lead} <= {now}"
+        ));
+        filters.push("m.end_ts > now()".into());
+        let sql = format!(
+            "SELECT json_build_object('market', m.address, 'title', m.title, \
+             'staked_a', m.staked_a::text, 'staked_b', m.staked_b::text, \
+             'warn_at', extract(epoch FROM m.end_ts)::bigint - {lead}, \
+             'minutes', ceil(extract(epoch FROM m.end_ts - now()) / 60)::int) \
+             FROM markets m WHERE {} ORDER BY m.end_ts",
+            filters.join(" AND ")
+        );
+        let markets = self.db.json(&sql, &args)?;
+        for market in markets {
+            let title = market["title"].as_str().unwrap_or_default();
+            let address = market["market"].as_str().unwrap_or_default();
+            let sent = if self.channel.alerts_wallets() {
+                let id = format!("closing {address}");
+                let body = format!(
+                    "Betting closes in {}{}.",
+                    duration(market["minutes"].as_i64().unwrap_or_default()),
+                    odds(&market)
+                );
+                self.alert_bettors(&id, address, "closing_soon", |_| {
+                    Some((format!("\"{title}\" closes soon"), body.clone()))
+                })?
+            } else {
+                let text = format!(
+                    "Betting on \"{title}\" closes in {} min{}",
+                    market["minutes"],
+                    odds(&market)
+                );
+                self.deliver(|channel| channel.post(&text, address))
+            };
+            if !sent {
+                return Ok(());
+            }
+            self.db.set_state(&key, &market["warn_at"].to_string())?;
+        }
+        self.db.set_state(&key, &now.to_string())
+    }
+
+    /// Reminds creators of markets whose resolve deadline is coming up
+    /// without a result, before they're cancelled for it.
+    fn post_resolve_due(&mut self) -> Result<()> {
+        let key = self.key("resolving");
+        let now = now();
+        let Some(since) = self.db.state(&key)? else {
+            self.db.set_state(&key, &now.to_string())?;
+            return Ok(());
+        };
+        let since: i64 = since.parse().context("invalid resolve_due cursor")?;
+        let lead = i64::from(self.channel.resolve_due_hours) * 3600;
+        let warn_at = format!(
+            "GREATEST(extract(epoch FROM m.resolve_deadline_ts)::bigint - {lead}, \
+             extract(epoch FROM m.end_ts)::bigint)"
+        );
+        let mut args = Args::default();
+        let mut filters = self.market_filters(&mut args);
+        filters.push("m.status IN ('open', 'pending_resolve')".into());
+        filters.push(format!("{warn_at} > {since}"));
+        filters.push(format!("{warn_at} <= {now}"));
+        filters.push("m.resolve_deadline_ts > now()".into());
+        let sql = format!(
+            "SELECT json_build_object('market', m.address, 'title', m.title, \
+             'creator', m.creator, 'warn_at', {warn_at}, \
+             'minutes', ceil(extract(epoch FROM m.resolve_deadline_ts - now()) / 60)::int) \
+             FROM markets m WHERE {} ORDER BY {warn_at}",
+            filters.join(" AND ")
+        );
+        let markets = self.db.json(&sql, &args)?;
+        for market in markets {
+            let title = market["title"].as_str().unwrap_or_default();
+            let creator = market["creator"].as_str().unwrap_or_default();
+            let left = duration(market["minutes"].as_i64().unwrap_or_default());
+            let address = market["market"].as_str().unwrap_or_default();
+            let sent = if self.channel.alerts_wallets() {
+                let body = format!(
+                    "Resolve it within {left}, or it will be cancelled and every bet refunded."
+                );
+                let heading = format!("\"{title}\" needs a result");
+                self.alert(creator, "resolve_due", &heading, &body, address)?
+            } else {
+                let text = format!(
+                    "\"{title}\" needs resolving within {left}, or it will be cancelled and \
+                     every bet refunded. Creator: {creator}"
+                );
+                self.deliver(|channel| channel.post(&text, address))
+            };
+            if !sent {
+                return Ok(());
+            }
+            self.db.set_state(&key, &market["warn_at"].to_string())?;
+        }
+        self.db.set_state(&key, &now.to_string())
+    }
+
+    /// Reminds winners to claim, every `reminder_hours`.
+    fn post_reminders(&mut self) -> Result<()> {
+        let key = self.key("reminded");
+        let now = now();
+        let Some(last) = self.db.state(&key)? else {
+            self.db.set_state(&key, &now.to_string())?;
+            return Ok(());
+        };
+        let last: i64 = last.parse().context("invalid reminder cursor")?;
+        if now - last < i64::from(self.channel.reminder_hours) * 3600 {
+            return Ok(());
+        }
+        let mut args = Args::default();
+        let mut filters = self.market_filters(&mut args);
+        filters.push("m.status IN ('resolved', 'cancelled')".into());
+        if self.channel.alerts_wallets() {
+            filters.extend(self.subscribed(&mut args, "p.owner", "unclaimed"));
+            if !self.remind_wallets(&filters.join(" AND "), &args)? {
+                return Ok(());
+            }
+            return self.db.set_state(&key, &now.to_string());
+        }
+        let sql = format!(
+            "SELECT json_build_object('market', m.address, 'title', m.title, \
+             'status', m.status, 'positions', u.positions, 'owed', u.owed::text) \
+             FROM markets m, LATERAL (SELECT count(*) FILTER (WHERE {OWED} > 0) AS positions, \
+               COALESCE(sum({OWED}), 0) AS owed \
+               FROM positions p WHERE p.market = m.address) u \
+             WHERE u.positions > 0 AND {} ORDER BY m.resolve_deadline_ts",
+            filters.join(" AND ")
+        );
+        let markets = self.db.json(&sql, &args)?;
+        for market in markets {
+            let title = market["title"].as_str().unwrap_or_default();
+            let owed = self
+                .channel
+                .amount(market["owed"].as_str().unwrap_or_default());
+            let text = match (market["status"].as_str(), market["positions"].as_u64()) {
+                (Some("cancelled"), Some(1)) => {
+                    format!("1 bettor hasn't claimed back {owed} from cancelled \"{title}\"")
+                }
+                (Some("cancelled"), positions) => format!(
+                    "{} bettors haven't claimed back {owed} from cancelled \"{title}\"",
+                    positions.unwrap_or_default()
+                ),
+                (_, Some(1)) => format!("1 winner hasn't claimed {owed} from \"{title}\" yet"),
+                (_, positions) => format!(
+                    "{} winners haven't claimed {owed} from \"{title}\" yet",
+                    positions.unwrap_or_default()
+                ),
+            };
+            let address = market["market"].as_str().unwrap_or_default();
+            if !self.deliver(|channel| channel.post(&text, address)) {
+                return Ok(());
+            }
+        }
+        self.db.set_state(&key, &now.to_string())
+    }
+
+    /// Alerts everyone who bet on `market`, with `alert` giving the
+    /// heading and body for a bettor owed the given raw units, returning
+    /// whether to move on. `id` names what the alerts are about, for a
+    /// retry to pick up where the last try stopped.
+    fn alert_bettors(
+        &mut self,
+        id: &str,
+        market: &str,
+        notification: &str,
+        alert: impl Fn(&str) -> Option<(String, String)>,
+    ) -> Result<bool> {
+        // Bettors are alerted in address order, so the last one reached
+        // is all a retry needs to know
+        let key = self.key("wallet");
+        let reached = self.db.state(&key)?.and_then(|state| {
+            let (alerts, owner) = state.rsplit_once(':')?;
+            (alerts == id).then(|| owner.to_string())
+        });
+        let mut args = Args::default();
+        let mut filters = vec![
+            format!("p.market = {}", args.add(market.to_string())),
+            "p.amount > 0".into(),
+        ];
+        if let Some(owner) = reached {
+            filters.push(format!("p.owner > {}", args.add(owner)));
+        }
+        filters.extend(self.subscribed(&mut args, "p.owner", notification));
+        let sql = format!(
+            "SELECT json_build_object('owner', p.owner, 'owed', sum({OWED})::text) \
+             FROM positions p JOIN markets m ON m.address = p.market \
+             WHERE {} GROUP BY p.owner ORDER BY p.owner",
+            filters.join(" AND ")
+        );
+        let bettors = self.db.json(&sql, &args)?;
+        for bettor in bettors {
+            let owner = bettor["owner"].as_str().unwrap_or_default();
+            let owed = bettor["owed"].as_str().unwrap_or("0");
+            let Some((heading, body)) = alert(owed) else {
+                continue;
+            };
+            if !self.alert(owner, notification, &heading, &body, market)? {
+                return Ok(false);
+            }
+            self.db.set_state(&key, &format!("{id}:{owner}"))?;
+        }
+        Ok(true)
+    }
+
+    /// Alerts each wallet with something to claim in the markets matching
+    /// `filters`, returning whether every alert went out or was skipped.
+    fn remind_wallets(&mut self, filters: &str, args: &Args) -> Result<bool> {
+        let sql = format!(
+            "SELECT json_build_object('market', m.address, 'title', m.title, \
+             'status', m.status, 'owner', p.owner, 'owed', sum({OWED})::text) \
+             FROM markets m JOIN positions p ON p.market = m.address \
+             WHERE {filters} GROUP BY m.address, p.owner HAVING sum({OWED}) > 0 \
+             ORDER BY m.resolve_deadline_ts, m.address, p.owner"
+        );
+        let owed = self.db.json(&sql, args)?;
+        for row in owed {
+            let title = row["title"].as_str().unwrap_or_default();
+            let amount = self
+                .channel
+                .amount(row["owed"].as_str().unwrap_or_default());
+            let body = match row["status"].as_str() {
+                Some("cancelled") => {
+                    format!(
+                        "You can still claim back your {amount} stake from cancelled \"{title}\"."
+                    )
+                }
+                _ => format!("You still have {amount} to claim from \"{title}\"."),
+            };
+            let owner = row["owner"].as_str().unwrap_or_default();
+            let market = row["market"].as_str().unwrap_or_default();
+            if !self.alert(owner, "unclaimed", "Unclaimed winnings", &body, market)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Alerts `wallet` about `market`: through Dialect, or each of the
+    /// wallet's subscriptions that asked for `notification`. Returns
+    /// whether to move on, as [`Notifier::deliver`] does.
+    fn alert(
+        &mut self,
+        wallet: &str,
+        notification: &str,
+        heading: &str,
+        body: &str,
+        market: &str,
+    ) -> Result<bool> {
+        let Some(kind) = self.channel.subscriptions() else {
+            return Ok(self.deliver(|channel| channel.alert(wallet, heading, body, market)));
+        };
+        let mut args = Args::default();
+        let sql = format!(
+            "SELECT json_build_object('id', id, 'target', target, 'p256dh', p256dh, \
+             'auth', auth) FROM subscriptions WHERE wallet = {} AND kind = {} \
+             AND {} = ANY (notify) ORDER BY id",
+            args.add(wallet.to_string()),
+            args.add(kind.to_string()),
+            args.add(notification.to_string())
+        );
+        let subscriptions = self.db.json(&sql, &args)?;
+        if subscriptions.is_empty() {
+            return Ok(true);
+        }
+        let mut gone = Vec::new();
+        let sent = self.deliver(|channel| {
+            let mut failed = None;
+            for subscription in &subscriptions {
+                match channel.notify(subscription, heading, body, market) {
+                    Ok(true) => {}
+                    Ok(false) => gone.extend(subscription["id"].as_i64()),
+                    Err(err) => failed = Some(err),
+                }
+            }
+            failed.map_or(Ok(()), Err)
+        });
+        if !gone.is_empty() {
+            let mut args = Args::default();
+            let sql = format!(
+                "DELETE FROM subscriptions WHERE id = ANY ({})",
+                args.add(gone)
+            );
+            self.db.execute(&sql, &args)?;
+        }
+        Ok(sent)
+    }
+
+    /// For email and web push, a condition on the wallet in `column` having
+    /// a subscription of the channel's kind that asked for `notification`.
+    fn subscribed(&self, args: &mut Args, column: &str, notification: &str) -> Option<String> {
+        let kind = self.channel.subscriptions()?;
+        Some(format!(
+            "EXISTS (SELECT 1 FROM subscriptions s WHERE s.wallet = {column} \
+             AND s.kind = {} AND {} = ANY (s.notify))",
+            args.add(kind.to_string()),
+            args.add(notification.to_string())
+        ))
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64)
+}
+
+/// `minutes` as `3h 20m`, or `45 min` under an hour.
+fn duration(minutes: i64) -> String {
+    match minutes {
+        ..=59 => format!("{minutes} min"),
+        _ if minutes % 60 == 0 => format!("{}h", minutes / 60),
+        _ => format!("{}h {}m", minutes / 60, minutes % 60),
+    }
+}
+
+/// The post for an event, if the channel wants one.
+fn event_text(channel: &Channel, event: &Value) -> Option<String> {
+    let data = &event["data"];
+    let title = event["title"].as_str().unwrap_or_default();
+    match event["event"].as_str()? {
+        "market_initialized" if channel.wants("market_created") => Some(format!(
+            "New market: \"{title}\"\nBetting closes {}",
+            event["end_ts"].as_str().unwrap_or_default()
+        )),
+        // A channel's own threshold replaces the markets'
+        name @ ("bet_placed" | "large_bet_placed") if channel.wants("big_bet") => {
+            let amount = data["amount"].as_str()?;
+            let big = match channel.big_bet() {
+                Some(threshold) => {
+                    name == "bet_placed" && amount.parse::<u128>().ok()? >= threshold
+                }
+                None => name == "large_bet_placed",
+            };
+            big.then(|| {
+                format!(
+                    "Big bet: {} on side {} in \"{title}\"{}",
+                    channel.amount(amount),
+                    data["side"].as_str().unwrap_or_default(),
+                    odds(event)
+                )
+            })
+        }
+        "resolved" if channel.wants("resolved") => Some(format!(
+            "\"{title}\" resolved: side {} wins. Winners can claim now.",
+            data["outcome"].as_str().unwrap_or_default()
+        )),
+        "cancelled" if channel.wants("resolved") => Some(format!(
+            "\"{title}\" was cancelled. Everyone can claim their stake back."
+        )),
+        _ => None,
+    }
+}
+
+/// The heading and body of the alert to a bettor owed `owed` when
+/// `event` resolved or cancelled their market, if there's one to send.
+fn bettor_alert(channel: &Channel, event: &Value, owed: &str) -> Option<(String, String)> {
+    let title = event["title"].as_str().unwrap_or_default();
+    let owed = (owed.parse::<u128>().ok()? > 0).then(|| channel.amount(owed));
+    match (event["event"].as_str()?, owed) {
+        ("resolved", owed) => {
+            let outcome = event["data"]["outcome"].as_str().unwrap_or_default();
+            let body = match owed {
+                Some(owed) => format!("Side {outcome} won. Claim your {owed} now."),
+                None => format!("Side {outcome} won."),
+            };
+            Some((format!("\"{title}\" resolved"), body))
+        }
+        ("cancelled", Some(owed)) => Some((
+            format!("\"{title}\" was cancelled"),
+            format!("Claim your {owed} stake back."),
+        )),
+        _ => None,
+    }
+}
+
+/// Each side's share of the pool, as ` (A 40% · B 60%)`.
+fn odds(row: &Value) -> String {
+    let stake = |side: &str| {
+        row[side]
+            .as_str()
+            .and_then(|amount| amount.parse::<f64>().ok())
+            .unwrap_or_default()
+    };
+    let (a, b) = (stake("staked_a"), stake("staked_b"));
+    if a + b == 0.0 {
+        return String::new();
+    }
+    let a = (a * 100.0 / (a + b)).round();
+    format!(" (A {a}% · B {}%)", 100.0 - a)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn formats_posts() {
+        let channel: Channel = toml::from_str(
+            "name = \"test\"\nkind = \"discord\"\ndecimals = 6\nsymbol = \"USDC\"\n\
+             big_bet = \"5\"\nnotify = [\"big_bet\", \"resolved\"]",
+        )
+        .unwrap();
+        assert_eq!(channel.amount("5000000"), "5 USDC");
+        assert_eq!(channel.amount("1250000"), "1.25 USDC");
+        assert_eq!(channel.amount("7"), "0.000007 USDC");
+        assert_eq!(channel.amount("0"), "0 USDC");
+
+        let bet = |amount: &str| {
+            json!({
+                "event": "bet_placed", "title": "Rain?", "staked_a": "2000000",
+                "staked_b": "5000000", "data": { "side": "B", "amount": amount },
+            })
+        };
+        assert_eq!(
+            event_text(&channel, &bet("5000000")).unwrap(),
+            "Big bet: 5 USDC on side B in \"Rain?\" (A 29% · B 71%)"
+        );
+        assert_eq!(event_text(&channel, &bet("4999999")), None);
+        let large = json!({
+            "event": "large_bet_placed", "title": "Rain?", "staked_a": "2000000",
+            "staked_b": "5000000", "data": { "side": "B", "amount": "1000000" },
+        });
+        assert_eq!(event_text(&channel, &large), None);
+        let market_thresholds: Channel =
+            toml::from_str("name = \"test\"\nkind = \"discord\"\ndecimals = 6\nsymbol = \"USDC\"")
+                .unwrap();
+        assert_eq!(
+            event_text(&market_thresholds, &large).unwrap(),
+            "Big bet: 1 USDC on side B in \"Rain?\" (A 29% · B 71%)"
+        );
+        assert_eq!(event_text(&market_thresholds, &bet("5000000")), None);
+        let created = json!({ "event": "market_initialized", "title": "Rain?" });
+        assert_eq!(event_text(&channel, &created), None);
+
+        let resolved = json!({ "event": "resolved", "title": "Rain?", "data": { "outcome": "B" } });
+        assert_eq!(
+            bettor_alert(&channel, &resolved, "6930000").unwrap(),
+            (
+                "\"Rain?\" resolved".into(),
+                "Side B won. Claim your 6.93 USDC now.".into()
+            )
+        );
+        assert_eq!(
+            bettor_alert(&channel, &resolved, "0").unwrap().1,
+            "Side B won."
+        );
+        assert_eq!(duration(45), "45 min");
+        assert_eq!(duration(180), "3h");
+        assert_eq!(duration(200), "3h 20m");
+        let cancelled = json!({ "event": "cancelled", "title": "Rain?" });
+        assert_eq!(bettor_alert(&channel, &cancelled, "0"), None);
+    }
+}
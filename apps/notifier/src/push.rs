@@ -0,0 +1,231 @@
+//! Web Push: messages encrypted for a browser's push subscription
+//! (RFC 8291) and sent to its push service, signed for by the sender's
+//! VAPID key (RFC 8292).
+//!
+//! The VAPID key is a P-256 private key in a PEM file, made with
+//! `openssl ecparam -name prime256v1 -genkey -noout -out vapid.pem`; the
+//! site hands its public key, printed when the notifier starts, to
+//! `pushManager.subscribe` as `applicationServerKey`.
+//!
+//! The payload is JSON, `{"title", "body", "url"}`, for the site's service
+//! worker to show.
+
+use std::fs;
+
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes128Gcm, KeyInit};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hkdf::Hkdf;
+use p256::ecdh::EphemeralSecret;
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::pkcs8::DecodePrivateKey;
+use p256::{PublicKey, SecretKey};
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+
+use crate::channels;
+
+/// The one record each message is sent as.
+const RECORD_SIZE: u32 = 4096;
+/// How long push services hold a message for an offline browser.
+const TTL_SECS: u32 = 86_400;
+/// How long a VAPID signature is good for; push services allow 24 hours.
+const VAPID_EXPIRY_SECS: i64 = 12 * 3600;
+
+/// A browser's push subscription.
+pub struct Subscription<'a> {
+    pub endpoint: &'a str,
+    /// Its P-256 public key and auth secret, base64url.
+    pub p256dh: &'a str,
+    pub auth: &'a str,
+}
+
+/// The base64url public key of the VAPID key at `path`.
+pub fn public_key(path: &str) -> Result<String> {
+    let point = signing_key(path)?.verifying_key().to_encoded_point(false);
+    Ok(URL_SAFE_NO_PAD.encode(point.as_bytes()))
+}
+
+/// Sends `payload` to `subscription`, returning `false` when the push
+/// service says the subscription is gone.
+pub fn send(
+    subscription: &Subscription,
+    payload: &[u8],
+    vapid_key: &str,
+    subject: &str,
+    now: i64,
+) -> Result<bool> {
+    let ua_public = URL_SAFE_NO_PAD
+        .decode(subscription.p256dh)
+        .context("invalid p256dh")?;
+    let auth = URL_SAFE_NO_PAD
+        .decode(subscription.auth)
+        .context("invalid auth")?;
+    if ua_public.len() != 65 || auth.len() != 16 {
+        bail!("invalid subscription keys");
+    }
+    let ua_key = PublicKey::from_sec1_bytes(&ua_public).context("invalid p256dh")?;
+    if payload.len() + 17 > RECORD_SIZE as usize - 86 {
+        bail!("push payload of {} bytes is too long", payload.len());
+    }
+
+    // A fresh key pair for each message
+    let ephemeral = EphemeralSecret::random(&mut OsRng);
+    let as_public = ephemeral.public_key().to_encoded_point(false);
+    let secret = ephemeral.diffie_hellman(&ua_key);
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let body = encrypt(
+        secret.raw_secret_bytes(),
+        &auth,
+        &ua_public,
+        as_public.as_bytes(),
+        &salt,
+        payload,
+    )?;
+
+    let origin = subscription
+        .endpoint
+        .splitn(4, '/')
+        .take(3)
+        .collect::<Vec<_>>()
+        .join("/");
+    let authorization = format!(
+        "vapid t={}, k={}",
+        vapid_jwt(&signing_key(vapid_key)?, &origin, subject, now),
+        public_key(vapid_key)?
+    );
+    let response = channels::http()
+        .post(subscription.endpoint)
+        .header("TTL", TTL_SECS)
+        .header("Urgency", "normal")
+        .header("Content-Encoding", "aes128gcm")
+        .header("Content-Type", "application/octet-stream")
+        .header("Authorization", authorization)
+        .body(body)
+        .send()
+        // Endpoints are a browser's secret too
+        .map_err(reqwest::Error::without_url)?;
+    match response.status().as_u16() {
+        200..=299 => Ok(true),
+        404 | 410 => Ok(false),
+        status => bail!("push service answered {status}"),
+    }
+}
+
+/// The `aes128gcm` body of a message (RFC 8291, section 3.4): a header
+/// naming the salt and the sender's public key, then the one record.
+fn encrypt(
+    secret: &[u8],
+    auth: &[u8],
+    ua_public: &[u8],
+    as_public: &[u8],
+    salt: &[u8; 16],
+    payload: &[u8],
+) -> Result<Vec<u8>> {
+    let key_info = [b"WebPush: info\0".as_slice(), ua_public, as_public].concat();
+    let mut ikm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(auth), secret)
+        .expand(&key_info, &mut ikm)
+        .map_err(|_| anyhow!("HKDF output too long"))?;
+    let content = Hkdf::<Sha256>::new(Some(salt), &ikm);
+    let mut cek = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    content
+        .expand(b"Content-Encoding: aes128gcm\0", &mut cek)
+        .and_then(|()| content.expand(b"Content-Encoding: nonce\0", &mut nonce))
+        .map_err(|_| anyhow!("HKDF output too long"))?;
+
+    let mut body = salt.to_vec();
+    body.extend(RECORD_SIZE.to_be_bytes());
+    body.push(as_public.len() as u8);
+    body.extend(as_public);
+    // The last (and only) record ends with a 2 delimiter
+    let mut record = payload.to_vec();
+    record.push(2);
+    let ciphertext = Aes128Gcm::new(&cek.into())
+        .encrypt(&nonce.into(), record.as_slice())
+        .map_err(|_| anyhow!("encrypting the push message failed"))?;
+    body.extend(ciphertext);
+    Ok(body)
+}
+
+/// A VAPID token for the push service at `audience` (RFC 8292, section 2).
+fn vapid_jwt(key: &SigningKey, audience: &str, subject: &str, now: i64) -> String {
+    let header = URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"ES256"}"#);
+    let claims = serde_json::json!({
+        "aud": audience,
+        "exp": now + VAPID_EXPIRY_SECS,
+        "sub": subject,
+    });
+    let signed = format!("{header}.{}", URL_SAFE_NO_PAD.encode(claims.to_string()));
+    // JWS wants the signature as `r` then `s`, 32 bytes each
+    let signature: Signature = key.sign(signed.as_bytes());
+    format!("{signed}.{}", URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+}
+
+/// The P-256 private key in the PEM file at `path`, SEC1 as `openssl
+/// ecparam` writes it or PKCS#8.
+fn signing_key(path: &str) -> Result<SigningKey> {
+    let pem = fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+    let key = SecretKey::from_sec1_pem(&pem)
+        .or_else(|_| SecretKey::from_pkcs8_pem(&pem))
+        .map_err(|_| anyhow!("{path} isn't a P-256 key"))?;
+    Ok(SigningKey::from(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use p256::ecdsa::signature::Verifier;
+
+    use super::*;
+
+    #[test]
+    fn encrypts_messages() {
+        let ua_public = [&[4u8][..], &[3; 64]].concat();
+        let as_public = [&[4u8][..], &[4; 64]].concat();
+        let body = encrypt(
+            &[1; 32],
+            &[2; 16],
+            &ua_public,
+            &as_public,
+            &[5; 16],
+            b"When I grow up, I want to be a watermelon",
+        )
+        .unwrap();
+        assert_eq!(body[..16], [5; 16]);
+        assert_eq!(body[16..21], [0, 0, 16, 0, 65]);
+        assert_eq!(body[21..86], as_public);
+        let record: String = body[86..]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        assert_eq!(
+            record,
+            "b21ca5bb04a794c1da32315283e2ccb337d9a262818d57532f281e09a51ee9c0\
+             ec62265de6bb0780275deddbcaf3003f9aacb1ac03276275d79e"
+        );
+    }
+
+    #[test]
+    fn signs_vapid_tokens() {
+        let key = SigningKey::from(SecretKey::from_slice(&[7; 32]).unwrap());
+        let token = vapid_jwt(&key, "https://push.example", "mailto:ops@example.com", 100);
+        let (signed, signature) = token.rsplit_once('.').unwrap();
+        let signature = Signature::from_slice(&URL_SAFE_NO_PAD.decode(signature).unwrap()).unwrap();
+        assert!(key
+            .verifying_key()
+            .verify(signed.as_bytes(), &signature)
+            .is_ok());
+
+        let claims = signed.split('.').nth(1).unwrap();
+        let claims: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).unwrap()).unwrap();
+        assert_eq!(claims["aud"], "https://push.example");
+        assert_eq!(claims["exp"], 100 + VAPID_EXPIRY_SECS);
+    }
+}
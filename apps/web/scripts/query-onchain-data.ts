@@ -11,7 +11,8 @@ interface OnChainMarket {
   marketId: anchor.BN;
   creator: PublicKey;
   mint: PublicKey;
-  vault: PublicKey;
+  vaultA: PublicKey;
+  vaultB: PublicKey;
   feeBps: number;
   endTs: anchor.BN;
   resolveDeadlineTs: anchor.BN;
@@ -21,7 +22,8 @@ interface OnChainMarket {
   outcome: any; // Optional BetSide
   creatorFeeWithdrawn: boolean;
   bump: number;
-  vaultBump: number;
+  vaultABump: number;
+  vaultBBump: number;
   title: string;
 }
 
@@ -106,7 +108,8 @@ class OnChainDataFetcher {
       marketId: market.marketId.toString(),
       creator: market.creator.toBase58(),
       mint: market.mint.toBase58(),
-      vault: market.vault.toBase58(),
+      vaultA: market.vaultA.toBase58(),
+      vaultB: market.vaultB.toBase58(),
       feeBps: market.feeBps,
       endTs: market.endTs.toNumber(),
       resolveDeadlineTs: market.resolveDeadlineTs.toNumber(),
@@ -44,7 +44,8 @@ class SimpleOnChainQuery {
       // - market_id: 8 bytes (u64)
       // - creator: 32 bytes (pubkey)
       // - mint: 32 bytes (pubkey)
-      // - vault: 32 bytes (pubkey)
+      // - vault_a: 32 bytes (pubkey)
+      // - vault_b: 32 bytes (pubkey)
       // - fee_bps: 2 bytes (u16)
       // - end_ts: 8 bytes (i64)
       // - resolve_deadline_ts: 8 bytes (i64)
@@ -66,8 +67,10 @@ class SimpleOnChainQuery {
       const mint = new PublicKey(data.slice(offset, offset + 32));
       offset += 32;
 
-      // Vault (32 bytes)
-      const vault = new PublicKey(data.slice(offset, offset + 32));
+      // Vaults holding each side's stakes (32 bytes each)
+      const vaultA = new PublicKey(data.slice(offset, offset + 32));
+      offset += 32;
+      const vaultB = new PublicKey(data.slice(offset, offset + 32));
       offset += 32;
 
       // Fee BPS (u16)
@@ -104,8 +107,8 @@ class SimpleOnChainQuery {
       const creatorFeeWithdrawn = data.readUInt8(offset) === 1;
       offset += 1;
 
-      // Skip bump and vault_bump
-      offset += 2;
+      // Skip bump, vault_a_bump and vault_b_bump
+      offset += 3;
 
       // Title (string) - first 4 bytes are length, then the string data
       const titleLength = data.readUInt32LE(offset);
@@ -121,7 +124,8 @@ class SimpleOnChainQuery {
         marketId: marketId.toString(),
         creator: creator.toBase58(),
         mint: mint.toBase58(),
-        vault: vault.toBase58(),
+        vaultA: vaultA.toBase58(),
+        vaultB: vaultB.toBase58(),
         feeBps,
         endTs: Number(endTs),
         resolveDeadlineTs: Number(resolveDeadlineTs),
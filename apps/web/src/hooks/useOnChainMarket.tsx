@@ -8,7 +8,8 @@ interface OnChainMarketData {
   marketId: string;
   creator: string;
   mint: string;
-  vault: string;
+  vaultA: string;
+  vaultB: string;
   feeBps: number;
   endTs: number;
   resolveDeadlineTs: number;
@@ -50,7 +51,10 @@ class OnChainMarketFetcher {
       const mint = new PublicKey(data.slice(offset, offset + 32));
       offset += 32;
 
-      const vault = new PublicKey(data.slice(offset, offset + 32));
+      const vaultA = new PublicKey(data.slice(offset, offset + 32));
+      offset += 32;
+
+      const vaultB = new PublicKey(data.slice(offset, offset + 32));
       offset += 32;
 
       const feeBps = data.readUInt16LE(offset);
@@ -82,8 +86,8 @@ class OnChainMarketFetcher {
       const creatorFeeWithdrawn = data.readUInt8(offset) === 1;
       offset += 1;
 
-      // Skip bump and vault_bump
-      offset += 2;
+      // Skip bump, vault_a_bump and vault_b_bump
+      offset += 3;
 
       // Title (string) - first 4 bytes are length, then the string data
       const titleLength = data.readUInt32LE(offset);
@@ -99,7 +103,8 @@ class OnChainMarketFetcher {
         marketId: marketId.toString(),
         creator: creator.toBase58(),
         mint: mint.toBase58(),
-        vault: vault.toBase58(),
+        vaultA: vaultA.toBase58(),
+        vaultB: vaultB.toBase58(),
         feeBps,
         endTs: Number(endTs),
         resolveDeadlineTs: Number(resolveDeadlineTs),
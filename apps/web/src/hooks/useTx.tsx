@@ -15,17 +15,35 @@ import { FRIENDS_BETS_IDL } from '@/idl/friends_bets';
 import { PROGRAM_ID } from '@/lib/chains/solana';
 import { type FriendsBets } from '@/types/friends_bets';
 
+// Mirrors the program's MarketOptions
+interface MarketOptions {
+  allowCashOut: boolean;
+  cashoutHaircutBps: number;
+  lockOdds: boolean;
+  burnFee: boolean;
+}
+
+const DEFAULT_MARKET_OPTIONS: MarketOptions = {
+  allowCashOut: false,
+  cashoutHaircutBps: 0,
+  lockOdds: false,
+  burnFee: false,
+};
+
 interface CreateMarketParams {
   title: string;
   feeBps: number;
   endTs: number;
   resolveDeadlineTs: number;
+  options?: MarketOptions;
 }
 
 interface PlaceBetParams {
   marketId: string;
   side: Side;
   amount: number;
+  // Worst odds to accept, in basis points of the stake; 0 takes any
+  minOddsBps?: number;
 }
 
 interface ResolveMarketParams {
@@ -47,6 +65,20 @@ function sideToAnchorFormat(side: Side) {
   return side === Side.SIDE_A ? { a: {} } : { b: {} };
 }
 
+// The program's config, which sets the fee ceiling and defaults for new markets:
+// seeds = [b"config"]
+const [CONFIG_PDA] = PublicKey.findProgramAddressSync([Buffer.from("config")], PROGRAM_ID);
+
+// Each side's stakes are held in their own vault:
+// seeds = [b"vault", market.key().as_ref(), &[side as u8]]
+function vaultAddress(market: PublicKey, side: Side): PublicKey {
+  const [vault] = PublicKey.findProgramAddressSync(
+    [Buffer.from("vault"), market.toBuffer(), Buffer.from([side === Side.SIDE_A ? 0 : 1])],
+    PROGRAM_ID
+  );
+  return vault;
+}
+
 export function useTx() {
   const { publicKey, signTransaction } = useWallet();
   const queryClient = useQueryClient();
@@ -125,12 +157,9 @@ export function useTx() {
       console.log('- Derived Market PDA:', marketPda.toBase58());
       console.log('- Program ID:', PROGRAM_ID.toBase58());
 
-      // Get vault PDA (not ATA) - contract uses: seeds = [b"vault", market.key().as_ref()]
-      const vaultSeed = Buffer.from("vault");
-      const [vaultPda] = PublicKey.findProgramAddressSync(
-        [vaultSeed, marketPda.toBuffer()],
-        PROGRAM_ID
-      );
+      // Vault PDAs (not ATAs), one per side
+      const vaultA = vaultAddress(marketPda, Side.SIDE_A);
+      const vaultB = vaultAddress(marketPda, Side.SIDE_B);
 
       // Build instruction via Anchor
       const ix = await program.methods
@@ -139,13 +168,16 @@ export function useTx() {
           params.feeBps,
           new anchor.BN(params.endTs),
           new anchor.BN(params.resolveDeadlineTs),
-          params.title
+          params.title,
+          params.options ?? DEFAULT_MARKET_OPTIONS
         )
         .accountsStrict({
           creator: publicKey,
+          config: CONFIG_PDA,
           mint,
           market: marketPda,
-          vault: vaultPda,
+          vaultA,
+          vaultB,
           systemProgram: anchor.web3.SystemProgram.programId,
           tokenProgram: new PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
           rent: anchor.web3.SYSVAR_RENT_PUBKEY,
@@ -210,7 +242,7 @@ export function useTx() {
       showSuccess('Market Created!', `Your prediction market is now live. View on Solscan: https://solscan.io/tx/${signature}?cluster=devnet`);
       console.log('Market creation transaction:', signature);
       console.log('Market PDA:', marketPda.toBase58());
-      console.log('Vault PDA:', vaultPda.toBase58());
+      console.log('Vault PDAs:', vaultA.toBase58(), vaultB.toBase58());
       
       return marketPda.toBase58();
     },
@@ -271,12 +303,8 @@ export function useTx() {
         PROGRAM_ID
       );
       
-      // Derive vault PDA
-      const vaultSeed = Buffer.from("vault");
-      const [vaultPda] = PublicKey.findProgramAddressSync(
-        [vaultSeed, marketPda.toBuffer()],
-        PROGRAM_ID
-      );
+      // The vault of the side bet on
+      const vaultPda = vaultAddress(marketPda, params.side);
 
       // Get user's token account
       const userTokenAccount = getAssociatedTokenAddressSync(
@@ -323,7 +351,7 @@ export function useTx() {
       // Build place bet instruction via Anchor
       const side = sideToAnchorFormat(params.side);
       const placeBetIx = await program.methods
-        .placeBet(side, new anchor.BN(params.amount))
+        .placeBet(side, new anchor.BN(params.amount), new anchor.BN(params.minOddsBps ?? 0))
         .accountsStrict({
           user: publicKey,
           market: marketPda,
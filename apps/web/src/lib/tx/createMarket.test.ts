@@ -1,15 +1,15 @@
 import { PublicKey, Connection, Keypair } from '@solana/web3.js';
 import * as anchor from '@coral-xyz/anchor';
 import { Program } from '@coral-xyz/anchor';
-import { getAssociatedTokenAddressSync } from '@solana/spl-token';
-import { keccak_256 } from '@noble/hashes/sha3';
 
 import { FRIENDS_BETS_IDL } from '@/idl/friends_bets';
 import { PROGRAM_ID } from '@/lib/chains/solana';
 
-function keccakBuf(str: string): Buffer {
-  const hash = keccak_256(str);
-  return Buffer.from(hash);
+// seeds = [b"market", creator.key().as_ref(), &market_id.to_le_bytes()]
+function marketIdSeed(marketId: number): Buffer {
+  const bytes = Buffer.alloc(8);
+  bytes.writeBigUInt64LE(BigInt(marketId), 0);
+  return bytes;
 }
 
 describe('IDL Parsing and Program Creation', () => {
@@ -99,13 +99,13 @@ describe('IDL Parsing and Program Creation', () => {
     console.log('Testing PDA derivation...');
     
     const creator = wallet.publicKey;
-    const title = "Test Market";
-    
-    const marketSeed = Buffer.from("market");
-    const titleSeed = keccakBuf(title);
     
     const [marketPda, bump] = PublicKey.findProgramAddressSync(
-      [marketSeed, creator.toBuffer(), titleSeed],
+      [Buffer.from("market"), creator.toBuffer(), marketIdSeed(1)],
+      PROGRAM_ID
+    );
+    const [otherPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), creator.toBuffer(), marketIdSeed(2)],
       PROGRAM_ID
     );
     
@@ -113,35 +113,37 @@ describe('IDL Parsing and Program Creation', () => {
     expect(marketPda instanceof PublicKey).toBe(true);
     expect(bump).toBeDefined();
     expect(typeof bump).toBe('number');
+    // Each market ID gets its own market
+    expect(marketPda.equals(otherPda)).toBe(false);
     
     console.log('✓ Market PDA derived:', marketPda.toBase58());
   });
 
-  test('Should correctly derive vault ATA', () => {
-    console.log('Testing vault ATA derivation...');
+  test('Should correctly derive a vault PDA per side', () => {
+    console.log('Testing vault PDA derivation...');
     
     const creator = wallet.publicKey;
-    const title = "Test Market";
-    const mint = new PublicKey('4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU'); // USDC devnet
-    
-    const marketSeed = Buffer.from("market");
-    const titleSeed = keccakBuf(title);
     
     const [marketPda] = PublicKey.findProgramAddressSync(
-      [marketSeed, creator.toBuffer(), titleSeed],
+      [Buffer.from("market"), creator.toBuffer(), marketIdSeed(1)],
       PROGRAM_ID
     );
     
-    const vaultAta = getAssociatedTokenAddressSync(
-      mint,
-      marketPda,
-      true // allow owner off curve (PDA)
+    // seeds = [b"vault", market.key().as_ref(), &[side as u8]]
+    const [vaultA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), marketPda.toBuffer(), Buffer.from([0])],
+      PROGRAM_ID
+    );
+    const [vaultB] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), marketPda.toBuffer(), Buffer.from([1])],
+      PROGRAM_ID
     );
     
-    expect(vaultAta).toBeDefined();
-    expect(vaultAta instanceof PublicKey).toBe(true);
+    expect(vaultA instanceof PublicKey).toBe(true);
+    expect(vaultB instanceof PublicKey).toBe(true);
+    expect(vaultA.equals(vaultB)).toBe(false);
     
-    console.log('✓ Vault ATA derived:', vaultAta.toBase58());
+    console.log('✓ Vault PDAs derived:', vaultA.toBase58(), vaultB.toBase58());
   });
 
   test('Should build initializeMarket instruction without errors', async () => {
@@ -155,19 +157,21 @@ describe('IDL Parsing and Program Creation', () => {
     const feeBps = 100;
     const endTs = Math.floor(Date.now() / 1000) + 86400; // 1 day from now
     const resolveDeadlineTs = endTs + 86400; // 2 days from now
-    
-    const marketSeed = Buffer.from("market");
-    const titleSeed = keccakBuf(title);
+    const marketId = 1;
     
     const [marketPda] = PublicKey.findProgramAddressSync(
-      [marketSeed, creator.toBuffer(), titleSeed],
+      [Buffer.from("market"), creator.toBuffer(), marketIdSeed(marketId)],
       PROGRAM_ID
     );
+    const [config] = PublicKey.findProgramAddressSync([Buffer.from("config")], PROGRAM_ID);
     
-    const vaultAta = getAssociatedTokenAddressSync(
-      mint,
-      marketPda,
-      true
+    const [vaultA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), marketPda.toBuffer(), Buffer.from([0])],
+      PROGRAM_ID
+    );
+    const [vaultB] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), marketPda.toBuffer(), Buffer.from([1])],
+      PROGRAM_ID
     );
     
     let instruction;
@@ -176,19 +180,22 @@ describe('IDL Parsing and Program Creation', () => {
     await expect(async () => {
       instruction = await program.methods
         .initializeMarket(
+          new anchor.BN(marketId),
           feeBps,
           new anchor.BN(endTs),
           new anchor.BN(resolveDeadlineTs),
-          title
+          title,
+          { allowCashOut: false, cashoutHaircutBps: 0, lockOdds: false, burnFee: false }
         )
         .accountsStrict({
           creator: creator,
+          config,
           mint: mint,
           market: marketPda,
-          vault: vaultAta,
+          vaultA,
+          vaultB,
           systemProgram: anchor.web3.SystemProgram.programId,
           tokenProgram: new PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
-          associatedTokenProgram: new PublicKey("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"),
           rent: anchor.web3.SYSVAR_RENT_PUBKEY,
         })
         .instruction();
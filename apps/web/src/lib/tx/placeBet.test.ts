@@ -60,17 +60,17 @@ describe('Place Bet PDA Derivation', () => {
     console.log('✓ Bump:', bump);
   });
 
-  test('Should correctly derive vault PDA', () => {
+  test('Should correctly derive the vault PDA of the side bet on', () => {
     console.log('Testing vault PDA derivation...');
     
     const marketId = 'a9jvGUcU8oWeHcSTQjcDjNjUzKQjbkXPwfqcPCmHVKS';
     const marketPda = new PublicKey(marketId);
     
-    // According to market creation: seeds = [b"vault", market.key().as_ref()]
+    // Each side has its own vault: seeds = [b"vault", market.key().as_ref(), &[side as u8]]
     const vaultSeed = Buffer.from("vault");
     
     const [vaultPda, bump] = PublicKey.findProgramAddressSync(
-      [vaultSeed, marketPda.toBuffer()],
+      [vaultSeed, marketPda.toBuffer(), Buffer.from([0])], // Side A
       PROGRAM_ID
     );
     
@@ -98,10 +98,10 @@ describe('Place Bet PDA Derivation', () => {
       PROGRAM_ID
     );
     
-    // Derive vault PDA  
+    // Derive the vault PDA of side A, which the bet goes into
     const vaultSeed = Buffer.from("vault");
     const [vaultPda] = PublicKey.findProgramAddressSync(
-      [vaultSeed, marketPda.toBuffer()],
+      [vaultSeed, marketPda.toBuffer(), Buffer.from([0])],
       PROGRAM_ID
     );
     
@@ -114,13 +114,14 @@ describe('Place Bet PDA Derivation', () => {
     
     const side = { a: {} }; // Side A
     const amount = new anchor.BN(1000000); // 1 USDC (6 decimals)
+    const minOddsBps = new anchor.BN(0); // Any odds
     
     let instruction;
     
     // This should not throw an error - matches the fixed useTx.tsx implementation
     await expect(async () => {
       instruction = await program.methods
-        .placeBet(side, amount)
+        .placeBet(side, amount, minOddsBps)
         .accountsStrict({
           user: user,
           market: marketPda,
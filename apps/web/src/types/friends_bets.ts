@@ -1,153 +1,6198 @@
+/**
+ * Program IDL in camelCase format in order to be used in JS/TS.
+ *
+ * Note that this is only a type helper and is not the actual IDL. The original
+ * IDL can be found at `target/idl/friends_bets.json`.
+ */
 export type FriendsBets = {
-  "version": "0.1.0",
-  "name": "friends_bets",
   "address": "BtNtmmrm3KHc5EmvednmUv43hxL8P3S2fsfPVpffx1Rt",
   "metadata": {
-    "name": "friends_bets",
+    "name": "friendsBets",
     "version": "0.1.0",
     "spec": "0.1.0",
     "description": "Created with Anchor"
   },
   "instructions": [
+    {
+      "name": "applyConfigUpdate",
+      "docs": [
+        "Applies the queued config update once its delay has passed. Anyone",
+        "can call this. Markets already created keep the terms they were",
+        "created with."
+      ],
+      "discriminator": [
+        211,
+        233,
+        51,
+        21,
+        33,
+        87,
+        209,
+        147
+      ],
+      "accounts": [
+        {
+          "name": "config",
+          "writable": true
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "burnFee",
+      "docs": [
+        "Burns the fee of a resolved market that opted into `burn_fee` instead",
+        "of paying it to the creator. Anyone can call this."
+      ],
+      "discriminator": [
+        145,
+        3,
+        153,
+        119,
+        219,
+        27,
+        45,
+        36
+      ],
+      "accounts": [
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "mint",
+          "writable": true
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "cancelConfigUpdate",
+      "docs": [
+        "Drops the queued config update."
+      ],
+      "discriminator": [
+        216,
+        180,
+        255,
+        207,
+        118,
+        146,
+        126,
+        89
+      ],
+      "accounts": [
+        {
+          "name": "admin",
+          "signer": true
+        },
+        {
+          "name": "config",
+          "writable": true
+        }
+      ],
+      "args": []
+    },
     {
       "name": "cancelExpired",
-      "discriminator": number[],
-      "accounts": any[],
+      "discriminator": [
+        248,
+        138,
+        112,
+        93,
+        177,
+        89,
+        249,
+        246
+      ],
+      "accounts": [
+        {
+          "name": "market",
+          "writable": true
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "cancelScheduledBet",
+      "docs": [
+        "Returns a scheduled bet's stake and rent to its owner. The owner can",
+        "cancel at any time; anyone can once the bet has expired or its",
+        "market has stopped taking bets."
+      ],
+      "discriminator": [
+        82,
+        101,
+        63,
+        69,
+        84,
+        107,
+        246,
+        9
+      ],
+      "accounts": [
+        {
+          "name": "caller",
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "scheduledBet",
+          "writable": true
+        },
+        {
+          "name": "owner",
+          "writable": true
+        },
+        {
+          "name": "escrow",
+          "writable": true
+        },
+        {
+          "name": "ownerTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "cashOut",
+      "discriminator": [
+        1,
+        110,
+        57,
+        58,
+        159,
+        157,
+        243,
+        192
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "position",
+          "writable": true
+        },
+        {
+          "name": "userTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "claim",
+      "docs": [
+        "Pays out a position of a finalized market. A market still waiting",
+        "on its resolution past the deadline is cancelled first, as",
+        "`cancel_expired` would, so refunds don't wait on a crank. Recorded",
+        "in the user's portfolio when passed it and its current page."
+      ],
+      "discriminator": [
+        62,
+        198,
+        214,
+        193,
+        213,
+        159,
+        108,
+        210
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "position",
+          "writable": true
+        },
+        {
+          "name": "userTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        },
+        {
+          "name": "portfolio",
+          "writable": true,
+          "optional": true
+        },
+        {
+          "name": "portfolioPage",
+          "docs": [
+            "when recording in it"
+          ],
+          "writable": true,
+          "optional": true
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "claimCompressed",
+      "docs": [
+        "Claims the compressed bet at `leaf_index`, proven against `root` by",
+        "`proof`, the leaf's siblings from the bottom up. `root` may be any",
+        "of the tree's recent roots. Expired markets are cancelled first, as",
+        "in `claim`."
+      ],
+      "discriminator": [
+        174,
+        61,
+        174,
+        123,
+        70,
+        253,
+        146,
+        118
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "positionTree",
+          "writable": true
+        },
+        {
+          "name": "userTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": [
+        {
+          "name": "root",
+          "type": {
+            "array": [
+              "u8",
+              32
+            ]
+          }
+        },
+        {
+          "name": "leafIndex",
+          "type": "u32"
+        },
+        {
+          "name": "side",
+          "type": {
+            "defined": {
+              "name": "betSide"
+            }
+          }
+        },
+        {
+          "name": "amount",
+          "type": "u64"
+        },
+        {
+          "name": "lockedPayout",
+          "type": "u64"
+        },
+        {
+          "name": "discountedStake",
+          "type": "u64"
+        },
+        {
+          "name": "proof",
+          "type": {
+            "vec": {
+              "array": [
+                "u8",
+                32
+              ]
+            }
+          }
+        }
+      ]
+    },
+    {
+      "name": "cloneMarket",
+      "docs": [
+        "Creates a new market with the title, fee, mint, options, fee splits,",
+        "charity, large bet threshold and stake caps of `source`."
+      ],
+      "discriminator": [
+        140,
+        10,
+        173,
+        215,
+        138,
+        83,
+        168,
+        229
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "config"
+        },
+        {
+          "name": "source"
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "mint"
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        },
+        {
+          "name": "rent"
+        }
+      ],
+      "args": [
+        {
+          "name": "marketId",
+          "type": "u64"
+        },
+        {
+          "name": "newEndTs",
+          "type": "i64"
+        },
+        {
+          "name": "newResolveDeadlineTs",
+          "type": "i64"
+        }
+      ]
+    },
+    {
+      "name": "closeActivityStats",
+      "docs": [
+        "Permissionless: closes a settled market's activity account once the",
+        "claim window has passed, returning its rent to whoever paid for it.",
+        "`gc_market` waits until it's closed."
+      ],
+      "discriminator": [
+        150,
+        224,
+        61,
+        153,
+        59,
+        214,
+        120,
+        27
+      ],
+      "accounts": [
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "activity",
+          "writable": true
+        },
+        {
+          "name": "payer",
+          "writable": true
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "closeAndResolve",
+      "docs": [
+        "Closes betting and resolves in one step once `end_ts` has passed,",
+        "skipping the intermediate `PendingResolve` state."
+      ],
+      "discriminator": [
+        25,
+        186,
+        156,
+        48,
+        220,
+        199,
+        144,
+        31
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        }
+      ],
+      "args": [
+        {
+          "name": "outcome",
+          "type": {
+            "defined": {
+              "name": "betSide"
+            }
+          }
+        }
+      ]
+    },
+    {
+      "name": "closeBetting",
+      "discriminator": [
+        183,
+        243,
+        76,
+        1,
+        242,
+        130,
+        138,
+        58
+      ],
+      "accounts": [
+        {
+          "name": "market",
+          "writable": true
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "closeBettorPage",
+      "docs": [
+        "Permissionless: closes one of a settled market's bettor pages once",
+        "the claim window has passed, returning its rent to whoever paid for",
+        "it. `gc_market` waits until every page is closed."
+      ],
+      "discriminator": [
+        117,
+        193,
+        38,
+        91,
+        123,
+        58,
+        180,
+        99
+      ],
+      "accounts": [
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "bettorPage",
+          "writable": true
+        },
+        {
+          "name": "payer",
+          "writable": true
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "closeMarketTitle",
+      "docs": [
+        "Permissionless: frees a settled market's title for new markets,",
+        "returning the registration's rent to the creator. `gc_market` waits",
+        "until it's closed."
+      ],
+      "discriminator": [
+        146,
+        109,
+        93,
+        111,
+        115,
+        49,
+        144,
+        47
+      ],
+      "accounts": [
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "marketTitle",
+          "writable": true
+        },
+        {
+          "name": "creator",
+          "writable": true
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "closePortfolio",
+      "docs": [
+        "Closes the user's portfolio and its current page, if it has one,",
+        "once its other pages are closed."
+      ],
+      "discriminator": [
+        2,
+        204,
+        2,
+        127,
+        139,
+        69,
+        76,
+        6
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "portfolio",
+          "writable": true
+        },
+        {
+          "name": "portfolioPage",
+          "writable": true,
+          "optional": true
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "closePortfolioPage",
+      "docs": [
+        "Closes a full page of the user's portfolio, refunding its rent."
+      ],
+      "discriminator": [
+        135,
+        17,
+        194,
+        130,
+        19,
+        90,
+        49,
+        195
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "portfolio",
+          "writable": true
+        },
+        {
+          "name": "portfolioPage",
+          "writable": true
+        }
+      ],
+      "args": [
+        {
+          "name": "index",
+          "type": "u32"
+        }
+      ]
+    },
+    {
+      "name": "collectInsurance",
+      "docs": [
+        "Moves a resolved market's insurance cut into the insurance vault of",
+        "its mint, creating the vault if needed. Anyone can call this."
+      ],
+      "discriminator": [
+        174,
+        138,
+        5,
+        122,
+        76,
+        66,
+        40,
+        158
+      ],
+      "accounts": [
+        {
+          "name": "payer",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "config"
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "mint"
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "insuranceVault",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "compactMarket",
+      "docs": [
+        "Shrinks a fully settled market down to its record: the title and",
+        "dependency, needed only while the market is live, are dropped and",
+        "the rent they took is refunded to the creator. The market keeps",
+        "every pool, outcome and fee figure and can still be collected by",
+        "`gc_market` once its claim window has passed."
+      ],
+      "discriminator": [
+        143,
+        42,
+        160,
+        21,
+        1,
+        102,
+        97,
+        128
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "compensate",
+      "docs": [
+        "Pays `amount` from the insurance vault of a cancelled market's mint",
+        "to the owner of `position`, making good a bettor of a market voided",
+        "over creator fraud or a program bug whose vault can't refund them.",
+        "Admin only. Compensation is capped at the stake the position hasn't",
+        "been refunded yet, and its claim pays that much less afterwards."
+      ],
+      "discriminator": [
+        27,
+        75,
+        62,
+        233,
+        36,
+        91,
+        225,
+        117
+      ],
+      "accounts": [
+        {
+          "name": "admin",
+          "signer": true
+        },
+        {
+          "name": "config",
+          "writable": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "insuranceVault",
+          "writable": true
+        },
+        {
+          "name": "position",
+          "docs": [
+            "The bettor being compensated, who must hold stake in the market"
+          ],
+          "writable": true
+        },
+        {
+          "name": "recipientTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": [
+        {
+          "name": "amount",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "distribute",
+      "docs": [
+        "Pushes payouts to a batch of positions passed in remaining accounts as",
+        "`[position, owner_ata]` pairs. Already-claimed positions are skipped so",
+        "overlapping batches don't fail."
+      ],
+      "discriminator": [
+        191,
+        44,
+        223,
+        207,
+        164,
+        236,
+        126,
+        61
+      ],
+      "accounts": [
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "distributeFees",
+      "docs": [
+        "Pays a resolved market's fee out to its split recipients and the",
+        "creator in one go. Recipients' token accounts follow in remaining",
+        "accounts, in the order of the market's `fee_splits`. Anyone can call",
+        "this."
+      ],
+      "discriminator": [
+        120,
+        56,
+        27,
+        7,
+        53,
+        176,
+        113,
+        186
+      ],
+      "accounts": [
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "creatorTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "donateToCharity",
+      "docs": [
+        "Pays a resolved market's charity cut to its charity. Anyone can call",
+        "this."
+      ],
+      "discriminator": [
+        231,
+        207,
+        59,
+        111,
+        59,
+        196,
+        32,
+        250
+      ],
+      "accounts": [
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "charityTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "enableActivityStats",
+      "docs": [
+        "Has `place_bet` keep the market's `MarketActivity` account: bets and",
+        "volume per hour over the last day and a count of bettors, from now",
+        "on. Lets other programs and frontends rank open markets by how busy",
+        "they are without an indexer. The creator pays the account's rent,",
+        "returned by `close_activity_stats`."
+      ],
+      "discriminator": [
+        73,
+        170,
+        159,
+        248,
+        79,
+        223,
+        136,
+        67
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "activity",
+          "writable": true
+        },
+        {
+          "name": "systemProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "enableBettorPages",
+      "docs": [
+        "Has `place_bet` record every bet on a market nobody has bet on yet",
+        "in `BettorPage` accounts, so instructions run at resolution can walk",
+        "the market's bettors on-chain. Pages are created as bets fill them",
+        "and closed with `close_bettor_page` once the claim window is over."
+      ],
+      "discriminator": [
+        244,
+        179,
+        113,
+        159,
+        150,
+        68,
+        179,
+        226
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "enableCompressedPositions",
+      "docs": [
+        "Switches a market nobody has bet on yet to compressed positions:",
+        "bets append leaves to a Merkle tree instead of creating position",
+        "accounts, and are claimed one by one with a proof. Worth the tree's",
+        "rent on public markets with many bettors; cash-outs and batch bets",
+        "aren't supported."
+      ],
+      "discriminator": [
+        250,
+        14,
+        135,
+        4,
+        47,
+        14,
+        105,
+        167
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "positionTree",
+          "writable": true
+        },
+        {
+          "name": "systemProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "executeCashout",
+      "docs": [
+        "Permissionless: cashes a position out for its owner once what it",
+        "would win has fallen to the owner's order, paying the executor",
+        "`CASHOUT_ORDER_TIP_BPS` of the payout."
+      ],
+      "discriminator": [
+        94,
+        99,
+        106,
+        117,
+        56,
+        65,
+        107,
+        145
+      ],
+      "accounts": [
+        {
+          "name": "executor",
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "owner"
+        },
+        {
+          "name": "position",
+          "writable": true
+        },
+        {
+          "name": "ownerTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "executorTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "executeScheduledBet",
+      "docs": [
+        "Permissionless: places a due scheduled bet for its owner out of its",
+        "escrow, as `place_bet` would, with the same remaining accounts. The",
+        "executor pays for the owner's position and bettor page if needed",
+        "and gets the rent of the scheduled bet and its escrow."
+      ],
+      "discriminator": [
+        251,
+        161,
+        122,
+        168,
+        129,
+        139,
+        234,
+        253
+      ],
+      "accounts": [
+        {
+          "name": "executor",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "scheduledBet",
+          "writable": true
+        },
+        {
+          "name": "owner"
+        },
+        {
+          "name": "position",
+          "writable": true
+        },
+        {
+          "name": "escrow",
+          "writable": true
+        },
+        {
+          "name": "vault",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        },
+        {
+          "name": "rent"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "forkMarket",
+      "docs": [
+        "Cancels a contested market so its stakes can be refunded, and opens a",
+        "replacement with clarified terms that points back at the original",
+        "and keeps its fee splits. Cancelling refunds a side that was about",
+        "to lose, so the creator can't do it alone: the config admin co-signs",
+        "as arbitrator, and the fork goes in the admin log."
+      ],
+      "discriminator": [
+        73,
+        123,
+        21,
+        226,
+        40,
+        114,
+        234,
+        36
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "admin",
+          "docs": [
+            "Arbitrates the fork along with the creator"
+          ],
+          "signer": true
+        },
+        {
+          "name": "config",
+          "writable": true
+        },
+        {
+          "name": "original",
+          "writable": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "mint"
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        },
+        {
+          "name": "rent"
+        }
+      ],
+      "args": [
+        {
+          "name": "marketId",
+          "type": "u64"
+        },
+        {
+          "name": "endTs",
+          "type": "i64"
+        },
+        {
+          "name": "resolveDeadlineTs",
+          "type": "i64"
+        },
+        {
+          "name": "title",
+          "type": "string"
+        }
+      ]
+    },
+    {
+      "name": "gcMarket",
+      "docs": [
+        "Permissionless cleanup of a settled market once the claim window has",
+        "passed and its bettor pages, activity account, title registration and",
+        "scheduled bets are closed. Leftover positions are passed",
+        "in remaining accounts, after the position tree on a compressed",
+        "market; any that still hold a payout abort the call. Positions, vaults",
+        "and market are closed and the reclaimed rent is split between the",
+        "caller and creator."
+      ],
+      "discriminator": [
+        205,
+        68,
+        170,
+        239,
+        130,
+        51,
+        188,
+        16
+      ],
+      "accounts": [
+        {
+          "name": "caller",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "creator",
+          "writable": true
+        },
+        {
+          "name": "creatorTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "initializeConfig",
+      "docs": [
+        "Creates the program-wide config and staking vault. Only the program's",
+        "upgrade authority can, and it becomes the admin; later changes wait",
+        "out `update_delay_secs`."
+      ],
+      "discriminator": [
+        208,
+        127,
+        21,
+        1,
+        194,
+        190,
+        196,
+        70
+      ],
+      "accounts": [
+        {
+          "name": "admin",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "config",
+          "writable": true
+        },
+        {
+          "name": "stakeMint"
+        },
+        {
+          "name": "stakeVault",
+          "writable": true
+        },
+        {
+          "name": "program",
+          "docs": [
+            "The deployed program, so its upgrade authority can be checked"
+          ]
+        },
+        {
+          "name": "programData"
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        },
+        {
+          "name": "rent"
+        }
+      ],
+      "args": [
+        {
+          "name": "unbondingSecs",
+          "type": "i64"
+        },
+        {
+          "name": "feeTiers",
+          "type": {
+            "vec": {
+              "defined": {
+                "name": "feeTier"
+              }
+            }
+          }
+        },
+        {
+          "name": "policy",
+          "type": {
+            "defined": {
+              "name": "marketPolicy"
+            }
+          }
+        },
+        {
+          "name": "updateDelaySecs",
+          "type": "i64"
+        }
+      ]
+    },
+    {
+      "name": "initializeMarket",
+      "docs": [
+        "A `fee_bps` of [`DEFAULT_FEE`], or an `end_ts` or",
+        "`resolve_deadline_ts` of 0, takes the config's default. The creator's",
+        "stake account may go first in remaining accounts to raise the fee",
+        "ceiling to their fee tier's."
+      ],
+      "discriminator": [
+        35,
+        35,
+        189,
+        193,
+        155,
+        48,
+        170,
+        203
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "config"
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "mint"
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        },
+        {
+          "name": "rent"
+        }
+      ],
+      "args": [
+        {
+          "name": "marketId",
+          "type": "u64"
+        },
+        {
+          "name": "feeBps",
+          "type": "u16"
+        },
+        {
+          "name": "endTs",
+          "type": "i64"
+        },
+        {
+          "name": "resolveDeadlineTs",
+          "type": "i64"
+        },
+        {
+          "name": "title",
+          "type": "string"
+        },
+        {
+          "name": "options",
+          "type": {
+            "defined": {
+              "name": "marketOptions"
+            }
+          }
+        }
+      ]
+    },
+    {
+      "name": "initializeMarketWithBet",
+      "docs": [
+        "Creates a market and places the creator's first bet on it in one",
+        "instruction, so a market never sits in the registry without stake",
+        "when a separate bet transaction fails. Defaults and the fee ceiling",
+        "work as in `initialize_market`, and the creator's stake account also",
+        "earns the bet their fee discount."
+      ],
+      "discriminator": [
+        35,
+        203,
+        46,
+        169,
+        45,
+        62,
+        196,
+        241
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "config"
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "mint"
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "position",
+          "writable": true
+        },
+        {
+          "name": "creatorTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        },
+        {
+          "name": "rent"
+        }
+      ],
+      "args": [
+        {
+          "name": "marketId",
+          "type": "u64"
+        },
+        {
+          "name": "feeBps",
+          "type": "u16"
+        },
+        {
+          "name": "endTs",
+          "type": "i64"
+        },
+        {
+          "name": "resolveDeadlineTs",
+          "type": "i64"
+        },
+        {
+          "name": "title",
+          "type": "string"
+        },
+        {
+          "name": "options",
+          "type": {
+            "defined": {
+              "name": "marketOptions"
+            }
+          }
+        },
+        {
+          "name": "side",
+          "type": {
+            "defined": {
+              "name": "betSide"
+            }
+          }
+        },
+        {
+          "name": "amount",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "openPortfolio",
+      "docs": [
+        "Opens the user's portfolio, which `place_bet`, `place_bet_init_ata`",
+        "and `claim` record in when passed it and its current page."
+      ],
+      "discriminator": [
+        46,
+        176,
+        168,
+        121,
+        6,
+        6,
+        222,
+        78
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "portfolio",
+          "writable": true
+        },
+        {
+          "name": "systemProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "placeBet",
+      "docs": [
+        "On a market that tracks bettors, the current bettor page goes first",
+        "in remaining accounts; the bettor pays for it when the last is full.",
+        "Stakers follow it with `[config, stake_account]` to bet at their fee",
+        "discount, which the position keeps whatever they do with the stake.",
+        "A bettor with a portfolio passes it and its current page to have a",
+        "new position recorded there."
+      ],
+      "discriminator": [
+        222,
+        62,
+        67,
+        220,
+        63,
+        166,
+        126,
+        33
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "position",
+          "writable": true
+        },
+        {
+          "name": "userTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "vault",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        },
+        {
+          "name": "rent"
+        },
+        {
+          "name": "portfolio",
+          "writable": true,
+          "optional": true
+        },
+        {
+          "name": "portfolioPage",
+          "docs": [
+            "when recording in it"
+          ],
+          "writable": true,
+          "optional": true
+        }
+      ],
+      "args": [
+        {
+          "name": "side",
+          "type": {
+            "defined": {
+              "name": "betSide"
+            }
+          }
+        },
+        {
+          "name": "amount",
+          "type": "u64"
+        },
+        {
+          "name": "minOddsBps",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "placeBetInitAta",
+      "docs": [
+        "`place_bet`, creating the user's associated token account for the",
+        "market mint first if it doesn't exist yet."
+      ],
+      "discriminator": [
+        26,
+        129,
+        246,
+        127,
+        28,
+        240,
+        161,
+        138
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "mint"
+        },
+        {
+          "name": "position",
+          "writable": true
+        },
+        {
+          "name": "userTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "vault",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "associatedTokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        },
+        {
+          "name": "rent"
+        },
+        {
+          "name": "portfolio",
+          "writable": true,
+          "optional": true
+        },
+        {
+          "name": "portfolioPage",
+          "docs": [
+            "when recording in it"
+          ],
+          "writable": true,
+          "optional": true
+        }
+      ],
+      "args": [
+        {
+          "name": "side",
+          "type": {
+            "defined": {
+              "name": "betSide"
+            }
+          }
+        },
+        {
+          "name": "amount",
+          "type": "u64"
+        },
+        {
+          "name": "minOddsBps",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "placeBetsBatch",
+      "docs": [
+        "Places several bets atomically. Each entry in `bets` is matched by four",
+        "remaining accounts: `[market, position, user_token_account, vault]`,",
+        "the vault being that of the side bet on, optionally followed by the",
+        "staker's `[config, stake_account]` for their fee discount.",
+        "Missing positions are created and paid for by `user`."
+      ],
+      "discriminator": [
+        59,
+        64,
+        217,
+        24,
+        62,
+        121,
+        208,
+        24
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        }
+      ],
+      "args": [
+        {
+          "name": "bets",
+          "type": {
+            "vec": {
+              "defined": {
+                "name": "batchBet"
+              }
+            }
+          }
+        }
+      ]
+    },
+    {
+      "name": "placeCompressedBet",
+      "docs": [
+        "Bets on a market with compressed positions, appending a leaf for",
+        "this bet alone; betting again appends another. Stakers pass",
+        "`[config, stake_account]` in remaining accounts for their fee",
+        "discount, as with `place_bet`."
+      ],
+      "discriminator": [
+        67,
+        106,
+        207,
+        12,
+        205,
+        6,
+        135,
+        239
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "positionTree",
+          "writable": true
+        },
+        {
+          "name": "userTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "vault",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": [
+        {
+          "name": "side",
+          "type": {
+            "defined": {
+              "name": "betSide"
+            }
+          }
+        },
+        {
+          "name": "amount",
+          "type": "u64"
+        },
+        {
+          "name": "minOddsBps",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "proposeConfigUpdate",
+      "docs": [
+        "Queues `update` to replace the admin, staking settings, market",
+        "policy and delay once the current delay has passed, replacing any",
+        "update already queued. The admin only signs, so a governance PDA",
+        "such as an SPL Governance realm's can hold the role."
+      ],
+      "discriminator": [
+        134,
+        33,
+        64,
+        84,
+        30,
+        156,
+        236,
+        79
+      ],
+      "accounts": [
+        {
+          "name": "admin",
+          "signer": true
+        },
+        {
+          "name": "config",
+          "writable": true
+        }
+      ],
+      "args": [
+        {
+          "name": "update",
+          "type": {
+            "defined": {
+              "name": "configUpdate"
+            }
+          }
+        }
+      ]
+    },
+    {
+      "name": "registerMarketTitle",
+      "docs": [
+        "Registers an open market under its title, `title_hash` being",
+        "[`title::title_hash`] of it, and `nonce`, so frontends can find it",
+        "before creating another market on the same question. Fails if",
+        "another market holds that title and nonce. Meant to go in the same",
+        "transaction as the market's creation; the creator pays the rent,",
+        "returned by `close_market_title`."
+      ],
+      "discriminator": [
+        1,
+        75,
+        200,
+        144,
+        180,
+        88,
+        97,
+        172
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "marketTitle",
+          "writable": true
+        },
+        {
+          "name": "systemProgram"
+        }
+      ],
+      "args": [
+        {
+          "name": "titleHash",
+          "type": {
+            "array": [
+              "u8",
+              32
+            ]
+          }
+        },
+        {
+          "name": "nonce",
+          "type": "u16"
+        }
+      ]
+    },
+    {
+      "name": "resolve",
+      "discriminator": [
+        246,
+        150,
+        236,
+        206,
+        108,
+        63,
+        58,
+        10
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        }
+      ],
+      "args": [
+        {
+          "name": "outcome",
+          "type": {
+            "defined": {
+              "name": "betSide"
+            }
+          }
+        }
+      ]
+    },
+    {
+      "name": "scheduleBet",
+      "docs": [
+        "Schedules a bet of `amount` on `side` that anyone can place for the",
+        "owner between `execute_after` and `expiry`, moving the stake into",
+        "the scheduled bet's escrow now. `id` tells apart the owner's",
+        "scheduled bets on the market. The owner pays the rent of both",
+        "accounts, which goes to whoever places or cancels the bet."
+      ],
+      "discriminator": [
+        142,
+        254,
+        215,
+        116,
+        191,
+        173,
+        193,
+        49
+      ],
+      "accounts": [
+        {
+          "name": "owner",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "mint"
+        },
+        {
+          "name": "scheduledBet",
+          "writable": true
+        },
+        {
+          "name": "escrow",
+          "writable": true
+        },
+        {
+          "name": "ownerTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        },
+        {
+          "name": "rent"
+        }
+      ],
+      "args": [
+        {
+          "name": "id",
+          "type": "u64"
+        },
+        {
+          "name": "side",
+          "type": {
+            "defined": {
+              "name": "betSide"
+            }
+          }
+        },
+        {
+          "name": "amount",
+          "type": "u64"
+        },
+        {
+          "name": "minOddsBps",
+          "type": "u64"
+        },
+        {
+          "name": "executeAfter",
+          "type": "i64"
+        },
+        {
+          "name": "expiry",
+          "type": "i64"
+        }
+      ]
+    },
+    {
+      "name": "setCashoutOrder",
+      "docs": [
+        "Places a standing order to cash the position out once what it would",
+        "win falls to `min_value`, or clears it with zero. Anyone can then",
+        "fill it with `execute_cashout` for a tip, in a later slot than the",
+        "stakes last moved in."
+      ],
+      "discriminator": [
+        222,
+        217,
+        109,
+        240,
+        10,
+        39,
+        238,
+        77
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "signer": true
+        },
+        {
+          "name": "market"
+        },
+        {
+          "name": "position",
+          "writable": true
+        }
+      ],
+      "args": [
+        {
+          "name": "minValue",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "setCharity",
+      "docs": [
+        "Sends `charity_bps` of the pool of a market nobody has bet on yet to",
+        "`charity_token_account` once it resolves, for groups whose losers",
+        "pay to charity. Passing no account and no cut drops the charity."
+      ],
+      "discriminator": [
+        228,
+        196,
+        204,
+        117,
+        199,
+        69,
+        82,
+        82
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "charityTokenAccount",
+          "optional": true
+        }
+      ],
+      "args": [
+        {
+          "name": "charityBps",
+          "type": "u16"
+        }
+      ]
+    },
+    {
+      "name": "setDependency",
+      "docs": [
+        "Makes `market` conditional on `parent` resolving to `outcome`. Only",
+        "allowed before any stake is placed so bettors always see the condition."
+      ],
+      "discriminator": [
+        161,
+        43,
+        240,
+        236,
+        155,
+        181,
+        52,
+        61
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "parent"
+        }
+      ],
+      "args": [
+        {
+          "name": "outcome",
+          "type": {
+            "defined": {
+              "name": "betSide"
+            }
+          }
+        }
+      ]
+    },
+    {
+      "name": "setFeeSplits",
+      "docs": [
+        "Shares the fee of a market nobody has bet on yet between up to",
+        "three recipients, such as the frontend the market was made on or a",
+        "league treasury, each taking `bps` of it. The creator keeps the rest.",
+        "An empty `splits` goes back to paying the creator alone."
+      ],
+      "discriminator": [
+        128,
+        192,
+        6,
+        243,
+        95,
+        243,
+        227,
+        114
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        }
+      ],
+      "args": [
+        {
+          "name": "splits",
+          "type": {
+            "vec": {
+              "defined": {
+                "name": "feeSplit"
+              }
+            }
+          }
+        }
+      ]
+    },
+    {
+      "name": "setLargeBetThreshold",
+      "docs": [
+        "Makes bets of at least `threshold` on an open market emit",
+        "`LargeBetPlaced` besides `BetPlaced`, for alerts on big swings. A",
+        "threshold of 0 turns these off."
+      ],
+      "discriminator": [
+        235,
+        12,
+        168,
+        181,
+        41,
+        157,
+        103,
+        164
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        }
+      ],
+      "args": [
+        {
+          "name": "threshold",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "setStakeCaps",
+      "docs": [
+        "Bounds how lopsided an open market can get: bets taking a side past",
+        "its cap (`cap_a`, `cap_b`) fail, as do bets leaving a side more than",
+        "`max_ratio_bps` / 10000 times the other once both have stake. 0",
+        "turns a limit off; a ratio must be at least 1x. Lowering a cap",
+        "below what's staked only stops further bets on that side."
+      ],
+      "discriminator": [
+        106,
+        70,
+        71,
+        84,
+        192,
+        167,
+        146,
+        19
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        }
+      ],
+      "args": [
+        {
+          "name": "capA",
+          "type": "u64"
+        },
+        {
+          "name": "capB",
+          "type": "u64"
+        },
+        {
+          "name": "maxRatioBps",
+          "type": "u32"
+        }
+      ]
+    },
+    {
+      "name": "stake",
+      "discriminator": [
+        206,
+        176,
+        202,
+        18,
+        200,
+        209,
+        179,
+        108
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "config"
+        },
+        {
+          "name": "stakeAccount",
+          "writable": true
+        },
+        {
+          "name": "userTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "stakeVault",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        },
+        {
+          "name": "systemProgram"
+        }
+      ],
+      "args": [
+        {
+          "name": "amount",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "unstake",
+      "docs": [
+        "Starts unbonding `amount`. It stops counting towards fee tiers right",
+        "away and can be withdrawn once the unbonding period has passed.",
+        "Unstaking again restarts the period for the whole unbonding balance."
+      ],
+      "discriminator": [
+        90,
+        95,
+        107,
+        42,
+        205,
+        124,
+        50,
+        225
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "signer": true
+        },
+        {
+          "name": "config"
+        },
+        {
+          "name": "stakeAccount",
+          "writable": true
+        }
+      ],
+      "args": [
+        {
+          "name": "amount",
+          "type": "u64"
+        }
+      ]
+    },
+    {
+      "name": "voidDependent",
+      "docs": [
+        "Voids a conditional market once its parent has been cancelled or",
+        "resolved against the required outcome. Anyone can call this."
+      ],
+      "discriminator": [
+        124,
+        238,
+        52,
+        96,
+        243,
+        243,
+        132,
+        211
+      ],
+      "accounts": [
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "parent"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "withdrawCreatorFee",
+      "discriminator": [
+        241,
+        228,
+        135,
+        153,
+        25,
+        22,
+        155,
+        62
+      ],
+      "accounts": [
+        {
+          "name": "creator",
+          "writable": true,
+          "signer": true
+        },
+        {
+          "name": "market",
+          "writable": true
+        },
+        {
+          "name": "creatorTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "vaultA",
+          "writable": true
+        },
+        {
+          "name": "vaultB",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
+      "args": []
+    },
+    {
+      "name": "withdrawUnstaked",
+      "discriminator": [
+        19,
+        202,
+        68,
+        255,
+        216,
+        40,
+        205,
+        61
+      ],
+      "accounts": [
+        {
+          "name": "user",
+          "signer": true
+        },
+        {
+          "name": "config"
+        },
+        {
+          "name": "stakeAccount",
+          "writable": true
+        },
+        {
+          "name": "userTokenAccount",
+          "writable": true
+        },
+        {
+          "name": "stakeVault",
+          "writable": true
+        },
+        {
+          "name": "tokenProgram"
+        }
+      ],
       "args": []
+    }
+  ],
+  "accounts": [
+    {
+      "name": "bettorPage",
+      "discriminator": [
+        132,
+        97,
+        255,
+        166,
+        50,
+        73,
+        74,
+        248
+      ]
+    },
+    {
+      "name": "config",
+      "discriminator": [
+        155,
+        12,
+        170,
+        224,
+        30,
+        250,
+        204,
+        130
+      ]
+    },
+    {
+      "name": "market",
+      "discriminator": [
+        219,
+        190,
+        213,
+        55,
+        0,
+        227,
+        198,
+        154
+      ]
+    },
+    {
+      "name": "marketActivity",
+      "discriminator": [
+        6,
+        164,
+        121,
+        167,
+        114,
+        135,
+        94,
+        218
+      ]
+    },
+    {
+      "name": "marketTitle",
+      "discriminator": [
+        74,
+        127,
+        51,
+        147,
+        97,
+        217,
+        1,
+        85
+      ]
+    },
+    {
+      "name": "portfolio",
+      "discriminator": [
+        94,
+        158,
+        71,
+        245,
+        122,
+        102,
+        110,
+        225
+      ]
+    },
+    {
+      "name": "portfolioPage",
+      "discriminator": [
+        32,
+        21,
+        139,
+        2,
+        152,
+        113,
+        88,
+        249
+      ]
+    },
+    {
+      "name": "position",
+      "discriminator": [
+        170,
+        188,
+        143,
+        228,
+        122,
+        64,
+        247,
+        208
+      ]
+    },
+    {
+      "name": "positionTree",
+      "discriminator": [
+        97,
+        3,
+        123,
+        118,
+        233,
+        133,
+        116,
+        211
+      ]
+    },
+    {
+      "name": "scheduledBet",
+      "discriminator": [
+        158,
+        235,
+        221,
+        246,
+        69,
+        85,
+        169,
+        73
+      ]
+    },
+    {
+      "name": "stakeAccount",
+      "discriminator": [
+        80,
+        158,
+        67,
+        124,
+        50,
+        189,
+        192,
+        255
+      ]
+    }
+  ],
+  "events": [
+    {
+      "discriminator": [
+        192,
+        104,
+        9,
+        134,
+        246,
+        6,
+        199,
+        141
+      ],
+      "name": "activityStatsClosed"
+    },
+    {
+      "discriminator": [
+        31,
+        245,
+        149,
+        88,
+        125,
+        184,
+        170,
+        14
+      ],
+      "name": "activityStatsEnabled"
+    },
+    {
+      "discriminator": [
+        81,
+        115,
+        237,
+        103,
+        63,
+        17,
+        184,
+        252
+      ],
+      "name": "adminActionLogged"
+    },
+    {
+      "discriminator": [
+        88,
+        88,
+        145,
+        226,
+        126,
+        206,
+        32,
+        0
+      ],
+      "name": "betPlaced"
+    },
+    {
+      "discriminator": [
+        155,
+        113,
+        126,
+        180,
+        159,
+        100,
+        174,
+        43
+      ],
+      "name": "betReceipt"
+    },
+    {
+      "discriminator": [
+        82,
+        253,
+        56,
+        132,
+        208,
+        109,
+        78,
+        249
+      ],
+      "name": "betScheduled"
+    },
+    {
+      "discriminator": [
+        23,
+        184,
+        157,
+        244,
+        65,
+        44,
+        152,
+        70
+      ],
+      "name": "bettingClosed"
+    },
+    {
+      "discriminator": [
+        249,
+        190,
+        168,
+        81,
+        55,
+        183,
+        98,
+        201
+      ],
+      "name": "bettorPageClosed"
+    },
+    {
+      "discriminator": [
+        65,
+        207,
+        25,
+        184,
+        167,
+        221,
+        18,
+        84
+      ],
+      "name": "bettorPagesEnabled"
+    },
+    {
+      "discriminator": [
+        136,
+        23,
+        42,
+        65,
+        143,
+        233,
+        234,
+        46
+      ],
+      "name": "cancelled"
+    },
+    {
+      "discriminator": [
+        186,
+        100,
+        138,
+        5,
+        143,
+        105,
+        29,
+        244
+      ],
+      "name": "cashOutOrderExecuted"
+    },
+    {
+      "discriminator": [
+        249,
+        74,
+        65,
+        82,
+        157,
+        22,
+        86,
+        1
+      ],
+      "name": "cashOutOrderSet"
+    },
+    {
+      "discriminator": [
+        141,
+        169,
+        223,
+        233,
+        161,
+        75,
+        111,
+        255
+      ],
+      "name": "cashedOut"
+    },
+    {
+      "discriminator": [
+        62,
+        140,
+        175,
+        86,
+        10,
+        21,
+        131,
+        223
+      ],
+      "name": "charityDonated"
+    },
+    {
+      "discriminator": [
+        152,
+        24,
+        207,
+        132,
+        32,
+        243,
+        132,
+        71
+      ],
+      "name": "charitySet"
+    },
+    {
+      "discriminator": [
+        217,
+        192,
+        123,
+        72,
+        108,
+        150,
+        248,
+        33
+      ],
+      "name": "claimed"
+    },
+    {
+      "discriminator": [
+        227,
+        185,
+        143,
+        17,
+        41,
+        57,
+        117,
+        154
+      ],
+      "name": "compensated"
+    },
+    {
+      "discriminator": [
+        169,
+        204,
+        99,
+        107,
+        87,
+        225,
+        106,
+        156
+      ],
+      "name": "compressedBetPlaced"
+    },
+    {
+      "discriminator": [
+        80,
+        30,
+        161,
+        48,
+        20,
+        149,
+        249,
+        108
+      ],
+      "name": "compressedPositionClaimed"
+    },
+    {
+      "discriminator": [
+        118,
+        51,
+        223,
+        222,
+        189,
+        52,
+        133,
+        14
+      ],
+      "name": "configUpdateCancelled"
+    },
+    {
+      "discriminator": [
+        119,
+        50,
+        50,
+        80,
+        209,
+        240,
+        6,
+        171
+      ],
+      "name": "configUpdateProposed"
+    },
+    {
+      "discriminator": [
+        40,
+        241,
+        230,
+        122,
+        11,
+        19,
+        198,
+        194
+      ],
+      "name": "configUpdated"
+    },
+    {
+      "discriminator": [
+        117,
+        158,
+        80,
+        247,
+        76,
+        103,
+        222,
+        157
+      ],
+      "name": "creatorFeeWithdrawn"
+    },
+    {
+      "discriminator": [
+        112,
+        206,
+        157,
+        20,
+        102,
+        67,
+        23,
+        80
+      ],
+      "name": "dependencySet"
+    },
+    {
+      "discriminator": [
+        145,
+        91,
+        45,
+        171,
+        189,
+        224,
+        44,
+        218
+      ],
+      "name": "feeBurned"
+    },
+    {
+      "discriminator": [
+        6,
+        165,
+        201,
+        210,
+        229,
+        129,
+        136,
+        176
+      ],
+      "name": "feeRebated"
+    },
+    {
+      "discriminator": [
+        143,
+        6,
+        27,
+        243,
+        85,
+        64,
+        149,
+        97
+      ],
+      "name": "feeSplitPaid"
+    },
+    {
+      "discriminator": [
+        16,
+        129,
+        56,
+        81,
+        229,
+        78,
+        139,
+        254
+      ],
+      "name": "feeSplitsSet"
+    },
+    {
+      "discriminator": [
+        107,
+        209,
+        122,
+        203,
+        220,
+        93,
+        83,
+        62
+      ],
+      "name": "insuranceCollected"
+    },
+    {
+      "discriminator": [
+        138,
+        249,
+        190,
+        157,
+        75,
+        47,
+        206,
+        217
+      ],
+      "name": "largeBetPlaced"
+    },
+    {
+      "discriminator": [
+        252,
+        192,
+        213,
+        20,
+        188,
+        120,
+        129,
+        109
+      ],
+      "name": "largeBetThresholdSet"
+    },
+    {
+      "discriminator": [
+        239,
+        162,
+        200,
+        252,
+        204,
+        186,
+        125,
+        93
+      ],
+      "name": "marketCloned"
+    },
+    {
+      "discriminator": [
+        151,
+        108,
+        4,
+        191,
+        31,
+        249,
+        70,
+        155
+      ],
+      "name": "marketCollected"
+    },
+    {
+      "discriminator": [
+        255,
+        247,
+        13,
+        104,
+        77,
+        43,
+        54,
+        212
+      ],
+      "name": "marketCompacted"
+    },
+    {
+      "discriminator": [
+        82,
+        169,
+        116,
+        155,
+        166,
+        195,
+        222,
+        237
+      ],
+      "name": "marketForked"
+    },
+    {
+      "discriminator": [
+        134,
+        160,
+        122,
+        87,
+        50,
+        3,
+        255,
+        81
+      ],
+      "name": "marketInitialized"
+    },
+    {
+      "discriminator": [
+        48,
+        209,
+        194,
+        247,
+        243,
+        233,
+        182,
+        56
+      ],
+      "name": "marketTitleClosed"
+    },
+    {
+      "discriminator": [
+        205,
+        84,
+        230,
+        2,
+        161,
+        62,
+        60,
+        192
+      ],
+      "name": "marketTitleRegistered"
+    },
+    {
+      "discriminator": [
+        5,
+        50,
+        67,
+        30,
+        6,
+        200,
+        213,
+        130
+      ],
+      "name": "portfolioClosed"
+    },
+    {
+      "discriminator": [
+        170,
+        57,
+        137,
+        212,
+        160,
+        177,
+        158,
+        108
+      ],
+      "name": "portfolioOpened"
+    },
+    {
+      "discriminator": [
+        58,
+        167,
+        138,
+        252,
+        208,
+        35,
+        124,
+        248
+      ],
+      "name": "portfolioPageClosed"
+    },
+    {
+      "discriminator": [
+        130,
+        247,
+        44,
+        136,
+        117,
+        157,
+        63,
+        58
+      ],
+      "name": "positionsCompressed"
+    },
+    {
+      "discriminator": [
+        148,
+        46,
+        187,
+        66,
+        35,
+        1,
+        255,
+        147
+      ],
+      "name": "resolved"
+    },
+    {
+      "discriminator": [
+        114,
+        223,
+        238,
+        93,
+        111,
+        243,
+        74,
+        148
+      ],
+      "name": "scheduledBetCancelled"
+    },
+    {
+      "discriminator": [
+        29,
+        246,
+        106,
+        175,
+        4,
+        150,
+        210,
+        53
+      ],
+      "name": "scheduledBetExecuted"
+    },
+    {
+      "discriminator": [
+        146,
+        119,
+        35,
+        16,
+        230,
+        180,
+        98,
+        67
+      ],
+      "name": "stakeCapsSet"
+    },
+    {
+      "discriminator": [
+        33,
+        120,
+        159,
+        58,
+        140,
+        255,
+        174,
+        79
+      ],
+      "name": "stakeWithdrawn"
+    },
+    {
+      "discriminator": [
+        11,
+        146,
+        45,
+        205,
+        230,
+        58,
+        213,
+        240
+      ],
+      "name": "staked"
+    },
+    {
+      "discriminator": [
+        21,
+        253,
+        177,
+        85,
+        129,
+        206,
+        42,
+        152
+      ],
+      "name": "unstakeRequested"
+    }
+  ],
+  "errors": [
+    {
+      "code": 6000,
+      "name": "feeTooHigh",
+      "msg": "Fee above the config's ceiling"
+    },
+    {
+      "code": 6001,
+      "name": "titleTooLong",
+      "msg": "Title too long"
+    },
+    {
+      "code": 6002,
+      "name": "endTimeInPast",
+      "msg": "End time must be in the future"
+    },
+    {
+      "code": 6003,
+      "name": "invalidDeadline",
+      "msg": "Resolve deadline must be after end time"
+    },
+    {
+      "code": 6004,
+      "name": "marketNotOpen",
+      "msg": "Market is not open for betting"
+    },
+    {
+      "code": 6005,
+      "name": "bettingClosed",
+      "msg": "Betting period has ended"
+    },
+    {
+      "code": 6006,
+      "name": "invalidAmount",
+      "msg": "Invalid bet amount"
+    },
+    {
+      "code": 6007,
+      "name": "overflow",
+      "msg": "Arithmetic overflow"
+    },
+    {
+      "code": 6008,
+      "name": "underflow",
+      "msg": "Arithmetic underflow"
+    },
+    {
+      "code": 6009,
+      "name": "bettingNotEnded",
+      "msg": "Betting period has not ended"
+    },
+    {
+      "code": 6010,
+      "name": "marketNotPendingResolve",
+      "msg": "Market is not pending resolution"
+    },
+    {
+      "code": 6011,
+      "name": "unauthorizedResolver",
+      "msg": "Unauthorized resolver"
+    },
+    {
+      "code": 6012,
+      "name": "resolutionDeadlinePassed",
+      "msg": "Resolution deadline has passed"
+    },
+    {
+      "code": 6013,
+      "name": "resolutionNotExpired",
+      "msg": "Resolution deadline has not been reached"
+    },
+    {
+      "code": 6014,
+      "name": "marketNotFinalized",
+      "msg": "Market is not finalized"
+    },
+    {
+      "code": 6015,
+      "name": "alreadyClaimed",
+      "msg": "Already claimed"
+    },
+    {
+      "code": 6016,
+      "name": "unauthorizedClaim",
+      "msg": "Unauthorized claim"
+    },
+    {
+      "code": 6017,
+      "name": "marketNotResolved",
+      "msg": "Market is not resolved"
+    },
+    {
+      "code": 6018,
+      "name": "unauthorizedWithdrawal",
+      "msg": "Unauthorized withdrawal"
+    },
+    {
+      "code": 6019,
+      "name": "feeAlreadyWithdrawn",
+      "msg": "Creator fee already withdrawn"
+    },
+    {
+      "code": 6020,
+      "name": "invalidMarketPda",
+      "msg": "Invalid market PDA"
+    },
+    {
+      "code": 6021,
+      "name": "haircutTooHigh",
+      "msg": "Cash-out haircut too high (max 50%)"
+    },
+    {
+      "code": 6022,
+      "name": "cashOutDisabled",
+      "msg": "Cash-out is not enabled for this market"
+    },
+    {
+      "code": 6023,
+      "name": "nothingToCashOut",
+      "msg": "Nothing to cash out"
+    },
+    {
+      "code": 6024,
+      "name": "oddsBelowMinimum",
+      "msg": "Odds moved below the requested minimum"
+    },
+    {
+      "code": 6025,
+      "name": "invalidRemainingAccounts",
+      "msg": "Remaining accounts must be non-empty groups of the expected size"
+    },
+    {
+      "code": 6026,
+      "name": "invalidPosition",
+      "msg": "Position does not belong to this market"
+    },
+    {
+      "code": 6027,
+      "name": "invalidRecipient",
+      "msg": "Recipient is not the position owner's associated token account"
+    },
+    {
+      "code": 6028,
+      "name": "invalidVault",
+      "msg": "Vault does not belong to this market"
+    },
+    {
+      "code": 6029,
+      "name": "invalidMint",
+      "msg": "Token account mint does not match the market"
+    },
+    {
+      "code": 6030,
+      "name": "marketAlreadyFinalized",
+      "msg": "Market is already resolved or cancelled"
+    },
+    {
+      "code": 6031,
+      "name": "marketHasStakes",
+      "msg": "Market already has stakes"
+    },
+    {
+      "code": 6032,
+      "name": "invalidDependency",
+      "msg": "Invalid parent market"
+    },
+    {
+      "code": 6033,
+      "name": "dependencyNotMet",
+      "msg": "Parent market has not resolved to the required outcome"
+    },
+    {
+      "code": 6034,
+      "name": "dependencyNotFailed",
+      "msg": "Parent market has not failed the dependency"
+    },
+    {
+      "code": 6035,
+      "name": "claimWindowOpen",
+      "msg": "Claim window has not ended"
+    },
+    {
+      "code": 6036,
+      "name": "outstandingBalance",
+      "msg": "Market still owes payouts or fees"
+    },
+    {
+      "code": 6037,
+      "name": "feeIsBurned",
+      "msg": "Market fee is burned, not withdrawn"
+    },
+    {
+      "code": 6038,
+      "name": "feeNotBurned",
+      "msg": "Market fee is not set to be burned"
+    },
+    {
+      "code": 6039,
+      "name": "invalidFeeTiers",
+      "msg": "Fee tiers must be ascending, at most 4, with discounts up to 100% and fee ceilings up to 20%"
+    },
+    {
+      "code": 6040,
+      "name": "invalidUnbondingPeriod",
+      "msg": "Unbonding period cannot be negative"
+    },
+    {
+      "code": 6041,
+      "name": "unauthorizedAdmin",
+      "msg": "Unauthorized admin"
+    },
+    {
+      "code": 6042,
+      "name": "invalidStakeAccount",
+      "msg": "Invalid stake account"
+    },
+    {
+      "code": 6043,
+      "name": "insufficientStake",
+      "msg": "Not enough stake"
+    },
+    {
+      "code": 6044,
+      "name": "stillUnbonding",
+      "msg": "Stake is still unbonding"
+    },
+    {
+      "code": 6045,
+      "name": "nothingToWithdraw",
+      "msg": "Nothing to withdraw"
+    },
+    {
+      "code": 6046,
+      "name": "marketCompressed",
+      "msg": "Market takes compressed bets only"
+    },
+    {
+      "code": 6047,
+      "name": "marketNotCompressed",
+      "msg": "Market does not have compressed positions"
+    },
+    {
+      "code": 6048,
+      "name": "compressionUnsupported",
+      "msg": "Compressed positions don't support cash-outs, bettor pages or activity stats"
+    },
+    {
+      "code": 6049,
+      "name": "positionTreeFull",
+      "msg": "Position tree is full"
+    },
+    {
+      "code": 6050,
+      "name": "invalidPositionProof",
+      "msg": "Position is not in the tree or was already claimed"
+    },
+    {
+      "code": 6051,
+      "name": "invalidBettorPage",
+      "msg": "Expected the market's current bettor page"
+    },
+    {
+      "code": 6052,
+      "name": "bettorPagesUnsupported",
+      "msg": "Bettor pages aren't supported on compressed markets or in batches"
+    },
+    {
+      "code": 6053,
+      "name": "bettorPagesOpen",
+      "msg": "Market still has bettor pages to close"
+    },
+    {
+      "code": 6054,
+      "name": "marketAlreadyCompact",
+      "msg": "Market is already compact"
+    },
+    {
+      "code": 6055,
+      "name": "invalidMarketPolicy",
+      "msg": "Market policy defaults must be within its ceilings, with positive windows and insurance up to 1%"
+    },
+    {
+      "code": 6056,
+      "name": "insuranceAlreadyCollected",
+      "msg": "Insurance already collected"
+    },
+    {
+      "code": 6057,
+      "name": "marketNotCancelled",
+      "msg": "Market has not been cancelled"
+    },
+    {
+      "code": 6058,
+      "name": "insufficientVaultBalance",
+      "msg": "Market vaults hold less than the payout"
+    },
+    {
+      "code": 6059,
+      "name": "invalidUpdateDelay",
+      "msg": "Config update delay cannot be negative"
+    },
+    {
+      "code": 6060,
+      "name": "noPendingConfigUpdate",
+      "msg": "No config update is pending"
+    },
+    {
+      "code": 6061,
+      "name": "configUpdateNotReady",
+      "msg": "Config update delay has not passed"
+    },
+    {
+      "code": 6062,
+      "name": "invalidFeeSplits",
+      "msg": "Fee splits must be at most 3, each above 0 and together at most 100%"
+    },
+    {
+      "code": 6063,
+      "name": "feeIsSplit",
+      "msg": "Fee is split; pay it out with distribute_fees"
+    },
+    {
+      "code": 6064,
+      "name": "invalidCharity",
+      "msg": "Charity needs a token account and a cut above 0 that leaves room for the fee and insurance"
+    },
+    {
+      "code": 6065,
+      "name": "charityAlreadyPaid",
+      "msg": "Charity already paid"
+    },
+    {
+      "code": 6066,
+      "name": "invalidActivityStats",
+      "msg": "Expected the market's activity account"
+    },
+    {
+      "code": 6067,
+      "name": "activityStatsUnsupported",
+      "msg": "Activity stats aren't supported on compressed markets or in batches"
+    },
+    {
+      "code": 6068,
+      "name": "activityStatsOpen",
+      "msg": "Market still has an activity account to close"
+    },
+    {
+      "code": 6069,
+      "name": "invalidStakeCaps",
+      "msg": "Stake ratio limit must be 0 or at least 10000 bps"
+    },
+    {
+      "code": 6070,
+      "name": "stakeCapExceeded",
+      "msg": "Bet would take its side past the market's stake cap"
+    },
+    {
+      "code": 6071,
+      "name": "invalidMarketTitle",
+      "msg": "Title registration must match the market's title, once per market"
+    },
+    {
+      "code": 6072,
+      "name": "marketTitleRegistered",
+      "msg": "Market still has a title registration to close"
+    },
+    {
+      "code": 6073,
+      "name": "invalidSchedule",
+      "msg": "Scheduled bets need an expiry in the future, after execute_after"
+    },
+    {
+      "code": 6074,
+      "name": "scheduledBetNotDue",
+      "msg": "Scheduled bet is not due"
+    },
+    {
+      "code": 6075,
+      "name": "scheduledBetActive",
+      "msg": "Only the owner can cancel a scheduled bet that can still be placed"
+    },
+    {
+      "code": 6076,
+      "name": "noCashOutOrder",
+      "msg": "Position has no cash-out order"
+    },
+    {
+      "code": 6077,
+      "name": "cashOutOrderNotTriggered",
+      "msg": "Position would still win more than the order's minimum"
+    },
+    {
+      "code": 6078,
+      "name": "invalidPortfolioPage",
+      "msg": "Portfolio page is not the portfolio's current page"
+    },
+    {
+      "code": 6079,
+      "name": "portfolioPageInUse",
+      "msg": "Only full portfolio pages can be closed"
+    },
+    {
+      "code": 6080,
+      "name": "portfolioPagesOpen",
+      "msg": "Portfolio still has pages to close"
+    },
+    {
+      "code": 6081,
+      "name": "positionSideMismatch",
+      "msg": "Position is on the other side; bet from another wallet or cash out first"
+    },
+    {
+      "code": 6082,
+      "name": "cashOutOrderTooSoon",
+      "msg": "Stakes moved this slot; the cash-out order can be filled from the next"
+    },
+    {
+      "code": 6083,
+      "name": "scheduledBetsPending",
+      "msg": "Market still has scheduled bets to place or cancel"
+    },
+    {
+      "code": 6084,
+      "name": "compensationTooHigh",
+      "msg": "Compensation would exceed the position's unrefunded stake"
+    }
+  ],
+  "types": [
+    {
+      "name": "activityBucket",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "start",
+            "docs": [
+              "Start of the hour the bucket holds."
+            ],
+            "type": "i64"
+          },
+          {
+            "name": "bets",
+            "type": "u32"
+          },
+          {
+            "name": "volume",
+            "type": "u64"
+          }
+        ]
+      }
+    },
+    {
+      "name": "activityStatsClosed",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "payer",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "activityStatsEnabled",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "docs": [
+        "A privileged action, as logged."
+      ],
+      "name": "adminAction",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "fields": [
+              {
+                "name": "config",
+                "type": {
+                  "defined": {
+                    "name": "configUpdate"
+                  }
+                }
+              }
+            ],
+            "name": "configInitialized"
+          },
+          {
+            "fields": [
+              {
+                "name": "update",
+                "type": {
+                  "defined": {
+                    "name": "configUpdate"
+                  }
+                }
+              },
+              {
+                "name": "readyTs",
+                "type": "i64"
+              }
+            ],
+            "name": "configUpdateProposed"
+          },
+          {
+            "fields": [
+              {
+                "name": "config",
+                "type": {
+                  "defined": {
+                    "name": "configUpdate"
+                  }
+                }
+              }
+            ],
+            "name": "configUpdateApplied"
+          },
+          {
+            "name": "configUpdateCancelled"
+          },
+          {
+            "fields": [
+              {
+                "name": "market",
+                "type": "pubkey"
+              },
+              {
+                "name": "recipient",
+                "type": "pubkey"
+              },
+              {
+                "name": "amount",
+                "type": "u64"
+              }
+            ],
+            "name": "compensated"
+          },
+          {
+            "fields": [
+              {
+                "name": "original",
+                "type": "pubkey"
+              },
+              {
+                "name": "market",
+                "type": "pubkey"
+              }
+            ],
+            "name": "marketForked"
+          }
+        ]
+      }
+    },
+    {
+      "docs": [
+        "Entry `sequence` of the admin log."
+      ],
+      "name": "adminActionLogged",
+      "type": {
+        "fields": [
+          {
+            "name": "sequence",
+            "type": "u64"
+          },
+          {
+            "name": "admin",
+            "type": "pubkey"
+          },
+          {
+            "name": "timestamp",
+            "type": "i64"
+          },
+          {
+            "name": "action",
+            "type": {
+              "defined": {
+                "name": "adminAction"
+              }
+            }
+          },
+          {
+            "docs": [
+              "The config's `admin_log_hash` after this entry."
+            ],
+            "name": "logHash",
+            "type": {
+              "array": [
+                "u8",
+                32
+              ]
+            }
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "batchBet",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "side",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "name": "minOddsBps",
+            "type": "u64"
+          }
+        ]
+      }
+    },
+    {
+      "name": "betPlaced",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "side",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "betReceipt",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "side",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "docs": [
+              "Locked payout per unit staked, in basis points (15_000 = 1.5x)."
+            ],
+            "name": "oddsBps",
+            "type": "u64"
+          },
+          {
+            "name": "lockedPayout",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "betScheduled",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "scheduledBet",
+            "type": "pubkey"
+          },
+          {
+            "name": "owner",
+            "type": "pubkey"
+          },
+          {
+            "name": "side",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "name": "executeAfter",
+            "type": "i64"
+          },
+          {
+            "name": "expiry",
+            "type": "i64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "betSide",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "name": "a"
+          },
+          {
+            "name": "b"
+          }
+        ]
+      }
+    },
+    {
+      "name": "bettingClosed",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "bettorEntry",
+      "docs": [
+        "A bet recorded in a bettor page."
+      ],
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "owner",
+            "type": "pubkey"
+          },
+          {
+            "name": "side",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ]
+      }
+    },
+    {
+      "name": "bettorPage",
+      "docs": [
+        "Up to [`BETTOR_PAGE_SIZE`] bets on a market that tracks bettors, in the",
+        "order they were placed. Page `index` holds bets `index * BETTOR_PAGE_SIZE`",
+        "onwards; a bettor who bets again appears again."
+      ],
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "index",
+            "type": "u32"
+          },
+          {
+            "name": "payer",
+            "docs": [
+              "Paid the page's rent, and gets it back when it's closed."
+            ],
+            "type": "pubkey"
+          },
+          {
+            "name": "bettors",
+            "type": {
+              "vec": {
+                "defined": {
+                  "name": "bettorEntry"
+                }
+              }
+            }
+          }
+        ]
+      }
+    },
+    {
+      "name": "bettorPageClosed",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "index",
+            "type": "u32"
+          },
+          {
+            "name": "payer",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "bettorPagesEnabled",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "cancelled",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "cashOutOrderExecuted",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "executor",
+            "type": "pubkey"
+          },
+          {
+            "name": "minValue",
+            "type": "u64"
+          },
+          {
+            "docs": [
+              "What the owner got, net of the tip."
+            ],
+            "name": "payout",
+            "type": "u64"
+          },
+          {
+            "name": "tip",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "cashOutOrderSet",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "docs": [
+              "Zero when the order was cleared."
+            ],
+            "name": "minValue",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "cashedOut",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "side",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          },
+          {
+            "name": "stake",
+            "type": "u64"
+          },
+          {
+            "name": "payout",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "changeLog",
+      "serialization": "bytemuck",
+      "repr": {
+        "kind": "c"
+      },
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "root",
+            "type": {
+              "array": [
+                "u8",
+                32
+              ]
+            }
+          },
+          {
+            "name": "path",
+            "docs": [
+              "Nodes on the changed leaf's path, from the leaf up."
+            ],
+            "type": {
+              "array": [
+                {
+                  "array": [
+                    "u8",
+                    32
+                  ]
+                },
+                20
+              ]
+            }
+          },
+          {
+            "name": "index",
+            "type": "u32"
+          },
+          {
+            "name": "padding",
+            "type": "u32"
+          }
+        ]
+      }
+    },
+    {
+      "name": "charityDonated",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "docs": [
+              "The charity's token account."
+            ],
+            "name": "charity",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "charitySet",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "charity",
+            "type": {
+              "option": "pubkey"
+            }
+          },
+          {
+            "name": "charityBps",
+            "type": "u16"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "claimed",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "compensated",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "recipient",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "docs": [
+        "A bet on a compressed market, with everything needed to rebuild its leaf."
+      ],
+      "name": "compressedBetPlaced",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "side",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "name": "lockedPayout",
+            "type": "u64"
+          },
+          {
+            "name": "discountedStake",
+            "type": "u64"
+          },
+          {
+            "name": "leafIndex",
+            "type": "u32"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "compressedPositionClaimed",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "leafIndex",
+            "type": "u32"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "config",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "admin",
+            "type": "pubkey"
+          },
+          {
+            "name": "stakeMint",
+            "type": "pubkey"
+          },
+          {
+            "name": "stakeVault",
+            "type": "pubkey"
+          },
+          {
+            "name": "unbondingSecs",
+            "type": "i64"
+          },
+          {
+            "name": "feeTiers",
+            "docs": [
+              "Ascending by `min_stake`; the highest tier reached applies."
+            ],
+            "type": {
+              "vec": {
+                "defined": {
+                  "name": "feeTier"
+                }
+              }
+            }
+          },
+          {
+            "name": "bump",
+            "type": "u8"
+          },
+          {
+            "name": "stakeVaultBump",
+            "type": "u8"
+          },
+          {
+            "name": "policy",
+            "type": {
+              "defined": {
+                "name": "marketPolicy"
+              }
+            }
+          },
+          {
+            "name": "updateDelaySecs",
+            "docs": [
+              "How long a proposed update waits before it can be applied."
+            ],
+            "type": "i64"
+          },
+          {
+            "name": "pendingUpdate",
+            "type": {
+              "option": {
+                "defined": {
+                  "name": "configUpdate"
+                }
+              }
+            }
+          },
+          {
+            "name": "updateReadyTs",
+            "docs": [
+              "When `pending_update` can be applied."
+            ],
+            "type": "i64"
+          },
+          {
+            "name": "adminActions",
+            "docs": [
+              "Privileged actions taken so far, numbered from 0."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "adminLogHash",
+            "docs": [
+              "Hash of the admin log up to the latest action, see [`admin_log`]."
+            ],
+            "type": {
+              "array": [
+                "u8",
+                32
+              ]
+            }
+          }
+        ]
+      }
+    },
+    {
+      "name": "configUpdate",
+      "docs": [
+        "Everything `propose_config_update` can change."
+      ],
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "admin",
+            "type": "pubkey"
+          },
+          {
+            "name": "unbondingSecs",
+            "type": "i64"
+          },
+          {
+            "name": "feeTiers",
+            "type": {
+              "vec": {
+                "defined": {
+                  "name": "feeTier"
+                }
+              }
+            }
+          },
+          {
+            "name": "policy",
+            "type": {
+              "defined": {
+                "name": "marketPolicy"
+              }
+            }
+          },
+          {
+            "name": "updateDelaySecs",
+            "type": "i64"
+          }
+        ]
+      }
+    },
+    {
+      "name": "configUpdateCancelled",
+      "type": {
+        "fields": [
+          {
+            "name": "admin",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "configUpdateProposed",
+      "type": {
+        "fields": [
+          {
+            "name": "admin",
+            "type": "pubkey"
+          },
+          {
+            "name": "update",
+            "type": {
+              "defined": {
+                "name": "configUpdate"
+              }
+            }
+          },
+          {
+            "name": "readyTs",
+            "type": "i64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "configUpdated",
+      "type": {
+        "fields": [
+          {
+            "name": "admin",
+            "type": "pubkey"
+          },
+          {
+            "name": "unbondingSecs",
+            "type": "i64"
+          },
+          {
+            "name": "feeTiers",
+            "type": {
+              "vec": {
+                "defined": {
+                  "name": "feeTier"
+                }
+              }
+            }
+          },
+          {
+            "name": "policy",
+            "type": {
+              "defined": {
+                "name": "marketPolicy"
+              }
+            }
+          },
+          {
+            "name": "updateDelaySecs",
+            "type": "i64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "creatorFeeWithdrawn",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "creator",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "dependencySet",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "parent",
+            "type": "pubkey"
+          },
+          {
+            "name": "outcome",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "feeBurned",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "mint",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "feeRebated",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "discountedStake",
+            "type": "u64"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "feeSplit",
+      "docs": [
+        "A recipient's share of a market's fee."
+      ],
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "recipient",
+            "type": "pubkey"
+          },
+          {
+            "name": "bps",
+            "type": "u16"
+          }
+        ]
+      }
+    },
+    {
+      "name": "feeSplitPaid",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "recipient",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "feeSplitsSet",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "splits",
+            "type": {
+              "vec": {
+                "defined": {
+                  "name": "feeSplit"
+                }
+              }
+            }
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "feeTier",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "minStake",
+            "type": "u64"
+          },
+          {
+            "name": "discountBps",
+            "type": "u16"
+          },
+          {
+            "name": "maxFeeBps",
+            "docs": [
+              "Fee ceiling for markets created by stakers in this tier, where it's",
+              "above the policy's."
+            ],
+            "type": "u16"
+          }
+        ]
+      }
+    },
+    {
+      "name": "insuranceCollected",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "mint",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "docs": [
+        "A bet of at least its market's `large_bet_threshold`, with the pools",
+        "it left behind."
+      ],
+      "name": "largeBetPlaced",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "side",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "name": "stakedA",
+            "type": "u64"
+          },
+          {
+            "name": "stakedB",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "largeBetThresholdSet",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "docs": [
+              "0 when large bets are no longer reported."
+            ],
+            "name": "threshold",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "market",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "marketId",
+            "type": "u64"
+          },
+          {
+            "name": "creator",
+            "type": "pubkey"
+          },
+          {
+            "name": "mint",
+            "type": "pubkey"
+          },
+          {
+            "name": "vaultA",
+            "docs": [
+              "Holds the stakes on side A."
+            ],
+            "type": "pubkey"
+          },
+          {
+            "name": "vaultB",
+            "docs": [
+              "Holds the stakes on side B."
+            ],
+            "type": "pubkey"
+          },
+          {
+            "name": "feeBps",
+            "type": "u16"
+          },
+          {
+            "name": "endTs",
+            "type": "i64"
+          },
+          {
+            "name": "resolveDeadlineTs",
+            "type": "i64"
+          },
+          {
+            "name": "stakedA",
+            "type": "u64"
+          },
+          {
+            "name": "stakedB",
+            "type": "u64"
+          },
+          {
+            "name": "status",
+            "type": {
+              "defined": {
+                "name": "marketStatus"
+              }
+            }
+          },
+          {
+            "name": "outcome",
+            "type": {
+              "option": {
+                "defined": {
+                  "name": "betSide"
+                }
+              }
+            }
+          },
+          {
+            "name": "creatorFeeWithdrawn",
+            "type": "bool"
+          },
+          {
+            "name": "bump",
+            "type": "u8"
+          },
+          {
+            "name": "vaultABump",
+            "type": "u8"
+          },
+          {
+            "name": "vaultBBump",
+            "type": "u8"
+          },
+          {
+            "name": "title",
+            "type": "string"
+          },
+          {
+            "name": "allowCashOut",
+            "type": "bool"
+          },
+          {
+            "name": "cashoutHaircutBps",
+            "type": "u16"
+          },
+          {
+            "name": "retained",
+            "docs": [
+              "Stake left behind by cash-outs, paid to winners with the rest of the",
+              "pool, or shared by stake between the remaining positions if the",
+              "market is cancelled."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "lockOdds",
+            "type": "bool"
+          },
+          {
+            "name": "lockedPayoutA",
+            "docs": [
+              "Sum of payouts locked in by bets on each side when `lock_odds` is set."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "lockedPayoutB",
+            "type": "u64"
+          },
+          {
+            "name": "forkedFrom",
+            "docs": [
+              "Market this one replaced via `fork_market`, if any."
+            ],
+            "type": {
+              "option": "pubkey"
+            }
+          },
+          {
+            "name": "dependency",
+            "docs": [
+              "Parent outcome this market is conditional on, if any."
+            ],
+            "type": {
+              "option": {
+                "defined": {
+                  "name": "marketDependency"
+                }
+              }
+            }
+          },
+          {
+            "name": "openPositions",
+            "docs": [
+              "Positions holding stake that haven't been claimed or cashed out."
+            ],
+            "type": "u32"
+          },
+          {
+            "name": "burnFee",
+            "docs": [
+              "Fee is burned via `burn_fee` instead of paid to the creator."
+            ],
+            "type": "bool"
+          },
+          {
+            "name": "feeDiscounts",
+            "docs": [
+              "Fee handed back to stakers so far, out of [`Market::staker_rebates`]."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "discountedStake",
+            "docs": [
+              "Sum of the positions' `discounted_stake`, fixing how much of the fee",
+              "goes back to stakers instead of the creator."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "compressed",
+            "docs": [
+              "Bets are leaves in the market's `PositionTree` instead of `Position` accounts."
+            ],
+            "type": "bool"
+          },
+          {
+            "name": "trackBettors",
+            "docs": [
+              "`place_bet` records every bet in the market's `BettorPage`s."
+            ],
+            "type": "bool"
+          },
+          {
+            "name": "bettorCount",
+            "docs": [
+              "Bets recorded in bettor pages so far."
+            ],
+            "type": "u32"
+          },
+          {
+            "name": "bettorPages",
+            "docs": [
+              "Bettor pages created and not yet closed."
+            ],
+            "type": "u32"
+          },
+          {
+            "name": "insuranceBps",
+            "docs": [
+              "Cut of the pool that goes to the insurance vault once resolved."
+            ],
+            "type": "u16"
+          },
+          {
+            "name": "insuranceCollected",
+            "type": "bool"
+          },
+          {
+            "name": "compensated",
+            "docs": [
+              "Paid out of insurance to this market's bettors after it was voided."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "charity",
+            "docs": [
+              "Token account given `charity_bps` of the pool once resolved, if any."
+            ],
+            "type": {
+              "option": "pubkey"
+            }
+          },
+          {
+            "name": "charityBps",
+            "type": "u16"
+          },
+          {
+            "name": "charityPaid",
+            "type": "bool"
+          },
+          {
+            "name": "largeBetThreshold",
+            "docs": [
+              "Bets of at least this much also emit `LargeBetPlaced`; 0 for none."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "trackActivity",
+            "docs": [
+              "`place_bet` keeps the market's `MarketActivity` account."
+            ],
+            "type": "bool"
+          },
+          {
+            "name": "capA",
+            "docs": [
+              "Most that can be staked on each side; 0 for no cap."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "capB",
+            "type": "u64"
+          },
+          {
+            "name": "maxRatioBps",
+            "docs": [
+              "Most a side can hold relative to the other once both have stake, in",
+              "basis points (20000 for 2x); 0 for no limit."
+            ],
+            "type": "u32"
+          },
+          {
+            "name": "titleRegistered",
+            "docs": [
+              "The market holds a `MarketTitle` registration."
+            ],
+            "type": "bool"
+          },
+          {
+            "name": "feeSplits",
+            "docs": [
+              "Recipients sharing the fee with the creator, paid by `distribute_fees`."
+            ],
+            "type": {
+              "vec": {
+                "defined": {
+                  "name": "feeSplit"
+                }
+              }
+            }
+          },
+          {
+            "name": "poolsChangedSlot",
+            "docs": [
+              "Slot the stakes last changed in; cash-out orders fill in a later one."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "scheduledBets",
+            "docs": [
+              "Scheduled bets still holding stake in escrow for this market; it",
+              "can't be collected while any are left."
+            ],
+            "type": "u32"
+          }
+        ]
+      }
+    },
+    {
+      "name": "marketActivity",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "payer",
+            "docs": [
+              "Paid the account's rent, and gets it back when it's closed."
+            ],
+            "type": "pubkey"
+          },
+          {
+            "name": "bettors",
+            "docs": [
+              "Bets that opened a position since activity was enabled; a bettor",
+              "who cashed out and bet again counts again."
+            ],
+            "type": "u32"
+          },
+          {
+            "name": "lastBetTs",
+            "type": "i64"
+          },
+          {
+            "name": "buckets",
+            "type": {
+              "array": [
+                {
+                  "defined": {
+                    "name": "activityBucket"
+                  }
+                },
+                24
+              ]
+            }
+          }
+        ]
+      }
+    },
+    {
+      "name": "marketCloned",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "source",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "marketCollected",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "caller",
+            "type": "pubkey"
+          },
+          {
+            "name": "positionsClosed",
+            "type": "u32"
+          },
+          {
+            "name": "callerShare",
+            "type": "u64"
+          },
+          {
+            "name": "creatorShare",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "marketCompacted",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "docs": [
+              "Lamports returned to the creator."
+            ],
+            "name": "refund",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "marketDependency",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "outcome",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          }
+        ]
+      }
+    },
+    {
+      "name": "marketForked",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "original",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "marketInitialized",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "creator",
+            "type": "pubkey"
+          },
+          {
+            "name": "title",
+            "type": "string"
+          },
+          {
+            "name": "feeBps",
+            "type": "u16"
+          },
+          {
+            "name": "endTs",
+            "type": "i64"
+          },
+          {
+            "name": "resolveDeadlineTs",
+            "type": "i64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "marketOptions",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "allowCashOut",
+            "type": "bool"
+          },
+          {
+            "name": "cashoutHaircutBps",
+            "type": "u16"
+          },
+          {
+            "name": "lockOdds",
+            "type": "bool"
+          },
+          {
+            "name": "burnFee",
+            "type": "bool"
+          }
+        ]
+      }
+    },
+    {
+      "name": "marketPolicy",
+      "docs": [
+        "Defaults and ceilings `initialize_market` applies, set by the config",
+        "admin."
+      ],
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "defaultFeeBps",
+            "docs": [
+              "Fee of markets created with `fee_bps` of [`DEFAULT_FEE`]."
+            ],
+            "type": "u16"
+          },
+          {
+            "name": "maxFeeBps",
+            "type": "u16"
+          },
+          {
+            "name": "defaultBettingSecs",
+            "docs": [
+              "Betting window of markets created with an `end_ts` of 0."
+            ],
+            "type": "i64"
+          },
+          {
+            "name": "defaultResolveSecs",
+            "docs": [
+              "Resolve window of markets created with a `resolve_deadline_ts` of 0."
+            ],
+            "type": "i64"
+          },
+          {
+            "name": "maxTitleLen",
+            "docs": [
+              "In bytes; at most the 64 a market account has room for."
+            ],
+            "type": "u16"
+          },
+          {
+            "name": "insuranceBps",
+            "docs": [
+              "Cut of every market's pool set aside for insurance, up to 1%."
+            ],
+            "type": "u16"
+          }
+        ]
+      }
+    },
+    {
+      "name": "marketStatus",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "name": "open"
+          },
+          {
+            "name": "pendingResolve"
+          },
+          {
+            "name": "resolved"
+          },
+          {
+            "name": "cancelled"
+          }
+        ]
+      }
+    },
+    {
+      "name": "marketTitle",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "titleHash",
+            "type": {
+              "array": [
+                "u8",
+                32
+              ]
+            }
+          },
+          {
+            "name": "nonce",
+            "type": "u16"
+          }
+        ]
+      }
     },
     {
-      "name": "claim",
-      "discriminator": number[],
-      "accounts": any[],
-      "args": []
+      "name": "marketTitleClosed",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "titleHash",
+            "type": {
+              "array": [
+                "u8",
+                32
+              ]
+            }
+          },
+          {
+            "name": "nonce",
+            "type": "u16"
+          }
+        ],
+        "kind": "struct"
+      }
     },
     {
-      "name": "closeBetting",
-      "discriminator": number[],
-      "accounts": any[],
-      "args": []
+      "name": "marketTitleRegistered",
+      "type": {
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "titleHash",
+            "type": {
+              "array": [
+                "u8",
+                32
+              ]
+            }
+          },
+          {
+            "name": "nonce",
+            "type": "u16"
+          }
+        ],
+        "kind": "struct"
+      }
     },
     {
-      "name": "initializeMarket",
-      "discriminator": number[],
-      "accounts": any[],
-      "args": any[]
+      "name": "portfolio",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "owner",
+            "type": "pubkey"
+          },
+          {
+            "name": "entryCount",
+            "docs": [
+              "Entries appended since the portfolio was opened."
+            ],
+            "type": "u32"
+          },
+          {
+            "name": "pages",
+            "docs": [
+              "Pages still open."
+            ],
+            "type": "u32"
+          },
+          {
+            "name": "bump",
+            "type": "u8"
+          }
+        ]
+      }
     },
     {
-      "name": "placeBet",
-      "discriminator": number[],
-      "accounts": any[],
-      "args": any[]
+      "name": "portfolioAction",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          {
+            "name": "bet"
+          },
+          {
+            "name": "claim"
+          }
+        ]
+      }
     },
     {
-      "name": "resolve",
-      "discriminator": number[],
-      "accounts": any[],
-      "args": any[]
+      "name": "portfolioClosed",
+      "type": {
+        "fields": [
+          {
+            "name": "owner",
+            "type": "pubkey"
+          },
+          {
+            "name": "portfolio",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
+      }
     },
     {
-      "name": "withdrawCreatorFee",
-      "discriminator": number[],
-      "accounts": any[],
-      "args": []
-    }
-  ],
-  "accounts": [
-    {
-      "name": "Market",
-      "discriminator": number[]
+      "name": "portfolioEntry",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "action",
+            "type": {
+              "defined": {
+                "name": "portfolioAction"
+              }
+            }
+          },
+          {
+            "name": "side",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          },
+          {
+            "name": "timestamp",
+            "type": "i64"
+          }
+        ]
+      }
     },
     {
-      "name": "Position",
-      "discriminator": number[]
-    }
-  ],
-  "events": any[],
-  "errors": any[],
-  "types": [
+      "name": "portfolioOpened",
+      "type": {
+        "fields": [
+          {
+            "name": "owner",
+            "type": "pubkey"
+          },
+          {
+            "name": "portfolio",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
     {
-      "name": "BetPlaced",
+      "name": "portfolioPage",
       "type": {
         "kind": "struct",
-        "fields": any[]
+        "fields": [
+          {
+            "name": "owner",
+            "type": "pubkey"
+          },
+          {
+            "name": "index",
+            "type": "u32"
+          },
+          {
+            "name": "entries",
+            "type": {
+              "vec": {
+                "defined": {
+                  "name": "portfolioEntry"
+                }
+              }
+            }
+          }
+        ]
       }
     },
     {
-      "name": "BetSide",
+      "name": "portfolioPageClosed",
       "type": {
-        "kind": "enum",
-        "variants": [
+        "fields": [
           {
-            "name": "A"
+            "name": "owner",
+            "type": "pubkey"
           },
           {
-            "name": "B"
+            "name": "index",
+            "type": "u32"
           }
-        ]
+        ],
+        "kind": "struct"
       }
     },
     {
-      "name": "BettingClosed",
+      "name": "position",
       "type": {
         "kind": "struct",
-        "fields": any[]
+        "fields": [
+          {
+            "name": "owner",
+            "type": "pubkey"
+          },
+          {
+            "name": "side",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "name": "claimed",
+            "type": "bool"
+          },
+          {
+            "name": "bump",
+            "type": "u8"
+          },
+          {
+            "name": "lockedPayout",
+            "docs": [
+              "Payout locked in across deposits on a `lock_odds` market."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "discountedStake",
+            "docs": [
+              "Stake placed at a staker's fee discount, each deposit weighted by",
+              "the discount its bettor had then; the position's rebate is this",
+              "stake's pro-rata share of the fee."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "cashoutMinValue",
+            "docs": [
+              "Standing cash-out order: anyone may cash the position out once what",
+              "it would win falls to this. Zero when there is none."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "compensated",
+            "docs": [
+              "Paid from insurance by `compensate` in place of a refund the vault",
+              "couldn't cover, and deducted from the claim."
+            ],
+            "type": "u64"
+          }
+        ]
       }
     },
     {
-      "name": "Cancelled",
+      "name": "positionTree",
+      "serialization": "bytemuck",
+      "repr": {
+        "kind": "c"
+      },
       "type": {
         "kind": "struct",
-        "fields": any[]
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "leafCount",
+            "docs": [
+              "Bets appended so far, and so the index of the next."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "sequenceNumber",
+            "docs": [
+              "Changes made so far, appends and claims alike."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "activeIndex",
+            "docs": [
+              "Entry in `change_logs` holding the latest change."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "bufferSize",
+            "docs": [
+              "Entries in `change_logs` filled so far."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "changeLogs",
+            "type": {
+              "array": [
+                {
+                  "defined": {
+                    "name": "changeLog"
+                  }
+                },
+                8
+              ]
+            }
+          },
+          {
+            "name": "nextProof",
+            "docs": [
+              "Siblings of the next leaf to append, so appending takes no proof."
+            ],
+            "type": {
+              "array": [
+                {
+                  "array": [
+                    "u8",
+                    32
+                  ]
+                },
+                20
+              ]
+            }
+          }
+        ]
       }
     },
     {
-      "name": "Claimed",
+      "name": "positionsCompressed",
       "type": {
-        "kind": "struct",
-        "fields": any[]
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "positionTree",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
       }
     },
     {
-      "name": "CreatorFeeWithdrawn",
+      "name": "resolved",
       "type": {
-        "kind": "struct",
-        "fields": any[]
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "outcome",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          }
+        ],
+        "kind": "struct"
       }
     },
     {
-      "name": "Market",
+      "name": "scheduledBet",
       "type": {
         "kind": "struct",
-        "fields": any[]
+        "fields": [
+          {
+            "name": "owner",
+            "type": "pubkey"
+          },
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "id",
+            "type": "u64"
+          },
+          {
+            "name": "side",
+            "type": {
+              "defined": {
+                "name": "betSide"
+              }
+            }
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "name": "minOddsBps",
+            "docs": [
+              "Passed on to the bet as its slippage bound, checked when it's placed."
+            ],
+            "type": "u64"
+          },
+          {
+            "name": "executeAfter",
+            "type": "i64"
+          },
+          {
+            "name": "expiry",
+            "type": "i64"
+          },
+          {
+            "name": "bump",
+            "type": "u8"
+          },
+          {
+            "name": "escrowBump",
+            "type": "u8"
+          }
+        ]
       }
     },
     {
-      "name": "MarketInitialized",
+      "name": "scheduledBetCancelled",
       "type": {
-        "kind": "struct",
-        "fields": any[]
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "scheduledBet",
+            "type": "pubkey"
+          },
+          {
+            "name": "owner",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
       }
     },
     {
-      "name": "MarketStatus",
+      "name": "scheduledBetExecuted",
       "type": {
-        "kind": "enum",
-        "variants": any[]
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "scheduledBet",
+            "type": "pubkey"
+          },
+          {
+            "name": "owner",
+            "type": "pubkey"
+          },
+          {
+            "name": "executor",
+            "type": "pubkey"
+          }
+        ],
+        "kind": "struct"
       }
     },
     {
-      "name": "Position",
+      "name": "stakeAccount",
       "type": {
         "kind": "struct",
-        "fields": any[]
+        "fields": [
+          {
+            "name": "owner",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "name": "unbondingAmount",
+            "type": "u64"
+          },
+          {
+            "name": "unbondingEndTs",
+            "type": "i64"
+          },
+          {
+            "name": "bump",
+            "type": "u8"
+          }
+        ]
       }
     },
     {
-      "name": "Resolved",
+      "name": "stakeCapsSet",
       "type": {
-        "kind": "struct",
-        "fields": any[]
+        "fields": [
+          {
+            "name": "market",
+            "type": "pubkey"
+          },
+          {
+            "name": "capA",
+            "type": "u64"
+          },
+          {
+            "name": "capB",
+            "type": "u64"
+          },
+          {
+            "name": "maxRatioBps",
+            "type": "u32"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "stakeWithdrawn",
+      "type": {
+        "fields": [
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "staked",
+      "type": {
+        "fields": [
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "name": "total",
+            "type": "u64"
+          }
+        ],
+        "kind": "struct"
+      }
+    },
+    {
+      "name": "unstakeRequested",
+      "type": {
+        "fields": [
+          {
+            "name": "user",
+            "type": "pubkey"
+          },
+          {
+            "name": "amount",
+            "type": "u64"
+          },
+          {
+            "name": "unbondingEndTs",
+            "type": "i64"
+          }
+        ],
+        "kind": "struct"
       }
     }
+  ],
+  "constants": [
+    {
+      "name": "activitySeed",
+      "type": "bytes",
+      "value": "[97, 99, 116, 105, 118, 105, 116, 121]"
+    },
+    {
+      "name": "bettorPageSeed",
+      "type": "bytes",
+      "value": "[98, 101, 116, 116, 111, 114, 95, 112, 97, 103, 101]"
+    },
+    {
+      "name": "cashoutOrderTipBps",
+      "docs": [
+        "Share of a cash-out order's payout that goes to whoever executes it."
+      ],
+      "type": "u16",
+      "value": "20"
+    },
+    {
+      "name": "configSeed",
+      "type": "bytes",
+      "value": "[99, 111, 110, 102, 105, 103]"
+    },
+    {
+      "name": "defaultFee",
+      "docs": [
+        "Pass as `fee_bps` to take the config's default fee."
+      ],
+      "type": "u16",
+      "value": "65535"
+    },
+    {
+      "name": "insuranceVaultSeed",
+      "type": "bytes",
+      "value": "[105, 110, 115, 117, 114, 97, 110, 99, 101, 95, 118, 97, 117, 108, 116]"
+    },
+    {
+      "name": "marketSeed",
+      "type": "bytes",
+      "value": "[109, 97, 114, 107, 101, 116]"
+    },
+    {
+      "name": "marketTitleSeed",
+      "type": "bytes",
+      "value": "[109, 97, 114, 107, 101, 116, 95, 116, 105, 116, 108, 101]"
+    },
+    {
+      "name": "portfolioPageSeed",
+      "type": "bytes",
+      "value": "[112, 111, 114, 116, 102, 111, 108, 105, 111, 95, 112, 97, 103, 101]"
+    },
+    {
+      "name": "portfolioSeed",
+      "type": "bytes",
+      "value": "[112, 111, 114, 116, 102, 111, 108, 105, 111]"
+    },
+    {
+      "name": "positionSeed",
+      "type": "bytes",
+      "value": "[112, 111, 115, 105, 116, 105, 111, 110]"
+    },
+    {
+      "name": "positionTreeSeed",
+      "type": "bytes",
+      "value": "[112, 111, 115, 105, 116, 105, 111, 110, 95, 116, 114, 101, 101]"
+    },
+    {
+      "name": "scheduledBetSeed",
+      "type": "bytes",
+      "value": "[115, 99, 104, 101, 100, 117, 108, 101, 100, 95, 98, 101, 116]"
+    },
+    {
+      "name": "scheduledEscrowSeed",
+      "type": "bytes",
+      "value": "[115, 99, 104, 101, 100, 117, 108, 101, 100, 95, 101, 115, 99, 114, 111, 119]"
+    },
+    {
+      "name": "stakeSeed",
+      "type": "bytes",
+      "value": "[115, 116, 97, 107, 101]"
+    },
+    {
+      "name": "stakeVaultSeed",
+      "type": "bytes",
+      "value": "[115, 116, 97, 107, 101, 95, 118, 97, 117, 108, 116]"
+    },
+    {
+      "name": "vaultSeed",
+      "type": "bytes",
+      "value": "[118, 97, 117, 108, 116]"
+    }
   ]
-};
\ No newline at end of file
+};
@@ -0,0 +1,16 @@
+//! Account, instruction, event and type definitions for the `friends_bets`
+//! program, generated from the IDL in `idls/` by `declare_program!`.
+//!
+//! Unlike the program crate this pulls in neither the program logic nor
+//! `anchor-spl`, so off-chain services can track a released interface
+//! without building the program. Refresh the IDL from the program with
+//! `anchor idl build` when the interface changes.
+
+// The generated CPI wrappers take an instruction's arguments one by one.
+#![allow(clippy::too_many_arguments)]
+
+use anchor_lang::prelude::*;
+
+declare_program!(friends_bets);
+
+pub use friends_bets::*;
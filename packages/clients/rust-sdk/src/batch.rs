@@ -0,0 +1,240 @@
+//! Batches spanning many markets, such as claiming a whole season at once.
+//!
+//! [`plan_batch`] packs instructions into as few transactions as fit the
+//! packet size limit. When the accounts don't fit legacy transactions, it
+//! reuses the lookup tables it's given and, if that isn't enough, plans a new
+//! table holding the batch's remaining accounts.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use friends_bets::BatchBet;
+use solana_address_lookup_table_interface::instruction::{
+    create_lookup_table, extend_lookup_table,
+};
+use solana_message::{v0, AddressLookupTableAccount, Message, VersionedMessage};
+
+use crate::instructions;
+
+/// Largest serialized transaction the network accepts.
+pub const MAX_TRANSACTION_SIZE: usize = 1232;
+
+/// Addresses per `extend_lookup_table`, keeping each extension within one
+/// transaction.
+const ADDRESSES_PER_EXTEND: usize = 30;
+const MAX_LOOKUP_TABLE_ADDRESSES: usize = 256;
+const SIGNATURE_SIZE: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BatchError {
+    #[error("instruction {0} doesn't fit in a transaction on its own")]
+    InstructionTooLarge(usize),
+}
+
+/// One `claim` per `(market, mint)` pair.
+pub fn claim_instructions(user: &Pubkey, markets: &[(Pubkey, Pubkey)]) -> Vec<Instruction> {
+    markets
+        .iter()
+        .map(|(market, mint)| instructions::claim(user, market, mint))
+        .collect()
+}
+
+/// `place_bets_batch` instructions carrying at most `bets_per_instruction`
+/// bets each.
+pub fn bet_instructions(
+    user: &Pubkey,
+    bets: &[(BatchBet, Pubkey)],
+    bets_per_instruction: usize,
+) -> Vec<Instruction> {
+    bets.chunks(bets_per_instruction.max(1))
+        .map(|chunk| instructions::place_bets_batch(user, chunk))
+        .collect()
+}
+
+/// Transactions for a batch, in the order they have to land.
+#[derive(Clone, Debug, Default)]
+pub struct BatchPlan {
+    /// Creates and extends new lookup tables. These must confirm, and the
+    /// tables be one slot old, before `transactions` are sent.
+    pub setup: Vec<Vec<Instruction>>,
+    /// Tables `transactions` are compiled against, existing and new. Empty
+    /// when legacy transactions suffice.
+    pub lookup_tables: Vec<AddressLookupTableAccount>,
+    pub transactions: Vec<Vec<Instruction>>,
+}
+
+impl BatchPlan {
+    /// Compiles one of `transactions` into a message, versioned if the plan
+    /// uses lookup tables.
+    pub fn message(
+        &self,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+        recent_blockhash: Hash,
+    ) -> VersionedMessage {
+        compile(payer, instructions, &self.lookup_tables, recent_blockhash)
+            .expect("planned transactions compile")
+    }
+}
+
+/// Packs `instructions` into transactions paid for by `payer`, at most
+/// `max_per_transaction` to a transaction so each stays within its compute
+/// budget.
+///
+/// `existing` tables are used whenever they cover any of the batch's
+/// accounts. A new table, owned by `payer` and derived from `recent_slot`,
+/// is only planned when the batch doesn't fit a single transaction and the
+/// table cuts the number of transactions needed.
+pub fn plan_batch(
+    payer: &Pubkey,
+    instructions: Vec<Instruction>,
+    existing: &[AddressLookupTableAccount],
+    recent_slot: u64,
+    max_per_transaction: usize,
+) -> Result<BatchPlan, BatchError> {
+    let limit = max_per_transaction.max(1);
+    let candidates = table_candidates(payer, &instructions);
+    let mut lookup_tables: Vec<AddressLookupTableAccount> = existing
+        .iter()
+        .filter(|table| table.addresses.iter().any(|a| candidates.contains(a)))
+        .cloned()
+        .collect();
+
+    let current = pack(payer, &instructions, &lookup_tables, limit);
+    if matches!(&current, Ok(txs) if txs.len() <= 1) {
+        return Ok(BatchPlan {
+            lookup_tables,
+            transactions: current?,
+            ..Default::default()
+        });
+    }
+
+    let uncovered: Vec<Pubkey> = candidates
+        .into_iter()
+        .filter(|address| !lookup_tables.iter().any(|t| t.addresses.contains(address)))
+        .collect();
+    let mut setup = Vec::new();
+    let mut with_new = lookup_tables.clone();
+    for (i, addresses) in uncovered.chunks(MAX_LOOKUP_TABLE_ADDRESSES).enumerate() {
+        let (create, key) = create_lookup_table(*payer, *payer, recent_slot + i as u64);
+        setup.push(vec![create]);
+        for chunk in addresses.chunks(ADDRESSES_PER_EXTEND) {
+            setup.push(vec![extend_lookup_table(
+                key,
+                *payer,
+                Some(*payer),
+                chunk.to_vec(),
+            )]);
+        }
+        with_new.push(AddressLookupTableAccount {
+            key,
+            addresses: addresses.to_vec(),
+        });
+    }
+
+    let improved = pack(payer, &instructions, &with_new, limit)?;
+    match current {
+        Ok(transactions) if setup.is_empty() || transactions.len() <= improved.len() => {
+            Ok(BatchPlan {
+                lookup_tables,
+                transactions,
+                ..Default::default()
+            })
+        }
+        _ => {
+            lookup_tables = with_new;
+            Ok(BatchPlan {
+                setup,
+                lookup_tables,
+                transactions: improved,
+            })
+        }
+    }
+}
+
+/// Accounts a lookup table can stand in for: everything but the payer,
+/// signers and invoked programs, which must be static keys.
+fn table_candidates(payer: &Pubkey, instructions: &[Instruction]) -> Vec<Pubkey> {
+    let programs: Vec<Pubkey> = instructions.iter().map(|ix| ix.program_id).collect();
+    let signers: Vec<Pubkey> = instructions
+        .iter()
+        .flat_map(|ix| ix.accounts.iter())
+        .filter(|meta| meta.is_signer)
+        .map(|meta| meta.pubkey)
+        .collect();
+
+    let mut candidates: Vec<Pubkey> = Vec::new();
+    for meta in instructions.iter().flat_map(|ix| ix.accounts.iter()) {
+        let key = meta.pubkey;
+        if key != *payer
+            && !programs.contains(&key)
+            && !signers.contains(&key)
+            && !candidates.contains(&key)
+        {
+            candidates.push(key);
+        }
+    }
+    candidates
+}
+
+/// Greedily fills transactions in order.
+fn pack(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    tables: &[AddressLookupTableAccount],
+    limit: usize,
+) -> Result<Vec<Vec<Instruction>>, BatchError> {
+    let mut transactions = Vec::new();
+    let mut current: Vec<Instruction> = Vec::new();
+
+    for (index, ix) in instructions.iter().enumerate() {
+        current.push(ix.clone());
+        if current.len() <= limit && fits(payer, &current, tables) {
+            continue;
+        }
+        current.pop();
+        if current.is_empty() {
+            return Err(BatchError::InstructionTooLarge(index));
+        }
+        transactions.push(std::mem::replace(&mut current, vec![ix.clone()]));
+        if !fits(payer, &current, tables) {
+            return Err(BatchError::InstructionTooLarge(index));
+        }
+    }
+
+    if !current.is_empty() {
+        transactions.push(current);
+    }
+    Ok(transactions)
+}
+
+fn fits(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    tables: &[AddressLookupTableAccount],
+) -> bool {
+    let Some(message) = compile(payer, instructions, tables, Hash::default()) else {
+        return false;
+    };
+    let signatures = message.header().num_required_signatures as usize;
+    // Signature count is a short vec; it fits in one byte here
+    1 + signatures * SIGNATURE_SIZE + message.serialize().len() <= MAX_TRANSACTION_SIZE
+}
+
+pub(crate) fn compile(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Option<VersionedMessage> {
+    if tables.is_empty() {
+        return Some(VersionedMessage::Legacy(Message::new_with_blockhash(
+            instructions,
+            Some(payer),
+            &recent_blockhash,
+        )));
+    }
+    v0::Message::try_compile(payer, instructions, tables, recent_blockhash)
+        .ok()
+        .map(VersionedMessage::V0)
+}
@@ -0,0 +1,280 @@
+//! Proofs for compressed positions.
+//!
+//! A compressed market keeps only a Merkle root of its bets on-chain; the
+//! leaves are in its `CompressedBetPlaced` events. [`CompressedPositions`]
+//! rebuilds the tree from those events, in order, and gives the root and
+//! proof `claim_compressed` needs for each bet. Leaves are hashed by the
+//! program's own [`compression`] functions.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use friends_bets::compression::{self, Node, EMPTY_LEAF, MAX_DEPTH};
+use friends_bets::{BetSide, Position};
+
+use crate::events::FriendBetsEvent;
+use crate::instructions;
+
+/// A bet on a compressed market, as its `CompressedBetPlaced` event
+/// recorded it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressedBet {
+    pub leaf_index: u32,
+    pub owner: Pubkey,
+    pub side: BetSide,
+    pub amount: u64,
+    pub locked_payout: u64,
+    pub discounted_stake: u64,
+    pub claimed: bool,
+}
+
+impl CompressedBet {
+    /// The leaf the program appended for this bet.
+    pub fn leaf(&self, market: &Pubkey) -> Node {
+        compression::position_leaf(
+            market,
+            &Position {
+                owner: self.owner,
+                side: self.side,
+                amount: self.amount,
+                claimed: false,
+                bump: 0,
+                locked_payout: self.locked_payout,
+                discounted_stake: self.discounted_stake,
+                cashout_min_value: 0,
+                compensated: 0,
+            },
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum CompressedError {
+    #[error("leaf {got} arrived out of order, expected leaf {expected}")]
+    OutOfOrder { expected: u32, got: u32 },
+    #[error("no bet at leaf {0}")]
+    UnknownLeaf(u32),
+    #[error("leaf {0} is already claimed")]
+    AlreadyClaimed(u32),
+}
+
+/// A compressed market's position tree, rebuilt from its events.
+#[derive(Clone, Debug)]
+pub struct CompressedPositions {
+    market: Pubkey,
+    bets: Vec<CompressedBet>,
+}
+
+impl CompressedPositions {
+    pub fn new(market: Pubkey) -> Self {
+        Self {
+            market,
+            bets: Vec::new(),
+        }
+    }
+
+    pub fn market(&self) -> Pubkey {
+        self.market
+    }
+
+    pub fn bets(&self) -> &[CompressedBet] {
+        &self.bets
+    }
+
+    /// The unclaimed bets of `owner`.
+    pub fn unclaimed(&self, owner: &Pubkey) -> impl Iterator<Item = &CompressedBet> + '_ {
+        let owner = *owner;
+        self.bets
+            .iter()
+            .filter(move |bet| bet.owner == owner && !bet.claimed)
+    }
+
+    /// Applies one of the market's events, in the order the program
+    /// emitted them. Events of other kinds or markets are ignored.
+    pub fn apply(&mut self, event: &FriendBetsEvent) -> Result<(), CompressedError> {
+        match event {
+            FriendBetsEvent::CompressedBetPlaced(e) if e.market == self.market => {
+                let expected = self.bets.len() as u32;
+                if e.leaf_index != expected {
+                    return Err(CompressedError::OutOfOrder {
+                        expected,
+                        got: e.leaf_index,
+                    });
+                }
+                self.bets.push(CompressedBet {
+                    leaf_index: e.leaf_index,
+                    owner: e.user,
+                    side: e.side,
+                    amount: e.amount,
+                    locked_payout: e.locked_payout,
+                    discounted_stake: e.discounted_stake,
+                    claimed: false,
+                });
+            }
+            FriendBetsEvent::CompressedPositionClaimed(e) if e.market == self.market => {
+                let bet = self
+                    .bets
+                    .get_mut(e.leaf_index as usize)
+                    .ok_or(CompressedError::UnknownLeaf(e.leaf_index))?;
+                if bet.claimed {
+                    return Err(CompressedError::AlreadyClaimed(e.leaf_index));
+                }
+                bet.claimed = true;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The current leaves, with claimed bets emptied as the program does.
+    fn leaves(&self) -> Vec<Node> {
+        self.bets
+            .iter()
+            .map(|bet| {
+                if bet.claimed {
+                    EMPTY_LEAF
+                } else {
+                    bet.leaf(&self.market)
+                }
+            })
+            .collect()
+    }
+
+    /// The tree's root once every applied event has landed.
+    pub fn root(&self) -> Node {
+        let empty = compression::empty_nodes();
+        let mut nodes = self.leaves();
+        for empty in &empty[..MAX_DEPTH] {
+            nodes = pair_up(&nodes, empty);
+        }
+        nodes.first().copied().unwrap_or(empty[MAX_DEPTH])
+    }
+
+    /// The siblings of `leaf_index`, from the bottom up, against
+    /// [`root`](Self::root).
+    pub fn proof(&self, leaf_index: u32) -> Result<[Node; MAX_DEPTH], CompressedError> {
+        if leaf_index as usize >= self.bets.len() {
+            return Err(CompressedError::UnknownLeaf(leaf_index));
+        }
+        let empty = compression::empty_nodes();
+        let mut nodes = self.leaves();
+        let mut proof = [EMPTY_LEAF; MAX_DEPTH];
+        let mut index = leaf_index as usize;
+        for (level, sibling) in proof.iter_mut().enumerate() {
+            *sibling = nodes.get(index ^ 1).copied().unwrap_or(empty[level]);
+            nodes = pair_up(&nodes, &empty[level]);
+            index /= 2;
+        }
+        Ok(proof)
+    }
+
+    /// Builds `claim_compressed` for the bet at `leaf_index`, against the
+    /// current root.
+    pub fn claim(&self, leaf_index: u32, mint: &Pubkey) -> Result<Instruction, CompressedError> {
+        let proof = self.proof(leaf_index)?;
+        let bet = &self.bets[leaf_index as usize];
+        if bet.claimed {
+            return Err(CompressedError::AlreadyClaimed(leaf_index));
+        }
+        Ok(instructions::claim_compressed(
+            &bet.owner,
+            &self.market,
+            mint,
+            self.root(),
+            leaf_index,
+            bet.side,
+            bet.amount,
+            bet.locked_payout,
+            bet.discounted_stake,
+            &proof,
+        ))
+    }
+}
+
+/// The level above `nodes`, padding an odd one out with `empty`.
+fn pair_up(nodes: &[Node], empty: &Node) -> Vec<Node> {
+    nodes
+        .chunks(2)
+        .map(|pair| compression::hash_pair(&pair[0], pair.get(1).unwrap_or(empty)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+    use friends_bets::compression::PositionTree;
+    use friends_bets::{CompressedBetPlaced, CompressedPositionClaimed};
+
+    use super::*;
+
+    fn bet(market: Pubkey, leaf_index: u32, amount: u64) -> FriendBetsEvent {
+        FriendBetsEvent::CompressedBetPlaced(CompressedBetPlaced {
+            market,
+            user: Pubkey::new_unique(),
+            side: [BetSide::A, BetSide::B][amount as usize % 2],
+            amount,
+            locked_payout: amount * 2,
+            discounted_stake: amount / 2,
+            leaf_index,
+        })
+    }
+
+    fn claimed(positions: &CompressedPositions, leaf_index: u32) -> FriendBetsEvent {
+        FriendBetsEvent::CompressedPositionClaimed(CompressedPositionClaimed {
+            market: positions.market(),
+            user: positions.bets()[leaf_index as usize].owner,
+            leaf_index,
+        })
+    }
+
+    #[test]
+    fn tracks_the_program_tree_through_bets_and_claims() {
+        let market = Pubkey::new_unique();
+        let mut tree = PositionTree::zeroed();
+        tree.initialize(market);
+        let mut positions = CompressedPositions::new(market);
+        assert_eq!(positions.root(), tree.root());
+
+        for (index, amount) in (100..111).enumerate() {
+            positions.apply(&bet(market, index as u32, amount)).unwrap();
+            let leaf = positions.bets()[index].leaf(&market);
+            assert_eq!(tree.append(leaf).unwrap(), index as u32);
+            assert_eq!(positions.root(), tree.root());
+        }
+
+        for leaf_index in [3, 0, 10, 7] {
+            let leaf = positions.bets()[leaf_index as usize].leaf(&market);
+            let proof = positions.proof(leaf_index).unwrap();
+            tree.replace(positions.root(), leaf_index, leaf, EMPTY_LEAF, &proof)
+                .unwrap();
+            positions.apply(&claimed(&positions, leaf_index)).unwrap();
+            assert_eq!(positions.root(), tree.root());
+        }
+        assert_eq!(positions.bets().iter().filter(|bet| bet.claimed).count(), 4);
+    }
+
+    #[test]
+    fn rejects_events_out_of_order_and_double_claims() {
+        let market = Pubkey::new_unique();
+        let mut positions = CompressedPositions::new(market);
+
+        // Other markets' events pass through
+        positions.apply(&bet(Pubkey::new_unique(), 5, 1)).unwrap();
+        assert_eq!(
+            positions.apply(&bet(market, 1, 1)),
+            Err(CompressedError::OutOfOrder {
+                expected: 0,
+                got: 1
+            })
+        );
+
+        positions.apply(&bet(market, 0, 1)).unwrap();
+        let claim = claimed(&positions, 0);
+        positions.apply(&claim).unwrap();
+        assert_eq!(
+            positions.apply(&claim),
+            Err(CompressedError::AlreadyClaimed(0))
+        );
+        assert!(positions.claim(0, &Pubkey::new_unique()).is_err());
+        assert_eq!(positions.proof(1), Err(CompressedError::UnknownLeaf(1)));
+    }
+}
@@ -0,0 +1,295 @@
+//! Typed decoding of the events the program emits.
+//!
+//! Events reach clients either as `Program data:` lines in transaction logs
+//! (`emit!`) or as self-CPI instruction data (`emit_cpi!`). Both carry the
+//! 8-byte event discriminator followed by the Borsh payload.
+//!
+//! Payloads are decoded without requiring the whole buffer to be consumed,
+//! so events from a newer program version that appends fields still decode
+//! into the current types. Discriminators this SDK doesn't know about are
+//! returned as [`FriendBetsEvent::Unknown`] rather than as an error.
+
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use friends_bets::admin_log::{AdminAction, EMPTY_ADMIN_LOG};
+use friends_bets::{
+    ActivityStatsClosed, ActivityStatsEnabled, AdminActionLogged, BetPlaced, BetReceipt,
+    BetScheduled, BettingClosed, BettorPageClosed, BettorPagesEnabled, Cancelled,
+    CashOutOrderExecuted, CashOutOrderSet, CashedOut, CharityDonated, CharitySet, Claimed,
+    Compensated, CompressedBetPlaced, CompressedPositionClaimed, ConfigUpdateCancelled,
+    ConfigUpdateProposed, ConfigUpdated, CreatorFeeWithdrawn, DependencySet, FeeBurned, FeeRebated,
+    FeeSplitPaid, FeeSplitsSet, InsuranceCollected, LargeBetPlaced, LargeBetThresholdSet,
+    MarketCloned, MarketCollected, MarketCompacted, MarketForked, MarketInitialized,
+    MarketTitleClosed, MarketTitleRegistered, PortfolioClosed, PortfolioOpened,
+    PortfolioPageClosed, PositionsCompressed, Resolved, ScheduledBetCancelled,
+    ScheduledBetExecuted, StakeCapsSet, StakeWithdrawn, Staked, UnstakeRequested,
+};
+
+use crate::{Config, PROGRAM_ID};
+
+#[derive(Debug, thiserror::Error)]
+pub enum EventError {
+    #[error("event data is shorter than its discriminator")]
+    TooShort,
+    #[error("invalid base64 in program data: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("failed to deserialize {name}: {source}")]
+    Deserialize {
+        name: &'static str,
+        source: std::io::Error,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AdminLogError {
+    #[error("admin log entry {found} found where {expected} was expected")]
+    OutOfSequence { expected: u64, found: u64 },
+    #[error("admin log entry {0} doesn't follow the entries before it")]
+    Broken(u64),
+    #[error("the admin log has {found} entries but the config counts {expected}")]
+    Incomplete { expected: u64, found: u64 },
+    #[error("the admin log doesn't end at the config's hash")]
+    HashMismatch,
+    #[error(transparent)]
+    Program(#[from] anchor_lang::error::Error),
+}
+
+macro_rules! events {
+    ($($name:ident),* $(,)?) => {
+        /// Any event emitted by the program.
+        #[derive(Clone, Debug)]
+        pub enum FriendBetsEvent {
+            $($name($name),)*
+            /// An event whose discriminator this SDK version doesn't know.
+            Unknown { discriminator: [u8; 8], data: Vec<u8> },
+        }
+
+        impl FriendBetsEvent {
+            /// Name of the event as declared in the program.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$name(_) => stringify!($name),)*
+                    Self::Unknown { .. } => "Unknown",
+                }
+            }
+        }
+
+        /// Decodes a discriminator-prefixed event payload.
+        pub fn decode_event(data: &[u8]) -> Result<FriendBetsEvent, EventError> {
+            if data.len() < 8 {
+                return Err(EventError::TooShort);
+            }
+            let (discriminator, mut payload) = data.split_at(8);
+            $(
+                if discriminator == <$name as Discriminator>::DISCRIMINATOR {
+                    return <$name as AnchorDeserialize>::deserialize(&mut payload)
+                        .map(FriendBetsEvent::$name)
+                        .map_err(|source| EventError::Deserialize {
+                            name: stringify!($name),
+                            source,
+                        });
+                }
+            )*
+            Ok(FriendBetsEvent::Unknown {
+                discriminator: discriminator.try_into().expect("split at 8"),
+                data: payload.to_vec(),
+            })
+        }
+    };
+}
+
+events!(
+    MarketInitialized,
+    MarketCloned,
+    MarketForked,
+    DependencySet,
+    BetPlaced,
+    LargeBetPlaced,
+    LargeBetThresholdSet,
+    StakeCapsSet,
+    BetReceipt,
+    CashedOut,
+    CashOutOrderSet,
+    CashOutOrderExecuted,
+    BettingClosed,
+    Resolved,
+    Cancelled,
+    Claimed,
+    CreatorFeeWithdrawn,
+    FeeSplitsSet,
+    FeeSplitPaid,
+    FeeBurned,
+    InsuranceCollected,
+    Compensated,
+    CharitySet,
+    CharityDonated,
+    FeeRebated,
+    ConfigUpdated,
+    ConfigUpdateProposed,
+    ConfigUpdateCancelled,
+    AdminActionLogged,
+    Staked,
+    UnstakeRequested,
+    StakeWithdrawn,
+    MarketCollected,
+    PositionsCompressed,
+    CompressedBetPlaced,
+    CompressedPositionClaimed,
+    BettorPagesEnabled,
+    BettorPageClosed,
+    ActivityStatsEnabled,
+    ActivityStatsClosed,
+    MarketTitleRegistered,
+    MarketTitleClosed,
+    BetScheduled,
+    ScheduledBetExecuted,
+    ScheduledBetCancelled,
+    PortfolioOpened,
+    PortfolioPageClosed,
+    PortfolioClosed,
+    MarketCompacted,
+);
+
+/// Decodes the data of an `emit_cpi!` self-invocation. Returns `None` when
+/// the instruction isn't an event CPI.
+pub fn decode_cpi_event(ix_data: &[u8]) -> Option<Result<FriendBetsEvent, EventError>> {
+    ix_data.strip_prefix(EVENT_IX_TAG_LE).map(decode_event)
+}
+
+/// Extracts the program's events from a transaction's log messages.
+///
+/// Tracks the invocation stack so `Program data:` lines from other programs,
+/// including ones invoked by this program, are skipped.
+pub fn parse_logs(logs: &[String]) -> Result<Vec<FriendBetsEvent>, EventError> {
+    let program_id = PROGRAM_ID.to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        if let Some(data) = rest.strip_prefix("data: ") {
+            if stack.last() == Some(&program_id.as_str()) {
+                events.push(decode_event(&STANDARD.decode(data)?)?);
+            }
+        } else if let Some((id, tail)) = rest.split_once(' ') {
+            if tail.starts_with("invoke [") {
+                stack.push(id);
+            } else if tail == "success" || tail.starts_with("failed") {
+                stack.pop();
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+/// Events in `logs` that concern `market`.
+pub fn market_events(logs: &[String], market: &Pubkey) -> Result<Vec<FriendBetsEvent>, EventError> {
+    Ok(parse_logs(logs)?
+        .into_iter()
+        .filter(|event| event.market() == Some(*market))
+        .collect())
+}
+
+/// Checks that `entries`, every [`AdminActionLogged`] in order, are the
+/// whole admin log `config` has hashed, none missing or altered.
+pub fn verify_admin_log(
+    entries: &[AdminActionLogged],
+    config: &Config,
+) -> Result<(), AdminLogError> {
+    let mut hash = EMPTY_ADMIN_LOG;
+    for (expected, entry) in (0..).zip(entries) {
+        if entry.sequence != expected {
+            return Err(AdminLogError::OutOfSequence {
+                expected,
+                found: entry.sequence,
+            });
+        }
+        if !entry.follows(&hash)? {
+            return Err(AdminLogError::Broken(entry.sequence));
+        }
+        hash = entry.log_hash;
+    }
+    let found = entries.len() as u64;
+    if found != config.admin_actions {
+        return Err(AdminLogError::Incomplete {
+            expected: config.admin_actions,
+            found,
+        });
+    }
+    if hash != config.admin_log_hash {
+        return Err(AdminLogError::HashMismatch);
+    }
+    Ok(())
+}
+
+impl FriendBetsEvent {
+    /// The market the event refers to, if any.
+    pub fn market(&self) -> Option<Pubkey> {
+        Some(match self {
+            Self::MarketInitialized(e) => e.market,
+            Self::MarketCloned(e) => e.market,
+            Self::MarketForked(e) => e.market,
+            Self::DependencySet(e) => e.market,
+            Self::BetPlaced(e) => e.market,
+            Self::LargeBetPlaced(e) => e.market,
+            Self::LargeBetThresholdSet(e) => e.market,
+            Self::StakeCapsSet(e) => e.market,
+            Self::BetReceipt(e) => e.market,
+            Self::CashedOut(e) => e.market,
+            Self::CashOutOrderSet(e) => e.market,
+            Self::CashOutOrderExecuted(e) => e.market,
+            Self::BettingClosed(e) => e.market,
+            Self::Resolved(e) => e.market,
+            Self::Cancelled(e) => e.market,
+            Self::Claimed(e) => e.market,
+            Self::CreatorFeeWithdrawn(e) => e.market,
+            Self::FeeSplitsSet(e) => e.market,
+            Self::FeeSplitPaid(e) => e.market,
+            Self::FeeBurned(e) => e.market,
+            Self::InsuranceCollected(e) => e.market,
+            Self::Compensated(e) => e.market,
+            Self::CharitySet(e) => e.market,
+            Self::CharityDonated(e) => e.market,
+            Self::FeeRebated(e) => e.market,
+            Self::MarketCollected(e) => e.market,
+            Self::PositionsCompressed(e) => e.market,
+            Self::CompressedBetPlaced(e) => e.market,
+            Self::CompressedPositionClaimed(e) => e.market,
+            Self::BettorPagesEnabled(e) => e.market,
+            Self::BettorPageClosed(e) => e.market,
+            Self::ActivityStatsEnabled(e) => e.market,
+            Self::ActivityStatsClosed(e) => e.market,
+            Self::MarketTitleRegistered(e) => e.market,
+            Self::MarketTitleClosed(e) => e.market,
+            Self::BetScheduled(e) => e.market,
+            Self::ScheduledBetExecuted(e) => e.market,
+            Self::ScheduledBetCancelled(e) => e.market,
+            Self::MarketCompacted(e) => e.market,
+            Self::AdminActionLogged(AdminActionLogged {
+                action: AdminAction::Compensated { market, .. },
+                ..
+            }) => *market,
+            Self::AdminActionLogged(AdminActionLogged {
+                action: AdminAction::MarketForked { original, .. },
+                ..
+            }) => *original,
+            Self::ConfigUpdated(_)
+            | Self::AdminActionLogged(_)
+            | Self::ConfigUpdateProposed(_)
+            | Self::ConfigUpdateCancelled(_)
+            | Self::Staked(_)
+            | Self::UnstakeRequested(_)
+            | Self::StakeWithdrawn(_)
+            | Self::PortfolioOpened(_)
+            | Self::PortfolioPageClosed(_)
+            | Self::PortfolioClosed(_)
+            | Self::Unknown { .. } => return None,
+        })
+    }
+}
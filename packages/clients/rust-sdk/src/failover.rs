@@ -0,0 +1,381 @@
+//! Failover across several RPC endpoints.
+//!
+//! [`Endpoints`] keeps endpoints in order of preference and runs each
+//! request against the first one that's healthy and has room under its rate
+//! limit, moving on to the next when an endpoint fails. One that fails
+//! [`HealthPolicy::failure_threshold`] times in a row is left out for
+//! [`HealthPolicy::cooldown`] and then given another chance; when every
+//! endpoint is out, the one due back soonest is tried anyway.
+//! [`Endpoints::check_due`] probes them all every
+//! [`HealthPolicy::check_interval`], so a failing endpoint drops out, and a
+//! recovered one comes back, without waiting on requests to find out.
+//!
+//! The transport is the caller's: requests are closures given an endpoint's
+//! URL, which say whether a failure was the endpoint's or the request's.
+
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// JSON-RPC error codes that say more about the node than the request, so
+/// another endpoint may well succeed: rate limited (429), unhealthy or
+/// behind (-32005), short of a requested minimum slot (-32016) and history
+/// pruned (-32001, -32011).
+pub const ENDPOINT_ERROR_CODES: [i64; 5] = [429, -32001, -32005, -32011, -32016];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Endpoint {
+    pub url: String,
+    /// Most requests a second to send it; unlimited when `None`.
+    pub rate_limit: Option<f64>,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum EndpointError {
+    #[error("no RPC endpoint given")]
+    Empty,
+    #[error("invalid rate limit in {0:?}; append #N to allow N requests a second")]
+    InvalidRate(String),
+}
+
+/// Parses `URL` or `URL#N`, an endpoint allowed `N` requests a second.
+impl FromStr for Endpoint {
+    type Err = EndpointError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let (url, rate_limit) = match text.rsplit_once('#') {
+            Some((url, rate)) => match rate.parse::<f64>() {
+                Ok(rate) if rate.is_finite() && rate > 0.0 => (url, Some(rate)),
+                _ => return Err(EndpointError::InvalidRate(text.to_string())),
+            },
+            None => (text, None),
+        };
+        if url.is_empty() {
+            return Err(EndpointError::Empty);
+        }
+        Ok(Self {
+            url: url.to_string(),
+            rate_limit,
+        })
+    }
+}
+
+/// Parses a comma-separated list of endpoints, most preferred first.
+pub fn parse_endpoints(list: &str) -> Result<Vec<Endpoint>, EndpointError> {
+    list.split(',').map(str::parse).collect()
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HealthPolicy {
+    /// Failures in a row that take an endpoint out.
+    pub failure_threshold: u32,
+    /// How long an endpoint stays out before it's tried again.
+    pub cooldown: Duration,
+    /// How often [`Endpoints::check_due`] probes every endpoint.
+    pub check_interval: Duration,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+            check_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// How a request went wrong.
+#[derive(Debug)]
+pub enum Failure<E> {
+    /// The endpoint is down, overloaded or behind; another may do better.
+    Endpoint(E),
+    /// The request itself was refused, as any endpoint would refuse it.
+    Request(E),
+}
+
+struct State {
+    url: String,
+    rate_limit: Option<f64>,
+    /// Requests that can go out now; negative once some are waiting.
+    tokens: f64,
+    refilled: Instant,
+    failures: u32,
+    down_until: Option<Instant>,
+}
+
+impl State {
+    /// How long until a request may go out.
+    fn wait(&mut self, now: Instant) -> Duration {
+        let Some(rate) = self.rate_limit else {
+            return Duration::ZERO;
+        };
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate.max(1.0));
+        self.refilled = now;
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / rate)
+        }
+    }
+
+    /// Takes the next request slot, returning how long to wait for it.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let wait = self.wait(now);
+        self.tokens -= 1.0;
+        wait
+    }
+
+    fn is_up(&self, now: Instant) -> bool {
+        self.down_until.is_none_or(|until| until <= now)
+    }
+}
+
+/// Endpoints shared by every request of a client.
+pub struct Endpoints {
+    states: Mutex<Vec<State>>,
+    policy: HealthPolicy,
+    last_check: Mutex<Instant>,
+}
+
+impl Endpoints {
+    /// Panics without endpoints; [`parse_endpoints`] always gives one.
+    pub fn new(endpoints: Vec<Endpoint>, policy: HealthPolicy) -> Self {
+        assert!(!endpoints.is_empty(), "no RPC endpoints");
+        let now = Instant::now();
+        let states = endpoints
+            .into_iter()
+            .map(|endpoint| State {
+                url: endpoint.url,
+                rate_limit: endpoint.rate_limit,
+                tokens: endpoint.rate_limit.map_or(0.0, |rate| rate.max(1.0)),
+                refilled: now,
+                failures: 0,
+                down_until: None,
+            })
+            .collect();
+        Self {
+            states: Mutex::new(states),
+            policy,
+            last_check: Mutex::new(now),
+        }
+    }
+
+    /// The most preferred endpoint's URL.
+    pub fn primary(&self) -> String {
+        self.states.lock().unwrap()[0].url.clone()
+    }
+
+    pub fn urls(&self) -> Vec<String> {
+        let states = self.states.lock().unwrap();
+        states.iter().map(|state| state.url.clone()).collect()
+    }
+
+    /// Runs `request` against endpoints in turn until one succeeds or
+    /// refuses the request, returning the last endpoint's error when every
+    /// endpoint fails.
+    pub fn call<T, E>(
+        &self,
+        mut request: impl FnMut(&str) -> Result<T, Failure<E>>,
+    ) -> Result<T, E> {
+        let count = self.states.lock().unwrap().len();
+        let mut tried = vec![false; count];
+        loop {
+            let (index, url, wait) = self.pick(&tried);
+            thread::sleep(wait);
+            tried[index] = true;
+            match request(&url) {
+                Ok(value) => {
+                    self.record(index, true);
+                    return Ok(value);
+                }
+                // The endpoint answered, so it's up
+                Err(Failure::Request(err)) => {
+                    self.record(index, true);
+                    return Err(err);
+                }
+                Err(Failure::Endpoint(err)) => {
+                    self.record(index, false);
+                    if tried.iter().all(|tried| *tried) {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Probes every endpoint with `probe`, which says whether it's
+    /// healthy, taking failing ones out straight away. Returns each URL and
+    /// whether it passed.
+    pub fn check(&self, mut probe: impl FnMut(&str) -> bool) -> Vec<(String, bool)> {
+        *self.last_check.lock().unwrap() = Instant::now();
+        let mut results = Vec::new();
+        for (index, url) in self.urls().into_iter().enumerate() {
+            let wait = self.states.lock().unwrap()[index].reserve(Instant::now());
+            thread::sleep(wait);
+            let healthy = probe(&url);
+            let mut states = self.states.lock().unwrap();
+            let state = &mut states[index];
+            if healthy {
+                state.failures = 0;
+                state.down_until = None;
+            } else {
+                state.failures = state.failures.max(self.policy.failure_threshold);
+                state.down_until = Some(Instant::now() + self.policy.cooldown);
+            }
+            results.push((url, healthy));
+        }
+        results
+    }
+
+    /// Runs [`Self::check`] when [`HealthPolicy::check_interval`] has passed
+    /// since the last one. A lone endpoint has nothing to fail over to, so
+    /// it isn't probed.
+    pub fn check_due(&self, probe: impl FnMut(&str) -> bool) -> Option<Vec<(String, bool)>> {
+        if self.states.lock().unwrap().len() < 2 {
+            return None;
+        }
+        {
+            let mut last_check = self.last_check.lock().unwrap();
+            if last_check.elapsed() < self.policy.check_interval {
+                return None;
+            }
+            // Claimed here so concurrent callers don't all probe
+            *last_check = Instant::now();
+        }
+        Some(self.check(probe))
+    }
+
+    /// Picks the endpoint for the next attempt among those not `tried`: the
+    /// first that's up and free to send, else the up one free soonest, else
+    /// the one due back soonest. Reserves a request slot on it.
+    fn pick(&self, tried: &[bool]) -> (usize, String, Duration) {
+        let mut states = self.states.lock().unwrap();
+        let now = Instant::now();
+        let candidates: Vec<usize> = (0..states.len()).filter(|i| !tried[*i]).collect();
+        let up: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|i| states[*i].is_up(now))
+            .collect();
+        let index = if up.is_empty() {
+            *candidates
+                .iter()
+                .min_by_key(|i| states[**i].down_until)
+                .expect("an untried endpoint is left")
+        } else {
+            let waits: Vec<(usize, Duration)> =
+                up.iter().map(|i| (*i, states[*i].wait(now))).collect();
+            waits
+                .iter()
+                .find(|(_, wait)| wait.is_zero())
+                .or_else(|| waits.iter().min_by_key(|(_, wait)| *wait))
+                .expect("an endpoint is up")
+                .0
+        };
+        let wait = states[index].reserve(now);
+        (index, states[index].url.clone(), wait)
+    }
+
+    fn record(&self, index: usize, ok: bool) {
+        let mut states = self.states.lock().unwrap();
+        let state = &mut states[index];
+        if ok {
+            state.failures = 0;
+            state.down_until = None;
+            return;
+        }
+        state.failures += 1;
+        if state.failures >= self.policy.failure_threshold {
+            state.down_until = Some(Instant::now() + self.policy.cooldown);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints(list: &str) -> Endpoints {
+        let policy = HealthPolicy {
+            failure_threshold: 2,
+            ..HealthPolicy::default()
+        };
+        Endpoints::new(parse_endpoints(list).unwrap(), policy)
+    }
+
+    #[test]
+    fn parses_endpoint_lists() {
+        assert_eq!(
+            parse_endpoints("https://a.example, https://b.example/?key=1#2.5").unwrap(),
+            vec![
+                Endpoint {
+                    url: "https://a.example".into(),
+                    rate_limit: None,
+                },
+                Endpoint {
+                    url: "https://b.example/?key=1".into(),
+                    rate_limit: Some(2.5),
+                },
+            ]
+        );
+        assert_eq!(parse_endpoints(""), Err(EndpointError::Empty));
+        assert!(matches!(
+            parse_endpoints("https://a.example#0"),
+            Err(EndpointError::InvalidRate(_))
+        ));
+    }
+
+    #[test]
+    fn fails_over_and_takes_failing_endpoints_out() {
+        let endpoints = endpoints("a,b");
+        let seen = Mutex::new(Vec::new());
+        let call = |down: &str| {
+            endpoints.call(|url| {
+                seen.lock().unwrap().push(url.to_string());
+                if url == down {
+                    Err(Failure::Endpoint(url.to_string()))
+                } else {
+                    Ok(url.to_string())
+                }
+            })
+        };
+        assert_eq!(call("a"), Ok("b".into()));
+        assert_eq!(call("a"), Ok("b".into()));
+        // Out after two failures in a row, so `a` isn't tried
+        assert_eq!(call("a"), Ok("b".into()));
+        assert_eq!(*seen.lock().unwrap(), ["a", "b", "a", "b", "b"]);
+        // With nothing else up, it's tried anyway, and back once it answers
+        assert_eq!(call("b"), Ok("a".into()));
+        assert_eq!(call("b"), Ok("a".into()));
+        assert_eq!(seen.lock().unwrap()[5..], ["b", "a", "a"]);
+
+        let refused: Result<(), _> = endpoints.call(|url| Err(Failure::Request(url.to_string())));
+        assert_eq!(refused, Err("a".to_string()));
+        let results = endpoints.check(|url| url == "b");
+        assert_eq!(results, [("a".into(), false), ("b".into(), true)]);
+        assert_eq!(
+            endpoints.call(|url| Ok::<_, Failure<()>>(url.to_string())),
+            Ok("b".into())
+        );
+    }
+
+    #[test]
+    fn spreads_requests_under_rate_limits() {
+        let endpoints = endpoints("a#1,b#1");
+        let start = Instant::now();
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            seen.push(
+                endpoints
+                    .call(|url| Ok::<_, Failure<()>>(url.to_string()))
+                    .unwrap(),
+            );
+        }
+        assert_eq!(seen, ["a", "b", "a"]);
+        assert!(start.elapsed() >= Duration::from_millis(900));
+    }
+}
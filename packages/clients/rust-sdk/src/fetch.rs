@@ -0,0 +1,410 @@
+//! `getProgramAccounts` helpers for markets, positions, bettor pages,
+//! activity accounts, title registrations, scheduled bets, portfolio pages
+//! and stake accounts.
+//!
+//! The filters here encode the byte layout of the program's accounts, so
+//! callers don't have to work out memcmp offsets by hand. The SDK doesn't
+//! pick an RPC client: implement [`ProgramAccounts`] over whichever one the
+//! application uses and translate [`AccountFilter`] into its filter type.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use friends_bets::activity::MarketActivity;
+use friends_bets::portfolio::PortfolioPage;
+use friends_bets::schedule::ScheduledBet;
+use friends_bets::title::{title_hash, MarketTitle};
+use friends_bets::{BettorPage, Market, MarketStatus, Position, StakeAccount};
+
+use crate::PROGRAM_ID;
+
+/// Byte offsets of the fields that are filterable in each account. All
+/// fields ahead of a market's `title` are fixed size, so everything up to
+/// `status` has a stable offset.
+pub mod offsets {
+    pub const DISCRIMINATOR: usize = 0;
+
+    pub const MARKET_ID: usize = 8;
+    pub const MARKET_CREATOR: usize = MARKET_ID + 8;
+    pub const MARKET_MINT: usize = MARKET_CREATOR + 32;
+    pub const MARKET_VAULT: usize = MARKET_MINT + 32;
+    pub const MARKET_FEE_BPS: usize = MARKET_VAULT + 32;
+    pub const MARKET_END_TS: usize = MARKET_FEE_BPS + 2;
+    pub const MARKET_RESOLVE_DEADLINE_TS: usize = MARKET_END_TS + 8;
+    pub const MARKET_STAKED_A: usize = MARKET_RESOLVE_DEADLINE_TS + 8;
+    pub const MARKET_STAKED_B: usize = MARKET_STAKED_A + 8;
+    pub const MARKET_STATUS: usize = MARKET_STAKED_B + 8;
+
+    pub const POSITION_OWNER: usize = 8;
+    pub const POSITION_SIDE: usize = POSITION_OWNER + 32;
+
+    pub const BETTOR_PAGE_MARKET: usize = 8;
+
+    pub const ACTIVITY_MARKET: usize = 8;
+
+    pub const MARKET_TITLE_MARKET: usize = 8;
+    pub const MARKET_TITLE_HASH: usize = MARKET_TITLE_MARKET + 32;
+
+    pub const SCHEDULED_BET_OWNER: usize = 8;
+    pub const SCHEDULED_BET_MARKET: usize = SCHEDULED_BET_OWNER + 32;
+
+    pub const PORTFOLIO_PAGE_OWNER: usize = 8;
+
+    pub const STAKE_OWNER: usize = 8;
+}
+
+/// A `getProgramAccounts` filter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AccountFilter {
+    DataSize(u64),
+    Memcmp { offset: usize, bytes: Vec<u8> },
+}
+
+impl AccountFilter {
+    fn memcmp(offset: usize, bytes: impl Into<Vec<u8>>) -> Self {
+        Self::Memcmp {
+            offset,
+            bytes: bytes.into(),
+        }
+    }
+
+    fn discriminator(discriminator: &[u8]) -> Self {
+        Self::memcmp(offsets::DISCRIMINATOR, discriminator)
+    }
+}
+
+/// Source of program accounts, typically an RPC client.
+pub trait ProgramAccounts {
+    type Error;
+
+    /// Returns the address and data of every account owned by `program_id`
+    /// that matches all of `filters`.
+    fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[AccountFilter],
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>, Self::Error>;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError<E> {
+    #[error("rpc error: {0}")]
+    Rpc(E),
+    #[error("failed to deserialize account {address}: {source}")]
+    Deserialize {
+        address: Pubkey,
+        source: anchor_lang::error::Error,
+    },
+}
+
+fn market_filters(extra: impl IntoIterator<Item = AccountFilter>) -> Vec<AccountFilter> {
+    let mut filters = vec![
+        AccountFilter::DataSize(Market::LEN as u64),
+        AccountFilter::discriminator(Market::DISCRIMINATOR),
+    ];
+    filters.extend(extra);
+    filters
+}
+
+/// Filters matching every market, except those shrunk by `compact_market`.
+pub fn markets_filters() -> Vec<AccountFilter> {
+    market_filters([])
+}
+
+/// Filters matching markets shrunk by `compact_market`.
+pub fn compacted_markets_filters() -> Vec<AccountFilter> {
+    vec![
+        AccountFilter::DataSize(Market::COMPACT_LEN as u64),
+        AccountFilter::discriminator(Market::DISCRIMINATOR),
+    ]
+}
+
+/// Filters matching markets created by `creator`.
+pub fn markets_by_creator_filters(creator: &Pubkey) -> Vec<AccountFilter> {
+    market_filters([AccountFilter::memcmp(
+        offsets::MARKET_CREATOR,
+        creator.to_bytes(),
+    )])
+}
+
+/// Filters matching markets denominated in `mint`.
+pub fn markets_by_mint_filters(mint: &Pubkey) -> Vec<AccountFilter> {
+    market_filters([AccountFilter::memcmp(offsets::MARKET_MINT, mint.to_bytes())])
+}
+
+/// Filters matching markets in `status`.
+pub fn markets_by_status_filters(status: MarketStatus) -> Vec<AccountFilter> {
+    market_filters([AccountFilter::memcmp(
+        offsets::MARKET_STATUS,
+        [status as u8],
+    )])
+}
+
+/// Filters matching every position.
+pub fn positions_filters() -> Vec<AccountFilter> {
+    vec![
+        AccountFilter::DataSize(Position::LEN as u64),
+        AccountFilter::discriminator(Position::DISCRIMINATOR),
+    ]
+}
+
+/// Filters matching positions held by `owner`.
+pub fn positions_by_owner_filters(owner: &Pubkey) -> Vec<AccountFilter> {
+    let mut filters = positions_filters();
+    filters.push(AccountFilter::memcmp(
+        offsets::POSITION_OWNER,
+        owner.to_bytes(),
+    ));
+    filters
+}
+
+/// Filters matching the bettor pages of `market`.
+pub fn bettor_pages_filters(market: &Pubkey) -> Vec<AccountFilter> {
+    vec![
+        AccountFilter::DataSize(BettorPage::LEN as u64),
+        AccountFilter::discriminator(BettorPage::DISCRIMINATOR),
+        AccountFilter::memcmp(offsets::BETTOR_PAGE_MARKET, market.to_bytes()),
+    ]
+}
+
+/// Filters matching every market's activity account.
+pub fn activity_filters() -> Vec<AccountFilter> {
+    vec![
+        AccountFilter::DataSize(MarketActivity::LEN as u64),
+        AccountFilter::discriminator(MarketActivity::DISCRIMINATOR),
+    ]
+}
+
+fn market_title_filters(filter: AccountFilter) -> Vec<AccountFilter> {
+    vec![
+        AccountFilter::DataSize(MarketTitle::LEN as u64),
+        AccountFilter::discriminator(MarketTitle::DISCRIMINATOR),
+        filter,
+    ]
+}
+
+/// Filters matching the registrations of `title`, normalized, under any
+/// nonce.
+pub fn market_titles_filters(title: &str) -> Vec<AccountFilter> {
+    market_title_filters(AccountFilter::memcmp(
+        offsets::MARKET_TITLE_HASH,
+        title_hash(title),
+    ))
+}
+
+/// Filters matching the title registration of `market`.
+pub fn market_title_of_filters(market: &Pubkey) -> Vec<AccountFilter> {
+    market_title_filters(AccountFilter::memcmp(
+        offsets::MARKET_TITLE_MARKET,
+        market.to_bytes(),
+    ))
+}
+
+/// Filters matching every scheduled bet.
+pub fn scheduled_bets_filters() -> Vec<AccountFilter> {
+    vec![
+        AccountFilter::DataSize(ScheduledBet::LEN as u64),
+        AccountFilter::discriminator(ScheduledBet::DISCRIMINATOR),
+    ]
+}
+
+/// Filters matching the scheduled bets of `owner`.
+pub fn scheduled_bets_by_owner_filters(owner: &Pubkey) -> Vec<AccountFilter> {
+    let mut filters = scheduled_bets_filters();
+    filters.push(AccountFilter::memcmp(
+        offsets::SCHEDULED_BET_OWNER,
+        owner.to_bytes(),
+    ));
+    filters
+}
+
+/// Filters matching the scheduled bets on `market`.
+pub fn scheduled_bets_by_market_filters(market: &Pubkey) -> Vec<AccountFilter> {
+    let mut filters = scheduled_bets_filters();
+    filters.push(AccountFilter::memcmp(
+        offsets::SCHEDULED_BET_MARKET,
+        market.to_bytes(),
+    ));
+    filters
+}
+
+/// Filters matching the open pages of `owner`'s portfolio.
+pub fn portfolio_pages_filters(owner: &Pubkey) -> Vec<AccountFilter> {
+    vec![
+        AccountFilter::DataSize(PortfolioPage::LEN as u64),
+        AccountFilter::discriminator(PortfolioPage::DISCRIMINATOR),
+        AccountFilter::memcmp(offsets::PORTFOLIO_PAGE_OWNER, owner.to_bytes()),
+    ]
+}
+
+/// Filters matching every stake account.
+pub fn stake_accounts_filters() -> Vec<AccountFilter> {
+    vec![
+        AccountFilter::DataSize(StakeAccount::LEN as u64),
+        AccountFilter::discriminator(StakeAccount::DISCRIMINATOR),
+    ]
+}
+
+/// Fetches and deserializes all program accounts of type `T` matching `filters`.
+pub fn fetch_accounts<T, C>(
+    client: &C,
+    filters: &[AccountFilter],
+) -> Result<Vec<(Pubkey, T)>, FetchError<C::Error>>
+where
+    T: AccountDeserialize,
+    C: ProgramAccounts,
+{
+    client
+        .get_program_accounts(&PROGRAM_ID, filters)
+        .map_err(FetchError::Rpc)?
+        .into_iter()
+        .map(|(address, data)| {
+            T::try_deserialize(&mut data.as_slice())
+                .map(|account| (address, account))
+                .map_err(|source| FetchError::Deserialize { address, source })
+        })
+        .collect()
+}
+
+pub fn fetch_markets<C: ProgramAccounts>(
+    client: &C,
+) -> Result<Vec<(Pubkey, Market)>, FetchError<C::Error>> {
+    fetch_accounts(client, &markets_filters())
+}
+
+pub fn fetch_markets_by_creator<C: ProgramAccounts>(
+    client: &C,
+    creator: &Pubkey,
+) -> Result<Vec<(Pubkey, Market)>, FetchError<C::Error>> {
+    fetch_accounts(client, &markets_by_creator_filters(creator))
+}
+
+pub fn fetch_markets_by_mint<C: ProgramAccounts>(
+    client: &C,
+    mint: &Pubkey,
+) -> Result<Vec<(Pubkey, Market)>, FetchError<C::Error>> {
+    fetch_accounts(client, &markets_by_mint_filters(mint))
+}
+
+pub fn fetch_markets_by_status<C: ProgramAccounts>(
+    client: &C,
+    status: MarketStatus,
+) -> Result<Vec<(Pubkey, Market)>, FetchError<C::Error>> {
+    fetch_accounts(client, &markets_by_status_filters(status))
+}
+
+/// Fetches markets still accepting bets. Markets past `end_ts` that nobody
+/// has closed yet are included; check `end_ts` against the clock if needed.
+pub fn fetch_open_markets<C: ProgramAccounts>(
+    client: &C,
+) -> Result<Vec<(Pubkey, Market)>, FetchError<C::Error>> {
+    fetch_markets_by_status(client, MarketStatus::Open)
+}
+
+/// Fetches the markets shrunk by `compact_market`, which
+/// [`fetch_markets`] leaves out. Their titles and dependencies are gone.
+pub fn fetch_compacted_markets<C: ProgramAccounts>(
+    client: &C,
+) -> Result<Vec<(Pubkey, Market)>, FetchError<C::Error>> {
+    fetch_accounts(client, &compacted_markets_filters())
+}
+
+/// Fetches every position. Group them by market with
+/// [`is_position_of`](crate::pda::is_position_of).
+pub fn fetch_positions<C: ProgramAccounts>(
+    client: &C,
+) -> Result<Vec<(Pubkey, Position)>, FetchError<C::Error>> {
+    fetch_accounts(client, &positions_filters())
+}
+
+/// Fetches the positions held by `owner`. Positions don't store their
+/// market, so match them against
+/// [`position_address`](crate::pda::position_address) when it's needed.
+pub fn fetch_positions_by_owner<C: ProgramAccounts>(
+    client: &C,
+    owner: &Pubkey,
+) -> Result<Vec<(Pubkey, Position)>, FetchError<C::Error>> {
+    fetch_accounts(client, &positions_by_owner_filters(owner))
+}
+
+/// Fetches the open bettor pages of `market`, in page order.
+pub fn fetch_bettor_pages<C: ProgramAccounts>(
+    client: &C,
+    market: &Pubkey,
+) -> Result<Vec<(Pubkey, BettorPage)>, FetchError<C::Error>> {
+    let mut pages: Vec<(Pubkey, BettorPage)> =
+        fetch_accounts(client, &bettor_pages_filters(market))?;
+    pages.sort_by_key(|(_, page)| page.index);
+    Ok(pages)
+}
+
+/// Fetches the activity accounts of every market keeping one, to rank
+/// markets with [`MarketActivity::recent`].
+pub fn fetch_market_activity<C: ProgramAccounts>(
+    client: &C,
+) -> Result<Vec<(Pubkey, MarketActivity)>, FetchError<C::Error>> {
+    fetch_accounts(client, &activity_filters())
+}
+
+/// Finds the markets registered under `title`, or a near-duplicate of it
+/// differing only in case, spacing or punctuation, in nonce order. Link
+/// to one of these instead of opening another market on the question.
+pub fn find_market_by_title<C: ProgramAccounts>(
+    client: &C,
+    title: &str,
+) -> Result<Vec<(Pubkey, MarketTitle)>, FetchError<C::Error>> {
+    let mut titles: Vec<(Pubkey, MarketTitle)> =
+        fetch_accounts(client, &market_titles_filters(title))?;
+    titles.sort_by_key(|(_, title)| title.nonce);
+    Ok(titles)
+}
+
+/// Fetches the title registration of `market`, if it holds one.
+pub fn fetch_market_title<C: ProgramAccounts>(
+    client: &C,
+    market: &Pubkey,
+) -> Result<Option<(Pubkey, MarketTitle)>, FetchError<C::Error>> {
+    Ok(fetch_accounts(client, &market_title_of_filters(market))?
+        .into_iter()
+        .next())
+}
+
+/// Fetches every scheduled bet, for a keeper to place the due ones with
+/// [`ScheduledBet::is_due`].
+pub fn fetch_scheduled_bets<C: ProgramAccounts>(
+    client: &C,
+) -> Result<Vec<(Pubkey, ScheduledBet)>, FetchError<C::Error>> {
+    fetch_accounts(client, &scheduled_bets_filters())
+}
+
+pub fn fetch_scheduled_bets_by_owner<C: ProgramAccounts>(
+    client: &C,
+    owner: &Pubkey,
+) -> Result<Vec<(Pubkey, ScheduledBet)>, FetchError<C::Error>> {
+    fetch_accounts(client, &scheduled_bets_by_owner_filters(owner))
+}
+
+/// Fetches the scheduled bets on `market`, which must be placed or
+/// cancelled before `gc_market` can collect it.
+pub fn fetch_scheduled_bets_by_market<C: ProgramAccounts>(
+    client: &C,
+    market: &Pubkey,
+) -> Result<Vec<(Pubkey, ScheduledBet)>, FetchError<C::Error>> {
+    fetch_accounts(client, &scheduled_bets_by_market_filters(market))
+}
+
+/// Fetches the open pages of `owner`'s portfolio, in page order.
+pub fn fetch_portfolio_pages<C: ProgramAccounts>(
+    client: &C,
+    owner: &Pubkey,
+) -> Result<Vec<(Pubkey, PortfolioPage)>, FetchError<C::Error>> {
+    let mut pages: Vec<(Pubkey, PortfolioPage)> =
+        fetch_accounts(client, &portfolio_pages_filters(owner))?;
+    pages.sort_by_key(|(_, page)| page.index);
+    Ok(pages)
+}
+
+pub fn fetch_stake_accounts<C: ProgramAccounts>(
+    client: &C,
+) -> Result<Vec<(Pubkey, StakeAccount)>, FetchError<C::Error>> {
+    fetch_accounts(client, &stake_accounts_filters())
+}
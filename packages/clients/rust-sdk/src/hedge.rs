@@ -0,0 +1,375 @@
+//! Hedge sizing: how much to bet on the other side of a position to lock in
+//! a result or cap what it can lose.
+//!
+//! A hedge changes the pools it's weighed against, so it's sized by
+//! simulating it with [`simulate_bet`] and settling the market both ways
+//! with the program's own payout methods. Fees, insurance, charity, locked
+//! odds and staker rebates all come out exactly as `claim` would pay them.
+//!
+//! The hedge has to come from a different wallet with no position in the
+//! market: the program refuses a bet on the other side of a wallet's own
+//! position.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use friends_bets::{BetSide, Market, MarketStatus, Position};
+
+use crate::instructions;
+use crate::simulate::simulate_bet;
+
+/// Hedge amounts either side of the sized one also tried, past any
+/// plateaus rounding leaves in the outcomes.
+const ROUNDING_SCAN: u64 = 8;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HedgeError {
+    #[error("the hedge must come from a wallet other than the position's")]
+    SameWallet,
+    #[error("the position has nothing staked")]
+    NothingToHedge,
+    #[error("no hedge keeps the loss to {0} or less")]
+    Unreachable(u64),
+    #[error(transparent)]
+    Program(#[from] anchor_lang::error::Error),
+}
+
+/// What to hedge for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HedgeGoal {
+    /// The best result that holds whichever side wins. Negative when the
+    /// position can only be hedged at a loss.
+    LockIn,
+    /// The smallest hedge after which the position and hedge together lose
+    /// at most `max_loss` if the position's side loses, keeping as much of
+    /// its upside as that allows.
+    CapLoss { max_loss: u64 },
+}
+
+/// Net result of a position and its hedge for each outcome: what both
+/// claims pay, less everything staked on them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HedgeOutcomes {
+    pub if_position_wins: i128,
+    pub if_hedge_wins: i128,
+}
+
+impl HedgeOutcomes {
+    /// The result the position and hedge are guaranteed.
+    pub fn worst(&self) -> i128 {
+        self.if_position_wins.min(self.if_hedge_wins)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hedge {
+    pub side: BetSide,
+    /// 0 when no hedge helps, or none is needed.
+    pub amount: u64,
+    /// Odds the hedge is placed at, passed as `place_bet`'s `min_odds_bps`
+    /// so it fails rather than land at worse.
+    pub odds_bps: u64,
+    pub outcomes: HedgeOutcomes,
+}
+
+/// Net results of `position` and a bet of `amount` from `hedger` on the
+/// other side, placed at `discount_bps` (0 for non-stakers).
+pub fn hedge_outcomes(
+    market: &Market,
+    position: &Position,
+    hedger: Pubkey,
+    amount: u64,
+    discount_bps: u16,
+) -> Result<HedgeOutcomes, HedgeError> {
+    Ok(simulate_hedge(market, position, hedger, amount, discount_bps)?.outcomes)
+}
+
+/// Sizes the hedge from `hedger` that reaches `goal` for `position`,
+/// placed at `discount_bps`.
+pub fn plan_hedge(
+    market: &Market,
+    position: &Position,
+    hedger: Pubkey,
+    goal: HedgeGoal,
+    discount_bps: u16,
+) -> Result<Hedge, HedgeError> {
+    if hedger == position.owner {
+        return Err(HedgeError::SameWallet);
+    }
+    if position.amount == 0 {
+        return Err(HedgeError::NothingToHedge);
+    }
+    let side = position.side.other();
+    let hedge = |amount| simulate_hedge(market, position, hedger, amount, discount_bps);
+    let limit = market
+        .bet_room(side)?
+        .unwrap_or(u64::MAX)
+        .min(u64::MAX - market.pool_total()?);
+
+    // The hedge's payout grows faster than the position's as it grows, so
+    // past the smallest hedge paying at least as much as the position,
+    // more only lowers both outcomes
+    let mut upper = position.amount.min(limit);
+    loop {
+        let outcomes = hedge(upper)?.outcomes;
+        if outcomes.if_hedge_wins >= outcomes.if_position_wins || upper == limit {
+            break;
+        }
+        upper = upper.saturating_mul(2).min(limit);
+    }
+
+    // Below that, the worst outcome is the position losing, which rises
+    // to a peak and falls again as fees eat into a larger hedge. Rounding
+    // flattens it into steps, so ties move right, towards the usual peak at
+    // `upper`, and the neighbourhood of the search's end is checked too
+    let (mut low, mut high) = (0, upper);
+    while high - low > 2 {
+        let third = (high - low) / 3;
+        if hedge(low + third)?.outcomes.worst() <= hedge(high - third)?.outcomes.worst() {
+            low += third + 1;
+        } else {
+            high -= third;
+        }
+    }
+    let (low, high) = (
+        low.saturating_sub(ROUNDING_SCAN),
+        high.saturating_add(ROUNDING_SCAN).min(upper),
+    );
+    let mut best = hedge(low)?;
+    for amount in low + 1..=high {
+        let candidate = hedge(amount)?;
+        if candidate.outcomes.worst() > best.outcomes.worst() {
+            best = candidate;
+        }
+    }
+
+    let HedgeGoal::CapLoss { max_loss } = goal else {
+        return Ok(best);
+    };
+    let floor = -i128::from(max_loss);
+    if best.outcomes.worst() < floor {
+        return Err(HedgeError::Unreachable(max_loss));
+    }
+    // Up to the peak, the outcome if the position loses only rises
+    let (mut low, mut high) = (0, best.amount);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if hedge(middle)?.outcomes.if_hedge_wins >= floor {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    hedge(low)
+}
+
+/// Hedges for each `(address, market, position)` in an open market, in the
+/// same order, skipping the rest.
+pub fn plan_hedges(
+    positions: &[(Pubkey, Market, Position)],
+    hedger: Pubkey,
+    goal: HedgeGoal,
+    discount_bps: u16,
+) -> Vec<(Pubkey, Result<Hedge, HedgeError>)> {
+    positions
+        .iter()
+        .filter(|(_, market, _)| market.status == MarketStatus::Open)
+        .map(|(address, market, position)| {
+            (
+                *address,
+                plan_hedge(market, position, hedger, goal, discount_bps),
+            )
+        })
+        .collect()
+}
+
+/// One `place_bet` from `hedger` per hedge worth placing, each failing if
+/// the odds have moved against it since it was planned.
+pub fn hedge_instructions(
+    hedger: &Pubkey,
+    hedges: &[(Pubkey, &Market, Hedge)],
+) -> Vec<Instruction> {
+    hedges
+        .iter()
+        .filter(|(_, _, hedge)| hedge.amount > 0)
+        .map(|(address, market, hedge)| {
+            instructions::place_bet_on(
+                hedger,
+                address,
+                market,
+                hedge.side,
+                hedge.amount,
+                hedge.odds_bps,
+            )
+        })
+        .collect()
+}
+
+fn simulate_hedge(
+    market: &Market,
+    position: &Position,
+    hedger: Pubkey,
+    amount: u64,
+    discount_bps: u16,
+) -> Result<Hedge, HedgeError> {
+    let side = position.side.other();
+    let (market, hedge, odds_bps) = if amount == 0 {
+        (market.clone(), None, 0)
+    } else {
+        let sim = simulate_bet(market, None, hedger, side, amount, discount_bps)?;
+        (sim.market, Some(sim.position), sim.quote.odds_bps)
+    };
+
+    let staked = i128::from(position.amount) + i128::from(amount);
+    let net = |outcome| -> Result<i128, HedgeError> {
+        let mut settled = market.clone();
+        settled.status = MarketStatus::Resolved;
+        settled.outcome = Some(outcome);
+        let mut paid = i128::from(settled.claim_total(position)?);
+        if let Some(hedge) = &hedge {
+            paid += i128::from(settled.claim_total(hedge)?);
+        }
+        Ok(paid - staked)
+    };
+    Ok(Hedge {
+        side,
+        amount,
+        odds_bps,
+        outcomes: HedgeOutcomes {
+            if_position_wins: net(position.side)?,
+            if_hedge_wins: net(side)?,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(fee_bps: u16) -> Market {
+        Market {
+            market_id: 1,
+            creator: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            vault_a: Pubkey::new_unique(),
+            vault_b: Pubkey::new_unique(),
+            fee_bps,
+            end_ts: 0,
+            resolve_deadline_ts: 0,
+            staked_a: 0,
+            staked_b: 0,
+            status: MarketStatus::Open,
+            outcome: None,
+            creator_fee_withdrawn: false,
+            bump: 0,
+            vault_a_bump: 0,
+            vault_b_bump: 0,
+            title: String::new(),
+            allow_cash_out: false,
+            cashout_haircut_bps: 0,
+            retained: 0,
+            lock_odds: false,
+            locked_payout_a: 0,
+            locked_payout_b: 0,
+            forked_from: None,
+            dependency: None,
+            open_positions: 0,
+            burn_fee: false,
+            fee_discounts: 0,
+            discounted_stake: 0,
+            compressed: false,
+            track_bettors: false,
+            bettor_count: 0,
+            bettor_pages: 0,
+            insurance_bps: 0,
+            insurance_collected: false,
+            compensated: 0,
+            charity: None,
+            charity_bps: 0,
+            charity_paid: false,
+            large_bet_threshold: 0,
+            track_activity: false,
+            cap_a: 0,
+            cap_b: 0,
+            max_ratio_bps: 0,
+            title_registered: false,
+            fee_splits: Vec::new(),
+            pools_changed_slot: 0,
+            scheduled_bets: 0,
+        }
+    }
+
+    fn bet(market: &mut Market, side: BetSide, amount: u64) -> Position {
+        let sim = simulate_bet(market, None, Pubkey::new_unique(), side, amount, 0).unwrap();
+        *market = sim.market;
+        sim.position
+    }
+
+    /// Alice's 100 on A, alongside 300 more on A and 600 on B, at a 5% fee.
+    fn alice() -> (Market, Position) {
+        let mut market = market(500);
+        let alice = bet(&mut market, BetSide::A, 100);
+        bet(&mut market, BetSide::A, 300);
+        bet(&mut market, BetSide::B, 600);
+        (market, alice)
+    }
+
+    #[test]
+    fn locking_in_evens_out_both_outcomes() {
+        let (market, alice) = alice();
+        let hedger = Pubkey::new_unique();
+        let unhedged = hedge_outcomes(&market, &alice, hedger, 0, 0).unwrap();
+        assert_eq!(
+            unhedged,
+            HedgeOutcomes {
+                if_position_wins: 137,
+                if_hedge_wins: -100,
+            }
+        );
+
+        // 200 on B makes both claims 285 of the 1140 left after the fee
+        let hedge = plan_hedge(&market, &alice, hedger, HedgeGoal::LockIn, 0).unwrap();
+        assert_eq!(hedge.side, BetSide::B);
+        assert_eq!(hedge.amount, 200);
+        assert_eq!(
+            hedge.outcomes,
+            HedgeOutcomes {
+                if_position_wins: -15,
+                if_hedge_wins: -15,
+            }
+        );
+    }
+
+    #[test]
+    fn capping_a_loss_takes_the_smallest_hedge() {
+        let (market, alice) = alice();
+        let hedger = Pubkey::new_unique();
+        let goal = HedgeGoal::CapLoss { max_loss: 50 };
+
+        let hedge = plan_hedge(&market, &alice, hedger, goal, 0).unwrap();
+        assert!(hedge.outcomes.if_hedge_wins >= -50);
+        assert!(hedge.outcomes.if_position_wins > -15);
+        let smaller = hedge_outcomes(&market, &alice, hedger, hedge.amount - 1, 0).unwrap();
+        assert!(smaller.if_hedge_wins < -50);
+
+        // Fees take 15 whatever the hedge
+        let goal = HedgeGoal::CapLoss { max_loss: 10 };
+        assert!(matches!(
+            plan_hedge(&market, &alice, hedger, goal, 0),
+            Err(HedgeError::Unreachable(10))
+        ));
+        let goal = HedgeGoal::CapLoss { max_loss: 100 };
+        assert_eq!(
+            plan_hedge(&market, &alice, hedger, goal, 0).unwrap().amount,
+            0
+        );
+    }
+
+    #[test]
+    fn hedges_come_from_another_wallet() {
+        let (market, alice) = alice();
+        assert!(matches!(
+            plan_hedge(&market, &alice, alice.owner, HedgeGoal::LockIn, 0),
+            Err(HedgeError::SameWallet)
+        ));
+    }
+}
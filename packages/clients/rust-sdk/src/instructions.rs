@@ -0,0 +1,1137 @@
+//! Instruction builders for every program instruction.
+//!
+//! Token accounts for users and creators are assumed to be associated token
+//! accounts. Callers with other layouts can fill the re-exported
+//! [`friends_bets::accounts`] structs themselves.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use anchor_spl::{associated_token, token};
+use friends_bets::compression::{Node, MAX_DEPTH};
+use friends_bets::portfolio::Portfolio;
+use friends_bets::schedule::ScheduledBet;
+use friends_bets::title::title_hash;
+use friends_bets::{
+    accounts, instruction, BatchBet, BetSide, ConfigUpdate, FeeSplit, FeeTier, Market,
+    MarketOptions, MarketPolicy,
+};
+
+use crate::pda::{
+    activity_address, bettor_page_address, config_address, current_bettor_page_address,
+    current_portfolio_page_address, insurance_vault_address, market_address, market_title_address,
+    portfolio_address, portfolio_page_address, position_address, position_tree_address,
+    program_data_address, scheduled_bet_address, scheduled_escrow_address, stake_address,
+    stake_vault_address, token_account_address, vault_address,
+};
+use crate::PROGRAM_ID;
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Parameters shared by `initialize_market`, `clone_market` and `fork_market`
+/// for the market being created.
+#[derive(Clone, Debug)]
+pub struct NewMarket {
+    pub creator: Pubkey,
+    pub mint: Pubkey,
+    pub market_id: u64,
+}
+
+impl NewMarket {
+    pub fn market(&self) -> Pubkey {
+        market_address(&self.creator, self.market_id).0
+    }
+
+    pub fn vault(&self, side: BetSide) -> Pubkey {
+        vault_address(&self.market(), side).0
+    }
+}
+
+/// Passes the creator's stake account to a market-creating instruction, so
+/// the fee ceiling of their fee tier applies.
+pub fn with_creator_stake(mut ix: Instruction, creator: &Pubkey) -> Instruction {
+    ix.accounts
+        .push(AccountMeta::new_readonly(stake_address(creator).0, false));
+    ix
+}
+
+/// Passes the config and the bettor's stake account to a betting
+/// instruction, so the bet is placed at their fee discount. Add it after
+/// any bettor page.
+pub fn with_bettor_stake(mut ix: Instruction, user: &Pubkey) -> Instruction {
+    ix.accounts.extend([
+        AccountMeta::new_readonly(config_address().0, false),
+        AccountMeta::new_readonly(stake_address(user).0, false),
+    ]);
+    ix
+}
+
+/// Records a `place_bet`, `place_bet_init_ata` or `claim` in the user's
+/// `portfolio`, passing it and its current page in place of the empty
+/// portfolio accounts the builders leave.
+pub fn with_portfolio(mut ix: Instruction, portfolio: &Portfolio) -> Instruction {
+    let accounts = [
+        portfolio_address(&portfolio.owner).0,
+        current_portfolio_page_address(portfolio),
+    ];
+    // Anchor passes a missing optional account as the program id
+    let empty = ix
+        .accounts
+        .iter_mut()
+        .filter(|meta| meta.pubkey == PROGRAM_ID);
+    for (meta, address) in empty.zip(accounts) {
+        *meta = AccountMeta::new(address, false);
+    }
+    ix
+}
+
+pub fn initialize_market(
+    new_market: &NewMarket,
+    fee_bps: u16,
+    end_ts: i64,
+    resolve_deadline_ts: i64,
+    title: String,
+    options: MarketOptions,
+) -> Instruction {
+    build(
+        accounts::InitializeMarket {
+            creator: new_market.creator,
+            config: config_address().0,
+            market: new_market.market(),
+            mint: new_market.mint,
+            vault_a: new_market.vault(BetSide::A),
+            vault_b: new_market.vault(BetSide::B),
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::InitializeMarket {
+            market_id: new_market.market_id,
+            fee_bps,
+            end_ts,
+            resolve_deadline_ts,
+            title,
+            options,
+        },
+    )
+}
+
+/// Builds `initialize_market_with_bet`, the creator betting `amount` on
+/// `side` from their associated token account.
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_market_with_bet(
+    new_market: &NewMarket,
+    fee_bps: u16,
+    end_ts: i64,
+    resolve_deadline_ts: i64,
+    title: String,
+    options: MarketOptions,
+    side: BetSide,
+    amount: u64,
+) -> Instruction {
+    let market = new_market.market();
+    build(
+        accounts::InitializeMarketWithBet {
+            creator: new_market.creator,
+            config: config_address().0,
+            market,
+            mint: new_market.mint,
+            vault_a: new_market.vault(BetSide::A),
+            vault_b: new_market.vault(BetSide::B),
+            position: position_address(&market, &new_market.creator).0,
+            creator_token_account: token_account_address(&new_market.creator, &new_market.mint),
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::InitializeMarketWithBet {
+            market_id: new_market.market_id,
+            fee_bps,
+            end_ts,
+            resolve_deadline_ts,
+            title,
+            options,
+            side,
+            amount,
+        },
+    )
+}
+
+pub fn clone_market(
+    new_market: &NewMarket,
+    source: &Pubkey,
+    new_end_ts: i64,
+    new_resolve_deadline_ts: i64,
+) -> Instruction {
+    build(
+        accounts::CloneMarket {
+            creator: new_market.creator,
+            config: config_address().0,
+            source: *source,
+            market: new_market.market(),
+            mint: new_market.mint,
+            vault_a: new_market.vault(BetSide::A),
+            vault_b: new_market.vault(BetSide::B),
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::CloneMarket {
+            market_id: new_market.market_id,
+            new_end_ts,
+            new_resolve_deadline_ts,
+        },
+    )
+}
+
+/// Builds `fork_market`, which the config's `admin` signs along with the
+/// creator.
+pub fn fork_market(
+    new_market: &NewMarket,
+    admin: &Pubkey,
+    original: &Pubkey,
+    end_ts: i64,
+    resolve_deadline_ts: i64,
+    title: String,
+) -> Instruction {
+    build(
+        accounts::ForkMarket {
+            creator: new_market.creator,
+            admin: *admin,
+            config: config_address().0,
+            original: *original,
+            market: new_market.market(),
+            mint: new_market.mint,
+            vault_a: new_market.vault(BetSide::A),
+            vault_b: new_market.vault(BetSide::B),
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::ForkMarket {
+            market_id: new_market.market_id,
+            end_ts,
+            resolve_deadline_ts,
+            title,
+        },
+    )
+}
+
+pub fn place_bet(
+    user: &Pubkey,
+    market: &Pubkey,
+    mint: &Pubkey,
+    side: BetSide,
+    amount: u64,
+    min_odds_bps: u64,
+) -> Instruction {
+    build(
+        accounts::PlaceBet {
+            user: *user,
+            market: *market,
+            position: position_address(market, user).0,
+            user_token_account: token_account_address(user, mint),
+            vault: vault_address(market, side).0,
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            portfolio: None,
+            portfolio_page: None,
+        },
+        instruction::PlaceBet {
+            side,
+            amount,
+            min_odds_bps,
+        },
+    )
+}
+
+/// Builds `place_bet` on `market`, fetched from `address`, passing its
+/// current bettor page when it tracks bettors and its activity account
+/// when it keeps one. A bet landing first can move
+/// the market on to a new page, failing this one with `InvalidBettorPage`;
+/// refetch the market and rebuild to retry.
+pub fn place_bet_on(
+    user: &Pubkey,
+    address: &Pubkey,
+    market: &Market,
+    side: BetSide,
+    amount: u64,
+    min_odds_bps: u64,
+) -> Instruction {
+    with_tracking_accounts(
+        place_bet(user, address, &market.mint, side, amount, min_odds_bps),
+        address,
+        market,
+    )
+}
+
+/// Builds `place_bet_init_ata`, which creates the user's associated token
+/// account for `mint` if they don't have one yet.
+pub fn place_bet_init_ata(
+    user: &Pubkey,
+    market: &Pubkey,
+    mint: &Pubkey,
+    side: BetSide,
+    amount: u64,
+    min_odds_bps: u64,
+) -> Instruction {
+    build(
+        accounts::PlaceBetInitAta {
+            user: *user,
+            market: *market,
+            mint: *mint,
+            position: position_address(market, user).0,
+            user_token_account: token_account_address(user, mint),
+            vault: vault_address(market, side).0,
+            token_program: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            portfolio: None,
+            portfolio_page: None,
+        },
+        instruction::PlaceBetInitAta {
+            side,
+            amount,
+            min_odds_bps,
+        },
+    )
+}
+
+/// [`place_bet_on`] for `place_bet_init_ata`.
+pub fn place_bet_init_ata_on(
+    user: &Pubkey,
+    address: &Pubkey,
+    market: &Market,
+    side: BetSide,
+    amount: u64,
+    min_odds_bps: u64,
+) -> Instruction {
+    with_tracking_accounts(
+        place_bet_init_ata(user, address, &market.mint, side, amount, min_odds_bps),
+        address,
+        market,
+    )
+}
+
+fn with_tracking_accounts(mut ix: Instruction, address: &Pubkey, market: &Market) -> Instruction {
+    if market.track_bettors {
+        ix.accounts.push(AccountMeta::new(
+            current_bettor_page_address(address, market),
+            false,
+        ));
+    }
+    if market.track_activity {
+        ix.accounts
+            .push(AccountMeta::new(activity_address(address).0, false));
+    }
+    ix
+}
+
+/// Builds `place_bets_batch`. Each bet is paired with its market's mint so
+/// the user's token account can be derived.
+pub fn place_bets_batch(user: &Pubkey, bets: &[(BatchBet, Pubkey)]) -> Instruction {
+    let mut ix = build(
+        accounts::PlaceBetsBatch {
+            user: *user,
+            token_program: token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::PlaceBetsBatch {
+            bets: bets.iter().map(|(bet, _)| *bet).collect(),
+        },
+    );
+    for (bet, mint) in bets {
+        ix.accounts.extend([
+            AccountMeta::new(bet.market, false),
+            AccountMeta::new(position_address(&bet.market, user).0, false),
+            AccountMeta::new(token_account_address(user, mint), false),
+            AccountMeta::new(vault_address(&bet.market, bet.side).0, false),
+        ]);
+    }
+    ix
+}
+
+pub fn enable_bettor_pages(creator: &Pubkey, market: &Pubkey) -> Instruction {
+    build(
+        accounts::EnableBettorPages {
+            creator: *creator,
+            market: *market,
+        },
+        instruction::EnableBettorPages {},
+    )
+}
+
+/// Builds `close_bettor_page` for page `index`, refunding `payer`, the
+/// page's `payer`.
+pub fn close_bettor_page(market: &Pubkey, index: u32, payer: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseBettorPage {
+            market: *market,
+            bettor_page: bettor_page_address(market, index).0,
+            payer: *payer,
+        },
+        instruction::CloseBettorPage {},
+    )
+}
+
+pub fn enable_activity_stats(creator: &Pubkey, market: &Pubkey) -> Instruction {
+    build(
+        accounts::EnableActivityStats {
+            creator: *creator,
+            market: *market,
+            activity: activity_address(market).0,
+            system_program: system_program::ID,
+        },
+        instruction::EnableActivityStats {},
+    )
+}
+
+/// Builds `close_activity_stats`, refunding `payer`, the activity
+/// account's `payer`.
+pub fn close_activity_stats(market: &Pubkey, payer: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseActivityStats {
+            market: *market,
+            activity: activity_address(market).0,
+            payer: *payer,
+        },
+        instruction::CloseActivityStats {},
+    )
+}
+
+/// Builds `register_market_title`, registering the market under `title`,
+/// its title, and `nonce`.
+pub fn register_market_title(
+    creator: &Pubkey,
+    market: &Pubkey,
+    title: &str,
+    nonce: u16,
+) -> Instruction {
+    build(
+        accounts::RegisterMarketTitle {
+            creator: *creator,
+            market: *market,
+            market_title: market_title_address(title, nonce).0,
+            system_program: system_program::ID,
+        },
+        instruction::RegisterMarketTitle {
+            title_hash: title_hash(title),
+            nonce,
+        },
+    )
+}
+
+/// Builds `close_market_title` for the registration at `market_title`,
+/// refunding `creator`, the market's creator.
+pub fn close_market_title(market: &Pubkey, market_title: &Pubkey, creator: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseMarketTitle {
+            market: *market,
+            market_title: *market_title,
+            creator: *creator,
+        },
+        instruction::CloseMarketTitle {},
+    )
+}
+
+/// Builds `schedule_bet`, escrowing `amount` from the owner's associated
+/// token account for `mint` until the bet is placed or cancelled.
+#[allow(clippy::too_many_arguments)]
+pub fn schedule_bet(
+    owner: &Pubkey,
+    market: &Pubkey,
+    mint: &Pubkey,
+    id: u64,
+    side: BetSide,
+    amount: u64,
+    min_odds_bps: u64,
+    execute_after: i64,
+    expiry: i64,
+) -> Instruction {
+    let scheduled_bet = scheduled_bet_address(market, owner, id).0;
+    build(
+        accounts::ScheduleBet {
+            owner: *owner,
+            market: *market,
+            mint: *mint,
+            scheduled_bet,
+            escrow: scheduled_escrow_address(&scheduled_bet).0,
+            owner_token_account: token_account_address(owner, mint),
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::ScheduleBet {
+            id,
+            side,
+            amount,
+            min_odds_bps,
+            execute_after,
+            expiry,
+        },
+    )
+}
+
+/// Builds `execute_scheduled_bet` for `scheduled`, fetched from `address`,
+/// on `market`, passing the market's current bettor page and activity
+/// account as [`place_bet_on`] does. Add the owner's stake with
+/// [`with_bettor_stake`] for their fee discount.
+pub fn execute_scheduled_bet(
+    executor: &Pubkey,
+    address: &Pubkey,
+    scheduled: &ScheduledBet,
+    market: &Market,
+) -> Instruction {
+    let ix = build(
+        accounts::ExecuteScheduledBet {
+            executor: *executor,
+            market: scheduled.market,
+            scheduled_bet: *address,
+            owner: scheduled.owner,
+            position: position_address(&scheduled.market, &scheduled.owner).0,
+            escrow: scheduled_escrow_address(address).0,
+            vault: vault_address(&scheduled.market, scheduled.side).0,
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::ExecuteScheduledBet {},
+    );
+    with_tracking_accounts(ix, &scheduled.market, market)
+}
+
+/// Builds `cancel_scheduled_bet` for `scheduled`, fetched from `address`,
+/// returning the stake to the owner's associated token account for `mint`.
+pub fn cancel_scheduled_bet(
+    caller: &Pubkey,
+    address: &Pubkey,
+    scheduled: &ScheduledBet,
+    mint: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::CancelScheduledBet {
+            caller: *caller,
+            market: scheduled.market,
+            scheduled_bet: *address,
+            owner: scheduled.owner,
+            escrow: scheduled_escrow_address(address).0,
+            owner_token_account: token_account_address(&scheduled.owner, mint),
+            token_program: token::ID,
+        },
+        instruction::CancelScheduledBet {},
+    )
+}
+
+pub fn enable_compressed_positions(creator: &Pubkey, market: &Pubkey) -> Instruction {
+    build(
+        accounts::EnableCompressedPositions {
+            creator: *creator,
+            market: *market,
+            position_tree: position_tree_address(market).0,
+            system_program: system_program::ID,
+        },
+        instruction::EnableCompressedPositions {},
+    )
+}
+
+pub fn place_compressed_bet(
+    user: &Pubkey,
+    market: &Pubkey,
+    mint: &Pubkey,
+    side: BetSide,
+    amount: u64,
+    min_odds_bps: u64,
+) -> Instruction {
+    build(
+        accounts::PlaceCompressedBet {
+            user: *user,
+            market: *market,
+            position_tree: position_tree_address(market).0,
+            user_token_account: token_account_address(user, mint),
+            vault: vault_address(market, side).0,
+            token_program: token::ID,
+        },
+        instruction::PlaceCompressedBet {
+            side,
+            amount,
+            min_odds_bps,
+        },
+    )
+}
+
+pub fn cash_out(user: &Pubkey, market: &Pubkey, mint: &Pubkey) -> Instruction {
+    build(
+        accounts::CashOut {
+            user: *user,
+            market: *market,
+            position: position_address(market, user).0,
+            user_token_account: token_account_address(user, mint),
+            vault_a: vault_address(market, BetSide::A).0,
+            vault_b: vault_address(market, BetSide::B).0,
+            token_program: token::ID,
+        },
+        instruction::CashOut {},
+    )
+}
+
+/// Builds `set_cashout_order`; a `min_value` of zero clears the order.
+pub fn set_cashout_order(user: &Pubkey, market: &Pubkey, min_value: u64) -> Instruction {
+    build(
+        accounts::SetCashOutOrder {
+            user: *user,
+            market: *market,
+            position: position_address(market, user).0,
+        },
+        instruction::SetCashoutOrder { min_value },
+    )
+}
+
+/// Builds `execute_cashout` for `owner`'s position, tipping `executor`'s
+/// token account.
+pub fn execute_cashout(
+    executor: &Pubkey,
+    market: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::ExecuteCashOut {
+            executor: *executor,
+            market: *market,
+            owner: *owner,
+            position: position_address(market, owner).0,
+            owner_token_account: token_account_address(owner, mint),
+            executor_token_account: token_account_address(executor, mint),
+            vault_a: vault_address(market, BetSide::A).0,
+            vault_b: vault_address(market, BetSide::B).0,
+            token_program: token::ID,
+        },
+        instruction::ExecuteCashout {},
+    )
+}
+
+pub fn close_betting(market: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseBetting { market: *market },
+        instruction::CloseBetting {},
+    )
+}
+
+/// Builds `resolve`. Conditional markets must pass their parent market.
+pub fn resolve(
+    creator: &Pubkey,
+    market: &Pubkey,
+    outcome: BetSide,
+    parent: Option<&Pubkey>,
+) -> Instruction {
+    let mut ix = build(
+        accounts::Resolve {
+            creator: *creator,
+            market: *market,
+        },
+        instruction::Resolve { outcome },
+    );
+    if let Some(parent) = parent {
+        ix.accounts.push(AccountMeta::new_readonly(*parent, false));
+    }
+    ix
+}
+
+/// Builds `close_and_resolve`. Conditional markets must pass their parent market.
+pub fn close_and_resolve(
+    creator: &Pubkey,
+    market: &Pubkey,
+    outcome: BetSide,
+    parent: Option<&Pubkey>,
+) -> Instruction {
+    let mut ix = build(
+        accounts::Resolve {
+            creator: *creator,
+            market: *market,
+        },
+        instruction::CloseAndResolve { outcome },
+    );
+    if let Some(parent) = parent {
+        ix.accounts.push(AccountMeta::new_readonly(*parent, false));
+    }
+    ix
+}
+
+pub fn set_dependency(
+    creator: &Pubkey,
+    market: &Pubkey,
+    parent: &Pubkey,
+    outcome: BetSide,
+) -> Instruction {
+    build(
+        accounts::SetDependency {
+            creator: *creator,
+            market: *market,
+            parent: *parent,
+        },
+        instruction::SetDependency { outcome },
+    )
+}
+
+pub fn void_dependent(market: &Pubkey, parent: &Pubkey) -> Instruction {
+    build(
+        accounts::VoidDependent {
+            market: *market,
+            parent: *parent,
+        },
+        instruction::VoidDependent {},
+    )
+}
+
+pub fn cancel_expired(market: &Pubkey) -> Instruction {
+    build(
+        accounts::CancelExpired { market: *market },
+        instruction::CancelExpired {},
+    )
+}
+
+pub fn claim(user: &Pubkey, market: &Pubkey, mint: &Pubkey) -> Instruction {
+    build(
+        accounts::Claim {
+            user: *user,
+            market: *market,
+            position: position_address(market, user).0,
+            user_token_account: token_account_address(user, mint),
+            vault_a: vault_address(market, BetSide::A).0,
+            vault_b: vault_address(market, BetSide::B).0,
+            token_program: token::ID,
+            system_program: system_program::ID,
+            portfolio: None,
+            portfolio_page: None,
+        },
+        instruction::Claim {},
+    )
+}
+
+pub fn open_portfolio(user: &Pubkey) -> Instruction {
+    build(
+        accounts::OpenPortfolio {
+            user: *user,
+            portfolio: portfolio_address(user).0,
+            system_program: system_program::ID,
+        },
+        instruction::OpenPortfolio {},
+    )
+}
+
+pub fn close_portfolio_page(user: &Pubkey, index: u32) -> Instruction {
+    build(
+        accounts::ClosePortfolioPage {
+            user: *user,
+            portfolio: portfolio_address(user).0,
+            portfolio_page: portfolio_page_address(user, index).0,
+        },
+        instruction::ClosePortfolioPage { index },
+    )
+}
+
+/// Builds `close_portfolio`, closing the current page along with it when
+/// the portfolio has one open.
+pub fn close_portfolio(portfolio: &Portfolio) -> Instruction {
+    build(
+        accounts::ClosePortfolio {
+            user: portfolio.owner,
+            portfolio: portfolio_address(&portfolio.owner).0,
+            portfolio_page: (portfolio.pages > 0)
+                .then(|| current_portfolio_page_address(portfolio)),
+        },
+        instruction::ClosePortfolio {},
+    )
+}
+
+/// Builds `claim_compressed` for the bet at `leaf_index`, with a `proof`
+/// against `root` such as [`CompressedPositions`] gives.
+///
+/// [`CompressedPositions`]: crate::compressed::CompressedPositions
+#[allow(clippy::too_many_arguments)]
+pub fn claim_compressed(
+    user: &Pubkey,
+    market: &Pubkey,
+    mint: &Pubkey,
+    root: Node,
+    leaf_index: u32,
+    side: BetSide,
+    amount: u64,
+    locked_payout: u64,
+    discounted_stake: u64,
+    proof: &[Node; MAX_DEPTH],
+) -> Instruction {
+    build(
+        accounts::ClaimCompressed {
+            user: *user,
+            market: *market,
+            position_tree: position_tree_address(market).0,
+            user_token_account: token_account_address(user, mint),
+            vault_a: vault_address(market, BetSide::A).0,
+            vault_b: vault_address(market, BetSide::B).0,
+            token_program: token::ID,
+        },
+        instruction::ClaimCompressed {
+            root,
+            leaf_index,
+            side,
+            amount,
+            locked_payout,
+            discounted_stake,
+            proof: proof.to_vec(),
+        },
+    )
+}
+
+/// Builds `distribute` for the positions of `owners`, paying their
+/// associated token accounts.
+pub fn distribute(market: &Pubkey, mint: &Pubkey, owners: &[Pubkey]) -> Instruction {
+    let mut ix = build(
+        accounts::Distribute {
+            market: *market,
+            vault_a: vault_address(market, BetSide::A).0,
+            vault_b: vault_address(market, BetSide::B).0,
+            token_program: token::ID,
+        },
+        instruction::Distribute {},
+    );
+    for owner in owners {
+        ix.accounts.extend([
+            AccountMeta::new(position_address(market, owner).0, false),
+            AccountMeta::new(token_account_address(owner, mint), false),
+        ]);
+    }
+    ix
+}
+
+pub fn withdraw_creator_fee(creator: &Pubkey, market: &Pubkey, mint: &Pubkey) -> Instruction {
+    build(
+        accounts::WithdrawCreatorFee {
+            creator: *creator,
+            market: *market,
+            creator_token_account: token_account_address(creator, mint),
+            vault_a: vault_address(market, BetSide::A).0,
+            vault_b: vault_address(market, BetSide::B).0,
+            token_program: token::ID,
+        },
+        instruction::WithdrawCreatorFee {},
+    )
+}
+
+pub fn set_fee_splits(creator: &Pubkey, market: &Pubkey, splits: Vec<FeeSplit>) -> Instruction {
+    build(
+        accounts::SetFeeSplits {
+            creator: *creator,
+            market: *market,
+        },
+        instruction::SetFeeSplits { splits },
+    )
+}
+
+/// Builds `distribute_fees` on `market`, fetched from `address`, paying each
+/// fee split to its recipient's associated token account and the rest to the
+/// creator's.
+pub fn distribute_fees(address: &Pubkey, market: &Market) -> Instruction {
+    let mut ix = build(
+        accounts::DistributeFees {
+            market: *address,
+            creator_token_account: token_account_address(&market.creator, &market.mint),
+            vault_a: vault_address(address, BetSide::A).0,
+            vault_b: vault_address(address, BetSide::B).0,
+            token_program: token::ID,
+        },
+        instruction::DistributeFees {},
+    );
+    ix.accounts.extend(market.fee_splits.iter().map(|split| {
+        AccountMeta::new(token_account_address(&split.recipient, &market.mint), false)
+    }));
+    ix
+}
+
+/// Builds whichever of `withdraw_creator_fee` and `distribute_fees` pays
+/// out the fee of `market`, fetched from `address`, for its creator.
+pub fn pay_creator_fee(creator: &Pubkey, address: &Pubkey, market: &Market) -> Instruction {
+    if market.fee_splits.is_empty() {
+        withdraw_creator_fee(creator, address, &market.mint)
+    } else {
+        distribute_fees(address, market)
+    }
+}
+
+pub fn compact_market(creator: &Pubkey, market: &Pubkey) -> Instruction {
+    build(
+        accounts::CompactMarket {
+            creator: *creator,
+            market: *market,
+        },
+        instruction::CompactMarket {},
+    )
+}
+
+pub fn burn_fee(market: &Pubkey, mint: &Pubkey) -> Instruction {
+    build(
+        accounts::BurnFee {
+            market: *market,
+            mint: *mint,
+            vault_a: vault_address(market, BetSide::A).0,
+            vault_b: vault_address(market, BetSide::B).0,
+            token_program: token::ID,
+        },
+        instruction::BurnFee {},
+    )
+}
+
+/// Builds `set_charity`, sending `charity_bps` of the pool to the token
+/// account `charity`; `None` with a cut of 0 drops the charity.
+pub fn set_charity(
+    creator: &Pubkey,
+    market: &Pubkey,
+    charity: Option<Pubkey>,
+    charity_bps: u16,
+) -> Instruction {
+    build(
+        accounts::SetCharity {
+            creator: *creator,
+            market: *market,
+            charity_token_account: charity,
+        },
+        instruction::SetCharity { charity_bps },
+    )
+}
+
+pub fn set_large_bet_threshold(creator: &Pubkey, market: &Pubkey, threshold: u64) -> Instruction {
+    build(
+        accounts::SetLargeBetThreshold {
+            creator: *creator,
+            market: *market,
+        },
+        instruction::SetLargeBetThreshold { threshold },
+    )
+}
+
+/// Builds `set_stake_caps`; 0 leaves a limit off.
+pub fn set_stake_caps(
+    creator: &Pubkey,
+    market: &Pubkey,
+    cap_a: u64,
+    cap_b: u64,
+    max_ratio_bps: u32,
+) -> Instruction {
+    build(
+        accounts::SetStakeCaps {
+            creator: *creator,
+            market: *market,
+        },
+        instruction::SetStakeCaps {
+            cap_a,
+            cap_b,
+            max_ratio_bps,
+        },
+    )
+}
+
+/// Builds `donate_to_charity`, paying the charity cut to the market's
+/// charity token account `charity`.
+pub fn donate_to_charity(market: &Pubkey, charity: &Pubkey) -> Instruction {
+    build(
+        accounts::DonateToCharity {
+            market: *market,
+            vault_a: vault_address(market, BetSide::A).0,
+            vault_b: vault_address(market, BetSide::B).0,
+            charity_token_account: *charity,
+            token_program: token::ID,
+        },
+        instruction::DonateToCharity {},
+    )
+}
+
+pub fn collect_insurance(payer: &Pubkey, market: &Pubkey, mint: &Pubkey) -> Instruction {
+    build(
+        accounts::CollectInsurance {
+            payer: *payer,
+            config: config_address().0,
+            market: *market,
+            mint: *mint,
+            vault_a: vault_address(market, BetSide::A).0,
+            vault_b: vault_address(market, BetSide::B).0,
+            insurance_vault: insurance_vault_address(mint).0,
+            token_program: token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::CollectInsurance {},
+    )
+}
+
+/// Builds `compensate`, paying `amount` of insurance to `owner` towards
+/// the stake of their position in `market`.
+pub fn compensate(
+    admin: &Pubkey,
+    market: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    build(
+        accounts::Compensate {
+            admin: *admin,
+            config: config_address().0,
+            market: *market,
+            insurance_vault: insurance_vault_address(mint).0,
+            position: position_address(market, owner).0,
+            recipient_token_account: token_account_address(owner, mint),
+            token_program: token::ID,
+        },
+        instruction::Compensate { amount },
+    )
+}
+
+/// Builds `gc_market`, closing the positions of `owners` along with the market.
+pub fn gc_market(
+    caller: &Pubkey,
+    market: &Pubkey,
+    creator: &Pubkey,
+    mint: &Pubkey,
+    owners: &[Pubkey],
+) -> Instruction {
+    let mut ix = build(
+        accounts::GcMarket {
+            caller: *caller,
+            market: *market,
+            creator: *creator,
+            creator_token_account: token_account_address(creator, mint),
+            vault_a: vault_address(market, BetSide::A).0,
+            vault_b: vault_address(market, BetSide::B).0,
+            token_program: token::ID,
+        },
+        instruction::GcMarket {},
+    );
+    ix.accounts.extend(
+        owners
+            .iter()
+            .map(|owner| AccountMeta::new(position_address(market, owner).0, false)),
+    );
+    ix
+}
+
+/// Builds `gc_market` for a compressed market, closing its position tree
+/// along with it.
+pub fn gc_compressed_market(
+    caller: &Pubkey,
+    market: &Pubkey,
+    creator: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let mut ix = gc_market(caller, market, creator, mint, &[]);
+    ix.accounts
+        .push(AccountMeta::new(position_tree_address(market).0, false));
+    ix
+}
+
+/// Builds `initialize_config`, which `admin` can only send as the
+/// program's upgrade authority.
+pub fn initialize_config(
+    admin: &Pubkey,
+    stake_mint: &Pubkey,
+    unbonding_secs: i64,
+    fee_tiers: Vec<FeeTier>,
+    policy: MarketPolicy,
+    update_delay_secs: i64,
+) -> Instruction {
+    build(
+        accounts::InitializeConfig {
+            admin: *admin,
+            config: config_address().0,
+            stake_mint: *stake_mint,
+            stake_vault: stake_vault_address().0,
+            program: PROGRAM_ID,
+            program_data: program_data_address().0,
+            token_program: token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::InitializeConfig {
+            unbonding_secs,
+            fee_tiers,
+            policy,
+            update_delay_secs,
+        },
+    )
+}
+
+pub fn propose_config_update(admin: &Pubkey, update: ConfigUpdate) -> Instruction {
+    build(
+        accounts::AdminConfig {
+            admin: *admin,
+            config: config_address().0,
+        },
+        instruction::ProposeConfigUpdate { update },
+    )
+}
+
+pub fn apply_config_update() -> Instruction {
+    build(
+        accounts::ApplyConfigUpdate {
+            config: config_address().0,
+        },
+        instruction::ApplyConfigUpdate {},
+    )
+}
+
+pub fn cancel_config_update(admin: &Pubkey) -> Instruction {
+    build(
+        accounts::AdminConfig {
+            admin: *admin,
+            config: config_address().0,
+        },
+        instruction::CancelConfigUpdate {},
+    )
+}
+
+pub fn stake(user: &Pubkey, stake_mint: &Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::Stake {
+            user: *user,
+            config: config_address().0,
+            stake_account: stake_address(user).0,
+            user_token_account: token_account_address(user, stake_mint),
+            stake_vault: stake_vault_address().0,
+            token_program: token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::Stake { amount },
+    )
+}
+
+pub fn unstake(user: &Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::Unstake {
+            user: *user,
+            config: config_address().0,
+            stake_account: stake_address(user).0,
+        },
+        instruction::Unstake { amount },
+    )
+}
+
+pub fn withdraw_unstaked(user: &Pubkey, stake_mint: &Pubkey) -> Instruction {
+    build(
+        accounts::WithdrawUnstaked {
+            user: *user,
+            config: config_address().0,
+            stake_account: stake_address(user).0,
+            user_token_account: token_account_address(user, stake_mint),
+            stake_vault: stake_vault_address().0,
+            token_program: token::ID,
+        },
+        instruction::WithdrawUnstaked {},
+    )
+}
@@ -0,0 +1,139 @@
+//! Solana keypair files and ed25519 signing, through `ed25519-dalek`.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use anchor_lang::prelude::Pubkey;
+use ed25519_dalek::{Signer, SigningKey};
+use rand_core::OsRng;
+use solana_signature::Signature;
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeypairError {
+    #[error("reading keypair {}", .0.display())]
+    Read(PathBuf, #[source] io::Error),
+    #[error("parsing keypair {}", .0.display())]
+    Parse(PathBuf, #[source] serde_json::Error),
+    #[error("keypair {} should hold 64 bytes", .0.display())]
+    Length(PathBuf),
+    #[error("keypair {} has a mismatched public key", .0.display())]
+    Mismatch(PathBuf),
+    #[error("writing keypair {}", .0.display())]
+    Write(PathBuf, #[source] io::Error),
+}
+
+/// A signing key, wiped from memory when dropped.
+pub struct Keypair {
+    key: SigningKey,
+}
+
+impl Keypair {
+    /// Reads a `solana-keygen` JSON file: 64 bytes, secret then public key.
+    pub fn read(path: &Path) -> Result<Self, KeypairError> {
+        let json = fs::read_to_string(path).map_err(|err| KeypairError::Read(path.into(), err))?;
+        let bytes: Vec<u8> =
+            serde_json::from_str(&json).map_err(|err| KeypairError::Parse(path.into(), err))?;
+        let bytes = <[u8; 64]>::try_from(bytes).map_err(|_| KeypairError::Length(path.into()))?;
+        let key = SigningKey::from_keypair_bytes(&bytes)
+            .map_err(|_| KeypairError::Mismatch(path.into()))?;
+        Ok(Self { key })
+    }
+
+    /// A fresh keypair from the OS random source.
+    pub fn generate() -> Self {
+        Self {
+            key: SigningKey::generate(&mut OsRng),
+        }
+    }
+
+    /// Writes a `solana-keygen` JSON file that only its owner can read, as
+    /// `solana-keygen` does, refusing to replace an existing file.
+    pub fn write(&self, path: &Path) -> Result<(), KeypairError> {
+        let json =
+            serde_json::to_string(&self.key.to_keypair_bytes().to_vec()).expect("bytes serialize");
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        options
+            .open(path)
+            .and_then(|mut file| file.write_all(json.as_bytes()))
+            .map_err(|err| KeypairError::Write(path.into(), err))
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        Pubkey::new_from_array(self.key.verifying_key().to_bytes())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature::from(self.key.sign(message).to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // RFC 8032, section 7.1, TEST 2
+    #[test]
+    fn signs_rfc8032_vector() {
+        let secret = hex("4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb");
+        let keypair = Keypair {
+            key: SigningKey::from_bytes(&secret.try_into().unwrap()),
+        };
+
+        assert_eq!(
+            keypair.pubkey().to_bytes().to_vec(),
+            hex("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c")
+        );
+        assert_eq!(
+            keypair.sign(&[0x72]).as_ref(),
+            hex(concat!(
+                "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da",
+                "085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00"
+            ))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_owner_only_files() {
+        let path = std::env::temp_dir().join(format!("friend-bets-keypair-{}", std::process::id()));
+        let keypair = Keypair::generate();
+        keypair.write(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        let read = Keypair::read(&path).unwrap();
+        let rewritten = keypair.write(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(read.pubkey(), keypair.pubkey());
+        assert!(matches!(rewritten, Err(KeypairError::Write(..))));
+    }
+
+    #[test]
+    fn rejects_mismatched_public_keys() {
+        let path =
+            std::env::temp_dir().join(format!("friend-bets-mismatch-{}", std::process::id()));
+        let mut bytes = Keypair::generate().key.to_keypair_bytes();
+        bytes[32..].copy_from_slice(&Keypair::generate().key.verifying_key().to_bytes());
+        fs::write(&path, serde_json::to_string(&bytes.to_vec()).unwrap()).unwrap();
+        let read = Keypair::read(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(read, Err(KeypairError::Mismatch(_))));
+    }
+}
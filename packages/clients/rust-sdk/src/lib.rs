@@ -0,0 +1,29 @@
+//! Client SDK for the `friends_bets` program: PDA derivation, instruction
+//! builders, account fetching, event decoding, payout simulation, hedge
+//! sizing, proofs for compressed positions, RPC endpoint failover, keypair
+//! files and the program's account and argument types.
+
+pub mod batch;
+pub mod compressed;
+pub mod events;
+pub mod failover;
+pub mod fetch;
+pub mod hedge;
+pub mod instructions;
+pub mod keypair;
+pub mod offline;
+pub mod pda;
+pub mod send;
+pub mod simulate;
+pub mod subscribe;
+
+pub use friends_bets::activity::{ActivityBucket, MarketActivity};
+pub use friends_bets::admin_log::AdminAction;
+pub use friends_bets::portfolio::{Portfolio, PortfolioAction, PortfolioEntry, PortfolioPage};
+pub use friends_bets::schedule::ScheduledBet;
+pub use friends_bets::title::MarketTitle;
+pub use friends_bets::{
+    self, BatchBet, BetQuote, BetSide, BettorEntry, BettorPage, Config, ConfigUpdate, FeeSplit,
+    FeeTier, Market, MarketDependency, MarketOptions, MarketPolicy, MarketStatus, Position,
+    StakeAccount, CASHOUT_ORDER_TIP_BPS, DEFAULT_FEE, ID as PROGRAM_ID,
+};
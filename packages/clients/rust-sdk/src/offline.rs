@@ -0,0 +1,172 @@
+//! Durable nonce transactions and offline signing.
+//!
+//! A transaction built against a durable nonce doesn't expire, so one
+//! signer can prepare it (say, a multisig creator's `resolve`), pass the
+//! serialized [`PartiallySignedTransaction`] around for the other
+//! signatures, and broadcast it whenever it's complete.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use solana_message::{AddressLookupTableAccount, VersionedMessage};
+use solana_nonce::state::State;
+use solana_nonce::versions::Versions;
+use solana_short_vec::{decode_shortu16_len, ShortU16};
+use solana_signature::{Signature, SIGNATURE_BYTES};
+use solana_system_interface::instruction::advance_nonce_account;
+
+use crate::batch::compile;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OfflineError {
+    #[error("nonce account data is invalid: {0}")]
+    InvalidNonceAccount(bincode::Error),
+    #[error("nonce account isn't initialized")]
+    UninitializedNonce,
+    #[error("instructions don't compile against the lookup tables")]
+    Compile,
+    #[error("{0} isn't a required signer of this transaction")]
+    NotASigner(Pubkey),
+    #[error("malformed transaction bytes")]
+    Malformed,
+    #[error("invalid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+}
+
+/// Current state of a durable nonce account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NonceInfo {
+    pub authority: Pubkey,
+    /// Value to use as the transaction's recent blockhash.
+    pub blockhash: Hash,
+}
+
+/// Decodes the data of a nonce account.
+pub fn read_nonce(data: &[u8]) -> Result<NonceInfo, OfflineError> {
+    let versions: Versions =
+        bincode::deserialize(data).map_err(OfflineError::InvalidNonceAccount)?;
+    match versions.state() {
+        State::Uninitialized => Err(OfflineError::UninitializedNonce),
+        State::Initialized(data) => Ok(NonceInfo {
+            authority: data.authority,
+            blockhash: data.blockhash(),
+        }),
+    }
+}
+
+/// Compiles `instructions` against a durable nonce. The nonce advance is
+/// prepended, as the runtime requires it to be the first instruction.
+pub fn nonce_message(
+    payer: &Pubkey,
+    nonce_account: &Pubkey,
+    nonce: &NonceInfo,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+) -> Result<VersionedMessage, OfflineError> {
+    let mut with_advance = Vec::with_capacity(instructions.len() + 1);
+    with_advance.push(advance_nonce_account(nonce_account, &nonce.authority));
+    with_advance.extend_from_slice(instructions);
+    compile(payer, &with_advance, lookup_tables, nonce.blockhash).ok_or(OfflineError::Compile)
+}
+
+/// A transaction collecting signatures from several parties.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartiallySignedTransaction {
+    pub message: VersionedMessage,
+    /// One per required signer, in account order; unsigned slots are the
+    /// default (all zero) signature.
+    pub signatures: Vec<Signature>,
+}
+
+impl PartiallySignedTransaction {
+    pub fn new(message: VersionedMessage) -> Self {
+        let signers = message.header().num_required_signatures as usize;
+        Self {
+            message,
+            signatures: vec![Signature::default(); signers],
+        }
+    }
+
+    /// Accounts that must sign, in signature order.
+    pub fn signers(&self) -> &[Pubkey] {
+        &self.message.static_account_keys()[..self.signatures.len()]
+    }
+
+    /// Bytes each signer signs.
+    pub fn message_bytes(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+
+    /// Records `signer`'s signature over [`message_bytes`](Self::message_bytes).
+    /// The signature isn't verified here; the cluster rejects bad ones.
+    pub fn add_signature(
+        &mut self,
+        signer: &Pubkey,
+        signature: Signature,
+    ) -> Result<(), OfflineError> {
+        let index = self
+            .signers()
+            .iter()
+            .position(|key| key == signer)
+            .ok_or(OfflineError::NotASigner(*signer))?;
+        self.signatures[index] = signature;
+        Ok(())
+    }
+
+    /// Signers that haven't signed yet.
+    pub fn missing_signers(&self) -> Vec<Pubkey> {
+        self.signers()
+            .iter()
+            .zip(&self.signatures)
+            .filter(|(_, signature)| **signature == Signature::default())
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    pub fn is_fully_signed(&self) -> bool {
+        self.missing_signers().is_empty()
+    }
+
+    /// Wire format, as accepted by `sendTransaction`.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = bincode::serialize(&ShortU16(self.signatures.len() as u16))
+            .expect("short vec length serializes");
+        for signature in &self.signatures {
+            bytes.extend_from_slice(signature.as_ref());
+        }
+        bytes.extend(self.message.serialize());
+        bytes
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, OfflineError> {
+        let (count, offset) = decode_shortu16_len(bytes).map_err(|_| OfflineError::Malformed)?;
+        let message_start = offset + count * SIGNATURE_BYTES;
+        let signature_bytes = bytes
+            .get(offset..message_start)
+            .ok_or(OfflineError::Malformed)?;
+        let signatures = signature_bytes
+            .chunks(SIGNATURE_BYTES)
+            .map(|chunk| Signature::try_from(chunk).map_err(|_| OfflineError::Malformed))
+            .collect::<Result<Vec<_>, _>>()?;
+        let message: VersionedMessage =
+            bincode::deserialize(&bytes[message_start..]).map_err(|_| OfflineError::Malformed)?;
+
+        if message.header().num_required_signatures as usize != signatures.len() {
+            return Err(OfflineError::Malformed);
+        }
+        Ok(Self {
+            message,
+            signatures,
+        })
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.serialize())
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<Self, OfflineError> {
+        Self::deserialize(&STANDARD.decode(encoded)?)
+    }
+}
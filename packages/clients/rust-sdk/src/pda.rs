@@ -0,0 +1,151 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address;
+use friends_bets::portfolio::Portfolio;
+use friends_bets::title::title_hash;
+use friends_bets::{
+    BetSide, Market, Position, ACTIVITY_SEED, BETTOR_PAGE_SEED, BETTOR_PAGE_SIZE, CONFIG_SEED,
+    INSURANCE_VAULT_SEED, MARKET_SEED, MARKET_TITLE_SEED, PORTFOLIO_PAGE_SEED, PORTFOLIO_SEED,
+    POSITION_SEED, POSITION_TREE_SEED, SCHEDULED_BET_SEED, SCHEDULED_ESCROW_SEED, STAKE_SEED,
+    STAKE_VAULT_SEED, VAULT_SEED,
+};
+use solana_sdk_ids::bpf_loader_upgradeable;
+
+use crate::PROGRAM_ID;
+
+/// Derives the market PDA
+pub fn market_address(creator: &Pubkey, market_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[MARKET_SEED, creator.as_ref(), &market_id.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Derives the PDA of the vault holding a market's stakes on `side`
+pub fn vault_address(market: &Pubkey, side: BetSide) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_SEED, market.as_ref(), &[side as u8]], &PROGRAM_ID)
+}
+
+/// Derives the position PDA for a user in a market
+pub fn position_address(market: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[POSITION_SEED, market.as_ref(), owner.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Whether the position account at `address` belongs to `market`. Positions
+/// don't store their market; this checks the address with the stored bump,
+/// which is much cheaper than deriving it with [`position_address`].
+pub fn is_position_of(address: &Pubkey, position: &Position, market: &Pubkey) -> bool {
+    Pubkey::create_program_address(
+        &[
+            POSITION_SEED,
+            market.as_ref(),
+            position.owner.as_ref(),
+            &[position.bump],
+        ],
+        &PROGRAM_ID,
+    )
+    .is_ok_and(|expected| expected == *address)
+}
+
+/// Derives the position tree PDA of a compressed market
+pub fn position_tree_address(market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[POSITION_TREE_SEED, market.as_ref()], &PROGRAM_ID)
+}
+
+/// Derives the PDA of a market's bettor page `index`
+pub fn bettor_page_address(market: &Pubkey, index: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[BETTOR_PAGE_SEED, market.as_ref(), &index.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+}
+
+/// The bettor page the market's next bet is recorded in
+pub fn current_bettor_page_address(address: &Pubkey, market: &Market) -> Pubkey {
+    bettor_page_address(address, market.bettor_count / BETTOR_PAGE_SIZE as u32).0
+}
+
+/// Derives the PDA of a market's activity account
+pub fn activity_address(market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ACTIVITY_SEED, market.as_ref()], &PROGRAM_ID)
+}
+
+/// Derives the PDA registering `title`, normalized, under `nonce`
+pub fn market_title_address(title: &str, nonce: u16) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[MARKET_TITLE_SEED, &title_hash(title), &nonce.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Derives the PDA of `owner`'s scheduled bet `id` on a market
+pub fn scheduled_bet_address(market: &Pubkey, owner: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            SCHEDULED_BET_SEED,
+            market.as_ref(),
+            owner.as_ref(),
+            &id.to_le_bytes(),
+        ],
+        &PROGRAM_ID,
+    )
+}
+
+/// Derives the PDA of the token account holding a scheduled bet's stake
+pub fn scheduled_escrow_address(scheduled_bet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[SCHEDULED_ESCROW_SEED, scheduled_bet.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Derives the PDA of a user's portfolio
+pub fn portfolio_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PORTFOLIO_SEED, owner.as_ref()], &PROGRAM_ID)
+}
+
+/// Derives the PDA of page `index` of a user's portfolio
+pub fn portfolio_page_address(owner: &Pubkey, index: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[PORTFOLIO_PAGE_SEED, owner.as_ref(), &index.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+}
+
+/// The portfolio page the user's next entry is recorded in
+pub fn current_portfolio_page_address(portfolio: &Portfolio) -> Pubkey {
+    portfolio_page_address(&portfolio.owner, portfolio.current_page()).0
+}
+
+/// Derives the program-wide config PDA
+pub fn config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED], &PROGRAM_ID)
+}
+
+/// Derives the program's ProgramData account, which holds its upgrade
+/// authority
+pub fn program_data_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PROGRAM_ID.as_ref()], &bpf_loader_upgradeable::ID)
+}
+
+/// Derives the staking vault PDA
+pub fn stake_vault_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[STAKE_VAULT_SEED], &PROGRAM_ID)
+}
+
+/// Derives the stake account PDA for a user
+pub fn stake_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[STAKE_SEED, owner.as_ref()], &PROGRAM_ID)
+}
+
+/// Derives the insurance vault PDA for a mint
+pub fn insurance_vault_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[INSURANCE_VAULT_SEED, mint.as_ref()], &PROGRAM_ID)
+}
+
+/// Associated token account of `owner` for `mint`
+pub fn token_account_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address(owner, mint)
+}
@@ -0,0 +1,217 @@
+//! Transaction send pipeline for congested periods.
+//!
+//! [`send_with_retry`] prepends compute budget instructions priced from
+//! recent prioritization fees, sends, rebroadcasts until the blockhash
+//! expires, and then retries with a fresh blockhash and a higher fee. The
+//! RPC client and the signer are supplied through [`SendClient`].
+
+use std::thread;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use solana_message::{AddressLookupTableAccount, VersionedMessage};
+use solana_sdk_ids::compute_budget;
+use solana_signature::Signature;
+
+use crate::batch::compile;
+
+/// `ComputeBudgetInstruction::SetComputeUnitLimit`
+pub fn set_compute_unit_limit(units: u32) -> Instruction {
+    let mut data = vec![2];
+    data.extend_from_slice(&units.to_le_bytes());
+    Instruction::new_with_bytes(compute_budget::ID, &data, vec![])
+}
+
+/// `ComputeBudgetInstruction::SetComputeUnitPrice`
+pub fn set_compute_unit_price(micro_lamports: u64) -> Instruction {
+    let mut data = vec![3];
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+    Instruction::new_with_bytes(compute_budget::ID, &data, vec![])
+}
+
+/// Status of a sent transaction, from least to most final.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// Landed and reached the given commitment.
+    Landed(Commitment),
+    /// Landed but the transaction failed.
+    Failed(String),
+}
+
+/// RPC access and signing for the send pipeline.
+pub trait SendClient {
+    type Error;
+
+    /// Prioritization fees paid in recent slots by transactions writing to
+    /// any of `writable`, in micro-lamports per compute unit.
+    fn recent_prioritization_fees(&self, writable: &[Pubkey]) -> Result<Vec<u64>, Self::Error>;
+
+    /// Latest blockhash and the last block height it's valid for.
+    fn latest_blockhash(&self) -> Result<(Hash, u64), Self::Error>;
+
+    fn block_height(&self) -> Result<u64, Self::Error>;
+
+    /// Signs `message` with every required signer and submits it without
+    /// preflight, returning the transaction signature.
+    fn sign_and_send(&self, message: &VersionedMessage) -> Result<Signature, Self::Error>;
+
+    fn signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<SignatureStatus>, Self::Error>;
+}
+
+/// How the compute unit price is picked from recent fees.
+#[derive(Clone, Copy, Debug)]
+pub struct PriorityFeePolicy {
+    /// Percentile of recent fees to match, 0-100.
+    pub percentile: u8,
+    pub min_micro_lamports: u64,
+    pub max_micro_lamports: u64,
+    /// Increase applied on each retry, in basis points of the previous price.
+    pub escalation_bps: u16,
+}
+
+impl Default for PriorityFeePolicy {
+    fn default() -> Self {
+        Self {
+            percentile: 75,
+            min_micro_lamports: 1_000,
+            max_micro_lamports: 2_000_000,
+            escalation_bps: 5_000,
+        }
+    }
+}
+
+fn percentile(values: &[u64], percentile: u8) -> u64 {
+    if values.is_empty() {
+        return 0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let index = (sorted.len() - 1) * percentile.min(100) as usize / 100;
+    sorted[index]
+}
+
+impl PriorityFeePolicy {
+    /// Compute unit price for `attempt` (0 for the first send).
+    pub fn price(&self, recent_fees: &[u64], attempt: u32) -> u64 {
+        let base = percentile(recent_fees, self.percentile).max(self.min_micro_lamports);
+        let mut price = base as u128;
+        for _ in 0..attempt {
+            price += price * self.escalation_bps as u128 / 10_000;
+        }
+        price.min(self.max_micro_lamports as u128) as u64
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SendConfig {
+    /// Compute unit limit to request; `None` keeps the runtime default.
+    pub compute_unit_limit: Option<u32>,
+    pub priority_fee: PriorityFeePolicy,
+    /// Blockhashes to try before giving up.
+    pub max_attempts: u32,
+    /// Delay between status checks, and between rebroadcasts.
+    pub poll_interval: Duration,
+    pub commitment: Commitment,
+}
+
+impl Default for SendConfig {
+    fn default() -> Self {
+        Self {
+            compute_unit_limit: None,
+            priority_fee: PriorityFeePolicy::default(),
+            max_attempts: 3,
+            poll_interval: Duration::from_millis(500),
+            commitment: Commitment::Confirmed,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SendError<E> {
+    #[error("rpc error: {0}")]
+    Rpc(E),
+    #[error("transaction {signature} failed: {reason}")]
+    Failed {
+        signature: Signature,
+        reason: String,
+    },
+    #[error("transaction didn't land after {attempts} attempts")]
+    Expired { attempts: u32 },
+    #[error("instructions don't compile against the lookup tables")]
+    Compile,
+}
+
+/// Sends `instructions` paid for by `payer`, retrying with fresh blockhashes
+/// until one lands at `config.commitment` or the attempts run out.
+pub fn send_with_retry<C: SendClient>(
+    client: &C,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    config: &SendConfig,
+) -> Result<Signature, SendError<C::Error>> {
+    let writable: Vec<Pubkey> = instructions
+        .iter()
+        .flat_map(|ix| ix.accounts.iter())
+        .filter(|meta| meta.is_writable)
+        .map(|meta| meta.pubkey)
+        .collect();
+    let recent_fees = client
+        .recent_prioritization_fees(&writable)
+        .map_err(SendError::Rpc)?;
+
+    for attempt in 0..config.max_attempts {
+        let price = config.priority_fee.price(&recent_fees, attempt);
+        let mut budgeted = Vec::with_capacity(instructions.len() + 2);
+        if let Some(units) = config.compute_unit_limit {
+            budgeted.push(set_compute_unit_limit(units));
+        }
+        budgeted.push(set_compute_unit_price(price));
+        budgeted.extend_from_slice(instructions);
+
+        let (blockhash, last_valid_block_height) =
+            client.latest_blockhash().map_err(SendError::Rpc)?;
+        let message =
+            compile(payer, &budgeted, lookup_tables, blockhash).ok_or(SendError::Compile)?;
+
+        let signature = client.sign_and_send(&message).map_err(SendError::Rpc)?;
+        loop {
+            thread::sleep(config.poll_interval);
+            match client
+                .signature_status(&signature)
+                .map_err(SendError::Rpc)?
+            {
+                Some(SignatureStatus::Landed(commitment)) if commitment >= config.commitment => {
+                    return Ok(signature)
+                }
+                Some(SignatureStatus::Landed(_)) => continue,
+                Some(SignatureStatus::Failed(reason)) => {
+                    return Err(SendError::Failed { signature, reason })
+                }
+                None => {}
+            }
+
+            if client.block_height().map_err(SendError::Rpc)? > last_valid_block_height {
+                break;
+            }
+            // Still valid but not seen yet; rebroadcast in case it was dropped
+            client.sign_and_send(&message).map_err(SendError::Rpc)?;
+        }
+    }
+
+    Err(SendError::Expired {
+        attempts: config.max_attempts,
+    })
+}
@@ -0,0 +1,465 @@
+//! Off-chain payout and odds simulation.
+//!
+//! Every figure is computed by the program's own [`Market`] methods and
+//! [`math`](friends_bets::math) functions, so what a frontend displays is
+//! what `place_bet`, `cash_out` and `claim` will actually pay, down to the
+//! rounding.
+
+use anchor_lang::prelude::{Pubkey, Result};
+use friends_bets::{math, BetQuote, BetSide, ErrorCode, Market, MarketStatus, Position};
+
+/// Payout per unit staked on each side if that side won with the pools as
+/// they stand, in basis points. A side nobody has bet on reports 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImpliedOdds {
+    pub a_bps: u64,
+    pub b_bps: u64,
+}
+
+pub fn implied_odds(market: &Market) -> Result<ImpliedOdds> {
+    let distributable = market.distributable()?;
+    let odds = |side| {
+        math::pro_rata(
+            distributable,
+            math::BPS_DENOMINATOR,
+            market.side_total(side),
+        )
+    };
+    Ok(ImpliedOdds {
+        a_bps: odds(BetSide::A)?,
+        b_bps: odds(BetSide::B)?,
+    })
+}
+
+/// Market and position state after a simulated bet.
+#[derive(Clone)]
+pub struct BetSimulation {
+    pub quote: BetQuote,
+    pub market: Market,
+    pub position: Position,
+}
+
+/// Simulates `user` betting `amount` on `side`, on top of their existing
+/// `position` if they have one, at the fee discount their stake earns
+/// (0 for non-stakers). Time-based checks are left to the caller.
+pub fn simulate_bet(
+    market: &Market,
+    position: Option<&Position>,
+    user: Pubkey,
+    side: BetSide,
+    amount: u64,
+    discount_bps: u16,
+) -> Result<BetSimulation> {
+    if market.status != MarketStatus::Open {
+        return Err(ErrorCode::MarketNotOpen.into());
+    }
+    if amount == 0 {
+        return Err(ErrorCode::InvalidAmount.into());
+    }
+    market.check_stake_caps(side, amount)?;
+
+    let mut market = market.clone();
+    let mut position = position.cloned().unwrap_or(Position {
+        owner: user,
+        side,
+        amount: 0,
+        claimed: false,
+        bump: 0,
+        locked_payout: 0,
+        discounted_stake: 0,
+        cashout_min_value: 0,
+        compensated: 0,
+    });
+    let quote = market.apply_bet(&mut position, user, side, amount, discount_bps)?;
+
+    Ok(BetSimulation {
+        quote,
+        market,
+        position,
+    })
+}
+
+/// Amount `position` would receive from `cash_out` right now.
+pub fn simulate_cash_out(market: &Market, position: &Position) -> Result<u64> {
+    if market.status != MarketStatus::Open {
+        return Err(ErrorCode::MarketNotOpen.into());
+    }
+    if !market.allow_cash_out {
+        return Err(ErrorCode::CashOutDisabled.into());
+    }
+    if position.amount == 0 {
+        return Err(ErrorCode::NothingToCashOut.into());
+    }
+
+    market.cash_out_value(position)
+}
+
+/// Whether anyone could fill `position`'s cash-out order with
+/// `execute_cashout`: it has one and what it would win has fallen to it.
+/// The program also waits for a slot after the stakes last moved.
+pub fn cashout_order_due(market: &Market, position: &Position) -> Result<bool> {
+    if position.cashout_min_value == 0 {
+        return Ok(false);
+    }
+    simulate_cash_out(market, position)?;
+    Ok(market.payout_if_won(position)? <= position.cashout_min_value)
+}
+
+/// What `claim` pays out for a position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClaimSimulation {
+    pub payout: u64,
+    /// Fee handed back to a staker, included in [`total`](Self::total).
+    pub rebate: u64,
+}
+
+impl ClaimSimulation {
+    pub fn total(&self) -> u64 {
+        self.payout + self.rebate
+    }
+}
+
+/// Simulates claiming `position`, with the rebate its bets earned.
+pub fn simulate_claim(market: &Market, position: &Position) -> Result<ClaimSimulation> {
+    if market.status != MarketStatus::Resolved && market.status != MarketStatus::Cancelled {
+        return Err(ErrorCode::MarketNotFinalized.into());
+    }
+    if position.claimed {
+        return Err(ErrorCode::AlreadyClaimed.into());
+    }
+
+    Ok(ClaimSimulation {
+        payout: market.payout_for(position)?,
+        rebate: market.fee_rebate_for(position)?,
+    })
+}
+
+/// What `position` is worth with the pools as they stand: its payout if its
+/// side won now while the market is undecided, and its actual payout once
+/// it's finalized. Claimed positions are still valued.
+pub fn position_value(market: &Market, position: &Position) -> Result<u64> {
+    match market.status {
+        MarketStatus::Resolved | MarketStatus::Cancelled => market.payout_for(position),
+        MarketStatus::Open | MarketStatus::PendingResolve => {
+            let mut settled = market.clone();
+            settled.status = MarketStatus::Resolved;
+            settled.outcome = Some(position.side);
+            settled.payout_for(position)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::error::Error;
+
+    use super::*;
+
+    fn market(fee_bps: u16, lock_odds: bool) -> Market {
+        Market {
+            market_id: 1,
+            creator: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            vault_a: Pubkey::new_unique(),
+            vault_b: Pubkey::new_unique(),
+            fee_bps,
+            end_ts: 0,
+            resolve_deadline_ts: 0,
+            staked_a: 0,
+            staked_b: 0,
+            status: MarketStatus::Open,
+            outcome: None,
+            creator_fee_withdrawn: false,
+            bump: 0,
+            vault_a_bump: 0,
+            vault_b_bump: 0,
+            title: String::new(),
+            allow_cash_out: true,
+            cashout_haircut_bps: 1_000,
+            retained: 0,
+            lock_odds,
+            locked_payout_a: 0,
+            locked_payout_b: 0,
+            forked_from: None,
+            dependency: None,
+            open_positions: 0,
+            burn_fee: false,
+            fee_discounts: 0,
+            discounted_stake: 0,
+            compressed: false,
+            track_bettors: false,
+            bettor_count: 0,
+            bettor_pages: 0,
+            insurance_bps: 0,
+            insurance_collected: false,
+            compensated: 0,
+            charity: None,
+            charity_bps: 0,
+            charity_paid: false,
+            large_bet_threshold: 0,
+            track_activity: false,
+            cap_a: 0,
+            cap_b: 0,
+            max_ratio_bps: 0,
+            title_registered: false,
+            fee_splits: Vec::new(),
+            pools_changed_slot: 0,
+            scheduled_bets: 0,
+        }
+    }
+
+    fn bet(market: &mut Market, side: BetSide, amount: u64) -> Position {
+        staker_bet(market, side, amount, 0)
+    }
+
+    fn staker_bet(market: &mut Market, side: BetSide, amount: u64, discount_bps: u16) -> Position {
+        let sim = simulate_bet(
+            market,
+            None,
+            Pubkey::new_unique(),
+            side,
+            amount,
+            discount_bps,
+        )
+        .unwrap();
+        *market = sim.market;
+        sim.position
+    }
+
+    fn resolve(market: &mut Market, outcome: BetSide) {
+        market.status = MarketStatus::Resolved;
+        market.outcome = Some(outcome);
+    }
+
+    fn error_code(err: Error) -> u32 {
+        match err {
+            Error::AnchorError(err) => err.error_code_number,
+            Error::ProgramError(err) => panic!("unexpected program error {err}"),
+        }
+    }
+
+    #[test]
+    fn pari_mutuel_payouts_round_down() {
+        let mut market = market(500, false);
+        let alice = bet(&mut market, BetSide::A, 100);
+        let bob = bet(&mut market, BetSide::A, 300);
+        let carol = bet(&mut market, BetSide::B, 600);
+
+        assert_eq!(
+            implied_odds(&market).unwrap(),
+            ImpliedOdds {
+                a_bps: 23_750,
+                b_bps: 15_833,
+            }
+        );
+
+        resolve(&mut market, BetSide::A);
+        let paid: Vec<u64> = [&alice, &bob, &carol]
+            .iter()
+            .map(|position| simulate_claim(&market, position).unwrap().total())
+            .collect();
+        assert_eq!(paid, vec![237, 712, 0]);
+        assert!(paid.iter().sum::<u64>() <= market.distributable().unwrap());
+    }
+
+    #[test]
+    fn locked_odds_pay_receipts_and_return_surplus() {
+        let mut market = market(500, true);
+        let alice = bet(&mut market, BetSide::A, 100);
+        assert_eq!(alice.locked_payout, 95);
+
+        let sim = simulate_bet(&market, None, Pubkey::new_unique(), BetSide::B, 100, 0).unwrap();
+        assert_eq!(sim.quote.odds_bps, 19_000);
+        market = sim.market;
+        let bob = sim.position;
+
+        resolve(&mut market, BetSide::A);
+        assert_eq!(simulate_claim(&market, &alice).unwrap().payout, 95);
+        assert_eq!(simulate_claim(&market, &bob).unwrap().payout, 95);
+    }
+
+    #[test]
+    fn position_value_tracks_the_pools_until_resolution() {
+        let mut market = market(0, false);
+        let alice = bet(&mut market, BetSide::A, 100);
+        let bob = bet(&mut market, BetSide::B, 300);
+        assert_eq!(position_value(&market, &alice).unwrap(), 400);
+        assert_eq!(position_value(&market, &bob).unwrap(), 400);
+
+        resolve(&mut market, BetSide::B);
+        assert_eq!(position_value(&market, &alice).unwrap(), 0);
+        assert_eq!(position_value(&market, &bob).unwrap(), 400);
+    }
+
+    #[test]
+    fn repeat_bets_accumulate_on_one_position() {
+        let mut market = market(0, false);
+        let user = Pubkey::new_unique();
+        let first = simulate_bet(&market, None, user, BetSide::A, 40, 0).unwrap();
+        market = first.market;
+        let second = simulate_bet(&market, Some(&first.position), user, BetSide::A, 60, 0).unwrap();
+
+        assert_eq!(second.position.amount, 100);
+        assert_eq!(second.market.staked_a, 100);
+        assert_eq!(second.market.open_positions, 1);
+    }
+
+    #[test]
+    fn cancelled_markets_refund_stake() {
+        let mut market = market(500, false);
+        let alice = staker_bet(&mut market, BetSide::A, 100, 5_000);
+        market.status = MarketStatus::Cancelled;
+
+        let claim = simulate_claim(&market, &alice).unwrap();
+        assert_eq!(
+            claim,
+            ClaimSimulation {
+                payout: 100,
+                rebate: 0
+            }
+        );
+    }
+
+    #[test]
+    fn unresolved_markets_past_their_deadline_refund_stake() {
+        let mut market = market(500, false);
+        market.resolve_deadline_ts = 100;
+        let alice = bet(&mut market, BetSide::A, 100);
+        market.status = MarketStatus::PendingResolve;
+
+        assert!(!market.cancel_if_expired(99));
+        assert!(simulate_claim(&market, &alice).is_err());
+        assert!(market.cancel_if_expired(100));
+        assert_eq!(simulate_claim(&market, &alice).unwrap().total(), 100);
+    }
+
+    #[test]
+    fn charity_cut_comes_out_of_the_winnings() {
+        let mut market = market(500, false);
+        market.charity = Some(Pubkey::new_unique());
+        market.charity_bps = 2_000;
+        let alice = bet(&mut market, BetSide::A, 400);
+        bet(&mut market, BetSide::B, 600);
+        resolve(&mut market, BetSide::A);
+
+        // 50 in fees and 200 to charity leave 750 of the 1000 pool
+        assert_eq!(market.charity_amount().unwrap(), 200);
+        assert_eq!(simulate_claim(&market, &alice).unwrap().total(), 750);
+    }
+
+    #[test]
+    fn stake_caps_bound_each_side() {
+        let mut market = market(0, false);
+        market.cap_a = 500;
+        market.max_ratio_bps = 30_000;
+        let user = Pubkey::new_unique();
+
+        // The ratio only applies once both sides have stake
+        bet(&mut market, BetSide::A, 400);
+        assert_eq!(market.bet_room(BetSide::A).unwrap(), Some(100));
+        assert_eq!(market.bet_room(BetSide::B).unwrap(), None);
+        assert!(simulate_bet(&market, None, user, BetSide::A, 101, 0).is_err());
+
+        bet(&mut market, BetSide::B, 100);
+        assert_eq!(market.bet_room(BetSide::A).unwrap(), Some(0));
+        assert_eq!(market.bet_room(BetSide::B).unwrap(), Some(1_100));
+        assert!(simulate_bet(&market, None, user, BetSide::B, 1_100, 0).is_ok());
+        assert!(simulate_bet(&market, None, user, BetSide::B, 1_101, 0).is_err());
+    }
+
+    #[test]
+    fn stakers_get_part_of_their_fee_share_back() {
+        let mut market = market(500, false);
+        let alice = staker_bet(&mut market, BetSide::A, 100, 5_000);
+        bet(&mut market, BetSide::B, 900);
+        resolve(&mut market, BetSide::A);
+
+        // Alice's share of the 50 fee is 5; half of it comes back
+        let claim = simulate_claim(&market, &alice).unwrap();
+        assert_eq!(
+            claim,
+            ClaimSimulation {
+                payout: 950,
+                rebate: 2
+            }
+        );
+        assert_eq!(market.creator_fee_due().unwrap(), 48);
+    }
+
+    #[test]
+    fn discounts_are_fixed_per_bet() {
+        let mut market = market(1_000, false);
+        let user = Pubkey::new_unique();
+        // Half the position was placed before staking, half at a full discount
+        let first = simulate_bet(&market, None, user, BetSide::A, 500, 0).unwrap();
+        let second = simulate_bet(
+            &first.market,
+            Some(&first.position),
+            user,
+            BetSide::A,
+            500,
+            10_000,
+        )
+        .unwrap();
+        assert_eq!(second.position.discounted_stake, 500);
+        market = second.market;
+        bet(&mut market, BetSide::B, 1_000);
+        resolve(&mut market, BetSide::B);
+
+        // The losing side still gets its rebate: half its 100 share of the fee
+        let claim = simulate_claim(&market, &second.position).unwrap();
+        assert_eq!(
+            claim,
+            ClaimSimulation {
+                payout: 0,
+                rebate: 50
+            }
+        );
+        assert_eq!(market.creator_fee_due().unwrap(), 150);
+    }
+
+    #[test]
+    fn cash_out_matches_program_haircut() {
+        let mut market = market(500, false);
+        let alice = bet(&mut market, BetSide::A, 100);
+        bet(&mut market, BetSide::B, 900);
+
+        assert_eq!(simulate_cash_out(&market, &alice).unwrap(), 86);
+    }
+
+    #[test]
+    fn cashout_order_is_due_once_its_winnings_fall_to_it() {
+        let mut market = market(500, false);
+        let mut alice = bet(&mut market, BetSide::A, 100);
+        bet(&mut market, BetSide::B, 900);
+
+        // Alice would win 950 of the pool; the order goes by that, not by
+        // the 86 cashing out pays, which doesn't depend on her side
+        assert!(!cashout_order_due(&market, &alice).unwrap());
+        alice.cashout_min_value = 300;
+        assert!(!cashout_order_due(&market, &alice).unwrap());
+
+        // More stake on her side shrinks her share to 180
+        bet(&mut market, BetSide::A, 900);
+        assert!(cashout_order_due(&market, &alice).unwrap());
+    }
+
+    #[test]
+    fn rejects_what_the_program_rejects() {
+        let mut market = market(500, false);
+        let mut alice = bet(&mut market, BetSide::A, 100);
+
+        let err = simulate_claim(&market, &alice).unwrap_err();
+        assert_eq!(error_code(err), u32::from(ErrorCode::MarketNotFinalized));
+
+        resolve(&mut market, BetSide::A);
+        alice.claimed = true;
+        let err = simulate_claim(&market, &alice).unwrap_err();
+        assert_eq!(error_code(err), u32::from(ErrorCode::AlreadyClaimed));
+
+        let err = simulate_bet(&market, None, Pubkey::new_unique(), BetSide::B, 1, 0)
+            .map(|sim| sim.quote)
+            .unwrap_err();
+        assert_eq!(error_code(err), u32::from(ErrorCode::MarketNotOpen));
+    }
+}
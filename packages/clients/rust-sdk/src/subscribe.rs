@@ -0,0 +1,265 @@
+//! Live account watching for odds displays and position trackers.
+//!
+//! [`watch`] runs a background thread that keeps a set of market and
+//! position accounts subscribed through an [`AccountFeed`] (usually an RPC
+//! websocket), reconnects with backoff when the connection drops, and
+//! refetches every account after each (re)connect so updates missed while
+//! disconnected aren't lost. Decoded updates arrive on the returned
+//! [`Subscription`], oldest first, never going back in slot for an account.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use friends_bets::{Market, Position};
+
+/// Raw account data, `None` for an account that doesn't exist.
+pub type AccountData = Option<Vec<u8>>;
+
+/// A change to an account, as reported by the feed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub address: Pubkey,
+    pub slot: u64,
+    /// `None` once the account is closed.
+    pub data: AccountData,
+}
+
+/// Transport for account updates.
+pub trait AccountFeed: Send + 'static {
+    type Error: Display;
+
+    /// Opens a fresh connection subscribed to `addresses`, dropping any
+    /// previous one.
+    fn connect(&mut self, addresses: &[Pubkey]) -> Result<(), Self::Error>;
+
+    /// Waits a bounded time for the next notification, returning `None` on
+    /// timeout so the watcher can check whether it's been stopped.
+    fn next_notification(&mut self) -> Result<Option<Notification>, Self::Error>;
+
+    /// Current state of `addresses` and the slot it was read at.
+    fn fetch(&mut self, addresses: &[Pubkey]) -> Result<(u64, Vec<AccountData>), Self::Error>;
+}
+
+#[derive(Clone)]
+pub enum AccountUpdate {
+    Market {
+        address: Pubkey,
+        slot: u64,
+        market: Box<Market>,
+    },
+    Position {
+        address: Pubkey,
+        slot: u64,
+        position: Position,
+    },
+    Closed {
+        address: Pubkey,
+        slot: u64,
+    },
+    /// The connection dropped; updates resume after reconnecting.
+    Disconnected {
+        error: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WatchConfig {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Market,
+    Position,
+}
+
+/// Handle to a running watcher. Dropping it stops the watcher.
+pub struct Subscription {
+    receiver: Receiver<AccountUpdate>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Subscription {
+    /// Waits up to `timeout` for the next update.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<AccountUpdate> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    pub fn try_recv(&self) -> Option<AccountUpdate> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Iterator for Subscription {
+    type Item = AccountUpdate;
+
+    fn next(&mut self) -> Option<AccountUpdate> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Starts watching `markets` and `positions` through `feed`.
+pub fn watch<F: AccountFeed>(
+    feed: F,
+    markets: &[Pubkey],
+    positions: &[Pubkey],
+    config: WatchConfig,
+) -> Subscription {
+    let kinds: HashMap<Pubkey, Kind> = markets
+        .iter()
+        .map(|address| (*address, Kind::Market))
+        .chain(positions.iter().map(|address| (*address, Kind::Position)))
+        .collect();
+    let (sender, receiver) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let watcher = Watcher {
+        feed,
+        kinds,
+        slots: HashMap::new(),
+        sender,
+        stop: stop.clone(),
+        config,
+    };
+    let handle = thread::spawn(move || watcher.run());
+
+    Subscription {
+        receiver,
+        stop,
+        handle: Some(handle),
+    }
+}
+
+struct Watcher<F> {
+    feed: F,
+    kinds: HashMap<Pubkey, Kind>,
+    /// Last slot delivered per account.
+    slots: HashMap<Pubkey, u64>,
+    sender: Sender<AccountUpdate>,
+    stop: Arc<AtomicBool>,
+    config: WatchConfig,
+}
+
+impl<F: AccountFeed> Watcher<F> {
+    fn run(mut self) {
+        let addresses: Vec<Pubkey> = self.kinds.keys().copied().collect();
+        let mut backoff = self.config.initial_backoff;
+
+        while !self.stopped() {
+            match self.session(&addresses) {
+                // Stopped, or the subscriber went away
+                Ok(()) => return,
+                Err(error) => {
+                    if !self.deliver(AccountUpdate::Disconnected { error }) {
+                        return;
+                    }
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(self.config.max_backoff);
+                }
+            }
+        }
+    }
+
+    /// One connection's lifetime. Returns `Ok` when the watcher should exit.
+    fn session(&mut self, addresses: &[Pubkey]) -> Result<(), String> {
+        self.feed.connect(addresses).map_err(|e| e.to_string())?;
+
+        // Fill whatever was missed while disconnected
+        let (slot, accounts) = self.feed.fetch(addresses).map_err(|e| e.to_string())?;
+        for (address, data) in addresses.iter().zip(accounts) {
+            let notification = Notification {
+                address: *address,
+                slot,
+                data,
+            };
+            if !self.apply(notification) {
+                return Ok(());
+            }
+        }
+
+        while !self.stopped() {
+            if let Some(notification) = self.feed.next_notification().map_err(|e| e.to_string())? {
+                if !self.apply(notification) {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes and delivers a notification unless it's stale. Returns false
+    /// once the subscriber is gone.
+    fn apply(&mut self, notification: Notification) -> bool {
+        let Notification {
+            address,
+            slot,
+            data,
+        } = notification;
+        let Some(kind) = self.kinds.get(&address).copied() else {
+            return true;
+        };
+        if self.slots.get(&address).is_some_and(|last| *last >= slot) {
+            return true;
+        }
+
+        let update = match data.filter(|data| !data.is_empty()) {
+            None => AccountUpdate::Closed { address, slot },
+            Some(data) => match kind {
+                Kind::Market => match Market::try_deserialize(&mut data.as_slice()) {
+                    Ok(market) => AccountUpdate::Market {
+                        address,
+                        slot,
+                        market: Box::new(market),
+                    },
+                    Err(_) => return true,
+                },
+                Kind::Position => match Position::try_deserialize(&mut data.as_slice()) {
+                    Ok(position) => AccountUpdate::Position {
+                        address,
+                        slot,
+                        position,
+                    },
+                    Err(_) => return true,
+                },
+            },
+        };
+
+        self.slots.insert(address, slot);
+        self.deliver(update)
+    }
+
+    fn deliver(&self, update: AccountUpdate) -> bool {
+        self.sender.send(update).is_ok()
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+}
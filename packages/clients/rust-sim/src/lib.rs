@@ -0,0 +1,279 @@
+//! Replays scripted market lifecycles against both the program, running in
+//! LiteSVM through [`friend_bets_test`], and the SDK's off-chain simulator,
+//! and diffs the two after every step.
+//!
+//! Frontends show odds, cash-out values and payouts from
+//! [`simulate`](friend_bets_sdk::simulate); a replay that diverges means
+//! they'd display one figure while the program pays another. Each step is
+//! checked for whether it succeeds or with which error, how many tokens it
+//! moves, and the market, positions and vault it leaves behind. A replay
+//! stops at the first step that diverges, since everything after it would
+//! too.
+//!
+//! Scripts are TOML files (see [`script`]); the ones in `scenarios/` are
+//! replayed by this crate's tests and by the `friend-bets-sim` binary.
+
+pub mod model;
+pub mod script;
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::InstructionError;
+use friend_bets_sdk::instructions;
+use friend_bets_test::{Keypair, MarketParams, Signer, TestBed};
+use friends_bets::{Market, MarketStatus, Position};
+use solana_transaction_error::TransactionError;
+
+use crate::model::{Model, Outcome};
+use crate::script::{Script, Step};
+
+/// Where the program and the simulator disagree after a step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    pub field: String,
+    /// What the simulator predicted.
+    pub displayed: String,
+    /// What the program did.
+    pub actual: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct StepReport {
+    /// Index into the script's steps.
+    pub index: usize,
+    pub step: Step,
+    /// `None` for steps the simulator doesn't predict.
+    pub predicted: Option<Outcome>,
+    pub actual: Outcome,
+    pub divergences: Vec<Divergence>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub title: String,
+    /// The steps replayed, up to and including the first that diverged.
+    pub steps: Vec<StepReport>,
+}
+
+impl Report {
+    pub fn diverged(&self) -> Option<&StepReport> {
+        self.steps.iter().find(|step| !step.divergences.is_empty())
+    }
+}
+
+/// Replays `script` on a fresh market in `bed`.
+pub fn replay(bed: &mut TestBed, script: &Script) -> Report {
+    let mint = bed.create_mint(6);
+    let creator = bed.create_user(&mint, 0);
+    let wallets: BTreeMap<String, Keypair> = script
+        .wallets
+        .iter()
+        .map(|(name, balance)| (name.clone(), bed.create_user(&mint, *balance)))
+        .collect();
+    let market = bed.create_market(
+        &creator,
+        &mint,
+        MarketParams {
+            market_id: 1,
+            fee_bps: script.fee_bps,
+            ends_in: script.ends_in,
+            resolve_window: script.resolve_window,
+            title: script.title.clone(),
+            options: script.options(),
+        },
+    );
+
+    let owners = wallets
+        .iter()
+        .map(|(name, wallet)| (name.clone(), wallet.pubkey()))
+        .collect();
+    let mut model = Model::new(bed.market(&market), owners, bed.now());
+    let mut report = Report {
+        title: script.title.clone(),
+        steps: Vec::new(),
+    };
+
+    for (index, step) in script.steps.iter().enumerate() {
+        let predicted = model.predict(step);
+        let actual = execute(bed, &market, &creator, &wallets, step);
+
+        let mut divergences = Vec::new();
+        if let Some(predicted) = &predicted {
+            if *predicted != actual {
+                divergences.push(divergence("result", predicted, &actual));
+            }
+        }
+        if divergences.is_empty() && matches!(actual, Outcome::Moved(_)) {
+            if let Err(error) = model.apply(step) {
+                divergences.push(divergence("simulation", &error, &actual));
+            }
+        }
+        if divergences.is_empty() {
+            divergences = diff(bed, &market, &wallets, &model);
+        }
+
+        let diverged = !divergences.is_empty();
+        report.steps.push(StepReport {
+            index,
+            step: step.clone(),
+            predicted,
+            actual,
+            divergences,
+        });
+        if diverged {
+            break;
+        }
+    }
+    report
+}
+
+/// Runs `step` in the program, measuring the tokens it moves by the
+/// balance of the wallet on the other side of the vault.
+fn execute(
+    bed: &mut TestBed,
+    market: &Pubkey,
+    creator: &Keypair,
+    wallets: &BTreeMap<String, Keypair>,
+    step: &Step,
+) -> Outcome {
+    let mint = bed.market(market).mint;
+    let wallet = match step {
+        Step::Bet { user, .. } | Step::CashOut { user } | Step::Claim { user } => &wallets[user],
+        _ => creator,
+    };
+    let before = bed.balance(&wallet.pubkey(), &mint);
+    let result = match step {
+        Step::Bet { side, amount, .. } => bed.bet(wallet, market, (*side).into(), *amount),
+        Step::CashOut { .. } => bed.cash_out(wallet, market),
+        Step::Claim { .. } => bed.claim(wallet, market),
+        Step::WithdrawFee => bed.withdraw_fee(creator, market),
+        Step::Warp { secs } => {
+            bed.warp_by(*secs);
+            return Outcome::Moved(0);
+        }
+        Step::Close => bed.close_betting(market),
+        Step::Resolve { outcome } => {
+            // `close_and_resolve` only takes a market that's still open
+            if bed.market(market).status == MarketStatus::PendingResolve {
+                let instruction =
+                    instructions::resolve(&creator.pubkey(), market, (*outcome).into(), None);
+                bed.send(&[instruction], &[creator])
+            } else {
+                bed.resolve(creator, market, (*outcome).into())
+            }
+        }
+        Step::Cancel => bed.cancel(market),
+    };
+    match result {
+        Ok(_) => Outcome::Moved(before.abs_diff(bed.balance(&wallet.pubkey(), &mint))),
+        Err(failed) => match failed.err {
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+                Outcome::Failed(code)
+            }
+            err => Outcome::Rejected(format!("{err:?}")),
+        },
+    }
+}
+
+/// Compares the model with the program's accounts.
+fn diff(
+    bed: &TestBed,
+    market: &Pubkey,
+    wallets: &BTreeMap<String, Keypair>,
+    model: &Model,
+) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+    let mut compare = |field: String, displayed: &dyn Debug, actual: &dyn Debug| {
+        let (displayed, actual) = (format!("{displayed:?}"), format!("{actual:?}"));
+        if displayed != actual {
+            divergences.push(Divergence {
+                field,
+                displayed,
+                actual,
+            });
+        }
+    };
+
+    let actual = bed.market(market);
+    compare_market(&model.market, &actual, &mut compare);
+    compare("vault".into(), &model.vault, &bed.vault_balance(market));
+    for (name, wallet) in wallets {
+        let displayed = model.positions.get(name);
+        let actual = bed.position(market, &wallet.pubkey());
+        match (displayed, &actual) {
+            (Some(displayed), Some(actual)) => {
+                compare_position(name, displayed, actual, &mut compare)
+            }
+            (None, None) => {}
+            _ => compare(
+                format!("{name}'s position"),
+                &displayed.is_some(),
+                &actual.is_some(),
+            ),
+        }
+    }
+    divergences
+}
+
+macro_rules! compare_fields {
+    ($compare:expr, $prefix:expr, $displayed:expr, $actual:expr, $($field:ident),*) => {
+        $($compare(
+            format!("{}.{}", $prefix, stringify!($field)),
+            &$displayed.$field,
+            &$actual.$field,
+        );)*
+    };
+}
+
+fn compare_market(
+    displayed: &Market,
+    actual: &Market,
+    compare: &mut impl FnMut(String, &dyn Debug, &dyn Debug),
+) {
+    compare_fields!(
+        compare,
+        "market",
+        displayed,
+        actual,
+        status,
+        outcome,
+        staked_a,
+        staked_b,
+        retained,
+        locked_payout_a,
+        locked_payout_b,
+        open_positions,
+        fee_discounts,
+        discounted_stake,
+        creator_fee_withdrawn
+    );
+}
+
+fn compare_position(
+    name: &str,
+    displayed: &Position,
+    actual: &Position,
+    compare: &mut impl FnMut(String, &dyn Debug, &dyn Debug),
+) {
+    compare_fields!(
+        compare,
+        format!("{name}'s position"),
+        displayed,
+        actual,
+        side,
+        amount,
+        locked_payout,
+        discounted_stake,
+        claimed
+    );
+}
+
+fn divergence(field: &str, displayed: &dyn Debug, actual: &dyn Debug) -> Divergence {
+    Divergence {
+        field: field.into(),
+        displayed: format!("{displayed:?}"),
+        actual: format!("{actual:?}"),
+    }
+}
@@ -0,0 +1,116 @@
+//! `friend-bets-sim`: replays lifecycle scripts against the program and the
+//! SDK's simulator and prints where they diverge.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::{anyhow, bail, Result};
+use friend_bets_sim::script::Script;
+use friend_bets_sim::{replay, Report};
+use friend_bets_test::TestBed;
+
+const USAGE: &str = "\
+Usage: friend-bets-sim [OPTIONS] <SCRIPT>...
+
+Arguments:
+  <SCRIPT>...         Lifecycle scripts to replay, or directories of them
+
+Options:
+      --program <SO>  The program to replay against, default the one `anchor build`
+                      writes [env: FRIEND_BETS_PROGRAM_SO]
+  -h, --help          Print this help
+
+Exits non-zero if any script diverges.
+";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<bool> {
+    let mut program = None;
+    let mut paths = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(true);
+            }
+            "--program" => {
+                program = Some(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--program needs a value"))?,
+                )
+            }
+            flag if flag.starts_with("--program=") => {
+                program = Some(flag["--program=".len()..].to_string())
+            }
+            flag if flag.starts_with('-') => bail!("unknown option {flag}\n\n{USAGE}"),
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    if paths.is_empty() {
+        bail!("no scripts given\n\n{USAGE}");
+    }
+
+    let mut scripts = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries = std::fs::read_dir(&path)?
+                .map(|entry| Ok(entry?.path()))
+                .collect::<Result<Vec<_>>>()?;
+            entries.retain(|entry| entry.extension().is_some_and(|ext| ext == "toml"));
+            entries.sort();
+            scripts.extend(entries);
+        } else {
+            scripts.push(path);
+        }
+    }
+
+    let mut all_match = true;
+    for path in scripts {
+        let script = Script::load(&path)?;
+        let mut bed = match &program {
+            Some(program) => TestBed::with_program(program),
+            None => TestBed::new(),
+        };
+        let report = replay(&mut bed, &script);
+        print_report(&path.display().to_string(), &report);
+        all_match &= report.diverged().is_none();
+    }
+    Ok(all_match)
+}
+
+fn print_report(path: &str, report: &Report) {
+    let Some(step) = report.diverged() else {
+        println!(
+            "ok        {path}: {} ({} steps)",
+            report.title,
+            report.steps.len()
+        );
+        return;
+    };
+    println!("DIVERGED  {path}: {}", report.title);
+    println!("  step {}: {}", step.index + 1, step.step);
+    let width = step
+        .divergences
+        .iter()
+        .map(|divergence| divergence.field.len())
+        .max()
+        .unwrap_or_default();
+    for divergence in &step.divergences {
+        println!(
+            "    {:width$}  displayed {}, actual {}",
+            divergence.field, divergence.displayed, divergence.actual
+        );
+    }
+}
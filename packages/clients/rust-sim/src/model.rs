@@ -0,0 +1,164 @@
+//! The off-chain side of a replay: a market as a frontend would track it,
+//! moved forward by the SDK's [`simulate`](friend_bets_sdk::simulate)
+//! functions instead of by the program.
+//!
+//! Bets, cash-outs, claims and the creator's fee are predicted, with the
+//! time checks the simulator leaves to its callers made against the
+//! model's own clock. Closing, resolving and cancelling aren't simulated
+//! off-chain, so the model takes those from the program's result.
+
+use std::collections::BTreeMap;
+
+use anchor_lang::error::Error;
+use anchor_lang::prelude::Pubkey;
+use friend_bets_sdk::simulate::{simulate_bet, simulate_cash_out, simulate_claim};
+use friends_bets::{BetSide, ErrorCode, Market, MarketStatus, Position};
+
+use crate::script::Step;
+
+/// What a step did, or is predicted to do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Succeeded, moving this many tokens into or out of the vault.
+    Moved(u64),
+    /// Failed with this error code.
+    Failed(u32),
+    /// Failed outside the program, such as a token transfer the wallet
+    /// couldn't cover. Never predicted.
+    Rejected(String),
+}
+
+pub struct Model {
+    pub market: Market,
+    pub positions: BTreeMap<String, Position>,
+    pub vault: u64,
+    pub now: i64,
+    owners: BTreeMap<String, Pubkey>,
+}
+
+impl Model {
+    /// Starts from the market as it was fetched after its creation.
+    pub fn new(market: Market, owners: BTreeMap<String, Pubkey>, now: i64) -> Self {
+        Self {
+            market,
+            positions: BTreeMap::new(),
+            vault: 0,
+            now,
+            owners,
+        }
+    }
+
+    /// What the simulator expects `step` to do, or `None` for steps it
+    /// doesn't simulate.
+    pub fn predict(&self, step: &Step) -> Option<Outcome> {
+        let outcome = match step {
+            Step::Bet { user, side, amount } => self.bet(user, (*side).into(), *amount),
+            Step::CashOut { user } => self.cash_out(user),
+            Step::Claim { user } => self.claim(user),
+            Step::WithdrawFee => self.creator_fee(),
+            Step::Warp { .. } | Step::Close | Step::Resolve { .. } | Step::Cancel => return None,
+        };
+        Some(match outcome {
+            Ok(amount) => Outcome::Moved(amount),
+            Err(error) => Outcome::Failed(code(&error)),
+        })
+    }
+
+    /// Moves the model past `step`, which the program carried out.
+    pub fn apply(&mut self, step: &Step) -> anchor_lang::Result<()> {
+        match step {
+            Step::Bet { user, side, amount } => {
+                let simulation = simulate_bet(
+                    &self.market,
+                    self.positions.get(user),
+                    self.owners[user],
+                    (*side).into(),
+                    *amount,
+                    0,
+                )?;
+                self.market = simulation.market;
+                self.positions.insert(user.clone(), simulation.position);
+                self.vault += amount;
+            }
+            Step::CashOut { user } => {
+                // Checks it as `cash_out` would before settling it the same way
+                self.cash_out(user)?;
+                let position = self.positions.get_mut(user).expect("cashed out");
+                let (_, payout) = self.market.settle_cash_out(position)?;
+                self.vault -= payout;
+            }
+            Step::Claim { user } => {
+                let position = self.positions.get_mut(user).expect("claimed");
+                let claim = simulate_claim(&self.market, position)?;
+                self.market.fee_discounts += claim.rebate;
+                self.market.release_position(position)?;
+                position.claimed = true;
+                self.vault -= claim.total();
+            }
+            Step::WithdrawFee => {
+                self.vault -= self.creator_fee()?;
+                self.market.creator_fee_withdrawn = true;
+            }
+            Step::Warp { secs } => self.now += secs,
+            Step::Close => self.market.status = MarketStatus::PendingResolve,
+            Step::Resolve { outcome } => {
+                self.market.status = MarketStatus::Resolved;
+                self.market.outcome = Some((*outcome).into());
+            }
+            Step::Cancel => self.market.status = MarketStatus::Cancelled,
+        }
+        Ok(())
+    }
+
+    fn bet(&self, user: &str, side: BetSide, amount: u64) -> anchor_lang::Result<u64> {
+        if self.market.status == MarketStatus::Open && self.now >= self.market.end_ts {
+            return Err(ErrorCode::BettingClosed.into());
+        }
+        simulate_bet(
+            &self.market,
+            self.positions.get(user),
+            self.owners[user],
+            side,
+            amount,
+            0,
+        )?;
+        Ok(amount)
+    }
+
+    fn cash_out(&self, user: &str) -> anchor_lang::Result<u64> {
+        let position = self.position(user)?;
+        if self.market.status == MarketStatus::Open && self.now >= self.market.end_ts {
+            return Err(ErrorCode::BettingClosed.into());
+        }
+        simulate_cash_out(&self.market, position)
+    }
+
+    fn claim(&self, user: &str) -> anchor_lang::Result<u64> {
+        Ok(simulate_claim(&self.market, self.position(user)?)?.total())
+    }
+
+    fn creator_fee(&self) -> anchor_lang::Result<u64> {
+        if self.market.status != MarketStatus::Resolved {
+            return Err(ErrorCode::MarketNotResolved.into());
+        }
+        if self.market.creator_fee_withdrawn {
+            return Err(ErrorCode::FeeAlreadyWithdrawn.into());
+        }
+        self.market.creator_fee_due()
+    }
+
+    /// A user who never bet has no position account for the program to
+    /// load.
+    fn position(&self, user: &str) -> anchor_lang::Result<&Position> {
+        self.positions
+            .get(user)
+            .ok_or_else(|| anchor_lang::error::ErrorCode::AccountNotInitialized.into())
+    }
+}
+
+fn code(error: &Error) -> u32 {
+    match error {
+        Error::AnchorError(error) => error.error_code_number,
+        Error::ProgramError(error) => u64::from(error.program_error.clone()) as u32,
+    }
+}
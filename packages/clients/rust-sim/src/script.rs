@@ -0,0 +1,168 @@
+//! Lifecycle scripts: a market's settings, the wallets that bet on it and
+//! the steps to replay, read from TOML.
+//!
+//! ```toml
+//! title = "Underdog wins with locked odds"
+//! fee_bps = 200
+//! lock_odds = true
+//!
+//! [wallets]
+//! alice = 5_000_000
+//! bob = 5_000_000
+//!
+//! [[step]]
+//! action = "bet"
+//! user = "alice"
+//! side = "A"
+//! amount = 2_000_000
+//!
+//! [[step]]
+//! action = "warp"
+//! secs = 86_400
+//!
+//! [[step]]
+//! action = "resolve"
+//! outcome = "B"
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use friends_bets::{BetSide, MarketOptions};
+use serde::Deserialize;
+
+/// The wallet that creates the market, resolves it and takes its fee.
+pub const CREATOR: &str = "creator";
+
+/// The longest title `initialize_market` takes.
+const MAX_TITLE_LEN: usize = 64;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Script {
+    pub title: String,
+    #[serde(default = "default_fee_bps")]
+    pub fee_bps: u16,
+    /// Seconds from the start until betting ends.
+    #[serde(default = "default_window")]
+    pub ends_in: i64,
+    /// Seconds after the end the creator has to resolve.
+    #[serde(default = "default_window")]
+    pub resolve_window: i64,
+    #[serde(default)]
+    pub lock_odds: bool,
+    #[serde(default)]
+    pub allow_cash_out: bool,
+    #[serde(default)]
+    pub cashout_haircut_bps: u16,
+    /// Each bettor's starting balance, in raw units.
+    pub wallets: BTreeMap<String, u64>,
+    #[serde(rename = "step")]
+    pub steps: Vec<Step>,
+}
+
+fn default_fee_bps() -> u16 {
+    200
+}
+
+fn default_window() -> i64 {
+    86_400
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum Side {
+    A,
+    B,
+}
+
+impl From<Side> for BetSide {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::A => BetSide::A,
+            Side::B => BetSide::B,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    Bet {
+        user: String,
+        side: Side,
+        amount: u64,
+    },
+    CashOut {
+        user: String,
+    },
+    /// Moves the clock forward.
+    Warp {
+        secs: i64,
+    },
+    Close,
+    /// Closes betting if it's still open and resolves, as the creator.
+    Resolve {
+        outcome: Side,
+    },
+    Cancel,
+    Claim {
+        user: String,
+    },
+    WithdrawFee,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::Bet { user, side, amount } => write!(f, "{user} bets {amount} on {side:?}"),
+            Step::CashOut { user } => write!(f, "{user} cashes out"),
+            Step::Warp { secs } => write!(f, "warp {secs}s"),
+            Step::Close => write!(f, "close betting"),
+            Step::Resolve { outcome } => write!(f, "resolve {outcome:?}"),
+            Step::Cancel => write!(f, "cancel"),
+            Step::Claim { user } => write!(f, "{user} claims"),
+            Step::WithdrawFee => write!(f, "withdraw fee"),
+        }
+    }
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let script: Script = toml::from_str(text)?;
+        if script.title.len() > MAX_TITLE_LEN {
+            bail!("the title is over {MAX_TITLE_LEN} bytes");
+        }
+        if script.wallets.contains_key(CREATOR) {
+            bail!("`{CREATOR}` is the market's creator and can't be a bettor");
+        }
+        for step in &script.steps {
+            match step {
+                Step::Bet { user, .. } | Step::CashOut { user } | Step::Claim { user }
+                    if !script.wallets.contains_key(user) =>
+                {
+                    bail!("step `{step}` names `{user}`, who isn't in [wallets]")
+                }
+                Step::Warp { secs } if *secs < 0 => bail!("step `{step}` goes back in time"),
+                _ => {}
+            }
+        }
+        Ok(script)
+    }
+
+    pub fn options(&self) -> MarketOptions {
+        MarketOptions {
+            allow_cash_out: self.allow_cash_out,
+            cashout_haircut_bps: self.cashout_haircut_bps,
+            lock_odds: self.lock_odds,
+            burn_fee: false,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Replays every script in `scenarios/` and fails on the first divergence
+//! between the program and the SDK's simulator.
+
+use std::path::Path;
+
+use friend_bets_sim::replay;
+use friend_bets_sim::script::Script;
+use friend_bets_test::TestBed;
+
+#[test]
+fn scenarios_match_the_simulator() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no scripts in {}", dir.display());
+
+    for path in paths {
+        let script = Script::load(&path).unwrap();
+        let report = replay(&mut TestBed::new(), &script);
+        if let Some(step) = report.diverged() {
+            panic!(
+                "{} diverged at step {} ({}): {:#?}",
+                path.display(),
+                step.index + 1,
+                step.step,
+                step.divergences
+            );
+        }
+        assert_eq!(report.steps.len(), script.steps.len());
+    }
+}
+
+#[test]
+fn scripts_name_known_wallets() {
+    let error = Script::parse(
+        r#"
+        title = "Typo"
+        [wallets]
+        alice = 1
+
+        [[step]]
+        action = "claim"
+        user = "alcie"
+        "#,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("alcie"), "{error}");
+}
@@ -0,0 +1,568 @@
+//! Test fixtures for programs and clients built on `friends_bets`.
+//!
+//! [`TestBed`] runs the program in [LiteSVM](litesvm) next to the SPL token
+//! programs and wraps the setup integration tests otherwise repeat: mints
+//! and funded users, markets and bets, moving the clock past a market's end
+//! or resolve deadline, and reading back market, vault and position state
+//! to assert on.
+//!
+//! ```no_run
+//! use friend_bets_test::{MarketParams, Signer, TestBed};
+//! use friends_bets::{BetSide, ErrorCode, MarketStatus};
+//!
+//! let mut bed = TestBed::new();
+//! let mint = bed.create_mint(6);
+//! let creator = bed.create_user(&mint, 0);
+//! let alice = bed.create_user(&mint, 5_000_000);
+//! let bob = bed.create_user(&mint, 5_000_000);
+//! let market = bed.create_market(&creator, &mint, MarketParams::default());
+//! bed.bet(&alice, &market, BetSide::A, 2_000_000).unwrap();
+//! bed.bet(&bob, &market, BetSide::B, 2_000_000).unwrap();
+//!
+//! bed.warp_past_end(&market);
+//! friend_bets_test::assert_error(
+//!     &bed.bet(&alice, &market, BetSide::A, 1_000_000),
+//!     ErrorCode::BettingClosed,
+//! );
+//! bed.resolve(&creator, &market, BetSide::A).unwrap();
+//! bed.assert_status(&market, MarketStatus::Resolved);
+//! bed.claim(&alice, &market).unwrap();
+//! bed.assert_balance(&alice.pubkey(), &mint, 6_920_000);
+//! ```
+//!
+//! The program is loaded from the `.so` that `anchor build` writes in this
+//! repository, or from `$FRIEND_BETS_PROGRAM_SO`; [`TestBed::with_program`]
+//! takes any path, such as one written by `solana program dump`.
+
+use std::path::Path;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::instruction::{Instruction, InstructionError};
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token::{self, spl_token};
+use friend_bets_sdk::instructions::{self, NewMarket};
+use friend_bets_sdk::pda::{
+    config_address, portfolio_address, position_address, program_data_address,
+    token_account_address, vault_address,
+};
+use friends_bets::portfolio::Portfolio;
+use friends_bets::schedule::ScheduledBet;
+use friends_bets::{
+    BetSide, ErrorCode, Market, MarketOptions, MarketPolicy, MarketStatus, Position,
+};
+use litesvm::types::TransactionResult;
+use litesvm::LiteSVM;
+use solana_account::Account;
+use solana_sdk_ids::bpf_loader_upgradeable;
+use solana_transaction::Transaction;
+use solana_transaction_error::TransactionError;
+
+pub use friend_bets_sdk::PROGRAM_ID;
+pub use litesvm;
+pub use solana_keypair::Keypair;
+pub use solana_signer::Signer;
+
+/// Where `anchor build` writes the program in this repository.
+const PROGRAM_SO: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../contracts/anchor/target/deploy/friends_bets.so"
+);
+
+/// The clock's time when a test bed starts, so tests see the same
+/// timestamps on every run.
+pub const START_TIME: i64 = 1_700_000_000;
+
+/// Lamports given to each user, enough for rent and fees.
+const USER_LAMPORTS: u64 = 10_000_000_000;
+
+/// A new market's settings, with its end and deadline relative to now.
+#[derive(Clone, Debug)]
+pub struct MarketParams {
+    /// Unique per creator.
+    pub market_id: u64,
+    pub fee_bps: u16,
+    /// Seconds from now until betting ends.
+    pub ends_in: i64,
+    /// Seconds after the end the creator has to resolve.
+    pub resolve_window: i64,
+    pub title: String,
+    pub options: MarketOptions,
+}
+
+impl Default for MarketParams {
+    fn default() -> Self {
+        Self {
+            market_id: 1,
+            fee_bps: 200,
+            ends_in: 86_400,
+            resolve_window: 86_400,
+            title: "Test market".into(),
+            options: MarketOptions {
+                allow_cash_out: false,
+                cashout_haircut_bps: 0,
+                lock_odds: false,
+                burn_fee: false,
+            },
+        }
+    }
+}
+
+/// The program running in LiteSVM, with a funded fee payer and the mint
+/// authority for every mint it creates.
+pub struct TestBed {
+    pub svm: LiteSVM,
+    pub payer: Keypair,
+    mint_authority: Keypair,
+}
+
+impl Default for TestBed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestBed {
+    /// Loads the program from `$FRIEND_BETS_PROGRAM_SO`, or from where
+    /// `anchor build` writes it. Panics when it isn't there.
+    pub fn new() -> Self {
+        let path = std::env::var("FRIEND_BETS_PROGRAM_SO").unwrap_or_else(|_| PROGRAM_SO.into());
+        Self::with_program(path)
+    }
+
+    pub fn with_program(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let elf = std::fs::read(path).unwrap_or_else(|err| {
+            panic!(
+                "loading the program from {}: {err}; run `anchor build` or set \
+                 FRIEND_BETS_PROGRAM_SO",
+                path.display()
+            )
+        });
+        let mut svm = LiteSVM::new();
+        let mut clock: Clock = svm.get_sysvar();
+        clock.unix_timestamp = START_TIME;
+        svm.set_sysvar(&clock);
+
+        let payer = Keypair::new();
+        svm.airdrop(&payer.pubkey(), USER_LAMPORTS)
+            .expect("funding the payer");
+        let mut bed = Self {
+            svm,
+            payer,
+            mint_authority: Keypair::new(),
+        };
+        bed.deploy(&elf);
+        bed
+    }
+
+    /// Deploys `elf` the way `solana program deploy` does, behind the
+    /// upgradeable loader with the payer as upgrade authority, since only
+    /// the upgrade authority can create the config.
+    fn deploy(&mut self, elf: &[u8]) {
+        let program_data = program_data_address().0;
+        let mut data = program_data_metadata(Some(&self.payer.pubkey()));
+        data.extend_from_slice(elf);
+        self.set_loader_account(program_data, data, false);
+
+        // UpgradeableLoaderState::Program
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend_from_slice(program_data.as_ref());
+        self.set_loader_account(PROGRAM_ID, data, true);
+    }
+
+    /// Hands the program's upgrade authority to `authority`, or makes the
+    /// program immutable with `None`.
+    pub fn set_upgrade_authority(&mut self, authority: Option<&Pubkey>) {
+        let program_data = program_data_address().0;
+        let mut account = self
+            .svm
+            .get_account(&program_data)
+            .expect("the program is deployed");
+        let metadata = program_data_metadata(authority);
+        account.data[..metadata.len()].copy_from_slice(&metadata);
+        self.svm
+            .set_account(program_data, account)
+            .expect("writing the program data");
+    }
+
+    fn set_loader_account(&mut self, address: Pubkey, data: Vec<u8>, executable: bool) {
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: bpf_loader_upgradeable::ID,
+            executable,
+            rent_epoch: 0,
+        };
+        self.svm
+            .set_account(address, account)
+            .expect("deploying the program");
+    }
+
+    /// Sends `instructions` in one transaction paid by [`Self::payer`] and
+    /// signed by `signers` too.
+    pub fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> TransactionResult {
+        // A fresh blockhash, so sending the same instructions twice isn't
+        // rejected as a duplicate
+        self.svm.expire_blockhash();
+        let mut all = vec![&self.payer];
+        all.extend(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &all,
+            self.svm.latest_blockhash(),
+        );
+        self.svm.send_transaction(transaction)
+    }
+
+    // Tokens
+
+    /// Creates a mint with `decimals`, held by the test bed's authority.
+    pub fn create_mint(&mut self, decimals: u8) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        self.set_packed(
+            mint,
+            spl_token::state::Mint {
+                mint_authority: COption::Some(self.mint_authority.pubkey()),
+                supply: 0,
+                decimals,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+        );
+        mint
+    }
+
+    /// A new wallet with SOL for fees and rent, and `amount` raw units of
+    /// `mint` in its associated token account.
+    pub fn create_user(&mut self, mint: &Pubkey, amount: u64) -> Keypair {
+        let user = Keypair::new();
+        self.svm
+            .airdrop(&user.pubkey(), USER_LAMPORTS)
+            .expect("funding a user");
+        self.fund(&user.pubkey(), mint, amount);
+        user
+    }
+
+    /// Mints `amount` to `owner`'s associated token account, creating it
+    /// when it doesn't exist.
+    pub fn fund(&mut self, owner: &Pubkey, mint: &Pubkey, amount: u64) {
+        let mut state = self
+            .unpack::<spl_token::state::Mint>(mint)
+            .unwrap_or_else(|| panic!("mint {mint} not found"));
+        state.supply += amount;
+        self.set_packed(*mint, state);
+
+        let address = token_account_address(owner, mint);
+        let account = match self.unpack::<spl_token::state::Account>(&address) {
+            Some(mut account) => {
+                account.amount += amount;
+                account
+            }
+            None => spl_token::state::Account {
+                mint: *mint,
+                owner: *owner,
+                amount,
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            },
+        };
+        self.set_packed(address, account);
+    }
+
+    /// What `owner` holds of `mint` in their associated token account.
+    pub fn balance(&self, owner: &Pubkey, mint: &Pubkey) -> u64 {
+        self.token_amount(&token_account_address(owner, mint))
+    }
+
+    fn token_amount(&self, address: &Pubkey) -> u64 {
+        self.unpack::<spl_token::state::Account>(address)
+            .map_or(0, |account| account.amount)
+    }
+
+    fn unpack<T: Pack>(&self, address: &Pubkey) -> Option<T> {
+        let account = self.svm.get_account(address)?;
+        Some(T::unpack(&account.data).expect("unpacking a token account"))
+    }
+
+    fn set_packed<T: Pack>(&mut self, address: Pubkey, state: T) {
+        let mut data = vec![0; T::LEN];
+        state.pack_into_slice(&mut data);
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(T::LEN),
+            data,
+            owner: token::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.svm
+            .set_account(address, account)
+            .expect("writing a token account");
+    }
+
+    // The program
+
+    /// Creates the program config with the payer, the upgrade authority, as
+    /// admin, no fee tiers and
+    /// `policy`, unless there is one already.
+    pub fn ensure_config(&mut self, policy: MarketPolicy) {
+        if self.svm.get_account(&config_address().0).is_some() {
+            return;
+        }
+        let stake_mint = self.create_mint(6);
+        let instruction = instructions::initialize_config(
+            &self.payer.pubkey(),
+            &stake_mint,
+            0,
+            Vec::new(),
+            policy,
+            0,
+        );
+        expect_ok(self.send(&[instruction], &[]), "creating the config");
+    }
+
+    /// Creates a market ending `params.ends_in` seconds from now, and the
+    /// config with the default policy first when there isn't one. Panics,
+    /// with the program's logs, when the program refuses it.
+    pub fn create_market(
+        &mut self,
+        creator: &Keypair,
+        mint: &Pubkey,
+        params: MarketParams,
+    ) -> Pubkey {
+        self.ensure_config(MarketPolicy::default());
+        let new_market = NewMarket {
+            creator: creator.pubkey(),
+            mint: *mint,
+            market_id: params.market_id,
+        };
+        let end_ts = self.now() + params.ends_in;
+        let instruction = instructions::initialize_market(
+            &new_market,
+            params.fee_bps,
+            end_ts,
+            end_ts + params.resolve_window,
+            params.title,
+            params.options,
+        );
+        expect_ok(self.send(&[instruction], &[creator]), "creating a market");
+        new_market.market()
+    }
+
+    /// Bets `amount` raw units on `side`, accepting any odds.
+    pub fn bet(
+        &mut self,
+        user: &Keypair,
+        market: &Pubkey,
+        side: BetSide,
+        amount: u64,
+    ) -> TransactionResult {
+        let account = self.market(market);
+        let instruction =
+            instructions::place_bet_on(&user.pubkey(), market, &account, side, amount, 0);
+        self.send(&[instruction], &[user])
+    }
+
+    /// Sells a position back to the pool on a market allowing cash-outs.
+    pub fn cash_out(&mut self, user: &Keypair, market: &Pubkey) -> TransactionResult {
+        let mint = self.market(market).mint;
+        let instruction = instructions::cash_out(&user.pubkey(), market, &mint);
+        self.send(&[instruction], &[user])
+    }
+
+    /// Closes betting and resolves in one go, passing the parent market of
+    /// a conditional one.
+    pub fn resolve(
+        &mut self,
+        creator: &Keypair,
+        market: &Pubkey,
+        outcome: BetSide,
+    ) -> TransactionResult {
+        let parent = self
+            .market(market)
+            .dependency
+            .map(|dependency| dependency.market);
+        let instruction =
+            instructions::close_and_resolve(&creator.pubkey(), market, outcome, parent.as_ref());
+        self.send(&[instruction], &[creator])
+    }
+
+    /// Ends betting once `end_ts` has passed, leaving the market to its
+    /// creator to resolve.
+    pub fn close_betting(&mut self, market: &Pubkey) -> TransactionResult {
+        self.send(&[instructions::close_betting(market)], &[])
+    }
+
+    /// Cancels a market with betting closed that its creator didn't resolve
+    /// by the deadline.
+    pub fn cancel(&mut self, market: &Pubkey) -> TransactionResult {
+        self.send(&[instructions::cancel_expired(market)], &[])
+    }
+
+    pub fn claim(&mut self, user: &Keypair, market: &Pubkey) -> TransactionResult {
+        let mint = self.market(market).mint;
+        let instruction = instructions::claim(&user.pubkey(), market, &mint);
+        self.send(&[instruction], &[user])
+    }
+
+    pub fn withdraw_fee(&mut self, creator: &Keypair, market: &Pubkey) -> TransactionResult {
+        let mint = self.market(market).mint;
+        let instruction = instructions::withdraw_creator_fee(&creator.pubkey(), market, &mint);
+        self.send(&[instruction], &[creator])
+    }
+
+    // The clock
+
+    pub fn now(&self) -> i64 {
+        self.svm.get_sysvar::<Clock>().unix_timestamp
+    }
+
+    /// Sets the clock to `unix_timestamp`, a slot later.
+    pub fn warp_to(&mut self, unix_timestamp: i64) {
+        let mut clock: Clock = self.svm.get_sysvar();
+        clock.unix_timestamp = unix_timestamp;
+        clock.slot += 1;
+        self.svm.set_sysvar(&clock);
+    }
+
+    pub fn warp_by(&mut self, seconds: i64) {
+        self.warp_to(self.now() + seconds);
+    }
+
+    /// Moves the clock a second past when betting on `market` ends.
+    pub fn warp_past_end(&mut self, market: &Pubkey) {
+        self.warp_to(self.market(market).end_ts + 1);
+    }
+
+    /// Moves the clock a second past `market`'s resolve deadline, when
+    /// anyone may cancel it.
+    pub fn warp_past_deadline(&mut self, market: &Pubkey) {
+        self.warp_to(self.market(market).resolve_deadline_ts + 1);
+    }
+
+    // State
+
+    pub fn market(&self, address: &Pubkey) -> Market {
+        self.decode(address)
+            .unwrap_or_else(|| panic!("market {address} not found"))
+    }
+
+    /// `owner`'s position in `market`; `None` before they bet or once it's
+    /// closed.
+    pub fn position(&self, market: &Pubkey, owner: &Pubkey) -> Option<Position> {
+        self.decode(&position_address(market, owner).0)
+    }
+
+    /// The scheduled bet at `address`; `None` once it's placed or cancelled.
+    pub fn scheduled_bet(&self, address: &Pubkey) -> Option<ScheduledBet> {
+        self.decode(address)
+    }
+
+    /// `owner`'s portfolio; `None` until they open one.
+    pub fn portfolio(&self, owner: &Pubkey) -> Option<Portfolio> {
+        self.decode(&portfolio_address(owner).0)
+    }
+
+    /// What both of `market`'s side vaults hold together.
+    pub fn vault_balance(&self, market: &Pubkey) -> u64 {
+        self.side_vault_balance(market, BetSide::A) + self.side_vault_balance(market, BetSide::B)
+    }
+
+    pub fn side_vault_balance(&self, market: &Pubkey, side: BetSide) -> u64 {
+        self.token_amount(&vault_address(market, side).0)
+    }
+
+    fn decode<T: AccountDeserialize>(&self, address: &Pubkey) -> Option<T> {
+        let account = self.svm.get_account(address)?;
+        Some(
+            T::try_deserialize(&mut account.data.as_slice())
+                .unwrap_or_else(|err| panic!("decoding {address}: {err}")),
+        )
+    }
+
+    // Assertions
+
+    #[track_caller]
+    pub fn assert_status(&self, market: &Pubkey, status: MarketStatus) {
+        assert_eq!(self.market(market).status, status, "status of {market}");
+    }
+
+    #[track_caller]
+    pub fn assert_vault_balance(&self, market: &Pubkey, amount: u64) {
+        assert_eq!(self.vault_balance(market), amount, "vault of {market}");
+    }
+
+    #[track_caller]
+    pub fn assert_balance(&self, owner: &Pubkey, mint: &Pubkey, amount: u64) {
+        assert_eq!(
+            self.balance(owner, mint),
+            amount,
+            "{owner}'s balance of {mint}"
+        );
+    }
+
+    /// Asserts `owner` holds an unclaimed position of `amount` on `side`.
+    #[track_caller]
+    pub fn assert_position(&self, market: &Pubkey, owner: &Pubkey, side: BetSide, amount: u64) {
+        let position = self
+            .position(market, owner)
+            .unwrap_or_else(|| panic!("{owner} has no position in {market}"));
+        assert_eq!(
+            (position.side, position.amount, position.claimed),
+            (side, amount, false),
+            "{owner}'s position in {market}"
+        );
+    }
+}
+
+/// Asserts the transaction failed with the program's `error`.
+#[track_caller]
+/// `UpgradeableLoaderState::ProgramData` deployed at slot 0, as bincode
+/// lays it out ahead of the ELF.
+fn program_data_metadata(authority: Option<&Pubkey>) -> Vec<u8> {
+    let mut data = 3u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&0u64.to_le_bytes());
+    match authority {
+        Some(authority) => {
+            data.push(1);
+            data.extend_from_slice(authority.as_ref());
+        }
+        None => data.extend_from_slice(&[0; 33]),
+    }
+    data
+}
+
+pub fn assert_error(result: &TransactionResult, error: ErrorCode) {
+    let code = u32::from(error);
+    match result {
+        Err(failed) => assert!(
+            matches!(
+                failed.err,
+                TransactionError::InstructionError(_, InstructionError::Custom(found))
+                    if found == code
+            ),
+            "expected {error:?}, got {:?}; logs:\n{}",
+            failed.err,
+            failed.meta.logs.join("\n")
+        ),
+        Ok(_) => panic!("expected {error:?}, but the transaction succeeded"),
+    }
+}
+
+#[track_caller]
+fn expect_ok(result: TransactionResult, what: &str) {
+    if let Err(failed) = result {
+        panic!(
+            "{what}: {:?}; logs:\n{}",
+            failed.err,
+            failed.meta.logs.join("\n")
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! A cloned market keeps the terms its creator set on the source after
+//! opening it: fee splits, charity, large bet threshold and stake caps.
+
+use friend_bets_sdk::instructions::{self, NewMarket};
+use friend_bets_sdk::pda::token_account_address;
+use friend_bets_test::litesvm::types::TransactionResult;
+use friend_bets_test::{MarketParams, Signer, TestBed};
+use friends_bets::FeeSplit;
+
+#[track_caller]
+fn ok(result: TransactionResult) {
+    if let Err(failed) = result {
+        panic!("{:?}; logs:\n{}", failed.err, failed.meta.logs.join("\n"));
+    }
+}
+
+#[test]
+fn clone_keeps_source_terms() {
+    let mut bed = TestBed::new();
+    let mint = bed.create_mint(6);
+    let creator = bed.create_user(&mint, 0);
+    let partner = bed.create_user(&mint, 0);
+    let charity = bed.create_user(&mint, 0);
+    let source = bed.create_market(&creator, &mint, MarketParams::default());
+    let charity_account = token_account_address(&charity.pubkey(), &mint);
+    let splits = vec![FeeSplit {
+        recipient: partner.pubkey(),
+        bps: 2500,
+    }];
+    let terms = [
+        instructions::set_fee_splits(&creator.pubkey(), &source, splits.clone()),
+        instructions::set_charity(&creator.pubkey(), &source, Some(charity_account), 300),
+        instructions::set_large_bet_threshold(&creator.pubkey(), &source, 5_000_000),
+        instructions::set_stake_caps(&creator.pubkey(), &source, 7_000_000, 9_000_000, 30_000),
+    ];
+    ok(bed.send(&terms, &[&creator]));
+
+    let clone = NewMarket {
+        creator: creator.pubkey(),
+        mint,
+        market_id: 2,
+    };
+    let end_ts = bed.now() + 86_400;
+    let ix = instructions::clone_market(&clone, &source, end_ts, end_ts + 86_400);
+    ok(bed.send(&[ix], &[&creator]));
+
+    let market = bed.market(&clone.market());
+    assert_eq!(market.fee_splits, splits);
+    assert_eq!(market.charity, Some(charity_account));
+    assert_eq!(market.charity_bps, 300);
+    assert_eq!(market.large_bet_threshold, 5_000_000);
+    assert_eq!(
+        (market.cap_a, market.cap_b, market.max_ratio_bps),
+        (7_000_000, 9_000_000, 30_000)
+    );
+}
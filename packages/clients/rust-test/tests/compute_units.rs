@@ -0,0 +1,872 @@
+//! Compute units used by every instruction, checked against the baselines
+//! recorded in `compute-units.toml`.
+//!
+//! Each instruction is measured on its own transaction with accounts sized
+//! the way they are in use: titles at the longest the program takes, the
+//! most fee tiers a config holds, and batches, distributions and cleanups
+//! over several positions at once. The test fails when any instruction
+//! uses more than its baseline plus the file's threshold, so a change that
+//! creeps towards the compute budget is caught in review rather than on
+//! mainnet.
+//!
+//! After an intended change, rerecord the baselines and commit them:
+//!
+//! ```text
+//! FRIEND_BETS_CU_UPDATE=1 cargo test --test compute_units
+//! ```
+//!
+//! or run the Program workflow by hand with `record_compute_units` set and
+//! commit the `compute-units.toml` it uploads.
+//!
+//! Outside CI, instructions without a baseline yet are recorded on the
+//! first run; in CI (`$CI` set) they fail like a regression.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use friend_bets_sdk::compressed::CompressedPositions;
+use friend_bets_sdk::events;
+use friend_bets_sdk::instructions::{self, NewMarket};
+use friend_bets_sdk::pda::{market_title_address, scheduled_bet_address, token_account_address};
+use friend_bets_test::{Keypair, MarketParams, Signer, TestBed};
+use friends_bets::{
+    BatchBet, BetSide, ConfigUpdate, FeeSplit, FeeTier, MarketOptions, MarketPolicy,
+    CLAIM_WINDOW_SECS, DEFAULT_FEE,
+};
+
+/// The longest title `initialize_market` takes.
+const TITLE: &str = "Will everyone who chipped in still be on speaking terms by June?";
+const FUNDS: u64 = 1_000_000_000;
+const BET: u64 = 1_000_000;
+/// Bets in one `place_bets_batch`, and positions passed to `distribute` and
+/// `gc_market` or bet on a compressed market.
+const BATCH: usize = 4;
+const POSITIONS: usize = 8;
+
+const HEADER: &str = "\
+# Compute units each instruction used when last recorded, checked by
+# tests/compute_units.rs. After an intended change, rerecord with
+#
+#   FRIEND_BETS_CU_UPDATE=1 cargo test --test compute_units
+#
+# and commit the result with it. Running the Program workflow by hand with
+# record_compute_units set records them on CI's machine and uploads the
+# file to commit.
+";
+
+fn baseline_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("compute-units.toml")
+}
+
+struct Baselines {
+    /// How far over its baseline an instruction may go, in percent.
+    threshold_pct: u64,
+    units: BTreeMap<String, u64>,
+}
+
+impl Baselines {
+    fn load() -> Self {
+        let path = baseline_path();
+        let text = std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("reading {}: {err}", path.display()));
+        let table: toml::Table = text
+            .parse()
+            .unwrap_or_else(|err| panic!("parsing {}: {err}", path.display()));
+        let threshold_pct = table
+            .get("threshold_pct")
+            .and_then(toml::Value::as_integer)
+            .expect("threshold_pct") as u64;
+        let units = table
+            .get("units")
+            .and_then(toml::Value::as_table)
+            .map(|units| {
+                units
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = value.as_integer().expect("compute units") as u64;
+                        (name.clone(), value)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            threshold_pct,
+            units,
+        }
+    }
+
+    fn save(&self) {
+        let mut text = format!(
+            "{HEADER}\n# How far over its baseline an instruction may go, in percent.\n\
+             threshold_pct = {}\n\n[units]\n",
+            self.threshold_pct
+        );
+        for (name, units) in &self.units {
+            writeln!(text, "\"{name}\" = {units}").unwrap();
+        }
+        std::fs::write(baseline_path(), text).expect("writing the baselines");
+    }
+}
+
+/// Sends each measured instruction alone and records what it used.
+struct Bench {
+    bed: TestBed,
+    measured: BTreeMap<String, u64>,
+}
+
+impl Bench {
+    /// Returns the transaction's logs.
+    #[track_caller]
+    fn measure(
+        &mut self,
+        name: &str,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Vec<String> {
+        match self.bed.send(&[instruction], signers) {
+            Ok(meta) => {
+                self.measured
+                    .insert(name.into(), meta.compute_units_consumed);
+                meta.logs
+            }
+            Err(failed) => panic!(
+                "{name}: {:?}; logs:\n{}",
+                failed.err,
+                failed.meta.logs.join("\n")
+            ),
+        }
+    }
+
+    /// Runs an instruction the benchmarks need but don't measure, returning
+    /// the transaction's logs.
+    #[track_caller]
+    fn setup(&mut self, instruction: Instruction, signers: &[&Keypair]) -> Vec<String> {
+        match self.bed.send(&[instruction], signers) {
+            Ok(meta) => meta.logs,
+            Err(failed) => panic!(
+                "setting up: {:?}; logs:\n{}",
+                failed.err,
+                failed.meta.logs.join("\n")
+            ),
+        }
+    }
+
+    fn market(
+        &mut self,
+        creator: &Keypair,
+        mint: &Pubkey,
+        id: u64,
+        options: MarketOptions,
+    ) -> Pubkey {
+        self.bed.create_market(
+            creator,
+            mint,
+            MarketParams {
+                market_id: id,
+                title: TITLE.into(),
+                options,
+                ..MarketParams::default()
+            },
+        )
+    }
+}
+
+fn options() -> MarketOptions {
+    MarketOptions {
+        allow_cash_out: false,
+        cashout_haircut_bps: 0,
+        lock_odds: false,
+        burn_fee: false,
+    }
+}
+
+/// Markets pay into insurance, so collecting and compensating move tokens.
+fn policy() -> MarketPolicy {
+    MarketPolicy {
+        insurance_bps: 10,
+        ..MarketPolicy::default()
+    }
+}
+
+fn fee_tiers() -> Vec<FeeTier> {
+    [
+        (1_000, 1_000),
+        (10_000, 2_500),
+        (100_000, 5_000),
+        (1_000_000, 10_000),
+    ]
+    .into_iter()
+    .map(|(min_stake, discount_bps)| FeeTier {
+        min_stake,
+        discount_bps,
+        max_fee_bps: 1_000,
+    })
+    .collect()
+}
+
+fn measure_all() -> BTreeMap<String, u64> {
+    let mut bench = Bench {
+        bed: TestBed::new(),
+        measured: BTreeMap::new(),
+    };
+    let mint = bench.bed.create_mint(6);
+    let stake_mint = bench.bed.create_mint(6);
+    let creator = bench.bed.create_user(&mint, 0);
+    let alice = bench.bed.create_user(&mint, FUNDS);
+    let bob = bench.bed.create_user(&mint, FUNDS);
+    let carol = bench.bed.create_user(&mint, FUNDS);
+    bench.bed.fund(&bob.pubkey(), &stake_mint, FUNDS);
+    let bettors: Vec<Keypair> = (0..POSITIONS)
+        .map(|_| bench.bed.create_user(&mint, FUNDS))
+        .collect();
+    let bet = |user: &Keypair, market: &Pubkey, side| {
+        instructions::place_bet(&user.pubkey(), market, &mint, side, BET, 0)
+    };
+
+    // Markets need a config, here holding every fee tier it can, created by
+    // the creator as upgrade authority
+
+    bench.bed.set_upgrade_authority(Some(&creator.pubkey()));
+    bench.measure(
+        "initialize_config",
+        instructions::initialize_config(
+            &creator.pubkey(),
+            &stake_mint,
+            60,
+            fee_tiers(),
+            policy(),
+            0,
+        ),
+        &[&creator],
+    );
+
+    // While betting is open
+
+    let new_market = NewMarket {
+        creator: creator.pubkey(),
+        mint,
+        market_id: 1,
+    };
+    let end_ts = bench.bed.now() + 86_400;
+    bench.measure(
+        "initialize_market",
+        instructions::initialize_market(
+            &new_market,
+            200,
+            end_ts,
+            end_ts + 86_400,
+            TITLE.into(),
+            options(),
+        ),
+        &[&creator],
+    );
+    let main = new_market.market();
+    let clone = NewMarket {
+        market_id: 2,
+        ..new_market.clone()
+    };
+    bench.measure(
+        "clone_market",
+        instructions::clone_market(&clone, &main, end_ts, end_ts + 86_400),
+        &[&creator],
+    );
+    bench.measure(
+        "initialize_market_with_bet",
+        instructions::initialize_market_with_bet(
+            &NewMarket {
+                creator: alice.pubkey(),
+                mint,
+                market_id: 25,
+            },
+            200,
+            end_ts,
+            end_ts + 86_400,
+            TITLE.into(),
+            options(),
+            BetSide::A,
+            BET,
+        ),
+        &[&alice],
+    );
+
+    bench.measure("place_bet", bet(&alice, &main, BetSide::A), &[&alice]);
+    bench.measure(
+        "place_bet/top_up",
+        bet(&alice, &main, BetSide::A),
+        &[&alice],
+    );
+    bench.measure(
+        "place_bet_init_ata",
+        instructions::place_bet_init_ata(&alice.pubkey(), &main, &mint, BetSide::A, BET, 0),
+        &[&alice],
+    );
+    bench.setup(bet(&bob, &main, BetSide::A), &[&bob]);
+    bench.measure(
+        "open_portfolio",
+        instructions::open_portfolio(&carol.pubkey()),
+        &[&carol],
+    );
+    // Carol's first entry opens her portfolio's first page
+    bench.measure(
+        "place_bet/portfolio",
+        instructions::with_portfolio(
+            bet(&carol, &main, BetSide::B),
+            &bench.bed.portfolio(&carol.pubkey()).unwrap(),
+        ),
+        &[&carol],
+    );
+
+    let locked = bench.market(
+        &creator,
+        &mint,
+        3,
+        MarketOptions {
+            lock_odds: true,
+            ..options()
+        },
+    );
+    bench.setup(bet(&bob, &locked, BetSide::B), &[&bob]);
+    bench.measure(
+        "place_bet/lock_odds",
+        bet(&alice, &locked, BetSide::A),
+        &[&alice],
+    );
+
+    let cash_out = bench.market(
+        &creator,
+        &mint,
+        4,
+        MarketOptions {
+            allow_cash_out: true,
+            cashout_haircut_bps: 500,
+            ..options()
+        },
+    );
+    bench.setup(bet(&alice, &cash_out, BetSide::A), &[&alice]);
+    bench.setup(bet(&bob, &cash_out, BetSide::B), &[&bob]);
+    bench.measure(
+        "cash_out",
+        instructions::cash_out(&alice.pubkey(), &cash_out, &mint),
+        &[&alice],
+    );
+    // An order with no floor is due straight away...
+    bench.measure(
+        "set_cashout_order",
+        instructions::set_cashout_order(&bob.pubkey(), &cash_out, u64::MAX),
+        &[&bob],
+    );
+    // ...in the slot after the stakes last moved
+    bench.bed.warp_by(1);
+    bench.measure(
+        "execute_cashout",
+        instructions::execute_cashout(&carol.pubkey(), &cash_out, &bob.pubkey(), &mint),
+        &[&carol],
+    );
+
+    let original = bench.market(&creator, &mint, 5, options());
+    bench.setup(bet(&alice, &original, BetSide::A), &[&alice]);
+    let fork = NewMarket {
+        market_id: 6,
+        ..new_market.clone()
+    };
+    bench.measure(
+        "fork_market",
+        instructions::fork_market(
+            &fork,
+            &creator.pubkey(),
+            &original,
+            end_ts,
+            end_ts + 86_400,
+            TITLE.into(),
+        ),
+        &[&creator],
+    );
+
+    let parent = bench.market(&creator, &mint, 7, options());
+    let dependent = bench.market(&creator, &mint, 8, options());
+    bench.measure(
+        "set_dependency",
+        instructions::set_dependency(&creator.pubkey(), &dependent, &parent, BetSide::A),
+        &[&creator],
+    );
+    // Forking the parent cancels it, which fails the dependency
+    let parent_fork = NewMarket {
+        market_id: 9,
+        ..new_market.clone()
+    };
+    bench.setup(
+        instructions::fork_market(
+            &parent_fork,
+            &creator.pubkey(),
+            &parent,
+            end_ts,
+            end_ts + 86_400,
+            TITLE.into(),
+        ),
+        &[&creator],
+    );
+    bench.measure(
+        "void_dependent",
+        instructions::void_dependent(&dependent, &parent),
+        &[],
+    );
+
+    let batch: Vec<(BatchBet, Pubkey)> = (0..BATCH as u64)
+        .map(|i| {
+            let market = bench.market(&creator, &mint, 10 + i, options());
+            let bet = BatchBet {
+                market,
+                side: BetSide::A,
+                amount: BET,
+                min_odds_bps: 0,
+            };
+            (bet, mint)
+        })
+        .collect();
+    bench.measure(
+        &format!("place_bets_batch/{BATCH}"),
+        instructions::place_bets_batch(&alice.pubkey(), &batch),
+        &[&alice],
+    );
+
+    let distributed = bench.market(&creator, &mint, 20, options());
+    for (i, bettor) in bettors.iter().enumerate() {
+        let side = if i % 2 == 0 { BetSide::A } else { BetSide::B };
+        bench.setup(bet(bettor, &distributed, side), &[bettor]);
+    }
+    let burned = bench.market(
+        &creator,
+        &mint,
+        21,
+        MarketOptions {
+            burn_fee: true,
+            ..options()
+        },
+    );
+    bench.setup(bet(&alice, &burned, BetSide::A), &[&alice]);
+    bench.setup(bet(&bob, &burned, BetSide::B), &[&bob]);
+    let expired = bench.market(&creator, &mint, 22, options());
+    bench.setup(bet(&alice, &expired, BetSide::A), &[&alice]);
+
+    let compressed = bench.market(&creator, &mint, 23, options());
+    bench.measure(
+        "enable_compressed_positions",
+        instructions::enable_compressed_positions(&creator.pubkey(), &compressed),
+        &[&creator],
+    );
+    let mut tree = CompressedPositions::new(compressed);
+    for (i, bettor) in bettors.iter().enumerate() {
+        let side = if i % 2 == 0 { BetSide::A } else { BetSide::B };
+        let bet =
+            instructions::place_compressed_bet(&bettor.pubkey(), &compressed, &mint, side, BET, 0);
+        let logs = if i == 0 {
+            bench.measure("place_compressed_bet", bet, &[bettor])
+        } else {
+            bench.setup(bet, &[bettor])
+        };
+        track(&mut tree, &logs);
+    }
+
+    let tracked = bench.market(&creator, &mint, 24, options());
+    bench.measure(
+        "enable_bettor_pages",
+        instructions::enable_bettor_pages(&creator.pubkey(), &tracked),
+        &[&creator],
+    );
+    let tracked_bet = |bench: &Bench, user: &Keypair| {
+        let market = bench.bed.market(&tracked);
+        instructions::place_bet_on(&user.pubkey(), &tracked, &market, BetSide::A, BET, 0)
+    };
+    bench.measure(
+        "place_bet/new_bettor_page",
+        tracked_bet(&bench, &alice),
+        &[&alice],
+    );
+    bench.measure("place_bet/bettor_page", tracked_bet(&bench, &bob), &[&bob]);
+
+    let split = bench.market(&creator, &mint, 25, options());
+    let splits = [&alice, &bob, &bettors[0]]
+        .into_iter()
+        .map(|recipient| FeeSplit {
+            recipient: recipient.pubkey(),
+            bps: 1000,
+        })
+        .collect();
+    bench.measure(
+        "set_fee_splits",
+        instructions::set_fee_splits(&creator.pubkey(), &split, splits),
+        &[&creator],
+    );
+    let charity = token_account_address(&bettors[1].pubkey(), &mint);
+    bench.measure(
+        "set_charity",
+        instructions::set_charity(&creator.pubkey(), &split, Some(charity), 1000),
+        &[&creator],
+    );
+    bench.measure(
+        "set_large_bet_threshold",
+        instructions::set_large_bet_threshold(&creator.pubkey(), &split, BET),
+        &[&creator],
+    );
+    bench.measure(
+        "place_bet/large_bet",
+        bet(&alice, &split, BetSide::A),
+        &[&alice],
+    );
+    bench.measure(
+        "enable_activity_stats",
+        instructions::enable_activity_stats(&creator.pubkey(), &split),
+        &[&creator],
+    );
+    bench.measure(
+        "place_bet/activity",
+        instructions::place_bet_on(
+            &bob.pubkey(),
+            &split,
+            &bench.bed.market(&split),
+            BetSide::B,
+            BET,
+            0,
+        ),
+        &[&bob],
+    );
+    bench.measure(
+        "set_stake_caps",
+        instructions::set_stake_caps(&creator.pubkey(), &split, BET * 10, BET * 10, 40_000),
+        &[&creator],
+    );
+    bench.measure(
+        "register_market_title",
+        instructions::register_market_title(&creator.pubkey(), &split, TITLE, 0),
+        &[&creator],
+    );
+
+    // Scheduled bets, one placed by a keeper and one cancelled
+    let schedule = |id| {
+        instructions::schedule_bet(
+            &carol.pubkey(),
+            &main,
+            &mint,
+            id,
+            BetSide::B,
+            BET,
+            0,
+            bench.bed.now(),
+            end_ts,
+        )
+    };
+    let (first, second) = (schedule(0), schedule(1));
+    bench.measure("schedule_bet", first, &[&carol]);
+    bench.setup(second, &[&carol]);
+    let scheduled = scheduled_bet_address(&main, &carol.pubkey(), 0).0;
+    bench.measure(
+        "execute_scheduled_bet",
+        instructions::execute_scheduled_bet(
+            &alice.pubkey(),
+            &scheduled,
+            &bench.bed.scheduled_bet(&scheduled).unwrap(),
+            &bench.bed.market(&main),
+        ),
+        &[&alice],
+    );
+    let scheduled = scheduled_bet_address(&main, &carol.pubkey(), 1).0;
+    bench.measure(
+        "cancel_scheduled_bet",
+        instructions::cancel_scheduled_bet(
+            &carol.pubkey(),
+            &scheduled,
+            &bench.bed.scheduled_bet(&scheduled).unwrap(),
+            &mint,
+        ),
+        &[&carol],
+    );
+
+    // Staking
+    let update = ConfigUpdate {
+        admin: creator.pubkey(),
+        unbonding_secs: 60,
+        fee_tiers: fee_tiers(),
+        policy: policy(),
+        update_delay_secs: 0,
+    };
+    bench.measure(
+        "propose_config_update",
+        instructions::propose_config_update(&creator.pubkey(), update),
+        &[&creator],
+    );
+    bench.measure(
+        "apply_config_update",
+        instructions::apply_config_update(),
+        &[],
+    );
+    bench.measure(
+        "stake",
+        instructions::stake(&bob.pubkey(), &stake_mint, 1_000_001),
+        &[&bob],
+    );
+    bench.measure(
+        "place_bet/stake_rebate",
+        instructions::with_bettor_stake(bet(&bob, &main, BetSide::A), &bob.pubkey()),
+        &[&bob],
+    );
+    let staker_market = NewMarket {
+        creator: bob.pubkey(),
+        mint,
+        market_id: 1,
+    };
+    bench.measure(
+        "initialize_market/creator_stake",
+        instructions::with_creator_stake(
+            instructions::initialize_market(
+                &staker_market,
+                DEFAULT_FEE,
+                0,
+                0,
+                TITLE.into(),
+                options(),
+            ),
+            &bob.pubkey(),
+        ),
+        &[&bob],
+    );
+    bench.measure("unstake", instructions::unstake(&bob.pubkey(), 1), &[&bob]);
+
+    // Betting over
+
+    bench.bed.warp_past_end(&main);
+    bench.measure("close_betting", instructions::close_betting(&main), &[]);
+    bench.measure(
+        "resolve",
+        instructions::resolve(&creator.pubkey(), &main, BetSide::A, None),
+        &[&creator],
+    );
+    bench.measure(
+        "close_and_resolve",
+        instructions::close_and_resolve(&creator.pubkey(), &distributed, BetSide::A, None),
+        &[&creator],
+    );
+    bench.setup(
+        instructions::close_and_resolve(&creator.pubkey(), &burned, BetSide::A, None),
+        &[&creator],
+    );
+    bench.setup(instructions::close_betting(&expired), &[]);
+    bench.setup(
+        instructions::close_and_resolve(&creator.pubkey(), &compressed, BetSide::A, None),
+        &[&creator],
+    );
+    bench.setup(
+        instructions::close_and_resolve(&creator.pubkey(), &tracked, BetSide::A, None),
+        &[&creator],
+    );
+    bench.setup(
+        instructions::close_and_resolve(&creator.pubkey(), &split, BetSide::A, None),
+        &[&creator],
+    );
+    bench.measure(
+        "withdraw_unstaked",
+        instructions::withdraw_unstaked(&bob.pubkey(), &stake_mint),
+        &[&bob],
+    );
+
+    bench.measure(
+        "claim",
+        instructions::claim(&alice.pubkey(), &main, &mint),
+        &[&alice],
+    );
+    bench.measure(
+        "claim/stake_rebate",
+        instructions::claim(&bob.pubkey(), &main, &mint),
+        &[&bob],
+    );
+    bench.measure(
+        "withdraw_creator_fee",
+        instructions::withdraw_creator_fee(&creator.pubkey(), &main, &mint),
+        &[&creator],
+    );
+    bench.measure("burn_fee", instructions::burn_fee(&burned, &mint), &[]);
+    bench.measure(
+        "distribute_fees/3",
+        instructions::distribute_fees(&split, &bench.bed.market(&split)),
+        &[],
+    );
+    bench.measure(
+        "donate_to_charity",
+        instructions::donate_to_charity(&split, &charity),
+        &[],
+    );
+    let owners: Vec<Pubkey> = bettors.iter().map(|bettor| bettor.pubkey()).collect();
+    bench.measure(
+        &format!("distribute/{POSITIONS}"),
+        instructions::distribute(&distributed, &mint, &owners),
+        &[],
+    );
+    bench.setup(
+        instructions::withdraw_creator_fee(&creator.pubkey(), &distributed, &mint),
+        &[&creator],
+    );
+    bench.measure(
+        "collect_insurance",
+        instructions::collect_insurance(&alice.pubkey(), &distributed, &mint),
+        &[&alice],
+    );
+    // Every winner claims, the first measured, so the market can be collected
+    for (leaf_index, bettor) in bettors.iter().enumerate().step_by(2) {
+        let claim = tree.claim(leaf_index as u32, &mint).expect("tracked bet");
+        let logs = if leaf_index == 0 {
+            bench.measure("claim_compressed", claim, &[bettor])
+        } else {
+            bench.setup(claim, &[bettor])
+        };
+        track(&mut tree, &logs);
+    }
+    bench.setup(
+        instructions::withdraw_creator_fee(&creator.pubkey(), &compressed, &mint),
+        &[&creator],
+    );
+    bench.setup(
+        instructions::collect_insurance(&alice.pubkey(), &compressed, &mint),
+        &[&alice],
+    );
+    // Once everyone is paid, the market can shrink to its record
+    for bettor in [&alice, &bob] {
+        bench.setup(
+            instructions::claim(&bettor.pubkey(), &tracked, &mint),
+            &[bettor],
+        );
+    }
+    bench.setup(
+        instructions::withdraw_creator_fee(&creator.pubkey(), &tracked, &mint),
+        &[&creator],
+    );
+    bench.measure(
+        "compact_market",
+        instructions::compact_market(&creator.pubkey(), &tracked),
+        &[&creator],
+    );
+    assert!(bench.bed.market(&tracked).title.is_empty());
+
+    bench.bed.warp_past_deadline(&expired);
+    bench.measure(
+        "cancel_expired",
+        instructions::cancel_expired(&expired),
+        &[],
+    );
+    bench.measure(
+        "compensate",
+        instructions::compensate(&creator.pubkey(), &expired, &mint, &alice.pubkey(), 1),
+        &[&creator],
+    );
+
+    // After the claim window
+
+    let deadline = bench.bed.market(&distributed).resolve_deadline_ts;
+    bench.bed.warp_to(deadline + CLAIM_WINDOW_SECS);
+    bench.measure(
+        &format!("gc_market/{POSITIONS}"),
+        instructions::gc_market(
+            &alice.pubkey(),
+            &distributed,
+            &creator.pubkey(),
+            &mint,
+            &owners,
+        ),
+        &[&alice],
+    );
+    bench.measure(
+        "close_portfolio",
+        instructions::close_portfolio(&bench.bed.portfolio(&carol.pubkey()).unwrap()),
+        &[&carol],
+    );
+    bench.measure(
+        "close_bettor_page",
+        instructions::close_bettor_page(&tracked, 0, &alice.pubkey()),
+        &[],
+    );
+    bench.measure(
+        "close_activity_stats",
+        instructions::close_activity_stats(&split, &creator.pubkey()),
+        &[],
+    );
+    bench.measure(
+        "close_market_title",
+        instructions::close_market_title(
+            &split,
+            &market_title_address(TITLE, 0).0,
+            &creator.pubkey(),
+        ),
+        &[],
+    );
+    bench.measure(
+        "gc_market/compressed",
+        instructions::gc_compressed_market(&alice.pubkey(), &compressed, &creator.pubkey(), &mint),
+        &[&alice],
+    );
+
+    bench.measured
+}
+
+/// Applies the compressed-position events in `logs` to `tree`.
+fn track(tree: &mut CompressedPositions, logs: &[String]) {
+    for event in events::parse_logs(logs).expect("program events decode") {
+        tree.apply(&event).expect("events arrive in order");
+    }
+}
+
+#[test]
+fn compute_units_stay_within_baselines() {
+    let measured = measure_all();
+    let mut baselines = Baselines::load();
+
+    if env::var_os("FRIEND_BETS_CU_UPDATE").is_some() {
+        baselines.units = measured;
+        baselines.save();
+        return;
+    }
+
+    let in_ci = env::var_os("CI").is_some();
+    let mut failures = Vec::new();
+    let mut recorded = Vec::new();
+    for (name, &units) in &measured {
+        let Some(&baseline) = baselines.units.get(name) else {
+            if in_ci {
+                failures.push(format!("{name}: {units} CU, with no baseline recorded"));
+            } else {
+                recorded.push(format!("{name}: {units} CU"));
+                baselines.units.insert(name.clone(), units);
+            }
+            continue;
+        };
+        let limit = baseline + baseline * baselines.threshold_pct / 100;
+        if units > limit {
+            failures.push(format!(
+                "{name}: {units} CU, up from {baseline} (limit {limit})"
+            ));
+        } else if units + baseline * baselines.threshold_pct / 100 < baseline {
+            println!("{name}: {units} CU, down from {baseline}; consider rerecording");
+        }
+    }
+    for name in baselines.units.keys() {
+        if !measured.contains_key(name) {
+            failures.push(format!("{name}: has a baseline but is no longer measured"));
+        }
+    }
+
+    if !recorded.is_empty() {
+        baselines.save();
+        println!(
+            "recorded new baselines in {}:\n  {}",
+            baseline_path().display(),
+            recorded.join("\n  ")
+        );
+    }
+    assert!(
+        failures.is_empty(),
+        "compute units off their baselines (threshold {}%):\n  {}",
+        baselines.threshold_pct,
+        failures.join("\n  ")
+    );
+}
@@ -0,0 +1,96 @@
+//! Accounts the program opens at addresses anyone can derive still open
+//! when someone has sent lamports there first. A plain `create_account`
+//! fails on such an address as already in use, so one transfer would block
+//! every bet needing the account for good.
+
+use anchor_lang::prelude::Pubkey;
+use friend_bets_sdk::instructions;
+use friend_bets_sdk::pda::{bettor_page_address, portfolio_page_address, position_address};
+use friend_bets_test::litesvm::types::TransactionResult;
+use friend_bets_test::{MarketParams, Signer, TestBed};
+use friends_bets::{BatchBet, BetSide};
+
+const FUNDS: u64 = 1_000_000_000;
+const BET: u64 = 1_000_000;
+
+/// Sends `address` the least a transfer can leave in a new account, short
+/// of the rent any of the program's accounts need.
+fn prefund(bed: &mut TestBed, address: &Pubkey) {
+    let lamports = bed.svm.minimum_balance_for_rent_exemption(0);
+    bed.svm.airdrop(address, lamports).expect("prefunding");
+}
+
+#[track_caller]
+fn ok(result: TransactionResult) {
+    if let Err(failed) = result {
+        panic!("{:?}; logs:\n{}", failed.err, failed.meta.logs.join("\n"));
+    }
+}
+
+#[test]
+fn prefunded_bettor_page_opens() {
+    let mut bed = TestBed::new();
+    let mint = bed.create_mint(6);
+    let creator = bed.create_user(&mint, 0);
+    let alice = bed.create_user(&mint, FUNDS);
+    let market = bed.create_market(&creator, &mint, MarketParams::default());
+    let enable = instructions::enable_bettor_pages(&creator.pubkey(), &market);
+    ok(bed.send(&[enable], &[&creator]));
+
+    let page = bettor_page_address(&market, 0).0;
+    prefund(&mut bed, &page);
+    ok(bed.bet(&alice, &market, BetSide::A, BET));
+
+    bed.assert_position(&market, &alice.pubkey(), BetSide::A, BET);
+    assert_eq!(bed.market(&market).bettor_pages, 1);
+    let page = bed.svm.get_account(&page).expect("the page");
+    assert_eq!(page.owner, friend_bets_test::PROGRAM_ID);
+    assert!(page.lamports >= bed.svm.minimum_balance_for_rent_exemption(page.data.len()));
+}
+
+#[test]
+fn prefunded_position_opens_in_a_batch() {
+    let mut bed = TestBed::new();
+    let mint = bed.create_mint(6);
+    let creator = bed.create_user(&mint, 0);
+    let alice = bed.create_user(&mint, FUNDS);
+    let market = bed.create_market(&creator, &mint, MarketParams::default());
+
+    prefund(&mut bed, &position_address(&market, &alice.pubkey()).0);
+    let bet = BatchBet {
+        market,
+        side: BetSide::B,
+        amount: BET,
+        min_odds_bps: 0,
+    };
+    let batch = instructions::place_bets_batch(&alice.pubkey(), &[(bet, mint)]);
+    ok(bed.send(&[batch], &[&alice]));
+
+    bed.assert_position(&market, &alice.pubkey(), BetSide::B, BET);
+}
+
+#[test]
+fn prefunded_portfolio_page_opens() {
+    let mut bed = TestBed::new();
+    let mint = bed.create_mint(6);
+    let creator = bed.create_user(&mint, 0);
+    let alice = bed.create_user(&mint, FUNDS);
+    let market = bed.create_market(&creator, &mint, MarketParams::default());
+    let open = instructions::open_portfolio(&alice.pubkey());
+    ok(bed.send(&[open], &[&alice]));
+
+    prefund(&mut bed, &portfolio_page_address(&alice.pubkey(), 0).0);
+    let bet = instructions::place_bet_on(
+        &alice.pubkey(),
+        &market,
+        &bed.market(&market),
+        BetSide::A,
+        BET,
+        0,
+    );
+    let portfolio = bed.portfolio(&alice.pubkey()).expect("the portfolio");
+    ok(bed.send(&[instructions::with_portfolio(bet, &portfolio)], &[&alice]));
+
+    let portfolio = bed.portfolio(&alice.pubkey()).expect("the portfolio");
+    assert_eq!((portfolio.pages, portfolio.entry_count), (1, 1));
+}
@@ -0,0 +1,484 @@
+//! Random sequences of market instructions, with the program's invariants
+//! checked after every step.
+//!
+//! Each run creates markets with random fees, windows and odds locking,
+//! then picks at random among creating another market, betting, closing
+//! betting, resolving (as the creator or not), cancelling, claiming,
+//! withdrawing the fee and moving the clock. The program refusing an
+//! instruction is fine; what's checked is what it lets through and the
+//! state left behind:
+//!
+//! - tokens are only moved between accounts, never made or lost;
+//! - a market's vaults hold at least what it still owes, and until it's
+//!   decided each side's vault holds at least that side's stakes;
+//! - a market's pools and open position count match its positions;
+//! - markets only move forward through their statuses, positions never
+//!   change side and claims are never undone;
+//! - instructions only succeed when the program's rules allow them, and
+//!   claims pay exactly the position's payout.
+//!
+//! `FUZZ_SEED` replays a run; `FUZZ_RUNS` and `FUZZ_STEPS` size them:
+//!
+//! ```text
+//! FUZZ_RUNS=2000 cargo test --release --test sequences
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anchor_lang::prelude::Pubkey;
+use friend_bets_sdk::instructions;
+use friend_bets_test::{Keypair, MarketParams, Signer, TestBed};
+use friends_bets::{BetSide, Market, MarketOptions, MarketStatus, Position};
+
+const USERS: usize = 4;
+/// What each user starts with, in raw units.
+const FUNDS: u64 = 1_000_000_000;
+const DAY: i64 = 86_400;
+
+/// xorshift64*, so a seed replays the same run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    fn side(&mut self) -> BetSide {
+        if self.chance(50) {
+            BetSide::A
+        } else {
+            BetSide::B
+        }
+    }
+}
+
+/// A market and every user's position in it, in `users` order.
+#[derive(Clone)]
+struct Seen {
+    market: Market,
+    positions: Vec<Option<Position>>,
+}
+
+struct Run {
+    bed: TestBed,
+    rng: Rng,
+    mint: Pubkey,
+    creator: Keypair,
+    users: Vec<Keypair>,
+    markets: Vec<Pubkey>,
+    next_id: u64,
+}
+
+#[test]
+fn random_sequences_keep_invariants() {
+    let runs: u64 = setting("FUZZ_RUNS", 20);
+    let steps: u64 = setting("FUZZ_STEPS", 200);
+    let first = match env::var("FUZZ_SEED") {
+        Ok(seed) => seed.parse().expect("FUZZ_SEED is a number"),
+        Err(_) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |now| now.as_nanos() as u64),
+    };
+    for run in 0..runs {
+        // Never zero, which xorshift can't leave
+        let seed = first.wrapping_add(run).max(1);
+        eprintln!("run {run}: FUZZ_SEED={seed}");
+        Run::new(seed).play(steps);
+    }
+}
+
+impl Run {
+    fn new(seed: u64) -> Self {
+        let mut bed = TestBed::new();
+        let mint = bed.create_mint(6);
+        let creator = bed.create_user(&mint, 0);
+        let users = (0..USERS).map(|_| bed.create_user(&mint, FUNDS)).collect();
+        let mut run = Self {
+            bed,
+            rng: Rng(seed),
+            mint,
+            creator,
+            users,
+            markets: Vec::new(),
+            next_id: 1,
+        };
+        run.create_market();
+        run
+    }
+
+    fn play(&mut self, steps: u64) {
+        let mut before = self.observe();
+        for step in 0..steps {
+            let action = self.step(&before);
+            let after = self.observe();
+            self.check(&before, &after)
+                .unwrap_or_else(|problem| panic!("step {step} ({action}): {problem}"));
+            before = after;
+        }
+    }
+
+    /// Takes one random action against the state `seen` before it, checking
+    /// that the program allowed only what its rules do.
+    fn step(&mut self, seen: &HashMap<Pubkey, Seen>) -> String {
+        let now = self.bed.now();
+        let market = self.markets[self.rng.below(self.markets.len() as u64) as usize];
+        let state = &seen[&market].market;
+        let index = self.rng.below(USERS as u64) as usize;
+        match self.rng.below(10) {
+            0 if self.markets.len() < 4 => {
+                self.create_market();
+                "initialize_market".into()
+            }
+            0..=2 => {
+                let user = &self.users[index];
+                let balance = self.bed.balance(&user.pubkey(), &self.mint);
+                let amount = match self.rng.below(4) {
+                    0 => 0,
+                    1 => balance + 1,
+                    _ => 1 + self.rng.below(balance.max(1)),
+                };
+                let side = self.rng.side();
+                let held = seen[&market].positions[index]
+                    .as_ref()
+                    .filter(|position| position.amount > 0)
+                    .map(|position| position.side);
+                let result = self.bed.bet(user, &market, side, amount);
+                if result.is_ok() {
+                    assert!(
+                        state.status == MarketStatus::Open
+                            && now < state.end_ts
+                            && amount > 0
+                            && amount <= balance
+                            && held.is_none_or(|held| held == side),
+                        "bet of {amount} on {side:?} allowed on a {:?} market at {now}, \
+                         ending {}, holding {held:?}",
+                        state.status,
+                        state.end_ts
+                    );
+                    assert_eq!(
+                        self.bed.balance(&user.pubkey(), &self.mint),
+                        balance - amount,
+                        "bet took a different amount"
+                    );
+                }
+                format!("place_bet {side:?} {amount} by user {index}")
+            }
+            3 => {
+                let result = self.bed.close_betting(&market);
+                if result.is_ok() {
+                    assert!(
+                        state.status == MarketStatus::Open && now >= state.end_ts,
+                        "betting closed on a {:?} market at {now}, ending {}",
+                        state.status,
+                        state.end_ts
+                    );
+                }
+                "close_betting".into()
+            }
+            4 => {
+                let by_creator = self.rng.chance(80);
+                let signer = if by_creator {
+                    &self.creator
+                } else {
+                    &self.users[index]
+                };
+                let outcome = self.rng.side();
+                let combined = self.rng.chance(50);
+                let instruction = if combined {
+                    instructions::close_and_resolve(&signer.pubkey(), &market, outcome, None)
+                } else {
+                    instructions::resolve(&signer.pubkey(), &market, outcome, None)
+                };
+                let result = self.bed.send(&[instruction], &[signer]);
+                if result.is_ok() {
+                    let status = if combined {
+                        MarketStatus::Open
+                    } else {
+                        MarketStatus::PendingResolve
+                    };
+                    assert!(
+                        by_creator
+                            && state.status == status
+                            && now >= state.end_ts
+                            && now < state.resolve_deadline_ts,
+                        "resolved a {:?} market at {now} (end {}, deadline {}), by the \
+                         creator: {by_creator}",
+                        state.status,
+                        state.end_ts,
+                        state.resolve_deadline_ts
+                    );
+                }
+                let name = if combined {
+                    "close_and_resolve"
+                } else {
+                    "resolve"
+                };
+                format!("{name} {outcome:?}, by the creator: {by_creator}")
+            }
+            5 => {
+                let result = self.bed.cancel(&market);
+                if result.is_ok() {
+                    assert!(
+                        state.status == MarketStatus::PendingResolve
+                            && now >= state.resolve_deadline_ts,
+                        "cancelled a {:?} market at {now}, deadline {}",
+                        state.status,
+                        state.resolve_deadline_ts
+                    );
+                }
+                "cancel_expired".into()
+            }
+            6 => {
+                let user = &self.users[index];
+                let balance = self.bed.balance(&user.pubkey(), &self.mint);
+                let result = self.bed.claim(user, &market);
+                if result.is_ok() {
+                    let position = seen[&market].positions[index]
+                        .clone()
+                        .expect("claimed without a position");
+                    assert!(!position.claimed, "claimed twice");
+                    // Claiming cancels a market left unresolved past its deadline
+                    let mut state = state.clone();
+                    state.cancel_if_expired(now);
+                    let payout = state.payout_for(&position).expect("a settled market");
+                    assert_eq!(
+                        self.bed.balance(&user.pubkey(), &self.mint),
+                        balance + payout,
+                        "claim paid other than the position's payout"
+                    );
+                }
+                format!("claim by user {index}")
+            }
+            7 => {
+                let balance = self.bed.balance(&self.creator.pubkey(), &self.mint);
+                let result = self.bed.withdraw_fee(&self.creator, &market);
+                if result.is_ok() {
+                    assert!(
+                        state.status == MarketStatus::Resolved && !state.creator_fee_withdrawn,
+                        "fee withdrawn from a {:?} market, withdrawn before: {}",
+                        state.status,
+                        state.creator_fee_withdrawn
+                    );
+                    let due = state.creator_fee_due().expect("the fee due");
+                    assert_eq!(
+                        self.bed.balance(&self.creator.pubkey(), &self.mint),
+                        balance + due,
+                        "withdrew other than the fee due"
+                    );
+                }
+                "withdraw_creator_fee".into()
+            }
+            _ => {
+                // Mostly small steps, sometimes past whole windows
+                let seconds = match self.rng.below(3) {
+                    0 => self.rng.below(60) as i64,
+                    1 => self.rng.below(DAY as u64) as i64,
+                    _ => self.rng.below(3 * DAY as u64) as i64,
+                };
+                self.bed.warp_by(seconds);
+                format!("warp {seconds}s")
+            }
+        }
+    }
+
+    fn create_market(&mut self) {
+        let params = MarketParams {
+            market_id: self.next_id,
+            fee_bps: self.rng.below(2_001) as u16,
+            ends_in: 1 + self.rng.below(2 * DAY as u64) as i64,
+            resolve_window: 1 + self.rng.below(2 * DAY as u64) as i64,
+            title: format!("Fuzz market {}", self.next_id),
+            options: MarketOptions {
+                allow_cash_out: false,
+                cashout_haircut_bps: 0,
+                lock_odds: self.rng.chance(50),
+                burn_fee: false,
+            },
+        };
+        self.next_id += 1;
+        let market = self.bed.create_market(&self.creator, &self.mint, params);
+        self.markets.push(market);
+    }
+
+    fn observe(&self) -> HashMap<Pubkey, Seen> {
+        self.markets
+            .iter()
+            .map(|market| {
+                let positions = self
+                    .users
+                    .iter()
+                    .map(|user| self.bed.position(market, &user.pubkey()))
+                    .collect();
+                let seen = Seen {
+                    market: self.bed.market(market),
+                    positions,
+                };
+                (*market, seen)
+            })
+            .collect()
+    }
+
+    fn check(
+        &self,
+        before: &HashMap<Pubkey, Seen>,
+        after: &HashMap<Pubkey, Seen>,
+    ) -> Result<(), String> {
+        // Conservation: everything minted is in a wallet or a vault
+        let wallets: u64 = self
+            .users
+            .iter()
+            .chain([&self.creator])
+            .map(|user| self.bed.balance(&user.pubkey(), &self.mint))
+            .sum();
+        let vaults: u64 = self
+            .markets
+            .iter()
+            .map(|market| self.bed.vault_balance(market))
+            .sum();
+        if wallets + vaults != FUNDS * USERS as u64 {
+            return Err(format!(
+                "{wallets} in wallets and {vaults} in vaults, but {} were minted",
+                FUNDS * USERS as u64
+            ));
+        }
+
+        for market in &self.markets {
+            let Seen {
+                market: state,
+                positions,
+            } = &after[market];
+            let held: Vec<&Position> = positions.iter().flatten().collect();
+
+            // Pools and counts match the positions
+            let staked = |side: BetSide| -> u64 {
+                held.iter()
+                    .filter(|position| position.side == side)
+                    .map(|position| position.amount)
+                    .sum()
+            };
+            if (state.staked_a, state.staked_b) != (staked(BetSide::A), staked(BetSide::B)) {
+                return Err(format!(
+                    "{market} pools {}/{}, but its positions hold {}/{}",
+                    state.staked_a,
+                    state.staked_b,
+                    staked(BetSide::A),
+                    staked(BetSide::B)
+                ));
+            }
+            let open = held
+                .iter()
+                .filter(|position| position.amount > 0 && !position.claimed)
+                .count();
+            if open != state.open_positions as usize {
+                return Err(format!(
+                    "{market} counts {} open positions, but {open} are",
+                    state.open_positions
+                ));
+            }
+
+            // Solvency: the vaults cover every unclaimed payout and the fee
+            let vault = self.bed.vault_balance(market);
+            let owed = match state.status {
+                MarketStatus::Open | MarketStatus::PendingResolve => {
+                    for side in [BetSide::A, BetSide::B] {
+                        let held = self.bed.side_vault_balance(market, side);
+                        if held < state.side_total(side) {
+                            return Err(format!(
+                                "{market}'s side {side:?} vault holds {held} but {} is staked \
+                                 on it",
+                                state.side_total(side)
+                            ));
+                        }
+                    }
+                    state.pool_total().map_err(|err| err.to_string())?
+                }
+                MarketStatus::Resolved | MarketStatus::Cancelled => {
+                    let mut owed = 0;
+                    for position in held.iter().filter(|position| !position.claimed) {
+                        owed += state.payout_for(position).map_err(|err| err.to_string())?;
+                    }
+                    if state.status == MarketStatus::Resolved && !state.creator_fee_withdrawn {
+                        owed += state.creator_fee_due().map_err(|err| err.to_string())?;
+                    }
+                    owed
+                }
+            };
+            if vault < owed {
+                return Err(format!(
+                    "{market} is insolvent: its vaults hold {vault} but it owes {owed}"
+                ));
+            }
+            if (state.status == MarketStatus::Resolved) != state.outcome.is_some() {
+                return Err(format!(
+                    "{market} is {:?} with outcome {:?}",
+                    state.status, state.outcome
+                ));
+            }
+
+            // Transitions only go forward
+            let Some(previous) = before.get(market) else {
+                continue;
+            };
+            let was = &previous.market;
+            let allowed = match (was.status, state.status) {
+                (from, to) if from == to => true,
+                (MarketStatus::Open, MarketStatus::PendingResolve | MarketStatus::Resolved) => true,
+                (
+                    MarketStatus::PendingResolve,
+                    MarketStatus::Resolved | MarketStatus::Cancelled,
+                ) => true,
+                _ => false,
+            };
+            if !allowed {
+                return Err(format!(
+                    "{market} went from {:?} to {:?}",
+                    was.status, state.status
+                ));
+            }
+            if was.outcome.is_some() && was.outcome != state.outcome {
+                return Err(format!("{market}'s outcome changed"));
+            }
+            if was.creator_fee_withdrawn && !state.creator_fee_withdrawn {
+                return Err(format!("{market}'s fee withdrawal was undone"));
+            }
+            for (index, (was, is)) in previous.positions.iter().zip(positions).enumerate() {
+                let (Some(was), Some(is)) = (was, is) else {
+                    continue;
+                };
+                if was.amount > 0 && was.side != is.side {
+                    return Err(format!(
+                        "user {index}'s position in {market} switched from {:?} to {:?}",
+                        was.side, is.side
+                    ));
+                }
+                if was.claimed && (!is.claimed || is.amount != was.amount) {
+                    return Err(format!(
+                        "user {index}'s claimed position in {market} changed"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn setting(name: &str, default: u64) -> u64 {
+    env::var(name).map_or(default, |value| {
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("{name} is a number"))
+    })
+}
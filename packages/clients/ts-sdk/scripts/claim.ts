@@ -2,6 +2,7 @@
 
 import { Connection, Keypair, PublicKey } from '@solana/web3.js';
 import { getAssociatedTokenAddress, TOKEN_PROGRAM_ID } from '@solana/spl-token';
+import { BN } from '@coral-xyz/anchor';
 import { createProgram } from '../src/anchor';
 import { getMarketAddress, getUserPosition } from '../src/pda';
 
@@ -16,16 +17,19 @@ async function claimWinnings() {
 
 		// Parse command line arguments
 		const creatorAddress = process.argv[2];
+		const marketIdStr = process.argv[3];
 
-		if (!creatorAddress) {
-			console.error('Usage: tsx claim.ts <creator_address>');
-			console.error('Example: tsx claim.ts 9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM');
+		if (!creatorAddress || !marketIdStr) {
+			console.error('Usage: tsx claim.ts <creator_address> <market_id>');
+			console.error('Example: tsx claim.ts 9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM 1');
 			process.exit(1);
 		}
 
 		const creator = new PublicKey(creatorAddress);
+		const marketId = new BN(marketIdStr);
 
 		console.log('Creator:', creator.toString());
+		console.log('Market ID:', marketId.toString());
 
 		// Setup connection and wallet
 		const connection = new Connection(RPC_URL, 'confirmed');
@@ -48,7 +52,7 @@ async function claimWinnings() {
 		});
 
 		// Generate PDAs
-		const [market] = getMarketAddress(creator, MINT);
+		const [market] = getMarketAddress(creator, marketId);
 		const { position } = getUserPosition(market, user.publicKey);
 
 		// Get user's token account
@@ -111,7 +115,8 @@ async function claimWinnings() {
 				market,
 				position,
 				userTokenAccount,
-				vault: marketAccount.vault,
+				vaultA: marketAccount.vaultA,
+				vaultB: marketAccount.vaultB,
 				tokenProgram: TOKEN_PROGRAM_ID,
 			})
 			.signers([user])
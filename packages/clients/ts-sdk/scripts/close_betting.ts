@@ -1,12 +1,12 @@
 #!/usr/bin/env tsx
 
 import { Connection, Keypair, PublicKey } from '@solana/web3.js';
+import { BN } from '@coral-xyz/anchor';
 import { createProgram } from '../src/anchor';
 import { getMarketAddress } from '../src/pda';
 
 // Load environment variables
 const RPC_URL = process.env.SOLANA_RPC_URL || 'https://api.testnet.solana.com';
-const MINT = new PublicKey(process.env.MINT || '11111111111111111111111111111111');
 const KEYPAIR_PATH = process.env.KEYPAIR_PATH || process.env.HOME + '/.config/solana/id.json';
 
 async function closeBetting() {
@@ -15,16 +15,19 @@ async function closeBetting() {
 
 		// Parse command line arguments
 		const creatorAddress = process.argv[2];
+		const marketIdStr = process.argv[3];
 
-		if (!creatorAddress) {
-			console.error('Usage: tsx close_betting.ts <creator_address>');
-			console.error('Example: tsx close_betting.ts 9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM');
+		if (!creatorAddress || !marketIdStr) {
+			console.error('Usage: tsx close_betting.ts <creator_address> <market_id>');
+			console.error('Example: tsx close_betting.ts 9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM 1');
 			process.exit(1);
 		}
 
 		const creator = new PublicKey(creatorAddress);
+		const marketId = new BN(marketIdStr);
 
 		console.log('Creator:', creator.toString());
+		console.log('Market ID:', marketId.toString());
 
 		// Setup connection and wallet (can be anyone, not just creator)
 		const connection = new Connection(RPC_URL, 'confirmed');
@@ -47,7 +50,7 @@ async function closeBetting() {
 		});
 
 		// Generate market PDA
-		const [market] = getMarketAddress(creator, MINT);
+		const [market] = getMarketAddress(creator, marketId);
 
 		console.log('Market:', market.toString());
 
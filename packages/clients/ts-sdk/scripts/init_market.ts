@@ -4,7 +4,8 @@ import { Connection, Keypair, PublicKey } from '@solana/web3.js';
 import { TOKEN_PROGRAM_ID } from '@solana/spl-token';
 import { BN } from '@coral-xyz/anchor';
 import { createProgram } from '../src/anchor';
-import { getMarketPDAs } from '../src/pda';
+import { getConfigAddress, getMarketPDAs } from '../src/pda';
+import { DEFAULT_MARKET_OPTIONS } from '../src/types';
 
 // Load environment variables
 const RPC_URL = process.env.SOLANA_RPC_URL || 'https://api.testnet.solana.com';
@@ -36,11 +37,17 @@ async function initializeMarket() {
 			}
 		});
 
+		// Markets are keyed by creator and an ID of the creator's choosing;
+		// the current time is as good as any when none is given
+		const marketId = new BN(process.argv[3] || Math.floor(Date.now() / 1000));
+
 		// Generate market PDAs
-		const { market, vault } = getMarketPDAs(creator.publicKey, MINT);
+		const { market, vaultA, vaultB } = getMarketPDAs(creator.publicKey, marketId);
+		const [config] = getConfigAddress();
 
+		console.log('Market ID:', marketId.toString());
 		console.log('Market PDA:', market.toString());
-		console.log('Vault PDA:', vault.toString());
+		console.log('Vault PDAs:', vaultA.toString(), vaultB.toString());
 
 		// Market parameters
 		const feeBps = 500; // 5%
@@ -55,12 +62,14 @@ async function initializeMarket() {
 
 		// Initialize market
 		const tx = await program.methods
-			.initializeMarket(feeBps, endTs, resolveDeadlineTs, title)
+			.initializeMarket(marketId, feeBps, endTs, resolveDeadlineTs, title, DEFAULT_MARKET_OPTIONS)
 			.accounts({
 				creator: creator.publicKey,
+				config,
 				market,
 				mint: MINT,
-				vault,
+				vaultA,
+				vaultB,
 				tokenProgram: TOKEN_PROGRAM_ID,
 			})
 			.signers([creator])
@@ -69,6 +78,7 @@ async function initializeMarket() {
 		console.log('✅ Market initialized!');
 		console.log('Transaction:', tx);
 		console.log('Market address:', market.toString());
+		console.log('Market ID:', marketId.toString());
 
 		// Verify market was created
 		const marketAccount = await program.account.market.fetch(market);
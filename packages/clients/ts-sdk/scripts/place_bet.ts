@@ -4,7 +4,7 @@ import { Connection, Keypair, PublicKey } from '@solana/web3.js';
 import { getAssociatedTokenAddress, TOKEN_PROGRAM_ID } from '@solana/spl-token';
 import { BN } from '@coral-xyz/anchor';
 import { createProgram } from '../src/anchor';
-import { getMarketAddress, getUserPosition } from '../src/pda';
+import { getMarketAddress, getUserPosition, getVaultAddress } from '../src/pda';
 import { BET_SIDE_A, BET_SIDE_B, BetSide } from '../src/types';
 
 // Load environment variables
@@ -18,22 +18,29 @@ async function placeBet() {
 
 		// Parse command line arguments
 		const creatorAddress = process.argv[2];
-		const sideStr = process.argv[3]?.toUpperCase();
-		const amountStr = process.argv[4];
-
-		if (!creatorAddress || !sideStr || !amountStr) {
-			console.error('Usage: tsx place_bet.ts <creator_address> <side:A|B> <amount>');
-			console.error('Example: tsx place_bet.ts 9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM A 1000');
+		const marketIdStr = process.argv[3];
+		const sideStr = process.argv[4]?.toUpperCase();
+		const amountStr = process.argv[5];
+		// Worst odds to accept, in basis points of the stake; 0 takes any
+		const minOddsStr = process.argv[6] || '0';
+
+		if (!creatorAddress || !marketIdStr || !sideStr || !amountStr) {
+			console.error('Usage: tsx place_bet.ts <creator_address> <market_id> <side:A|B> <amount> [min_odds_bps]');
+			console.error('Example: tsx place_bet.ts 9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM 1 A 1000 15000');
 			process.exit(1);
 		}
 
 		const creator = new PublicKey(creatorAddress);
+		const marketId = new BN(marketIdStr);
 		const side: BetSide = sideStr === 'A' ? BET_SIDE_A : BET_SIDE_B;
 		const amount = new BN(amountStr);
+		const minOddsBps = new BN(minOddsStr);
 
 		console.log('Creator:', creator.toString());
+		console.log('Market ID:', marketId.toString());
 		console.log('Side:', sideStr);
 		console.log('Amount:', amount.toString());
+		console.log('Min odds (bps):', minOddsBps.toString());
 
 		// Setup connection and wallet
 		const connection = new Connection(RPC_URL, 'confirmed');
@@ -56,15 +63,14 @@ async function placeBet() {
 		});
 
 		// Generate PDAs
-		const [market] = getMarketAddress(creator, MINT);
+		const [market] = getMarketAddress(creator, marketId);
 		const { position } = getUserPosition(market, user.publicKey);
 
 		// Get user's token account
 		const userTokenAccount = await getAssociatedTokenAddress(MINT, user.publicKey);
 
-		// Fetch market to get vault
-		const marketAccount = await program.account.market.fetch(market);
-		const vault = marketAccount.vault;
+		// The bet goes into the vault of its side
+		const [vault] = getVaultAddress(market, side);
 
 		console.log('Market:', market.toString());
 		console.log('Position PDA:', position.toString());
@@ -73,7 +79,7 @@ async function placeBet() {
 
 		// Place bet
 		const tx = await program.methods
-			.placeBet(side, amount)
+			.placeBet(side, amount, minOddsBps)
 			.accounts({
 				user: user.publicKey,
 				market,
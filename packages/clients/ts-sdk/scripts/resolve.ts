@@ -1,13 +1,13 @@
 #!/usr/bin/env tsx
 
 import { Connection, Keypair, PublicKey } from '@solana/web3.js';
+import { BN } from '@coral-xyz/anchor';
 import { createProgram } from '../src/anchor';
 import { getMarketAddress } from '../src/pda';
 import { BET_SIDE_A, BET_SIDE_B, BetSide } from '../src/types';
 
 // Load environment variables
 const RPC_URL = process.env.SOLANA_RPC_URL || 'https://api.testnet.solana.com';
-const MINT = new PublicKey(process.env.MINT || '11111111111111111111111111111111');
 const KEYPAIR_PATH = process.env.KEYPAIR_PATH || process.env.HOME + '/.config/solana/id.json';
 
 async function resolveMarket() {
@@ -15,16 +15,19 @@ async function resolveMarket() {
 		console.log('⚖️ Resolving market...');
 
 		// Parse command line arguments
-		const outcomeStr = process.argv[2]?.toUpperCase();
+		const marketIdStr = process.argv[2];
+		const outcomeStr = process.argv[3]?.toUpperCase();
 
-		if (!outcomeStr || !['A', 'B'].includes(outcomeStr)) {
-			console.error('Usage: tsx resolve.ts <outcome:A|B>');
-			console.error('Example: tsx resolve.ts A');
+		if (!marketIdStr || !outcomeStr || !['A', 'B'].includes(outcomeStr)) {
+			console.error('Usage: tsx resolve.ts <market_id> <outcome:A|B>');
+			console.error('Example: tsx resolve.ts 1 A');
 			process.exit(1);
 		}
 
+		const marketId = new BN(marketIdStr);
 		const outcome: BetSide = outcomeStr === 'A' ? BET_SIDE_A : BET_SIDE_B;
 
+		console.log('Market ID:', marketId.toString());
 		console.log('Outcome:', outcomeStr);
 
 		// Setup connection and wallet
@@ -48,7 +51,7 @@ async function resolveMarket() {
 		});
 
 		// Generate market PDA
-		const [market] = getMarketAddress(creator.publicKey, MINT);
+		const [market] = getMarketAddress(creator.publicKey, marketId);
 
 		console.log('Market:', market.toString());
 
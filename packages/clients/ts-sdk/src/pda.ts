@@ -1,36 +1,49 @@
 import { PublicKey } from '@solana/web3.js';
+import { BN } from '@coral-xyz/anchor';
+import { BetSide } from './types';
 
 export const PROGRAM_ID = new PublicKey("BtNtmmrm3KHc5EmvednmUv43hxL8P3S2fsfPVpffx1Rt");
 
 /**
- * Derives the market PDA
+ * Derives the PDA of the program's config
+ */
+export function getConfigAddress(
+	programId: PublicKey = PROGRAM_ID
+): [PublicKey, number] {
+	return PublicKey.findProgramAddressSync([Buffer.from("config")], programId);
+}
+
+/**
+ * Derives the PDA of the creator's market with ID `marketId`
  */
 export function getMarketAddress(
 	creator: PublicKey,
-	mint: PublicKey,
+	marketId: BN | number,
 	programId: PublicKey = PROGRAM_ID
 ): [PublicKey, number] {
 	return PublicKey.findProgramAddressSync(
 		[
 			Buffer.from("market"),
 			creator.toBuffer(),
-			mint.toBuffer(),
+			new BN(marketId).toArrayLike(Buffer, "le", 8),
 		],
 		programId
 	);
 }
 
 /**
- * Derives the vault PDA for a market
+ * Derives the PDA of the vault holding a market's stakes on `side`
  */
 export function getVaultAddress(
 	market: PublicKey,
+	side: BetSide,
 	programId: PublicKey = PROGRAM_ID
 ): [PublicKey, number] {
 	return PublicKey.findProgramAddressSync(
 		[
 			Buffer.from("vault"),
 			market.toBuffer(),
+			Buffer.from(['a' in side ? 0 : 1]),
 		],
 		programId
 	);
@@ -57,15 +70,18 @@ export function getPositionAddress(
 /**
  * Get all PDAs for a market
  */
-export function getMarketPDAs(creator: PublicKey, mint: PublicKey, programId: PublicKey = PROGRAM_ID) {
-	const [market, marketBump] = getMarketAddress(creator, mint, programId);
-	const [vault, vaultBump] = getVaultAddress(market, programId);
+export function getMarketPDAs(creator: PublicKey, marketId: BN | number, programId: PublicKey = PROGRAM_ID) {
+	const [market, marketBump] = getMarketAddress(creator, marketId, programId);
+	const [vaultA, vaultABump] = getVaultAddress(market, { a: {} }, programId);
+	const [vaultB, vaultBBump] = getVaultAddress(market, { b: {} }, programId);
 
 	return {
 		market,
 		marketBump,
-		vault,
-		vaultBump,
+		vaultA,
+		vaultABump,
+		vaultB,
+		vaultBBump,
 	};
 }
 
@@ -4,7 +4,10 @@ import { BN } from '@coral-xyz/anchor';
 export interface MarketData {
 	creator: PublicKey;
 	mint: PublicKey;
-	vault: PublicKey;
+	/** Holds the stakes on side A */
+	vaultA: PublicKey;
+	/** Holds the stakes on side B */
+	vaultB: PublicKey;
 	feeBps: number;
 	endTs: BN;
 	resolveDeadlineTs: BN;
@@ -14,7 +17,8 @@ export interface MarketData {
 	outcome?: BetSide;
 	creatorFeeWithdrawn: boolean;
 	bump: number;
-	vaultBump: number;
+	vaultABump: number;
+	vaultBBump: number;
 	title: string;
 }
 
@@ -34,6 +38,25 @@ export type MarketStatus =
 
 export type BetSide = { a: {} } | { b: {} };
 
+/** Settings chosen when a market is created */
+export interface MarketOptions {
+	/** Lets bettors sell their position back before betting closes */
+	allowCashOut: boolean;
+	/** Share of a cash-out's value kept in the pool, in basis points */
+	cashoutHaircutBps: number;
+	/** Fixes each bet's odds when it's placed */
+	lockOdds: boolean;
+	/** Burns the creator fee instead of paying it out */
+	burnFee: boolean;
+}
+
+export const DEFAULT_MARKET_OPTIONS: MarketOptions = {
+	allowCashOut: false,
+	cashoutHaircutBps: 0,
+	lockOdds: false,
+	burnFee: false,
+};
+
 export interface MarketInfo {
 	publicKey: PublicKey;
 	data: MarketData;
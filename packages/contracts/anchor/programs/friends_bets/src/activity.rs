@@ -0,0 +1,140 @@
+//! Rolling activity of a market: bets and volume per hour over the last
+//! [`ACTIVITY_BUCKETS`] hours, plus how many bettors it has drawn, kept
+//! on-chain so "hot markets" can be ranked without an indexer and read by
+//! other programs.
+//!
+//! Hours cycle through the buckets: hour `h` (a timestamp divided by
+//! [`ACTIVITY_BUCKET_SECS`]) lands in bucket `h % ACTIVITY_BUCKETS`, which
+//! is cleared when a bet lands in it that belongs to a later hour than the
+//! one it holds. A bucket is only current while its `start` is within the
+//! window asked about, so quiet hours read as nothing without being
+//! written.
+
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+/// Hours of activity kept.
+pub const ACTIVITY_BUCKETS: usize = 24;
+/// Length of a bucket.
+pub const ACTIVITY_BUCKET_SECS: i64 = 3600;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ActivityBucket {
+    /// Start of the hour the bucket holds.
+    pub start: i64,
+    pub bets: u32,
+    pub volume: u64,
+}
+
+impl ActivityBucket {
+    pub const LEN: usize = 8 + 4 + 8;
+}
+
+#[account]
+pub struct MarketActivity {
+    pub market: Pubkey,
+    /// Paid the account's rent, and gets it back when it's closed.
+    pub payer: Pubkey,
+    /// Bets that opened a position since activity was enabled; a bettor
+    /// who cashed out and bet again counts again.
+    pub bettors: u32,
+    pub last_bet_ts: i64,
+    pub buckets: [ActivityBucket; ACTIVITY_BUCKETS],
+}
+
+impl MarketActivity {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // market
+        32 + // payer
+        4 + // bettors
+        8 + // last_bet_ts
+        ACTIVITY_BUCKETS * ActivityBucket::LEN; // buckets
+
+    /// Adds a bet of `amount` placed at `now`, by a bettor it opened a
+    /// position for when `new_bettor` is set.
+    pub fn record(&mut self, now: i64, amount: u64, new_bettor: bool) -> Result<()> {
+        let start = now - now.rem_euclid(ACTIVITY_BUCKET_SECS);
+        let index = (start / ACTIVITY_BUCKET_SECS).rem_euclid(ACTIVITY_BUCKETS as i64) as usize;
+        let bucket = &mut self.buckets[index];
+        if bucket.start != start {
+            *bucket = ActivityBucket {
+                start,
+                ..Default::default()
+            };
+        }
+        bucket.bets = bucket.bets.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        bucket.volume = bucket
+            .volume
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        if new_bettor {
+            self.bettors = self.bettors.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        }
+        self.last_bet_ts = now;
+        Ok(())
+    }
+
+    /// Bets and volume over the last `hours` as of `now`, counting the
+    /// current hour as the last; at most [`ACTIVITY_BUCKETS`] hours back.
+    pub fn recent(&self, now: i64, hours: usize) -> (u32, u64) {
+        let current = now - now.rem_euclid(ACTIVITY_BUCKET_SECS);
+        let hours = hours.min(ACTIVITY_BUCKETS) as i64;
+        let since = current - (hours - 1) * ACTIVITY_BUCKET_SECS;
+        self.buckets
+            .iter()
+            .filter(|bucket| bucket.bets > 0 && (since..=current).contains(&bucket.start))
+            .fold((0, 0), |(bets, volume), bucket| {
+                (
+                    bets.saturating_add(bucket.bets),
+                    volume.saturating_add(bucket.volume),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = ACTIVITY_BUCKET_SECS;
+
+    fn activity() -> MarketActivity {
+        MarketActivity {
+            market: Pubkey::new_unique(),
+            payer: Pubkey::new_unique(),
+            bettors: 0,
+            last_bet_ts: 0,
+            buckets: [ActivityBucket::default(); ACTIVITY_BUCKETS],
+        }
+    }
+
+    #[test]
+    fn sums_the_hours_asked_for() {
+        let mut activity = activity();
+        let now = 1_700_000_000;
+        activity.record(now - 2 * HOUR, 100, true).unwrap();
+        activity.record(now - HOUR, 200, true).unwrap();
+        activity.record(now, 300, false).unwrap();
+        activity.record(now, 400, true).unwrap();
+
+        assert_eq!(activity.recent(now, 1), (2, 700));
+        assert_eq!(activity.recent(now, 2), (3, 900));
+        assert_eq!(activity.recent(now, ACTIVITY_BUCKETS * 2), (4, 1_000));
+        assert_eq!(activity.recent(now + 2 * HOUR, 2), (0, 0));
+        assert_eq!(activity.bettors, 3);
+        assert_eq!(activity.last_bet_ts, now);
+    }
+
+    #[test]
+    fn buckets_are_reused_once_a_day_old() {
+        let mut activity = activity();
+        let now = 1_700_000_000;
+        activity.record(now, 100, true).unwrap();
+        let later = now + ACTIVITY_BUCKETS as i64 * HOUR;
+        activity.record(later, 50, true).unwrap();
+
+        assert_eq!(activity.recent(later, ACTIVITY_BUCKETS), (1, 50));
+        assert_eq!(activity.recent(now, 1), (0, 0));
+    }
+}
@@ -0,0 +1,115 @@
+//! The admin log: every privileged action the program allows, numbered and
+//! chained into a hash kept in the config, so the operator's history of
+//! interventions can be checked against the chain rather than taken from
+//! off-chain records.
+//!
+//! Each action emits an [`AdminActionLogged`](crate::AdminActionLogged)
+//! carrying its sequence number and the log hash after it:
+//! [`admin_log_hash`] of the previous hash and the action. Replaying the
+//! events from sequence 0 and an all-zero hash must reach the config's
+//! `admin_log_hash` after `admin_actions` of them; a gap in the sequence or
+//! a hash that doesn't follow shows an event is missing or altered.
+//!
+//! The actions are the config's creation and its updates, insurance
+//! payouts, and forks of contested markets, which the admin co-signs with
+//! the creator. The program has no pause, freeze or emergency withdrawal,
+//! so there's nothing else for the admin to do.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::ConfigUpdate;
+
+/// The log hash before any action.
+pub const EMPTY_ADMIN_LOG: [u8; 32] = [0; 32];
+
+/// A privileged action, as logged.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum AdminAction {
+    /// The config was created with these settings.
+    ConfigInitialized {
+        config: ConfigUpdate,
+    },
+    ConfigUpdateProposed {
+        update: ConfigUpdate,
+        ready_ts: i64,
+    },
+    /// A proposed update took effect. Anyone can apply one, so it's logged
+    /// under the admin who proposed it.
+    ConfigUpdateApplied {
+        config: ConfigUpdate,
+    },
+    ConfigUpdateCancelled,
+    /// Insurance paid out of a cancelled market's mint's vault.
+    Compensated {
+        market: Pubkey,
+        recipient: Pubkey,
+        amount: u64,
+    },
+    /// `original` was cancelled and replaced by `market`.
+    MarketForked {
+        original: Pubkey,
+        market: Pubkey,
+    },
+}
+
+/// The log hash after `action`, taken by `admin` at `timestamp` as action
+/// number `sequence`, when it was `previous` before.
+pub fn admin_log_hash(
+    previous: &[u8; 32],
+    sequence: u64,
+    admin: &Pubkey,
+    timestamp: i64,
+    action: &AdminAction,
+) -> Result<[u8; 32]> {
+    let mut data = Vec::new();
+    action.serialize(&mut data)?;
+    Ok(hashv(&[
+        previous,
+        &sequence.to_le_bytes(),
+        admin.as_ref(),
+        &timestamp.to_le_bytes(),
+        &data,
+    ])
+    .to_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AdminActionLogged;
+
+    fn entry(previous: &[u8; 32], sequence: u64, action: AdminAction) -> AdminActionLogged {
+        let admin = Pubkey::new_unique();
+        AdminActionLogged {
+            sequence,
+            admin,
+            timestamp: 100,
+            log_hash: admin_log_hash(previous, sequence, &admin, 100, &action).unwrap(),
+            action,
+        }
+    }
+
+    #[test]
+    fn entries_chain_in_order() {
+        let first = entry(&EMPTY_ADMIN_LOG, 0, AdminAction::ConfigUpdateCancelled);
+        let action = AdminAction::Compensated {
+            market: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            amount: 50,
+        };
+        let mut second = entry(&first.log_hash, 1, action);
+
+        assert!(first.follows(&EMPTY_ADMIN_LOG).unwrap());
+        assert!(second.follows(&first.log_hash).unwrap());
+        assert!(!second.follows(&EMPTY_ADMIN_LOG).unwrap());
+
+        // An altered entry no longer reaches the hash after it
+        second.action = AdminAction::Compensated {
+            market: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            amount: 5,
+        };
+        assert!(!second.follows(&first.log_hash).unwrap());
+    }
+}
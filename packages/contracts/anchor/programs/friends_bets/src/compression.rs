@@ -0,0 +1,335 @@
+//! Compressed positions: a concurrent Merkle tree of bets, for markets with
+//! too many bettors to pay rent on a position account each.
+//!
+//! Every bet appends a leaf hashing its market, owner, side, amount and
+//! locked payout, and a claim proves its leaf and replaces it with
+//! [`EMPTY_LEAF`], so it can't be proven again. Leaves live off-chain, in
+//! the `CompressedBetPlaced` events; the account holds the root, the
+//! siblings of the next leaf to append, and the last [`MAX_BUFFER_SIZE`]
+//! changes. A proof built against any root still in that buffer is
+//! brought up to date with the changes since, so claims sent in the same
+//! slot don't invalidate each other.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::{ErrorCode, Position};
+
+/// Levels below the root; a tree holds `2^MAX_DEPTH` bets.
+pub const MAX_DEPTH: usize = 20;
+/// Changes kept for bringing older proofs up to date.
+pub const MAX_BUFFER_SIZE: usize = 8;
+/// A leaf never appended, or claimed.
+pub const EMPTY_LEAF: [u8; 32] = [0; 32];
+
+/// A tree node. Account fields spell it out as `[u8; 32]`, since the IDL
+/// build can't see through the alias.
+pub type Node = [u8; 32];
+
+/// The leaf a bet appends.
+pub fn position_leaf(market: &Pubkey, position: &Position) -> Node {
+    hashv(&[
+        market.as_ref(),
+        position.owner.as_ref(),
+        &[position.side as u8],
+        &position.amount.to_le_bytes(),
+        &position.locked_payout.to_le_bytes(),
+        &position.discounted_stake.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+pub fn hash_pair(left: &Node, right: &Node) -> Node {
+    hashv(&[left, right]).to_bytes()
+}
+
+/// Roots of empty subtrees, by height: `[0]` is an empty leaf and
+/// `[MAX_DEPTH]` the root of an empty tree.
+pub fn empty_nodes() -> [Node; MAX_DEPTH + 1] {
+    let mut nodes = [EMPTY_LEAF; MAX_DEPTH + 1];
+    for level in 1..=MAX_DEPTH {
+        nodes[level] = hash_pair(&nodes[level - 1], &nodes[level - 1]);
+    }
+    nodes
+}
+
+/// Hashes `leaf` at `index` up to the root with `proof`, returning the
+/// root and the nodes on the way, from the leaf up.
+pub fn compute_path(
+    leaf: Node,
+    index: u32,
+    proof: &[Node; MAX_DEPTH],
+) -> (Node, [Node; MAX_DEPTH]) {
+    let mut path = [EMPTY_LEAF; MAX_DEPTH];
+    let mut node = leaf;
+    for (level, sibling) in proof.iter().enumerate() {
+        path[level] = node;
+        node = if index >> level & 1 == 1 {
+            hash_pair(sibling, &node)
+        } else {
+            hash_pair(&node, sibling)
+        };
+    }
+    (node, path)
+}
+
+/// The level at which the paths of two different leaves meet, where one's
+/// node is the other's sibling.
+fn meeting_level(a: u32, b: u32) -> usize {
+    (u32::BITS - 1 - (a ^ b).leading_zeros()) as usize
+}
+
+#[zero_copy]
+pub struct ChangeLog {
+    pub root: [u8; 32],
+    /// Nodes on the changed leaf's path, from the leaf up.
+    pub path: [[u8; 32]; MAX_DEPTH],
+    pub index: u32,
+    pub _padding: u32,
+}
+
+#[account(zero_copy)]
+pub struct PositionTree {
+    pub market: Pubkey,
+    /// Bets appended so far, and so the index of the next.
+    pub leaf_count: u64,
+    /// Changes made so far, appends and claims alike.
+    pub sequence_number: u64,
+    /// Entry in `change_logs` holding the latest change.
+    pub active_index: u64,
+    /// Entries in `change_logs` filled so far.
+    pub buffer_size: u64,
+    pub change_logs: [ChangeLog; MAX_BUFFER_SIZE],
+    /// Siblings of the next leaf to append, so appending takes no proof.
+    pub next_proof: [[u8; 32]; MAX_DEPTH],
+}
+
+impl PositionTree {
+    pub const LEN: usize = 8 + std::mem::size_of::<PositionTree>();
+
+    pub fn initialize(&mut self, market: Pubkey) {
+        let empty = empty_nodes();
+        let mut path = [EMPTY_LEAF; MAX_DEPTH];
+        path.copy_from_slice(&empty[..MAX_DEPTH]);
+
+        self.market = market;
+        self.leaf_count = 0;
+        self.sequence_number = 0;
+        self.active_index = 0;
+        self.buffer_size = 1;
+        self.change_logs[0] = ChangeLog {
+            root: empty[MAX_DEPTH],
+            path,
+            index: 0,
+            _padding: 0,
+        };
+        self.next_proof = path;
+    }
+
+    pub fn root(&self) -> Node {
+        self.change_logs[self.active_index as usize].root
+    }
+
+    /// Appends `leaf`, returning its index.
+    pub fn append(&mut self, leaf: Node) -> Result<u32> {
+        require!(
+            self.leaf_count < 1 << MAX_DEPTH,
+            ErrorCode::PositionTreeFull
+        );
+        let index = self.leaf_count as u32;
+        let (root, path) = compute_path(leaf, index, &self.next_proof);
+
+        // The next leaf shares this one's siblings above the level where
+        // their paths meet, has this one's node as its sibling there, and
+        // only empty subtrees beside it below
+        let next = index + 1;
+        if next < 1 << MAX_DEPTH {
+            let level = meeting_level(index, next);
+            let empty = empty_nodes();
+            self.next_proof[..level].copy_from_slice(&empty[..level]);
+            self.next_proof[level] = path[level];
+        }
+
+        self.leaf_count += 1;
+        self.push_change(root, path, index);
+        Ok(index)
+    }
+
+    /// Replaces `leaf` at `index` with `new_leaf`, given a proof against
+    /// `root`, which may be any root still in the change log.
+    pub fn replace(
+        &mut self,
+        root: Node,
+        index: u32,
+        leaf: Node,
+        new_leaf: Node,
+        proof: &[Node],
+    ) -> Result<()> {
+        require!(
+            (index as u64) < self.leaf_count,
+            ErrorCode::InvalidPositionProof
+        );
+        let mut proof: [Node; MAX_DEPTH] = proof
+            .try_into()
+            .map_err(|_| ErrorCode::InvalidPositionProof)?;
+
+        // Bring the proof up to date with every change since `root`
+        let age = (0..self.buffer_size)
+            .find(|&age| self.change_log(age).root == root)
+            .ok_or(ErrorCode::InvalidPositionProof)?;
+        for age in (0..age).rev() {
+            let change = self.change_log(age);
+            require!(change.index != index, ErrorCode::InvalidPositionProof);
+            let level = meeting_level(change.index, index);
+            proof[level] = change.path[level];
+        }
+        require!(
+            compute_path(leaf, index, &proof).0 == self.root(),
+            ErrorCode::InvalidPositionProof
+        );
+
+        let (root, path) = compute_path(new_leaf, index, &proof);
+        if self.leaf_count < 1 << MAX_DEPTH {
+            let level = meeting_level(index, self.leaf_count as u32);
+            self.next_proof[level] = path[level];
+        }
+        self.push_change(root, path, index);
+        Ok(())
+    }
+
+    /// The change made `age` changes before the latest.
+    fn change_log(&self, age: u64) -> &ChangeLog {
+        let slot = (self.active_index + MAX_BUFFER_SIZE as u64 - age) % MAX_BUFFER_SIZE as u64;
+        &self.change_logs[slot as usize]
+    }
+
+    fn push_change(&mut self, root: Node, path: [Node; MAX_DEPTH], index: u32) {
+        self.active_index = (self.active_index + 1) % MAX_BUFFER_SIZE as u64;
+        self.buffer_size = (self.buffer_size + 1).min(MAX_BUFFER_SIZE as u64);
+        self.sequence_number += 1;
+        self.change_logs[self.active_index as usize] = ChangeLog {
+            root,
+            path,
+            index,
+            _padding: 0,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+
+    use super::*;
+
+    fn tree() -> PositionTree {
+        let mut tree = PositionTree::zeroed();
+        tree.initialize(Pubkey::new_unique());
+        tree
+    }
+
+    fn leaf(n: u8) -> Node {
+        [n; 32]
+    }
+
+    /// The root and a proof for `index`, from every leaf.
+    fn full(leaves: &[Node], index: usize) -> (Node, Vec<Node>) {
+        let empty = empty_nodes();
+        let mut level_nodes = leaves.to_vec();
+        let mut proof = Vec::new();
+        let mut index = index;
+        for empty_node in &empty[..MAX_DEPTH] {
+            let sibling = index ^ 1;
+            proof.push(level_nodes.get(sibling).copied().unwrap_or(*empty_node));
+            level_nodes = level_nodes
+                .chunks(2)
+                .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(empty_node)))
+                .collect();
+            index /= 2;
+        }
+        (
+            level_nodes.first().copied().unwrap_or(empty[MAX_DEPTH]),
+            proof,
+        )
+    }
+
+    #[test]
+    fn appends_match_a_tree_built_from_every_leaf() {
+        let mut tree = tree();
+        assert_eq!(tree.root(), empty_nodes()[MAX_DEPTH]);
+        let mut leaves = Vec::new();
+        for n in 1..=13 {
+            assert_eq!(tree.append(leaf(n)).unwrap(), n as u32 - 1);
+            leaves.push(leaf(n));
+            assert_eq!(tree.root(), full(&leaves, 0).0);
+        }
+    }
+
+    #[test]
+    fn replaces_with_proofs_against_recent_roots() {
+        let mut tree = tree();
+        let mut leaves: Vec<Node> = (1..=6).map(leaf).collect();
+        for leaf in &leaves {
+            tree.append(*leaf).unwrap();
+        }
+
+        // Two claims built against the same root, landing one after the other
+        let (root, proof_1) = full(&leaves, 1);
+        let (_, proof_4) = full(&leaves, 4);
+        tree.replace(root, 1, leaves[1], EMPTY_LEAF, &proof_1)
+            .unwrap();
+        tree.replace(root, 4, leaves[4], EMPTY_LEAF, &proof_4)
+            .unwrap();
+        leaves[1] = EMPTY_LEAF;
+        leaves[4] = EMPTY_LEAF;
+        assert_eq!(tree.root(), full(&leaves, 0).0);
+
+        // Appends after replacements still land on the right root
+        tree.append(leaf(7)).unwrap();
+        leaves.push(leaf(7));
+        assert_eq!(tree.root(), full(&leaves, 0).0);
+    }
+
+    #[test]
+    fn rejects_claimed_leaves_and_bad_proofs() {
+        let mut tree = tree();
+        let leaves: Vec<Node> = (1..=4).map(leaf).collect();
+        for leaf in &leaves {
+            tree.append(*leaf).unwrap();
+        }
+        let (root, proof) = full(&leaves, 2);
+
+        // The wrong leaf, the wrong index and an unknown root
+        assert!(tree.replace(root, 2, leaf(9), EMPTY_LEAF, &proof).is_err());
+        assert!(tree
+            .replace(root, 3, leaves[2], EMPTY_LEAF, &proof)
+            .is_err());
+        assert!(tree
+            .replace(leaf(9), 2, leaves[2], EMPTY_LEAF, &proof)
+            .is_err());
+
+        tree.replace(root, 2, leaves[2], EMPTY_LEAF, &proof)
+            .unwrap();
+        // Replaying the claim, with the old proof or a fresh one
+        assert!(tree
+            .replace(root, 2, leaves[2], EMPTY_LEAF, &proof)
+            .is_err());
+        let mut claimed = leaves.clone();
+        claimed[2] = EMPTY_LEAF;
+        let (root, proof) = full(&claimed, 2);
+        assert!(tree
+            .replace(root, 2, leaves[2], EMPTY_LEAF, &proof)
+            .is_err());
+    }
+
+    #[test]
+    fn forgets_roots_older_than_the_buffer() {
+        let mut tree = tree();
+        tree.append(leaf(1)).unwrap();
+        let (root, proof) = full(&[leaf(1)], 0);
+        for n in 2..=MAX_BUFFER_SIZE as u8 + 1 {
+            tree.append(leaf(n)).unwrap();
+        }
+        assert!(tree.replace(root, 0, leaf(1), EMPTY_LEAF, &proof).is_err());
+    }
+}
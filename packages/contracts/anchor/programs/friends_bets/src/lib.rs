@@ -1,456 +1,3758 @@
+//! Pari-mutuel betting markets between friends.
+//!
+//! Other Anchor programs can compose with this one by depending on it with
+//! the `cpi` feature, which drops the entrypoint and exposes `cpi::*`
+//! instruction wrappers and `cpi::accounts::*` structs:
+//!
+//! ```ignore
+//! friends_bets::cpi::initialize_market(
+//!     CpiContext::new_with_signer(program, accounts, signer_seeds),
+//!     market_id, fee_bps, end_ts, resolve_deadline_ts, title, options,
+//! )?;
+//! ```
+//!
+//! Market, vault, position, position tree, bettor page, activity, market
+//! title, scheduled bet, stake and insurance vault addresses derive from the `*_SEED` constants below and
+//! [`ID`]. A PDA acting as creator or bettor signs via `new_with_signer` and, when it pays for account creation, must be a
+//! plain system account holding no data.
+//!
+//! Programs ranking markets by how busy they are can read a market's
+//! [`activity::MarketActivity`] as an `Account` and call its `recent`.
+//!
+//! Everything the config admin does is numbered and hashed into the
+//! config's [`admin_log`], so the operator's interventions can be audited
+//! from the chain alone.
+//!
+//! Markets take classic SPL Token mints only; Token-2022 mints fail the
+//! mint's owner check. Stakes, pools and payouts are all raw token units.
+//! Token-2022's interest-bearing mints only show interest in UI amounts
+//! and never change raw balances, so supporting them later needs no extra
+//! accounting.
+
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::keccak;
-use anchor_spl::associated_token::AssociatedToken;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
 
+pub mod activity;
+pub mod admin_log;
+pub mod compression;
+pub mod math;
+pub mod portfolio;
+pub mod schedule;
+pub mod title;
+
+use crate::activity::MarketActivity;
+use crate::admin_log::AdminAction;
+use crate::compression::PositionTree;
+use crate::portfolio::{Portfolio, PortfolioAction, PortfolioEntry, PortfolioPage};
+use crate::schedule::ScheduledBet;
+use crate::title::MarketTitle;
+
 declare_id!("BtNtmmrm3KHc5EmvednmUv43hxL8P3S2fsfPVpffx1Rt");
 
 const MAX_FEE_BPS: u16 = 2000; // 20%
 const MAX_TITLE_LEN: usize = 64;
+const MAX_CASHOUT_HAIRCUT_BPS: u16 = 5000; // 50%
+const MAX_INSURANCE_BPS: u16 = 100; // 1%
+/// Share of a cash-out order's payout that goes to whoever executes it.
+#[constant]
+pub const CASHOUT_ORDER_TIP_BPS: u16 = 20; // 0.2%
+pub const CLAIM_WINDOW_SECS: i64 = 180 * 24 * 60 * 60; // after resolve_deadline_ts
+pub const GC_DUST_LIMIT: u64 = 1_000; // raw token units left over from rounding
+const MAX_FEE_TIERS: usize = 4;
+const MAX_FEE_SPLITS: usize = 3;
+pub const BETTOR_PAGE_SIZE: usize = 32; // bets recorded per page
 
-#[program]
-pub mod friends_bets {
-    use super::*;
-
-    pub fn initialize_market(
-        ctx: Context<InitializeMarket>,
-        market_id: u64,
-        fee_bps: u16,
-        end_ts: i64,
-        resolve_deadline_ts: i64,
-        title: String,
-    ) -> Result<()> {
-        require!(fee_bps <= MAX_FEE_BPS, ErrorCode::FeeTooHigh);
-        require!(title.len() <= MAX_TITLE_LEN, ErrorCode::TitleTooLong);
-        require!(
-            end_ts > Clock::get()?.unix_timestamp,
-            ErrorCode::EndTimeInPast
-        );
-        require!(resolve_deadline_ts > end_ts, ErrorCode::InvalidDeadline);
-
-        let market = &mut ctx.accounts.market;
-        let vault = &ctx.accounts.vault;
-
-        market.market_id = market_id;
-        market.creator = ctx.accounts.creator.key();
-        market.mint = ctx.accounts.mint.key();
-        market.vault = vault.key();
-        market.fee_bps = fee_bps;
-        market.end_ts = end_ts;
-        market.resolve_deadline_ts = resolve_deadline_ts;
-        market.staked_a = 0;
-        market.staked_b = 0;
-        market.status = MarketStatus::Open;
-        market.outcome = None;
-        market.creator_fee_withdrawn = false;
-        market.bump = ctx.bumps.market;
-        market.vault_bump = ctx.bumps.vault;
-        market.title = title.clone();
-
-        emit!(MarketInitialized {
-            market: market.key(),
-            creator: market.creator,
-            title,
-            fee_bps,
-            end_ts,
-            resolve_deadline_ts,
-        });
+/// Pass as `fee_bps` to take the config's default fee.
+#[constant]
+pub const DEFAULT_FEE: u16 = u16::MAX;
 
-        Ok(())
-    }
+#[constant]
+pub const MARKET_SEED: &[u8] = b"market";
+#[constant]
+pub const VAULT_SEED: &[u8] = b"vault";
+#[constant]
+pub const POSITION_SEED: &[u8] = b"position";
+#[constant]
+pub const POSITION_TREE_SEED: &[u8] = b"position_tree";
+#[constant]
+pub const BETTOR_PAGE_SEED: &[u8] = b"bettor_page";
+#[constant]
+pub const ACTIVITY_SEED: &[u8] = b"activity";
+#[constant]
+pub const MARKET_TITLE_SEED: &[u8] = b"market_title";
+#[constant]
+pub const SCHEDULED_BET_SEED: &[u8] = b"scheduled_bet";
+#[constant]
+pub const SCHEDULED_ESCROW_SEED: &[u8] = b"scheduled_escrow";
+#[constant]
+pub const PORTFOLIO_SEED: &[u8] = b"portfolio";
+#[constant]
+pub const PORTFOLIO_PAGE_SEED: &[u8] = b"portfolio_page";
+#[constant]
+pub const CONFIG_SEED: &[u8] = b"config";
+#[constant]
+pub const STAKE_VAULT_SEED: &[u8] = b"stake_vault";
+#[constant]
+pub const STAKE_SEED: &[u8] = b"stake";
+#[constant]
+pub const INSURANCE_VAULT_SEED: &[u8] = b"insurance_vault";
 
-    pub fn place_bet(ctx: Context<PlaceBet>, side: BetSide, amount: u64) -> Result<()> {
-        let market = &mut ctx.accounts.market;
-        let position = &mut ctx.accounts.position;
+// `#[program]` still emits the deprecated `AccountInfo::realloc` in the IDL
+// handlers it generates next to the module, so the module gets one of its own
+// where that's allowed, and everything it defines is re-exported from here.
+#[allow(deprecated)]
+mod program_module {
+    use super::*;
 
-        require!(
-            market.status == MarketStatus::Open,
-            ErrorCode::MarketNotOpen
-        );
-        require!(
-            Clock::get()?.unix_timestamp < market.end_ts,
-            ErrorCode::BettingClosed
-        );
-        require!(amount > 0, ErrorCode::InvalidAmount);
-
-        // Transfer tokens from user to vault
-        let cpi_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.user_token_account.to_account_info(),
-                to: ctx.accounts.vault.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
-            },
-        );
-        token::transfer(cpi_ctx, amount)?;
+    #[program]
+    pub mod friends_bets {
+        use super::*;
 
-        // Update market stakes
-        match side {
-            BetSide::A => {
-                market.staked_a = market
-                    .staked_a
-                    .checked_add(amount)
-                    .ok_or(ErrorCode::Overflow)?
-            }
-            BetSide::B => {
-                market.staked_b = market
-                    .staked_b
-                    .checked_add(amount)
-                    .ok_or(ErrorCode::Overflow)?
-            }
+        /// A `fee_bps` of [`DEFAULT_FEE`], or an `end_ts` or
+        /// `resolve_deadline_ts` of 0, takes the config's default. The creator's
+        /// stake account may go first in remaining accounts to raise the fee
+        /// ceiling to their fee tier's.
+        pub fn initialize_market(
+            ctx: Context<InitializeMarket>,
+            market_id: u64,
+            fee_bps: u16,
+            end_ts: i64,
+            resolve_deadline_ts: i64,
+            title: String,
+            options: MarketOptions,
+        ) -> Result<()> {
+            let creator = ctx.accounts.creator.key();
+            let policy = creator_policy(&ctx.accounts.config, &creator, ctx.remaining_accounts)?;
+            init_market(
+                &mut ctx.accounts.market,
+                &policy,
+                market_id,
+                creator,
+                ctx.accounts.mint.key(),
+                (ctx.accounts.vault_a.key(), ctx.accounts.vault_b.key()),
+                (ctx.bumps.market, ctx.bumps.vault_a, ctx.bumps.vault_b),
+                fee_bps,
+                end_ts,
+                resolve_deadline_ts,
+                title,
+                options,
+            )
         }
 
-        // Update position
-        position.owner = ctx.accounts.user.key();
-        position.side = side;
-        position.amount = position
-            .amount
-            .checked_add(amount)
-            .ok_or(ErrorCode::Overflow)?;
-        position.claimed = false;
-        position.bump = ctx.bumps.position;
+        /// Creates a market and places the creator's first bet on it in one
+        /// instruction, so a market never sits in the registry without stake
+        /// when a separate bet transaction fails. Defaults and the fee ceiling
+        /// work as in `initialize_market`, and the creator's stake account also
+        /// earns the bet their fee discount.
+        #[allow(clippy::too_many_arguments)]
+        pub fn initialize_market_with_bet(
+            ctx: Context<InitializeMarketWithBet>,
+            market_id: u64,
+            fee_bps: u16,
+            end_ts: i64,
+            resolve_deadline_ts: i64,
+            title: String,
+            options: MarketOptions,
+            side: BetSide,
+            amount: u64,
+        ) -> Result<()> {
+            let creator = ctx.accounts.creator.key();
+            let config = &ctx.accounts.config;
+            let staked = creator_stake(&creator, ctx.remaining_accounts)?;
+            let policy = staked.map_or(config.policy, |staked| config.policy_for(staked));
+            let discount_bps = staked.map_or(0, |staked| config.discount_bps_for(staked));
+            init_market(
+                &mut ctx.accounts.market,
+                &policy,
+                market_id,
+                creator,
+                ctx.accounts.mint.key(),
+                (ctx.accounts.vault_a.key(), ctx.accounts.vault_b.key()),
+                (ctx.bumps.market, ctx.bumps.vault_a, ctx.bumps.vault_b),
+                fee_bps,
+                end_ts,
+                resolve_deadline_ts,
+                title,
+                options,
+            )?;
 
-        emit!(BetPlaced {
-            market: market.key(),
-            user: ctx.accounts.user.key(),
-            side,
-            amount,
-        });
+            let position = &mut ctx.accounts.position;
+            record_bet(
+                &mut ctx.accounts.market,
+                position,
+                creator,
+                side,
+                amount,
+                0,
+                discount_bps,
+            )?;
+            position.bump = ctx.bumps.position;
 
-        Ok(())
-    }
+            let vault = match side {
+                BetSide::A => &ctx.accounts.vault_a,
+                BetSide::B => &ctx.accounts.vault_b,
+            };
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.creator_token_account.to_account_info(),
+                    to: vault.to_account_info(),
+                    authority: ctx.accounts.creator.to_account_info(),
+                },
+            );
+            token::transfer(cpi_ctx, amount)?;
 
-    pub fn close_betting(ctx: Context<CloseBetting>) -> Result<()> {
-        let market = &mut ctx.accounts.market;
+            Ok(())
+        }
 
-        require!(
-            market.status == MarketStatus::Open,
-            ErrorCode::MarketNotOpen
-        );
-        require!(
-            Clock::get()?.unix_timestamp >= market.end_ts,
-            ErrorCode::BettingNotEnded
-        );
+        /// Creates a new market with the title, fee, mint, options, fee splits,
+        /// charity, large bet threshold and stake caps of `source`.
+        pub fn clone_market(
+            ctx: Context<CloneMarket>,
+            market_id: u64,
+            new_end_ts: i64,
+            new_resolve_deadline_ts: i64,
+        ) -> Result<()> {
+            let source = &ctx.accounts.source;
+            let creator = ctx.accounts.creator.key();
+            let policy = creator_policy(&ctx.accounts.config, &creator, ctx.remaining_accounts)?;
 
-        market.status = MarketStatus::PendingResolve;
+            init_market(
+                &mut ctx.accounts.market,
+                &policy,
+                market_id,
+                creator,
+                source.mint,
+                (ctx.accounts.vault_a.key(), ctx.accounts.vault_b.key()),
+                (ctx.bumps.market, ctx.bumps.vault_a, ctx.bumps.vault_b),
+                source.fee_bps,
+                new_end_ts,
+                new_resolve_deadline_ts,
+                source.title.clone(),
+                source.options(),
+            )?;
+            copy_terms(&mut ctx.accounts.market, source)?;
 
-        emit!(BettingClosed {
-            market: market.key(),
-        });
+            emit!(MarketCloned {
+                market: ctx.accounts.market.key(),
+                source: source.key(),
+            });
 
-        Ok(())
-    }
+            Ok(())
+        }
 
-    pub fn resolve(ctx: Context<Resolve>, outcome: BetSide) -> Result<()> {
-        let market = &mut ctx.accounts.market;
+        /// Cancels a contested market so its stakes can be refunded, and opens a
+        /// replacement with clarified terms that points back at the original
+        /// and keeps its fee splits. Cancelling refunds a side that was about
+        /// to lose, so the creator can't do it alone: the config admin co-signs
+        /// as arbitrator, and the fork goes in the admin log.
+        pub fn fork_market(
+            ctx: Context<ForkMarket>,
+            market_id: u64,
+            end_ts: i64,
+            resolve_deadline_ts: i64,
+            title: String,
+        ) -> Result<()> {
+            let original = &mut ctx.accounts.original;
 
-        require!(
-            market.status == MarketStatus::PendingResolve,
-            ErrorCode::MarketNotPendingResolve
-        );
-        require!(
-            ctx.accounts.creator.key() == market.creator,
-            ErrorCode::UnauthorizedResolver
-        );
-        require!(
-            Clock::get()?.unix_timestamp < market.resolve_deadline_ts,
-            ErrorCode::ResolutionDeadlinePassed
-        );
+            require!(
+                original.status == MarketStatus::Open
+                    || original.status == MarketStatus::PendingResolve,
+                ErrorCode::MarketAlreadyFinalized
+            );
+            require!(
+                ctx.accounts.creator.key() == original.creator,
+                ErrorCode::UnauthorizedResolver
+            );
 
-        market.status = MarketStatus::Resolved;
-        market.outcome = Some(outcome);
+            original.status = MarketStatus::Cancelled;
 
-        emit!(Resolved {
-            market: market.key(),
-            outcome,
-        });
+            emit!(Cancelled {
+                market: original.key(),
+            });
+            let action = AdminAction::MarketForked {
+                original: original.key(),
+                market: ctx.accounts.market.key(),
+            };
+            emit!(ctx.accounts.config.log_admin_action(action)?);
 
-        Ok(())
-    }
+            let creator = ctx.accounts.creator.key();
+            let policy = creator_policy(&ctx.accounts.config, &creator, ctx.remaining_accounts)?;
+            init_market(
+                &mut ctx.accounts.market,
+                &policy,
+                market_id,
+                creator,
+                original.mint,
+                (ctx.accounts.vault_a.key(), ctx.accounts.vault_b.key()),
+                (ctx.bumps.market, ctx.bumps.vault_a, ctx.bumps.vault_b),
+                original.fee_bps,
+                end_ts,
+                resolve_deadline_ts,
+                title,
+                original.options(),
+            )?;
+            ctx.accounts.market.forked_from = Some(original.key());
+            // The replacement shares its fee and gives to charity as the
+            // original would have
+            copy_terms(&mut ctx.accounts.market, original)?;
 
-    pub fn cancel_expired(ctx: Context<CancelExpired>) -> Result<()> {
-        let market = &mut ctx.accounts.market;
+            emit!(MarketForked {
+                market: ctx.accounts.market.key(),
+                original: original.key(),
+            });
 
-        require!(
-            market.status == MarketStatus::PendingResolve,
-            ErrorCode::MarketNotPendingResolve
-        );
-        require!(
-            Clock::get()?.unix_timestamp >= market.resolve_deadline_ts,
-            ErrorCode::ResolutionNotExpired
-        );
+            Ok(())
+        }
 
-        market.status = MarketStatus::Cancelled;
+        /// On a market that tracks bettors, the current bettor page goes first
+        /// in remaining accounts; the bettor pays for it when the last is full.
+        /// Stakers follow it with `[config, stake_account]` to bet at their fee
+        /// discount, which the position keeps whatever they do with the stake.
+        /// A bettor with a portfolio passes it and its current page to have a
+        /// new position recorded there.
+        pub fn place_bet<'info>(
+            ctx: Context<'_, '_, 'info, 'info, PlaceBet<'info>>,
+            side: BetSide,
+            amount: u64,
+            min_odds_bps: u64,
+        ) -> Result<()> {
+            let accounts = ctx.accounts;
+            bet_and_transfer(
+                BetAccounts {
+                    owner: accounts.user.key(),
+                    payer: &accounts.user,
+                    market: &mut accounts.market,
+                    position: &mut accounts.position,
+                    position_bump: ctx.bumps.position,
+                    source: &accounts.user_token_account,
+                    authority: accounts.user.to_account_info(),
+                    vault: &accounts.vault,
+                    token_program: &accounts.token_program,
+                    system_program: &accounts.system_program,
+                    portfolio: portfolio_accounts(
+                        &mut accounts.portfolio,
+                        &accounts.portfolio_page,
+                    )?,
+                },
+                ctx.remaining_accounts,
+                side,
+                amount,
+                min_odds_bps,
+                &[],
+            )
+        }
 
-        emit!(Cancelled {
-            market: market.key(),
-        });
+        /// `place_bet`, creating the user's associated token account for the
+        /// market mint first if it doesn't exist yet.
+        pub fn place_bet_init_ata<'info>(
+            ctx: Context<'_, '_, 'info, 'info, PlaceBetInitAta<'info>>,
+            side: BetSide,
+            amount: u64,
+            min_odds_bps: u64,
+        ) -> Result<()> {
+            let accounts = ctx.accounts;
+            bet_and_transfer(
+                BetAccounts {
+                    owner: accounts.user.key(),
+                    payer: &accounts.user,
+                    market: &mut accounts.market,
+                    position: &mut accounts.position,
+                    position_bump: ctx.bumps.position,
+                    source: &accounts.user_token_account,
+                    authority: accounts.user.to_account_info(),
+                    vault: &accounts.vault,
+                    token_program: &accounts.token_program,
+                    system_program: &accounts.system_program,
+                    portfolio: portfolio_accounts(
+                        &mut accounts.portfolio,
+                        &accounts.portfolio_page,
+                    )?,
+                },
+                ctx.remaining_accounts,
+                side,
+                amount,
+                min_odds_bps,
+                &[],
+            )
+        }
 
-        Ok(())
-    }
+        /// Places several bets atomically. Each entry in `bets` is matched by four
+        /// remaining accounts: `[market, position, user_token_account, vault]`,
+        /// the vault being that of the side bet on, optionally followed by the
+        /// staker's `[config, stake_account]` for their fee discount.
+        /// Missing positions are created and paid for by `user`.
+        pub fn place_bets_batch<'info>(
+            ctx: Context<'_, '_, 'info, 'info, PlaceBetsBatch<'info>>,
+            bets: Vec<BatchBet>,
+        ) -> Result<()> {
+            let extra = ctx.remaining_accounts.len().checked_sub(bets.len() * 4);
+            require!(
+                !bets.is_empty() && matches!(extra, Some(0 | 2)),
+                ErrorCode::InvalidRemainingAccounts
+            );
+            let (bet_accounts, stake_accounts) = ctx.remaining_accounts.split_at(bets.len() * 4);
 
-    pub fn claim(ctx: Context<Claim>) -> Result<()> {
-        let market = &ctx.accounts.market;
-        let position = &mut ctx.accounts.position;
+            let user = ctx.accounts.user.key();
+            let discount_bps = staker_discount_bps(&user, stake_accounts)?;
 
-        require!(
-            market.status == MarketStatus::Resolved || market.status == MarketStatus::Cancelled,
-            ErrorCode::MarketNotFinalized
-        );
-        require!(!position.claimed, ErrorCode::AlreadyClaimed);
-        require!(
-            position.owner == ctx.accounts.user.key(),
-            ErrorCode::UnauthorizedClaim
-        );
+            for (bet, accounts) in bets.iter().zip(bet_accounts.chunks(4)) {
+                let (market_info, position_info, user_token_info, vault_info) =
+                    (&accounts[0], &accounts[1], &accounts[2], &accounts[3]);
 
-        let payout = if market.status == MarketStatus::Cancelled {
-            // Refund original amount
-            position.amount
-        } else {
-            // Calculate payout based on outcome
-            let outcome = market.outcome.unwrap();
-            if position.side != outcome {
-                0 // Lost bet
-            } else {
-                // Won bet - calculate pro-rata share
-                let total_staked = market
-                    .staked_a
-                    .checked_add(market.staked_b)
-                    .ok_or(ErrorCode::Overflow)?;
-                let fee_amount = (total_staked as u128)
-                    .checked_mul(market.fee_bps as u128)
-                    .ok_or(ErrorCode::Overflow)?
-                    .checked_div(10_000)
-                    .ok_or(ErrorCode::Overflow)? as u64;
+                let mut market = Account::<Market>::try_from(market_info)?;
+                require_keys_eq!(market.key(), bet.market, ErrorCode::InvalidMarketPda);
+                require!(!market.compressed, ErrorCode::MarketCompressed);
+                require!(!market.track_bettors, ErrorCode::BettorPagesUnsupported);
+                require!(!market.track_activity, ErrorCode::ActivityStatsUnsupported);
+                require_keys_eq!(
+                    vault_info.key(),
+                    market.vault(bet.side),
+                    ErrorCode::InvalidVault
+                );
 
-                let distributable = total_staked
-                    .checked_sub(fee_amount)
-                    .ok_or(ErrorCode::Underflow)?;
+                let user_token_account = Account::<TokenAccount>::try_from(user_token_info)?;
+                require_keys_eq!(user_token_account.mint, market.mint, ErrorCode::InvalidMint);
+                require_keys_eq!(user_token_account.owner, user, ErrorCode::UnauthorizedClaim);
 
-                let winning_side_total = match outcome {
-                    BetSide::A => market.staked_a,
-                    BetSide::B => market.staked_b,
-                };
+                let (expected_position, position_bump) = Pubkey::find_program_address(
+                    &[POSITION_SEED, market.key().as_ref(), user.as_ref()],
+                    ctx.program_id,
+                );
+                require_keys_eq!(
+                    position_info.key(),
+                    expected_position,
+                    ErrorCode::InvalidPosition
+                );
 
-                if winning_side_total == 0 {
-                    0
+                let mut position = if position_info.data_is_empty() {
+                    let market_key = market.key();
+                    let position_seeds = &[
+                        POSITION_SEED,
+                        market_key.as_ref(),
+                        user.as_ref(),
+                        &[position_bump],
+                    ];
+                    create_pda_account(
+                        &ctx.accounts.user,
+                        position_info,
+                        &ctx.accounts.system_program,
+                        Position::LEN,
+                        &position_seeds[..],
+                    )?;
+                    let mut position = Account::<Position>::try_from_unchecked(position_info)?;
+                    position.bump = position_bump;
+                    position
                 } else {
-                    ((distributable as u128)
-                        .checked_mul(position.amount as u128)
-                        .ok_or(ErrorCode::Overflow)?
-                        .checked_div(winning_side_total as u128)
-                        .ok_or(ErrorCode::Overflow)?) as u64
-                }
+                    Account::<Position>::try_from(position_info)?
+                };
+
+                record_bet(
+                    &mut market,
+                    &mut position,
+                    user,
+                    bet.side,
+                    bet.amount,
+                    bet.min_odds_bps,
+                    discount_bps,
+                )?;
+
+                let cpi_ctx = CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: user_token_info.clone(),
+                        to: vault_info.clone(),
+                        authority: ctx.accounts.user.to_account_info(),
+                    },
+                );
+                token::transfer(cpi_ctx, bet.amount)?;
+
+                // Persist now so a later entry on the same market sees this bet
+                market.exit(ctx.program_id)?;
+                position.exit(ctx.program_id)?;
             }
-        };
 
-        if payout > 0 {
-            // Transfer payout from vault to user
-            let _market_key = market.key();
-            let seeds = &[
-                b"market",
-                market.creator.as_ref(),
-                &market.market_id.to_le_bytes(),
-                &[market.bump],
-            ];
-            let signer = &[&seeds[..]];
+            Ok(())
+        }
 
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault.to_account_info(),
-                    to: ctx.accounts.user_token_account.to_account_info(),
-                    authority: ctx.accounts.market.to_account_info(),
-                },
-                signer,
+        pub fn cash_out(ctx: Context<CashOut>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            let position = &mut ctx.accounts.position;
+
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
             );
-            token::transfer(cpi_ctx, payout)?;
-        }
+            require!(
+                Clock::get()?.unix_timestamp < market.end_ts,
+                ErrorCode::BettingClosed
+            );
+            require!(market.allow_cash_out, ErrorCode::CashOutDisabled);
+            require!(
+                position.owner == ctx.accounts.user.key(),
+                ErrorCode::UnauthorizedClaim
+            );
+            require!(position.amount > 0, ErrorCode::NothingToCashOut);
 
-        position.claimed = true;
+            let side = position.side;
+            let (stake, payout) = market.settle_cash_out(position)?;
+            market.pools_changed_slot = Clock::get()?.slot;
 
-        emit!(Claimed {
-            market: market.key(),
-            user: ctx.accounts.user.key(),
-            amount: payout,
-        });
+            let mut vaults = Vaults::new(&ctx.accounts.vault_a, &ctx.accounts.vault_b);
+            vaults.transfer(
+                market,
+                side,
+                &ctx.accounts.user_token_account,
+                &ctx.accounts.token_program,
+                payout,
+            )?;
 
-        Ok(())
-    }
+            emit!(CashedOut {
+                market: market.key(),
+                user: ctx.accounts.user.key(),
+                side,
+                stake,
+                payout,
+            });
 
-    pub fn withdraw_creator_fee(ctx: Context<WithdrawCreatorFee>) -> Result<()> {
-        let market = &mut ctx.accounts.market;
+            Ok(())
+        }
 
-        require!(
-            market.status == MarketStatus::Resolved,
-            ErrorCode::MarketNotResolved
-        );
-        require!(
-            ctx.accounts.creator.key() == market.creator,
-            ErrorCode::UnauthorizedWithdrawal
-        );
-        require!(
-            !market.creator_fee_withdrawn,
-            ErrorCode::FeeAlreadyWithdrawn
-        );
+        /// Places a standing order to cash the position out once what it would
+        /// win falls to `min_value`, or clears it with zero. Anyone can then
+        /// fill it with `execute_cashout` for a tip, in a later slot than the
+        /// stakes last moved in.
+        pub fn set_cashout_order(ctx: Context<SetCashOutOrder>, min_value: u64) -> Result<()> {
+            let market = &ctx.accounts.market;
+            let position = &mut ctx.accounts.position;
 
-        let total_staked = market
-            .staked_a
-            .checked_add(market.staked_b)
-            .ok_or(ErrorCode::Overflow)?;
-        let fee_amount = (total_staked as u128)
-            .checked_mul(market.fee_bps as u128)
-            .ok_or(ErrorCode::Overflow)?
-            .checked_div(10_000)
-            .ok_or(ErrorCode::Overflow)? as u64;
+            if min_value > 0 {
+                require!(
+                    market.status == MarketStatus::Open,
+                    ErrorCode::MarketNotOpen
+                );
+                require!(
+                    Clock::get()?.unix_timestamp < market.end_ts,
+                    ErrorCode::BettingClosed
+                );
+                require!(market.allow_cash_out, ErrorCode::CashOutDisabled);
+                require!(position.amount > 0, ErrorCode::NothingToCashOut);
+            }
+            position.cashout_min_value = min_value;
 
-        if fee_amount > 0 {
-            // Transfer fee from vault to creator
-            let _market_key = market.key();
-            let market_creator = market.creator;
-            let market_id = market.market_id;
-            let market_bump = market.bump;
-            let seeds = &[
-                b"market",
-                market_creator.as_ref(),
-                &market_id.to_le_bytes(),
-                &[market_bump],
-            ];
-            let signer = &[&seeds[..]];
+            emit!(CashOutOrderSet {
+                market: market.key(),
+                user: ctx.accounts.user.key(),
+                min_value,
+            });
 
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault.to_account_info(),
-                    to: ctx.accounts.creator_token_account.to_account_info(),
-                    authority: market.to_account_info(),
-                },
-                signer,
-            );
-            token::transfer(cpi_ctx, fee_amount)?;
+            Ok(())
         }
 
-        market.creator_fee_withdrawn = true;
+        /// Permissionless: cashes a position out for its owner once what it
+        /// would win has fallen to the owner's order, paying the executor
+        /// `CASHOUT_ORDER_TIP_BPS` of the payout.
+        pub fn execute_cashout(ctx: Context<ExecuteCashOut>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            let position = &mut ctx.accounts.position;
 
-        emit!(CreatorFeeWithdrawn {
-            market: market.key(),
-            creator: ctx.accounts.creator.key(),
-            amount: fee_amount,
-        });
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(
+                Clock::get()?.unix_timestamp < market.end_ts,
+                ErrorCode::BettingClosed
+            );
+            require!(market.allow_cash_out, ErrorCode::CashOutDisabled);
+            require!(position.amount > 0, ErrorCode::NothingToCashOut);
+            let min_value = position.cashout_min_value;
+            require!(min_value > 0, ErrorCode::NoCashOutOrder);
+            // Betting into the other side and filling the order in the same
+            // slot would let an executor trigger it at will
+            let slot = Clock::get()?.slot;
+            require!(
+                slot > market.pools_changed_slot,
+                ErrorCode::CashOutOrderTooSoon
+            );
+            require!(
+                market.payout_if_won(position)? <= min_value,
+                ErrorCode::CashOutOrderNotTriggered
+            );
 
-        Ok(())
-    }
-}
+            let side = position.side;
+            let (stake, payout) = market.settle_cash_out(position)?;
+            market.pools_changed_slot = slot;
+            let tip = math::bps_of(payout, CASHOUT_ORDER_TIP_BPS)?;
+            let owed = payout.checked_sub(tip).ok_or(ErrorCode::Underflow)?;
 
-#[derive(Accounts)]
-#[instruction(market_id: u64, fee_bps: u16, end_ts: i64, resolve_deadline_ts: i64, title: String)]
-pub struct InitializeMarket<'info> {
-    #[account(mut)]
-    pub creator: Signer<'info>,
+            let mut vaults = Vaults::new(&ctx.accounts.vault_a, &ctx.accounts.vault_b);
+            vaults.transfer(
+                market,
+                side,
+                &ctx.accounts.owner_token_account,
+                &ctx.accounts.token_program,
+                owed,
+            )?;
+            vaults.transfer(
+                market,
+                side,
+                &ctx.accounts.executor_token_account,
+                &ctx.accounts.token_program,
+                tip,
+            )?;
 
-    #[account(
-        init,
-        payer = creator,
-        space = Market::LEN,
-        seeds = [b"market", creator.key().as_ref(), &market_id.to_le_bytes()],
-        bump
-    )]
-    pub market: Account<'info, Market>,
+            emit!(CashedOut {
+                market: market.key(),
+                user: position.owner,
+                side,
+                stake,
+                payout: owed,
+            });
+            emit!(CashOutOrderExecuted {
+                market: market.key(),
+                user: position.owner,
+                executor: ctx.accounts.executor.key(),
+                min_value,
+                payout: owed,
+                tip,
+            });
 
-    pub mint: Account<'info, Mint>,
+            Ok(())
+        }
 
-    #[account(
-        init,
-        payer = creator,
-        token::mint = mint,
-        token::authority = market,
-        seeds = [b"vault", market.key().as_ref()],
-        bump
-    )]
-    pub vault: Account<'info, TokenAccount>,
+        pub fn close_betting(ctx: Context<CloseBetting>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
-}
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(
+                Clock::get()?.unix_timestamp >= market.end_ts,
+                ErrorCode::BettingNotEnded
+            );
 
-#[derive(Accounts)]
-#[instruction(side: BetSide, amount: u64)]
-pub struct PlaceBet<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+            market.status = MarketStatus::PendingResolve;
 
-    #[account(mut)]
-    pub market: Account<'info, Market>,
+            emit!(BettingClosed {
+                market: market.key(),
+            });
 
-    #[account(
-        init_if_needed,
-        payer = user,
-        space = Position::LEN,
-        seeds = [b"position", market.key().as_ref(), user.key().as_ref()],
-        bump
-    )]
-    pub position: Account<'info, Position>,
+            Ok(())
+        }
 
-    #[account(
-        mut,
-        constraint = user_token_account.mint == market.mint,
-        constraint = user_token_account.owner == user.key()
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
+        pub fn resolve(ctx: Context<Resolve>, outcome: BetSide) -> Result<()> {
+            let market = &mut ctx.accounts.market;
 
-    #[account(
-        mut,
-        constraint = vault.key() == market.vault
-    )]
-    pub vault: Account<'info, TokenAccount>,
+            require!(
+                market.status == MarketStatus::PendingResolve,
+                ErrorCode::MarketNotPendingResolve
+            );
+            require!(
+                ctx.accounts.creator.key() == market.creator,
+                ErrorCode::UnauthorizedResolver
+            );
+            require!(
+                Clock::get()?.unix_timestamp < market.resolve_deadline_ts,
+                ErrorCode::ResolutionDeadlinePassed
+            );
+            check_dependency(market, ctx.remaining_accounts)?;
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
-}
+            market.status = MarketStatus::Resolved;
+            market.outcome = Some(outcome);
 
-#[derive(Accounts)]
-pub struct CloseBetting<'info> {
-    #[account(mut)]
-    pub market: Account<'info, Market>,
-}
+            emit!(Resolved {
+                market: market.key(),
+                outcome,
+            });
 
-#[derive(Accounts)]
-#[instruction(outcome: BetSide)]
-pub struct Resolve<'info> {
-    pub creator: Signer<'info>,
+            Ok(())
+        }
 
-    #[account(
-        mut,
-        constraint = market.creator == creator.key()
-    )]
-    pub market: Account<'info, Market>,
-}
+        /// Closes betting and resolves in one step once `end_ts` has passed,
+        /// skipping the intermediate `PendingResolve` state.
+        pub fn close_and_resolve(ctx: Context<Resolve>, outcome: BetSide) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            let now = Clock::get()?.unix_timestamp;
 
-#[derive(Accounts)]
-pub struct CancelExpired<'info> {
-    #[account(mut)]
-    pub market: Account<'info, Market>,
-}
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(
+                ctx.accounts.creator.key() == market.creator,
+                ErrorCode::UnauthorizedResolver
+            );
+            require!(now >= market.end_ts, ErrorCode::BettingNotEnded);
+            require!(
+                now < market.resolve_deadline_ts,
+                ErrorCode::ResolutionDeadlinePassed
+            );
+            check_dependency(market, ctx.remaining_accounts)?;
 
-#[derive(Accounts)]
-pub struct Claim<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+            market.status = MarketStatus::Resolved;
+            market.outcome = Some(outcome);
 
-    #[account(mut)]
-    pub market: Account<'info, Market>,
+            emit!(BettingClosed {
+                market: market.key(),
+            });
+            emit!(Resolved {
+                market: market.key(),
+                outcome,
+            });
 
-    #[account(
-        mut,
-        seeds = [b"position", market.key().as_ref(), user.key().as_ref()],
-        bump = position.bump
-    )]
-    pub position: Account<'info, Position>,
+            Ok(())
+        }
 
-    #[account(
-        mut,
+        /// Makes `market` conditional on `parent` resolving to `outcome`. Only
+        /// allowed before any stake is placed so bettors always see the condition.
+        pub fn set_dependency(ctx: Context<SetDependency>, outcome: BetSide) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            let parent = &ctx.accounts.parent;
+
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(market.pool_total()? == 0, ErrorCode::MarketHasStakes);
+            require!(
+                parent.key() != market.key() && parent.status != MarketStatus::Cancelled,
+                ErrorCode::InvalidDependency
+            );
+
+            market.dependency = Some(MarketDependency {
+                market: parent.key(),
+                outcome,
+            });
+
+            emit!(DependencySet {
+                market: market.key(),
+                parent: parent.key(),
+                outcome,
+            });
+
+            Ok(())
+        }
+
+        /// Voids a conditional market once its parent has been cancelled or
+        /// resolved against the required outcome. Anyone can call this.
+        pub fn void_dependent(ctx: Context<VoidDependent>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            let parent = &ctx.accounts.parent;
+
+            let dependency = market.dependency.ok_or(ErrorCode::InvalidDependency)?;
+            require_keys_eq!(
+                parent.key(),
+                dependency.market,
+                ErrorCode::InvalidDependency
+            );
+            require!(
+                market.status == MarketStatus::Open
+                    || market.status == MarketStatus::PendingResolve,
+                ErrorCode::MarketAlreadyFinalized
+            );
+            let parent_failed = match parent.status {
+                MarketStatus::Cancelled => true,
+                MarketStatus::Resolved => parent.outcome != Some(dependency.outcome),
+                _ => false,
+            };
+            require!(parent_failed, ErrorCode::DependencyNotFailed);
+
+            market.status = MarketStatus::Cancelled;
+
+            emit!(Cancelled {
+                market: market.key(),
+            });
+
+            Ok(())
+        }
+
+        pub fn cancel_expired(ctx: Context<CancelExpired>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::PendingResolve,
+                ErrorCode::MarketNotPendingResolve
+            );
+            require!(cancel_if_expired(market)?, ErrorCode::ResolutionNotExpired);
+
+            Ok(())
+        }
+
+        /// Pays out a position of a finalized market. A market still waiting
+        /// on its resolution past the deadline is cancelled first, as
+        /// `cancel_expired` would, so refunds don't wait on a crank. Recorded
+        /// in the user's portfolio when passed it and its current page.
+        pub fn claim(ctx: Context<Claim>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            let position = &mut ctx.accounts.position;
+
+            cancel_if_expired(market)?;
+            require!(
+                market.status == MarketStatus::Resolved || market.status == MarketStatus::Cancelled,
+                ErrorCode::MarketNotFinalized
+            );
+            require!(!position.claimed, ErrorCode::AlreadyClaimed);
+            require!(
+                position.owner == ctx.accounts.user.key(),
+                ErrorCode::UnauthorizedClaim
+            );
+
+            pay_claim(
+                market,
+                position,
+                &ctx.accounts.user,
+                &ctx.accounts.user_token_account,
+                &mut Vaults::new(&ctx.accounts.vault_a, &ctx.accounts.vault_b),
+                &ctx.accounts.token_program,
+            )?;
+            position.claimed = true;
+
+            let accounts = ctx.accounts;
+            if let Some(portfolio) =
+                portfolio_accounts(&mut accounts.portfolio, &accounts.portfolio_page)?
+            {
+                record_portfolio(
+                    portfolio,
+                    &accounts.user,
+                    &accounts.system_program,
+                    PortfolioEntry {
+                        market: accounts.market.key(),
+                        action: PortfolioAction::Claim,
+                        side: accounts.position.side,
+                        timestamp: Clock::get()?.unix_timestamp,
+                    },
+                )?;
+            }
+
+            Ok(())
+        }
+
+        /// Opens the user's portfolio, which `place_bet`, `place_bet_init_ata`
+        /// and `claim` record in when passed it and its current page.
+        pub fn open_portfolio(ctx: Context<OpenPortfolio>) -> Result<()> {
+            let portfolio = &mut ctx.accounts.portfolio;
+            portfolio.owner = ctx.accounts.user.key();
+            portfolio.entry_count = 0;
+            portfolio.pages = 0;
+            portfolio.bump = ctx.bumps.portfolio;
+
+            emit!(PortfolioOpened {
+                owner: portfolio.owner,
+                portfolio: portfolio.key(),
+            });
+
+            Ok(())
+        }
+
+        /// Closes a full page of the user's portfolio, refunding its rent.
+        pub fn close_portfolio_page(ctx: Context<ClosePortfolioPage>, index: u32) -> Result<()> {
+            let portfolio = &mut ctx.accounts.portfolio;
+
+            require!(
+                index < portfolio.current_page(),
+                ErrorCode::PortfolioPageInUse
+            );
+            portfolio.pages = portfolio.pages.checked_sub(1).ok_or(ErrorCode::Underflow)?;
+
+            emit!(PortfolioPageClosed {
+                owner: portfolio.owner,
+                index,
+            });
+
+            Ok(())
+        }
+
+        /// Closes the user's portfolio and its current page, if it has one,
+        /// once its other pages are closed.
+        pub fn close_portfolio(ctx: Context<ClosePortfolio>) -> Result<()> {
+            let open = u32::from(ctx.accounts.portfolio_page.is_some());
+            require!(
+                ctx.accounts.portfolio.pages == open,
+                ErrorCode::PortfolioPagesOpen
+            );
+
+            emit!(PortfolioClosed {
+                owner: ctx.accounts.portfolio.owner,
+                portfolio: ctx.accounts.portfolio.key(),
+            });
+
+            Ok(())
+        }
+
+        /// Switches a market nobody has bet on yet to compressed positions:
+        /// bets append leaves to a Merkle tree instead of creating position
+        /// accounts, and are claimed one by one with a proof. Worth the tree's
+        /// rent on public markets with many bettors; cash-outs and batch bets
+        /// aren't supported.
+        pub fn enable_compressed_positions(ctx: Context<EnableCompressedPositions>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(market.pool_total()? == 0, ErrorCode::MarketHasStakes);
+            require!(
+                !market.allow_cash_out && !market.track_bettors && !market.track_activity,
+                ErrorCode::CompressionUnsupported
+            );
+
+            ctx.accounts
+                .position_tree
+                .load_init()?
+                .initialize(market.key());
+            market.compressed = true;
+
+            emit!(PositionsCompressed {
+                market: market.key(),
+                position_tree: ctx.accounts.position_tree.key(),
+            });
+
+            Ok(())
+        }
+
+        /// Bets on a market with compressed positions, appending a leaf for
+        /// this bet alone; betting again appends another. Stakers pass
+        /// `[config, stake_account]` in remaining accounts for their fee
+        /// discount, as with `place_bet`.
+        pub fn place_compressed_bet(
+            ctx: Context<PlaceCompressedBet>,
+            side: BetSide,
+            amount: u64,
+            min_odds_bps: u64,
+        ) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            let user = ctx.accounts.user.key();
+            require!(market.compressed, ErrorCode::MarketNotCompressed);
+            let discount_bps = staker_discount_bps(&user, ctx.remaining_accounts)?;
+
+            let mut position = Position {
+                owner: user,
+                side,
+                amount: 0,
+                claimed: false,
+                bump: 0,
+                locked_payout: 0,
+                discounted_stake: 0,
+                cashout_min_value: 0,
+                compensated: 0,
+            };
+            record_bet(
+                market,
+                &mut position,
+                user,
+                side,
+                amount,
+                min_odds_bps,
+                discount_bps,
+            )?;
+            let leaf = compression::position_leaf(&market.key(), &position);
+            let leaf_index = ctx.accounts.position_tree.load_mut()?.append(leaf)?;
+
+            emit!(CompressedBetPlaced {
+                market: market.key(),
+                user,
+                side,
+                amount,
+                locked_payout: position.locked_payout,
+                discounted_stake: position.discounted_stake,
+                leaf_index,
+            });
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            );
+            token::transfer(cpi_ctx, amount)?;
+
+            Ok(())
+        }
+
+        /// Claims the compressed bet at `leaf_index`, proven against `root` by
+        /// `proof`, the leaf's siblings from the bottom up. `root` may be any
+        /// of the tree's recent roots. Expired markets are cancelled first, as
+        /// in `claim`.
+        #[allow(clippy::too_many_arguments)]
+        pub fn claim_compressed(
+            ctx: Context<ClaimCompressed>,
+            root: [u8; 32],
+            leaf_index: u32,
+            side: BetSide,
+            amount: u64,
+            locked_payout: u64,
+            discounted_stake: u64,
+            proof: Vec<[u8; 32]>,
+        ) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            cancel_if_expired(market)?;
+            require!(
+                market.status == MarketStatus::Resolved || market.status == MarketStatus::Cancelled,
+                ErrorCode::MarketNotFinalized
+            );
+            require!(market.compressed, ErrorCode::MarketNotCompressed);
+
+            // Only the owner's own bet hashes to the leaf, and claiming empties it
+            let position = Position {
+                owner: ctx.accounts.user.key(),
+                side,
+                amount,
+                claimed: false,
+                bump: 0,
+                locked_payout,
+                discounted_stake,
+                cashout_min_value: 0,
+                compensated: 0,
+            };
+            let leaf = compression::position_leaf(&market.key(), &position);
+            ctx.accounts.position_tree.load_mut()?.replace(
+                root,
+                leaf_index,
+                leaf,
+                compression::EMPTY_LEAF,
+                &proof,
+            )?;
+
+            pay_claim(
+                market,
+                &position,
+                &ctx.accounts.user,
+                &ctx.accounts.user_token_account,
+                &mut Vaults::new(&ctx.accounts.vault_a, &ctx.accounts.vault_b),
+                &ctx.accounts.token_program,
+            )?;
+
+            emit!(CompressedPositionClaimed {
+                market: market.key(),
+                user: ctx.accounts.user.key(),
+                leaf_index,
+            });
+
+            Ok(())
+        }
+
+        /// Has `place_bet` record every bet on a market nobody has bet on yet
+        /// in `BettorPage` accounts, so instructions run at resolution can walk
+        /// the market's bettors on-chain. Pages are created as bets fill them
+        /// and closed with `close_bettor_page` once the claim window is over.
+        pub fn enable_bettor_pages(ctx: Context<EnableBettorPages>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(market.pool_total()? == 0, ErrorCode::MarketHasStakes);
+            require!(!market.compressed, ErrorCode::BettorPagesUnsupported);
+
+            market.track_bettors = true;
+
+            emit!(BettorPagesEnabled {
+                market: market.key(),
+            });
+
+            Ok(())
+        }
+
+        /// Permissionless: closes one of a settled market's bettor pages once
+        /// the claim window has passed, returning its rent to whoever paid for
+        /// it. `gc_market` waits until every page is closed.
+        pub fn close_bettor_page(ctx: Context<CloseBettorPage>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            require_claim_window_over(market)?;
+
+            market.bettor_pages = market
+                .bettor_pages
+                .checked_sub(1)
+                .ok_or(ErrorCode::Overflow)?;
+
+            emit!(BettorPageClosed {
+                market: market.key(),
+                index: ctx.accounts.bettor_page.index,
+                payer: ctx.accounts.payer.key(),
+            });
+
+            Ok(())
+        }
+
+        /// Has `place_bet` keep the market's `MarketActivity` account: bets and
+        /// volume per hour over the last day and a count of bettors, from now
+        /// on. Lets other programs and frontends rank open markets by how busy
+        /// they are without an indexer. The creator pays the account's rent,
+        /// returned by `close_activity_stats`.
+        pub fn enable_activity_stats(ctx: Context<EnableActivityStats>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(!market.compressed, ErrorCode::ActivityStatsUnsupported);
+
+            market.track_activity = true;
+            let activity = &mut ctx.accounts.activity;
+            activity.market = market.key();
+            activity.payer = ctx.accounts.creator.key();
+
+            emit!(ActivityStatsEnabled {
+                market: market.key(),
+            });
+
+            Ok(())
+        }
+
+        /// Permissionless: closes a settled market's activity account once the
+        /// claim window has passed, returning its rent to whoever paid for it.
+        /// `gc_market` waits until it's closed.
+        pub fn close_activity_stats(ctx: Context<CloseActivityStats>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            require_claim_window_over(market)?;
+
+            market.track_activity = false;
+
+            emit!(ActivityStatsClosed {
+                market: market.key(),
+                payer: ctx.accounts.payer.key(),
+            });
+
+            Ok(())
+        }
+
+        /// Registers an open market under its title, `title_hash` being
+        /// [`title::title_hash`] of it, and `nonce`, so frontends can find it
+        /// before creating another market on the same question. Fails if
+        /// another market holds that title and nonce. Meant to go in the same
+        /// transaction as the market's creation; the creator pays the rent,
+        /// returned by `close_market_title`.
+        pub fn register_market_title(
+            ctx: Context<RegisterMarketTitle>,
+            title_hash: [u8; 32],
+            nonce: u16,
+        ) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(
+                !market.title_registered
+                    && !title::normalize_title(&market.title).is_empty()
+                    && title_hash == title::title_hash(&market.title),
+                ErrorCode::InvalidMarketTitle
+            );
+
+            market.title_registered = true;
+            let registration = &mut ctx.accounts.market_title;
+            registration.market = market.key();
+            registration.title_hash = title_hash;
+            registration.nonce = nonce;
+
+            emit!(MarketTitleRegistered {
+                market: market.key(),
+                title_hash,
+                nonce,
+            });
+
+            Ok(())
+        }
+
+        /// Permissionless: frees a settled market's title for new markets,
+        /// returning the registration's rent to the creator. `gc_market` waits
+        /// until it's closed.
+        pub fn close_market_title(ctx: Context<CloseMarketTitle>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Resolved || market.status == MarketStatus::Cancelled,
+                ErrorCode::MarketNotFinalized
+            );
+
+            market.title_registered = false;
+
+            emit!(MarketTitleClosed {
+                market: market.key(),
+                title_hash: ctx.accounts.market_title.title_hash,
+                nonce: ctx.accounts.market_title.nonce,
+            });
+
+            Ok(())
+        }
+
+        /// Schedules a bet of `amount` on `side` that anyone can place for the
+        /// owner between `execute_after` and `expiry`, moving the stake into
+        /// the scheduled bet's escrow now. `id` tells apart the owner's
+        /// scheduled bets on the market. The owner pays the rent of both
+        /// accounts, which goes to whoever places or cancels the bet.
+        #[allow(clippy::too_many_arguments)]
+        pub fn schedule_bet(
+            ctx: Context<ScheduleBet>,
+            id: u64,
+            side: BetSide,
+            amount: u64,
+            min_odds_bps: u64,
+            execute_after: i64,
+            expiry: i64,
+        ) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            let now = Clock::get()?.unix_timestamp;
+
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(now < market.end_ts, ErrorCode::BettingClosed);
+            require!(!market.compressed, ErrorCode::MarketCompressed);
+            require!(amount > 0, ErrorCode::InvalidAmount);
+            require!(
+                execute_after < expiry && now < expiry,
+                ErrorCode::InvalidSchedule
+            );
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.owner_token_account.to_account_info(),
+                    to: ctx.accounts.escrow.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            );
+            token::transfer(cpi_ctx, amount)?;
+            market.scheduled_bets = market
+                .scheduled_bets
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+
+            let scheduled = &mut ctx.accounts.scheduled_bet;
+            scheduled.owner = ctx.accounts.owner.key();
+            scheduled.market = market.key();
+            scheduled.id = id;
+            scheduled.side = side;
+            scheduled.amount = amount;
+            scheduled.min_odds_bps = min_odds_bps;
+            scheduled.execute_after = execute_after;
+            scheduled.expiry = expiry;
+            scheduled.bump = ctx.bumps.scheduled_bet;
+            scheduled.escrow_bump = ctx.bumps.escrow;
+
+            emit!(BetScheduled {
+                market: market.key(),
+                scheduled_bet: scheduled.key(),
+                owner: scheduled.owner,
+                side,
+                amount,
+                execute_after,
+                expiry,
+            });
+
+            Ok(())
+        }
+
+        /// Permissionless: places a due scheduled bet for its owner out of its
+        /// escrow, as `place_bet` would, with the same remaining accounts. The
+        /// executor pays for the owner's position and bettor page if needed
+        /// and gets the rent of the scheduled bet and its escrow.
+        pub fn execute_scheduled_bet<'info>(
+            ctx: Context<'_, '_, 'info, 'info, ExecuteScheduledBet<'info>>,
+        ) -> Result<()> {
+            let accounts = ctx.accounts;
+            let scheduled = &accounts.scheduled_bet;
+            require!(
+                scheduled.is_due(Clock::get()?.unix_timestamp),
+                ErrorCode::ScheduledBetNotDue
+            );
+
+            let (owner, side, amount, min_odds_bps) = (
+                scheduled.owner,
+                scheduled.side,
+                scheduled.amount,
+                scheduled.min_odds_bps,
+            );
+            let market_key = accounts.market.key();
+            let id_bytes = scheduled.id.to_le_bytes();
+            let seeds = &[
+                SCHEDULED_BET_SEED,
+                market_key.as_ref(),
+                owner.as_ref(),
+                &id_bytes,
+                &[scheduled.bump],
+            ];
+            let signer = &[&seeds[..]];
+            let scheduled_info = scheduled.to_account_info();
+
+            bet_and_transfer(
+                BetAccounts {
+                    owner,
+                    payer: &accounts.executor,
+                    market: &mut accounts.market,
+                    position: &mut accounts.position,
+                    position_bump: ctx.bumps.position,
+                    source: &accounts.escrow,
+                    authority: scheduled_info.clone(),
+                    vault: &accounts.vault,
+                    token_program: &accounts.token_program,
+                    system_program: &accounts.system_program,
+                    portfolio: None,
+                },
+                ctx.remaining_accounts,
+                side,
+                amount,
+                min_odds_bps,
+                signer,
+            )?;
+            accounts.market.release_scheduled_bet()?;
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                accounts.token_program.to_account_info(),
+                token::CloseAccount {
+                    account: accounts.escrow.to_account_info(),
+                    destination: accounts.executor.to_account_info(),
+                    authority: scheduled_info,
+                },
+                signer,
+            );
+            token::close_account(cpi_ctx)?;
+
+            emit!(ScheduledBetExecuted {
+                market: market_key,
+                scheduled_bet: accounts.scheduled_bet.key(),
+                owner,
+                executor: accounts.executor.key(),
+            });
+
+            Ok(())
+        }
+
+        /// Returns a scheduled bet's stake and rent to its owner. The owner can
+        /// cancel at any time; anyone can once the bet has expired or its
+        /// market has stopped taking bets.
+        pub fn cancel_scheduled_bet(ctx: Context<CancelScheduledBet>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            let scheduled = &ctx.accounts.scheduled_bet;
+            let now = Clock::get()?.unix_timestamp;
+
+            require!(
+                ctx.accounts.caller.key() == scheduled.owner
+                    || scheduled.is_expired(now)
+                    || market.status != MarketStatus::Open
+                    || now >= market.end_ts,
+                ErrorCode::ScheduledBetActive
+            );
+
+            let market_key = market.key();
+            let id_bytes = scheduled.id.to_le_bytes();
+            let seeds = &[
+                SCHEDULED_BET_SEED,
+                market_key.as_ref(),
+                scheduled.owner.as_ref(),
+                &id_bytes,
+                &[scheduled.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.escrow.to_account_info(),
+                    to: ctx.accounts.owner_token_account.to_account_info(),
+                    authority: scheduled.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(cpi_ctx, ctx.accounts.escrow.amount)?;
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::CloseAccount {
+                    account: ctx.accounts.escrow.to_account_info(),
+                    destination: ctx.accounts.owner.to_account_info(),
+                    authority: scheduled.to_account_info(),
+                },
+                signer,
+            );
+            token::close_account(cpi_ctx)?;
+            market.release_scheduled_bet()?;
+
+            emit!(ScheduledBetCancelled {
+                market: market_key,
+                scheduled_bet: scheduled.key(),
+                owner: scheduled.owner,
+                amount: scheduled.amount,
+            });
+
+            Ok(())
+        }
+
+        /// Pushes payouts to a batch of positions passed in remaining accounts as
+        /// `[position, owner_ata]` pairs. Already-claimed positions are skipped so
+        /// overlapping batches don't fail.
+        pub fn distribute<'info>(
+            ctx: Context<'_, '_, 'info, 'info, Distribute<'info>>,
+        ) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Resolved || market.status == MarketStatus::Cancelled,
+                ErrorCode::MarketNotFinalized
+            );
+            // `is_multiple_of` is newer than the Rust the SBF toolchain ships
+            #[allow(clippy::manual_is_multiple_of)]
+            let paired = ctx.remaining_accounts.len() % 2 == 0;
+            require!(
+                !ctx.remaining_accounts.is_empty() && paired,
+                ErrorCode::InvalidRemainingAccounts
+            );
+
+            let market_key = market.key();
+            let mut vaults = Vaults::new(&ctx.accounts.vault_a, &ctx.accounts.vault_b);
+
+            for pair in ctx.remaining_accounts.chunks(2) {
+                let mut position = Account::<Position>::try_from(&pair[0])?;
+                let expected_position = Pubkey::create_program_address(
+                    &[
+                        POSITION_SEED,
+                        market_key.as_ref(),
+                        position.owner.as_ref(),
+                        &[position.bump],
+                    ],
+                    ctx.program_id,
+                )
+                .map_err(|_| ErrorCode::InvalidPosition)?;
+                require_keys_eq!(
+                    position.key(),
+                    expected_position,
+                    ErrorCode::InvalidPosition
+                );
+                if position.claimed {
+                    continue;
+                }
+
+                let owner_ata = &pair[1];
+                require_keys_eq!(
+                    owner_ata.key(),
+                    get_associated_token_address(&position.owner, &market.mint),
+                    ErrorCode::InvalidRecipient
+                );
+
+                let (payout, rebate) = market.settle_claim(&position)?;
+                vaults.transfer(
+                    market,
+                    position.side,
+                    owner_ata,
+                    &ctx.accounts.token_program,
+                    payout,
+                )?;
+
+                market.release_position(&position)?;
+                position.claimed = true;
+                position.exit(ctx.program_id)?;
+
+                emit_claim(market_key, position.owner, &position, payout, rebate);
+            }
+
+            Ok(())
+        }
+
+        pub fn withdraw_creator_fee(ctx: Context<WithdrawCreatorFee>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Resolved,
+                ErrorCode::MarketNotResolved
+            );
+            require!(
+                ctx.accounts.creator.key() == market.creator,
+                ErrorCode::UnauthorizedWithdrawal
+            );
+            require!(
+                !market.creator_fee_withdrawn,
+                ErrorCode::FeeAlreadyWithdrawn
+            );
+            require!(!market.burn_fee, ErrorCode::FeeIsBurned);
+            require!(market.fee_splits.is_empty(), ErrorCode::FeeIsSplit);
+
+            let fee_amount = market.creator_fee_due()?;
+
+            // Fees come out of the losing side's stake first
+            let mut vaults = Vaults::new(&ctx.accounts.vault_a, &ctx.accounts.vault_b);
+            vaults.transfer(
+                market,
+                market.losing_side()?,
+                &ctx.accounts.creator_token_account,
+                &ctx.accounts.token_program,
+                fee_amount,
+            )?;
+
+            market.creator_fee_withdrawn = true;
+
+            emit!(CreatorFeeWithdrawn {
+                market: market.key(),
+                creator: ctx.accounts.creator.key(),
+                amount: fee_amount,
+            });
+
+            Ok(())
+        }
+
+        /// Burns the fee of a resolved market that opted into `burn_fee` instead
+        /// of paying it to the creator. Anyone can call this.
+        pub fn burn_fee(ctx: Context<BurnFee>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Resolved,
+                ErrorCode::MarketNotResolved
+            );
+            require!(market.burn_fee, ErrorCode::FeeNotBurned);
+            require!(
+                !market.creator_fee_withdrawn,
+                ErrorCode::FeeAlreadyWithdrawn
+            );
+
+            let fee_amount = market.creator_fee_due()?;
+
+            let vaults = Vaults::new(&ctx.accounts.vault_a, &ctx.accounts.vault_b);
+            let parts = vaults.split(market.losing_side()?, fee_amount)?;
+            let seeds = &[
+                MARKET_SEED,
+                market.creator.as_ref(),
+                &market.market_id.to_le_bytes(),
+                &[market.bump],
+            ];
+            let signer = &[&seeds[..]];
+            for (vault, part) in [vaults.a, vaults.b].into_iter().zip(parts) {
+                if part == 0 {
+                    continue;
+                }
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Burn {
+                        mint: ctx.accounts.mint.to_account_info(),
+                        from: vault.to_account_info(),
+                        authority: market.to_account_info(),
+                    },
+                    signer,
+                );
+                token::burn(cpi_ctx, part)?;
+            }
+
+            market.creator_fee_withdrawn = true;
+
+            emit!(FeeBurned {
+                market: market.key(),
+                mint: market.mint,
+                amount: fee_amount,
+            });
+
+            Ok(())
+        }
+
+        /// Shares the fee of a market nobody has bet on yet between up to
+        /// three recipients, such as the frontend the market was made on or a
+        /// league treasury, each taking `bps` of it. The creator keeps the rest.
+        /// An empty `splits` goes back to paying the creator alone.
+        pub fn set_fee_splits(ctx: Context<SetFeeSplits>, splits: Vec<FeeSplit>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(market.pool_total()? == 0, ErrorCode::MarketHasStakes);
+            require!(!market.burn_fee, ErrorCode::FeeIsBurned);
+            validate_fee_splits(&splits)?;
+
+            market.fee_splits = splits.clone();
+
+            emit!(FeeSplitsSet {
+                market: market.key(),
+                splits,
+            });
+
+            Ok(())
+        }
+
+        /// Pays a resolved market's fee out to its split recipients and the
+        /// creator in one go. Recipients' token accounts follow in remaining
+        /// accounts, in the order of the market's `fee_splits`. Anyone can call
+        /// this.
+        pub fn distribute_fees<'info>(
+            ctx: Context<'_, '_, 'info, 'info, DistributeFees<'info>>,
+        ) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Resolved,
+                ErrorCode::MarketNotResolved
+            );
+            require!(
+                !market.creator_fee_withdrawn,
+                ErrorCode::FeeAlreadyWithdrawn
+            );
+            require!(!market.burn_fee, ErrorCode::FeeIsBurned);
+            require!(
+                ctx.remaining_accounts.len() == market.fee_splits.len(),
+                ErrorCode::InvalidRemainingAccounts
+            );
+
+            let (split_amounts, creator_amount) = market.split_fee()?;
+            let losing_side = market.losing_side()?;
+            let mut vaults = Vaults::new(&ctx.accounts.vault_a, &ctx.accounts.vault_b);
+            let splits = market.fee_splits.clone();
+            for ((split, amount), recipient_ata) in
+                splits.iter().zip(split_amounts).zip(ctx.remaining_accounts)
+            {
+                require_keys_eq!(
+                    recipient_ata.key(),
+                    get_associated_token_address(&split.recipient, &market.mint),
+                    ErrorCode::InvalidRecipient
+                );
+                vaults.transfer(
+                    market,
+                    losing_side,
+                    recipient_ata,
+                    &ctx.accounts.token_program,
+                    amount,
+                )?;
+
+                emit!(FeeSplitPaid {
+                    market: market.key(),
+                    recipient: split.recipient,
+                    amount,
+                });
+            }
+
+            vaults.transfer(
+                market,
+                losing_side,
+                &ctx.accounts.creator_token_account,
+                &ctx.accounts.token_program,
+                creator_amount,
+            )?;
+
+            market.creator_fee_withdrawn = true;
+
+            emit!(CreatorFeeWithdrawn {
+                market: market.key(),
+                creator: market.creator,
+                amount: creator_amount,
+            });
+
+            Ok(())
+        }
+
+        /// Sends `charity_bps` of the pool of a market nobody has bet on yet to
+        /// `charity_token_account` once it resolves, for groups whose losers
+        /// pay to charity. Passing no account and no cut drops the charity.
+        pub fn set_charity(ctx: Context<SetCharity>, charity_bps: u16) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(market.pool_total()? == 0, ErrorCode::MarketHasStakes);
+
+            let charity = ctx
+                .accounts
+                .charity_token_account
+                .as_ref()
+                .map(|account| account.key());
+            match charity {
+                Some(_) => validate_charity(market, charity_bps)?,
+                None => require!(charity_bps == 0, ErrorCode::InvalidCharity),
+            }
+            market.charity = charity;
+            market.charity_bps = charity_bps;
+
+            emit!(CharitySet {
+                market: market.key(),
+                charity,
+                charity_bps,
+            });
+
+            Ok(())
+        }
+
+        /// Makes bets of at least `threshold` on an open market emit
+        /// `LargeBetPlaced` besides `BetPlaced`, for alerts on big swings. A
+        /// threshold of 0 turns these off.
+        pub fn set_large_bet_threshold(
+            ctx: Context<SetLargeBetThreshold>,
+            threshold: u64,
+        ) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+
+            market.large_bet_threshold = threshold;
+
+            emit!(LargeBetThresholdSet {
+                market: market.key(),
+                threshold,
+            });
+
+            Ok(())
+        }
+
+        /// Bounds how lopsided an open market can get: bets taking a side past
+        /// its cap (`cap_a`, `cap_b`) fail, as do bets leaving a side more than
+        /// `max_ratio_bps` / 10000 times the other once both have stake. 0
+        /// turns a limit off; a ratio must be at least 1x. Lowering a cap
+        /// below what's staked only stops further bets on that side.
+        pub fn set_stake_caps(
+            ctx: Context<SetStakeCaps>,
+            cap_a: u64,
+            cap_b: u64,
+            max_ratio_bps: u32,
+        ) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Open,
+                ErrorCode::MarketNotOpen
+            );
+            require!(
+                max_ratio_bps == 0 || u64::from(max_ratio_bps) >= math::BPS_DENOMINATOR,
+                ErrorCode::InvalidStakeCaps
+            );
+
+            market.cap_a = cap_a;
+            market.cap_b = cap_b;
+            market.max_ratio_bps = max_ratio_bps;
+
+            emit!(StakeCapsSet {
+                market: market.key(),
+                cap_a,
+                cap_b,
+                max_ratio_bps,
+            });
+
+            Ok(())
+        }
+
+        /// Pays a resolved market's charity cut to its charity. Anyone can call
+        /// this.
+        pub fn donate_to_charity(ctx: Context<DonateToCharity>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Resolved,
+                ErrorCode::MarketNotResolved
+            );
+            require!(!market.charity_paid, ErrorCode::CharityAlreadyPaid);
+
+            let amount = market.charity_amount()?;
+
+            let mut vaults = Vaults::new(&ctx.accounts.vault_a, &ctx.accounts.vault_b);
+            vaults.transfer(
+                market,
+                market.losing_side()?,
+                &ctx.accounts.charity_token_account,
+                &ctx.accounts.token_program,
+                amount,
+            )?;
+
+            market.charity_paid = true;
+
+            emit!(CharityDonated {
+                market: market.key(),
+                charity: ctx.accounts.charity_token_account.key(),
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Moves a resolved market's insurance cut into the insurance vault of
+        /// its mint, creating the vault if needed. Anyone can call this.
+        pub fn collect_insurance(ctx: Context<CollectInsurance>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Resolved,
+                ErrorCode::MarketNotResolved
+            );
+            require!(
+                !market.insurance_collected,
+                ErrorCode::InsuranceAlreadyCollected
+            );
+
+            let amount = market.insurance_amount()?;
+
+            let mut vaults = Vaults::new(&ctx.accounts.vault_a, &ctx.accounts.vault_b);
+            vaults.transfer(
+                market,
+                market.losing_side()?,
+                &ctx.accounts.insurance_vault,
+                &ctx.accounts.token_program,
+                amount,
+            )?;
+
+            market.insurance_collected = true;
+
+            emit!(InsuranceCollected {
+                market: market.key(),
+                mint: market.mint,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Pays `amount` from the insurance vault of a cancelled market's mint
+        /// to the owner of `position`, making good a bettor of a market voided
+        /// over creator fraud or a program bug whose vault can't refund them.
+        /// Admin only. Compensation is capped at the stake the position hasn't
+        /// been refunded yet, and its claim pays that much less afterwards.
+        pub fn compensate(ctx: Context<Compensate>, amount: u64) -> Result<()> {
+            require!(amount > 0, ErrorCode::InvalidAmount);
+            require!(
+                ctx.accounts.market.status == MarketStatus::Cancelled,
+                ErrorCode::MarketNotCancelled
+            );
+
+            let position = &mut ctx.accounts.position;
+            require!(!position.claimed, ErrorCode::AlreadyClaimed);
+            position.compensated = position
+                .compensated
+                .checked_add(amount)
+                .ok_or(ErrorCode::Overflow)?;
+            require!(
+                position.compensated <= position.amount,
+                ErrorCode::CompensationTooHigh
+            );
+
+            let config = &ctx.accounts.config;
+            let seeds = &[CONFIG_SEED, &[config.bump]];
+            let signer = &[&seeds[..]];
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.insurance_vault.to_account_info(),
+                    to: ctx.accounts.recipient_token_account.to_account_info(),
+                    authority: config.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(cpi_ctx, amount)?;
+
+            let market = &mut ctx.accounts.market;
+            market.compensated = market
+                .compensated
+                .checked_add(amount)
+                .ok_or(ErrorCode::Overflow)?;
+
+            let recipient = ctx.accounts.position.owner;
+            emit!(Compensated {
+                market: market.key(),
+                recipient,
+                amount,
+            });
+            let action = AdminAction::Compensated {
+                market: market.key(),
+                recipient,
+                amount,
+            };
+            let config = &mut ctx.accounts.config;
+            emit!(config.log_admin_action(action)?);
+
+            Ok(())
+        }
+
+        /// Shrinks a fully settled market down to its record: the title and
+        /// dependency, needed only while the market is live, are dropped and
+        /// the rent they took is refunded to the creator. The market keeps
+        /// every pool, outcome and fee figure and can still be collected by
+        /// `gc_market` once its claim window has passed.
+        pub fn compact_market(ctx: Context<CompactMarket>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require!(
+                market.status == MarketStatus::Resolved || market.status == MarketStatus::Cancelled,
+                ErrorCode::MarketNotFinalized
+            );
+            require!(
+                market.open_positions == 0
+                    && (market.status == MarketStatus::Cancelled
+                        || market.creator_fee_withdrawn
+                        || market.fee_amount()? == 0),
+                ErrorCode::OutstandingBalance
+            );
+
+            let market_info = market.to_account_info();
+            require!(
+                market_info.data_len() > Market::COMPACT_LEN,
+                ErrorCode::MarketAlreadyCompact
+            );
+
+            // Written back at this size when the instruction exits
+            market.title = String::new();
+            market.dependency = None;
+            market.fee_splits = Vec::new();
+            market_info.resize(Market::COMPACT_LEN)?;
+
+            let refund = market_info
+                .lamports()
+                .saturating_sub(Rent::get()?.minimum_balance(Market::COMPACT_LEN));
+            let creator_info = ctx.accounts.creator.to_account_info();
+            **creator_info.try_borrow_mut_lamports()? = creator_info
+                .lamports()
+                .checked_add(refund)
+                .ok_or(ErrorCode::Overflow)?;
+            **market_info.try_borrow_mut_lamports()? -= refund;
+
+            emit!(MarketCompacted {
+                market: market.key(),
+                refund,
+            });
+
+            Ok(())
+        }
+
+        /// Permissionless cleanup of a settled market once the claim window has
+        /// passed and its bettor pages, activity account, title registration and
+        /// scheduled bets are closed. Leftover positions are passed
+        /// in remaining accounts, after the position tree on a compressed
+        /// market; any that still hold a payout abort the call. Positions, vaults
+        /// and market are closed and the reclaimed rent is split between the
+        /// caller and creator.
+        pub fn gc_market<'info>(ctx: Context<'_, '_, 'info, 'info, GcMarket<'info>>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+
+            require_claim_window_over(market)?;
+            require!(market.bettor_pages == 0, ErrorCode::BettorPagesOpen);
+            require!(!market.track_activity, ErrorCode::ActivityStatsOpen);
+            require!(!market.title_registered, ErrorCode::MarketTitleRegistered);
+            require!(market.scheduled_bets == 0, ErrorCode::ScheduledBetsPending);
+            require!(
+                market.status == MarketStatus::Cancelled
+                    || (market.creator_fee_withdrawn || market.fee_amount()? == 0)
+                        && (market.insurance_collected || market.insurance_amount()? == 0)
+                        && (market.charity_paid || market.charity_amount()? == 0),
+                ErrorCode::OutstandingBalance
+            );
+
+            let market_key = market.key();
+            let market_info = market.to_account_info();
+            let mut positions_closed: u32 = 0;
+
+            let (position_tree, positions) = if market.compressed {
+                let (tree_info, positions) = ctx
+                    .remaining_accounts
+                    .split_first()
+                    .ok_or(ErrorCode::InvalidRemainingAccounts)?;
+                let (expected_tree, _) = Pubkey::find_program_address(
+                    &[POSITION_TREE_SEED, market_key.as_ref()],
+                    ctx.program_id,
+                );
+                require_keys_eq!(
+                    tree_info.key(),
+                    expected_tree,
+                    ErrorCode::InvalidRemainingAccounts
+                );
+                (Some(tree_info), positions)
+            } else {
+                (None, ctx.remaining_accounts)
+            };
+
+            for position_info in positions.iter() {
+                let position = Account::<Position>::try_from(position_info)?;
+                let expected_position = Pubkey::create_program_address(
+                    &[
+                        POSITION_SEED,
+                        market_key.as_ref(),
+                        position.owner.as_ref(),
+                        &[position.bump],
+                    ],
+                    ctx.program_id,
+                )
+                .map_err(|_| ErrorCode::InvalidPosition)?;
+                require_keys_eq!(
+                    position.key(),
+                    expected_position,
+                    ErrorCode::InvalidPosition
+                );
+                if !position.claimed {
+                    require!(
+                        market.payout_for(&position)? == 0,
+                        ErrorCode::OutstandingBalance
+                    );
+                    market.release_position(&position)?;
+                }
+
+                close_into(position_info, &market_info)?;
+                positions_closed += 1;
+            }
+
+            // Compressed bets left unclaimed can't be passed in to check; any
+            // payout they're still owed keeps the vaults over the dust limit
+            require!(
+                market.compressed || market.open_positions == 0,
+                ErrorCode::OutstandingBalance
+            );
+            if let Some(tree_info) = position_tree {
+                close_into(tree_info, &market_info)?;
+            }
+
+            let market_creator = market.creator;
+            let market_id = market.market_id;
+            let market_bump = market.bump;
+            let seeds = &[
+                MARKET_SEED,
+                market_creator.as_ref(),
+                &market_id.to_le_bytes(),
+                &[market_bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            // Rounding dust belongs to nobody; hand it to the creator. Anything
+            // larger is still owed to someone and keeps the market alive.
+            let vaults = [&ctx.accounts.vault_a, &ctx.accounts.vault_b];
+            let dust = vaults[0]
+                .amount
+                .checked_add(vaults[1].amount)
+                .ok_or(ErrorCode::Overflow)?;
+            require!(
+                dust <= market.unowed_balance()?,
+                ErrorCode::OutstandingBalance
+            );
+            for vault in vaults {
+                if vault.amount > 0 {
+                    let cpi_ctx = CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        Transfer {
+                            from: vault.to_account_info(),
+                            to: ctx.accounts.creator_token_account.to_account_info(),
+                            authority: market_info.clone(),
+                        },
+                        signer,
+                    );
+                    token::transfer(cpi_ctx, vault.amount)?;
+                }
+
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::CloseAccount {
+                        account: vault.to_account_info(),
+                        destination: market_info.clone(),
+                        authority: market_info.clone(),
+                    },
+                    signer,
+                );
+                token::close_account(cpi_ctx)?;
+            }
+
+            let reclaimed = market_info.lamports();
+            let caller_share = reclaimed / 2;
+            let creator_share = reclaimed - caller_share;
+            **ctx.accounts.caller.try_borrow_mut_lamports()? += caller_share;
+            **ctx.accounts.creator.try_borrow_mut_lamports()? += creator_share;
+            **market_info.try_borrow_mut_lamports()? = 0;
+            market_info.assign(&system_program::ID);
+            market_info.resize(0)?;
+
+            emit!(MarketCollected {
+                market: market_key,
+                caller: ctx.accounts.caller.key(),
+                positions_closed,
+                caller_share,
+                creator_share,
+            });
+
+            Ok(())
+        }
+
+        /// Creates the program-wide config and staking vault. Only the program's
+        /// upgrade authority can, and it becomes the admin; later changes wait
+        /// out `update_delay_secs`.
+        pub fn initialize_config(
+            ctx: Context<InitializeConfig>,
+            unbonding_secs: i64,
+            fee_tiers: Vec<FeeTier>,
+            policy: MarketPolicy,
+            update_delay_secs: i64,
+        ) -> Result<()> {
+            validate_fee_tiers(&fee_tiers)?;
+            validate_policy(&policy)?;
+            require!(unbonding_secs >= 0, ErrorCode::InvalidUnbondingPeriod);
+            require!(update_delay_secs >= 0, ErrorCode::InvalidUpdateDelay);
+
+            let config = &mut ctx.accounts.config;
+            config.admin = ctx.accounts.admin.key();
+            config.stake_mint = ctx.accounts.stake_mint.key();
+            config.stake_vault = ctx.accounts.stake_vault.key();
+            config.unbonding_secs = unbonding_secs;
+            config.fee_tiers = fee_tiers;
+            config.policy = policy;
+            config.bump = ctx.bumps.config;
+            config.stake_vault_bump = ctx.bumps.stake_vault;
+            config.update_delay_secs = update_delay_secs;
+            config.pending_update = None;
+            config.update_ready_ts = 0;
+            config.admin_actions = 0;
+            config.admin_log_hash = admin_log::EMPTY_ADMIN_LOG;
+
+            emit!(ConfigUpdated {
+                admin: config.admin,
+                unbonding_secs,
+                fee_tiers: config.fee_tiers.clone(),
+                policy,
+                update_delay_secs,
+            });
+            let action = AdminAction::ConfigInitialized {
+                config: config.settings(),
+            };
+            emit!(config.log_admin_action(action)?);
+
+            Ok(())
+        }
+
+        /// Queues `update` to replace the admin, staking settings, market
+        /// policy and delay once the current delay has passed, replacing any
+        /// update already queued. The admin only signs, so a governance PDA
+        /// such as an SPL Governance realm's can hold the role.
+        pub fn propose_config_update(
+            ctx: Context<AdminConfig>,
+            update: ConfigUpdate,
+        ) -> Result<()> {
+            validate_fee_tiers(&update.fee_tiers)?;
+            validate_policy(&update.policy)?;
+            require!(
+                update.unbonding_secs >= 0,
+                ErrorCode::InvalidUnbondingPeriod
+            );
+            require!(update.update_delay_secs >= 0, ErrorCode::InvalidUpdateDelay);
+
+            let config = &mut ctx.accounts.config;
+            config.update_ready_ts = Clock::get()?
+                .unix_timestamp
+                .checked_add(config.update_delay_secs)
+                .ok_or(ErrorCode::Overflow)?;
+
+            emit!(ConfigUpdateProposed {
+                admin: config.admin,
+                update: update.clone(),
+                ready_ts: config.update_ready_ts,
+            });
+            let action = AdminAction::ConfigUpdateProposed {
+                update: update.clone(),
+                ready_ts: config.update_ready_ts,
+            };
+            emit!(config.log_admin_action(action)?);
+
+            config.pending_update = Some(update);
+
+            Ok(())
+        }
+
+        /// Applies the queued config update once its delay has passed. Anyone
+        /// can call this. Markets already created keep the terms they were
+        /// created with.
+        pub fn apply_config_update(ctx: Context<ApplyConfigUpdate>) -> Result<()> {
+            let config = &mut ctx.accounts.config;
+            require!(
+                Clock::get()?.unix_timestamp >= config.update_ready_ts,
+                ErrorCode::ConfigUpdateNotReady
+            );
+            let update = config
+                .pending_update
+                .take()
+                .ok_or(ErrorCode::NoPendingConfigUpdate)?;
+            // Logged before it takes effect, under the admin who proposed it
+            let action = AdminAction::ConfigUpdateApplied {
+                config: update.clone(),
+            };
+            emit!(config.log_admin_action(action)?);
+
+            config.admin = update.admin;
+            config.unbonding_secs = update.unbonding_secs;
+            config.fee_tiers = update.fee_tiers;
+            config.policy = update.policy;
+            config.update_delay_secs = update.update_delay_secs;
+            config.update_ready_ts = 0;
+
+            emit!(ConfigUpdated {
+                admin: config.admin,
+                unbonding_secs: config.unbonding_secs,
+                fee_tiers: config.fee_tiers.clone(),
+                policy: config.policy,
+                update_delay_secs: config.update_delay_secs,
+            });
+
+            Ok(())
+        }
+
+        /// Drops the queued config update.
+        pub fn cancel_config_update(ctx: Context<AdminConfig>) -> Result<()> {
+            let config = &mut ctx.accounts.config;
+            require!(
+                config.pending_update.take().is_some(),
+                ErrorCode::NoPendingConfigUpdate
+            );
+            config.update_ready_ts = 0;
+
+            emit!(ConfigUpdateCancelled {
+                admin: config.admin,
+            });
+            emit!(config.log_admin_action(AdminAction::ConfigUpdateCancelled)?);
+
+            Ok(())
+        }
+
+        pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+            require!(amount > 0, ErrorCode::InvalidAmount);
+
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    to: ctx.accounts.stake_vault.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            );
+            token::transfer(cpi_ctx, amount)?;
+
+            let stake_account = &mut ctx.accounts.stake_account;
+            stake_account.owner = ctx.accounts.user.key();
+            stake_account.amount = stake_account
+                .amount
+                .checked_add(amount)
+                .ok_or(ErrorCode::Overflow)?;
+            stake_account.bump = ctx.bumps.stake_account;
+
+            emit!(Staked {
+                user: stake_account.owner,
+                amount,
+                total: stake_account.amount,
+            });
+
+            Ok(())
+        }
+
+        /// Starts unbonding `amount`. It stops counting towards fee tiers right
+        /// away and can be withdrawn once the unbonding period has passed.
+        /// Unstaking again restarts the period for the whole unbonding balance.
+        pub fn unstake(ctx: Context<Unstake>, amount: u64) -> Result<()> {
+            let stake_account = &mut ctx.accounts.stake_account;
+
+            require!(amount > 0, ErrorCode::InvalidAmount);
+            require!(amount <= stake_account.amount, ErrorCode::InsufficientStake);
+
+            stake_account.amount -= amount;
+            stake_account.unbonding_amount = stake_account
+                .unbonding_amount
+                .checked_add(amount)
+                .ok_or(ErrorCode::Overflow)?;
+            stake_account.unbonding_end_ts = Clock::get()?
+                .unix_timestamp
+                .checked_add(ctx.accounts.config.unbonding_secs)
+                .ok_or(ErrorCode::Overflow)?;
+
+            emit!(UnstakeRequested {
+                user: stake_account.owner,
+                amount,
+                unbonding_end_ts: stake_account.unbonding_end_ts,
+            });
+
+            Ok(())
+        }
+
+        pub fn withdraw_unstaked(ctx: Context<WithdrawUnstaked>) -> Result<()> {
+            let stake_account = &mut ctx.accounts.stake_account;
+            let config = &ctx.accounts.config;
+
+            let amount = stake_account.unbonding_amount;
+            require!(amount > 0, ErrorCode::NothingToWithdraw);
+            require!(
+                Clock::get()?.unix_timestamp >= stake_account.unbonding_end_ts,
+                ErrorCode::StillUnbonding
+            );
+
+            let seeds = &[CONFIG_SEED, &[config.bump]];
+            let signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.stake_vault.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: config.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(cpi_ctx, amount)?;
+
+            stake_account.unbonding_amount = 0;
+
+            emit!(StakeWithdrawn {
+                user: stake_account.owner,
+                amount,
+            });
+
+            Ok(())
+        }
+    }
+}
+pub use program_module::*;
+
+/// Validates market parameters against `policy`, filling in its defaults,
+/// and writes the initial state of a freshly created market account.
+/// `vaults` are the side A and B vaults, and `bumps` the market's PDA bump
+/// followed by theirs.
+#[allow(clippy::too_many_arguments)]
+fn init_market(
+    market: &mut Account<Market>,
+    policy: &MarketPolicy,
+    market_id: u64,
+    creator: Pubkey,
+    mint: Pubkey,
+    vaults: (Pubkey, Pubkey),
+    bumps: (u8, u8, u8),
+    fee_bps: u16,
+    end_ts: i64,
+    resolve_deadline_ts: i64,
+    title: String,
+    options: MarketOptions,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let fee_bps = if fee_bps == DEFAULT_FEE {
+        policy.default_fee_bps
+    } else {
+        fee_bps
+    };
+    let end_ts = if end_ts == 0 {
+        now.checked_add(policy.default_betting_secs)
+            .ok_or(ErrorCode::Overflow)?
+    } else {
+        end_ts
+    };
+    let resolve_deadline_ts = if resolve_deadline_ts == 0 {
+        end_ts
+            .checked_add(policy.default_resolve_secs)
+            .ok_or(ErrorCode::Overflow)?
+    } else {
+        resolve_deadline_ts
+    };
+
+    require!(fee_bps <= policy.max_fee_bps, ErrorCode::FeeTooHigh);
+    require!(
+        title.len() <= policy.max_title_len as usize,
+        ErrorCode::TitleTooLong
+    );
+    require!(
+        options.cashout_haircut_bps <= MAX_CASHOUT_HAIRCUT_BPS,
+        ErrorCode::HaircutTooHigh
+    );
+    require!(end_ts > now, ErrorCode::EndTimeInPast);
+    require!(resolve_deadline_ts > end_ts, ErrorCode::InvalidDeadline);
+
+    market.market_id = market_id;
+    market.creator = creator;
+    market.mint = mint;
+    market.vault_a = vaults.0;
+    market.vault_b = vaults.1;
+    market.fee_bps = fee_bps;
+    market.end_ts = end_ts;
+    market.resolve_deadline_ts = resolve_deadline_ts;
+    market.staked_a = 0;
+    market.staked_b = 0;
+    market.status = MarketStatus::Open;
+    market.outcome = None;
+    market.creator_fee_withdrawn = false;
+    market.bump = bumps.0;
+    market.vault_a_bump = bumps.1;
+    market.vault_b_bump = bumps.2;
+    market.title = title.clone();
+    market.allow_cash_out = options.allow_cash_out;
+    market.cashout_haircut_bps = options.cashout_haircut_bps;
+    market.retained = 0;
+    market.lock_odds = options.lock_odds;
+    market.locked_payout_a = 0;
+    market.locked_payout_b = 0;
+    market.forked_from = None;
+    market.dependency = None;
+    market.open_positions = 0;
+    market.burn_fee = options.burn_fee;
+    market.fee_discounts = 0;
+    market.discounted_stake = 0;
+    market.compressed = false;
+    market.track_bettors = false;
+    market.bettor_count = 0;
+    market.bettor_pages = 0;
+    market.insurance_bps = policy.insurance_bps;
+    market.insurance_collected = false;
+    market.compensated = 0;
+    market.charity = None;
+    market.charity_bps = 0;
+    market.charity_paid = false;
+    market.large_bet_threshold = 0;
+    market.track_activity = false;
+    market.cap_a = 0;
+    market.cap_b = 0;
+    market.max_ratio_bps = 0;
+    market.title_registered = false;
+    market.fee_splits = Vec::new();
+    market.pools_changed_slot = 0;
+    market.scheduled_bets = 0;
+
+    emit!(MarketInitialized {
+        market: market.key(),
+        creator: market.creator,
+        title,
+        fee_bps,
+        end_ts,
+        resolve_deadline_ts,
+    });
+
+    Ok(())
+}
+
+/// Gives a market opened from `source` the terms `init_market` doesn't
+/// take: where its fee goes, its charity cut, its large bet threshold and
+/// its stake caps.
+fn copy_terms(market: &mut Market, source: &Market) -> Result<()> {
+    market.fee_splits = source.fee_splits.clone();
+    if source.charity.is_some() {
+        validate_charity(market, source.charity_bps)?;
+        market.charity = source.charity;
+        market.charity_bps = source.charity_bps;
+    }
+    market.large_bet_threshold = source.large_bet_threshold;
+    market.cap_a = source.cap_a;
+    market.cap_b = source.cap_b;
+    market.max_ratio_bps = source.max_ratio_bps;
+    Ok(())
+}
+
+/// Cancels `market` if it's still waiting on a resolution past its
+/// deadline. Returns whether it did.
+fn cancel_if_expired(market: &mut Account<Market>) -> Result<bool> {
+    if !market.cancel_if_expired(Clock::get()?.unix_timestamp) {
+        return Ok(false);
+    }
+
+    emit!(Cancelled {
+        market: market.key(),
+    });
+
+    Ok(true)
+}
+
+/// Settled markets keep their accounts until the claim window has passed.
+fn require_claim_window_over(market: &Market) -> Result<()> {
+    require!(
+        market.status == MarketStatus::Resolved || market.status == MarketStatus::Cancelled,
+        ErrorCode::MarketNotFinalized
+    );
+    require!(
+        Clock::get()?.unix_timestamp
+            >= market
+                .resolve_deadline_ts
+                .checked_add(CLAIM_WINDOW_SECS)
+                .ok_or(ErrorCode::Overflow)?,
+        ErrorCode::ClaimWindowOpen
+    );
+    Ok(())
+}
+
+/// The accounts `place_bet`, `place_bet_init_ata` and
+/// `execute_scheduled_bet` share. `owner` gets the position, `payer` pays
+/// for a new bettor or portfolio page and `authority` moves the stake out
+/// of `source`.
+struct BetAccounts<'a, 'info> {
+    owner: Pubkey,
+    payer: &'a Signer<'info>,
+    market: &'a mut Account<'info, Market>,
+    position: &'a mut Account<'info, Position>,
+    position_bump: u8,
+    source: &'a Account<'info, TokenAccount>,
+    authority: AccountInfo<'info>,
+    vault: &'a Account<'info, TokenAccount>,
+    token_program: &'a Program<'info, Token>,
+    system_program: &'a Program<'info, System>,
+    portfolio: Option<PortfolioAccounts<'a, 'info>>,
+}
+
+/// A bettor's portfolio and its current page.
+struct PortfolioAccounts<'a, 'info> {
+    portfolio: &'a mut Account<'info, Portfolio>,
+    page: &'a AccountInfo<'info>,
+}
+
+/// The portfolio accounts an instruction was given: both, or neither for
+/// a bettor who doesn't keep a portfolio.
+fn portfolio_accounts<'a, 'info>(
+    portfolio: &'a mut Option<Account<'info, Portfolio>>,
+    page: &'a Option<UncheckedAccount<'info>>,
+) -> Result<Option<PortfolioAccounts<'a, 'info>>> {
+    match (portfolio, page) {
+        (Some(portfolio), Some(page)) => Ok(Some(PortfolioAccounts { portfolio, page })),
+        (None, None) => Ok(None),
+        _ => err!(ErrorCode::InvalidPortfolioPage),
+    }
+}
+
+/// Records a single bet and moves its stake into the vault, signing for
+/// `authority` with `signer_seeds` when it's a PDA. On a market that
+/// tracks bettors the current bettor page comes first in
+/// `remaining_accounts`, then the market's activity account if it keeps
+/// one, then the owner's optional `[config, stake_account]`.
+fn bet_and_transfer<'info>(
+    accounts: BetAccounts<'_, 'info>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    side: BetSide,
+    amount: u64,
+    min_odds_bps: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let BetAccounts {
+        owner,
+        payer,
+        market,
+        position,
+        position_bump,
+        source,
+        authority,
+        vault,
+        token_program,
+        system_program,
+        portfolio,
+    } = accounts;
+    require!(!market.compressed, ErrorCode::MarketCompressed);
+
+    let activity_index = usize::from(market.track_bettors);
+    let stake_accounts = remaining_accounts
+        .get(activity_index + usize::from(market.track_activity)..)
+        .unwrap_or_default();
+    let discount_bps = staker_discount_bps(&owner, stake_accounts)?;
+    let new_bettor = position.amount == 0;
+    record_bet(
+        market,
+        position,
+        owner,
+        side,
+        amount,
+        min_odds_bps,
+        discount_bps,
+    )?;
+    position.bump = position_bump;
+
+    if market.track_bettors {
+        let page_info = remaining_accounts
+            .first()
+            .ok_or(ErrorCode::InvalidBettorPage)?;
+        record_bettor(
+            market,
+            page_info,
+            payer,
+            system_program,
+            BettorEntry {
+                owner,
+                side,
+                amount,
+            },
+        )?;
+    }
+
+    if market.track_activity {
+        let activity_info = remaining_accounts
+            .get(activity_index)
+            .ok_or(ErrorCode::InvalidActivityStats)?;
+        record_activity(market, activity_info, amount, new_bettor)?;
+    }
+
+    if let (Some(portfolio), true) = (portfolio, new_bettor) {
+        record_portfolio(
+            portfolio,
+            payer,
+            system_program,
+            PortfolioEntry {
+                market: market.key(),
+                action: PortfolioAction::Bet,
+                side,
+                timestamp: Clock::get()?.unix_timestamp,
+            },
+        )?;
+    }
+
+    // Transfer tokens from the bettor to the vault
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Transfer {
+            from: source.to_account_info(),
+            to: vault.to_account_info(),
+            authority,
+        },
+        signer_seeds,
+    );
+    token::transfer(cpi_ctx, amount)?;
+
+    Ok(())
+}
+
+/// Creates the program-owned account `account` of `space` bytes at a PDA
+/// signed for by `seeds`, paid for by `payer`.
+///
+/// As with Anchor's `init`, an address someone has already sent lamports to
+/// is topped up to rent exemption, then allocated and assigned. A plain
+/// `create_account` would fail on it as already in use, letting anyone
+/// block the account's creation for a transfer of one lamport.
+fn create_pda_account<'info>(
+    payer: &Signer<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer_seeds = &[seeds];
+    let lamports = account.lamports();
+    if lamports == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::CreateAccount {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+                signer_seeds,
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    let shortfall = rent.saturating_sub(lamports);
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            signer_seeds,
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            signer_seeds,
+        ),
+        &crate::ID,
+    )
+}
+
+/// Appends `entry` to the market's current bettor page, creating the page
+/// paid for by `payer` when the previous one is full.
+fn record_bettor<'info>(
+    market: &mut Account<'info, Market>,
+    page_info: &'info AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    entry: BettorEntry,
+) -> Result<()> {
+    let market_key = market.key();
+    let index = market.bettor_count / BETTOR_PAGE_SIZE as u32;
+    let index_bytes = index.to_le_bytes();
+    let (expected_page, page_bump) = Pubkey::find_program_address(
+        &[BETTOR_PAGE_SEED, market_key.as_ref(), &index_bytes],
+        &crate::ID,
+    );
+    require_keys_eq!(page_info.key(), expected_page, ErrorCode::InvalidBettorPage);
+
+    let mut page = if page_info.data_is_empty() {
+        let page_seeds = &[
+            BETTOR_PAGE_SEED,
+            market_key.as_ref(),
+            &index_bytes,
+            &[page_bump],
+        ];
+        create_pda_account(
+            payer,
+            page_info,
+            system_program,
+            BettorPage::LEN,
+            &page_seeds[..],
+        )?;
+        let mut page = Account::<BettorPage>::try_from_unchecked(page_info)?;
+        page.market = market_key;
+        page.index = index;
+        page.payer = payer.key();
+        market.bettor_pages = market
+            .bettor_pages
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+        page
+    } else {
+        Account::<BettorPage>::try_from(page_info)?
+    };
+
+    page.bettors.push(entry);
+    market.bettor_count = market
+        .bettor_count
+        .checked_add(1)
+        .ok_or(ErrorCode::Overflow)?;
+    page.exit(&crate::ID)
+}
+
+/// Appends `entry` to the owner's current portfolio page, creating the
+/// page paid for by `payer` when the previous one is full.
+fn record_portfolio<'info>(
+    accounts: PortfolioAccounts<'_, 'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    entry: PortfolioEntry,
+) -> Result<()> {
+    let PortfolioAccounts { portfolio, page } = accounts;
+    let owner = portfolio.owner;
+    let index = portfolio.current_page();
+    let index_bytes = index.to_le_bytes();
+    let (expected_page, page_bump) = Pubkey::find_program_address(
+        &[PORTFOLIO_PAGE_SEED, owner.as_ref(), &index_bytes],
+        &crate::ID,
+    );
+    require_keys_eq!(page.key(), expected_page, ErrorCode::InvalidPortfolioPage);
+
+    let mut data = if page.data_is_empty() {
+        let page_seeds = &[
+            PORTFOLIO_PAGE_SEED,
+            owner.as_ref(),
+            &index_bytes,
+            &[page_bump],
+        ];
+        create_pda_account(
+            payer,
+            page,
+            system_program,
+            PortfolioPage::LEN,
+            &page_seeds[..],
+        )?;
+        portfolio.pages = portfolio.pages.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        PortfolioPage {
+            owner,
+            index,
+            entries: Vec::new(),
+        }
+    } else {
+        require_keys_eq!(*page.owner, crate::ID, ErrorCode::InvalidPortfolioPage);
+        PortfolioPage::try_deserialize(&mut &page.try_borrow_data()?[..])?
+    };
+
+    data.entries.push(entry);
+    portfolio.entry_count = portfolio
+        .entry_count
+        .checked_add(1)
+        .ok_or(ErrorCode::Overflow)?;
+    data.try_serialize(&mut &mut page.try_borrow_mut_data()?[..])
+}
+
+/// Adds a bet to the market's activity account.
+fn record_activity<'info>(
+    market: &Account<'info, Market>,
+    activity_info: &'info AccountInfo<'info>,
+    amount: u64,
+    new_bettor: bool,
+) -> Result<()> {
+    let (expected, _) =
+        Pubkey::find_program_address(&[ACTIVITY_SEED, market.key().as_ref()], &crate::ID);
+    require_keys_eq!(
+        activity_info.key(),
+        expected,
+        ErrorCode::InvalidActivityStats
+    );
+
+    let mut activity = Account::<MarketActivity>::try_from(activity_info)?;
+    activity.record(Clock::get()?.unix_timestamp, amount, new_bettor)?;
+    activity.exit(&crate::ID)
+}
+
+/// Moves all lamports of a program-owned account into `destination` and
+/// hands the emptied account back to the system program.
+fn close_into(info: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    let lamports = info.lamports();
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ErrorCode::Overflow)?;
+    **info.try_borrow_mut_lamports()? = 0;
+    info.assign(&system_program::ID);
+    info.resize(0)?;
+    Ok(())
+}
+
+fn validate_fee_splits(splits: &[FeeSplit]) -> Result<()> {
+    require!(splits.len() <= MAX_FEE_SPLITS, ErrorCode::InvalidFeeSplits);
+    let mut total: u64 = 0;
+    for split in splits {
+        require!(split.bps > 0, ErrorCode::InvalidFeeSplits);
+        total += split.bps as u64;
+    }
+    require!(total <= math::BPS_DENOMINATOR, ErrorCode::InvalidFeeSplits);
+    Ok(())
+}
+
+/// A charity cut has to leave the fee and insurance whole.
+fn validate_charity(market: &Market, charity_bps: u16) -> Result<()> {
+    require!(
+        charity_bps > 0
+            && charity_bps as u64 + market.fee_bps as u64 + market.insurance_bps as u64
+                <= math::BPS_DENOMINATOR,
+        ErrorCode::InvalidCharity
+    );
+    Ok(())
+}
+
+fn validate_fee_tiers(fee_tiers: &[FeeTier]) -> Result<()> {
+    require!(fee_tiers.len() <= MAX_FEE_TIERS, ErrorCode::InvalidFeeTiers);
+    for (i, tier) in fee_tiers.iter().enumerate() {
+        require!(
+            tier.discount_bps as u64 <= math::BPS_DENOMINATOR && tier.max_fee_bps <= MAX_FEE_BPS,
+            ErrorCode::InvalidFeeTiers
+        );
+        if i > 0 {
+            require!(
+                tier.min_stake > fee_tiers[i - 1].min_stake,
+                ErrorCode::InvalidFeeTiers
+            );
+        }
+    }
+    Ok(())
+}
+
+fn validate_policy(policy: &MarketPolicy) -> Result<()> {
+    require!(
+        policy.max_fee_bps <= MAX_FEE_BPS
+            && policy.default_fee_bps <= policy.max_fee_bps
+            && policy.default_betting_secs > 0
+            && policy.default_resolve_secs > 0
+            && policy.max_title_len as usize <= MAX_TITLE_LEN
+            && policy.insurance_bps <= MAX_INSURANCE_BPS,
+        ErrorCode::InvalidMarketPolicy
+    );
+    Ok(())
+}
+
+/// The policy a market by `creator` is held to, with the fee ceiling of
+/// their fee tier when their stake account is the first remaining account.
+fn creator_policy(
+    config: &Config,
+    creator: &Pubkey,
+    remaining_accounts: &[AccountInfo],
+) -> Result<MarketPolicy> {
+    Ok(creator_stake(creator, remaining_accounts)?
+        .map_or(config.policy, |staked| config.policy_for(staked)))
+}
+
+/// What `creator` has staked, when their stake account is the first
+/// remaining account.
+fn creator_stake(creator: &Pubkey, remaining_accounts: &[AccountInfo]) -> Result<Option<u64>> {
+    let Some(stake_info) = remaining_accounts.first() else {
+        return Ok(None);
+    };
+
+    require_keys_eq!(*stake_info.owner, crate::ID, ErrorCode::InvalidStakeAccount);
+    let stake_account = StakeAccount::try_deserialize(&mut &stake_info.try_borrow_data()?[..])?;
+    require_keys_eq!(
+        stake_account.owner,
+        *creator,
+        ErrorCode::InvalidStakeAccount
+    );
+
+    Ok(Some(stake_account.amount))
+}
+
+/// Fee discount earned by `user`'s stake, read from the optional
+/// `[config, stake_account]` pair at the start of `remaining_accounts`.
+/// Returns 0 when the pair is not supplied.
+fn staker_discount_bps(user: &Pubkey, remaining_accounts: &[AccountInfo]) -> Result<u16> {
+    let [config_info, stake_info, ..] = remaining_accounts else {
+        return Ok(0);
+    };
+
+    require_keys_eq!(
+        *config_info.owner,
+        crate::ID,
+        ErrorCode::InvalidStakeAccount
+    );
+    require_keys_eq!(*stake_info.owner, crate::ID, ErrorCode::InvalidStakeAccount);
+    let config = Config::try_deserialize(&mut &config_info.try_borrow_data()?[..])?;
+    let stake_account = StakeAccount::try_deserialize(&mut &stake_info.try_borrow_data()?[..])?;
+    require_keys_eq!(stake_account.owner, *user, ErrorCode::InvalidStakeAccount);
+
+    Ok(config.discount_bps_for(stake_account.amount))
+}
+
+/// For a conditional market, requires the parent market (first remaining
+/// account) to have resolved to the outcome the market depends on.
+fn check_dependency(market: &Market, remaining_accounts: &[AccountInfo]) -> Result<()> {
+    let Some(dependency) = market.dependency else {
+        return Ok(());
+    };
+
+    let parent_info = remaining_accounts
+        .first()
+        .ok_or(ErrorCode::InvalidDependency)?;
+    require_keys_eq!(
+        parent_info.key(),
+        dependency.market,
+        ErrorCode::InvalidDependency
+    );
+    require_keys_eq!(*parent_info.owner, crate::ID, ErrorCode::InvalidDependency);
+
+    let parent = Market::try_deserialize(&mut &parent_info.try_borrow_data()?[..])?;
+    require!(
+        parent.status == MarketStatus::Resolved && parent.outcome == Some(dependency.outcome),
+        ErrorCode::DependencyNotMet
+    );
+
+    Ok(())
+}
+
+/// A market's side vaults, tracking what each holds as payouts leave them.
+struct Vaults<'a, 'info> {
+    a: &'a Account<'info, TokenAccount>,
+    b: &'a Account<'info, TokenAccount>,
+    balances: [u64; 2],
+}
+
+impl<'a, 'info> Vaults<'a, 'info> {
+    fn new(a: &'a Account<'info, TokenAccount>, b: &'a Account<'info, TokenAccount>) -> Self {
+        Self {
+            a,
+            b,
+            balances: [a.amount, b.amount],
+        }
+    }
+
+    /// What to take from each vault to pay `amount`, `first`'s vault first.
+    fn split(&self, first: BetSide, amount: u64) -> Result<[u64; 2]> {
+        math::split_payout(self.balances, first as usize, amount)
+    }
+
+    /// Pays `amount` to `to` under the market's signature, starting with
+    /// `first`'s vault and taking what it lacks from the other.
+    fn transfer(
+        &mut self,
+        market: &Account<'info, Market>,
+        first: BetSide,
+        to: &impl ToAccountInfo<'info>,
+        token_program: &Program<'info, Token>,
+        amount: u64,
+    ) -> Result<()> {
+        let parts = self.split(first, amount)?;
+        let seeds = &[
+            MARKET_SEED,
+            market.creator.as_ref(),
+            &market.market_id.to_le_bytes(),
+            &[market.bump],
+        ];
+        let signer = &[&seeds[..]];
+        for (i, (vault, part)) in [self.a, self.b].into_iter().zip(parts).enumerate() {
+            if part == 0 {
+                continue;
+            }
+            let cpi_ctx = CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Transfer {
+                    from: vault.to_account_info(),
+                    to: to.to_account_info(),
+                    authority: market.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(cpi_ctx, part)?;
+            self.balances[i] -= part;
+        }
+        Ok(())
+    }
+}
+
+/// Pays `user` what `position` is owed, plus the staker rebate it earned
+/// at bet time, and drops it from the market's open positions. The caller
+/// marks it claimed.
+fn pay_claim<'info>(
+    market: &mut Account<'info, Market>,
+    position: &Position,
+    user: &Signer<'info>,
+    user_token_account: &Account<'info, TokenAccount>,
+    vaults: &mut Vaults<'_, 'info>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    let (payout, rebate) = market.settle_claim(position)?;
+
+    vaults.transfer(
+        market,
+        position.side,
+        user_token_account,
+        token_program,
+        payout,
+    )?;
+
+    market.release_position(position)?;
+
+    emit_claim(market.key(), user.key(), position, payout, rebate);
+
+    Ok(())
+}
+
+/// Emits `Claimed` for a payout, and `FeeRebated` when it includes a rebate.
+fn emit_claim(market: Pubkey, user: Pubkey, position: &Position, payout: u64, rebate: u64) {
+    emit!(Claimed {
+        market,
+        user,
+        amount: payout,
+    });
+
+    if rebate > 0 {
+        emit!(FeeRebated {
+            market,
+            user,
+            discounted_stake: position.discounted_stake,
+            amount: rebate,
+        });
+    }
+}
+
+/// Validates a bet against `market` and applies it to the market and
+/// position state, at the fee discount of `user`'s stake. The caller moves
+/// the tokens into the vault.
+fn record_bet(
+    market: &mut Account<Market>,
+    position: &mut Position,
+    user: Pubkey,
+    side: BetSide,
+    amount: u64,
+    min_odds_bps: u64,
+    discount_bps: u16,
+) -> Result<()> {
+    require!(
+        market.status == MarketStatus::Open,
+        ErrorCode::MarketNotOpen
+    );
+    require!(
+        Clock::get()?.unix_timestamp < market.end_ts,
+        ErrorCode::BettingClosed
+    );
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    market.check_stake_caps(side, amount)?;
+
+    let quote = market.apply_bet(position, user, side, amount, discount_bps)?;
+    require!(quote.odds_bps >= min_odds_bps, ErrorCode::OddsBelowMinimum);
+    market.pools_changed_slot = Clock::get()?.slot;
+
+    emit!(BetPlaced {
+        market: market.key(),
+        user,
+        side,
+        amount,
+    });
+
+    if market.large_bet_threshold > 0 && amount >= market.large_bet_threshold {
+        emit!(LargeBetPlaced {
+            market: market.key(),
+            user,
+            side,
+            amount,
+            staked_a: market.staked_a,
+            staked_b: market.staked_b,
+        });
+    }
+
+    if market.lock_odds {
+        emit!(BetReceipt {
+            market: market.key(),
+            user,
+            side,
+            amount,
+            odds_bps: quote.odds_bps,
+            locked_payout: quote.implied_payout,
+        });
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, fee_bps: u16, end_ts: i64, resolve_deadline_ts: i64, title: String)]
+pub struct InitializeMarket<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = Market::LEN,
+        seeds = [MARKET_SEED, creator.key().as_ref(), &market_id.to_le_bytes()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = mint,
+        token::authority = market,
+        seeds = [VAULT_SEED, market.key().as_ref(), &[BetSide::A as u8]],
+        bump
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = mint,
+        token::authority = market,
+        seeds = [VAULT_SEED, market.key().as_ref(), &[BetSide::B as u8]],
+        bump
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct InitializeMarketWithBet<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = Market::LEN,
+        seeds = [MARKET_SEED, creator.key().as_ref(), &market_id.to_le_bytes()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = mint,
+        token::authority = market,
+        seeds = [VAULT_SEED, market.key().as_ref(), &[BetSide::A as u8]],
+        bump
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = mint,
+        token::authority = market,
+        seeds = [VAULT_SEED, market.key().as_ref(), &[BetSide::B as u8]],
+        bump
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = Position::LEN,
+        seeds = [POSITION_SEED, market.key().as_ref(), creator.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        constraint = creator_token_account.mint == mint.key(),
+        constraint = creator_token_account.owner == creator.key()
+    )]
+    pub creator_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CloneMarket<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub source: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = Market::LEN,
+        seeds = [MARKET_SEED, creator.key().as_ref(), &market_id.to_le_bytes()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(constraint = mint.key() == source.mint @ ErrorCode::InvalidMint)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = mint,
+        token::authority = market,
+        seeds = [VAULT_SEED, market.key().as_ref(), &[BetSide::A as u8]],
+        bump
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = mint,
+        token::authority = market,
+        seeds = [VAULT_SEED, market.key().as_ref(), &[BetSide::B as u8]],
+        bump
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ForkMarket<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    /// Arbitrates the fork along with the creator
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        constraint = original.creator == creator.key()
+    )]
+    pub original: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = Market::LEN,
+        seeds = [MARKET_SEED, creator.key().as_ref(), &market_id.to_le_bytes()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(constraint = mint.key() == original.mint @ ErrorCode::InvalidMint)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = mint,
+        token::authority = market,
+        seeds = [VAULT_SEED, market.key().as_ref(), &[BetSide::A as u8]],
+        bump
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = mint,
+        token::authority = market,
+        seeds = [VAULT_SEED, market.key().as_ref(), &[BetSide::B as u8]],
+        bump
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(side: BetSide, amount: u64, min_odds_bps: u64)]
+pub struct PlaceBet<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = Position::LEN,
+        seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == market.mint,
+        constraint = user_token_account.owner == user.key()
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault.key() == market.vault(side) @ ErrorCode::InvalidVault
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    #[account(
+        mut,
+        seeds = [PORTFOLIO_SEED, user.key().as_ref()],
+        bump = portfolio.bump
+    )]
+    pub portfolio: Option<Account<'info, Portfolio>>,
+
+    /// CHECK: the portfolio's current page, checked and created if needed
+    /// when recording in it
+    #[account(mut)]
+    pub portfolio_page: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(side: BetSide, amount: u64, min_odds_bps: u64)]
+pub struct PlaceBetInitAta<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(constraint = mint.key() == market.mint)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = Position::LEN,
+        seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault.key() == market.vault(side) @ ErrorCode::InvalidVault
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    #[account(
+        mut,
+        seeds = [PORTFOLIO_SEED, user.key().as_ref()],
+        bump = portfolio.bump
+    )]
+    pub portfolio: Option<Account<'info, Portfolio>>,
+
+    /// CHECK: the portfolio's current page, checked and created if needed
+    /// when recording in it
+    #[account(mut)]
+    pub portfolio_page: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBetsBatch<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CashOut<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == market.mint,
+        constraint = user_token_account.owner == user.key()
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_a.key() == market.vault_a @ ErrorCode::InvalidVault
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b.key() == market.vault_b @ ErrorCode::InvalidVault
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetCashOutOrder<'info> {
+    pub user: Signer<'info>,
+
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteCashOut<'info> {
+    pub executor: Signer<'info>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: the position's owner, who gets the payout
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [POSITION_SEED, market.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        constraint = owner_token_account.mint == market.mint,
+        constraint = owner_token_account.owner == owner.key()
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = executor_token_account.mint == market.mint
+    )]
+    pub executor_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_a.key() == market.vault_a @ ErrorCode::InvalidVault
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b.key() == market.vault_b @ ErrorCode::InvalidVault
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBetting<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+#[instruction(outcome: BetSide)]
+pub struct Resolve<'info> {
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct SetDependency<'info> {
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key()
+    )]
+    pub market: Account<'info, Market>,
+
+    pub parent: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct VoidDependent<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    pub parent: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct CancelExpired<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct Claim<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, market.key().as_ref(), user.key().as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == market.mint,
+        constraint = user_token_account.owner == user.key()
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_a.key() == market.vault_a @ ErrorCode::InvalidVault
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b.key() == market.vault_b @ ErrorCode::InvalidVault
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [PORTFOLIO_SEED, user.key().as_ref()],
+        bump = portfolio.bump
+    )]
+    pub portfolio: Option<Account<'info, Portfolio>>,
+
+    /// CHECK: the portfolio's current page, checked and created if needed
+    /// when recording in it
+    #[account(mut)]
+    pub portfolio_page: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct OpenPortfolio<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init,
+        payer = user,
+        space = Portfolio::LEN,
+        seeds = [PORTFOLIO_SEED, user.key().as_ref()],
+        bump
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u32)]
+pub struct ClosePortfolioPage<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PORTFOLIO_SEED, user.key().as_ref()],
+        bump = portfolio.bump
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [PORTFOLIO_PAGE_SEED, user.key().as_ref(), &index.to_le_bytes()],
+        bump
+    )]
+    pub portfolio_page: Account<'info, PortfolioPage>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePortfolio<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [PORTFOLIO_SEED, user.key().as_ref()],
+        bump = portfolio.bump
+    )]
+    pub portfolio: Account<'info, Portfolio>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [
+            PORTFOLIO_PAGE_SEED,
+            user.key().as_ref(),
+            &portfolio.current_page().to_le_bytes()
+        ],
+        bump
+    )]
+    pub portfolio_page: Option<Account<'info, PortfolioPage>>,
+}
+
+#[derive(Accounts)]
+pub struct EnableCompressedPositions<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key()
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = PositionTree::LEN,
+        seeds = [POSITION_TREE_SEED, market.key().as_ref()],
+        bump
+    )]
+    pub position_tree: AccountLoader<'info, PositionTree>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(side: BetSide)]
+pub struct PlaceCompressedBet<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_TREE_SEED, market.key().as_ref()],
+        bump
+    )]
+    pub position_tree: AccountLoader<'info, PositionTree>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == market.mint,
+        constraint = user_token_account.owner == user.key()
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault.key() == market.vault(side) @ ErrorCode::InvalidVault
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCompressed<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_TREE_SEED, market.key().as_ref()],
+        bump
+    )]
+    pub position_tree: AccountLoader<'info, PositionTree>,
+
+    #[account(
+        mut,
         constraint = user_token_account.mint == market.mint,
         constraint = user_token_account.owner == user.key()
     )]
@@ -458,76 +3760,1493 @@ pub struct Claim<'info> {
 
     #[account(
         mut,
-        constraint = vault.key() == market.vault
+        constraint = vault_a.key() == market.vault_a @ ErrorCode::InvalidVault
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b.key() == market.vault_b @ ErrorCode::InvalidVault
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct EnableBettorPages<'info> {
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBettorPage<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = payer,
+        constraint = bettor_page.market == market.key() @ ErrorCode::InvalidBettorPage
+    )]
+    pub bettor_page: Account<'info, BettorPage>,
+
+    /// CHECK: receives the page's rent
+    #[account(
+        mut,
+        address = bettor_page.payer
+    )]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EnableActivityStats<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key()
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = MarketActivity::LEN,
+        seeds = [ACTIVITY_SEED, market.key().as_ref()],
+        bump
+    )]
+    pub activity: Account<'info, MarketActivity>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseActivityStats<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [ACTIVITY_SEED, market.key().as_ref()],
+        bump
+    )]
+    pub activity: Account<'info, MarketActivity>,
+
+    /// CHECK: receives the account's rent
+    #[account(
+        mut,
+        address = activity.payer
+    )]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(title_hash: [u8; 32], nonce: u16)]
+pub struct RegisterMarketTitle<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key()
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = MarketTitle::LEN,
+        seeds = [MARKET_TITLE_SEED, title_hash.as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub market_title: Account<'info, MarketTitle>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseMarketTitle<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = creator,
+        constraint = market_title.market == market.key() @ ErrorCode::InvalidMarketTitle
+    )]
+    pub market_title: Account<'info, MarketTitle>,
+
+    /// CHECK: receives the registration's rent
+    #[account(
+        mut,
+        address = market.creator
+    )]
+    pub creator: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct ScheduleBet<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(constraint = mint.key() == market.mint @ ErrorCode::InvalidMint)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = ScheduledBet::LEN,
+        seeds = [
+            SCHEDULED_BET_SEED,
+            market.key().as_ref(),
+            owner.key().as_ref(),
+            &id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub scheduled_bet: Account<'info, ScheduledBet>,
+
+    #[account(
+        init,
+        payer = owner,
+        token::mint = mint,
+        token::authority = scheduled_bet,
+        seeds = [SCHEDULED_ESCROW_SEED, scheduled_bet.key().as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token_account.mint == market.mint,
+        constraint = owner_token_account.owner == owner.key()
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteScheduledBet<'info> {
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = executor,
+        has_one = market,
+        has_one = owner
+    )]
+    pub scheduled_bet: Account<'info, ScheduledBet>,
+
+    /// CHECK: the scheduled bet's owner, who gets the position
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = executor,
+        space = Position::LEN,
+        seeds = [POSITION_SEED, market.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        seeds = [SCHEDULED_ESCROW_SEED, scheduled_bet.key().as_ref()],
+        bump = scheduled_bet.escrow_bump
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault.key() == market.vault(scheduled_bet.side) @ ErrorCode::InvalidVault
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct CancelScheduledBet<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = owner,
+        has_one = market,
+        has_one = owner
+    )]
+    pub scheduled_bet: Account<'info, ScheduledBet>,
+
+    /// CHECK: the scheduled bet's owner, who gets the stake and rent back
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [SCHEDULED_ESCROW_SEED, scheduled_bet.key().as_ref()],
+        bump = scheduled_bet.escrow_bump
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token_account.mint == market.mint,
+        constraint = owner_token_account.owner == owner.key()
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Distribute<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        constraint = vault_a.key() == market.vault_a @ ErrorCode::InvalidVault
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b.key() == market.vault_b @ ErrorCode::InvalidVault
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawCreatorFee<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key()
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        constraint = creator_token_account.mint == market.mint,
+        constraint = creator_token_account.owner == creator.key()
+    )]
+    pub creator_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_a.key() == market.vault_a @ ErrorCode::InvalidVault
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b.key() == market.vault_b @ ErrorCode::InvalidVault
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeSplits<'info> {
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeFees<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        address = get_associated_token_address(&market.creator, &market.mint) @ ErrorCode::InvalidRecipient
+    )]
+    pub creator_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_a.key() == market.vault_a @ ErrorCode::InvalidVault
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b.key() == market.vault_b @ ErrorCode::InvalidVault
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetCharity<'info> {
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key()
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(constraint = charity_token_account.mint == market.mint @ ErrorCode::InvalidMint)]
+    pub charity_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct SetLargeBetThreshold<'info> {
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct SetStakeCaps<'info> {
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key()
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct DonateToCharity<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        constraint = vault_a.key() == market.vault_a @ ErrorCode::InvalidVault
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b.key() == market.vault_b @ ErrorCode::InvalidVault
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = Some(charity_token_account.key()) == market.charity @ ErrorCode::InvalidRecipient
+    )]
+    pub charity_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CompactMarket<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = market.creator == creator.key() @ ErrorCode::UnauthorizedWithdrawal
+    )]
+    pub market: Account<'info, Market>,
+}
+
+#[derive(Accounts)]
+pub struct BurnFee<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        address = market.mint @ ErrorCode::InvalidMint
+    )]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = vault_a.key() == market.vault_a @ ErrorCode::InvalidVault
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b.key() == market.vault_b @ ErrorCode::InvalidVault
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CollectInsurance<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(address = market.mint @ ErrorCode::InvalidMint)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = vault_a.key() == market.vault_a @ ErrorCode::InvalidVault
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b.key() == market.vault_b @ ErrorCode::InvalidVault
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        token::mint = mint,
+        token::authority = config,
+        seeds = [INSURANCE_VAULT_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub insurance_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Compensate<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [INSURANCE_VAULT_SEED, market.mint.as_ref()],
+        bump
+    )]
+    pub insurance_vault: Account<'info, TokenAccount>,
+
+    /// The bettor being compensated, who must hold stake in the market
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, market.key().as_ref(), position.owner.as_ref()],
+        bump = position.bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        address = get_associated_token_address(&position.owner, &market.mint)
+            @ ErrorCode::InvalidRecipient
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct GcMarket<'info> {
+    #[account(mut)]
+    pub caller: Signer<'info>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: receives the creator's share of reclaimed rent
+    #[account(
+        mut,
+        address = market.creator @ ErrorCode::UnauthorizedWithdrawal
+    )]
+    pub creator: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = creator_token_account.mint == market.mint,
+        constraint = creator_token_account.owner == market.creator
+    )]
+    pub creator_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_a.key() == market.vault_a @ ErrorCode::InvalidVault
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = vault_b.key() == market.vault_b @ ErrorCode::InvalidVault
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = Config::LEN,
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    pub stake_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = admin,
+        token::mint = stake_mint,
+        token::authority = config,
+        seeds = [STAKE_VAULT_SEED],
+        bump
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+
+    /// The deployed program, so its upgrade authority can be checked
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key())
+            @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub program: Program<'info, crate::program::FriendsBets>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct AdminConfig<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::UnauthorizedAdmin
+    )]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyConfigUpdate<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = StakeAccount::LEN,
+        seeds = [STAKE_SEED, user.key().as_ref()],
+        bump
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == config.stake_mint,
+        constraint = user_token_account.owner == user.key()
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = stake_vault.key() == config.stake_vault
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Unstake<'info> {
+    pub user: Signer<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [STAKE_SEED, user.key().as_ref()],
+        bump = stake_account.bump
     )]
-    pub vault: Account<'info, TokenAccount>,
-
-    pub token_program: Program<'info, Token>,
+    pub stake_account: Account<'info, StakeAccount>,
 }
 
 #[derive(Accounts)]
-pub struct WithdrawCreatorFee<'info> {
-    #[account(mut)]
-    pub creator: Signer<'info>,
+pub struct WithdrawUnstaked<'info> {
+    pub user: Signer<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
 
     #[account(
         mut,
-        constraint = market.creator == creator.key()
+        seeds = [STAKE_SEED, user.key().as_ref()],
+        bump = stake_account.bump
     )]
-    pub market: Account<'info, Market>,
+    pub stake_account: Account<'info, StakeAccount>,
 
     #[account(
         mut,
-        constraint = creator_token_account.mint == market.mint,
-        constraint = creator_token_account.owner == creator.key()
+        constraint = user_token_account.mint == config.stake_mint,
+        constraint = user_token_account.owner == user.key()
     )]
-    pub creator_token_account: Account<'info, TokenAccount>,
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = stake_vault.key() == config.stake_vault
+    )]
+    pub stake_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[account]
+pub struct Market {
+    pub market_id: u64,
+    pub creator: Pubkey,
+    pub mint: Pubkey,
+    /// Holds the stakes on side A.
+    pub vault_a: Pubkey,
+    /// Holds the stakes on side B.
+    pub vault_b: Pubkey,
+    pub fee_bps: u16,
+    pub end_ts: i64,
+    pub resolve_deadline_ts: i64,
+    pub staked_a: u64,
+    pub staked_b: u64,
+    pub status: MarketStatus,
+    pub outcome: Option<BetSide>,
+    pub creator_fee_withdrawn: bool,
+    pub bump: u8,
+    pub vault_a_bump: u8,
+    pub vault_b_bump: u8,
+    pub title: String,
+    pub allow_cash_out: bool,
+    pub cashout_haircut_bps: u16,
+    /// Stake left behind by cash-outs, paid to winners with the rest of the
+    /// pool, or shared by stake between the remaining positions if the
+    /// market is cancelled.
+    pub retained: u64,
+    pub lock_odds: bool,
+    /// Sum of payouts locked in by bets on each side when `lock_odds` is set.
+    pub locked_payout_a: u64,
+    pub locked_payout_b: u64,
+    /// Market this one replaced via `fork_market`, if any.
+    pub forked_from: Option<Pubkey>,
+    /// Parent outcome this market is conditional on, if any.
+    pub dependency: Option<MarketDependency>,
+    /// Positions holding stake that haven't been claimed or cashed out.
+    pub open_positions: u32,
+    /// Fee is burned via `burn_fee` instead of paid to the creator.
+    pub burn_fee: bool,
+    /// Fee handed back to stakers so far, out of [`Market::staker_rebates`].
+    pub fee_discounts: u64,
+    /// Sum of the positions' `discounted_stake`, fixing how much of the fee
+    /// goes back to stakers instead of the creator.
+    pub discounted_stake: u64,
+    /// Bets are leaves in the market's `PositionTree` instead of `Position` accounts.
+    pub compressed: bool,
+    /// `place_bet` records every bet in the market's `BettorPage`s.
+    pub track_bettors: bool,
+    /// Bets recorded in bettor pages so far.
+    pub bettor_count: u32,
+    /// Bettor pages created and not yet closed.
+    pub bettor_pages: u32,
+    /// Cut of the pool that goes to the insurance vault once resolved.
+    pub insurance_bps: u16,
+    pub insurance_collected: bool,
+    /// Paid out of insurance to this market's bettors after it was voided.
+    pub compensated: u64,
+    /// Token account given `charity_bps` of the pool once resolved, if any.
+    pub charity: Option<Pubkey>,
+    pub charity_bps: u16,
+    pub charity_paid: bool,
+    /// Bets of at least this much also emit `LargeBetPlaced`; 0 for none.
+    pub large_bet_threshold: u64,
+    /// `place_bet` keeps the market's `MarketActivity` account.
+    pub track_activity: bool,
+    /// Most that can be staked on each side; 0 for no cap.
+    pub cap_a: u64,
+    pub cap_b: u64,
+    /// Most a side can hold relative to the other once both have stake, in
+    /// basis points (20000 for 2x); 0 for no limit.
+    pub max_ratio_bps: u32,
+    /// The market holds a `MarketTitle` registration.
+    pub title_registered: bool,
+    /// Recipients sharing the fee with the creator, paid by `distribute_fees`.
+    pub fee_splits: Vec<FeeSplit>,
+    /// Slot the stakes last changed in; cash-out orders fill in a later one.
+    pub pools_changed_slot: u64,
+    /// Scheduled bets still holding stake in escrow for this market; it
+    /// can't be collected while any are left.
+    pub scheduled_bets: u32,
+}
+
+impl Market {
+    pub const LEN: usize = 8 + // discriminator
+        8 + // market_id
+        32 + // creator
+        32 + // mint
+        32 + // vault_a
+        32 + // vault_b
+        2 + // fee_bps
+        8 + // end_ts
+        8 + // resolve_deadline_ts
+        8 + // staked_a
+        8 + // staked_b
+        1 + // status
+        1 + 1 + // outcome (Option<BetSide>)
+        1 + // creator_fee_withdrawn
+        1 + // bump
+        1 + // vault_a_bump
+        1 + // vault_b_bump
+        4 + MAX_TITLE_LEN + // title
+        1 + // allow_cash_out
+        2 + // cashout_haircut_bps
+        8 + // retained
+        1 + // lock_odds
+        8 + // locked_payout_a
+        8 + // locked_payout_b
+        1 + 32 + // forked_from
+        1 + 32 + 1 + // dependency
+        4 + // open_positions
+        1 + // burn_fee
+        8 + // fee_discounts
+        8 + // discounted_stake
+        1 + // compressed
+        1 + // track_bettors
+        4 + // bettor_count
+        4 + // bettor_pages
+        2 + // insurance_bps
+        1 + // insurance_collected
+        8 + // compensated
+        1 + 32 + // charity
+        2 + // charity_bps
+        1 + // charity_paid
+        8 + // large_bet_threshold
+        1 + // track_activity
+        8 + // cap_a
+        8 + // cap_b
+        4 + // max_ratio_bps
+        1 + // title_registered
+        4 + MAX_FEE_SPLITS * FeeSplit::LEN + // fee_splits
+        8 + // pools_changed_slot
+        4; // scheduled_bets
+
+    /// Size of a market after `compact_market`, with no title, dependency
+    /// or fee splits.
+    pub const COMPACT_LEN: usize =
+        Self::LEN - MAX_TITLE_LEN - (32 + 1) - MAX_FEE_SPLITS * FeeSplit::LEN;
+
+    /// The vault holding the stakes on `side`.
+    pub fn vault(&self, side: BetSide) -> Pubkey {
+        match side {
+            BetSide::A => self.vault_a,
+            BetSide::B => self.vault_b,
+        }
+    }
+
+    /// The side that lost a resolved market.
+    pub fn losing_side(&self) -> Result<BetSide> {
+        Ok(self.outcome.ok_or(ErrorCode::MarketNotResolved)?.other())
+    }
+
+    /// Most that can still be bet on `side` under the market's stake caps,
+    /// or `None` when nothing limits it.
+    pub fn bet_room(&self, side: BetSide) -> Result<Option<u64>> {
+        let staked = self.side_total(side);
+        let cap = match side {
+            BetSide::A => self.cap_a,
+            BetSide::B => self.cap_b,
+        };
+        let mut room = (cap > 0).then(|| cap.saturating_sub(staked));
+
+        let other = self.side_total(side.other());
+        if self.max_ratio_bps > 0 && staked > 0 && other > 0 {
+            let limit = u128::from(other) * u128::from(self.max_ratio_bps)
+                / u128::from(math::BPS_DENOMINATOR);
+            let limit = u64::try_from(limit).unwrap_or(u64::MAX);
+            let ratio_room = limit.saturating_sub(staked);
+            room = Some(room.map_or(ratio_room, |room| room.min(ratio_room)));
+        }
+        Ok(room)
+    }
+
+    /// Fails when betting `amount` on `side` would break a stake cap.
+    pub fn check_stake_caps(&self, side: BetSide, amount: u64) -> Result<()> {
+        if let Some(room) = self.bet_room(side)? {
+            require!(amount <= room, ErrorCode::StakeCapExceeded);
+        }
+        Ok(())
+    }
+
+    /// Most the vaults can still hold once everyone is paid for `gc_market`
+    /// to hand it to the creator: rounding dust, and on a cancelled market
+    /// every bettor cashed out of, the stake their cash-outs retained, as
+    /// there's nobody left to refund it to.
+    pub fn unowed_balance(&self) -> Result<u64> {
+        if self.status == MarketStatus::Cancelled && self.total_staked()? == 0 {
+            return Ok(GC_DUST_LIMIT
+                .checked_add(self.retained)
+                .ok_or(ErrorCode::Overflow)?);
+        }
+        Ok(GC_DUST_LIMIT)
+    }
+
+    /// Cancels the market if it's still waiting on a resolution at `now`,
+    /// past its deadline, as `claim` does. Returns whether it did.
+    pub fn cancel_if_expired(&mut self, now: i64) -> bool {
+        let expired =
+            self.status == MarketStatus::PendingResolve && now >= self.resolve_deadline_ts;
+        if expired {
+            self.status = MarketStatus::Cancelled;
+        }
+        expired
+    }
+
+    pub fn side_total(&self, side: BetSide) -> u64 {
+        match side {
+            BetSide::A => self.staked_a,
+            BetSide::B => self.staked_b,
+        }
+    }
+
+    pub fn total_staked(&self) -> Result<u64> {
+        Ok(self
+            .staked_a
+            .checked_add(self.staked_b)
+            .ok_or(ErrorCode::Overflow)?)
+    }
+
+    /// Everything the vault owes out: live stakes plus retained cash-out stake.
+    pub fn pool_total(&self) -> Result<u64> {
+        Ok(self
+            .total_staked()?
+            .checked_add(self.retained)
+            .ok_or(ErrorCode::Overflow)?)
+    }
+
+    pub fn fee_amount(&self) -> Result<u64> {
+        math::bps_of(self.pool_total()?, self.fee_bps)
+    }
+
+    pub fn insurance_amount(&self) -> Result<u64> {
+        math::bps_of(self.pool_total()?, self.insurance_bps)
+    }
+
+    pub fn charity_amount(&self) -> Result<u64> {
+        math::bps_of(self.pool_total()?, self.charity_bps)
+    }
+
+    pub fn distributable(&self) -> Result<u64> {
+        Ok(self
+            .pool_total()?
+            .checked_sub(self.fee_amount()?)
+            .and_then(|rest| rest.checked_sub(self.insurance_amount().ok()?))
+            .and_then(|rest| rest.checked_sub(self.charity_amount().ok()?))
+            .ok_or(ErrorCode::Underflow)?)
+    }
+
+    pub fn options(&self) -> MarketOptions {
+        MarketOptions {
+            allow_cash_out: self.allow_cash_out,
+            cashout_haircut_bps: self.cashout_haircut_bps,
+            lock_odds: self.lock_odds,
+            burn_fee: self.burn_fee,
+        }
+    }
+
+    /// Fee owed to the creator, less what goes back to stakers.
+    pub fn creator_fee_due(&self) -> Result<u64> {
+        Ok(self
+            .fee_amount()?
+            .checked_sub(self.staker_rebates()?)
+            .ok_or(ErrorCode::Underflow)?)
+    }
+
+    /// What each fee split is paid out of the creator's fee, in the order of
+    /// `fee_splits`, and what's left for the creator.
+    pub fn split_fee(&self) -> Result<(Vec<u64>, u64)> {
+        let fee = self.creator_fee_due()?;
+        let mut rest = fee;
+        let mut amounts = Vec::with_capacity(self.fee_splits.len());
+        for split in &self.fee_splits {
+            let amount = math::bps_of(fee, split.bps)?;
+            rest = rest.checked_sub(amount).ok_or(ErrorCode::Underflow)?;
+            amounts.push(amount);
+        }
+        Ok((amounts, rest))
+    }
+
+    /// Part of the fee going back to stakers, set by the discounts their
+    /// bets were placed at. Nothing is rebated on cancelled markets.
+    pub fn staker_rebates(&self) -> Result<u64> {
+        if self.status != MarketStatus::Resolved {
+            return Ok(0);
+        }
+        math::pro_rata(
+            self.fee_amount()?,
+            self.discounted_stake,
+            self.total_staked()?,
+        )
+    }
+
+    /// Part of the fee `position` gets back, from the discounts its bets
+    /// were placed at. Rounds down, so the rebates never add up to more
+    /// than [`Market::staker_rebates`].
+    pub fn fee_rebate_for(&self, position: &Position) -> Result<u64> {
+        if self.status != MarketStatus::Resolved {
+            return Ok(0);
+        }
+        math::pro_rata(
+            self.fee_amount()?,
+            position.discounted_stake,
+            self.total_staked()?,
+        )
+    }
+
+    /// What claiming `position` pays: its payout plus its rebate.
+    pub fn claim_total(&self, position: &Position) -> Result<u64> {
+        Ok(self
+            .payout_for(position)?
+            .checked_add(self.fee_rebate_for(position)?)
+            .ok_or(ErrorCode::Overflow)?)
+    }
+
+    /// [`Market::claim_total`] and the rebate in it for claiming
+    /// `position`, adding the rebate to `fee_discounts`.
+    pub fn settle_claim(&mut self, position: &Position) -> Result<(u64, u64)> {
+        let rebate = self.fee_rebate_for(position)?;
+        let total = self.claim_total(position)?;
+        self.fee_discounts = self
+            .fee_discounts
+            .checked_add(rebate)
+            .ok_or(ErrorCode::Overflow)?;
+        Ok((total, rebate))
+    }
+
+    /// Drops a position that is being claimed or emptied from `open_positions`.
+    pub fn release_position(&mut self, position: &Position) -> Result<()> {
+        if position.amount > 0 {
+            self.open_positions = self
+                .open_positions
+                .checked_sub(1)
+                .ok_or(ErrorCode::Underflow)?;
+        }
+        Ok(())
+    }
+
+    /// Drops a scheduled bet that was placed or cancelled from `scheduled_bets`.
+    pub fn release_scheduled_bet(&mut self) -> Result<()> {
+        self.scheduled_bets = self
+            .scheduled_bets
+            .checked_sub(1)
+            .ok_or(ErrorCode::Underflow)?;
+        Ok(())
+    }
+
+    /// What cashing out `position` would pay right now: its stake less its
+    /// part of the pool's cuts and the haircut. Stake retained from earlier
+    /// cash-outs isn't shared with those leaving, only with those who stay.
+    pub fn cash_out_value(&self, position: &Position) -> Result<u64> {
+        math::cash_out_value(
+            self.distributable()?,
+            position.amount,
+            self.pool_total()?,
+            self.cashout_haircut_bps,
+        )
+    }
+
+    /// What `position` would be paid if its side won with the pools as
+    /// they stand, which cash-out orders trigger on.
+    pub fn payout_if_won(&self, position: &Position) -> Result<u64> {
+        let distributable = self.distributable()?;
+        if !self.lock_odds {
+            return math::pro_rata(
+                distributable,
+                position.amount,
+                self.side_total(position.side),
+            );
+        }
+        let locked_total = self.locked_payout_total(position.side);
+        if locked_total <= distributable {
+            Ok(position.locked_payout)
+        } else {
+            math::pro_rata(distributable, position.locked_payout, locked_total)
+        }
+    }
+
+    /// Takes `position`'s stake out of the pools and empties it, keeping
+    /// what isn't paid out in the pool for the remaining bettors. Returns
+    /// the stake and the payout.
+    pub fn settle_cash_out(&mut self, position: &mut Position) -> Result<(u64, u64)> {
+        let stake = position.amount;
+        let payout = self.cash_out_value(position)?;
+
+        match position.side {
+            BetSide::A => {
+                self.staked_a = self
+                    .staked_a
+                    .checked_sub(stake)
+                    .ok_or(ErrorCode::Underflow)?;
+                self.locked_payout_a = self
+                    .locked_payout_a
+                    .checked_sub(position.locked_payout)
+                    .ok_or(ErrorCode::Underflow)?;
+            }
+            BetSide::B => {
+                self.staked_b = self
+                    .staked_b
+                    .checked_sub(stake)
+                    .ok_or(ErrorCode::Underflow)?;
+                self.locked_payout_b = self
+                    .locked_payout_b
+                    .checked_sub(position.locked_payout)
+                    .ok_or(ErrorCode::Underflow)?;
+            }
+        }
+        let kept = stake.checked_sub(payout).ok_or(ErrorCode::Underflow)?;
+        self.retained = self.retained.checked_add(kept).ok_or(ErrorCode::Overflow)?;
+        self.discounted_stake = self
+            .discounted_stake
+            .checked_sub(position.discounted_stake)
+            .ok_or(ErrorCode::Underflow)?;
+        self.release_position(position)?;
+        position.amount = 0;
+        position.locked_payout = 0;
+        position.discounted_stake = 0;
+        position.cashout_min_value = 0;
+
+        Ok((stake, payout))
+    }
+
+    /// Adds a deposit of `amount` on `side` to the pools and to `position`,
+    /// locking in its payout on `lock_odds` markets and its share of the fee
+    /// rebate at `discount_bps`.
+    pub fn apply_bet(
+        &mut self,
+        position: &mut Position,
+        user: Pubkey,
+        side: BetSide,
+        amount: u64,
+        discount_bps: u16,
+    ) -> Result<BetQuote> {
+        // Adding to a position on the other side would move its whole stake
+        // there, claiming against a pool it never joined
+        require!(
+            position.amount == 0 || position.side == side,
+            ErrorCode::PositionSideMismatch
+        );
+        if position.amount == 0 {
+            self.open_positions = self
+                .open_positions
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+
+        // Update market stakes
+        match side {
+            BetSide::A => {
+                self.staked_a = self
+                    .staked_a
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::Overflow)?
+            }
+            BetSide::B => {
+                self.staked_b = self
+                    .staked_b
+                    .checked_add(amount)
+                    .ok_or(ErrorCode::Overflow)?
+            }
+        }
+
+        // What this deposit would win if the pool closed right now
+        let implied_payout = math::pro_rata(self.distributable()?, amount, self.side_total(side))?;
+        let odds_bps = math::pro_rata(implied_payout, math::BPS_DENOMINATOR, amount)?;
+
+        let locked_payout = if self.lock_odds {
+            match side {
+                BetSide::A => {
+                    self.locked_payout_a = self
+                        .locked_payout_a
+                        .checked_add(implied_payout)
+                        .ok_or(ErrorCode::Overflow)?
+                }
+                BetSide::B => {
+                    self.locked_payout_b = self
+                        .locked_payout_b
+                        .checked_add(implied_payout)
+                        .ok_or(ErrorCode::Overflow)?
+                }
+            }
+            implied_payout
+        } else {
+            0
+        };
+
+        // Update position
+        position.owner = user;
+        position.side = side;
+        position.amount = position
+            .amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        position.locked_payout = position
+            .locked_payout
+            .checked_add(locked_payout)
+            .ok_or(ErrorCode::Overflow)?;
+        let discounted = math::bps_of(amount, discount_bps)?;
+        position.discounted_stake = position
+            .discounted_stake
+            .checked_add(discounted)
+            .ok_or(ErrorCode::Overflow)?;
+        self.discounted_stake = self
+            .discounted_stake
+            .checked_add(discounted)
+            .ok_or(ErrorCode::Overflow)?;
+        position.claimed = false;
+
+        Ok(BetQuote {
+            implied_payout,
+            odds_bps,
+        })
+    }
+
+    pub fn locked_payout_total(&self, side: BetSide) -> u64 {
+        match side {
+            BetSide::A => self.locked_payout_a,
+            BetSide::B => self.locked_payout_b,
+        }
+    }
+
+    /// Amount `position` is owed once the market is resolved or cancelled.
+    pub fn payout_for(&self, position: &Position) -> Result<u64> {
+        if self.status == MarketStatus::Cancelled {
+            // Refund the stake less what insurance already paid towards it,
+            // and share what cash-outs left behind between the bettors who
+            // stayed in
+            let retained = math::pro_rata(self.retained, position.amount, self.total_staked()?)?;
+            return Ok(position
+                .amount
+                .checked_sub(position.compensated)
+                .ok_or(ErrorCode::Underflow)?
+                .checked_add(retained)
+                .ok_or(ErrorCode::Overflow)?);
+        }
+
+        let outcome = self.outcome.ok_or(ErrorCode::MarketNotFinalized)?;
+        let distributable = self.distributable()?;
+
+        if !self.lock_odds {
+            return if position.side != outcome {
+                Ok(0) // Lost bet
+            } else {
+                // Won bet - calculate pro-rata share
+                math::pro_rata(distributable, position.amount, self.side_total(outcome))
+            };
+        }
+
+        // Locked odds: winners get their locked payouts, scaled down if the
+        // pool can't cover them all; any surplus goes back to the losing side.
+        let locked_total = self.locked_payout_total(outcome);
+        if position.side == outcome {
+            if locked_total <= distributable {
+                Ok(position.locked_payout)
+            } else {
+                math::pro_rata(distributable, position.locked_payout, locked_total)
+            }
+        } else {
+            let surplus = distributable.saturating_sub(locked_total);
+            math::pro_rata(surplus, position.amount, self.side_total(outcome.other()))
+        }
+    }
+}
+
+/// Terms a deposit got when it was placed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BetQuote {
+    /// What the deposit would win if the pool closed right after it.
+    pub implied_payout: u64,
+    /// `implied_payout` per unit staked, in basis points.
+    pub odds_bps: u64,
+}
+
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub stake_mint: Pubkey,
+    pub stake_vault: Pubkey,
+    pub unbonding_secs: i64,
+    /// Ascending by `min_stake`; the highest tier reached applies.
+    pub fee_tiers: Vec<FeeTier>,
+    pub bump: u8,
+    pub stake_vault_bump: u8,
+    pub policy: MarketPolicy,
+    /// How long a proposed update waits before it can be applied.
+    pub update_delay_secs: i64,
+    pub pending_update: Option<ConfigUpdate>,
+    /// When `pending_update` can be applied.
+    pub update_ready_ts: i64,
+    /// Privileged actions taken so far, numbered from 0.
+    pub admin_actions: u64,
+    /// Hash of the admin log up to the latest action, see [`admin_log`].
+    pub admin_log_hash: [u8; 32],
+}
+
+impl Config {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // admin
+        32 + // stake_mint
+        32 + // stake_vault
+        8 + // unbonding_secs
+        4 + MAX_FEE_TIERS * FeeTier::LEN + // fee_tiers
+        1 + // bump
+        1 + // stake_vault_bump
+        MarketPolicy::LEN + // policy
+        8 + // update_delay_secs
+        1 + ConfigUpdate::LEN + // pending_update
+        8 + // update_ready_ts
+        8 + // admin_actions
+        32; // admin_log_hash
+
+    /// The settings an update could change, as they stand.
+    pub fn settings(&self) -> ConfigUpdate {
+        ConfigUpdate {
+            admin: self.admin,
+            unbonding_secs: self.unbonding_secs,
+            fee_tiers: self.fee_tiers.clone(),
+            policy: self.policy,
+            update_delay_secs: self.update_delay_secs,
+        }
+    }
+
+    /// Appends `action`, taken by the current admin, to the admin log and
+    /// returns the event recording it.
+    pub fn log_admin_action(&mut self, action: AdminAction) -> Result<AdminActionLogged> {
+        let admin = self.admin;
+        let sequence = self.admin_actions;
+        let timestamp = Clock::get()?.unix_timestamp;
+        self.admin_log_hash =
+            admin_log::admin_log_hash(&self.admin_log_hash, sequence, &admin, timestamp, &action)?;
+        self.admin_actions = sequence.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        Ok(AdminActionLogged {
+            sequence,
+            admin,
+            timestamp,
+            action,
+            log_hash: self.admin_log_hash,
+        })
+    }
+
+    fn tier_for(&self, staked: u64) -> Option<&FeeTier> {
+        self.fee_tiers
+            .iter()
+            .rev()
+            .find(|tier| staked >= tier.min_stake)
+    }
+
+    pub fn discount_bps_for(&self, staked: u64) -> u16 {
+        self.tier_for(staked).map_or(0, |tier| tier.discount_bps)
+    }
+
+    /// The market policy for a creator with `staked`, with the higher of
+    /// its fee ceiling and their fee tier's.
+    pub fn policy_for(&self, staked: u64) -> MarketPolicy {
+        let mut policy = self.policy;
+        if let Some(tier) = self.tier_for(staked) {
+            policy.max_fee_bps = policy.max_fee_bps.max(tier.max_fee_bps);
+        }
+        policy
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeTier {
+    pub min_stake: u64,
+    pub discount_bps: u16,
+    /// Fee ceiling for markets created by stakers in this tier, where it's
+    /// above the policy's.
+    pub max_fee_bps: u16,
+}
+
+impl FeeTier {
+    pub const LEN: usize = 8 + 2 + 2;
+}
+
+/// Everything `propose_config_update` can change.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConfigUpdate {
+    pub admin: Pubkey,
+    pub unbonding_secs: i64,
+    pub fee_tiers: Vec<FeeTier>,
+    pub policy: MarketPolicy,
+    pub update_delay_secs: i64,
+}
+
+impl ConfigUpdate {
+    pub const LEN: usize = 32 + 8 + 4 + MAX_FEE_TIERS * FeeTier::LEN + MarketPolicy::LEN + 8;
+}
+
+/// Defaults and ceilings `initialize_market` applies, set by the config
+/// admin.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarketPolicy {
+    /// Fee of markets created with `fee_bps` of [`DEFAULT_FEE`].
+    pub default_fee_bps: u16,
+    pub max_fee_bps: u16,
+    /// Betting window of markets created with an `end_ts` of 0.
+    pub default_betting_secs: i64,
+    /// Resolve window of markets created with a `resolve_deadline_ts` of 0.
+    pub default_resolve_secs: i64,
+    /// In bytes; at most the 64 a market account has room for.
+    pub max_title_len: u16,
+    /// Cut of every market's pool set aside for insurance, up to 1%.
+    pub insurance_bps: u16,
+}
+
+impl MarketPolicy {
+    pub const LEN: usize = 2 + 2 + 8 + 8 + 2 + 2;
+}
+
+impl Default for MarketPolicy {
+    /// The limits markets had before the policy was configurable, with a
+    /// week each to bet and to resolve.
+    fn default() -> Self {
+        Self {
+            default_fee_bps: 0,
+            max_fee_bps: MAX_FEE_BPS,
+            default_betting_secs: 7 * 24 * 60 * 60,
+            default_resolve_secs: 7 * 24 * 60 * 60,
+            max_title_len: MAX_TITLE_LEN as u16,
+            insurance_bps: 0,
+        }
+    }
+}
+
+#[account]
+pub struct StakeAccount {
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub unbonding_amount: u64,
+    pub unbonding_end_ts: i64,
+    pub bump: u8,
+}
+
+impl StakeAccount {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        8 + // amount
+        8 + // unbonding_amount
+        8 + // unbonding_end_ts
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarketDependency {
+    pub market: Pubkey,
+    pub outcome: BetSide,
+}
 
-    #[account(
-        mut,
-        constraint = vault.key() == market.vault
-    )]
-    pub vault: Account<'info, TokenAccount>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchBet {
+    pub market: Pubkey,
+    pub side: BetSide,
+    pub amount: u64,
+    pub min_odds_bps: u64,
+}
 
-    pub token_program: Program<'info, Token>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarketOptions {
+    pub allow_cash_out: bool,
+    pub cashout_haircut_bps: u16,
+    pub lock_odds: bool,
+    pub burn_fee: bool,
 }
 
-#[account]
-pub struct Market {
-    pub market_id: u64,
-    pub creator: Pubkey,
-    pub mint: Pubkey,
-    pub vault: Pubkey,
-    pub fee_bps: u16,
-    pub end_ts: i64,
-    pub resolve_deadline_ts: i64,
-    pub staked_a: u64,
-    pub staked_b: u64,
-    pub status: MarketStatus,
-    pub outcome: Option<BetSide>,
-    pub creator_fee_withdrawn: bool,
-    pub bump: u8,
-    pub vault_bump: u8,
-    pub title: String,
+/// A recipient's share of a market's fee.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeSplit {
+    pub recipient: Pubkey,
+    pub bps: u16,
 }
 
-impl Market {
-    const LEN: usize = 8 + // discriminator
-        8 + // market_id
-        32 + // creator
-        32 + // mint
-        32 + // vault
-        2 + // fee_bps
-        8 + // end_ts
-        8 + // resolve_deadline_ts
-        8 + // staked_a
-        8 + // staked_b
-        1 + // status
-        1 + 1 + // outcome (Option<BetSide>)
-        1 + // creator_fee_withdrawn
-        1 + // bump
-        1 + // vault_bump
-        4 + MAX_TITLE_LEN; // title
+impl FeeSplit {
+    pub const LEN: usize = 32 + 2;
 }
 
 #[account]
@@ -537,18 +5256,62 @@ pub struct Position {
     pub amount: u64,
     pub claimed: bool,
     pub bump: u8,
+    /// Payout locked in across deposits on a `lock_odds` market.
+    pub locked_payout: u64,
+    /// Stake placed at a staker's fee discount, each deposit weighted by
+    /// the discount its bettor had then; the position's rebate is this
+    /// stake's pro-rata share of the fee.
+    pub discounted_stake: u64,
+    /// Standing cash-out order: anyone may cash the position out once what
+    /// it would win falls to this. Zero when there is none.
+    pub cashout_min_value: u64,
+    /// Paid from insurance by `compensate` in place of a refund the vault
+    /// couldn't cover, and deducted from the claim.
+    pub compensated: u64,
 }
 
 impl Position {
-    const LEN: usize = 8 + // discriminator
+    pub const LEN: usize = 8 + // discriminator
         32 + // owner
         1 + // side
         8 + // amount
         1 + // claimed
-        1; // bump
+        1 + // bump
+        8 + // locked_payout
+        8 + // discounted_stake
+        8 + // cashout_min_value
+        8; // compensated
+}
+
+/// A bet recorded in a bettor page.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BettorEntry {
+    pub owner: Pubkey,
+    pub side: BetSide,
+    pub amount: u64,
+}
+
+/// Up to [`BETTOR_PAGE_SIZE`] bets on a market that tracks bettors, in the
+/// order they were placed. Page `index` holds bets `index * BETTOR_PAGE_SIZE`
+/// onwards; a bettor who bets again appears again.
+#[account]
+pub struct BettorPage {
+    pub market: Pubkey,
+    pub index: u32,
+    /// Paid the page's rent, and gets it back when it's closed.
+    pub payer: Pubkey,
+    pub bettors: Vec<BettorEntry>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+impl BettorPage {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // market
+        4 + // index
+        32 + // payer
+        4 + BETTOR_PAGE_SIZE * (32 + 1 + 8); // bettors
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MarketStatus {
     Open,
     PendingResolve,
@@ -556,14 +5319,24 @@ pub enum MarketStatus {
     Cancelled,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BetSide {
     A,
     B,
 }
 
+impl BetSide {
+    pub fn other(self) -> Self {
+        match self {
+            Self::A => Self::B,
+            Self::B => Self::A,
+        }
+    }
+}
+
 // Events
 #[event]
+#[derive(Clone, Debug)]
 pub struct MarketInitialized {
     pub market: Pubkey,
     pub creator: Pubkey,
@@ -574,6 +5347,29 @@ pub struct MarketInitialized {
 }
 
 #[event]
+#[derive(Clone, Debug)]
+pub struct MarketCloned {
+    pub market: Pubkey,
+    pub source: Pubkey,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct MarketForked {
+    pub market: Pubkey,
+    pub original: Pubkey,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct DependencySet {
+    pub market: Pubkey,
+    pub parent: Pubkey,
+    pub outcome: BetSide,
+}
+
+#[event]
+#[derive(Clone, Debug)]
 pub struct BetPlaced {
     pub market: Pubkey,
     pub user: Pubkey,
@@ -581,23 +5377,112 @@ pub struct BetPlaced {
     pub amount: u64,
 }
 
+/// A bet of at least its market's `large_bet_threshold`, with the pools
+/// it left behind.
+#[event]
+#[derive(Clone, Debug)]
+pub struct LargeBetPlaced {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub side: BetSide,
+    pub amount: u64,
+    pub staked_a: u64,
+    pub staked_b: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct LargeBetThresholdSet {
+    pub market: Pubkey,
+    /// 0 when large bets are no longer reported.
+    pub threshold: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct BetReceipt {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub side: BetSide,
+    pub amount: u64,
+    /// Locked payout per unit staked, in basis points (15_000 = 1.5x).
+    pub odds_bps: u64,
+    pub locked_payout: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct CashedOut {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub side: BetSide,
+    pub stake: u64,
+    pub payout: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct CashOutOrderSet {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    /// Zero when the order was cleared.
+    pub min_value: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct CashOutOrderExecuted {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub executor: Pubkey,
+    pub min_value: u64,
+    /// What the owner got, net of the tip.
+    pub payout: u64,
+    pub tip: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct PortfolioOpened {
+    pub owner: Pubkey,
+    pub portfolio: Pubkey,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct PortfolioPageClosed {
+    pub owner: Pubkey,
+    pub index: u32,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct PortfolioClosed {
+    pub owner: Pubkey,
+    pub portfolio: Pubkey,
+}
+
 #[event]
+#[derive(Clone, Debug)]
 pub struct BettingClosed {
     pub market: Pubkey,
 }
 
 #[event]
+#[derive(Clone, Debug)]
 pub struct Resolved {
     pub market: Pubkey,
     pub outcome: BetSide,
 }
 
 #[event]
+#[derive(Clone, Debug)]
 pub struct Cancelled {
     pub market: Pubkey,
 }
 
 #[event]
+#[derive(Clone, Debug)]
 pub struct Claimed {
     pub market: Pubkey,
     pub user: Pubkey,
@@ -605,15 +5490,282 @@ pub struct Claimed {
 }
 
 #[event]
+#[derive(Clone, Debug)]
+pub struct FeeSplitsSet {
+    pub market: Pubkey,
+    pub splits: Vec<FeeSplit>,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct FeeSplitPaid {
+    pub market: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
 pub struct CreatorFeeWithdrawn {
     pub market: Pubkey,
     pub creator: Pubkey,
     pub amount: u64,
 }
 
+#[event]
+#[derive(Clone, Debug)]
+pub struct FeeBurned {
+    pub market: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct CharitySet {
+    pub market: Pubkey,
+    pub charity: Option<Pubkey>,
+    pub charity_bps: u16,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct CharityDonated {
+    pub market: Pubkey,
+    /// The charity's token account.
+    pub charity: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct InsuranceCollected {
+    pub market: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct Compensated {
+    pub market: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct FeeRebated {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub discounted_stake: u64,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct ConfigUpdated {
+    pub admin: Pubkey,
+    pub unbonding_secs: i64,
+    pub fee_tiers: Vec<FeeTier>,
+    pub policy: MarketPolicy,
+    pub update_delay_secs: i64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct ConfigUpdateProposed {
+    pub admin: Pubkey,
+    pub update: ConfigUpdate,
+    pub ready_ts: i64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct ConfigUpdateCancelled {
+    pub admin: Pubkey,
+}
+
+/// Entry `sequence` of the admin log.
+#[event]
+#[derive(Clone, Debug)]
+pub struct AdminActionLogged {
+    pub sequence: u64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+    pub action: AdminAction,
+    /// The config's `admin_log_hash` after this entry.
+    pub log_hash: [u8; 32],
+}
+
+impl AdminActionLogged {
+    /// Whether this entry follows a log whose hash was `previous`.
+    pub fn follows(&self, previous: &[u8; 32]) -> Result<bool> {
+        let hash = admin_log::admin_log_hash(
+            previous,
+            self.sequence,
+            &self.admin,
+            self.timestamp,
+            &self.action,
+        )?;
+        Ok(hash == self.log_hash)
+    }
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct Staked {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub total: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct UnstakeRequested {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub unbonding_end_ts: i64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct StakeWithdrawn {
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct PositionsCompressed {
+    pub market: Pubkey,
+    pub position_tree: Pubkey,
+}
+
+/// A bet on a compressed market, with everything needed to rebuild its leaf.
+#[event]
+#[derive(Clone, Debug)]
+pub struct CompressedBetPlaced {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub side: BetSide,
+    pub amount: u64,
+    pub locked_payout: u64,
+    pub discounted_stake: u64,
+    pub leaf_index: u32,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct CompressedPositionClaimed {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub leaf_index: u32,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct MarketCompacted {
+    pub market: Pubkey,
+    /// Lamports returned to the creator.
+    pub refund: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct BettorPagesEnabled {
+    pub market: Pubkey,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct BettorPageClosed {
+    pub market: Pubkey,
+    pub index: u32,
+    pub payer: Pubkey,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct StakeCapsSet {
+    pub market: Pubkey,
+    pub cap_a: u64,
+    pub cap_b: u64,
+    pub max_ratio_bps: u32,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct MarketTitleRegistered {
+    pub market: Pubkey,
+    pub title_hash: [u8; 32],
+    pub nonce: u16,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct MarketTitleClosed {
+    pub market: Pubkey,
+    pub title_hash: [u8; 32],
+    pub nonce: u16,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct BetScheduled {
+    pub market: Pubkey,
+    pub scheduled_bet: Pubkey,
+    pub owner: Pubkey,
+    pub side: BetSide,
+    pub amount: u64,
+    pub execute_after: i64,
+    pub expiry: i64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct ScheduledBetExecuted {
+    pub market: Pubkey,
+    pub scheduled_bet: Pubkey,
+    pub owner: Pubkey,
+    pub executor: Pubkey,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct ScheduledBetCancelled {
+    pub market: Pubkey,
+    pub scheduled_bet: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct ActivityStatsEnabled {
+    pub market: Pubkey,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct ActivityStatsClosed {
+    pub market: Pubkey,
+    pub payer: Pubkey,
+}
+
+#[event]
+#[derive(Clone, Debug)]
+pub struct MarketCollected {
+    pub market: Pubkey,
+    pub caller: Pubkey,
+    pub positions_closed: u32,
+    pub caller_share: u64,
+    pub creator_share: u64,
+}
+
 #[error_code]
 pub enum ErrorCode {
-    #[msg("Fee too high (max 20%)")]
+    #[msg("Fee above the config's ceiling")]
     FeeTooHigh,
     #[msg("Title too long")]
     TitleTooLong,
@@ -655,4 +5807,132 @@ pub enum ErrorCode {
     FeeAlreadyWithdrawn,
     #[msg("Invalid market PDA")]
     InvalidMarketPda,
+    #[msg("Cash-out haircut too high (max 50%)")]
+    HaircutTooHigh,
+    #[msg("Cash-out is not enabled for this market")]
+    CashOutDisabled,
+    #[msg("Nothing to cash out")]
+    NothingToCashOut,
+    #[msg("Odds moved below the requested minimum")]
+    OddsBelowMinimum,
+    #[msg("Remaining accounts must be non-empty groups of the expected size")]
+    InvalidRemainingAccounts,
+    #[msg("Position does not belong to this market")]
+    InvalidPosition,
+    #[msg("Recipient is not the position owner's associated token account")]
+    InvalidRecipient,
+    #[msg("Vault does not belong to this market")]
+    InvalidVault,
+    #[msg("Token account mint does not match the market")]
+    InvalidMint,
+    #[msg("Market is already resolved or cancelled")]
+    MarketAlreadyFinalized,
+    #[msg("Market already has stakes")]
+    MarketHasStakes,
+    #[msg("Invalid parent market")]
+    InvalidDependency,
+    #[msg("Parent market has not resolved to the required outcome")]
+    DependencyNotMet,
+    #[msg("Parent market has not failed the dependency")]
+    DependencyNotFailed,
+    #[msg("Claim window has not ended")]
+    ClaimWindowOpen,
+    #[msg("Market still owes payouts or fees")]
+    OutstandingBalance,
+    #[msg("Market fee is burned, not withdrawn")]
+    FeeIsBurned,
+    #[msg("Market fee is not set to be burned")]
+    FeeNotBurned,
+    #[msg("Fee tiers must be ascending, at most 4, with discounts up to 100% and fee ceilings up to 20%")]
+    InvalidFeeTiers,
+    #[msg("Unbonding period cannot be negative")]
+    InvalidUnbondingPeriod,
+    #[msg("Unauthorized admin")]
+    UnauthorizedAdmin,
+    #[msg("Invalid stake account")]
+    InvalidStakeAccount,
+    #[msg("Not enough stake")]
+    InsufficientStake,
+    #[msg("Stake is still unbonding")]
+    StillUnbonding,
+    #[msg("Nothing to withdraw")]
+    NothingToWithdraw,
+    #[msg("Market takes compressed bets only")]
+    MarketCompressed,
+    #[msg("Market does not have compressed positions")]
+    MarketNotCompressed,
+    #[msg("Compressed positions don't support cash-outs, bettor pages or activity stats")]
+    CompressionUnsupported,
+    #[msg("Position tree is full")]
+    PositionTreeFull,
+    #[msg("Position is not in the tree or was already claimed")]
+    InvalidPositionProof,
+    #[msg("Expected the market's current bettor page")]
+    InvalidBettorPage,
+    #[msg("Bettor pages aren't supported on compressed markets or in batches")]
+    BettorPagesUnsupported,
+    #[msg("Market still has bettor pages to close")]
+    BettorPagesOpen,
+    #[msg("Market is already compact")]
+    MarketAlreadyCompact,
+    #[msg("Market policy defaults must be within its ceilings, with positive windows and insurance up to 1%")]
+    InvalidMarketPolicy,
+    #[msg("Insurance already collected")]
+    InsuranceAlreadyCollected,
+    #[msg("Market has not been cancelled")]
+    MarketNotCancelled,
+    #[msg("Market vaults hold less than the payout")]
+    InsufficientVaultBalance,
+    #[msg("Config update delay cannot be negative")]
+    InvalidUpdateDelay,
+    #[msg("No config update is pending")]
+    NoPendingConfigUpdate,
+    #[msg("Config update delay has not passed")]
+    ConfigUpdateNotReady,
+    #[msg("Fee splits must be at most 3, each above 0 and together at most 100%")]
+    InvalidFeeSplits,
+    #[msg("Fee is split; pay it out with distribute_fees")]
+    FeeIsSplit,
+    #[msg("Charity needs a token account and a cut above 0 that leaves room for the fee and insurance")]
+    InvalidCharity,
+    #[msg("Charity already paid")]
+    CharityAlreadyPaid,
+    #[msg("Expected the market's activity account")]
+    InvalidActivityStats,
+    #[msg("Activity stats aren't supported on compressed markets or in batches")]
+    ActivityStatsUnsupported,
+    #[msg("Market still has an activity account to close")]
+    ActivityStatsOpen,
+    #[msg("Stake ratio limit must be 0 or at least 10000 bps")]
+    InvalidStakeCaps,
+    #[msg("Bet would take its side past the market's stake cap")]
+    StakeCapExceeded,
+    #[msg("Title registration must match the market's title, once per market")]
+    InvalidMarketTitle,
+    #[msg("Market still has a title registration to close")]
+    MarketTitleRegistered,
+    #[msg("Scheduled bets need an expiry in the future, after execute_after")]
+    InvalidSchedule,
+    #[msg("Scheduled bet is not due")]
+    ScheduledBetNotDue,
+    #[msg("Only the owner can cancel a scheduled bet that can still be placed")]
+    ScheduledBetActive,
+    #[msg("Position has no cash-out order")]
+    NoCashOutOrder,
+    #[msg("Position would still win more than the order's minimum")]
+    CashOutOrderNotTriggered,
+    #[msg("Portfolio page is not the portfolio's current page")]
+    InvalidPortfolioPage,
+    #[msg("Only full portfolio pages can be closed")]
+    PortfolioPageInUse,
+    #[msg("Portfolio still has pages to close")]
+    PortfolioPagesOpen,
+    #[msg("Position is on the other side; bet from another wallet or cash out first")]
+    PositionSideMismatch,
+    #[msg("Stakes moved this slot; the cash-out order can be filled from the next")]
+    CashOutOrderTooSoon,
+    #[msg("Market still has scheduled bets to place or cancel")]
+    ScheduledBetsPending,
+    #[msg("Compensation would exceed the position's unrefunded stake")]
+    CompensationTooHigh,
 }
@@ -0,0 +1,95 @@
+//! Fixed-point helpers for fees and payouts. Any change here has to keep
+//! the properties in `tests/payouts.rs` passing.
+
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Portion of `total` at `bps` basis points, rounded down.
+pub fn bps_of(total: u64, bps: u16) -> Result<u64> {
+    Ok((total as u128)
+        .checked_mul(bps as u128)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_div(BPS_DENOMINATOR as u128)
+        .ok_or(ErrorCode::Overflow)? as u64)
+}
+
+/// Share of `pool` owed to `amount` out of `total`, rounded down.
+/// Returns 0 when `total` is 0.
+pub fn pro_rata(pool: u64, amount: u64, total: u64) -> Result<u64> {
+    if total == 0 {
+        return Ok(0);
+    }
+
+    Ok(((pool as u128)
+        .checked_mul(amount as u128)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_div(total as u128)
+        .ok_or(ErrorCode::Overflow)?) as u64)
+}
+
+/// Value paid for exiting `amount` early: its share by stake of the
+/// distributable part of `pool_total`, less `haircut_bps`. Never more than
+/// `amount`, since the pool's cuts only take away from it.
+pub fn cash_out_value(
+    distributable: u64,
+    amount: u64,
+    pool_total: u64,
+    haircut_bps: u16,
+) -> Result<u64> {
+    let implied = pro_rata(distributable, amount, pool_total)?;
+    let haircut = bps_of(implied, haircut_bps)?;
+    implied
+        .checked_sub(haircut)
+        .ok_or_else(|| ErrorCode::Underflow.into())
+}
+
+/// Parts of `amount` to take from two vaults holding `balances`: the
+/// vault at `first` pays what it can and the other the rest.
+pub fn split_payout(balances: [u64; 2], first: usize, amount: u64) -> Result<[u64; 2]> {
+    let mut parts = [0; 2];
+    parts[first] = amount.min(balances[first]);
+    parts[1 - first] = amount - parts[first];
+    require!(
+        parts[1 - first] <= balances[1 - first],
+        ErrorCode::InsufficientVaultBalance
+    );
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bps_of_rounds_down() {
+        assert_eq!(bps_of(1_000, 250).unwrap(), 25);
+        assert_eq!(bps_of(999, 1).unwrap(), 0);
+        assert_eq!(bps_of(u64::MAX, 10_000).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn pro_rata_rounds_down_and_handles_empty_total() {
+        assert_eq!(pro_rata(950, 100, 400).unwrap(), 237);
+        assert_eq!(pro_rata(950, 300, 400).unwrap(), 712);
+        assert_eq!(pro_rata(950, 100, 0).unwrap(), 0);
+        assert_eq!(pro_rata(u64::MAX, u64::MAX, u64::MAX).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn cash_out_value_applies_haircut() {
+        // 100 of 1_000 staked against 950 distributable is 95, less 10%
+        assert_eq!(cash_out_value(950, 100, 1_000, 1_000).unwrap(), 86);
+        assert_eq!(cash_out_value(950, 100, 1_000, 0).unwrap(), 95);
+    }
+
+    #[test]
+    fn split_payout_drains_first_vault_first() {
+        assert_eq!(split_payout([100, 50], 0, 80).unwrap(), [80, 0]);
+        assert_eq!(split_payout([100, 50], 1, 80).unwrap(), [30, 50]);
+        assert_eq!(split_payout([100, 50], 1, 0).unwrap(), [0, 0]);
+        assert!(split_payout([100, 50], 0, 151).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! Portfolios, an opt-in on-chain index of the markets a wallet has bet on,
+//! so its open and past positions can be listed without knowing every
+//! market address or scanning all positions.
+//!
+//! A wallet opens its `Portfolio` once; from then on `place_bet` and
+//! `place_bet_init_ata` append an entry when they open a position, and
+//! `claim` one when it pays out, as long as the client passes the
+//! portfolio and its current page. Entries are kept in order in
+//! [`PortfolioPage`]s of [`PORTFOLIO_PAGE_SIZE`]: page `index` holds
+//! entries `index * PORTFOLIO_PAGE_SIZE` onwards and is created, paid for
+//! by the owner, when the previous one fills up. A market the owner cashed
+//! out of and bet on again appears again. Full pages can be closed to get
+//! their rent back, and the portfolio with its last page once the others
+//! are gone.
+
+use anchor_lang::prelude::*;
+
+use crate::BetSide;
+
+/// Entries held by one portfolio page.
+pub const PORTFOLIO_PAGE_SIZE: usize = 32;
+
+#[account]
+pub struct Portfolio {
+    pub owner: Pubkey,
+    /// Entries appended since the portfolio was opened.
+    pub entry_count: u32,
+    /// Pages still open.
+    pub pages: u32,
+    pub bump: u8,
+}
+
+impl Portfolio {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        4 + // entry_count
+        4 + // pages
+        1; // bump
+
+    /// Index of the page the next entry goes to.
+    pub fn current_page(&self) -> u32 {
+        self.entry_count / PORTFOLIO_PAGE_SIZE as u32
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortfolioAction {
+    /// Opened a position on the market.
+    Bet,
+    /// Claimed the position's payout or refund.
+    Claim,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PortfolioEntry {
+    pub market: Pubkey,
+    pub action: PortfolioAction,
+    pub side: BetSide,
+    pub timestamp: i64,
+}
+
+impl PortfolioEntry {
+    pub const LEN: usize = 32 + 1 + 1 + 8;
+}
+
+#[account]
+pub struct PortfolioPage {
+    pub owner: Pubkey,
+    pub index: u32,
+    pub entries: Vec<PortfolioEntry>,
+}
+
+impl PortfolioPage {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        4 + // index
+        4 + PORTFOLIO_PAGE_SIZE * PortfolioEntry::LEN; // entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_fill_in_order() {
+        let mut portfolio = Portfolio {
+            owner: Pubkey::new_unique(),
+            entry_count: 0,
+            pages: 0,
+            bump: 0,
+        };
+
+        assert_eq!(portfolio.current_page(), 0);
+        portfolio.entry_count = PORTFOLIO_PAGE_SIZE as u32 - 1;
+        assert_eq!(portfolio.current_page(), 0);
+        portfolio.entry_count = PORTFOLIO_PAGE_SIZE as u32;
+        assert_eq!(portfolio.current_page(), 1);
+    }
+}
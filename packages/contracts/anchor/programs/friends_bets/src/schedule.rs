@@ -0,0 +1,87 @@
+//! Scheduled bets, which a bettor funds now and anyone places for them
+//! later, so a bet can wait for the final hour of a market without its
+//! bettor being online.
+//!
+//! A scheduled bet is a PDA derived from the market, its owner and an id
+//! the owner picks, and holds its stake in an escrow token account it is
+//! the authority of. Once `execute_after` has passed, anyone can place it
+//! until `expiry`; whoever does pays for the owner's position if they have
+//! none yet and gets the rent of the scheduled bet and its escrow, which
+//! the owner paid when scheduling it. The owner can cancel it at any time,
+//! and anyone can once it has expired or the market stopped taking bets,
+//! which returns the stake and rent to the owner.
+//!
+//! The market counts its scheduled bets until each is placed or cancelled,
+//! and `gc_market` won't collect it while any are left, so their escrows
+//! are never stranded behind a closed market.
+
+use anchor_lang::prelude::*;
+
+use crate::BetSide;
+
+#[account]
+pub struct ScheduledBet {
+    pub owner: Pubkey,
+    pub market: Pubkey,
+    pub id: u64,
+    pub side: BetSide,
+    pub amount: u64,
+    /// Passed on to the bet as its slippage bound, checked when it's placed.
+    pub min_odds_bps: u64,
+    pub execute_after: i64,
+    pub expiry: i64,
+    pub bump: u8,
+    pub escrow_bump: u8,
+}
+
+impl ScheduledBet {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // owner
+        32 + // market
+        8 + // id
+        1 + // side
+        8 + // amount
+        8 + // min_odds_bps
+        8 + // execute_after
+        8 + // expiry
+        1 + // bump
+        1; // escrow_bump
+
+    /// Whether the bet can be placed at `now`.
+    pub fn is_due(&self, now: i64) -> bool {
+        self.execute_after <= now && now < self.expiry
+    }
+
+    /// Whether the bet can no longer be placed at `now`.
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.expiry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_runs_from_execute_after_until_expiry() {
+        let scheduled = ScheduledBet {
+            owner: Pubkey::new_unique(),
+            market: Pubkey::new_unique(),
+            id: 0,
+            side: BetSide::A,
+            amount: 100,
+            min_odds_bps: 0,
+            execute_after: 1_000,
+            expiry: 2_000,
+            bump: 0,
+            escrow_bump: 0,
+        };
+
+        assert!(!scheduled.is_due(999));
+        assert!(scheduled.is_due(1_000));
+        assert!(scheduled.is_due(1_999));
+        assert!(!scheduled.is_due(2_000));
+        assert!(!scheduled.is_expired(1_999));
+        assert!(scheduled.is_expired(2_000));
+    }
+}
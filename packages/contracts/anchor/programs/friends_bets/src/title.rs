@@ -0,0 +1,64 @@
+//! Title registrations, which let frontends find the market already taking
+//! bets on a question before opening a near-duplicate.
+//!
+//! A registration is a PDA derived from [`title_hash`] of the market's
+//! normalized title and a nonce, so only one market can hold a title and
+//! nonce at a time, and anyone can look a title up without an indexer.
+//! The nonce lets a question be asked again, like next season's final,
+//! while an earlier market still holds it.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+#[account]
+pub struct MarketTitle {
+    pub market: Pubkey,
+    pub title_hash: [u8; 32],
+    pub nonce: u16,
+}
+
+impl MarketTitle {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // market
+        32 + // title_hash
+        2; // nonce
+}
+
+/// `title` lowercased, with its words joined by single spaces and
+/// punctuation dropped, so titles differing only in case, spacing or
+/// punctuation compare equal.
+pub fn normalize_title(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// SHA-256 of [`normalize_title`], the seed a market's title is registered
+/// under.
+pub fn title_hash(title: &str) -> [u8; 32] {
+    hash(normalize_title(title).as_bytes()).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn near_duplicates_normalize_alike() {
+        assert_eq!(
+            normalize_title("  Will it RAIN on Saturday?! "),
+            "will it rain on saturday"
+        );
+        assert_eq!(
+            title_hash("Will it rain on Saturday?"),
+            title_hash("will it rain on  saturday")
+        );
+        assert_ne!(
+            title_hash("Will it rain on Saturday?"),
+            title_hash("Will it rain on Sunday?")
+        );
+    }
+}
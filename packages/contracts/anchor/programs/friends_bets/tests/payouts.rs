@@ -0,0 +1,339 @@
+//! Properties of the payout math over random stakes, fees and outcomes.
+//!
+//! Whatever the bets, a settled market must never pay out more than its
+//! vault took in: claims, staker rebates and the creator's fee together fit
+//! in the pool. Winners get at least their rounded-down share, and a
+//! cancelled market hands back exactly what was deposited, less what
+//! insurance already paid its bettors. Cashing out early, by the owner or
+//! through an order, keeps all of that true for the bettors who stay in,
+//! and orders trigger on what winning would pay. Any change to `math.rs`
+//! or the payout methods on `Market` should keep these passing.
+
+use anchor_lang::prelude::Pubkey;
+use friends_bets::{BetSide, Market, MarketStatus, Position};
+use proptest::prelude::*;
+
+/// Largest single stake, so sums of many stay far from overflowing.
+const MAX_STAKE: u64 = 1_000_000_000_000_000;
+
+fn market(fee_bps: u16, lock_odds: bool) -> Market {
+    Market {
+        market_id: 1,
+        creator: Pubkey::new_unique(),
+        mint: Pubkey::new_unique(),
+        vault_a: Pubkey::new_unique(),
+        vault_b: Pubkey::new_unique(),
+        fee_bps,
+        end_ts: 0,
+        resolve_deadline_ts: 1,
+        staked_a: 0,
+        staked_b: 0,
+        status: MarketStatus::Open,
+        outcome: None,
+        creator_fee_withdrawn: false,
+        bump: 0,
+        vault_a_bump: 0,
+        vault_b_bump: 0,
+        title: String::new(),
+        allow_cash_out: false,
+        cashout_haircut_bps: 0,
+        retained: 0,
+        lock_odds,
+        locked_payout_a: 0,
+        locked_payout_b: 0,
+        forked_from: None,
+        dependency: None,
+        open_positions: 0,
+        burn_fee: false,
+        fee_discounts: 0,
+        discounted_stake: 0,
+        compressed: false,
+        track_bettors: false,
+        bettor_count: 0,
+        bettor_pages: 0,
+        insurance_bps: 0,
+        insurance_collected: false,
+        compensated: 0,
+        charity: None,
+        charity_bps: 0,
+        charity_paid: false,
+        large_bet_threshold: 0,
+        track_activity: false,
+        cap_a: 0,
+        cap_b: 0,
+        max_ratio_bps: 0,
+        title_registered: false,
+        fee_splits: Vec::new(),
+        pools_changed_slot: 0,
+        scheduled_bets: 0,
+    }
+}
+
+fn side(a: bool) -> BetSide {
+    if a {
+        BetSide::A
+    } else {
+        BetSide::B
+    }
+}
+
+/// Places each bet from its own wallet, in order, as `place_bet` would,
+/// at the fee discount paired with it.
+fn place(market: &mut Market, bets: &[(bool, u64)], discounts: &[u16]) -> Vec<Position> {
+    bets.iter()
+        .zip(discounts.iter().chain(std::iter::repeat(&0)))
+        .map(|(&(a, amount), &discount_bps)| {
+            let owner = Pubkey::new_unique();
+            let mut position = Position {
+                owner,
+                side: side(a),
+                amount: 0,
+                claimed: false,
+                bump: 0,
+                locked_payout: 0,
+                discounted_stake: 0,
+                cashout_min_value: 0,
+                compensated: 0,
+            };
+            market
+                .apply_bet(&mut position, owner, side(a), amount, discount_bps)
+                .expect("stakes fit in u64");
+            position
+        })
+        .collect()
+}
+
+fn bets() -> impl Strategy<Value = Vec<(bool, u64)>> {
+    prop::collection::vec((any::<bool>(), 1..=MAX_STAKE), 1..40)
+}
+
+proptest! {
+    #[test]
+    fn claims_rebates_and_fee_fit_in_the_vault(
+        bets in bets(),
+        fee_bps in 0u16..=2_000,
+        lock_odds in any::<bool>(),
+        outcome_a in any::<bool>(),
+        discounts in prop::collection::vec(
+            prop::sample::select(vec![0u16, 2_500, 5_000, 10_000]),
+            40,
+        ),
+    ) {
+        let mut market = market(fee_bps, lock_odds);
+        let positions = place(&mut market, &bets, &discounts);
+        let vault: u64 = bets.iter().map(|(_, amount)| amount).sum();
+        prop_assert_eq!(market.pool_total().unwrap(), vault);
+
+        market.status = MarketStatus::Resolved;
+        market.outcome = Some(side(outcome_a));
+        // The creator's cut is fixed before anyone claims
+        let fee = market.creator_fee_due().unwrap();
+        // Claim in order, rebating stakers' share of the fee as `claim` does
+        let mut paid = 0u64;
+        for position in &positions {
+            paid += market.settle_claim(position).unwrap().0;
+        }
+        prop_assert!(market.fee_discounts <= market.staker_rebates().unwrap());
+        prop_assert!(
+            paid + fee <= vault,
+            "paid {} plus a fee of {} out of {}", paid, fee, vault
+        );
+    }
+
+    #[test]
+    fn winners_get_at_least_their_share(
+        bets in bets(),
+        fee_bps in 0u16..=2_000,
+        lock_odds in any::<bool>(),
+        outcome_a in any::<bool>(),
+    ) {
+        let mut market = market(fee_bps, lock_odds);
+        let positions = place(&mut market, &bets, &[]);
+        market.status = MarketStatus::Resolved;
+        let outcome = side(outcome_a);
+        market.outcome = Some(outcome);
+
+        let distributable = market.distributable().unwrap();
+        let locked_total = market.locked_payout_total(outcome);
+        let winners: Vec<&Position> = positions.iter().filter(|p| p.side == outcome).collect();
+        let mut paid = 0u64;
+        for position in &winners {
+            let payout = market.payout_for(position).unwrap();
+            // The floor: a pro-rata share of what's distributable, by stake,
+            // or with locked odds, the locked payout when the pool covers
+            // them all and a share by locked payout when it doesn't
+            let (weight, total) = if lock_odds {
+                (position.locked_payout, locked_total)
+            } else {
+                (position.amount, market.side_total(outcome))
+            };
+            let share = (distributable as u128 * weight as u128)
+                .checked_div(total as u128)
+                .unwrap_or_default() as u64;
+            let floor = if lock_odds { share.min(position.locked_payout) } else { share };
+            prop_assert!(payout >= floor, "paid {} under the floor of {}", payout, floor);
+            if lock_odds {
+                prop_assert!(payout <= position.locked_payout);
+            }
+            paid += payout;
+        }
+        // Rounding leaves less than a unit per winner behind
+        if !lock_odds && !winners.is_empty() {
+            prop_assert!(distributable - paid < winners.len() as u64);
+        }
+    }
+
+    #[test]
+    fn cash_outs_and_claims_fit_in_the_vault(
+        bets in bets(),
+        fee_bps in 0u16..=2_000,
+        haircut_bps in 0u16..=5_000,
+        lock_odds in any::<bool>(),
+        outcome_a in any::<bool>(),
+        cash_outs in prop::collection::vec(any::<bool>(), 40),
+    ) {
+        let mut market = market(fee_bps, lock_odds);
+        market.cashout_haircut_bps = haircut_bps;
+        let mut positions = place(&mut market, &bets, &[]);
+        let vault: u64 = bets.iter().map(|(_, amount)| amount).sum();
+
+        let mut paid = 0u64;
+        for (position, cash_out) in positions.iter_mut().zip(&cash_outs) {
+            if *cash_out {
+                let (stake, payout) = market.settle_cash_out(position).unwrap();
+                prop_assert_eq!(position.amount, 0);
+                // What earlier bettors left behind stays with those still in
+                prop_assert!(payout <= stake);
+                paid += payout;
+            }
+        }
+        prop_assert_eq!(market.pool_total().unwrap() + paid, vault);
+
+        market.status = MarketStatus::Resolved;
+        market.outcome = Some(side(outcome_a));
+        let fee = market.creator_fee_due().unwrap();
+        for position in positions.iter().filter(|p| p.amount > 0) {
+            paid += market.settle_claim(position).unwrap().0;
+        }
+        prop_assert!(
+            paid + fee <= vault,
+            "paid {} plus a fee of {} out of {}", paid, fee, vault
+        );
+    }
+
+    #[test]
+    fn cancelling_refunds_exactly_the_deposits(
+        bets in bets(),
+        fee_bps in 0u16..=2_000,
+        lock_odds in any::<bool>(),
+    ) {
+        let mut market = market(fee_bps, lock_odds);
+        let positions = place(&mut market, &bets, &[10_000; 40]);
+        market.status = MarketStatus::Cancelled;
+
+        for (position, (_, amount)) in positions.iter().zip(&bets) {
+            prop_assert_eq!(market.payout_for(position).unwrap(), *amount);
+            prop_assert_eq!(market.fee_rebate_for(position).unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn cancelling_after_cash_outs_refunds_the_vault(
+        bets in bets(),
+        fee_bps in 0u16..=2_000,
+        haircut_bps in 0u16..=5_000,
+        cash_outs in prop::collection::vec(any::<bool>(), 40),
+    ) {
+        let mut market = market(fee_bps, false);
+        market.cashout_haircut_bps = haircut_bps;
+        let mut positions = place(&mut market, &bets, &[]);
+        for (position, cash_out) in positions.iter_mut().zip(&cash_outs) {
+            if *cash_out {
+                market.settle_cash_out(position).unwrap();
+            }
+        }
+        let vault = market.pool_total().unwrap();
+        market.status = MarketStatus::Cancelled;
+
+        // Those still in get their stake back and share the retained stake,
+        // leaving at most a unit of rounding each, or the retained stake
+        // when nobody stayed in
+        let mut refunded = 0u64;
+        let mut staying = 0u64;
+        for position in positions.iter().filter(|p| p.amount > 0) {
+            let refund = market.payout_for(position).unwrap();
+            prop_assert!(refund >= position.amount);
+            refunded += refund;
+            staying += 1;
+        }
+        prop_assert!(refunded <= vault);
+        let left = vault - refunded;
+        if staying > 0 {
+            prop_assert!(left <= staying);
+        } else {
+            prop_assert_eq!(left, market.retained);
+        }
+        prop_assert!(left <= market.unowed_balance().unwrap());
+    }
+
+    #[test]
+    fn compensation_comes_off_the_refund(
+        bets in bets(),
+        fee_bps in 0u16..=2_000,
+        compensated in prop::collection::vec(0u64..=MAX_STAKE, 40),
+    ) {
+        let mut market = market(fee_bps, false);
+        let mut positions = place(&mut market, &bets, &[]);
+        market.status = MarketStatus::Cancelled;
+
+        // Insurance and the vault together refund each bettor exactly once
+        for (position, &compensated) in positions.iter_mut().zip(&compensated) {
+            let refund = market.payout_for(position).unwrap();
+            position.compensated = compensated.min(position.amount);
+            prop_assert_eq!(
+                market.payout_for(position).unwrap() + position.compensated,
+                refund
+            );
+        }
+    }
+
+    #[test]
+    fn orders_trigger_on_what_winning_pays(
+        bets in bets(),
+        fee_bps in 0u16..=2_000,
+        lock_odds in any::<bool>(),
+    ) {
+        let mut market = market(fee_bps, lock_odds);
+        let positions = place(&mut market, &bets, &[]);
+        let if_won: Vec<u64> = positions
+            .iter()
+            .map(|position| market.payout_if_won(position).unwrap())
+            .collect();
+
+        market.status = MarketStatus::Resolved;
+        for (position, if_won) in positions.iter().zip(if_won) {
+            market.outcome = Some(position.side);
+            prop_assert_eq!(market.payout_for(position).unwrap(), if_won);
+        }
+    }
+}
+
+#[test]
+fn positions_keep_their_side() {
+    let mut market = market(200, false);
+    let mut positions = place(&mut market, &[(true, 100), (false, 1)], &[]);
+    let alice = &mut positions[0];
+
+    let flipped = market.apply_bet(alice, alice.owner, BetSide::B, 1, 0);
+    assert_eq!(
+        flipped.unwrap_err(),
+        friends_bets::ErrorCode::PositionSideMismatch.into()
+    );
+    assert_eq!((alice.side, alice.amount), (BetSide::A, 100));
+    assert_eq!((market.staked_a, market.staked_b), (100, 1));
+
+    market
+        .apply_bet(alice, alice.owner, BetSide::A, 1, 0)
+        .unwrap();
+    assert_eq!(alice.amount, 101);
+}
@@ -1,16 +1,268 @@
 import * as anchor from "@coral-xyz/anchor";
-import { Program } from "@coral-xyz/anchor";
-import { Anchor } from "../target/types/anchor";
+import { AnchorError, BN, Program } from "@coral-xyz/anchor";
+import {
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import { FriendsBets } from "../target/types/friends_bets";
 
-describe("anchor", () => {
-  // Configure the client to use the local cluster.
-  anchor.setProvider(anchor.AnchorProvider.env());
+const BPF_LOADER_UPGRADEABLE = new PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111"
+);
 
-  const program = anchor.workspace.anchor as Program<Anchor>;
+const SIDES = [{ a: {} }, { b: {} }];
+const NO_OPTIONS = {
+  allowCashOut: false,
+  cashoutHaircutBps: 0,
+  lockOdds: false,
+  burnFee: false,
+};
+// The config's fee ceiling
+const MAX_FEE_BPS = 2000;
 
-  it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
-    console.log("Your transaction signature", tx);
+describe("friends_bets", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.friendsBets as Program<FriendsBets>;
+  // `anchor test` deploys with the provider's wallet as upgrade authority,
+  // which makes it the only wallet that can create the config
+  const admin = (provider.wallet as anchor.Wallet).payer;
+
+  const pda = (...seeds: Buffer[]) =>
+    PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+  const marketAddress = (creator: PublicKey, marketId: BN) =>
+    pda(
+      Buffer.from("market"),
+      creator.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8)
+    );
+  const vaultAddress = (market: PublicKey, side: number) =>
+    pda(Buffer.from("vault"), market.toBuffer(), Buffer.from([side]));
+  const positionAddress = (market: PublicKey, owner: PublicKey) =>
+    pda(Buffer.from("position"), market.toBuffer(), owner.toBuffer());
+
+  let mint: PublicKey;
+  let nextMarketId = 1;
+
+  async function expectError(transaction: Promise<unknown>, code: string) {
+    try {
+      await transaction;
+    } catch (err) {
+      expect(err).to.be.instanceOf(AnchorError);
+      expect((err as AnchorError).error.errorCode.code).to.equal(code);
+      return;
+    }
+    expect.fail(`expected ${code}`);
+  }
+
+  /** A funded wallet holding `amount` of the test mint. */
+  async function createUser(amount: number) {
+    const user = Keypair.generate();
+    const signature = await provider.connection.requestAirdrop(
+      user.publicKey,
+      LAMPORTS_PER_SOL
+    );
+    await provider.connection.confirmTransaction(signature, "confirmed");
+    const account = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      admin,
+      mint,
+      user.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      admin,
+      mint,
+      account.address,
+      admin,
+      amount
+    );
+    return { user, tokenAccount: account.address };
+  }
+
+  /** Creates a market open for an hour, with a 5% fee by default. */
+  async function createMarket(
+    creator: Keypair,
+    feeBps = 500,
+    options = NO_OPTIONS
+  ) {
+    const marketId = new BN(nextMarketId++);
+    const market = marketAddress(creator.publicKey, marketId);
+    const now = Math.floor(Date.now() / 1000);
+    await program.methods
+      .initializeMarket(
+        marketId,
+        feeBps,
+        new BN(now + 3600),
+        new BN(now + 7200),
+        "Test market",
+        options
+      )
+      .accountsPartial({
+        creator: creator.publicKey,
+        config: pda(Buffer.from("config")),
+        market,
+        mint,
+        vaultA: vaultAddress(market, 0),
+        vaultB: vaultAddress(market, 1),
+      })
+      .signers([creator])
+      .rpc();
+    return market;
+  }
+
+  /** Bets `amount` on side A (0) or B (1). */
+  function placeBet(
+    user: Keypair,
+    tokenAccount: PublicKey,
+    market: PublicKey,
+    side: number,
+    amount: number,
+    minOddsBps = 0
+  ) {
+    return program.methods
+      .placeBet(SIDES[side], new BN(amount), new BN(minOddsBps))
+      .accountsPartial({
+        user: user.publicKey,
+        market,
+        position: positionAddress(market, user.publicKey),
+        userTokenAccount: tokenAccount,
+        vault: vaultAddress(market, side),
+      })
+      .signers([user])
+      .rpc();
+  }
+
+  before(async () => {
+    mint = await createMint(provider.connection, admin, admin.publicKey, null, 6);
+
+    const config = pda(Buffer.from("config"));
+    if (await program.account.config.fetchNullable(config)) {
+      return;
+    }
+    await program.methods
+      .initializeConfig(
+        new BN(0),
+        [],
+        {
+          defaultFeeBps: 0,
+          maxFeeBps: MAX_FEE_BPS,
+          defaultBettingSecs: new BN(7 * 24 * 60 * 60),
+          defaultResolveSecs: new BN(7 * 24 * 60 * 60),
+          maxTitleLen: 64,
+          insuranceBps: 0,
+        },
+        new BN(0)
+      )
+      .accountsPartial({
+        admin: admin.publicKey,
+        config,
+        stakeMint: mint,
+        stakeVault: pda(Buffer.from("stake_vault")),
+        program: program.programId,
+        programData: PublicKey.findProgramAddressSync(
+          [program.programId.toBuffer()],
+          BPF_LOADER_UPGRADEABLE
+        )[0],
+      })
+      .rpc();
+  });
+
+  it("creates markets under the config's policy", async () => {
+    await expectError(createMarket(admin, MAX_FEE_BPS + 1), "FeeTooHigh");
+
+    const options = { ...NO_OPTIONS, allowCashOut: true, cashoutHaircutBps: 300 };
+    const market = await createMarket(admin, MAX_FEE_BPS, options);
+    const account = await program.account.market.fetch(market);
+    expect(account.feeBps).to.equal(MAX_FEE_BPS);
+    expect(account.allowCashOut).to.be.true;
+    expect(account.cashoutHaircutBps).to.equal(300);
+  });
+
+  it("forks a market only with the admin's signature", async () => {
+    const { user: creator } = await createUser(0);
+    const original = await createMarket(creator);
+    const fork = (admin: Keypair) => {
+      const marketId = new BN(nextMarketId++);
+      const market = marketAddress(creator.publicKey, marketId);
+      const now = Math.floor(Date.now() / 1000);
+      return program.methods
+        .forkMarket(marketId, new BN(now + 3600), new BN(now + 7200), "Clarified")
+        .accountsPartial({
+          creator: creator.publicKey,
+          admin: admin.publicKey,
+          config: pda(Buffer.from("config")),
+          original,
+          market,
+          mint,
+          vaultA: vaultAddress(market, 0),
+          vaultB: vaultAddress(market, 1),
+        })
+        .signers(admin === creator ? [creator] : [creator, admin])
+        .rpc();
+    };
+
+    // The creator can't cancel their market alone
+    await expectError(fork(creator), "UnauthorizedAdmin");
+    await fork(admin);
+
+    const account = await program.account.market.fetch(original);
+    expect(account.status).to.deep.equal({ cancelled: {} });
+  });
+
+  it("refuses bets below the bettor's minimum odds", async () => {
+    const market = await createMarket(admin);
+    const { user, tokenAccount } = await createUser(2_000_000);
+
+    // Alone on side A after the 5% fee, a bet pays 0.95 of itself
+    await expectError(
+      placeBet(user, tokenAccount, market, 0, 1_000_000, 9_501),
+      "OddsBelowMinimum"
+    );
+    await placeBet(user, tokenAccount, market, 0, 1_000_000, 9_500);
+
+    const position = await program.account.position.fetch(
+      positionAddress(market, user.publicKey)
+    );
+    expect(position.amount.toNumber()).to.equal(1_000_000);
+  });
+
+  it("keeps each side's stakes in its own vault", async () => {
+    const market = await createMarket(admin);
+    const alice = await createUser(3_000_000);
+    const bob = await createUser(1_000_000);
+
+    await placeBet(alice.user, alice.tokenAccount, market, 0, 3_000_000);
+    await placeBet(bob.user, bob.tokenAccount, market, 1, 1_000_000);
+    // A bet into the other side's vault is refused
+    await expectError(
+      program.methods
+        .placeBet(SIDES[1], new BN(1), new BN(0))
+        .accountsPartial({
+          user: bob.user.publicKey,
+          market,
+          position: positionAddress(market, bob.user.publicKey),
+          userTokenAccount: bob.tokenAccount,
+          vault: vaultAddress(market, 0),
+        })
+        .signers([bob.user])
+        .rpc(),
+      "InvalidVault"
+    );
+
+    const balance = async (side: number) =>
+      (await getAccount(provider.connection, vaultAddress(market, side)))
+        .amount;
+    expect(await balance(0)).to.equal(BigInt(3_000_000));
+    expect(await balance(1)).to.equal(BigInt(1_000_000));
+
+    const account = await program.account.market.fetch(market);
+    expect(account.vaultA.equals(vaultAddress(market, 0))).to.be.true;
+    expect(account.vaultB.equals(vaultAddress(market, 1))).to.be.true;
   });
 });